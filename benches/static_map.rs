@@ -0,0 +1,71 @@
+//! Benchmarks comparing a mutable layered map with its frozen snapshot
+//!
+//! Measures point queries over a dense occupancy slab:
+//! - `LayeredMap::query_occupancy` on the hash map layer
+//! - `StaticMap::query` on the packed snapshot
+//!
+//! Run with:
+//! ```bash
+//! cargo bench --bench static_map
+//! ```
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use octaindex3d::layers::{LayerType, LayeredMap, OccupancyLayer, StaticMap};
+use octaindex3d::Index64;
+use std::hint::black_box;
+
+/// A slab 64×64 cells wide and 8 cells thick, with a few holes
+fn slab() -> Vec<Index64> {
+    let mut cells = Vec::new();
+    for x in 0..64u16 {
+        for y in 0..64u16 {
+            for z in 96..104u16 {
+                if (x ^ y ^ z) % 7 != 0 {
+                    cells.push(Index64::new(0, 0, 10, 1000 + x, 1000 + y, z).unwrap());
+                }
+            }
+        }
+    }
+    cells
+}
+
+fn bench_queries(c: &mut Criterion) {
+    let mut group = c.benchmark_group("static_map_queries");
+    let cells = slab();
+    let mut occupancy = OccupancyLayer::new();
+    for (i, &idx) in cells.iter().enumerate() {
+        occupancy.update_occupancy(idx, i % 4 == 0, 0.8);
+    }
+    let mut map = LayeredMap::new();
+    map.add_occupancy_layer(occupancy);
+    let frozen = StaticMap::from(&map);
+
+    // Query a mix of present cells and cells just outside the slab
+    let queries: Vec<_> = cells
+        .iter()
+        .map(|idx| {
+            let (x, y, z) = idx.decode_coords();
+            Index64::new(0, 0, 10, x, y, z + 4).unwrap()
+        })
+        .collect();
+    group.throughput(Throughput::Elements(queries.len() as u64));
+
+    group.bench_function(BenchmarkId::new("query", "layered_map"), |b| {
+        b.iter(|| {
+            for &idx in &queries {
+                black_box(map.query_occupancy(black_box(idx)));
+            }
+        });
+    });
+    group.bench_function(BenchmarkId::new("query", "static_map"), |b| {
+        b.iter(|| {
+            for &idx in &queries {
+                black_box(frozen.query(LayerType::Occupancy, black_box(idx)));
+            }
+        });
+    });
+    group.finish();
+}
+
+criterion_group!(static_map_benches, bench_queries);
+criterion_main!(static_map_benches);
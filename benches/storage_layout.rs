@@ -0,0 +1,139 @@
+//! Benchmarks comparing TSDF and temporal occupancy storage layouts
+//!
+//! Measures hash map (AoS) against structure-of-arrays block storage for a
+//! dense surface patch:
+//! - Integration of a depth frame's worth of updates
+//! - Whole-map surface extraction
+//! - Point queries
+//! - Temporal statistics and stale pruning over the timestamp column
+//!
+//! Run with:
+//! ```bash
+//! cargo bench --bench storage_layout
+//! ```
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use octaindex3d::clock::SimClock;
+use octaindex3d::layers::{Layer, StorageLayout, TSDFLayer, TemporalOccupancyLayer};
+use octaindex3d::Index64;
+use std::hint::black_box;
+use std::sync::Arc;
+
+const LAYOUTS: [(&str, StorageLayout); 2] =
+    [("map", StorageLayout::Map), ("soa", StorageLayout::Soa)];
+
+/// A wavy slab 64×64 cells wide and 8 cells thick, with its signed distances
+fn slab() -> Vec<(Index64, f32)> {
+    let mut cells = Vec::new();
+    for x in 0..64u16 {
+        for y in 0..64u16 {
+            let surface = 100.0 + ((x as f32 * 0.2).sin() + (y as f32 * 0.15).cos()) * 2.0;
+            for z in 96..104u16 {
+                let idx = Index64::new(0, 0, 10, 1000 + x, 1000 + y, z).unwrap();
+                cells.push((idx, (z as f32 - surface) * 0.02));
+            }
+        }
+    }
+    cells
+}
+
+fn build(layout: StorageLayout, cells: &[(Index64, f32)]) -> TSDFLayer {
+    let mut layer = TSDFLayer::new(0.1).with_layout(layout);
+    let updates: Vec<_> = cells.iter().map(|&(idx, d)| (idx, d, 1.0)).collect();
+    layer.batch_update(&updates).unwrap();
+    layer
+}
+
+fn bench_integration(c: &mut Criterion) {
+    let mut group = c.benchmark_group("layout_integration");
+    let cells = slab();
+    let updates: Vec<_> = cells.iter().map(|&(idx, d)| (idx, d, 1.0)).collect();
+    group.throughput(Throughput::Elements(updates.len() as u64));
+
+    for (name, layout) in LAYOUTS {
+        let mut layer = build(layout, &cells);
+        group.bench_function(BenchmarkId::new("batch_update", name), |b| {
+            b.iter(|| layer.batch_update(black_box(&updates)).unwrap());
+        });
+    }
+    group.finish();
+}
+
+fn bench_surface_scan(c: &mut Criterion) {
+    let mut group = c.benchmark_group("layout_surface_scan");
+    let cells = slab();
+    group.throughput(Throughput::Elements(cells.len() as u64));
+
+    for (name, layout) in LAYOUTS {
+        let layer = build(layout, &cells);
+        group.bench_function(BenchmarkId::new("get_surface_voxels", name), |b| {
+            b.iter(|| black_box(layer.get_surface_voxels(black_box(0.02))));
+        });
+    }
+    group.finish();
+}
+
+fn bench_queries(c: &mut Criterion) {
+    let mut group = c.benchmark_group("layout_queries");
+    let cells = slab();
+    group.throughput(Throughput::Elements(cells.len() as u64));
+
+    for (name, layout) in LAYOUTS {
+        let layer = build(layout, &cells);
+        group.bench_function(BenchmarkId::new("query", name), |b| {
+            b.iter(|| {
+                for &(idx, _) in &cells {
+                    black_box(layer.query(black_box(idx)));
+                }
+            });
+        });
+    }
+    group.finish();
+}
+
+/// Temporal layer over the slab, with half of it observed 4 s after the
+/// other half and the clock 2 s past that, so pruning drops one half
+fn temporal(layout: StorageLayout, cells: &[(Index64, f32)]) -> TemporalOccupancyLayer {
+    let clock = SimClock::new(0.0);
+    let mut layer = TemporalOccupancyLayer::new()
+        .with_clock(Arc::new(clock.clone()))
+        .with_layout(layout);
+    for (i, &(idx, distance)) in cells.iter().enumerate() {
+        if i == cells.len() / 2 {
+            clock.advance(4.0);
+        }
+        layer.update_occupancy(idx, distance < 0.0, 0.8);
+    }
+    clock.advance(2.0);
+    layer
+}
+
+fn bench_temporal(c: &mut Criterion) {
+    let mut group = c.benchmark_group("layout_temporal");
+    let cells = slab();
+    group.throughput(Throughput::Elements(cells.len() as u64));
+
+    for (name, layout) in LAYOUTS {
+        let layer = temporal(layout, &cells);
+        group.bench_function(BenchmarkId::new("stats", name), |b| {
+            b.iter(|| black_box(layer.stats()));
+        });
+        group.bench_function(BenchmarkId::new("prune_stale", name), |b| {
+            b.iter_batched(
+                || temporal(layout, &cells),
+                |mut layer| layer.prune_stale(),
+                BatchSize::LargeInput,
+            );
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    layout_benches,
+    bench_integration,
+    bench_surface_scan,
+    bench_queries,
+    bench_temporal
+);
+criterion_main!(layout_benches);
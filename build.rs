@@ -15,9 +15,37 @@ fn main() {
     // Based on proper 3D Hilbert curve algorithm
     generate_hilbert_tables(&mut f).unwrap();
 
+    #[cfg(feature = "ffi")]
+    generate_c_header(Path::new(&out_dir));
+
     println!("cargo:rerun-if-changed=build.rs");
 }
 
+/// Generate the C header for `src/ffi.rs` into `OUT_DIR/octaindex3d.h`
+///
+/// The copy in `include/` is checked against it by `ffi::tests`.
+#[cfg(feature = "ffi")]
+fn generate_c_header(out_dir: &Path) {
+    let config = cbindgen::Config {
+        language: cbindgen::Language::C,
+        include_guard: Some("OCTAINDEX3D_H".to_string()),
+        header: Some(
+            "/* octaindex3d C API. Generated by cbindgen from src/ffi.rs; do not edit. */"
+                .to_string(),
+        ),
+        documentation_style: cbindgen::DocumentationStyle::C99,
+        usize_is_size_t: true,
+        ..Default::default()
+    };
+    cbindgen::Builder::new()
+        .with_config(config)
+        .with_src("src/ffi.rs")
+        .generate()
+        .expect("failed to generate C header")
+        .write_to_file(out_dir.join("octaindex3d.h"));
+    println!("cargo:rerun-if-changed=src/ffi.rs");
+}
+
 fn generate_hilbert_tables(f: &mut File) -> std::io::Result<()> {
     writeln!(f, "// Auto-generated Hilbert curve state tables")?;
     writeln!(f, "// DO NOT EDIT - Generated by build.rs\n")?;
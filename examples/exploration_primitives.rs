@@ -146,6 +146,8 @@ fn demo_information_gain() -> octaindex3d::Result<()> {
         sensor_fov: std::f32::consts::PI / 3.0, // 60°
         ray_resolution: 5.0,
         unknown_weight: 1.0,
+        voxel_size: 0.1,
+        ..Default::default()
     };
 
     println!("Testing viewpoints around frontier at (2.5, 2.5, 1.0):");
@@ -53,17 +53,13 @@ fn main() -> Result<()> {
 
     // 6. Morton-encoded Index64 keys for storage and range queries
     let index = Index64::new(0, 0, 5, 100, 200, 300)?;
-    println!(
-        "\n6. Index64 key: {} (bech32m: {})",
-        index,
-        index.to_bech32m()?
-    );
+    println!("\n6. Index64 key: {:#} (bech32m: {})", index, index);
 
     // 7. Route64 IDs round-trip through human-readable bech32m strings
     let route = Route64::new(0, 100, 200, 300)?;
-    let encoded = route.to_bech32m()?;
-    assert_eq!(Route64::from_bech32m(&encoded)?, route);
-    println!("7. Route64 {} <-> {}", route, encoded);
+    let encoded = route.to_string();
+    assert_eq!(encoded.parse::<Route64>()?, route);
+    println!("7. Route64 {:#} <-> {}", route, encoded);
 
     Ok(())
 }
@@ -0,0 +1,152 @@
+/* octaindex3d C API. Generated by cbindgen from src/ffi.rs; do not edit. */
+
+#ifndef OCTAINDEX3D_H
+#define OCTAINDEX3D_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Success
+#define OI3D_OK 0
+
+// A required pointer argument was null
+#define OI3D_ERR_NULL_POINTER -1
+
+// A coordinate, tier or LOD is outside its valid range
+#define OI3D_ERR_OUT_OF_RANGE -2
+
+// Coordinates do not share the same parity (not a BCC lattice point)
+#define OI3D_ERR_INVALID_PARITY -3
+
+// A raw value or string is not a valid encoded ID
+#define OI3D_ERR_DECODE -4
+
+// The output buffer is too small; see `OI3D_BECH32M_BUFFER_LEN`
+#define OI3D_ERR_BUFFER_TOO_SMALL -5
+
+// The cell has no parent (LOD 0) or no children (LOD 15)
+#define OI3D_ERR_NO_RELATIVE -6
+
+// Any other failure
+#define OI3D_ERR_OTHER -99
+
+// Maximum number of neighbors of a cell
+#define OI3D_NEIGHBOR_COUNT 14
+
+// Number of children of a cell
+#define OI3D_CHILD_COUNT 8
+
+// Buffer size that fits any bech32m ID string and its terminating NUL
+#define OI3D_BECH32M_BUFFER_LEN 64
+
+// Library version as a static NUL-terminated string
+const char *oi3d_version(void);
+
+// Static NUL-terminated description of a status code
+const char *oi3d_status_message(int32_t status);
+
+// Encode an Index64 from frame, scale tier (0-3), LOD (0-15) and coordinates
+//
+// # Safety
+//
+// `out` must be valid for writes.
+int32_t oi3d_index64_new(uint8_t frame,
+                         uint8_t tier,
+                         uint8_t lod,
+                         uint16_t x,
+                         uint16_t y,
+                         uint16_t z,
+                         uint64_t *out);
+
+// Decode an Index64 into its fields; any out pointer may be null to skip it
+//
+// # Safety
+//
+// Non-null pointers must be valid for writes.
+int32_t oi3d_index64_decode(uint64_t value,
+                            uint8_t *frame,
+                            uint8_t *tier,
+                            uint8_t *lod,
+                            uint16_t *x,
+                            uint16_t *y,
+                            uint16_t *z);
+
+// Parent of an Index64 (one LOD coarser)
+//
+// # Safety
+//
+// `out` must be valid for writes.
+int32_t oi3d_index64_parent(uint64_t value, uint64_t *out);
+
+// The `OI3D_CHILD_COUNT` children of an Index64 (one LOD finer)
+//
+// # Safety
+//
+// `out` must be valid for `OI3D_CHILD_COUNT` writes.
+int32_t oi3d_index64_children(uint64_t value, uint64_t *out);
+
+// BCC neighbors of an Index64 within the 16-bit coordinate range
+//
+// Writes up to `capacity` IDs to `out` and the neighbor count to
+// `out_len`; a capacity of `OI3D_NEIGHBOR_COUNT` always suffices.
+//
+// # Safety
+//
+// `out` must be valid for `capacity` writes and `out_len` for one write.
+int32_t oi3d_index64_neighbors(uint64_t value, uint64_t *out, size_t capacity, size_t *out_len);
+
+// Write the bech32m string of an Index64, NUL-terminated
+//
+// # Safety
+//
+// `buf` must be valid for `capacity` writes.
+int32_t oi3d_index64_to_bech32m(uint64_t value, char *buf, size_t capacity);
+
+// Parse an Index64 from its bech32m string
+//
+// # Safety
+//
+// `s` must be a NUL-terminated string and `out` valid for writes.
+int32_t oi3d_index64_from_bech32m(const char *s, uint64_t *out);
+
+// Encode a Route64 from scale tier (0-3) and signed BCC coordinates
+//
+// # Safety
+//
+// `out` must be valid for writes.
+int32_t oi3d_route64_new(uint8_t tier, int32_t x, int32_t y, int32_t z, uint64_t *out);
+
+// Decode a Route64 into its fields; any out pointer may be null to skip it
+//
+// # Safety
+//
+// Non-null pointers must be valid for writes.
+int32_t oi3d_route64_decode(uint64_t value, uint8_t *tier, int32_t *x, int32_t *y, int32_t *z);
+
+// BCC neighbors of a Route64 within the coordinate range
+//
+// Same buffer contract as `oi3d_index64_neighbors`.
+//
+// # Safety
+//
+// `out` must be valid for `capacity` writes and `out_len` for one write.
+int32_t oi3d_route64_neighbors(uint64_t value, uint64_t *out, size_t capacity, size_t *out_len);
+
+// Write the bech32m string of a Route64, NUL-terminated
+//
+// # Safety
+//
+// `buf` must be valid for `capacity` writes.
+int32_t oi3d_route64_to_bech32m(uint64_t value, char *buf, size_t capacity);
+
+// Parse a Route64 from its bech32m string
+//
+// # Safety
+//
+// `s` must be a NUL-terminated string and `out` valid for writes.
+int32_t oi3d_route64_from_bech32m(const char *s, uint64_t *out);
+
+#endif  /* OCTAINDEX3D_H */
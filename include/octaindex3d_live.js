@@ -0,0 +1,128 @@
+// Reference decoder for OctaIndex3D live map frames.
+//
+// Frames are the binary WebSocket messages sent by
+// `octaindex3d::streaming::LiveServer`; the `streaming::live` module
+// documentation describes the wire format. This file has no dependencies
+// and works in browsers and Node as an ES module.
+//
+//   import { LiveMap, connect } from "./octaindex3d_live.js";
+//
+//   const map = new LiveMap();
+//   connect("ws://robot:9001", (frame) => {
+//     map.apply(frame);
+//     redraw(map.chunks); // Map of chunk key -> [{ x, y, z, lod, value }]
+//   });
+
+/** Layer names by `LayerType::tag` */
+export const LAYER_TYPES = ["TSDF", "ESDF", "Occupancy", "Color", "Intensity", "Derived"];
+
+const TAG = "OLV1";
+const HEADER_LEN = 16;
+
+/** Split an `Index64` into its fields. */
+export function indexFields(key) {
+  return {
+    tier: Number((key >> 60n) & 0x3n),
+    frame: Number((key >> 52n) & 0xffn),
+    lod: Number((key >> 48n) & 0xfn),
+    morton: key & 0xffffffffffffn,
+  };
+}
+
+/** Coordinates of a 48-bit Morton code: bit 3i of x, 3i + 1 of y, 3i + 2 of z. */
+export function mortonDecode(morton) {
+  let lo = Number(morton & 0xffffffn);
+  let hi = Number(morton >> 24n);
+  const xyz = [0, 0, 0];
+  for (let i = 0; i < 8; i++) {
+    for (let axis = 0; axis < 3; axis++) {
+      xyz[axis] |= ((lo >> (3 * i + axis)) & 1) << i;
+      xyz[axis] |= ((hi >> (3 * i + axis)) & 1) << (i + 8);
+    }
+  }
+  return xyz;
+}
+
+/**
+ * Decode one frame from an ArrayBuffer.
+ *
+ * Returns `{ seq, kind, layerType, chunkLod, chunks }` where `kind` is
+ * "delta" or "snapshot" and each chunk is `{ key, cells }` with cells
+ * `{ x, y, z, lod, value }`. A chunk listed in a frame replaces what the
+ * viewer held for it; a snapshot replaces everything.
+ */
+export function decodeFrame(buffer) {
+  const view = new DataView(buffer);
+  const tag = String.fromCharCode(...new Uint8Array(buffer, 0, 4));
+  if (buffer.byteLength < HEADER_LEN || tag !== TAG) {
+    throw new Error("not an OctaIndex3D live frame");
+  }
+  const frame = {
+    seq: view.getUint32(4, true),
+    kind: view.getUint8(8) === 1 ? "snapshot" : "delta",
+    layerType: LAYER_TYPES[view.getUint8(9)],
+    chunkLod: view.getUint8(10),
+    chunks: [],
+  };
+  const groups = view.getUint32(12, true);
+  let at = HEADER_LEN;
+  for (let g = 0; g < groups; g++) {
+    const key = view.getBigUint64(at, true);
+    const lod = view.getUint8(at + 8);
+    const count = view.getUint32(at + 9, true);
+    at += 13;
+    const chunk = indexFields(key);
+    const base = chunk.morton << BigInt(3 * (lod - chunk.lod));
+    let last = frame.chunks[frame.chunks.length - 1];
+    if (!last || last.key !== key) {
+      last = { key, cells: [] };
+      frame.chunks.push(last);
+    }
+    for (let c = 0; c < count; c++) {
+      const offset = BigInt(view.getUint32(at, true));
+      const value = view.getFloat32(at + 4, true);
+      at += 8;
+      const [x, y, z] = mortonDecode(base | offset);
+      last.cells.push({ x, y, z, lod, value });
+    }
+  }
+  if (at !== buffer.byteLength) {
+    throw new Error("live frame has trailing bytes");
+  }
+  return frame;
+}
+
+/** Viewer-side map state kept up to date by applying frames in order. */
+export class LiveMap {
+  constructor() {
+    /** Cells by chunk key */
+    this.chunks = new Map();
+    this.layerType = null;
+    this.seq = null;
+  }
+
+  /** Apply a decoded frame, returning the keys of the chunks it touched. */
+  apply(frame) {
+    if (frame.kind === "snapshot") {
+      this.chunks.clear();
+    }
+    this.layerType = frame.layerType;
+    this.seq = frame.seq;
+    for (const chunk of frame.chunks) {
+      if (chunk.cells.length === 0) {
+        this.chunks.delete(chunk.key);
+      } else {
+        this.chunks.set(chunk.key, chunk.cells);
+      }
+    }
+    return frame.chunks.map((chunk) => chunk.key);
+  }
+}
+
+/** Open a WebSocket to a `LiveServer` and call `onFrame` with each decoded frame. */
+export function connect(url, onFrame) {
+  const socket = new WebSocket(url);
+  socket.binaryType = "arraybuffer";
+  socket.onmessage = (event) => onFrame(decodeFrame(event.data));
+  return socket;
+}
@@ -0,0 +1,772 @@
+//! Building analysis on indoor occupancy maps
+//!
+//! [`extract_floorplan`] turns a 3D occupancy map into a 2D floorplan for
+//! facility-management and CAD tools:
+//!
+//! 1. Occupied voxels within a height band are projected onto a 2D grid
+//!    with one cell per 2 × 2 lattice-unit column, which holds one voxel of
+//!    each BCC sublattice per layer.
+//! 2. Columns occupied over at least `min_fill` of the band are walls;
+//!    this drops furniture and clutter that only reach part-way up.
+//! 3. Wall regions smaller than `min_region_cells` are discarded as noise.
+//! 4. The outlines of the remaining regions are traced into polygons
+//!    (outer boundaries counter-clockwise, holes clockwise) and simplified
+//!    with Douglas–Peucker.
+//!
+//! Coordinates are lattice units of the occupancy voxels multiplied by
+//! [`FloorplanConfig::scale`]; set it to the voxel size for meters.
+//! Floorplans export to GeoJSON and to DXF for CAD tools
+//! ([`Floorplan::to_dxf`]).
+//!
+//! [`detect_passages`] works on the same grid and finds the doors and
+//! corridors connecting rooms, with their clear widths, for room-graph
+//! navigation and accessibility checks.
+//!
+//! # Example
+//!
+//! ```
+//! use octaindex3d::analysis::extract_floorplan;
+//! use octaindex3d::layers::OccupancyLayer;
+//! use octaindex3d::Index64;
+//!
+//! # fn main() -> octaindex3d::Result<()> {
+//! // A free-standing wall 40 lattice units long
+//! let mut occupancy = OccupancyLayer::new();
+//! for x in 100..140u16 {
+//!     for y in 100..104u16 {
+//!         for z in 100..120u16 {
+//!             if x % 2 == y % 2 && y % 2 == z % 2 {
+//!                 occupancy.update_occupancy(Index64::new(0, 0, 8, x, y, z)?, true, 0.9);
+//!             }
+//!         }
+//!     }
+//! }
+//!
+//! let plan = extract_floorplan(&occupancy, 105..=115);
+//! assert_eq!(plan.walls.len(), 1);
+//! assert_eq!(plan.walls[0].exterior.len(), 4); // A rectangle
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::layers::OccupancyLayer;
+use crate::Index64;
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::ops::RangeInclusive;
+
+pub(crate) type Point = (f64, f64);
+
+/// Obstacle columns either side of a passage
+pub(crate) type Sides = [(i32, i32); 2];
+
+/// Settings for [`extract_floorplan_with`]
+#[derive(Debug, Clone)]
+pub struct FloorplanConfig {
+    /// Share of the height band a column must be occupied over to count as
+    /// wall (default: 0.5)
+    pub min_fill: f64,
+    /// Smallest wall region kept, in grid cells (default: 4)
+    pub min_region_cells: usize,
+    /// Douglas–Peucker tolerance in grid cells (default: 0.75)
+    pub simplify_tolerance: f64,
+    /// Factor from lattice units to output coordinates (default: 1)
+    pub scale: f64,
+}
+
+impl Default for FloorplanConfig {
+    fn default() -> Self {
+        Self {
+            min_fill: 0.5,
+            min_region_cells: 4,
+            simplify_tolerance: 0.75,
+            scale: 1.0,
+        }
+    }
+}
+
+/// Footprint of one connected wall region
+#[derive(Debug, Clone, PartialEq)]
+pub struct WallPolygon {
+    /// Outer boundary, counter-clockwise, first vertex not repeated
+    pub exterior: Vec<Point>,
+    /// Boundaries of enclosed free space, clockwise
+    pub holes: Vec<Vec<Point>>,
+}
+
+impl WallPolygon {
+    /// Area covered by the wall, excluding its holes
+    pub fn area(&self) -> f64 {
+        signed_area(&self.exterior) + self.holes.iter().map(|h| signed_area(h)).sum::<f64>()
+    }
+}
+
+/// 2D floorplan extracted from occupancy
+#[derive(Debug, Clone, Default)]
+pub struct Floorplan {
+    /// Wall footprints
+    pub walls: Vec<WallPolygon>,
+}
+
+impl Floorplan {
+    /// Total wall footprint area
+    pub fn wall_area(&self) -> f64 {
+        self.walls.iter().map(WallPolygon::area).sum()
+    }
+
+    /// Walls as an AutoCAD R12 DXF drawing
+    ///
+    /// Every ring becomes a closed 2D `POLYLINE` on layer `WALLS`. R12 is
+    /// the oldest DXF revision and is read by every CAD package.
+    pub fn to_dxf(&self) -> String {
+        let mut out = String::new();
+        let mut group = |code: u16, value: &str| {
+            out.push_str(&format!("{code:>3}\n{value}\n"));
+        };
+        group(0, "SECTION");
+        group(2, "HEADER");
+        group(9, "$ACADVER");
+        group(1, "AC1009");
+        group(0, "ENDSEC");
+        group(0, "SECTION");
+        group(2, "ENTITIES");
+        let rings = self
+            .walls
+            .iter()
+            .flat_map(|wall| std::iter::once(&wall.exterior).chain(&wall.holes));
+        for ring in rings {
+            group(0, "POLYLINE");
+            group(8, "WALLS");
+            group(66, "1");
+            // R12 readers expect the polyline's dummy point
+            group(10, "0.0");
+            group(20, "0.0");
+            group(30, "0.0");
+            group(70, "1");
+            for &(x, y) in ring {
+                group(0, "VERTEX");
+                group(8, "WALLS");
+                group(10, &x.to_string());
+                group(20, &y.to_string());
+            }
+            group(0, "SEQEND");
+            group(8, "WALLS");
+        }
+        group(0, "ENDSEC");
+        group(0, "EOF");
+        out
+    }
+}
+
+/// Extract a floorplan from the occupancy between heights `height_band`
+///
+/// Heights are lattice `z` coordinates. Uses default [`FloorplanConfig`]
+/// settings.
+pub fn extract_floorplan(
+    occupancy: &OccupancyLayer,
+    height_band: RangeInclusive<u16>,
+) -> Floorplan {
+    extract_floorplan_with(occupancy, height_band, &FloorplanConfig::default())
+}
+
+/// [`extract_floorplan`] with explicit settings
+pub fn extract_floorplan_with(
+    occupancy: &OccupancyLayer,
+    height_band: RangeInclusive<u16>,
+    config: &FloorplanConfig,
+) -> Floorplan {
+    let wall = wall_columns(occupancy, &height_band, config.min_fill);
+    let mut walls = Vec::new();
+    for region in regions(&wall) {
+        if region.len() < config.min_region_cells {
+            continue;
+        }
+        let to_output = |(i, j): (i32, i32)| {
+            // Cell (i, j) spans lattice x in [2i, 2i + 2) around voxels 2i and 2i + 1
+            let x = (2 * i) as f64 - 0.5;
+            let y = (2 * j) as f64 - 0.5;
+            (x * config.scale, y * config.scale)
+        };
+        let tolerance = config.simplify_tolerance * 2.0 * config.scale;
+        let mut exteriors = Vec::new();
+        let mut holes = Vec::new();
+        for ring in trace(&region) {
+            let ring: Vec<Point> = ring.into_iter().map(to_output).collect();
+            let ring = simplify(&ring, tolerance);
+            if ring.len() < 3 {
+                continue;
+            }
+            if signed_area(&ring) > 0.0 {
+                exteriors.push(ring);
+            } else {
+                holes.push(ring);
+            }
+        }
+        // A connected region has a single outer boundary
+        let Some(exterior) = exteriors
+            .into_iter()
+            .max_by(|a, b| signed_area(a).total_cmp(&signed_area(b)))
+        else {
+            continue;
+        };
+        walls.push(WallPolygon { exterior, holes });
+    }
+    walls.sort_by(|a, b| a.exterior[0].partial_cmp(&b.exterior[0]).unwrap());
+    Floorplan { walls }
+}
+
+/// Settings for [`detect_passages`]
+#[derive(Debug, Clone)]
+pub struct PassageParams {
+    /// Lattice `z` range sliced for walls and free space
+    pub height_band: RangeInclusive<u16>,
+    /// Share of the height band a column must be occupied over to count as
+    /// wall (default: 0.5)
+    pub min_fill: f64,
+    /// Narrowest passage reported, in output units (default: 0)
+    pub min_width: f64,
+    /// Widest passage reported, in output units (default: 16)
+    pub max_width: f64,
+    /// Factor from lattice units to output coordinates (default: 1)
+    pub scale: f64,
+    /// Name of the output unit, e.g. `"m"` when `scale` is meters per
+    /// lattice unit (default: none)
+    pub unit: Option<&'static str>,
+}
+
+impl PassageParams {
+    /// Default settings for a height band
+    pub fn new(height_band: RangeInclusive<u16>) -> Self {
+        Self {
+            height_band,
+            min_fill: 0.5,
+            min_width: 0.0,
+            max_width: 16.0,
+            scale: 1.0,
+            unit: None,
+        }
+    }
+}
+
+/// Narrow free-space gap such as a door or a corridor
+#[derive(Debug, Clone, PartialEq)]
+pub struct Passage {
+    /// Center of the narrowest section
+    pub center: Point,
+    /// Clear width at the narrowest section, to within one grid cell
+    pub width: f64,
+    /// Centers of the obstacle columns either side of the narrowest
+    /// section, e.g. the door jambs
+    pub sides: (Point, Point),
+    /// Free voxels of the height band inside the passage, sorted
+    pub cells: Vec<Index64>,
+}
+
+/// Find doors, openings and corridors between rooms
+///
+/// Slices walls and observed free space at `params.height_band` onto the
+/// floorplan grid and computes the distance from every free column to its
+/// nearest wall or unobserved column. Passages lie on the ridges of this
+/// distance field where the nearest obstacles of neighbouring columns are
+/// on opposite sides; the distance between those obstacles is the clear
+/// width. Connected ridge columns no wider than `max_width` form one
+/// passage. Passages are sorted by center.
+pub fn detect_passages(occupancy: &OccupancyLayer, params: &PassageParams) -> Vec<Passage> {
+    passage_map(occupancy, params)
+        .passages
+        .into_iter()
+        .map(|(passage, _)| passage)
+        .collect()
+}
+
+/// Free space of a height band on the floorplan grid, with its passages
+pub(crate) struct PassageMap {
+    /// Free voxels of each free column
+    pub(crate) free: FxHashMap<(i32, i32), Vec<Index64>>,
+    /// Passages with the obstacle columns either side of their narrowest
+    /// section
+    pub(crate) passages: Vec<(Passage, Sides)>,
+}
+
+/// Slice free space and find its passages; see [`detect_passages`]
+pub(crate) fn passage_map(occupancy: &OccupancyLayer, params: &PassageParams) -> PassageMap {
+    let band = &params.height_band;
+    let wall = wall_columns(occupancy, band, params.min_fill);
+    let mut free: FxHashMap<(i32, i32), Vec<Index64>> = FxHashMap::default();
+    for idx in occupancy.get_free_voxels() {
+        let (x, y, z) = idx.decode_coords();
+        let cell = column(x, y);
+        if band.contains(&z) && !wall.contains(&cell) {
+            free.entry(cell).or_default().push(idx);
+        }
+    }
+    let sites = nearest_obstacles(&free);
+
+    // Ridge columns: the directions to the nearest obstacles of two
+    // neighbours differ by more than 120 degrees
+    let cell_size = 2.0 * params.scale;
+    let mut widths: FxHashMap<(i32, i32), (f64, Sides)> = FxHashMap::default();
+    for (&cell, &site) in &sites {
+        let v = (site.0 - cell.0, site.1 - cell.1);
+        for other in neighbours(cell) {
+            let Some(&other_site) = sites.get(&other) else {
+                continue;
+            };
+            let w = (other_site.0 - other.0, other_site.1 - other.1);
+            let dot = (v.0 * w.0 + v.1 * w.1) as f64;
+            let norms = (((v.0 * v.0 + v.1 * v.1) * (w.0 * w.0 + w.1 * w.1)) as f64).sqrt();
+            if dot >= -0.5 * norms {
+                continue;
+            }
+            // Obstacle columns are centered half a column beyond the gap
+            let span = (grid_dist2(site, other_site) as f64).sqrt() - 1.0;
+            let width = span * cell_size;
+            if (params.min_width..=params.max_width).contains(&width) {
+                let sides = [site.min(other_site), site.max(other_site)];
+                let narrowest = widths.entry(cell).or_insert((f64::INFINITY, sides));
+                if (width, sides) < *narrowest {
+                    *narrowest = (width, sides);
+                }
+            }
+        }
+    }
+
+    let narrow: FxHashSet<(i32, i32)> = widths.keys().copied().collect();
+    let mut passages: Vec<(Passage, Sides)> = regions(&narrow)
+        .into_iter()
+        .map(|region| {
+            let mut region: Vec<(i32, i32)> = region.into_iter().collect();
+            region.sort_unstable();
+            let (width, sides) = region
+                .iter()
+                .map(|cell| widths[cell])
+                .min_by(|a, b| a.partial_cmp(b).unwrap())
+                .expect("regions are not empty");
+            let narrowest: Vec<&(i32, i32)> = region
+                .iter()
+                .filter(|cell| widths[*cell].0 == width)
+                .collect();
+            let n = narrowest.len() as f64;
+            let (sx, sy) = narrowest.iter().fold((0.0, 0.0), |(sx, sy), &&cell| {
+                let (x, y) = column_center(cell, 1.0);
+                (sx + x, sy + y)
+            });
+            let mut cells: Vec<Index64> = region
+                .iter()
+                .flat_map(|cell| free[cell].iter().copied())
+                .collect();
+            cells.sort_unstable();
+            let passage = Passage {
+                center: (sx / n * params.scale, sy / n * params.scale),
+                width,
+                sides: (
+                    column_center(sides[0], params.scale),
+                    column_center(sides[1], params.scale),
+                ),
+                cells,
+            };
+            (passage, sides)
+        })
+        .collect();
+    passages.sort_by(|a, b| a.0.center.partial_cmp(&b.0.center).unwrap());
+    PassageMap { free, passages }
+}
+
+/// Nearest non-free column of every free column
+///
+/// Obstacle sites spread outwards in order of distance, each column
+/// keeping the first site to reach it. This matches the exact Euclidean
+/// distance transform up to rare off-by-one-site errors.
+fn nearest_obstacles<T>(free: &FxHashMap<(i32, i32), T>) -> FxHashMap<(i32, i32), (i32, i32)> {
+    let mut heap = BinaryHeap::new();
+    for &cell in free.keys() {
+        for n in neighbours(cell) {
+            if !free.contains_key(&n) {
+                heap.push(Reverse((grid_dist2(cell, n), cell, n)));
+            }
+        }
+    }
+    let mut sites = FxHashMap::default();
+    while let Some(Reverse((_, cell, site))) = heap.pop() {
+        if sites.contains_key(&cell) {
+            continue;
+        }
+        sites.insert(cell, site);
+        for n in neighbours(cell) {
+            if free.contains_key(&n) && !sites.contains_key(&n) {
+                heap.push(Reverse((grid_dist2(n, site), n, site)));
+            }
+        }
+    }
+    sites
+}
+
+/// The 8 grid neighbours of a column
+fn neighbours((i, j): (i32, i32)) -> impl Iterator<Item = (i32, i32)> {
+    (-1..=1)
+        .flat_map(move |di| (-1..=1).map(move |dj| (i + di, j + dj)))
+        .filter(move |&n| n != (i, j))
+}
+
+/// Squared distance between grid columns
+fn grid_dist2(a: (i32, i32), b: (i32, i32)) -> i32 {
+    (a.0 - b.0).pow(2) + (a.1 - b.1).pow(2)
+}
+
+/// Grid column holding a lattice point
+pub(crate) fn column(x: u16, y: u16) -> (i32, i32) {
+    (x as i32 >> 1, y as i32 >> 1)
+}
+
+/// Output coordinates of a column's center
+///
+/// Column `(i, j)` spans lattice x in `[2i - 0.5, 2i + 1.5)`, around the
+/// voxels at `2i` and `2i + 1`.
+pub(crate) fn column_center((i, j): (i32, i32), scale: f64) -> Point {
+    (
+        ((2 * i) as f64 + 0.5) * scale,
+        ((2 * j) as f64 + 0.5) * scale,
+    )
+}
+
+/// Columns occupied over at least `min_fill` of the height band
+fn wall_columns(
+    occupancy: &OccupancyLayer,
+    height_band: &RangeInclusive<u16>,
+    min_fill: f64,
+) -> FxHashSet<(i32, i32)> {
+    // Distinct occupied heights per column
+    let mut columns: FxHashMap<(i32, i32), FxHashSet<u16>> = FxHashMap::default();
+    for idx in occupancy.get_occupied_voxels() {
+        let (x, y, z) = idx.decode_coords();
+        if height_band.contains(&z) {
+            columns.entry(column(x, y)).or_default().insert(z);
+        }
+    }
+    let layers = height_band.len() as f64;
+    columns
+        .into_iter()
+        .filter(|(_, heights)| heights.len() as f64 >= min_fill * layers)
+        .map(|(cell, _)| cell)
+        .collect()
+}
+
+/// 8-connected components of a cell set
+fn regions(cells: &FxHashSet<(i32, i32)>) -> Vec<FxHashSet<(i32, i32)>> {
+    let mut seen: FxHashSet<(i32, i32)> = FxHashSet::default();
+    let mut sorted: Vec<(i32, i32)> = cells.iter().copied().collect();
+    sorted.sort_unstable();
+    let mut out = Vec::new();
+    for start in sorted {
+        if !seen.insert(start) {
+            continue;
+        }
+        let mut region = FxHashSet::default();
+        let mut stack = vec![start];
+        while let Some(cell) = stack.pop() {
+            region.insert(cell);
+            for n in neighbours(cell) {
+                if cells.contains(&n) && seen.insert(n) {
+                    stack.push(n);
+                }
+            }
+        }
+        out.push(region);
+    }
+    out
+}
+
+/// Boundary rings of a region on the grid-corner lattice, with the region
+/// on the left of every edge
+fn trace(region: &FxHashSet<(i32, i32)>) -> Vec<Vec<(i32, i32)>> {
+    // Directed boundary edges keyed by start corner
+    let mut outgoing: FxHashMap<(i32, i32), Vec<(i32, i32)>> = FxHashMap::default();
+    for &(i, j) in region {
+        let free = |di: i32, dj: i32| !region.contains(&(i + di, j + dj));
+        let mut edge = |a: (i32, i32), b: (i32, i32)| outgoing.entry(a).or_default().push(b);
+        if free(0, -1) {
+            edge((i, j), (i + 1, j));
+        }
+        if free(1, 0) {
+            edge((i + 1, j), (i + 1, j + 1));
+        }
+        if free(0, 1) {
+            edge((i + 1, j + 1), (i, j + 1));
+        }
+        if free(-1, 0) {
+            edge((i, j + 1), (i, j));
+        }
+    }
+
+    let mut starts: Vec<(i32, i32)> = outgoing.keys().copied().collect();
+    starts.sort_unstable();
+    let mut rings = Vec::new();
+    for start in starts {
+        while outgoing.get(&start).is_some_and(|e| !e.is_empty()) {
+            let mut ring = vec![start];
+            let mut previous = start;
+            let mut current = take_edge(&mut outgoing, start, None);
+            while current != start {
+                ring.push(current);
+                let heading = (current.0 - previous.0, current.1 - previous.1);
+                let next = take_edge(&mut outgoing, current, Some(heading));
+                previous = current;
+                current = next;
+            }
+            rings.push(drop_collinear(ring));
+        }
+    }
+    rings
+}
+
+/// Remove and return an edge leaving `corner`, preferring a left turn
+/// from `heading` so that diagonally touching cells stay in one ring
+fn take_edge(
+    outgoing: &mut FxHashMap<(i32, i32), Vec<(i32, i32)>>,
+    corner: (i32, i32),
+    heading: Option<(i32, i32)>,
+) -> (i32, i32) {
+    let edges = outgoing
+        .get_mut(&corner)
+        .expect("boundary edges form closed rings");
+    let pick = heading
+        .and_then(|(dx, dy)| {
+            let left = (corner.0 - dy, corner.1 + dx);
+            edges.iter().position(|&e| e == left)
+        })
+        .unwrap_or(0);
+    edges.swap_remove(pick)
+}
+
+/// Drop vertices in the middle of straight runs
+fn drop_collinear(ring: Vec<(i32, i32)>) -> Vec<(i32, i32)> {
+    let n = ring.len();
+    (0..n)
+        .filter(|&k| {
+            let (a, b, c) = (ring[(k + n - 1) % n], ring[k], ring[(k + 1) % n]);
+            (b.0 - a.0) * (c.1 - b.1) != (b.1 - a.1) * (c.0 - b.0)
+        })
+        .map(|k| ring[k])
+        .collect()
+}
+
+/// Douglas–Peucker simplification of a closed ring
+fn simplify(ring: &[Point], tolerance: f64) -> Vec<Point> {
+    if ring.len() <= 4 || tolerance <= 0.0 {
+        return ring.to_vec();
+    }
+    // Split at the vertex farthest from the first so both halves are open
+    let far = (1..ring.len())
+        .max_by(|&a, &b| dist2(ring[0], ring[a]).total_cmp(&dist2(ring[0], ring[b])))
+        .expect("ring has several vertices");
+    let mut first: Vec<Point> = ring[..=far].to_vec();
+    let mut second: Vec<Point> = ring[far..].to_vec();
+    second.push(ring[0]);
+    first = douglas_peucker(&first, tolerance);
+    second = douglas_peucker(&second, tolerance);
+    first.pop();
+    second.pop();
+    first.extend(second);
+    first
+}
+
+fn douglas_peucker(points: &[Point], tolerance: f64) -> Vec<Point> {
+    let (a, b) = (points[0], points[points.len() - 1]);
+    let (index, distance) = points[1..points.len() - 1]
+        .iter()
+        .enumerate()
+        .map(|(k, &p)| (k + 1, segment_distance(p, a, b)))
+        .max_by(|x, y| x.1.total_cmp(&y.1))
+        .unwrap_or((0, 0.0));
+    if distance <= tolerance {
+        return vec![a, b];
+    }
+    let mut left = douglas_peucker(&points[..=index], tolerance);
+    let right = douglas_peucker(&points[index..], tolerance);
+    left.pop();
+    left.extend(right);
+    left
+}
+
+fn segment_distance(p: Point, a: Point, b: Point) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length2 = dx * dx + dy * dy;
+    let t = if length2 > 0.0 {
+        (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / length2).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    dist2(p, (a.0 + t * dx, a.1 + t * dy)).sqrt()
+}
+
+fn dist2(a: Point, b: Point) -> f64 {
+    (a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)
+}
+
+/// Shoelace area, positive for counter-clockwise rings
+fn signed_area(ring: &[Point]) -> f64 {
+    let n = ring.len();
+    (0..n)
+        .map(|k| {
+            let (a, b) = (ring[k], ring[(k + 1) % n]);
+            a.0 * b.1 - b.0 * a.1
+        })
+        .sum::<f64>()
+        / 2.0
+}
+
+#[cfg(feature = "serde")]
+mod report {
+    //! GeoJSON floorplan export
+
+    use super::*;
+    use crate::error::{Error, Result};
+    use serde_json::{json, Value};
+
+    impl Floorplan {
+        /// Walls as a GeoJSON FeatureCollection of polygons
+        ///
+        /// Coordinates are written as extracted; readers expecting WGS84
+        /// need them reprojected.
+        pub fn to_geojson(&self) -> Result<String> {
+            let close = |ring: &Vec<Point>| -> Value {
+                let mut coords: Vec<Value> = ring.iter().map(|&(x, y)| json!([x, y])).collect();
+                coords.push(json!([ring[0].0, ring[0].1]));
+                Value::Array(coords)
+            };
+            let features: Vec<Value> = self
+                .walls
+                .iter()
+                .map(|wall| {
+                    let mut rings = vec![close(&wall.exterior)];
+                    rings.extend(wall.holes.iter().map(close));
+                    json!({
+                        "type": "Feature",
+                        "geometry": { "type": "Polygon", "coordinates": rings },
+                        "properties": { "kind": "wall", "area": wall.area() },
+                    })
+                })
+                .collect();
+            let collection = json!({ "type": "FeatureCollection", "features": features });
+            serde_json::to_string_pretty(&collection).map_err(|e| Error::Codec(e.to_string()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Fill the lattice points of a box with occupied voxels
+    fn fill(occupancy: &mut OccupancyLayer, x: (u16, u16), y: (u16, u16), z: (u16, u16)) {
+        for xi in x.0..x.1 {
+            for yi in y.0..y.1 {
+                for zi in z.0..z.1 {
+                    if xi % 2 == yi % 2 && yi % 2 == zi % 2 {
+                        let idx = Index64::new(0, 0, 8, xi, yi, zi).unwrap();
+                        occupancy.update_occupancy(idx, true, 0.9);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_room_outline_with_clutter() {
+        let mut occupancy = OccupancyLayer::new();
+        // Four walls enclosing a 40 x 30 room, 4 units thick
+        fill(&mut occupancy, (100, 140), (100, 104), (100, 120));
+        fill(&mut occupancy, (100, 140), (126, 130), (100, 120));
+        fill(&mut occupancy, (100, 104), (100, 130), (100, 120));
+        fill(&mut occupancy, (136, 140), (100, 130), (100, 120));
+        // A table reaching only the bottom of the band, and a stray voxel
+        fill(&mut occupancy, (114, 124), (110, 118), (100, 107));
+        fill(&mut occupancy, (120, 121), (120, 121), (100, 120));
+
+        let plan = extract_floorplan(&occupancy, 105..=115);
+        assert_eq!(plan.walls.len(), 1);
+        let wall = &plan.walls[0];
+        assert_eq!(wall.exterior.len(), 4);
+        assert_eq!(wall.holes.len(), 1);
+        assert_eq!(wall.holes[0].len(), 4);
+        assert!(signed_area(&wall.holes[0]) < 0.0);
+        // Outer 40 x 30 minus inner 32 x 22
+        assert!((wall.area() - (40.0 * 30.0 - 32.0 * 22.0)).abs() < 1e-9);
+
+        let scaled = extract_floorplan_with(
+            &occupancy,
+            105..=115,
+            &FloorplanConfig {
+                scale: 0.05,
+                ..Default::default()
+            },
+        );
+        assert!((scaled.wall_area() - wall.area() * 0.0025).abs() < 1e-9);
+
+        let dxf = plan.to_dxf();
+        assert_eq!(dxf.matches("POLYLINE").count(), 2);
+        assert_eq!(dxf.matches("VERTEX").count(), 8);
+        assert!(dxf.contains("POLYLINE\n  8\nWALLS\n 66\n1\n 10\n0.0\n 20\n0.0\n 30\n0.0\n"));
+        assert!(dxf.ends_with("  0\nEOF\n"));
+    }
+
+    #[test]
+    fn test_door_between_rooms() -> crate::Result<()> {
+        let mut occupancy = OccupancyLayer::new();
+        // Two 36 x 22 rooms with a door 8 units wide in the wall between
+        let solid = |x: u16, y: u16| {
+            !(104..176).contains(&x)
+                || !(104..126).contains(&y)
+                || ((138..142).contains(&x) && !(110..118).contains(&y))
+        };
+        for x in 100..180u16 {
+            for y in 100..130u16 {
+                for z in 100..120u16 {
+                    if x % 2 == y % 2 && y % 2 == z % 2 {
+                        let idx = Index64::new(0, 0, 8, x, y, z)?;
+                        occupancy.update_occupancy(idx, solid(x, y), 0.9);
+                    }
+                }
+            }
+        }
+
+        let params = PassageParams {
+            max_width: 12.0,
+            ..PassageParams::new(105..=115)
+        };
+        let passages = detect_passages(&occupancy, &params);
+        assert_eq!(passages.len(), 1);
+        let door = &passages[0];
+        assert_eq!(door.width, 8.0);
+        // Jambs at the ends of the two wall segments
+        assert_eq!(door.sides.0 .0, door.sides.1 .0);
+        assert_eq!(door.sides.1 .1 - door.sides.0 .1, 10.0);
+        assert!((door.center.0 - 140.0).abs() <= 2.0);
+        assert!((door.center.1 - 114.0).abs() <= 2.0);
+        assert!(door.cells.iter().all(|idx| {
+            let (x, y, _) = idx.decode_coords();
+            (134..146).contains(&x) && (110..118).contains(&y)
+        }));
+
+        // Too narrow for the threshold
+        let params = PassageParams {
+            max_width: 6.0,
+            ..params
+        };
+        assert!(detect_passages(&occupancy, &params).is_empty());
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_geojson_polygons() {
+        let mut occupancy = OccupancyLayer::new();
+        fill(&mut occupancy, (100, 120), (100, 104), (100, 110));
+        let plan = extract_floorplan(&occupancy, 100..=109);
+        let json: serde_json::Value = serde_json::from_str(&plan.to_geojson().unwrap()).unwrap();
+        let ring = &json["features"][0]["geometry"]["coordinates"][0];
+        assert_eq!(ring.as_array().unwrap().len(), 5);
+        assert_eq!(ring[0], ring[4]);
+    }
+}
@@ -0,0 +1,378 @@
+//! Compressed sets of [`Index64`] cells with boolean operations
+//!
+//! [`CellSet`] is the Index64 counterpart of S2's `CellUnion`: a set of cells
+//! at mixed levels of detail, stored as sorted, disjoint runs of the finest
+//! (LOD 15) descendants they cover. A solid region of millions of cells
+//! collapses to a handful of runs, and union, intersection and difference
+//! are linear merges of the run lists.
+//!
+//! Cells follow the hierarchy of [`Index64::parent`]: a cell at LOD `l`
+//! contains every cell at a finer LOD whose Morton code, shifted right by 3
+//! bits per level, equals its own. Cells in different frames or scale tiers
+//! never overlap.
+//!
+//! Sets are normalized: iteration yields the fewest cells covering the set,
+//! so eight siblings are reported as their parent.
+//!
+//! # Example
+//!
+//! ```
+//! use octaindex3d::{CellSet, Index64};
+//!
+//! # fn main() -> octaindex3d::Result<()> {
+//! let parent = Index64::new(0, 0, 4, 1, 2, 3)?;
+//! let children: CellSet = parent.children().into_iter().collect();
+//!
+//! // Eight siblings normalize to their parent
+//! assert_eq!(children.iter().collect::<Vec<_>>(), vec![parent]);
+//!
+//! let one_child = CellSet::from_cells([parent.children()[0]]);
+//! let rest = children.difference(&one_child);
+//! assert_eq!(rest.cell_count(), 7);
+//! assert!(!rest.intersects(parent.children()[0]));
+//! assert!(children.contains_set(&rest));
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::ids::Index64;
+
+/// Finest LOD; run bounds are expressed in descendants at this level
+const MAX_LOD: u32 = 15;
+
+/// Bits of a leaf key below the frame/tier prefix (48-bit Morton + 3 per level)
+const LEAF_BITS: u32 = 48 + 3 * MAX_LOD;
+
+/// Mask of the 48 Morton bits of an Index64
+const MORTON_MASK: u64 = (1 << 48) - 1;
+
+/// Sorted, normalized set of [`Index64`] cells at mixed LODs
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct CellSet {
+    /// Disjoint, non-adjacent inclusive leaf-key ranges in ascending order
+    runs: Vec<(u128, u128)>,
+}
+
+impl CellSet {
+    /// Create an empty set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build a set from cells in any order, with duplicates or overlaps
+    pub fn from_cells<I: IntoIterator<Item = Index64>>(cells: I) -> Self {
+        let mut runs: Vec<(u128, u128)> = cells.into_iter().map(leaf_range).collect();
+        runs.sort_unstable();
+        Self {
+            runs: normalize(runs),
+        }
+    }
+
+    /// Add a cell
+    ///
+    /// Inserting is linear in the number of runs; prefer
+    /// [`CellSet::from_cells`] or [`CellSet::union`] for bulk updates.
+    pub fn insert(&mut self, cell: Index64) {
+        *self = self.union(&Self {
+            runs: vec![leaf_range(cell)],
+        });
+    }
+
+    /// Check if the set is empty
+    pub fn is_empty(&self) -> bool {
+        self.runs.is_empty()
+    }
+
+    /// Number of contiguous runs stored
+    pub fn run_count(&self) -> usize {
+        self.runs.len()
+    }
+
+    /// Number of cells in the normalized representation
+    pub fn cell_count(&self) -> usize {
+        self.iter().count()
+    }
+
+    /// Check if the cell and all its descendants are in the set
+    pub fn contains(&self, cell: Index64) -> bool {
+        let (start, end) = leaf_range(cell);
+        match self.run_before(start) {
+            Some(i) => self.runs[i].1 >= end,
+            None => false,
+        }
+    }
+
+    /// Check if the cell or any of its descendants are in the set
+    pub fn intersects(&self, cell: Index64) -> bool {
+        let (start, end) = leaf_range(cell);
+        let i = self.runs.partition_point(|&(_, e)| e < start);
+        self.runs.get(i).is_some_and(|&(s, _)| s <= end)
+    }
+
+    /// Check if every cell of `other` is in this set
+    pub fn contains_set(&self, other: &CellSet) -> bool {
+        let mut i = 0;
+        for &(start, end) in &other.runs {
+            while i < self.runs.len() && self.runs[i].1 < start {
+                i += 1;
+            }
+            match self.runs.get(i) {
+                Some(&(s, e)) if s <= start && end <= e => {}
+                _ => return false,
+            }
+        }
+        true
+    }
+
+    /// Cells in either set
+    pub fn union(&self, other: &CellSet) -> CellSet {
+        let mut runs = Vec::with_capacity(self.runs.len() + other.runs.len());
+        let (mut i, mut j) = (0, 0);
+        while i < self.runs.len() || j < other.runs.len() {
+            let take_self = match (self.runs.get(i), other.runs.get(j)) {
+                (Some(a), Some(b)) => a <= b,
+                (Some(_), None) => true,
+                _ => false,
+            };
+            if take_self {
+                runs.push(self.runs[i]);
+                i += 1;
+            } else {
+                runs.push(other.runs[j]);
+                j += 1;
+            }
+        }
+        CellSet {
+            runs: normalize(runs),
+        }
+    }
+
+    /// Cells in both sets
+    pub fn intersection(&self, other: &CellSet) -> CellSet {
+        let mut runs = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < self.runs.len() && j < other.runs.len() {
+            let (a, b) = (self.runs[i], other.runs[j]);
+            let start = a.0.max(b.0);
+            let end = a.1.min(b.1);
+            if start <= end {
+                runs.push((start, end));
+            }
+            if a.1 < b.1 {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        CellSet { runs }
+    }
+
+    /// Cells in this set but not in `other`
+    pub fn difference(&self, other: &CellSet) -> CellSet {
+        let mut runs = Vec::new();
+        let mut j = 0;
+        for &(start, end) in &self.runs {
+            let mut cursor = start;
+            while j < other.runs.len() && other.runs[j].1 < cursor {
+                j += 1;
+            }
+            let mut k = j;
+            while cursor <= end {
+                match other.runs.get(k) {
+                    Some(&(s, e)) if s <= end => {
+                        if s > cursor {
+                            runs.push((cursor, s - 1));
+                        }
+                        if e >= end {
+                            break;
+                        }
+                        cursor = cursor.max(e + 1);
+                        k += 1;
+                    }
+                    _ => {
+                        runs.push((cursor, end));
+                        break;
+                    }
+                }
+            }
+        }
+        CellSet { runs }
+    }
+
+    /// Iterate over the normalized cells in ascending order
+    ///
+    /// Each run is split into the largest aligned cells it contains.
+    pub fn iter(&self) -> impl Iterator<Item = Index64> + '_ {
+        self.runs.iter().flat_map(|&(start, end)| RunCells {
+            next: start,
+            end,
+            done: false,
+        })
+    }
+
+    /// Index of the last run starting at or before `key`
+    fn run_before(&self, key: u128) -> Option<usize> {
+        self.runs.partition_point(|&(s, _)| s <= key).checked_sub(1)
+    }
+}
+
+impl FromIterator<Index64> for CellSet {
+    fn from_iter<I: IntoIterator<Item = Index64>>(iter: I) -> Self {
+        Self::from_cells(iter)
+    }
+}
+
+impl Extend<Index64> for CellSet {
+    fn extend<I: IntoIterator<Item = Index64>>(&mut self, iter: I) {
+        *self = self.union(&Self::from_cells(iter));
+    }
+}
+
+/// Inclusive range of LOD-15 leaf keys covered by a cell
+///
+/// The key places the frame and tier prefix above the Morton code of the
+/// cell's descendants, so cells of different spaces never overlap.
+fn leaf_range(cell: Index64) -> (u128, u128) {
+    let prefix = (cell.raw() >> 52) as u128;
+    let shift = 3 * (MAX_LOD - cell.lod() as u32);
+    let start = (prefix << LEAF_BITS) | ((cell.morton() as u128) << shift);
+    (start, start + ((1u128 << shift) - 1))
+}
+
+/// Merge overlapping and adjacent runs of a list sorted by start
+fn normalize(runs: Vec<(u128, u128)>) -> Vec<(u128, u128)> {
+    let mut merged: Vec<(u128, u128)> = Vec::with_capacity(runs.len());
+    for (start, end) in runs {
+        match merged.last_mut() {
+            Some(last) if start <= last.1.saturating_add(1) => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// Largest aligned cells covering one run
+struct RunCells {
+    next: u128,
+    end: u128,
+    done: bool,
+}
+
+impl Iterator for RunCells {
+    type Item = Index64;
+
+    fn next(&mut self) -> Option<Index64> {
+        while !self.done && self.next <= self.end {
+            let key = self.next;
+            let prefix = (key >> LEAF_BITS) as u64;
+            let leaf = key & ((1u128 << LEAF_BITS) - 1);
+
+            // Coarsest level whose cell starts here and fits in the run
+            let mut level = MAX_LOD;
+            loop {
+                let size = 1u128 << (3 * level);
+                let fits = leaf % size == 0 && key + (size - 1) <= self.end;
+                if fits && (leaf >> (3 * level)) <= MORTON_MASK as u128 {
+                    break;
+                }
+                if level == 0 {
+                    break;
+                }
+                level -= 1;
+            }
+
+            let size = 1u128 << (3 * level);
+            let morton = leaf >> (3 * level);
+            if key + (size - 1) >= self.end {
+                self.done = true;
+            } else {
+                self.next = key + size;
+            }
+            if morton > MORTON_MASK as u128 {
+                // Beyond the 16-bit coordinate range at every LOD
+                self.done = true;
+                return None;
+            }
+
+            let lod = (MAX_LOD - level) as u64;
+            let raw = (prefix << 52) | (lod << 48) | morton as u64;
+            if let Ok(cell) = Index64::from_value(raw) {
+                return Some(cell);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Result;
+    use std::collections::HashSet;
+
+    fn leaves(set: &CellSet, lod: u8) -> HashSet<Index64> {
+        let mut out = HashSet::new();
+        for cell in set.iter() {
+            let mut level = vec![cell];
+            while level[0].lod() < lod {
+                level = level.iter().flat_map(|c| c.children()).collect();
+            }
+            out.extend(level);
+        }
+        out
+    }
+
+    #[test]
+    fn test_boolean_ops_match_hash_sets() -> Result<()> {
+        // Mixed LODs: coarse blocks plus scattered fine cells
+        let mut a = vec![
+            Index64::new(0, 0, 3, 1, 1, 1)?,
+            Index64::new(0, 0, 4, 0, 0, 0)?,
+        ];
+        let mut b = vec![
+            Index64::new(0, 0, 4, 2, 2, 2)?,
+            Index64::new(1, 0, 4, 0, 0, 0)?,
+        ];
+        for i in 0..20u16 {
+            a.push(Index64::new(0, 0, 5, i, 2 * i % 7, 3)?);
+            b.push(Index64::new(0, 0, 5, 4 + i % 5, 4, i % 6)?);
+        }
+        let (sa, sb) = (CellSet::from_cells(a), CellSet::from_cells(b));
+        let (ha, hb) = (leaves(&sa, 5), leaves(&sb, 5));
+
+        assert_eq!(leaves(&sa.union(&sb), 5), &ha | &hb);
+        assert_eq!(leaves(&sa.intersection(&sb), 5), &ha & &hb);
+        assert_eq!(leaves(&sa.difference(&sb), 5), &ha - &hb);
+        assert_eq!(leaves(&sb.difference(&sa), 5), &hb - &ha);
+
+        let both = sa.union(&sb);
+        assert!(both.contains_set(&sa) && both.contains_set(&sb));
+        assert!(!sa.contains_set(&both));
+        assert!(sa.difference(&sa).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalization_and_queries() -> Result<()> {
+        let parent = Index64::new(0, 0, 6, 5, 6, 7)?;
+        let grandchildren: Vec<Index64> = parent
+            .children()
+            .iter()
+            .flat_map(|c| c.children())
+            .collect();
+        let mut set = CellSet::from_cells(grandchildren[1..].iter().copied());
+        assert_eq!(set.cell_count(), 7 + 7);
+        assert!(!set.contains(parent));
+        assert!(set.intersects(parent));
+        assert!(!set.intersects(grandchildren[0]));
+
+        set.insert(grandchildren[0]);
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![parent]);
+        assert_eq!(set.run_count(), 1);
+        assert!(set.contains(grandchildren[42]));
+
+        // Other frames never overlap
+        assert!(!set.intersects(Index64::new(1, 0, 6, 5, 6, 7)?));
+        Ok(())
+    }
+}
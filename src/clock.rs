@@ -0,0 +1,181 @@
+//! Time sources for time-dependent components
+//!
+//! Components that age data, such as
+//! [`TemporalOccupancyLayer`](crate::layers::TemporalOccupancyLayer) and
+//! the checkpoint interval of [`WalMap`](crate::layers::WalMap), read the
+//! time from a [`Clock`] instead of the system clock, so simulations can
+//! run faster than real time and recorded data replays deterministically.
+//!
+//! Times are seconds as `f64`, the unit [`TimestampLayer`] and frame
+//! trajectories take explicitly, so `clock.now()` can be passed there too.
+//!
+//! - [`WallClock`]: real time, the default
+//! - [`SimClock`]: advanced by the simulation; clones share one time
+//! - [`ReplayClock`]: follows the stamps of replayed messages
+//!
+//! [`TimestampLayer`]: crate::layers::TimestampLayer
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Source of the current time
+pub trait Clock: Send + Sync {
+    /// Current time in seconds
+    fn now(&self) -> f64;
+}
+
+impl<C: Clock + ?Sized> Clock for Arc<C> {
+    fn now(&self) -> f64 {
+        (**self).now()
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now(&self) -> f64 {
+        (**self).now()
+    }
+}
+
+/// Real time in seconds since the Unix epoch
+///
+/// Monotonic: the epoch time is read once at creation and advanced by
+/// [`Instant`], so system clock adjustments do not make it jump.
+#[derive(Debug, Clone, Copy)]
+pub struct WallClock {
+    origin: Instant,
+    epoch: f64,
+}
+
+impl WallClock {
+    /// Clock reading the current real time
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+            epoch: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs_f64(),
+        }
+    }
+}
+
+impl Default for WallClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for WallClock {
+    fn now(&self) -> f64 {
+        self.epoch + self.origin.elapsed().as_secs_f64()
+    }
+}
+
+/// Simulated time, changed only by the simulation
+///
+/// Clones share the same time, so one handle can drive every component
+/// given a clone.
+///
+/// # Example
+/// ```
+/// use octaindex3d::clock::{Clock, SimClock};
+///
+/// let clock = SimClock::new(10.0);
+/// let layer_clock = clock.clone();
+/// clock.advance(0.5);
+/// assert_eq!(layer_clock.now(), 10.5);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SimClock {
+    /// Bits of the current time
+    time: Arc<AtomicU64>,
+}
+
+impl SimClock {
+    /// Clock starting at `start` seconds
+    pub fn new(start: f64) -> Self {
+        Self {
+            time: Arc::new(AtomicU64::new(start.to_bits())),
+        }
+    }
+
+    /// Set the time
+    pub fn set(&self, time: f64) {
+        self.time.store(time.to_bits(), Ordering::Release);
+    }
+
+    /// Move the time forward by `seconds`
+    pub fn advance(&self, seconds: f64) {
+        // Only the simulation writes, so a read-modify-write race is benign
+        self.set(self.now() + seconds);
+    }
+}
+
+impl Clock for SimClock {
+    fn now(&self) -> f64 {
+        f64::from_bits(self.time.load(Ordering::Acquire))
+    }
+}
+
+/// Time of recorded data being replayed
+///
+/// Each replayed message reports its stamp with
+/// [`observe`](Self::observe); the clock reads the latest stamp seen and
+/// never runs backward on out-of-order messages. Clones share the same
+/// time, like [`SimClock`].
+#[derive(Debug, Clone, Default)]
+pub struct ReplayClock {
+    time: SimClock,
+}
+
+impl ReplayClock {
+    /// Clock reading `start` until the first stamp is observed
+    pub fn new(start: f64) -> Self {
+        Self {
+            time: SimClock::new(start),
+        }
+    }
+
+    /// Advance to the stamp of a replayed message, if it is later
+    pub fn observe(&self, stamp: f64) {
+        let _ = self
+            .time
+            .time
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |bits| {
+                (stamp > f64::from_bits(bits)).then_some(stamp.to_bits())
+            });
+    }
+}
+
+impl Clock for ReplayClock {
+    fn now(&self) -> f64 {
+        self.time.now()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clocks() {
+        let wall = WallClock::new();
+        let (a, b) = (wall.now(), wall.now());
+        assert!(a > 1.0e9 && b >= a);
+
+        let sim = SimClock::new(2.0);
+        let shared: Arc<dyn Clock> = Arc::new(sim.clone());
+        sim.advance(1.5);
+        assert_eq!(shared.now(), 3.5);
+        sim.set(1.0);
+        assert_eq!(shared.now(), 1.0);
+
+        let replay = ReplayClock::new(0.0);
+        replay.observe(5.0);
+        replay.observe(4.0);
+        assert_eq!(replay.clone().now(), 5.0);
+        replay.observe(6.25);
+        assert_eq!(replay.now(), 6.25);
+    }
+}
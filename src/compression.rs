@@ -1,13 +1,22 @@
 //! Compression layer for OctaIndex3D container format
 //!
-//! Provides pluggable compression with LZ4 (default) and optional Zstd support.
+//! Provides pluggable compression with LZ4 (default) and optional Zstd support,
+//! plus delta/varint encoding for sorted [`Index64`] collections.
+//!
+//! Small blocks compress poorly on their own because every block starts
+//! from an empty history. With the `zstd` feature,
+//! [`train_zstd_dictionary`] builds a shared dictionary from sample blocks
+//! and [`ZstdDictCompression`] primes every block with it.
 
 use crate::error::{Error, Result};
+use crate::ids::Index64;
 
 /// LZ4 compression codec ID
 pub const CODEC_LZ4: u8 = 0;
 /// Zstandard compression codec ID
 pub const CODEC_ZSTD: u8 = 1;
+/// Zstandard with a shared dictionary codec ID
+pub const CODEC_ZSTD_DICT: u8 = 2;
 /// No compression codec ID
 pub const CODEC_NONE: u8 = 3;
 
@@ -88,6 +97,81 @@ impl Compression for ZstdCompression {
     }
 }
 
+/// Train a zstd dictionary of at most `max_size` bytes on sample blocks
+///
+/// Samples should be representative blocks, a few hundred or more;
+/// dictionaries of 16–112 KB suit blocks of a few KB. Training fails when
+/// the samples are too few or too small to learn from.
+#[cfg(feature = "zstd")]
+pub fn train_zstd_dictionary<S: AsRef<[u8]>>(samples: &[S], max_size: usize) -> Result<Vec<u8>> {
+    zstd::dict::from_samples(samples, max_size)
+        .map_err(|e| Error::Codec(format!("Zstd dictionary training failed: {}", e)))
+}
+
+/// Zstd compression with a shared dictionary (requires 'zstd' feature)
+///
+/// Blocks compressed with a dictionary decompress only with the same
+/// dictionary. [`ContainerWriterV2::with_zstd_dictionary`](crate::container_v2::ContainerWriterV2::with_zstd_dictionary)
+/// stores it in the container, so readers pick it up automatically.
+#[cfg(feature = "zstd")]
+pub struct ZstdDictCompression {
+    level: i32,
+    dictionary_len: usize,
+    encoder: zstd::dict::EncoderDictionary<'static>,
+    decoder: zstd::dict::DecoderDictionary<'static>,
+}
+
+#[cfg(feature = "zstd")]
+impl ZstdDictCompression {
+    /// Create with default level (5)
+    pub fn new(dictionary: &[u8]) -> Self {
+        Self::with_level(dictionary, 5)
+    }
+
+    /// Create with custom level (1-22)
+    pub fn with_level(dictionary: &[u8], level: i32) -> Self {
+        Self {
+            level,
+            dictionary_len: dictionary.len(),
+            encoder: zstd::dict::EncoderDictionary::copy(dictionary, level),
+            decoder: zstd::dict::DecoderDictionary::copy(dictionary),
+        }
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl std::fmt::Debug for ZstdDictCompression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ZstdDictCompression")
+            .field("level", &self.level)
+            .field("dictionary_len", &self.dictionary_len)
+            .finish()
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl Compression for ZstdDictCompression {
+    fn codec_id(&self) -> u8 {
+        CODEC_ZSTD_DICT
+    }
+
+    fn compress(&self, src: &[u8]) -> Result<Vec<u8>> {
+        zstd::bulk::Compressor::with_prepared_dictionary(&self.encoder)
+            .and_then(|mut c| c.compress(src))
+            .map_err(|e| Error::Codec(format!("Zstd compression failed: {}", e)))
+    }
+
+    fn decompress(&self, src: &[u8]) -> Result<Vec<u8>> {
+        use std::io::Read;
+
+        let mut out = Vec::new();
+        zstd::stream::read::Decoder::with_prepared_dictionary(src, &self.decoder)
+            .and_then(|mut d| d.read_to_end(&mut out))
+            .map_err(|e| Error::Codec(format!("Zstd decompression failed: {}", e)))?;
+        Ok(out)
+    }
+}
+
 /// No compression (passthrough)
 #[derive(Debug, Clone, Copy)]
 pub struct NoCompression;
@@ -112,6 +196,8 @@ pub fn default_compression() -> Box<dyn Compression> {
 }
 
 /// Get compression by codec ID
+///
+/// Dictionary codecs need their dictionary and are not available here.
 pub fn get_compression(codec_id: u8) -> Result<Box<dyn Compression>> {
     match codec_id {
         CODEC_LZ4 => Ok(Box::new(Lz4Compression)),
@@ -122,6 +208,117 @@ pub fn get_compression(codec_id: u8) -> Result<Box<dyn Compression>> {
     }
 }
 
+/// Append `value` as an unsigned LEB128 varint
+pub fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Read an unsigned LEB128 varint at `*pos`, advancing past it
+pub fn read_varint(src: &[u8], pos: &mut usize) -> Result<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *src
+            .get(*pos)
+            .ok_or_else(|| Error::Codec("truncated varint".to_string()))?;
+        *pos += 1;
+        if shift == 63 && byte > 1 {
+            break;
+        }
+        value |= ((byte & 0x7F) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(Error::Codec("varint overflows u64".to_string()))
+}
+
+/// Encode a sorted list of cells as first value + varint deltas
+///
+/// Layout: codec ID byte, then the codec's encoding of
+/// `varint(count) varint(first) varint(delta)...`. Nearby cells in Morton
+/// order differ in their low bits only, so deltas mostly take one or two
+/// bytes; [`Lz4Compression`] further squeezes repetitive gaps, while
+/// [`NoCompression`] keeps decoding trivially cheap.
+///
+/// Cells must be in ascending order (duplicates allowed).
+///
+/// # Example
+///
+/// ```
+/// use octaindex3d::compression::{decode_index64_sorted, encode_index64_sorted, Lz4Compression};
+/// use octaindex3d::Index64;
+///
+/// # fn main() -> octaindex3d::Result<()> {
+/// let cells: Vec<Index64> = Index64::iter_box(0, 0, 5, (0, 0, 0), (15, 15, 15))?.collect();
+/// let bytes = encode_index64_sorted(&cells, &Lz4Compression)?;
+/// assert!(bytes.len() * 5 < cells.len() * 8);
+/// assert_eq!(decode_index64_sorted(&bytes)?, cells);
+/// # Ok(())
+/// # }
+/// ```
+pub fn encode_index64_sorted(cells: &[Index64], codec: &dyn Compression) -> Result<Vec<u8>> {
+    let mut raw = Vec::with_capacity(cells.len() * 2 + 16);
+    write_varint(&mut raw, cells.len() as u64);
+    let mut previous: Option<u64> = None;
+    for cell in cells {
+        let value = cell.raw();
+        let delta = match previous {
+            None => value,
+            Some(p) if value >= p => value - p,
+            Some(_) => {
+                return Err(Error::InvalidFormat(
+                    "cells must be sorted in ascending order".to_string(),
+                ))
+            }
+        };
+        write_varint(&mut raw, delta);
+        previous = Some(value);
+    }
+
+    let mut out = vec![codec.codec_id()];
+    out.extend_from_slice(&codec.compress(&raw)?);
+    Ok(out)
+}
+
+/// Decode cells written by [`encode_index64_sorted`]
+pub fn decode_index64_sorted(src: &[u8]) -> Result<Vec<Index64>> {
+    let (&codec_id, payload) = src
+        .split_first()
+        .ok_or_else(|| Error::Codec("empty Index64 stream".to_string()))?;
+    let raw = get_compression(codec_id)?.decompress(payload)?;
+
+    let mut pos = 0;
+    let count = read_varint(&raw, &mut pos)? as usize;
+    // Every entry takes at least one byte
+    if count > raw.len() - pos {
+        return Err(Error::Codec(format!(
+            "Index64 stream claims {} entries in {} bytes",
+            count,
+            raw.len() - pos
+        )));
+    }
+    let mut cells = Vec::with_capacity(count);
+    let mut value = 0u64;
+    for _ in 0..count {
+        let delta = read_varint(&raw, &mut pos)?;
+        value = value
+            .checked_add(delta)
+            .ok_or_else(|| Error::Codec("Index64 delta overflows".to_string()))?;
+        cells.push(Index64::from_value(value)?);
+    }
+    if pos != raw.len() {
+        return Err(Error::Codec(format!(
+            "{} trailing bytes after Index64 stream",
+            raw.len() - pos
+        )));
+    }
+    Ok(cells)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -151,6 +348,33 @@ mod tests {
         assert_eq!(data, decompressed.as_slice());
     }
 
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd_dictionary() {
+        // Small occupancy-like blocks sharing structure but not content
+        let blocks: Vec<Vec<u8>> = (0..400u32)
+            .map(|i| {
+                (0..2048u32)
+                    .map(|j| match (j * 7 + i * 13) % 23 {
+                        0 => (i % 251) as u8,
+                        k if k < 12 => 0x7F,
+                        _ => (j % 5) as u8,
+                    })
+                    .collect()
+            })
+            .collect();
+        let dictionary = train_zstd_dictionary(&blocks, 16 * 1024).unwrap();
+        assert!(!dictionary.is_empty() && dictionary.len() <= 16 * 1024);
+
+        let with_dict = ZstdDictCompression::new(&dictionary);
+        let plain = ZstdCompression::new();
+        let block = &blocks[7];
+        let compressed = with_dict.compress(block).unwrap();
+        assert!(compressed.len() < plain.compress(block).unwrap().len());
+        assert_eq!(with_dict.decompress(&compressed).unwrap(), *block);
+        assert!(get_compression(CODEC_ZSTD_DICT).is_err());
+    }
+
     #[test]
     fn test_no_compression() {
         let codec = NoCompression;
@@ -162,4 +386,51 @@ mod tests {
         let decompressed = codec.decompress(&compressed).unwrap();
         assert_eq!(data, decompressed.as_slice());
     }
+
+    #[test]
+    fn test_varint_roundtrip() {
+        let values = [0, 1, 127, 128, 300, u32::MAX as u64, u64::MAX];
+        let mut buf = Vec::new();
+        for &v in &values {
+            write_varint(&mut buf, v);
+        }
+        assert_eq!(&buf[..4], &[0, 1, 127, 0x80]);
+
+        let mut pos = 0;
+        for &v in &values {
+            assert_eq!(read_varint(&buf, &mut pos).unwrap(), v);
+        }
+        assert_eq!(pos, buf.len());
+        assert!(read_varint(&buf, &mut pos).is_err());
+        assert!(read_varint(&[0xFF; 11], &mut 0).is_err());
+    }
+
+    #[test]
+    fn test_index64_delta_roundtrip() {
+        let mut cells: Vec<Index64> = (0..2000u16)
+            .map(|i| Index64::new(0, 0, 8, 100 + i % 20, 200 + (i / 20) % 10, i / 200).unwrap())
+            .collect();
+        cells.sort_unstable();
+        cells.push(*cells.last().unwrap()); // Duplicates are allowed
+
+        let plain = encode_index64_sorted(&cells, &NoCompression).unwrap();
+        assert_eq!(plain[0], CODEC_NONE);
+        assert!(plain.len() * 3 < cells.len() * 8);
+        assert_eq!(decode_index64_sorted(&plain).unwrap(), cells);
+
+        let packed = encode_index64_sorted(&cells, &Lz4Compression).unwrap();
+        assert!(packed.len() < plain.len());
+        assert_eq!(decode_index64_sorted(&packed).unwrap(), cells);
+
+        assert_eq!(
+            decode_index64_sorted(&encode_index64_sorted(&[], &NoCompression).unwrap()).unwrap(),
+            vec![]
+        );
+
+        let mut unsorted = cells.clone();
+        unsorted.swap(0, 1);
+        assert!(encode_index64_sorted(&unsorted, &NoCompression).is_err());
+        assert!(decode_index64_sorted(&plain[..plain.len() - 1]).is_err());
+        assert!(decode_index64_sorted(&[]).is_err());
+    }
 }
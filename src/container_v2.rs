@@ -5,17 +5,72 @@
 //! - Fast open via footer + TOC
 //! - Crash recovery with checkpoints
 //! - Optional SHA-256 integrity
+//! - Keyed blocks that later writes supersede or delete, reclaimed by
+//!   [`compact`]
+//! - Reopening a finished container to append, see
+//!   [`ContainerWriterV2::append`]
+//! - The registered frames its blocks use, restored by [`ContainerReaderV2`]
+//! - An optional zstd dictionary shared by all frames, stored after the header
+//! - Streamed payloads of any size, written with bounded memory as a run of
+//!   frames that readers join back together
+//! - A block index ahead of the final TOC, for seeking straight to the
+//!   blocks of a key range, see [`BlockIndex`]
+//! - Memory-mapped reading with the `mmap` feature, see [`ContainerReaderMmap`]
+//! - Per-cell records grouped into blocks by Morton range, see [`CellChunking`]
+//! - Typed key-value metadata readable without decompressing any block,
+//!   see [`ContainerWriterV2::set_metadata`]
+//! - A whole-file digest in every footer, with [`verify`] reporting damaged
+//!   frames and [`recover`] salvaging the intact ones
+//!
+//! Every checksum in the format, per frame, block index and footer digest
+//! alike, is CRC32 (IEEE), as computed by `crc32fast`. CRC32C and XXH3 are
+//! not used.
 
-use crate::compression::Compression;
+use crate::compression::{get_compression, read_varint, write_varint, Compression, CODEC_NONE};
 use crate::error::{Error, Result};
+use crate::frame::{export_frames, import_frames};
+use crate::layers::is_valid_bcc;
+use crate::{FrameId, Index64};
 use crc32fast::Hasher;
-use std::io::{Seek, Write};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::{self, File};
+use std::io::{Read, Seek, Write};
+use std::ops::Range;
+use std::path::Path;
 
 #[cfg(feature = "container_v2")]
 use sha2::{Digest, Sha256};
 
 const MAGIC_V2: &[u8; 8] = b"OCTA3D2\0";
 const FORMAT_VERSION_V2: u8 = 2;
+/// Header flag: frames carry SHA-256 hashes
+const HEADER_FLAG_SHA256: u8 = 0x01;
+/// Header flag: a dictionary section follows the header
+const HEADER_FLAG_DICTIONARY: u8 = 0x02;
+/// Header flag: a block index section precedes the final TOC
+const HEADER_FLAG_BLOCK_INDEX: u8 = 0x04;
+/// Header flag: footers carry a CRC32 of the container from the end of
+/// the header to the footer
+const HEADER_FLAG_DIGEST: u8 = 0x08;
+/// Tag opening the block index section
+const BLOCK_INDEX_TAG: &[u8; 4] = b"OBI1";
+/// Bytes per block index entry: key, offset and stored length
+pub(crate) const BLOCK_INDEX_ENTRY_LEN: usize = 20;
+/// Frame header flag: an 8-byte block key follows the frame header
+const FRAME_FLAG_KEYED: u8 = 0x01;
+/// Frame header flag: the frame holds the frame registry section
+const FRAME_FLAG_REGISTRY: u8 = 0x02;
+/// Frame header flag: the next frame continues this frame's payload
+const FRAME_FLAG_CONTINUED: u8 = 0x04;
+/// Frame header flag: the keyed frame deletes its block
+const FRAME_FLAG_TOMBSTONE: u8 = 0x08;
+/// Frame header flag: the frame holds the metadata section, uncompressed
+const FRAME_FLAG_METADATA: u8 = 0x10;
+/// Tag opening the metadata section
+const METADATA_TAG: &[u8; 4] = b"OMD1";
+/// Tag opening a block of [`ContainerWriterV2::write_cell`] records
+const CELL_RECORDS_TAG: &[u8; 4] = b"OCR1";
 
 /// Stream configuration for Container v2
 #[derive(Debug, Clone)]
@@ -26,6 +81,41 @@ pub struct StreamConfig {
     pub checkpoint_bytes: usize,
     /// Enable SHA-256 hashing (default: false)
     pub enable_sha256: bool,
+    /// Order of keyed blocks in the file (default: insertion order)
+    pub block_order: BlockOrder,
+    /// Payload bytes per frame of a streamed write (default: 4MB)
+    pub stream_chunk_bytes: usize,
+    /// How [`ContainerWriterV2::write_cell`] groups cells into blocks
+    /// (default: arrival order)
+    pub cell_chunking: CellChunking,
+    /// Cells per block in [`CellChunking::Arrival`] order (default: 512)
+    pub cells_per_block: usize,
+}
+
+impl StreamConfig {
+    /// Builder form setting [`Self::block_order`]
+    pub fn with_block_order(mut self, block_order: BlockOrder) -> Self {
+        self.block_order = block_order;
+        self
+    }
+
+    /// Builder form setting [`Self::stream_chunk_bytes`]
+    pub fn with_stream_chunk_bytes(mut self, bytes: usize) -> Self {
+        self.stream_chunk_bytes = bytes;
+        self
+    }
+
+    /// Builder form setting [`Self::cell_chunking`]
+    pub fn with_cell_chunking(mut self, cell_chunking: CellChunking) -> Self {
+        self.cell_chunking = cell_chunking;
+        self
+    }
+
+    /// Builder form setting [`Self::cells_per_block`]
+    pub fn with_cells_per_block(mut self, cells: usize) -> Self {
+        self.cells_per_block = cells;
+        self
+    }
 }
 
 impl Default for StreamConfig {
@@ -34,21 +124,206 @@ impl Default for StreamConfig {
             checkpoint_frames: 1000,
             checkpoint_bytes: 64 * 1024 * 1024,
             enable_sha256: false,
+            block_order: BlockOrder::default(),
+            stream_chunk_bytes: 4 * 1024 * 1024,
+            cell_chunking: CellChunking::default(),
+            cells_per_block: 512,
+        }
+    }
+}
+
+/// Typed value of a container metadata entry
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetadataValue {
+    /// UTF-8 text, such as a software version
+    String(String),
+    /// Signed integer
+    Int(i64),
+    /// Floating-point number, such as a map origin coordinate
+    Float(f64),
+    /// Opaque bytes, such as a sensor calibration blob
+    Bytes(Vec<u8>),
+}
+
+impl MetadataValue {
+    /// Type tag in the metadata section
+    fn tag(&self) -> u8 {
+        match self {
+            MetadataValue::String(_) => 0,
+            MetadataValue::Int(_) => 1,
+            MetadataValue::Float(_) => 2,
+            MetadataValue::Bytes(_) => 3,
+        }
+    }
+
+    /// The text of a string value
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            MetadataValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// An integer value
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            MetadataValue::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// A number, converting integers
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            MetadataValue::Int(value) => Some(*value as f64),
+            MetadataValue::Float(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The bytes of a binary value
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            MetadataValue::Bytes(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl From<&str> for MetadataValue {
+    fn from(value: &str) -> Self {
+        MetadataValue::String(value.to_string())
+    }
+}
+
+impl From<String> for MetadataValue {
+    fn from(value: String) -> Self {
+        MetadataValue::String(value)
+    }
+}
+
+impl From<i64> for MetadataValue {
+    fn from(value: i64) -> Self {
+        MetadataValue::Int(value)
+    }
+}
+
+impl From<f64> for MetadataValue {
+    fn from(value: f64) -> Self {
+        MetadataValue::Float(value)
+    }
+}
+
+impl From<Vec<u8>> for MetadataValue {
+    fn from(value: Vec<u8>) -> Self {
+        MetadataValue::Bytes(value)
+    }
+}
+
+/// A cell and its record, as written with [`ContainerWriterV2::write_cell`]
+pub type CellRecord = (Index64, Vec<u8>);
+
+/// How [`ContainerWriterV2::write_cell`] partitions cells into blocks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CellChunking {
+    /// Cells fill plain frames of [`StreamConfig::cells_per_block`] in the
+    /// order they arrive
+    #[default]
+    Arrival,
+    /// Cells whose Morton codes agree above the low `bits` bits share a
+    /// block, keyed by the bucket's first possible cell
+    ///
+    /// Spatially close cells land in the same block whatever order they
+    /// arrive in, which compresses far better and lets a region query read
+    /// the few blocks covering it through the [`BlockIndex`]. `bits` is a
+    /// multiple of 3 for cubic buckets: 9 gives 8×8×8 codes, of which 128
+    /// are BCC lattice points. A bucket is written once all of its codes
+    /// have arrived, or all of its lattice points if it holds only those,
+    /// and otherwise at [`ContainerWriterV2::finish`], so memory grows with
+    /// the buckets left incomplete. Cells arriving for a bucket already
+    /// written go to plain frames, which [`ContainerReaderV2::cells`] reads
+    /// too.
+    MortonBuckets {
+        /// Low Morton bits spanned by a bucket, at most 48
+        bits: u8,
+    },
+}
+
+impl CellChunking {
+    /// Key of the block holding `idx`, or `None` in arrival order
+    pub fn bucket_key(self, idx: Index64) -> Result<Option<Index64>> {
+        match self {
+            CellChunking::Arrival => Ok(None),
+            CellChunking::MortonBuckets { bits } if bits <= 48 => {
+                let low = (1u64 << bits) - 1;
+                Index64::from_value(idx.raw() & !low).map(Some)
+            }
+            CellChunking::MortonBuckets { bits } => Err(Error::InvalidFormat(format!(
+                "Morton buckets of {} bits exceed the 48-bit code",
+                bits
+            ))),
         }
     }
 }
 
+/// Cells of a [`CellChunking::MortonBuckets`] block being filled
+#[derive(Default)]
+struct CellBucket {
+    cells: BTreeMap<Index64, Vec<u8>>,
+    /// Cells on the BCC lattice
+    lattice_cells: u64,
+}
+
+/// BCC lattice points in a bucket spanning the low `bits` Morton bits
+///
+/// From 3 bits on, the bucket is an aligned box with even extents, of
+/// which a quarter of the points have all-even or all-odd coordinates.
+/// Smaller buckets hold at most one.
+fn bucket_lattice_points(bits: u8) -> u64 {
+    if bits >= 3 {
+        1 << (bits - 2)
+    } else {
+        1
+    }
+}
+
+/// Order in which [`ContainerWriterV2::write_block`] blocks reach the file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlockOrder {
+    /// Each block is written as it arrives
+    #[default]
+    Insertion,
+    /// Blocks are buffered and written sorted by frame, tier, LOD and then
+    /// the Hilbert index of their key
+    ///
+    /// A Hilbert curve visits every aligned tile completely before leaving
+    /// it, so the chunks of any tile end up contiguous and a region query
+    /// reads a few long runs instead of scattered blocks. The buffer is
+    /// flushed whenever it reaches the [`StreamConfig`] checkpoint limits
+    /// and at [`ContainerWriterV2::finish`], so the ordering holds within
+    /// each such window.
+    ///
+    /// Needs the `hilbert` feature; without it
+    /// [`ContainerWriterV2::write_block`] returns an error.
+    Hilbert,
+}
+
 /// Container v2 header (32 bytes)
 #[derive(Debug, Clone)]
 pub struct HeaderV2 {
     /// Container format version (currently 2).
     pub format_version: u8,
-    /// Feature flags; bit 0 indicates per-frame SHA-256 hashes are present.
+    /// Feature flags; bit 0 indicates per-frame SHA-256 hashes are present,
+    /// bit 1 a zstd dictionary section between the header and the first frame,
+    /// bit 2 a block index section, bit 3 footer digests.
     pub flags: u8,
     /// Unique stream identifier, derived from the creation timestamp.
     pub stream_id: u64,
-    /// Byte offset of the first frame (immediately after the 32-byte header).
+    /// Byte offset of the first frame (immediately after the 32-byte header
+    /// and the dictionary section, if any).
     pub first_frame_offset: u64,
+    /// Byte offset of the block index section, or 0 without one (48 bits).
+    pub block_index_offset: u64,
 }
 
 impl HeaderV2 {
@@ -57,7 +332,11 @@ impl HeaderV2 {
     pub fn new(enable_sha256: bool) -> Self {
         use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-        let flags = if enable_sha256 { 0x01 } else { 0x00 };
+        let flags = if enable_sha256 {
+            HEADER_FLAG_SHA256
+        } else {
+            0x00
+        };
         let stream_id = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap_or_else(|_| Duration::from_secs(0))
@@ -68,12 +347,28 @@ impl HeaderV2 {
             flags,
             stream_id,
             first_frame_offset: 32,
+            block_index_offset: 0,
         }
     }
 
     /// Returns true if frames in this stream carry SHA-256 integrity hashes.
     pub fn has_sha256(&self) -> bool {
-        (self.flags & 0x01) != 0
+        (self.flags & HEADER_FLAG_SHA256) != 0
+    }
+
+    /// Returns true if a zstd dictionary section follows the header.
+    pub fn has_dictionary(&self) -> bool {
+        (self.flags & HEADER_FLAG_DICTIONARY) != 0
+    }
+
+    /// Returns true if the container has a block index section.
+    pub fn has_block_index(&self) -> bool {
+        (self.flags & HEADER_FLAG_BLOCK_INDEX) != 0
+    }
+
+    /// Returns true if footers carry a digest, see [`Footer::digest`].
+    pub fn has_digest(&self) -> bool {
+        (self.flags & HEADER_FLAG_DIGEST) != 0
     }
 
     /// Serializes the header to its fixed 32-byte on-disk representation.
@@ -82,7 +377,7 @@ impl HeaderV2 {
         bytes[0..8].copy_from_slice(MAGIC_V2);
         bytes[8] = self.format_version;
         bytes[9] = self.flags;
-        // bytes[10..14] reserved = 0
+        bytes[10..16].copy_from_slice(&self.block_index_offset.to_be_bytes()[2..]);
         bytes[16..24].copy_from_slice(&self.stream_id.to_be_bytes());
         bytes[24..32].copy_from_slice(&self.first_frame_offset.to_be_bytes());
         bytes
@@ -96,9 +391,12 @@ impl HeaderV2 {
             return Err(Error::InvalidFormat("Invalid magic number".to_string()));
         }
 
+        let mut block_index_offset = [0u8; 8];
+        block_index_offset[2..].copy_from_slice(&bytes[10..16]);
         Ok(Self {
             format_version: bytes[8],
             flags: bytes[9],
+            block_index_offset: u64::from_be_bytes(block_index_offset),
             stream_id: u64::from_be_bytes(
                 bytes[16..24]
                     .try_into()
@@ -189,11 +487,18 @@ pub struct Footer {
     pub toc_len: u64,
     /// Number of TOC entries (frames) recorded.
     pub entry_count: u64,
-    /// Copy of the header flags, for recovery without re-reading the header.
+    /// Copy of the header flags, for recovery without re-reading the header,
+    /// with the digest in the high 32 bits.
     pub flags_copy: u64,
 }
 
 impl Footer {
+    /// CRC32 of every byte from the end of the header to this footer, if
+    /// [`HeaderV2::has_digest`]
+    pub fn digest(&self) -> u32 {
+        (self.flags_copy >> 32) as u32
+    }
+
     /// Serializes the footer to its fixed 32-byte on-disk representation.
     pub fn to_bytes(&self) -> [u8; 32] {
         let mut bytes = [0u8; 32];
@@ -231,15 +536,80 @@ impl Footer {
     }
 }
 
+/// Writer hashing the bytes that extend the stream, for footer digests
+///
+/// Bytes rewritten behind the end, such as the header, are not hashed.
+struct DigestWriter<W> {
+    inner: W,
+    /// Position of the next write
+    pos: u64,
+    /// End of the hashed bytes
+    hashed_to: u64,
+    hasher: Option<Hasher>,
+}
+
+impl<W> DigestWriter<W> {
+    /// Digest of the bytes hashed so far
+    fn digest(&self) -> Option<u32> {
+        self.hasher.as_ref().map(|h| h.clone().finalize())
+    }
+}
+
+impl<W: Write> Write for DigestWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        if let (Some(hasher), true) = (&mut self.hasher, self.pos == self.hashed_to) {
+            hasher.update(&buf[..n]);
+            self.hashed_to += n as u64;
+        }
+        self.pos += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W: Seek> Seek for DigestWriter<W> {
+    fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+        self.pos = self.inner.seek(pos)?;
+        Ok(self.pos)
+    }
+}
+
 /// Container v2 writer
 pub struct ContainerWriterV2<W: Write + Seek> {
-    writer: W,
+    writer: DigestWriter<W>,
     config: StreamConfig,
     header: HeaderV2,
+    /// Stream position of the header
+    header_offset: u64,
     compression: Box<dyn Compression>,
     toc_entries: Vec<TocEntry>,
     bytes_since_checkpoint: usize,
     next_seq: u64,
+    frames: BTreeSet<FrameId>,
+    /// Stored pieces of the latest write of each block key
+    blocks: HashMap<Index64, Vec<BlockIndexEntry>>,
+    /// Whether the last frame written continues into the next
+    run_open: bool,
+    /// Blocks waiting to be written in [`BlockOrder::Hilbert`] order
+    #[cfg(feature = "hilbert")]
+    pending: Vec<(Index64, Vec<u8>)>,
+    #[cfg(feature = "hilbert")]
+    pending_bytes: usize,
+    /// Cells of the open [`CellChunking::Arrival`] block
+    arrival_cells: Vec<CellRecord>,
+    /// Cells of incomplete [`CellChunking::MortonBuckets`] blocks, by key
+    cell_buckets: BTreeMap<Index64, CellBucket>,
+    /// Keys of blocks already stored; later cells of these buckets go to
+    /// plain frames so the stored block is not superseded
+    written_buckets: BTreeSet<Index64>,
+    /// Metadata written at [`Self::finish`]
+    metadata: BTreeMap<String, MetadataValue>,
+    /// Whether the container already holds a metadata section
+    metadata_stored: bool,
 }
 
 impl<W: Write + Seek> ContainerWriterV2<W> {
@@ -247,19 +617,39 @@ impl<W: Write + Seek> ContainerWriterV2<W> {
     ///
     /// Frames are LZ4-compressed by default; see [`Self::with_compression`].
     pub fn new(mut writer: W, config: StreamConfig) -> Result<Self> {
-        let header = HeaderV2::new(config.enable_sha256);
+        let mut header = HeaderV2::new(config.enable_sha256);
+        header.flags |= HEADER_FLAG_DIGEST;
+        let header_offset = writer.stream_position()?;
 
         // Write header
         writer.write_all(&header.to_bytes())?;
 
         Ok(Self {
-            writer,
+            writer: DigestWriter {
+                inner: writer,
+                pos: header_offset + 32,
+                hashed_to: header_offset + 32,
+                hasher: Some(Hasher::new()),
+            },
             config,
             header,
+            header_offset,
             compression: Box::new(crate::compression::Lz4Compression),
             toc_entries: Vec::new(),
             bytes_since_checkpoint: 0,
             next_seq: 0,
+            frames: BTreeSet::new(),
+            blocks: HashMap::new(),
+            run_open: false,
+            #[cfg(feature = "hilbert")]
+            pending: Vec::new(),
+            #[cfg(feature = "hilbert")]
+            pending_bytes: 0,
+            arrival_cells: Vec::new(),
+            cell_buckets: BTreeMap::new(),
+            written_buckets: BTreeSet::new(),
+            metadata: BTreeMap::new(),
+            metadata_stored: false,
         })
     }
 
@@ -269,11 +659,342 @@ impl<W: Write + Seek> ContainerWriterV2<W> {
         Ok(self)
     }
 
+    /// Compresses every frame with zstd primed by `dictionary`, storing the
+    /// dictionary in the container so readers can decode the frames.
+    ///
+    /// Must be called before the first frame is written. Train the
+    /// dictionary on representative blocks with
+    /// [`train_zstd_dictionary`](crate::compression::train_zstd_dictionary).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use octaindex3d::compression::train_zstd_dictionary;
+    /// use octaindex3d::container_v2::{ContainerReaderV2, ContainerWriterV2, StreamConfig};
+    /// use octaindex3d::Index64;
+    /// use std::io::Cursor;
+    ///
+    /// # fn main() -> octaindex3d::Result<()> {
+    /// let chunk = |i: u16| -> Vec<u8> { (0..1024).map(|j| ((j * 7 + i * 3) % 11) as u8).collect() };
+    /// let samples: Vec<Vec<u8>> = (0..200).map(chunk).collect();
+    /// let dictionary = train_zstd_dictionary(&samples, 8 * 1024)?;
+    ///
+    /// let mut bytes = Vec::new();
+    /// let mut writer = ContainerWriterV2::new(Cursor::new(&mut bytes), StreamConfig::default())?
+    ///     .with_zstd_dictionary(dictionary.clone(), 5)?;
+    /// writer.write_block(Index64::new(0, 0, 4, 1, 2, 3)?, &chunk(500))?;
+    /// writer.finish()?;
+    ///
+    /// let reader = ContainerReaderV2::from_bytes(bytes)?;
+    /// assert_eq!(reader.dictionary(), Some(dictionary.as_slice()));
+    /// assert_eq!(reader.read_frame(0)?, chunk(500));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "zstd")]
+    pub fn with_zstd_dictionary(mut self, dictionary: Vec<u8>, level: i32) -> Result<Self> {
+        use std::io::SeekFrom;
+
+        if self.next_seq > 0 || self.header.has_dictionary() {
+            return Err(Error::InvalidFormat(
+                "the dictionary must be set once, before the first frame".to_string(),
+            ));
+        }
+        let len = u32::try_from(dictionary.len())
+            .map_err(|_| Error::InvalidFormat("dictionary exceeds 4 GiB".to_string()))?;
+        self.compression = Box::new(crate::compression::ZstdDictCompression::with_level(
+            &dictionary,
+            level,
+        ));
+
+        // The section sits right after the header, which is rewritten to
+        // point past it
+        let section_end = self.writer.stream_position()? + 4 + len as u64;
+        self.writer.write_all(&len.to_be_bytes())?;
+        self.writer.write_all(&dictionary)?;
+        self.header.flags |= HEADER_FLAG_DICTIONARY;
+        self.header.first_frame_offset += 4 + len as u64;
+        self.rewrite_header()?;
+        self.writer.seek(SeekFrom::Start(section_end))?;
+        Ok(self)
+    }
+
     /// Appends one frame of data, compressing it and recording a TOC entry.
     ///
     /// A checkpoint (TOC + footer) is flushed automatically once the configured
     /// frame-count or byte thresholds in [`StreamConfig`] are reached.
     pub fn write_frame(&mut self, data: &[u8]) -> Result<()> {
+        self.write_frame_inner(None, 0, data)
+    }
+
+    /// Appends a frame holding the data of the chunk `key`.
+    ///
+    /// A later block with the same key supersedes this one; [`compact`]
+    /// drops superseded blocks and reorders the rest by key. The TOC entry
+    /// records the key's LOD and scale tier, and the key's frame is stored
+    /// with the container.
+    ///
+    /// With [`BlockOrder::Hilbert`] the block is buffered and written later;
+    /// without the `hilbert` feature that order is an error.
+    pub fn write_block(&mut self, key: Index64, data: &[u8]) -> Result<()> {
+        self.frames.insert(key.frame_id());
+        match self.config.block_order {
+            BlockOrder::Insertion => self.write_frame_inner(Some(key), 0, data),
+            #[cfg(feature = "hilbert")]
+            BlockOrder::Hilbert => {
+                self.pending.push((key, data.to_vec()));
+                self.pending_bytes += data.len();
+                if self.pending.len() >= self.config.checkpoint_frames
+                    || self.pending_bytes >= self.config.checkpoint_bytes
+                {
+                    self.flush_pending()?;
+                }
+                Ok(())
+            }
+            #[cfg(not(feature = "hilbert"))]
+            BlockOrder::Hilbert => Err(Error::InvalidFormat(
+                "Hilbert block order needs the hilbert feature".to_string(),
+            )),
+        }
+    }
+
+    /// Write buffered blocks in Hilbert order
+    #[cfg(feature = "hilbert")]
+    fn flush_pending(&mut self) -> Result<()> {
+        let mut pending = std::mem::take(&mut self.pending);
+        self.pending_bytes = 0;
+        pending.sort_by_cached_key(|(key, _)| {
+            // Hilbert64 orders by tier, frame and LOD before the curve index
+            crate::Hilbert64::try_from(*key).map_or(key.raw(), |h| h.as_u64())
+        });
+        for (key, data) in pending {
+            self.write_frame_inner(Some(key), 0, &data)?;
+        }
+        Ok(())
+    }
+
+    #[cfg(not(feature = "hilbert"))]
+    fn flush_pending(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Appends one frame whose payload is read from `reader` until it ends,
+    /// returning the payload length.
+    ///
+    /// The payload is compressed and written in pieces of
+    /// [`StreamConfig::stream_chunk_bytes`], so memory stays bounded by
+    /// about two pieces whatever the payload size. Readers see a single
+    /// frame; [`ContainerReaderV2::copy_frame`] decompresses it piece by
+    /// piece as well.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use octaindex3d::container_v2::{ContainerReaderV2, ContainerWriterV2, StreamConfig};
+    /// use std::io::{Cursor, Read};
+    ///
+    /// # fn main() -> octaindex3d::Result<()> {
+    /// let config = StreamConfig {
+    ///     stream_chunk_bytes: 1000,
+    ///     ..StreamConfig::default()
+    /// };
+    /// let mut bytes = Vec::new();
+    /// let mut writer = ContainerWriterV2::new(Cursor::new(&mut bytes), config)?;
+    /// let layer = std::io::repeat(7).take(10_500);
+    /// assert_eq!(writer.write_stream(layer)?, 10_500);
+    /// writer.finish()?;
+    ///
+    /// let reader = ContainerReaderV2::from_bytes(bytes)?;
+    /// assert_eq!(reader.frame_count(), 1);
+    /// let mut out = Vec::new();
+    /// assert_eq!(reader.copy_frame(0, &mut out)?, 10_500);
+    /// assert_eq!(out, vec![7; 10_500]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn write_stream<R: Read>(&mut self, reader: R) -> Result<u64> {
+        self.write_stream_inner(None, reader)
+    }
+
+    /// Appends a block for chunk `key` whose payload is read from
+    /// `reader`, as [`Self::write_stream`] does.
+    ///
+    /// Streamed blocks are written as they arrive, whatever the
+    /// [`BlockOrder`], and supersede earlier blocks with the same key like
+    /// [`Self::write_block`].
+    pub fn write_block_stream<R: Read>(&mut self, key: Index64, reader: R) -> Result<u64> {
+        self.frames.insert(key.frame_id());
+        self.write_stream_inner(Some(key), reader)
+    }
+
+    /// Sets metadata entry `key`, replacing any earlier value
+    ///
+    /// Metadata is written uncompressed at [`Self::finish`], so readers get
+    /// it from [`ContainerReaderV2::metadata`] without decompressing any
+    /// block. Appending keeps the entries already stored.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use octaindex3d::container_v2::{ContainerReaderV2, ContainerWriterV2, MetadataValue, StreamConfig};
+    /// use std::io::Cursor;
+    ///
+    /// # fn main() -> octaindex3d::Result<()> {
+    /// let mut bytes = Vec::new();
+    /// let mut writer = ContainerWriterV2::new(Cursor::new(&mut bytes), StreamConfig::default())?;
+    /// writer.set_metadata("software.version", "mapper 2.4.1");
+    /// writer.set_metadata("origin.latitude", 47.6205);
+    /// writer.set_metadata("lidar.extrinsics", vec![0u8; 48]);
+    /// writer.write_frame(b"scan")?;
+    /// writer.finish()?;
+    ///
+    /// let reader = ContainerReaderV2::from_bytes(bytes)?;
+    /// assert_eq!(reader.metadata_value("origin.latitude"), Some(&MetadataValue::Float(47.6205)));
+    /// assert_eq!(reader.metadata_value("software.version").and_then(|v| v.as_str()), Some("mapper 2.4.1"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_metadata(&mut self, key: impl Into<String>, value: impl Into<MetadataValue>) {
+        self.metadata.insert(key.into(), value.into());
+    }
+
+    /// Removes metadata entry `key`, returning its value
+    pub fn remove_metadata(&mut self, key: &str) -> Option<MetadataValue> {
+        self.metadata.remove(key)
+    }
+
+    /// Metadata to be written, by key
+    pub fn metadata(&self) -> &BTreeMap<String, MetadataValue> {
+        &self.metadata
+    }
+
+    /// Adds the record of cell `idx` to a block chosen by
+    /// [`StreamConfig::cell_chunking`]
+    ///
+    /// Blocks are written once full and at [`Self::finish`]; read them
+    /// back with [`ContainerReaderV2::cells`]. With Morton buckets a later
+    /// record of the same cell replaces an earlier one still in its bucket.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use octaindex3d::container_v2::{CellChunking, ContainerReaderV2, ContainerWriterV2, StreamConfig};
+    /// use octaindex3d::Index64;
+    /// use std::io::Cursor;
+    ///
+    /// # fn main() -> octaindex3d::Result<()> {
+    /// let config = StreamConfig::default().with_cell_chunking(CellChunking::MortonBuckets { bits: 9 });
+    /// let mut bytes = Vec::new();
+    /// let mut writer = ContainerWriterV2::new(Cursor::new(&mut bytes), config)?;
+    /// // Cells arriving from two far-apart places interleaved
+    /// for i in 0..16u16 {
+    ///     writer.write_cell(Index64::new(0, 0, 8, i % 8, 0, 0)?, &[1])?;
+    ///     writer.write_cell(Index64::new(0, 0, 8, 896 + i % 8, 0, 0)?, &[2])?;
+    /// }
+    /// writer.finish()?;
+    ///
+    /// // ... are stored as one block per place
+    /// let reader = ContainerReaderV2::from_bytes(bytes)?;
+    /// assert_eq!(reader.block_index()?.unwrap().entries().len(), 2);
+    /// assert_eq!(reader.cells()?.len(), 16);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn write_cell(&mut self, idx: Index64, record: &[u8]) -> Result<()> {
+        let chunking = self.config.cell_chunking;
+        let key = match chunking.bucket_key(idx)? {
+            Some(key) if !self.written_buckets.contains(&key) => key,
+            _ => {
+                self.arrival_cells.push((idx, record.to_vec()));
+                if self.arrival_cells.len() >= self.config.cells_per_block.max(1) {
+                    self.flush_arrival_cells()?;
+                }
+                return Ok(());
+            }
+        };
+        let CellChunking::MortonBuckets { bits } = chunking else {
+            unreachable!("arrival order has no bucket keys")
+        };
+        let bucket = self.cell_buckets.entry(key).or_default();
+        let (x, y, z) = idx.decode_coords();
+        if bucket.cells.insert(idx, record.to_vec()).is_none()
+            && is_valid_bcc(x as i32, y as i32, z as i32)
+        {
+            bucket.lattice_cells += 1;
+        }
+        // Complete once every code has arrived or, for cells all on the
+        // lattice, every lattice point
+        let cells = bucket.cells.len() as u64;
+        let lattice_only = cells == bucket.lattice_cells;
+        if cells > (1u64 << bits) - 1 || (lattice_only && cells >= bucket_lattice_points(bits)) {
+            let bucket = self.cell_buckets.remove(&key).unwrap_or_default();
+            self.write_bucket(key, bucket.cells)?;
+        }
+        Ok(())
+    }
+
+    fn write_bucket(&mut self, key: Index64, cells: BTreeMap<Index64, Vec<u8>>) -> Result<()> {
+        self.written_buckets.insert(key);
+        self.write_block(key, &encode_cell_records(cells))
+    }
+
+    fn flush_arrival_cells(&mut self) -> Result<()> {
+        if self.arrival_cells.is_empty() {
+            return Ok(());
+        }
+        // Readers keep the last record of a cell, so bucket blocks still
+        // buffered for Hilbert order must land before later records
+        if matches!(
+            self.config.cell_chunking,
+            CellChunking::MortonBuckets { .. }
+        ) {
+            self.flush_pending()?;
+        }
+        let cells = std::mem::take(&mut self.arrival_cells);
+        self.write_frame(&encode_cell_records(cells))
+    }
+
+    /// Deletes block `key`, writing a tombstone that hides every earlier
+    /// write of it from readers.
+    ///
+    /// A later [`Self::write_block`] with the key stores it again.
+    /// [`compact`] drops the tombstone together with the block.
+    pub fn delete_block(&mut self, key: Index64) -> Result<()> {
+        #[cfg(feature = "hilbert")]
+        self.pending.retain(|(pending, _)| *pending != key);
+        self.write_frame_inner(Some(key), FRAME_FLAG_TOMBSTONE, &[])
+    }
+
+    fn write_stream_inner<R: Read>(&mut self, key: Option<Index64>, mut reader: R) -> Result<u64> {
+        let chunk = self.config.stream_chunk_bytes.max(1);
+        let mut current = read_chunk(&mut reader, chunk)?;
+        let mut total = current.len() as u64;
+        loop {
+            // Read ahead so the last piece is written without the flag
+            let next = if current.len() == chunk {
+                read_chunk(&mut reader, chunk)?
+            } else {
+                Vec::new()
+            };
+            if next.is_empty() {
+                self.write_frame_inner(key, 0, &current)?;
+                return Ok(total);
+            }
+            self.write_frame_inner(key, FRAME_FLAG_CONTINUED, &current)?;
+            total += next.len() as u64;
+            current = next;
+        }
+    }
+
+    /// Store frame `id` with the container, for data written with
+    /// [`Self::write_frame`] that refers to it
+    ///
+    /// Frames of block keys are stored automatically.
+    pub fn include_frame(&mut self, id: FrameId) {
+        self.frames.insert(id);
+    }
+
+    fn write_frame_inner(&mut self, key: Option<Index64>, flags: u8, data: &[u8]) -> Result<()> {
         let uncompressed_len = data.len() as u32;
         let offset = self.writer.stream_position()?;
 
@@ -301,11 +1022,14 @@ impl<W: Write + Seek> ContainerWriterV2<W> {
         frame_header[0] = self.compression.codec_id();
         frame_header[1] = 0; // codec_vers
         frame_header[2] = 0; // graph_id
-        frame_header[3] = 0; // pad
+        frame_header[3] = flags | if key.is_some() { FRAME_FLAG_KEYED } else { 0 };
         frame_header[4..8].copy_from_slice(&uncompressed_len.to_be_bytes());
         frame_header[8..12].copy_from_slice(&compressed_len.to_be_bytes());
         frame_header[12..16].copy_from_slice(&crc32.to_be_bytes());
         self.writer.write_all(&frame_header)?;
+        if let Some(key) = key {
+            self.writer.write_all(&key.raw().to_be_bytes())?;
+        }
 
         // Write compressed data
         self.writer.write_all(&compressed)?;
@@ -316,6 +1040,22 @@ impl<W: Write + Seek> ContainerWriterV2<W> {
             self.writer.write_all(&hash)?;
         }
 
+        if let Some(key) = key {
+            let piece = BlockIndexEntry {
+                key,
+                offset,
+                len: (self.writer.stream_position()? - offset) as u32,
+            };
+            if flags & FRAME_FLAG_TOMBSTONE != 0 {
+                self.blocks.remove(&key);
+            } else if self.run_open {
+                self.blocks.entry(key).or_default().push(piece);
+            } else {
+                self.blocks.insert(key, vec![piece]);
+            }
+        }
+        self.run_open = flags & FRAME_FLAG_CONTINUED != 0;
+
         // Add TOC entry
         self.toc_entries.push(TocEntry {
             offset,
@@ -323,8 +1063,8 @@ impl<W: Write + Seek> ContainerWriterV2<W> {
             compressed_len,
             codec: self.compression.codec_id(),
             graph: 0,
-            lod: 0,
-            tier: 0,
+            lod: key.map_or(0, |k| k.lod()),
+            tier: key.map_or(0, |k| k.scale_tier()),
             seq: self.next_seq,
         });
 
@@ -341,6 +1081,14 @@ impl<W: Write + Seek> ContainerWriterV2<W> {
         Ok(())
     }
 
+    /// Write the header again over the original, leaving the stream there
+    fn rewrite_header(&mut self) -> Result<()> {
+        self.writer
+            .seek(std::io::SeekFrom::Start(self.header_offset))?;
+        self.writer.write_all(&self.header.to_bytes())?;
+        Ok(())
+    }
+
     fn write_checkpoint(&mut self) -> Result<()> {
         let toc_offset = self.writer.stream_position()?;
 
@@ -357,7 +1105,7 @@ impl<W: Write + Seek> ContainerWriterV2<W> {
             toc_offset,
             toc_len,
             entry_count,
-            flags_copy: self.header.flags as u64,
+            flags_copy: self.header.flags as u64 | (self.writer.digest().unwrap_or(0) as u64) << 32,
         };
         self.writer.write_all(&footer.to_bytes())?;
         self.writer.flush()?;
@@ -368,9 +1116,48 @@ impl<W: Write + Seek> ContainerWriterV2<W> {
 
     /// Finalizes the container, writing the last checkpoint (TOC + footer).
     ///
+    /// Custom frames used by the container, with their ancestors, are
+    /// written first as a registry section, as registered at this point.
+    ///
     /// Must be called for the container to be readable; dropping the writer
     /// without calling `finish` leaves only data up to the last checkpoint.
     pub fn finish(mut self) -> Result<()> {
+        for (key, bucket) in std::mem::take(&mut self.cell_buckets) {
+            self.write_bucket(key, bucket.cells)?;
+        }
+        self.flush_pending()?;
+        self.flush_arrival_cells()?;
+        let registry = export_frames(self.frames.iter().copied());
+        // An empty record is the version byte and a zero count
+        if registry.len() > 3 {
+            self.write_frame_inner(None, FRAME_FLAG_REGISTRY, &registry)?;
+        }
+        // An empty section clears the entries of an earlier session
+        if !self.metadata.is_empty() || self.metadata_stored {
+            let section = encode_metadata(&self.metadata)?;
+            let codec = std::mem::replace(
+                &mut self.compression,
+                Box::new(crate::compression::NoCompression),
+            );
+            let written = self.write_frame_inner(None, FRAME_FLAG_METADATA, &section);
+            self.compression = codec;
+            written?;
+        }
+
+        if !self.blocks.is_empty() {
+            let mut blocks: Vec<Vec<BlockIndexEntry>> =
+                self.blocks.drain().map(|(_, b)| b).collect();
+            blocks.sort_unstable_by_key(|pieces| pieces[0].key);
+            let offset = self.writer.stream_position()?;
+            self.writer
+                .write_all(&encode_block_index(blocks.iter().flatten()))?;
+            let end = self.writer.stream_position()?;
+            self.header.flags |= HEADER_FLAG_BLOCK_INDEX;
+            self.header.block_index_offset = block_index_offset(offset)?;
+            self.rewrite_header()?;
+            self.writer.seek(std::io::SeekFrom::Start(end))?;
+        }
+
         // Write final checkpoint
         if !self.toc_entries.is_empty() {
             self.write_checkpoint()?;
@@ -379,58 +1166,2332 @@ impl<W: Write + Seek> ContainerWriterV2<W> {
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::io::Cursor;
+impl<W: Read + Write + Seek> ContainerWriterV2<W> {
+    /// Reopens the finished container in `file` to append frames, without
+    /// rewriting what it holds.
+    ///
+    /// New blocks supersede or [delete](Self::delete_block) earlier ones
+    /// as in a single session, and [`Self::finish`] (or any checkpoint)
+    /// leaves a container listing the old and new frames; [`compact`]
+    /// reclaims the space of superseded blocks later. The frames stored
+    /// with the container are registered, as on read, and a dictionary it
+    /// holds keeps compressing new frames. Only the header, TOC and frame
+    /// headers are read.
+    ///
+    /// A streamed frame the last checkpoint cut short is dropped. Until
+    /// `finish`, the header lists no block index.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use octaindex3d::container_v2::{ContainerReaderV2, ContainerWriterV2, StreamConfig};
+    /// use octaindex3d::Index64;
+    /// use std::fs::{File, OpenOptions};
+    ///
+    /// # fn main() -> octaindex3d::Result<()> {
+    /// let path = std::env::temp_dir().join(format!("append-doc-{}.oct2", std::process::id()));
+    /// let key = |x| Index64::new(0, 0, 6, x, 0, 0);
+    ///
+    /// let mut writer = ContainerWriterV2::new(File::create(&path)?, StreamConfig::default())?;
+    /// writer.write_block(key(1)?, b"morning")?;
+    /// writer.write_block(key(2)?, b"morning")?;
+    /// writer.finish()?;
+    ///
+    /// // Later in the session
+    /// let file = OpenOptions::new().read(true).write(true).open(&path)?;
+    /// let mut writer = ContainerWriterV2::append(file, StreamConfig::default())?;
+    /// writer.write_block(key(1)?, b"afternoon")?;
+    /// writer.delete_block(key(2)?)?;
+    /// writer.finish()?;
+    ///
+    /// let reader = ContainerReaderV2::open(&path)?;
+    /// assert_eq!(reader.frame_count(), 2); // the deleted block is hidden
+    /// assert_eq!(reader.read_frame(1)?, b"afternoon");
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn append(mut file: W, config: StreamConfig) -> Result<Self> {
+        use std::io::SeekFrom;
 
-    #[test]
-    fn test_header_v2_roundtrip() {
-        let header = HeaderV2::new(true);
-        assert!(header.has_sha256());
+        let invalid = |msg: &str| Error::InvalidFormat(msg.to_string());
+        let mut raw = [0u8; 32];
+        file.seek(SeekFrom::Start(0))?;
+        file.read_exact(&mut raw)?;
+        let mut header = HeaderV2::from_bytes(&raw)?;
+        if header.format_version != FORMAT_VERSION_V2 {
+            return Err(Error::InvalidFormat(format!(
+                "Unsupported format version: {}",
+                header.format_version
+            )));
+        }
+        let end = file.seek(SeekFrom::End(0))?;
+        if end < 64 {
+            return Err(invalid("container is too short"));
+        }
+        file.seek(SeekFrom::Start(end - 32))?;
+        file.read_exact(&mut raw)?;
+        let footer = Footer::from_bytes(&raw);
+        if footer.entry_count.checked_mul(32) != Some(footer.toc_len)
+            || footer.toc_offset.checked_add(footer.toc_len) != Some(end - 32)
+            || footer.toc_offset < header.first_frame_offset
+        {
+            return Err(invalid("container does not end with a complete checkpoint"));
+        }
+        let mut toc = vec![0u8; footer.toc_len as usize];
+        file.seek(SeekFrom::Start(footer.toc_offset))?;
+        file.read_exact(&mut toc)?;
 
-        let bytes = header.to_bytes();
-        let header2 = HeaderV2::from_bytes(&bytes).unwrap();
+        let compression = stored_compression(&mut file, &header)?;
 
-        assert_eq!(header.format_version, header2.format_version);
-        assert_eq!(header.flags, header2.flags);
-        assert_eq!(header.stream_id, header2.stream_id);
-    }
+        // Frame headers first, to find a streamed run cut short at the end
+        let hash_len = if header.has_sha256() { 32 } else { 0 };
+        let mut stored = Vec::with_capacity(toc.len() / 32);
+        for raw in toc.chunks_exact(32) {
+            let entry = TocEntry::from_bytes(raw.try_into().expect("32 bytes"));
+            if entry.offset < header.first_frame_offset || entry.offset + 16 > footer.toc_offset {
+                return Err(invalid("TOC entry points outside the frame region"));
+            }
+            let mut frame_header = [0u8; 24];
+            file.seek(SeekFrom::Start(entry.offset))?;
+            file.read_exact(&mut frame_header[..16])?;
+            let flags = frame_header[3];
+            let key = if flags & FRAME_FLAG_KEYED != 0 {
+                file.read_exact(&mut frame_header[16..])?;
+                let raw = u64::from_be_bytes(frame_header[16..24].try_into().unwrap());
+                Some(Index64::from_value(raw)?)
+            } else {
+                None
+            };
+            stored.push((entry, flags, key));
+        }
+        let mut kept = stored.len();
+        while kept > 0 && stored[kept - 1].1 & FRAME_FLAG_CONTINUED != 0 {
+            kept -= 1;
+        }
+        stored.truncate(kept);
 
-    #[test]
-    fn test_toc_entry_roundtrip() {
-        let entry = TocEntry {
-            offset: 1000,
-            uncompressed_len: 500,
-            compressed_len: 300,
-            codec: 0,
-            graph: 0,
-            lod: 5,
-            tier: 1,
-            seq: 42,
+        let mut toc_entries = Vec::with_capacity(stored.len());
+        let mut blocks: HashMap<Index64, Vec<BlockIndexEntry>> = HashMap::new();
+        let mut frames = BTreeSet::new();
+        let mut registry = None;
+        let mut metadata_entry = None;
+        let mut run_open = false;
+        for (entry, flags, key) in stored {
+            if let Some(key) = key {
+                let piece = BlockIndexEntry {
+                    key,
+                    offset: entry.offset,
+                    len: 24 + entry.compressed_len + hash_len,
+                };
+                if flags & FRAME_FLAG_TOMBSTONE != 0 {
+                    blocks.remove(&key);
+                } else if run_open {
+                    blocks.entry(key).or_default().push(piece);
+                } else {
+                    blocks.insert(key, vec![piece]);
+                }
+                frames.insert(key.frame_id());
+            }
+            if flags & FRAME_FLAG_REGISTRY != 0 {
+                registry = Some(entry.clone());
+            }
+            if flags & FRAME_FLAG_METADATA != 0 {
+                metadata_entry = Some(entry.clone());
+            }
+            run_open = flags & FRAME_FLAG_CONTINUED != 0;
+            toc_entries.push(entry);
+        }
+        if let Some(entry) = registry {
+            let mut stored = vec![0u8; entry.compressed_len as usize];
+            file.seek(SeekFrom::Start(entry.offset + 16))?;
+            file.read_exact(&mut stored)?;
+            let record = match entry.codec {
+                crate::compression::CODEC_ZSTD_DICT => compression.decompress(&stored)?,
+                codec => get_compression(codec)?.decompress(&stored)?,
+            };
+            frames.extend(import_frames(&record)?);
+        }
+        let metadata = match metadata_entry {
+            Some(entry) => {
+                let mut stored = vec![0u8; entry.compressed_len as usize];
+                file.seek(SeekFrom::Start(entry.offset + 16))?;
+                file.read_exact(&mut stored)?;
+                Some(decode_metadata(
+                    &get_compression(entry.codec)?.decompress(&stored)?,
+                )?)
+            }
+            None => None,
         };
 
-        let bytes = entry.to_bytes();
-        let entry2 = TocEntry::from_bytes(&bytes);
+        // The old block index describes old data until finish writes one
+        if header.has_block_index() {
+            header.flags &= !HEADER_FLAG_BLOCK_INDEX;
+            header.block_index_offset = 0;
+            file.seek(SeekFrom::Start(0))?;
+            file.write_all(&header.to_bytes())?;
+        }
+        // Footer digests go on from the bytes already stored
+        let hasher = if header.has_digest() {
+            let mut hasher = Hasher::new();
+            file.seek(SeekFrom::Start(32))?;
+            std::io::copy(&mut (&mut file).take(end - 32), &mut HashSink(&mut hasher))?;
+            Some(hasher)
+        } else {
+            None
+        };
+        file.seek(SeekFrom::Start(end))?;
+        let written_buckets = blocks.keys().copied().collect();
 
-        assert_eq!(entry.offset, entry2.offset);
-        assert_eq!(entry.seq, entry2.seq);
-        assert_eq!(entry.lod, entry2.lod);
+        Ok(Self {
+            writer: DigestWriter {
+                inner: file,
+                pos: end,
+                hashed_to: end,
+                hasher,
+            },
+            next_seq: toc_entries.iter().map(|e| e.seq + 1).max().unwrap_or(0),
+            config,
+            header,
+            header_offset: 0,
+            compression,
+            toc_entries,
+            bytes_since_checkpoint: 0,
+            frames,
+            blocks,
+            run_open: false,
+            #[cfg(feature = "hilbert")]
+            pending: Vec::new(),
+            #[cfg(feature = "hilbert")]
+            pending_bytes: 0,
+            arrival_cells: Vec::new(),
+            cell_buckets: BTreeMap::new(),
+            written_buckets,
+            metadata_stored: metadata.is_some(),
+            metadata: metadata.unwrap_or_default(),
+        })
     }
+}
 
-    #[test]
-    fn test_container_v2_write() {
-        let mut buffer = Vec::new();
-        let config = StreamConfig::default();
+/// Writer feeding a hasher
+struct HashSink<'a>(&'a mut Hasher);
 
-        {
-            let mut writer = ContainerWriterV2::new(Cursor::new(&mut buffer), config).unwrap();
-            writer.write_frame(b"Hello, world!").unwrap();
-            writer.write_frame(b"Frame 2").unwrap();
-            writer.finish().unwrap();
-        }
+impl Write for HashSink<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.update(buf);
+        Ok(buf.len())
+    }
 
-        // Verify header magic
-        assert_eq!(&buffer[0..8], b"OCTA3D2\0");
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Codec for frames appended to a container: its dictionary, if it has
+/// one and zstd is enabled, LZ4 otherwise
+fn stored_compression<R: Read + Seek>(
+    file: &mut R,
+    header: &HeaderV2,
+) -> Result<Box<dyn Compression>> {
+    #[cfg(feature = "zstd")]
+    if header.has_dictionary() {
+        let mut section = vec![0u8; header.first_frame_offset.saturating_sub(32) as usize];
+        file.seek(std::io::SeekFrom::Start(32))?;
+        file.read_exact(&mut section)?;
+        let dictionary = section
+            .get(4..)
+            .ok_or_else(|| Error::InvalidFormat("dictionary section is truncated".to_string()))?;
+        return Ok(Box::new(crate::compression::ZstdDictCompression::new(
+            dictionary,
+        )));
+    }
+    #[cfg(not(feature = "zstd"))]
+    let _ = (file, header);
+    Ok(Box::new(crate::compression::Lz4Compression))
+}
+
+/// Up to `len` bytes from `reader`, fewer only at its end
+fn read_chunk<R: Read>(reader: &mut R, len: usize) -> Result<Vec<u8>> {
+    let mut buf = Vec::with_capacity(len);
+    reader.take(len as u64).read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+/// A frame as readers see it: one stored frame, or a run of them joined
+/// by [`FRAME_FLAG_CONTINUED`]
+struct LogicalFrame {
+    key: Option<Index64>,
+    /// Stored frames in order, with their compressed payloads
+    parts: Vec<(TocEntry, Range<usize>)>,
+}
+
+/// Container v2 reader
+///
+/// Opening a container registers the frames stored in it with the process
+/// frame registry, so its block keys resolve to the same frames they were
+/// written with. A stored frame that conflicts with one already registered
+/// under the same id fails the open with [`Error::FrameConflict`].
+///
+/// # Example
+///
+/// ```
+/// use octaindex3d::container_v2::{ContainerReaderV2, ContainerWriterV2, StreamConfig};
+/// use octaindex3d::{frame, FrameDescriptor, Index64};
+/// use std::io::Cursor;
+///
+/// # fn main() -> octaindex3d::Result<()> {
+/// frame::register_frame(60, FrameDescriptor::new("dock", "local", "Loading dock", true, 0.1))?;
+///
+/// let mut bytes = Vec::new();
+/// let mut writer = ContainerWriterV2::new(Cursor::new(&mut bytes), StreamConfig::default())?;
+/// writer.write_block(Index64::new(60, 0, 4, 1, 2, 3)?, b"chunk")?;
+/// writer.finish()?;
+///
+/// let reader = ContainerReaderV2::from_bytes(bytes)?;
+/// assert_eq!(reader.stored_frames(), &[60]);
+/// assert_eq!(reader.read_frame(0)?, b"chunk");
+/// # Ok(())
+/// # }
+/// ```
+pub struct ContainerReaderV2 {
+    bytes: Storage,
+    /// Whether frame CRCs were checked on open; if not, each read checks
+    crcs_checked: bool,
+    header: HeaderV2,
+    frames: Vec<LogicalFrame>,
+    stored_frames: Vec<FrameId>,
+    metadata: BTreeMap<String, MetadataValue>,
+    /// Dictionary section contents
+    dictionary: Option<Range<usize>>,
+    /// Codec for frames compressed with the dictionary
+    dictionary_codec: Option<Box<dyn Compression>>,
+}
+
+impl ContainerReaderV2 {
+    /// Reads the container at `path` and registers its stored frames
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_bytes(fs::read(path)?)
+    }
+
+    /// Reads a container held in memory and registers its stored frames
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self> {
+        Self::from_storage(Storage::Owned(bytes), true)
+    }
+
+    fn from_storage(bytes: Storage, check_crcs: bool) -> Result<Self> {
+        let (header, stored) = read_frames(&bytes, check_crcs)?;
+        let dictionary = dictionary_section(&bytes, &header)?;
+        #[cfg(feature = "zstd")]
+        let dictionary_codec = dictionary.clone().map(|range| {
+            Box::new(crate::compression::ZstdDictCompression::new(&bytes[range]))
+                as Box<dyn Compression>
+        });
+        #[cfg(not(feature = "zstd"))]
+        let dictionary_codec = None;
+        let mut frames = Vec::with_capacity(stored.len());
+        let mut registry = None;
+        let mut metadata = None;
+        let mut deleted: HashMap<Index64, u64> = HashMap::new();
+        let mut open: Option<LogicalFrame> = None;
+        for frame in stored {
+            if frame.flags & FRAME_FLAG_REGISTRY != 0 {
+                // A later section describes the registry as last written
+                registry = Some(frame);
+                continue;
+            }
+            if frame.flags & FRAME_FLAG_METADATA != 0 {
+                metadata = Some(frame);
+                continue;
+            }
+            if let (Some(key), true) = (frame.key, frame.flags & FRAME_FLAG_TOMBSTONE != 0) {
+                deleted.insert(key, frame.entry.seq);
+                continue;
+            }
+            let mut logical = open.take().unwrap_or(LogicalFrame {
+                key: frame.key,
+                parts: Vec::new(),
+            });
+            logical.parts.push((frame.entry, frame.data));
+            if frame.flags & FRAME_FLAG_CONTINUED != 0 {
+                open = Some(logical);
+            } else {
+                frames.push(logical);
+            }
+        }
+        // A run still open was cut short by a checkpoint and is ignored;
+        // tombstones hide the writes before them
+        frames.retain(|frame| {
+            frame
+                .key
+                .and_then(|key| deleted.get(&key))
+                .map_or(true, |&seq| frame.parts[0].0.seq > seq)
+        });
+        let stored_frames = match registry {
+            Some(frame) => {
+                if !check_crcs {
+                    check_crc(&bytes, &frame.entry, frame.data.clone())?;
+                }
+                import_frames(&decompress(
+                    &bytes,
+                    &frame.entry,
+                    frame.data,
+                    dictionary_codec.as_deref(),
+                )?)?
+            }
+            None => Vec::new(),
+        };
+        let metadata = match metadata {
+            Some(frame) => {
+                if !check_crcs {
+                    check_crc(&bytes, &frame.entry, frame.data.clone())?;
+                }
+                decode_metadata(&decompress(&bytes, &frame.entry, frame.data, None)?)?
+            }
+            None => BTreeMap::new(),
+        };
+        Ok(Self {
+            crcs_checked: check_crcs,
+            metadata,
+            header,
+            frames,
+            stored_frames,
+            dictionary,
+            dictionary_codec,
+            bytes,
+        })
+    }
+
+    /// The stream header
+    pub fn header(&self) -> &HeaderV2 {
+        &self.header
+    }
+
+    /// Number of data frames and blocks, in TOC order
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Latest record of every cell written with
+    /// [`ContainerWriterV2::write_cell`], in index order
+    ///
+    /// Superseded and deleted blocks are skipped.
+    pub fn cells(&self) -> Result<Vec<CellRecord>> {
+        let latest: HashMap<Index64, usize> = (0..self.frame_count())
+            .filter_map(|i| Some((self.frame_key(i)?, i)))
+            .collect();
+        let mut cells = BTreeMap::new();
+        for i in 0..self.frame_count() {
+            if self.frame_key(i).is_some_and(|key| latest[&key] != i) {
+                continue;
+            }
+            if let Some(records) = decode_cell_records(&self.frame_data(i)?)? {
+                cells.extend(records);
+            }
+        }
+        Ok(cells.into_iter().collect())
+    }
+
+    /// Block key of frame `i`, or `None` for a plain frame
+    pub fn frame_key(&self, i: usize) -> Option<Index64> {
+        self.frames.get(i).and_then(|frame| frame.key)
+    }
+
+    /// Decompressed payload length of frame `i`
+    pub fn frame_len(&self, i: usize) -> Result<u64> {
+        Ok(self
+            .frame(i)?
+            .parts
+            .iter()
+            .map(|(entry, _)| entry.uncompressed_len as u64)
+            .sum())
+    }
+
+    /// Decompressed payload of frame `i`
+    pub fn read_frame(&self, i: usize) -> Result<Vec<u8>> {
+        let mut out = Vec::with_capacity(self.frame_len(i)? as usize);
+        self.copy_frame(i, &mut out)?;
+        Ok(out)
+    }
+
+    /// Decompress frame `i` into `out` one stored piece at a time,
+    /// returning its length
+    pub fn copy_frame<W: Write>(&self, i: usize, out: &mut W) -> Result<u64> {
+        let mut total = 0;
+        for (entry, data) in &self.frame(i)?.parts {
+            let payload = self.payload(entry, data.clone())?;
+            out.write_all(&payload)?;
+            total += payload.len() as u64;
+        }
+        Ok(total)
+    }
+
+    /// Payload of frame `i`, borrowed from the container when it is stored
+    /// uncompressed in one piece and decompressed otherwise
+    pub fn frame_data(&self, i: usize) -> Result<Cow<'_, [u8]>> {
+        match self.frame(i)?.parts.as_slice() {
+            [(entry, data)] => self.payload(entry, data.clone()),
+            _ => self.read_frame(i).map(Cow::Owned),
+        }
+    }
+
+    /// One stored piece, checking its CRC first if open did not
+    fn payload(&self, entry: &TocEntry, data: Range<usize>) -> Result<Cow<'_, [u8]>> {
+        if !self.crcs_checked {
+            check_crc(&self.bytes, entry, data.clone())?;
+        }
+        if entry.codec == CODEC_NONE && entry.uncompressed_len as usize == data.len() {
+            return Ok(Cow::Borrowed(&self.bytes[data]));
+        }
+        decompress(&self.bytes, entry, data, self.dictionary_codec.as_deref()).map(Cow::Owned)
+    }
+
+    fn frame(&self, i: usize) -> Result<&LogicalFrame> {
+        self.frames
+            .get(i)
+            .ok_or_else(|| Error::OutOfRange(format!("frame {} of {}", i, self.frames.len())))
+    }
+
+    /// The zstd dictionary stored with the container, if any
+    ///
+    /// Frames compressed with it need the `zstd` feature to read.
+    pub fn dictionary(&self) -> Option<&[u8]> {
+        self.dictionary.clone().map(|range| &self.bytes[range])
+    }
+
+    /// Frames registered from the container's registry section, parents
+    /// first
+    pub fn stored_frames(&self) -> &[FrameId] {
+        &self.stored_frames
+    }
+
+    /// Metadata from the latest metadata section, by key
+    pub fn metadata(&self) -> &BTreeMap<String, MetadataValue> {
+        &self.metadata
+    }
+
+    /// Value of metadata entry `key`
+    pub fn metadata_value(&self, key: &str) -> Option<&MetadataValue> {
+        self.metadata.get(key)
+    }
+
+    /// Checks every frame of the container, see [`verify`]
+    ///
+    /// Useful with [`ContainerReaderMmap`], which checks frames only as
+    /// they are read.
+    pub fn verify(&self) -> Result<VerifyReport> {
+        verify_bytes(&self.bytes)
+    }
+
+    /// The block index, if the container has one
+    pub fn block_index(&self) -> Result<Option<BlockIndex>> {
+        if !self.header.has_block_index() {
+            return Ok(None);
+        }
+        let section = usize::try_from(self.header.block_index_offset)
+            .ok()
+            .and_then(|offset| self.bytes.get(offset..))
+            .ok_or_else(|| Error::InvalidFormat("block index outside the container".to_string()))?;
+        BlockIndex::decode(section).map(Some)
+    }
+}
+
+/// Where one stored piece of a block sits in a container
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockIndexEntry {
+    /// Block key
+    pub key: Index64,
+    /// Byte offset of the stored frame
+    pub offset: u64,
+    /// Stored length of the frame: header, key, payload and hash
+    pub len: u32,
+}
+
+impl BlockIndexEntry {
+    /// LOD of the block key
+    pub fn lod(&self) -> u8 {
+        self.key.lod()
+    }
+}
+
+/// Index of the latest write of every block in a container, sorted by key
+///
+/// [`ContainerWriterV2::finish`] and [`compact`] store the index in a
+/// section the header points to, so a reader can go from the header to the
+/// blocks of a key range with two seeks and no scan. A block written with
+/// [`ContainerWriterV2::write_block_stream`] has one entry per stored piece,
+/// in order.
+///
+/// # Example
+///
+/// ```
+/// use octaindex3d::container_v2::{BlockIndex, ContainerWriterV2, StreamConfig};
+/// use octaindex3d::Index64;
+/// use std::io::Cursor;
+///
+/// # fn main() -> octaindex3d::Result<()> {
+/// let mut file = Cursor::new(Vec::new());
+/// let mut writer = ContainerWriterV2::new(&mut file, StreamConfig::default())?;
+/// for x in 0..100 {
+///     writer.write_block(Index64::new(0, 0, 5, x, 0, 0)?, &[x as u8; 64])?;
+/// }
+/// writer.finish()?;
+///
+/// let index = BlockIndex::read(&mut file)?.expect("written by finish");
+/// let near = Index64::new(0, 0, 5, 10, 0, 0)?.morton();
+/// let far = Index64::new(0, 0, 5, 13, 0, 0)?.morton();
+/// assert_eq!(index.morton_range(0, 0, 5, near, far).len(), 4);
+///
+/// let key = Index64::new(0, 0, 5, 42, 0, 0)?;
+/// assert_eq!(index.read_block(&mut file, key)?, Some(vec![42; 64]));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlockIndex {
+    entries: Vec<BlockIndexEntry>,
+}
+
+impl BlockIndex {
+    /// Reads the index of the container in `source` through its header,
+    /// or `None` if it has none
+    pub fn read<R: Read + Seek>(source: &mut R) -> Result<Option<Self>> {
+        use std::io::SeekFrom;
+
+        let mut header = [0u8; 32];
+        source.seek(SeekFrom::Start(0))?;
+        source.read_exact(&mut header)?;
+        let header = HeaderV2::from_bytes(&header)?;
+        if !header.has_block_index() {
+            return Ok(None);
+        }
+        let end = source.seek(SeekFrom::End(0))?;
+        let mut section = vec![0u8; 8];
+        source.seek(SeekFrom::Start(header.block_index_offset))?;
+        source.read_exact(&mut section)?;
+        let count = u32::from_be_bytes(section[4..8].try_into().unwrap()) as u64;
+        // A corrupt count must not size the buffer past the file
+        let len = 8 + count * BLOCK_INDEX_ENTRY_LEN as u64 + 4;
+        if header.block_index_offset.saturating_add(len) > end {
+            return Err(Error::InvalidFormat("block index is truncated".to_string()));
+        }
+        section.resize(len as usize, 0);
+        source.read_exact(&mut section[8..])?;
+        Self::decode(&section).map(Some)
+    }
+
+    pub(crate) fn decode(section: &[u8]) -> Result<Self> {
+        let invalid = |msg: &str| Error::InvalidFormat(format!("block index {}", msg));
+        if section.get(..4) != Some(BLOCK_INDEX_TAG) || section.len() < 8 {
+            return Err(invalid("tag missing"));
+        }
+        let count = u32::from_be_bytes(section[4..8].try_into().unwrap()) as usize;
+        let end = 8 + count * BLOCK_INDEX_ENTRY_LEN;
+        let stored = section
+            .get(end..end + 4)
+            .ok_or_else(|| invalid("is truncated"))?;
+        let expected = u32::from_be_bytes(stored.try_into().unwrap());
+        let actual = crc32fast::hash(&section[..end]);
+        if actual != expected {
+            return Err(Error::CrcMismatch { expected, actual });
+        }
+        let entries = section[8..end]
+            .chunks_exact(BLOCK_INDEX_ENTRY_LEN)
+            .map(|raw| {
+                Ok(BlockIndexEntry {
+                    key: Index64::from_value(u64::from_be_bytes(raw[0..8].try_into().unwrap()))?,
+                    offset: u64::from_be_bytes(raw[8..16].try_into().unwrap()),
+                    len: u32::from_be_bytes(raw[16..20].try_into().unwrap()),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if entries.windows(2).any(|w| w[0].key > w[1].key) {
+            return Err(invalid("is not sorted"));
+        }
+        Ok(Self { entries })
+    }
+
+    /// All entries, sorted by key
+    pub fn entries(&self) -> &[BlockIndexEntry] {
+        &self.entries
+    }
+
+    /// Stored pieces of block `key`, empty if it was never written
+    pub fn get(&self, key: Index64) -> &[BlockIndexEntry] {
+        let start = self.entries.partition_point(|e| e.key < key);
+        let end = self.entries.partition_point(|e| e.key <= key);
+        &self.entries[start..end]
+    }
+
+    /// Entries of the blocks in one frame, tier and LOD whose keys have a
+    /// Morton code in `lo..=hi`
+    pub fn morton_range(
+        &self,
+        frame: FrameId,
+        tier: u8,
+        lod: u8,
+        lo: u64,
+        hi: u64,
+    ) -> &[BlockIndexEntry] {
+        // Keys sort by tier, frame, LOD and then Morton code
+        let order = |e: &BlockIndexEntry| {
+            (
+                e.key.scale_tier(),
+                e.key.frame_id(),
+                e.key.lod(),
+                e.key.morton(),
+            )
+        };
+        let start = self
+            .entries
+            .partition_point(|e| order(e) < (tier, frame, lod, lo));
+        let end = self
+            .entries
+            .partition_point(|e| order(e) <= (tier, frame, lod, hi));
+        &self.entries[start..end.max(start)]
+    }
+
+    /// Reads and decompresses block `key` from the container in `source`,
+    /// seeking straight to its pieces
+    ///
+    /// Blocks compressed with a container dictionary need
+    /// [`ContainerReaderV2`].
+    pub fn read_block<R: Read + Seek>(
+        &self,
+        source: &mut R,
+        key: Index64,
+    ) -> Result<Option<Vec<u8>>> {
+        let pieces = self.get(key);
+        if pieces.is_empty() {
+            return Ok(None);
+        }
+        let mut out = Vec::new();
+        for piece in pieces {
+            let mut stored = vec![0u8; piece.len as usize];
+            source.seek(std::io::SeekFrom::Start(piece.offset))?;
+            source.read_exact(&mut stored)?;
+            out.extend_from_slice(&decode_indexed_frame(&stored, key, None)?);
+        }
+        Ok(Some(out))
+    }
+}
+
+/// Metadata section: tag, entry count, then per entry the key, type tag
+/// and value, lengths as varints and numbers little-endian
+fn encode_metadata(metadata: &BTreeMap<String, MetadataValue>) -> Result<Vec<u8>> {
+    let mut section = METADATA_TAG.to_vec();
+    write_varint(&mut section, metadata.len() as u64);
+    for (key, value) in metadata {
+        write_varint(&mut section, key.len() as u64);
+        section.extend_from_slice(key.as_bytes());
+        section.push(value.tag());
+        let bytes: Cow<[u8]> = match value {
+            MetadataValue::String(text) => Cow::Borrowed(text.as_bytes()),
+            MetadataValue::Int(number) => Cow::Owned(number.to_le_bytes().to_vec()),
+            MetadataValue::Float(number) => Cow::Owned(number.to_le_bytes().to_vec()),
+            MetadataValue::Bytes(bytes) => Cow::Borrowed(bytes),
+        };
+        write_varint(&mut section, bytes.len() as u64);
+        section.extend_from_slice(&bytes);
+    }
+    if section.len() > u32::MAX as usize {
+        return Err(Error::InvalidFormat("metadata exceeds 4 GiB".to_string()));
+    }
+    Ok(section)
+}
+
+fn decode_metadata(section: &[u8]) -> Result<BTreeMap<String, MetadataValue>> {
+    let invalid = |msg: &str| Error::InvalidFormat(format!("metadata section {}", msg));
+    let body = section
+        .strip_prefix(METADATA_TAG.as_slice())
+        .ok_or_else(|| invalid("has no tag"))?;
+    let mut at = 0;
+    let field = |at: &mut usize| -> Result<&[u8]> {
+        let len = read_varint(body, at)? as usize;
+        let end = at.checked_add(len).ok_or_else(|| invalid("is truncated"))?;
+        let bytes = body.get(*at..end).ok_or_else(|| invalid("is truncated"))?;
+        *at = end;
+        Ok(bytes)
+    };
+    let count = read_varint(body, &mut at)?;
+    let mut metadata = BTreeMap::new();
+    for _ in 0..count {
+        let key = String::from_utf8(field(&mut at)?.to_vec())
+            .map_err(|_| invalid("has a key that is not UTF-8"))?;
+        let tag = *body.get(at).ok_or_else(|| invalid("is truncated"))?;
+        at += 1;
+        let bytes = field(&mut at)?;
+        let number = || -> Result<[u8; 8]> {
+            bytes
+                .try_into()
+                .map_err(|_| invalid("has a number that is not 8 bytes"))
+        };
+        let value = match tag {
+            0 => MetadataValue::String(
+                String::from_utf8(bytes.to_vec())
+                    .map_err(|_| invalid("has a string that is not UTF-8"))?,
+            ),
+            1 => MetadataValue::Int(i64::from_le_bytes(number()?)),
+            2 => MetadataValue::Float(f64::from_le_bytes(number()?)),
+            3 => MetadataValue::Bytes(bytes.to_vec()),
+            _ => return Err(invalid(&format!("has unknown value type {}", tag))),
+        };
+        metadata.insert(key, value);
+    }
+    Ok(metadata)
+}
+
+/// Block of cell records: tag, count, the cell indices as zigzag varint
+/// deltas, the record lengths as varints and then the records
+///
+/// Columns keep like bytes together, and the deltas of a sorted bucket are
+/// mostly 1, so bucketed blocks compress to little more than their records.
+fn encode_cell_records(cells: impl IntoIterator<Item = CellRecord>) -> Vec<u8> {
+    let cells: Vec<CellRecord> = cells.into_iter().collect();
+    let mut payload = CELL_RECORDS_TAG.to_vec();
+    payload.extend_from_slice(&(cells.len() as u32).to_le_bytes());
+    let mut prev = 0u64;
+    for (idx, _) in &cells {
+        let delta = idx.raw().wrapping_sub(prev) as i64;
+        write_varint(&mut payload, ((delta << 1) ^ (delta >> 63)) as u64);
+        prev = idx.raw();
+    }
+    for (_, record) in &cells {
+        write_varint(&mut payload, record.len() as u64);
+    }
+    for (_, record) in &cells {
+        payload.extend_from_slice(record);
+    }
+    payload
+}
+
+/// Cell records of a block, or `None` if it holds something else
+pub fn decode_cell_records(payload: &[u8]) -> Result<Option<Vec<CellRecord>>> {
+    let Some(body) = payload.strip_prefix(CELL_RECORDS_TAG.as_slice()) else {
+        return Ok(None);
+    };
+    let invalid = || Error::InvalidFormat("cell record block is truncated".to_string());
+    let count = u32::from_le_bytes(body.get(..4).ok_or_else(invalid)?.try_into().unwrap());
+    // Every cell takes at least two bytes of varints
+    let count = count as usize;
+    if count > body.len() / 2 {
+        return Err(invalid());
+    }
+    let mut at = 4;
+    let mut prev = 0u64;
+    let mut indices = Vec::with_capacity(count);
+    for _ in 0..count {
+        let zigzag = read_varint(body, &mut at)?;
+        prev = prev.wrapping_add(((zigzag >> 1) as i64 ^ -((zigzag & 1) as i64)) as u64);
+        indices.push(Index64::from_value(prev)?);
+    }
+    let mut lens = Vec::with_capacity(count);
+    for _ in 0..count {
+        lens.push(read_varint(body, &mut at)? as usize);
+    }
+    let mut cells = Vec::with_capacity(count);
+    for (idx, len) in indices.into_iter().zip(lens) {
+        let end = at.checked_add(len).ok_or_else(invalid)?;
+        cells.push((idx, body.get(at..end).ok_or_else(invalid)?.to_vec()));
+        at = end;
+    }
+    Ok(Some(cells))
+}
+
+/// Payload of a stored keyed frame found through the block index
+pub(crate) fn decode_indexed_frame(
+    stored: &[u8],
+    key: Index64,
+    dictionary: Option<&dyn Compression>,
+) -> Result<Vec<u8>> {
+    let invalid = |msg: &str| Error::InvalidFormat(format!("indexed block {}", msg));
+    if stored.len() < 24 || stored[3] & FRAME_FLAG_KEYED == 0 {
+        return Err(invalid("is not a keyed frame"));
+    }
+    if u64::from_be_bytes(stored[16..24].try_into().unwrap()) != key.raw() {
+        return Err(invalid("has another key"));
+    }
+    let uncompressed_len = u32::from_be_bytes(stored[4..8].try_into().unwrap()) as usize;
+    let compressed_len = u32::from_be_bytes(stored[8..12].try_into().unwrap()) as usize;
+    let data = stored
+        .get(24..24 + compressed_len)
+        .ok_or_else(|| invalid("is truncated"))?;
+    let expected = u32::from_be_bytes(stored[12..16].try_into().unwrap());
+    let actual = crc32fast::hash(data);
+    if actual != expected {
+        return Err(Error::CrcMismatch { expected, actual });
+    }
+    let payload = match dictionary {
+        Some(codec) if codec.codec_id() == stored[0] => codec.decompress(data)?,
+        _ => get_compression(stored[0])?.decompress(data)?,
+    };
+    if payload.len() != uncompressed_len {
+        return Err(invalid("decompressed to the wrong length"));
+    }
+    Ok(payload)
+}
+
+/// Block index section: tag, entry count, entries and a CRC of the rest
+fn encode_block_index<'a>(entries: impl Iterator<Item = &'a BlockIndexEntry>) -> Vec<u8> {
+    let mut section = BLOCK_INDEX_TAG.to_vec();
+    section.extend_from_slice(&[0; 4]);
+    let mut count = 0u32;
+    for entry in entries {
+        section.extend_from_slice(&entry.key.raw().to_be_bytes());
+        section.extend_from_slice(&entry.offset.to_be_bytes());
+        section.extend_from_slice(&entry.len.to_be_bytes());
+        count += 1;
+    }
+    section[4..8].copy_from_slice(&count.to_be_bytes());
+    let crc = crc32fast::hash(&section);
+    section.extend_from_slice(&crc.to_be_bytes());
+    section
+}
+
+/// Checks that a block index offset fits the header's 48 bits
+fn block_index_offset(offset: u64) -> Result<u64> {
+    if offset >> 48 != 0 {
+        return Err(Error::InvalidFormat(
+            "block index offset exceeds 48 bits".to_string(),
+        ));
+    }
+    Ok(offset)
+}
+
+fn decompress(
+    bytes: &[u8],
+    entry: &TocEntry,
+    data: Range<usize>,
+    dictionary: Option<&dyn Compression>,
+) -> Result<Vec<u8>> {
+    let payload = match dictionary {
+        Some(codec) if codec.codec_id() == entry.codec => codec.decompress(&bytes[data])?,
+        _ => get_compression(entry.codec)?.decompress(&bytes[data])?,
+    };
+    if payload.len() != entry.uncompressed_len as usize {
+        return Err(Error::InvalidFormat(format!(
+            "frame decompressed to {} bytes, expected {}",
+            payload.len(),
+            entry.uncompressed_len
+        )));
+    }
+    Ok(payload)
+}
+
+/// Compares a stored piece against the CRC in its frame header
+fn check_crc(bytes: &[u8], entry: &TocEntry, data: Range<usize>) -> Result<()> {
+    let at = entry.offset as usize + 12;
+    let expected = u32::from_be_bytes(bytes[at..at + 4].try_into().unwrap());
+    let actual = crc32fast::hash(&bytes[data]);
+    if actual != expected {
+        return Err(Error::CrcMismatch { expected, actual });
+    }
+    Ok(())
+}
+
+/// Bytes of a container held by a reader
+enum Storage {
+    Owned(Vec<u8>),
+    #[cfg(feature = "mmap")]
+    Mapped(memmap2::Mmap),
+}
+
+impl std::ops::Deref for Storage {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Storage::Owned(bytes) => bytes,
+            #[cfg(feature = "mmap")]
+            Storage::Mapped(map) => map,
+        }
+    }
+}
+
+/// Memory-mapped container reader
+///
+/// Opening maps the file and reads only the header, footer, TOC and frame
+/// headers, so the open cost does not grow with the payload. Each frame's
+/// CRC is checked when the frame is read. Frames stored uncompressed come
+/// back from [`Self::frame_data`] as slices of the mapping; compressed
+/// frames are decompressed on each read. [`Self::reader`] gives the full
+/// [`ContainerReaderV2`] API over the same mapping.
+///
+/// The file must not be truncated or rewritten in place while it is mapped.
+/// [`compact`] replaces a container by renaming a new file over it, which
+/// leaves existing mappings of the old file intact.
+///
+/// # Example
+///
+/// ```
+/// use octaindex3d::compression::NoCompression;
+/// use octaindex3d::container_v2::{ContainerReaderMmap, ContainerWriterV2, StreamConfig};
+/// use std::borrow::Cow;
+/// use std::fs::File;
+///
+/// # fn main() -> octaindex3d::Result<()> {
+/// let path = std::env::temp_dir().join(format!("mmap-doc-{}.oct2", std::process::id()));
+/// let mut writer = ContainerWriterV2::new(File::create(&path)?, StreamConfig::default())?
+///     .with_compression(Box::new(NoCompression))?;
+/// writer.write_frame(b"raw cells")?;
+/// writer.finish()?;
+///
+/// let map = ContainerReaderMmap::open(&path)?;
+/// assert!(matches!(map.frame_data(0)?, Cow::Borrowed(b"raw cells")));
+/// assert_eq!(map.reader().frame_count(), 1);
+/// # drop(map);
+/// # std::fs::remove_file(&path)?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "mmap")]
+pub struct ContainerReaderMmap {
+    reader: ContainerReaderV2,
+}
+
+#[cfg(feature = "mmap")]
+impl ContainerReaderMmap {
+    /// Maps the container at `path` and registers its stored frames
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = File::open(path)?;
+        // SAFETY: the mapping is read-only and the type documents that the
+        // file must not be modified in place while mapped
+        let map = unsafe { memmap2::Mmap::map(&file)? };
+        Ok(Self {
+            reader: ContainerReaderV2::from_storage(Storage::Mapped(map), false)?,
+        })
+    }
+
+    /// The reader over the mapping
+    pub fn reader(&self) -> &ContainerReaderV2 {
+        &self.reader
+    }
+
+    /// Payload of frame `i`, see [`ContainerReaderV2::frame_data`]
+    pub fn frame_data(&self, i: usize) -> Result<Cow<'_, [u8]>> {
+        self.reader.frame_data(i)
+    }
+
+    /// The whole mapped file
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.reader.bytes
+    }
+}
+
+/// Summary of a [`compact`] run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompactionStats {
+    /// Frames listed in the container's latest TOC
+    pub frames_before: usize,
+    /// Frames kept after dropping superseded blocks
+    pub frames_after: usize,
+    /// File size before compaction, in bytes
+    pub bytes_before: u64,
+    /// File size after compaction, in bytes
+    pub bytes_after: u64,
+}
+
+impl CompactionStats {
+    /// Bytes freed by compaction
+    pub fn bytes_reclaimed(&self) -> u64 {
+        self.bytes_before.saturating_sub(self.bytes_after)
+    }
+}
+
+/// One frame located through the TOC, with its raw on-disk bytes
+struct StoredFrame<'a> {
+    entry: TocEntry,
+    key: Option<Index64>,
+    flags: u8,
+    bytes: &'a [u8],
+    /// Compressed payload, as a range of the container
+    data: Range<usize>,
+}
+
+/// Rewrites the container at `path` in place, reclaiming space left by
+/// long append sessions.
+///
+/// Compaction:
+/// - drops the intermediate checkpoints (every checkpoint repeats the whole
+///   TOC) and anything written after the last one,
+/// - drops keyed blocks superseded by a later [`ContainerWriterV2::write_block`]
+///   with the same key, keeping the pieces of streamed frames together,
+/// - drops blocks deleted by [`ContainerWriterV2::delete_block`] and their
+///   tombstones,
+/// - writes unkeyed frames first in their original order, then blocks in
+///   key order (Morton order within each frame, tier and LOD),
+/// - rebuilds a single TOC and footer.
+///
+/// Frames are copied without recompression, but their CRCs are verified.
+/// The new file is written beside the original and renamed over it, so an
+/// interrupted compaction leaves the original intact.
+///
+/// # Example
+///
+/// ```
+/// use octaindex3d::container_v2::{compact, ContainerWriterV2, StreamConfig};
+/// use octaindex3d::Index64;
+/// use std::fs::File;
+///
+/// # fn main() -> octaindex3d::Result<()> {
+/// let path = std::env::temp_dir().join(format!("compact-doc-{}.oct2", std::process::id()));
+/// let chunk = Index64::new(0, 0, 4, 2, 2, 2)?;
+///
+/// let mut writer = ContainerWriterV2::new(File::create(&path)?, StreamConfig::default())?;
+/// writer.write_block(chunk, b"first version")?;
+/// writer.write_block(chunk, b"second version")?;
+/// writer.finish()?;
+///
+/// let stats = compact(&path)?;
+/// assert_eq!((stats.frames_before, stats.frames_after), (2, 1));
+/// assert!(stats.bytes_reclaimed() > 0);
+/// # std::fs::remove_file(&path)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn compact<P: AsRef<Path>>(path: P) -> Result<CompactionStats> {
+    let path = path.as_ref();
+    let bytes = fs::read(path)?;
+    let (header, frames) = read_frames(&bytes, true)?;
+    let frames_before = frames.len();
+
+    // Streamed payloads move as a whole; a run cut short is dropped
+    let mut runs: Vec<Range<usize>> = Vec::new();
+    let mut start = 0;
+    for (i, frame) in frames.iter().enumerate() {
+        if frame.flags & FRAME_FLAG_CONTINUED == 0 {
+            runs.push(start..i + 1);
+            start = i + 1;
+        }
+    }
+
+    // Latest write of each key wins
+    let mut latest: HashMap<Index64, usize> = HashMap::new();
+    let mut kept = Vec::with_capacity(runs.len());
+    for (r, run) in runs.iter().enumerate() {
+        let frame = &frames[run.start];
+        match frame.key {
+            Some(key) => {
+                let slot = latest.entry(key).or_insert(r);
+                if frames[runs[*slot].start].entry.seq < frame.entry.seq {
+                    *slot = r;
+                }
+            }
+            None => kept.push(r),
+        }
+    }
+    kept.sort_by_key(|&r| frames[runs[r].start].entry.seq);
+    // Deleted blocks go with their tombstones
+    latest.retain(|_, r| frames[runs[*r].start].flags & FRAME_FLAG_TOMBSTONE == 0);
+    let mut blocks: Vec<(Index64, usize)> = latest.into_iter().collect();
+    blocks.sort_unstable();
+    kept.extend(blocks.into_iter().map(|(_, r)| r));
+
+    let kept: Vec<&StoredFrame> = kept
+        .iter()
+        .flat_map(|&r| &frames[runs[r].clone()])
+        .collect();
+    let out = rebuild(&bytes, header, &kept)?;
+    replace_file(path, &out)?;
+
+    Ok(CompactionStats {
+        frames_before,
+        frames_after: kept.len(),
+        bytes_before: bytes.len() as u64,
+        bytes_after: out.len() as u64,
+    })
+}
+
+/// A container holding `frames` of `bytes` in the order given, with a new
+/// block index, TOC and footer
+///
+/// The header and the dictionary section are kept as they are.
+fn rebuild(bytes: &[u8], mut header: HeaderV2, frames: &[&StoredFrame]) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(bytes.len());
+    out.extend_from_slice(&bytes[..header.first_frame_offset as usize]);
+    let mut toc = Vec::with_capacity(frames.len());
+    let mut blocks: HashMap<Index64, Vec<BlockIndexEntry>> = HashMap::new();
+    let mut run_open = false;
+    for frame in frames {
+        if let Some(key) = frame.key {
+            let piece = BlockIndexEntry {
+                key,
+                offset: out.len() as u64,
+                len: frame.bytes.len() as u32,
+            };
+            if frame.flags & FRAME_FLAG_TOMBSTONE != 0 {
+                blocks.remove(&key);
+            } else if run_open {
+                blocks.entry(key).or_default().push(piece);
+            } else {
+                blocks.insert(key, vec![piece]);
+            }
+        }
+        run_open = frame.flags & FRAME_FLAG_CONTINUED != 0;
+        toc.push(TocEntry {
+            offset: out.len() as u64,
+            seq: toc.len() as u64,
+            ..frame.entry.clone()
+        });
+        out.extend_from_slice(frame.bytes);
+    }
+
+    header.flags &= !HEADER_FLAG_BLOCK_INDEX;
+    header.flags |= HEADER_FLAG_DIGEST;
+    header.block_index_offset = 0;
+    if !blocks.is_empty() {
+        let mut blocks: Vec<Vec<BlockIndexEntry>> = blocks.into_values().collect();
+        blocks.sort_unstable_by_key(|pieces| pieces[0].key);
+        header.flags |= HEADER_FLAG_BLOCK_INDEX;
+        header.block_index_offset = block_index_offset(out.len() as u64)?;
+        out.extend_from_slice(&encode_block_index(blocks.iter().flatten()));
+    }
+    out[..32].copy_from_slice(&header.to_bytes());
+    let toc_offset = out.len() as u64;
+    for entry in &toc {
+        out.extend_from_slice(&entry.to_bytes());
+    }
+    let footer = Footer {
+        toc_offset,
+        toc_len: (toc.len() * 32) as u64,
+        entry_count: toc.len() as u64,
+        flags_copy: header.flags as u64 | (crc32fast::hash(&out[32..]) as u64) << 32,
+    };
+    out.extend_from_slice(&footer.to_bytes());
+    Ok(out)
+}
+
+/// Write `out` beside `path` and rename it over, so an interruption leaves
+/// the original intact
+fn replace_file(path: &Path, out: &[u8]) -> Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".compact");
+    {
+        let mut file = File::create(&tmp)?;
+        file.write_all(out)?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Contents of the dictionary section, as a range of the container
+fn dictionary_section(bytes: &[u8], header: &HeaderV2) -> Result<Option<Range<usize>>> {
+    if !header.has_dictionary() {
+        return Ok(None);
+    }
+    let end = header.first_frame_offset as usize;
+    let len = bytes
+        .get(32..36)
+        .map(|b| u32::from_be_bytes(b.try_into().unwrap()) as usize);
+    if len.map(|len| 36 + len) != Some(end) {
+        return Err(Error::InvalidFormat(
+            "dictionary section does not end at the first frame".to_string(),
+        ));
+    }
+    Ok(Some(36..end))
+}
+
+/// Locates every frame listed in the footer's TOC, checking payload CRCs
+/// if `check_crcs`
+fn read_frames(bytes: &[u8], check_crcs: bool) -> Result<(HeaderV2, Vec<StoredFrame<'_>>)> {
+    let invalid = |msg: &str| Error::InvalidFormat(msg.to_string());
+    if bytes.len() < 64 {
+        return Err(invalid("container is too short"));
+    }
+    let header = HeaderV2::from_bytes(bytes[..32].try_into().expect("32 bytes"))?;
+    if header.format_version != FORMAT_VERSION_V2 {
+        return Err(Error::InvalidFormat(format!(
+            "Unsupported format version: {}",
+            header.format_version
+        )));
+    }
+    let footer_at = bytes.len() - 32;
+    let footer = Footer::from_bytes(bytes[footer_at..].try_into().expect("32 bytes"));
+    let toc_end = footer.toc_offset.checked_add(footer.toc_len);
+    if footer.entry_count.checked_mul(32) != Some(footer.toc_len)
+        || toc_end != Some(footer_at as u64)
+        || footer.toc_offset < header.first_frame_offset
+    {
+        return Err(invalid("container does not end with a complete checkpoint"));
+    }
+    if check_crcs && header.has_digest() {
+        let actual = crc32fast::hash(&bytes[32..footer_at]);
+        if actual != footer.digest() {
+            return Err(Error::CrcMismatch {
+                expected: footer.digest(),
+                actual,
+            });
+        }
+    }
+
+    let toc_offset = footer.toc_offset as usize;
+    let frames = bytes[toc_offset..footer_at]
+        .chunks_exact(32)
+        .map(|raw| {
+            let entry = TocEntry::from_bytes(raw.try_into().expect("32 bytes"));
+            let frame = parse_frame(bytes, &header, toc_offset, entry)?;
+            if check_crcs {
+                check_crc(bytes, &frame.entry, frame.data.clone())?;
+            }
+            Ok(frame)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok((header, frames))
+}
+
+/// The frame a TOC entry points to, checked to lie before the TOC at
+/// `toc_offset` but not against its CRC
+fn parse_frame<'a>(
+    bytes: &'a [u8],
+    header: &HeaderV2,
+    toc_offset: usize,
+    entry: TocEntry,
+) -> Result<StoredFrame<'a>> {
+    let invalid = |msg: &str| Error::InvalidFormat(msg.to_string());
+    let hash_len = if header.has_sha256() { 32 } else { 0 };
+    let start = usize::try_from(entry.offset)
+        .ok()
+        .filter(|&o| o >= header.first_frame_offset as usize && o + 16 <= toc_offset)
+        .ok_or_else(|| invalid("TOC entry points outside the frame region"))?;
+    let frame_header = &bytes[start..start + 16];
+    let flags = frame_header[3];
+    let keyed = flags & FRAME_FLAG_KEYED != 0;
+    let compressed_len = u32::from_be_bytes(frame_header[8..12].try_into().unwrap());
+
+    let data_start = start + 16 + if keyed { 8 } else { 0 };
+    let data_end = data_start + compressed_len as usize;
+    let end = data_end + hash_len;
+    if end > toc_offset {
+        return Err(invalid("frame extends past the TOC"));
+    }
+    let key = if keyed {
+        let raw = u64::from_be_bytes(bytes[start + 16..data_start].try_into().unwrap());
+        Some(Index64::from_value(raw)?)
+    } else {
+        None
+    };
+    Ok(StoredFrame {
+        entry,
+        key,
+        flags,
+        bytes: &bytes[start..end],
+        data: data_start..data_end,
+    })
+}
+
+/// A damaged frame found by [`verify`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CorruptFrame {
+    /// Position of the frame in the TOC
+    pub toc_index: usize,
+    /// Byte offset of the frame
+    pub offset: u64,
+    /// Block key, if the frame header was readable and keyed
+    pub key: Option<Index64>,
+    /// What failed
+    pub reason: String,
+}
+
+/// Outcome of [`verify`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VerifyReport {
+    /// Frames listed by the checkpoint used
+    pub frames: usize,
+    /// Frames whose stored bytes fail their checks
+    pub corrupt: Vec<CorruptFrame>,
+    /// Whether the footer digest matched, or `None` for containers
+    /// written without digests
+    pub digest_ok: Option<bool>,
+    /// Bytes after the checkpoint used, nonzero when the end of the file
+    /// was damaged and an earlier checkpoint was found
+    pub trailing_bytes: u64,
+}
+
+impl VerifyReport {
+    /// Whether nothing is damaged
+    pub fn is_intact(&self) -> bool {
+        self.corrupt.is_empty() && self.digest_ok != Some(false) && self.trailing_bytes == 0
+    }
+}
+
+/// Checks every frame of the container at `path`, reporting the damaged
+/// ones instead of failing on the first
+///
+/// Each frame is checked against the CRC32 in its header, decompressed
+/// and, with SHA-256 enabled, hashed; the footer's CRC32 digest covers the bytes no frame check
+/// does, such as the TOC. When the end of the file is damaged the latest
+/// intact checkpoint before it is used. Fails only without a readable
+/// header or any intact checkpoint.
+///
+/// # Example
+///
+/// ```
+/// use octaindex3d::container_v2::{recover, verify, ContainerWriterV2, StreamConfig};
+/// use octaindex3d::Index64;
+/// use std::fs::{self, File};
+///
+/// # fn main() -> octaindex3d::Result<()> {
+/// let path = std::env::temp_dir().join(format!("verify-doc-{}.oct2", std::process::id()));
+/// let mut writer = ContainerWriterV2::new(File::create(&path)?, StreamConfig::default())?;
+/// for x in 0..4 {
+///     writer.write_block(Index64::new(0, 0, 4, x, 0, 0)?, &[x as u8; 256])?;
+/// }
+/// writer.finish()?;
+/// assert!(verify(&path)?.is_intact());
+///
+/// // A bit flips on the SD card
+/// let mut bytes = fs::read(&path)?;
+/// bytes[100] ^= 0x10;
+/// fs::write(&path, &bytes)?;
+/// let report = verify(&path)?;
+/// assert_eq!(report.corrupt.len(), 1);
+///
+/// // Salvage the other three blocks
+/// let salvaged = path.with_extension("recovered");
+/// recover(&path, &salvaged)?;
+/// assert!(verify(&salvaged)?.is_intact());
+/// # fs::remove_file(&path)?;
+/// # fs::remove_file(&salvaged)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn verify<P: AsRef<Path>>(path: P) -> Result<VerifyReport> {
+    verify_bytes(&fs::read(path)?)
+}
+
+/// [`verify`] for a container held in memory
+pub fn verify_bytes(bytes: &[u8]) -> Result<VerifyReport> {
+    Ok(scan(bytes)?.1)
+}
+
+/// Writes the intact frames of the container at `path` to a new container
+/// at `out`, returning what [`verify`] found
+///
+/// A streamed block with any damaged piece is dropped whole, as is one cut
+/// short by the end of the checkpoint used.
+pub fn recover<P: AsRef<Path>, Q: AsRef<Path>>(path: P, out: Q) -> Result<VerifyReport> {
+    let bytes = fs::read(path)?;
+    let (header, report, frames) = scan(&bytes)?;
+    let mut kept = Vec::with_capacity(frames.len());
+    let mut run: Vec<&StoredFrame> = Vec::new();
+    let mut run_damaged = false;
+    for (flags, frame) in &frames {
+        match frame {
+            Some(frame) => run.push(frame),
+            None => run_damaged = true,
+        }
+        // A frame whose header was unreadable ends its run
+        if flags.map_or(true, |f| f & FRAME_FLAG_CONTINUED == 0) {
+            if !run_damaged {
+                kept.append(&mut run);
+            }
+            run.clear();
+            run_damaged = false;
+        }
+    }
+    let mut file = File::create(out)?;
+    file.write_all(&rebuild(&bytes, header, &kept)?)?;
+    file.sync_all()?;
+    Ok(report)
+}
+
+/// Header, verification report and each frame of the checkpoint used:
+/// its flags if its header was readable, and the frame if it is intact
+#[allow(clippy::type_complexity)]
+fn scan(
+    bytes: &[u8],
+) -> Result<(
+    HeaderV2,
+    VerifyReport,
+    Vec<(Option<u8>, Option<StoredFrame<'_>>)>,
+)> {
+    let header = HeaderV2::from_bytes(
+        bytes
+            .get(..32)
+            .and_then(|b| b.try_into().ok())
+            .ok_or_else(|| Error::InvalidFormat("container is too short".to_string()))?,
+    )?;
+    let (footer_at, footer) = find_checkpoint(bytes, &header)
+        .ok_or_else(|| Error::InvalidFormat("container has no intact checkpoint".to_string()))?;
+    let dictionary = dictionary_section(bytes, &header).ok().flatten();
+    #[cfg(feature = "zstd")]
+    let dictionary_codec = dictionary.map(|range| {
+        Box::new(crate::compression::ZstdDictCompression::new(&bytes[range]))
+            as Box<dyn Compression>
+    });
+    #[cfg(not(feature = "zstd"))]
+    let dictionary_codec: Option<Box<dyn Compression>> = dictionary.and(None);
+
+    let mut report = VerifyReport {
+        frames: footer.entry_count as usize,
+        digest_ok: header
+            .has_digest()
+            .then(|| crc32fast::hash(&bytes[32..footer_at]) == footer.digest()),
+        trailing_bytes: (bytes.len() - footer_at - 32) as u64,
+        ..VerifyReport::default()
+    };
+    let toc_offset = footer.toc_offset as usize;
+    let mut frames = Vec::with_capacity(report.frames);
+    for (toc_index, raw) in bytes[toc_offset..footer_at].chunks_exact(32).enumerate() {
+        let entry = TocEntry::from_bytes(raw.try_into().expect("32 bytes"));
+        let offset = entry.offset;
+        let frame = parse_frame(bytes, &header, toc_offset, entry);
+        let flags = frame.as_ref().ok().map(|f| f.flags);
+        let key = frame.as_ref().ok().and_then(|f| f.key);
+        let checked = frame.and_then(|frame| {
+            check_crc(bytes, &frame.entry, frame.data.clone())?;
+            let payload = decompress(
+                bytes,
+                &frame.entry,
+                frame.data.clone(),
+                dictionary_codec.as_deref(),
+            )?;
+            if header.has_sha256() {
+                let stored = &bytes[frame.data.end..frame.data.end + 32];
+                if Sha256::digest(&payload).as_slice() != stored {
+                    return Err(Error::Sha256Mismatch);
+                }
+            }
+            Ok(frame)
+        });
+        match checked {
+            Ok(frame) => frames.push((flags, Some(frame))),
+            Err(error) => {
+                report.corrupt.push(CorruptFrame {
+                    toc_index,
+                    offset,
+                    key,
+                    reason: error.to_string(),
+                });
+                frames.push((flags, None));
+            }
+        }
+    }
+    Ok((header, report, frames))
+}
+
+/// Offset and contents of the latest checkpoint footer that fits the
+/// container: the one at the end, or else the latest earlier one whose TOC
+/// lies in the frame region and whose digest, if any, matches
+///
+/// Earlier candidates are scanned forward with a running digest, so each
+/// byte is hashed once however many offsets look like footers.
+fn find_checkpoint(bytes: &[u8], header: &HeaderV2) -> Option<(usize, Footer)> {
+    let first = header.first_frame_offset as usize;
+    let shape_fits = |at: usize| -> Option<Footer> {
+        let footer = Footer::from_bytes(bytes.get(at..at + 32)?.try_into().ok()?);
+        let toc = usize::try_from(footer.toc_offset).ok()?;
+        (footer.entry_count.checked_mul(32) == Some(footer.toc_len)
+            && footer.toc_offset.checked_add(footer.toc_len) == Some(at as u64)
+            && toc >= first)
+            .then_some(footer)
+    };
+    let entries_fit = |at: usize, footer: &Footer| {
+        bytes[footer.toc_offset as usize..at]
+            .chunks_exact(32)
+            .all(|raw| {
+                let entry = TocEntry::from_bytes(raw.try_into().expect("32 bytes"));
+                entry.offset >= first as u64 && entry.offset < footer.toc_offset
+            })
+    };
+    let last = bytes.len().checked_sub(32)?;
+    if let Some(footer) = shape_fits(last).filter(|footer| entries_fit(last, footer)) {
+        return Some((last, footer));
+    }
+    let mut hasher = crc32fast::Hasher::new();
+    let mut hashed = 32.min(last);
+    let mut found = None;
+    for at in first..last {
+        let Some(footer) = shape_fits(at) else {
+            continue;
+        };
+        if header.has_digest() {
+            hasher.update(&bytes[hashed..at]);
+            hashed = at;
+            if hasher.clone().finalize() != footer.digest() {
+                continue;
+            }
+        }
+        if entries_fit(at, &footer) {
+            found = Some((at, footer));
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_header_v2_roundtrip() {
+        let header = HeaderV2::new(true);
+        assert!(header.has_sha256());
+
+        let bytes = header.to_bytes();
+        let header2 = HeaderV2::from_bytes(&bytes).unwrap();
+
+        assert_eq!(header.format_version, header2.format_version);
+        assert_eq!(header.flags, header2.flags);
+        assert_eq!(header.stream_id, header2.stream_id);
+
+        let header = HeaderV2 {
+            flags: HEADER_FLAG_BLOCK_INDEX,
+            block_index_offset: 0xABCD_1234_5678,
+            ..header
+        };
+        let header2 = HeaderV2::from_bytes(&header.to_bytes()).unwrap();
+        assert!(header2.has_block_index());
+        assert_eq!(header2.block_index_offset, 0xABCD_1234_5678);
+        assert_eq!(header2.first_frame_offset, 32);
+    }
+
+    #[test]
+    fn test_toc_entry_roundtrip() {
+        let entry = TocEntry {
+            offset: 1000,
+            uncompressed_len: 500,
+            compressed_len: 300,
+            codec: 0,
+            graph: 0,
+            lod: 5,
+            tier: 1,
+            seq: 42,
+        };
+
+        let bytes = entry.to_bytes();
+        let entry2 = TocEntry::from_bytes(&bytes);
+
+        assert_eq!(entry.offset, entry2.offset);
+        assert_eq!(entry.seq, entry2.seq);
+        assert_eq!(entry.lod, entry2.lod);
+    }
+
+    #[test]
+    fn test_container_v2_write() {
+        let mut buffer = Vec::new();
+        let config = StreamConfig::default();
+
+        {
+            let mut writer = ContainerWriterV2::new(Cursor::new(&mut buffer), config).unwrap();
+            writer.write_frame(b"Hello, world!").unwrap();
+            writer.write_frame(b"Frame 2").unwrap();
+            writer.finish().unwrap();
+        }
+
+        // Verify header magic
+        assert_eq!(&buffer[0..8], b"OCTA3D2\0");
+    }
+
+    #[test]
+    fn test_compact_drops_superseded_blocks() {
+        let path =
+            std::env::temp_dir().join(format!("octaindex3d-compact-{}.oct2", std::process::id()));
+        let key = |x| Index64::new(0, 0, 4, x, 0, 0).unwrap();
+        let config = StreamConfig {
+            checkpoint_frames: 2,
+            enable_sha256: true,
+            ..StreamConfig::default()
+        };
+        {
+            let mut writer = ContainerWriterV2::new(File::create(&path).unwrap(), config).unwrap();
+            writer.write_block(key(6), b"six, stale").unwrap();
+            writer.write_frame(b"metadata").unwrap();
+            writer.write_block(key(2), b"two").unwrap();
+            writer.write_block(key(6), b"six").unwrap();
+            writer.finish().unwrap();
+        }
+
+        let stats = compact(&path).unwrap();
+        assert_eq!((stats.frames_before, stats.frames_after), (4, 3));
+        assert!(stats.bytes_reclaimed() > 0);
+
+        let bytes = fs::read(&path).unwrap();
+        let (header, frames) = read_frames(&bytes, true).unwrap();
+        assert!(header.has_sha256());
+        let keys: Vec<_> = frames.iter().map(|f| f.key).collect();
+        assert_eq!(keys, vec![None, Some(key(2)), Some(key(6))]);
+        assert!(frames
+            .iter()
+            .enumerate()
+            .all(|(i, f)| f.entry.seq == i as u64));
+        assert_eq!(frames[1].entry.lod, 4);
+
+        // Compacting again changes nothing
+        let again = compact(&path).unwrap();
+        assert_eq!(again.bytes_before, again.bytes_after);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_frame_registry_round_trip() {
+        use crate::frame::{self, FrameDescriptor, FrameTransform};
+
+        let site = FrameDescriptor::new("V2-SITE", "local", "Site", true, 1.0);
+        let arm = FrameDescriptor::new("V2-ARM", "local", "Arm", true, 0.01).with_parent(
+            130,
+            FrameTransform::IDENTITY.with_translation([1.0, 2.0, 3.0]),
+        );
+        frame::register_frame(130, site).unwrap();
+        frame::register_frame(131, arm.clone()).unwrap();
+
+        let mut buffer = Vec::new();
+        {
+            let config = StreamConfig::default();
+            let mut writer = ContainerWriterV2::new(Cursor::new(&mut buffer), config).unwrap();
+            writer
+                .write_block(Index64::new(131, 0, 4, 1, 1, 1).unwrap(), b"arm")
+                .unwrap();
+            writer
+                .write_block(Index64::new(0, 0, 4, 1, 1, 1).unwrap(), b"ecef")
+                .unwrap();
+            writer.write_frame(b"plain").unwrap();
+            writer.finish().unwrap();
+        }
+
+        // The arm and its parent are stored; ECEF is built in
+        let reader = ContainerReaderV2::from_bytes(buffer.clone()).unwrap();
+        assert_eq!(reader.stored_frames(), &[130, 131]);
+        assert_eq!(reader.frame_count(), 3);
+        assert_eq!(reader.read_frame(2).unwrap(), b"plain");
+        assert_eq!(reader.frame_key(2), None);
+        assert_eq!(*frame::get_frame(131).unwrap(), arm);
+
+        // A record that disagrees with this process's frames is rejected
+        let record = export_frames([131]);
+        let mut changed = record.clone();
+        let at = record.windows(6).position(|w| w == b"V2-ARM").unwrap();
+        changed[at + 5] = b'X';
+        assert_eq!(import_frames(&changed), Err(Error::FrameConflict(131)));
+        assert!(import_frames(&record[..record.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_streamed_blocks() {
+        let path =
+            std::env::temp_dir().join(format!("octaindex3d-stream-{}.oct2", std::process::id()));
+        let key = |x| Index64::new(0, 0, 4, x, 0, 0).unwrap();
+        let payload = |n: usize, seed: u8| -> Vec<u8> {
+            (0..n).map(|i| (i as u8).wrapping_mul(seed)).collect()
+        };
+        let config = StreamConfig {
+            checkpoint_frames: 2,
+            stream_chunk_bytes: 100,
+            ..StreamConfig::default()
+        };
+        {
+            let mut writer = ContainerWriterV2::new(File::create(&path).unwrap(), config).unwrap();
+            let n = writer
+                .write_block_stream(key(1), payload(350, 3).as_slice())
+                .unwrap();
+            assert_eq!(n, 350);
+            writer.write_block(key(2), b"two").unwrap();
+            writer
+                .write_block_stream(key(1), payload(200, 5).as_slice())
+                .unwrap();
+            writer.write_stream(&[][..]).unwrap();
+            writer.finish().unwrap();
+        }
+
+        let reader = ContainerReaderV2::open(&path).unwrap();
+        assert_eq!(reader.frame_count(), 4);
+        assert_eq!(reader.frame_len(0).unwrap(), 350);
+        assert_eq!(reader.read_frame(0).unwrap(), payload(350, 3));
+        assert_eq!(reader.read_frame(2).unwrap(), payload(200, 5));
+        assert_eq!(reader.frame_key(2), Some(key(1)));
+        assert_eq!(reader.read_frame(3).unwrap(), b"");
+
+        // 4 + 1 + 2 + 1 pieces; the superseded stream goes as a whole
+        let stats = compact(&path).unwrap();
+        assert_eq!((stats.frames_before, stats.frames_after), (8, 4));
+        let reader = ContainerReaderV2::open(&path).unwrap();
+        assert_eq!(reader.frame_count(), 3);
+        assert_eq!(reader.frame_key(1), Some(key(1)));
+        assert_eq!(reader.read_frame(1).unwrap(), payload(200, 5));
+        assert_eq!(reader.read_frame(2).unwrap(), b"two");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_dictionary_survives_compaction() {
+        use crate::compression::{train_zstd_dictionary, CODEC_ZSTD_DICT};
+
+        let path =
+            std::env::temp_dir().join(format!("octaindex3d-dict-{}.oct2", std::process::id()));
+        let chunk = |i: u16| -> Vec<u8> {
+            (0..4096u16)
+                .map(|j| if (j + i) % 9 < 6 { 0 } else { (j % 7) as u8 })
+                .collect()
+        };
+        let samples: Vec<_> = (0..300).map(chunk).collect();
+        let dictionary = train_zstd_dictionary(&samples, 4096).unwrap();
+        let key = |x| Index64::new(0, 0, 4, x, 0, 0).unwrap();
+        {
+            let file = File::create(&path).unwrap();
+            let mut writer = ContainerWriterV2::new(file, StreamConfig::default())
+                .unwrap()
+                .with_zstd_dictionary(dictionary.clone(), 3)
+                .unwrap();
+            writer.write_block(key(1), &chunk(1)).unwrap();
+            writer.write_block(key(1), &chunk(2)).unwrap();
+            writer.write_block(key(3), &chunk(3)).unwrap();
+            writer.finish().unwrap();
+        }
+
+        // Too late once frames are written
+        let mut buffer = Vec::new();
+        let mut late =
+            ContainerWriterV2::new(Cursor::new(&mut buffer), StreamConfig::default()).unwrap();
+        late.write_frame(b"first").unwrap();
+        assert!(late.with_zstd_dictionary(dictionary.clone(), 3).is_err());
+
+        let stats = compact(&path).unwrap();
+        assert_eq!((stats.frames_before, stats.frames_after), (3, 2));
+        let reader = ContainerReaderV2::open(&path).unwrap();
+        assert!(reader.header().has_dictionary());
+        assert_eq!(reader.dictionary(), Some(dictionary.as_slice()));
+        assert_eq!(reader.frames[0].parts[0].0.codec, CODEC_ZSTD_DICT);
+        assert_eq!(reader.read_frame(0).unwrap(), chunk(2));
+        assert_eq!(reader.read_frame(1).unwrap(), chunk(3));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_morton_bucket_cell_chunking() {
+        // A 16^3 region arriving in scrambled order
+        let cells: Vec<Index64> = (0..4096u32)
+            .map(|i| {
+                let j = (i * 1237) % 4096;
+                Index64::new(
+                    0,
+                    0,
+                    8,
+                    (j % 16) as u16,
+                    (j / 16 % 16) as u16,
+                    (j / 256) as u16,
+                )
+                .unwrap()
+            })
+            .collect();
+        let write = |cell_chunking| {
+            let config = StreamConfig {
+                cell_chunking,
+                ..StreamConfig::default()
+            };
+            let mut bytes = Vec::new();
+            let mut writer = ContainerWriterV2::new(Cursor::new(&mut bytes), config).unwrap();
+            for &idx in &cells {
+                let (x, y, z) = idx.decode_coords();
+                writer.write_cell(idx, &[(x + y + z) as u8; 4]).unwrap();
+            }
+            // Complete buckets are written as soon as their last cell arrives
+            assert!(writer.cell_buckets.is_empty());
+            writer.finish().unwrap();
+            bytes
+        };
+        let arrival = write(CellChunking::Arrival);
+        let buckets = write(CellChunking::MortonBuckets { bits: 9 });
+        assert!(buckets.len() * 4 < arrival.len());
+
+        let by_arrival = ContainerReaderV2::from_bytes(arrival).unwrap();
+        let by_bucket = ContainerReaderV2::from_bytes(buckets).unwrap();
+        assert_eq!(by_bucket.cells().unwrap(), by_arrival.cells().unwrap());
+        assert_eq!(by_bucket.cells().unwrap().len(), 4096);
+
+        // An 8^3 corner of the region is one block
+        let index = by_bucket.block_index().unwrap().unwrap();
+        assert_eq!(index.entries().len(), 8);
+        let corner = Index64::new(0, 0, 8, 8, 8, 8).unwrap();
+        let range = index.morton_range(0, 0, 8, corner.morton(), corner.morton() + 511);
+        assert_eq!(range.len(), 1);
+        assert!(StreamConfig {
+            cell_chunking: CellChunking::MortonBuckets { bits: 49 },
+            ..StreamConfig::default()
+        }
+        .cell_chunking
+        .bucket_key(corner)
+        .is_err());
+    }
+
+    #[test]
+    fn test_lattice_buckets_are_written_when_complete() {
+        // The BCC points of a 16^3 region, scrambled
+        let cells: Vec<Index64> = (0..4096u32)
+            .map(|i| (i * 1237) % 4096)
+            .map(|j| ((j % 16) as u16, (j / 16 % 16) as u16, (j / 256) as u16))
+            .filter(|&(x, y, z)| (x ^ y) & 1 == 0 && (y ^ z) & 1 == 0)
+            .map(|(x, y, z)| Index64::new(0, 0, 8, x, y, z).unwrap())
+            .collect();
+        assert_eq!(cells.len(), 1024);
+        let config =
+            StreamConfig::default().with_cell_chunking(CellChunking::MortonBuckets { bits: 9 });
+        let mut bytes = Vec::new();
+        let mut writer = ContainerWriterV2::new(Cursor::new(&mut bytes), config).unwrap();
+        for &idx in &cells {
+            writer.write_cell(idx, &[7]).unwrap();
+        }
+        assert!(writer.cell_buckets.is_empty());
+        // A straggler for a written bucket must not supersede its block
+        let straggler = Index64::new(0, 0, 8, 1, 0, 0).unwrap();
+        writer.write_cell(straggler, &[9]).unwrap();
+        writer.finish().unwrap();
+
+        let reader = ContainerReaderV2::from_bytes(bytes).unwrap();
+        assert_eq!(reader.block_index().unwrap().unwrap().entries().len(), 8);
+        let stored = reader.cells().unwrap();
+        assert_eq!(stored.len(), 1025);
+        assert!(stored.contains(&(straggler, vec![9])));
+    }
+
+    #[test]
+    fn test_block_index_seeks_latest_blocks() {
+        let path =
+            std::env::temp_dir().join(format!("octaindex3d-index-{}.oct2", std::process::id()));
+        let key = |x| Index64::new(0, 0, 6, x, 1, 0).unwrap();
+        let config = StreamConfig {
+            checkpoint_frames: 3,
+            stream_chunk_bytes: 10,
+            ..StreamConfig::default()
+        };
+        {
+            let mut writer = ContainerWriterV2::new(File::create(&path).unwrap(), config).unwrap();
+            writer.write_frame(b"notes").unwrap();
+            for x in (0..20).rev() {
+                writer.write_block(key(x), &[x as u8; 8]).unwrap();
+            }
+            writer.write_block(key(7), b"seven again").unwrap();
+            writer.write_block_stream(key(3), &[3u8; 25][..]).unwrap();
+            writer.finish().unwrap();
+        }
+
+        let mut file = File::open(&path).unwrap();
+        let index = BlockIndex::read(&mut file).unwrap().unwrap();
+        // 19 one-piece blocks and 3 pieces of the streamed one
+        assert_eq!(index.entries().len(), 22);
+        assert_eq!(index.get(key(3)).len(), 3);
+        assert_eq!(index.get(key(3))[0].lod(), 6);
+        assert!(index.get(key(20)).is_empty());
+        let range = index.morton_range(0, 0, 6, key(5).morton(), key(8).morton());
+        assert_eq!(range.len(), 4);
+        assert!(range
+            .iter()
+            .all(|e| (5..=8).contains(&e.key.decode_coords().0)));
+        assert_eq!(
+            index.read_block(&mut file, key(7)).unwrap().unwrap(),
+            b"seven again"
+        );
+        assert_eq!(
+            index.read_block(&mut file, key(3)).unwrap().unwrap(),
+            vec![3u8; 25]
+        );
+        assert_eq!(index.read_block(&mut file, key(20)).unwrap(), None);
+
+        // A corrupt entry count is caught before allocating for it
+        let mut bytes = fs::read(&path).unwrap();
+        let at = HeaderV2::from_bytes(bytes[..32].try_into().unwrap())
+            .unwrap()
+            .block_index_offset as usize;
+        bytes[at + 4..at + 8].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(BlockIndex::read(&mut Cursor::new(bytes)).is_err());
+
+        // Compaction moves every block and rebuilds the index
+        compact(&path).unwrap();
+        let reader = ContainerReaderV2::open(&path).unwrap();
+        let compacted = reader.block_index().unwrap().unwrap();
+        assert_eq!(compacted.entries().len(), 22);
+        assert_ne!(compacted, index);
+        let mut file = File::open(&path).unwrap();
+        for x in 0..20 {
+            let block = compacted.read_block(&mut file, key(x)).unwrap().unwrap();
+            let i = (0..reader.frame_count())
+                .find(|&i| reader.frame_key(i) == Some(key(x)))
+                .unwrap();
+            assert_eq!(block, reader.read_frame(i).unwrap());
+        }
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_metadata_survives_append() {
+        let path =
+            std::env::temp_dir().join(format!("octaindex3d-metadata-{}.oct2", std::process::id()));
+        {
+            let mut writer =
+                ContainerWriterV2::new(File::create(&path).unwrap(), StreamConfig::default())
+                    .unwrap();
+            writer.set_metadata("site", "dock 4");
+            writer.set_metadata("scans", 12i64);
+            writer.set_metadata("calibration", vec![1u8, 2, 3]);
+            writer
+                .write_block(Index64::new(0, 0, 4, 1, 1, 1).unwrap(), &[7; 64])
+                .unwrap();
+            writer.finish().unwrap();
+        }
+        let reader = ContainerReaderV2::open(&path).unwrap();
+        assert_eq!(reader.metadata().len(), 3);
+        assert_eq!(reader.metadata_value("scans").unwrap().as_f64(), Some(12.0));
+        assert_eq!(
+            reader.metadata_value("calibration").unwrap().as_bytes(),
+            Some(&[1u8, 2, 3][..])
+        );
+        // Metadata frames are not data frames
+        assert_eq!(reader.frame_count(), 1);
+
+        let open = || {
+            fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(&path)
+                .unwrap()
+        };
+        let mut writer = ContainerWriterV2::append(open(), StreamConfig::default()).unwrap();
+        assert_eq!(writer.metadata().len(), 3);
+        writer.set_metadata("scans", 13i64);
+        writer.remove_metadata("calibration");
+        writer.finish().unwrap();
+        compact(&path).unwrap();
+        let reader = ContainerReaderV2::open(&path).unwrap();
+        assert_eq!(
+            reader.metadata_value("scans"),
+            Some(&MetadataValue::Int(13))
+        );
+        assert_eq!(
+            reader.metadata_value("site").unwrap().as_str(),
+            Some("dock 4")
+        );
+        assert_eq!(reader.metadata_value("calibration"), None);
+
+        let mut writer = ContainerWriterV2::append(open(), StreamConfig::default()).unwrap();
+        writer.remove_metadata("scans");
+        writer.remove_metadata("site");
+        writer.finish().unwrap();
+        assert!(ContainerReaderV2::open(&path)
+            .unwrap()
+            .metadata()
+            .is_empty());
+
+        let mut section = encode_metadata(&BTreeMap::from([(
+            "n".to_string(),
+            MetadataValue::Float(1.5),
+        )]))
+        .unwrap();
+        section.pop();
+        assert!(decode_metadata(&section).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_verify_and_recover_damaged_container() {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("octaindex3d-damaged-{}.oct2", std::process::id()));
+        let salvaged = path.with_extension("recovered");
+        let key = |x| Index64::new(0, 0, 5, x, 3, 3).unwrap();
+        let config = StreamConfig {
+            checkpoint_frames: 4,
+            stream_chunk_bytes: 100,
+            enable_sha256: true,
+            ..StreamConfig::default()
+        };
+        let mut good = Vec::new();
+        let mut writer = ContainerWriterV2::new(Cursor::new(&mut good), config).unwrap();
+        for x in 0..8 {
+            writer.write_block(key(x), &[x as u8; 64]).unwrap();
+        }
+        writer.write_block_stream(key(8), &[8u8; 250][..]).unwrap();
+        writer.finish().unwrap();
+        assert!(verify_bytes(&good).unwrap().is_intact());
+        let located = |bytes: &[u8], x: u16| {
+            let reader = ContainerReaderV2::from_bytes(bytes.to_vec()).unwrap();
+            let index = reader.block_index().unwrap().unwrap();
+            index.get(key(x)).to_vec()
+        };
+
+        // One flipped payload byte in block 2 and one in the second piece of
+        // the streamed block
+        let mut damaged = good.clone();
+        damaged[located(&good, 2)[0].offset as usize + 30] ^= 1;
+        damaged[located(&good, 8)[1].offset as usize + 30] ^= 1;
+        assert!(ContainerReaderV2::from_bytes(damaged.clone()).is_err());
+        let report = verify_bytes(&damaged).unwrap();
+        assert_eq!(report.digest_ok, Some(false));
+        let keys: Vec<_> = report.corrupt.iter().map(|c| c.key).collect();
+        assert_eq!(keys, vec![Some(key(2)), Some(key(8))]);
+
+        fs::write(&path, &damaged).unwrap();
+        recover(&path, &salvaged).unwrap();
+        assert!(verify(&salvaged).unwrap().is_intact());
+        let reader = ContainerReaderV2::open(&salvaged).unwrap();
+        let mut keys: Vec<u16> = (0..reader.frame_count())
+            .map(|i| reader.frame_key(i).unwrap().decode_coords().0)
+            .collect();
+        keys.sort();
+        assert_eq!(keys, vec![0, 1, 3, 4, 5, 6, 7]);
+
+        // Bytes outside any frame payload are caught by the digest
+        let mut toc_damaged = good.clone();
+        let toc = good.len() - 32 - 32;
+        toc_damaged[toc + 18] ^= 1;
+        assert!(matches!(
+            ContainerReaderV2::from_bytes(toc_damaged.clone()),
+            Err(Error::CrcMismatch { .. })
+        ));
+        let report = verify_bytes(&toc_damaged).unwrap();
+        assert!(report.corrupt.is_empty());
+        assert_eq!(report.digest_ok, Some(false));
+
+        // A cut-off end falls back to the last intact checkpoint
+        let cut = &good[..good.len() - 40];
+        let report = verify_bytes(cut).unwrap();
+        assert!(report.trailing_bytes > 0);
+        assert_eq!(report.digest_ok, Some(true));
+        assert!(report.frames >= 4);
+        fs::write(&path, cut).unwrap();
+        recover(&path, &salvaged).unwrap();
+        let salvaged_report = verify(&salvaged).unwrap();
+        assert!(salvaged_report.is_intact());
+        assert_eq!(salvaged_report.frames, report.frames);
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(&salvaged).unwrap();
+    }
+
+    #[test]
+    fn test_find_checkpoint_in_truncated_container() {
+        let config = StreamConfig {
+            checkpoint_frames: 2,
+            ..StreamConfig::default()
+        };
+        let mut bytes = Vec::new();
+        let mut writer = ContainerWriterV2::new(Cursor::new(&mut bytes), config).unwrap();
+        for i in 0..9u8 {
+            writer.write_frame(&[i; 40]).unwrap();
+        }
+        writer.finish().unwrap();
+        let header = HeaderV2::from_bytes(bytes[..32].try_into().unwrap()).unwrap();
+        assert!(header.has_digest());
+
+        // Every cut-off length finds the latest checkpoint still whole
+        let mut frames = 0;
+        for len in 33..bytes.len() {
+            let Some((at, footer)) = find_checkpoint(&bytes[..len], &header) else {
+                assert_eq!(frames, 0);
+                continue;
+            };
+            assert!(at + 32 <= len);
+            assert_eq!(crc32fast::hash(&bytes[32..at]), footer.digest());
+            assert!(footer.entry_count >= frames);
+            frames = footer.entry_count;
+        }
+        assert!(frames >= 8);
+        let (at, footer) = find_checkpoint(&bytes, &header).unwrap();
+        assert_eq!((at, footer.entry_count), (bytes.len() - 32, 9));
+    }
+
+    #[test]
+    fn test_append_after_interrupted_session() {
+        let path =
+            std::env::temp_dir().join(format!("octaindex3d-append-{}.oct2", std::process::id()));
+        let key = |x| Index64::new(0, 0, 6, x, 2, 2).unwrap();
+        let config = StreamConfig {
+            checkpoint_frames: 4,
+            stream_chunk_bytes: 10,
+            ..StreamConfig::default()
+        };
+        {
+            let mut writer =
+                ContainerWriterV2::new(File::create(&path).unwrap(), config.clone()).unwrap();
+            for x in 0..3 {
+                writer.write_block(key(x), &[x as u8; 4]).unwrap();
+            }
+            // The source fails after the checkpoint that follows the first
+            // piece, and the session ends without finishing
+            struct Lost;
+            impl Read for Lost {
+                fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                    Err(std::io::Error::other("sensor lost"))
+                }
+            }
+            let source = (&[9u8; 20][..]).chain(Lost);
+            assert!(writer.write_block_stream(key(0), source).is_err());
+        }
+        assert_eq!(ContainerReaderV2::open(&path).unwrap().frame_count(), 3);
+
+        let open = || {
+            fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(&path)
+                .unwrap()
+        };
+        let mut writer = ContainerWriterV2::append(open(), config.clone()).unwrap();
+        writer.write_block(key(1), b"updated").unwrap();
+        writer.delete_block(key(2)).unwrap();
+        writer.write_block(key(3), b"new").unwrap();
+        writer.finish().unwrap();
+
+        let mut writer = ContainerWriterV2::append(open(), config).unwrap();
+        writer.write_block(key(2), b"restored").unwrap();
+        writer.delete_block(key(3)).unwrap();
+        writer.finish().unwrap();
+
+        let latest = |reader: &ContainerReaderV2| {
+            let mut blocks = HashMap::new();
+            for i in 0..reader.frame_count() {
+                blocks.insert(reader.frame_key(i).unwrap(), reader.read_frame(i).unwrap());
+            }
+            let mut blocks: Vec<_> = blocks.into_iter().collect();
+            blocks.sort();
+            blocks
+        };
+        let expected = vec![
+            (key(0), vec![0u8; 4]),
+            (key(1), b"updated".to_vec()),
+            (key(2), b"restored".to_vec()),
+        ];
+        let reader = ContainerReaderV2::open(&path).unwrap();
+        assert_eq!(latest(&reader), expected);
+        let index = reader.block_index().unwrap().unwrap();
+        let indexed: Vec<Index64> = index.entries().iter().map(|e| e.key).collect();
+        assert_eq!(indexed, vec![key(0), key(1), key(2)]);
+
+        let stats = compact(&path).unwrap();
+        assert_eq!(stats.frames_after, 3);
+        let reader = ContainerReaderV2::open(&path).unwrap();
+        assert_eq!(reader.frame_count(), 3);
+        assert_eq!(latest(&reader), expected);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(feature = "hilbert")]
+    #[test]
+    fn test_hilbert_buckets_keep_later_cell_records() {
+        let config = StreamConfig {
+            block_order: BlockOrder::Hilbert,
+            cells_per_block: 2,
+            ..StreamConfig::default()
+        }
+        .with_cell_chunking(CellChunking::MortonBuckets { bits: 3 });
+        let cell = |x, y, z| Index64::new(0, 0, 8, x, y, z).unwrap();
+        let mut buffer = Vec::new();
+        let mut writer = ContainerWriterV2::new(Cursor::new(&mut buffer), config).unwrap();
+        // A full bucket is buffered for Hilbert order...
+        for i in 0..8 {
+            writer
+                .write_cell(cell(i & 1, i >> 1 & 1, i >> 2), &[1])
+                .unwrap();
+        }
+        // ... when two of its cells are written again, once mid-stream
+        // and once at finish
+        writer.write_cell(cell(0, 0, 0), &[2]).unwrap();
+        writer.write_cell(cell(1, 1, 1), &[3]).unwrap();
+        writer.write_cell(cell(1, 0, 0), &[4]).unwrap();
+        writer.finish().unwrap();
+
+        let cells = ContainerReaderV2::from_bytes(buffer)
+            .unwrap()
+            .cells()
+            .unwrap();
+        assert_eq!(cells.len(), 8);
+        assert!(cells.contains(&(cell(0, 0, 0), vec![2])));
+        assert!(cells.contains(&(cell(1, 1, 1), vec![3])));
+        assert!(cells.contains(&(cell(1, 0, 0), vec![4])));
+        assert!(cells.contains(&(cell(0, 1, 0), vec![1])));
+    }
+
+    #[cfg(not(feature = "hilbert"))]
+    #[test]
+    fn test_hilbert_block_order_needs_feature() {
+        let config = StreamConfig {
+            block_order: BlockOrder::Hilbert,
+            ..StreamConfig::default()
+        };
+        let mut buffer = Vec::new();
+        let mut writer = ContainerWriterV2::new(Cursor::new(&mut buffer), config).unwrap();
+        let key = Index64::new(0, 0, 3, 0, 0, 0).unwrap();
+        assert!(writer.write_block(key, &[1]).is_err());
+    }
+
+    #[cfg(feature = "hilbert")]
+    #[test]
+    fn test_hilbert_block_order() {
+        let config = StreamConfig {
+            block_order: BlockOrder::Hilbert,
+            checkpoint_frames: 40,
+            ..StreamConfig::default()
+        };
+        let mut buffer = Vec::new();
+        let mut keys = Vec::new();
+        {
+            let mut writer = ContainerWriterV2::new(Cursor::new(&mut buffer), config).unwrap();
+            // Rows of an 8×8 slab arrive in scan order
+            for y in 0..8 {
+                for x in 0..8 {
+                    let key = Index64::new(0, 0, 3, x, y, 0).unwrap();
+                    writer.write_block(key, &[x as u8, y as u8]).unwrap();
+                    keys.push(key);
+                }
+            }
+            writer.finish().unwrap();
+        }
+
+        let reader = ContainerReaderV2::from_bytes(buffer).unwrap();
+        let written: Vec<_> = (0..reader.frame_count())
+            .map(|i| reader.frame_key(i).unwrap())
+            .collect();
+        let hilbert = |k: &Index64| crate::Hilbert64::try_from(*k).unwrap().as_u64();
+        // Sorted within each checkpoint window of 40 blocks
+        assert!(written[..40]
+            .windows(2)
+            .all(|w| hilbert(&w[0]) < hilbert(&w[1])));
+        assert!(written[40..]
+            .windows(2)
+            .all(|w| hilbert(&w[0]) < hilbert(&w[1])));
+        let mut all = written.clone();
+        all.sort();
+        keys.sort();
+        assert_eq!(all, keys);
+
+        // With one window, every aligned 2×2 tile is contiguous
+        let mut sorted = keys.clone();
+        sorted.sort_by_key(hilbert);
+        for run in sorted.chunks(4) {
+            let tile = run[0].parent().unwrap();
+            assert!(run.iter().all(|k| k.parent() == Some(tile)));
+        }
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_mmap_checks_crc_on_read() {
+        let path = std::env::temp_dir().join(format!("mmap-crc-{}.oct2", std::process::id()));
+        {
+            let mut writer =
+                ContainerWriterV2::new(File::create(&path).unwrap(), StreamConfig::default())
+                    .unwrap()
+                    .with_compression(Box::new(crate::compression::NoCompression))
+                    .unwrap();
+            writer.write_frame(b"intact").unwrap();
+            writer.write_frame(b"damaged").unwrap();
+            writer.write_stream(&b"streamed"[..]).unwrap();
+            writer.finish().unwrap();
+        }
+        let mut bytes = fs::read(&path).unwrap();
+        let at = bytes.windows(7).position(|w| w == b"damaged").unwrap();
+        bytes[at] = b'D';
+        fs::write(&path, &bytes).unwrap();
+
+        // The damage only surfaces when the damaged frame is read
+        assert!(ContainerReaderV2::from_bytes(bytes).is_err());
+        let map = ContainerReaderMmap::open(&path).unwrap();
+        assert_eq!(map.reader().frame_count(), 3);
+        assert!(matches!(
+            map.frame_data(0).unwrap(),
+            Cow::Borrowed(b"intact")
+        ));
+        assert!(matches!(map.frame_data(1), Err(Error::CrcMismatch { .. })));
+        assert_eq!(map.frame_data(2).unwrap().as_ref(), b"streamed");
+        drop(map);
+        fs::remove_file(&path).unwrap();
     }
 }
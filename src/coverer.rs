@@ -0,0 +1,480 @@
+//! Mixed-LOD coverings of geometric regions
+//!
+//! [`RegionCoverer`] approximates a region (sphere, box, polygon prism or
+//! any [`Region`] implementation) by a small set of [`Index64`] cells across
+//! levels of detail, in the spirit of S2's `RegionCoverer`. Cells fully
+//! inside the region are kept as large as possible, and only cells on the
+//! boundary are subdivided, down to `max_lod` or until the `max_cells`
+//! budget is used up.
+//!
+//! Region coordinates are cell coordinates at `max_lod`, the coordinates
+//! [`Index64::new`] takes at that level. A cell at a coarser LOD `l` with
+//! coordinates `c` spans `[c · 2^(max_lod - l), (c + 1) · 2^(max_lod - l))`
+//! on each axis, following the hierarchy of [`Index64::parent`].
+//!
+//! # Example
+//!
+//! ```
+//! use octaindex3d::coverer::{RegionCoverer, Sphere};
+//! use octaindex3d::CellSet;
+//!
+//! # fn main() -> octaindex3d::Result<()> {
+//! let coverer = RegionCoverer {
+//!     min_lod: 2,
+//!     max_lod: 10,
+//!     max_cells: 64,
+//!     ..Default::default()
+//! };
+//! let sphere = Sphere::new([500.0, 500.0, 500.0], 100.0);
+//!
+//! let cells = coverer.covering(&sphere)?;
+//! assert!(cells.len() <= 64);
+//!
+//! // Every point of the sphere lies in one of the cells
+//! let set = CellSet::from_cells(cells);
+//! let center = octaindex3d::Index64::new(0, 0, 10, 500, 500, 500)?;
+//! assert!(set.contains(center));
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{Error, Result};
+use crate::ids::{FrameId, Index64};
+use std::collections::BinaryHeap;
+
+/// A region that can be tested against axis-aligned boxes
+///
+/// Boxes are given by their `min` and `max` corners. Both tests may be
+/// conservative: `intersects_box` may return `true` for a box that only
+/// touches the region, and `contains_box` may return `false` for a box that
+/// is inside, at the cost of a larger covering.
+pub trait Region {
+    /// Bounding box of the region as `(min, max)`
+    fn bounds(&self) -> ([f64; 3], [f64; 3]);
+
+    /// Check if the box is entirely inside the region
+    fn contains_box(&self, min: [f64; 3], max: [f64; 3]) -> bool;
+
+    /// Check if the box overlaps the region
+    fn intersects_box(&self, min: [f64; 3], max: [f64; 3]) -> bool;
+}
+
+/// Solid sphere
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sphere {
+    /// Center point
+    pub center: [f64; 3],
+    /// Radius
+    pub radius: f64,
+}
+
+impl Sphere {
+    /// Create a sphere
+    pub fn new(center: [f64; 3], radius: f64) -> Self {
+        Self { center, radius }
+    }
+}
+
+impl Region for Sphere {
+    fn bounds(&self) -> ([f64; 3], [f64; 3]) {
+        let r = self.radius;
+        let c = self.center;
+        (
+            [c[0] - r, c[1] - r, c[2] - r],
+            [c[0] + r, c[1] + r, c[2] + r],
+        )
+    }
+
+    fn contains_box(&self, min: [f64; 3], max: [f64; 3]) -> bool {
+        // Farthest corner inside the sphere
+        let d2: f64 = (0..3)
+            .map(|i| {
+                let d = (self.center[i] - min[i])
+                    .abs()
+                    .max((max[i] - self.center[i]).abs());
+                d * d
+            })
+            .sum();
+        d2 <= self.radius * self.radius
+    }
+
+    fn intersects_box(&self, min: [f64; 3], max: [f64; 3]) -> bool {
+        // Nearest point of the box strictly inside the sphere
+        let d2: f64 = (0..3)
+            .map(|i| {
+                let d = self.center[i] - self.center[i].clamp(min[i], max[i]);
+                d * d
+            })
+            .sum();
+        d2 < self.radius * self.radius
+    }
+}
+
+/// Axis-aligned box
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    /// Minimum corner
+    pub min: [f64; 3],
+    /// Maximum corner
+    pub max: [f64; 3],
+}
+
+impl Aabb {
+    /// Create a box from two opposite corners
+    pub fn new(a: [f64; 3], b: [f64; 3]) -> Self {
+        Self {
+            min: [a[0].min(b[0]), a[1].min(b[1]), a[2].min(b[2])],
+            max: [a[0].max(b[0]), a[1].max(b[1]), a[2].max(b[2])],
+        }
+    }
+}
+
+impl Region for Aabb {
+    fn bounds(&self) -> ([f64; 3], [f64; 3]) {
+        (self.min, self.max)
+    }
+
+    fn contains_box(&self, min: [f64; 3], max: [f64; 3]) -> bool {
+        (0..3).all(|i| self.min[i] <= min[i] && max[i] <= self.max[i])
+    }
+
+    fn intersects_box(&self, min: [f64; 3], max: [f64; 3]) -> bool {
+        (0..3).all(|i| self.min[i] < max[i] && min[i] < self.max[i])
+    }
+}
+
+/// Vertical prism over a simple polygon in the XY plane
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolygonPrism {
+    /// Polygon vertices `(x, y)`, implicitly closed
+    pub vertices: Vec<(f64, f64)>,
+    /// Bottom of the prism
+    pub min_z: f64,
+    /// Top of the prism
+    pub max_z: f64,
+}
+
+impl PolygonPrism {
+    /// Create a prism
+    pub fn new(vertices: Vec<(f64, f64)>, min_z: f64, max_z: f64) -> Self {
+        Self {
+            vertices,
+            min_z,
+            max_z,
+        }
+    }
+
+    fn edges(&self) -> impl Iterator<Item = ((f64, f64), (f64, f64))> + '_ {
+        let n = self.vertices.len();
+        (0..n).map(move |i| (self.vertices[i], self.vertices[(i + 1) % n]))
+    }
+
+    /// Even-odd point-in-polygon test
+    fn contains_point(&self, (px, py): (f64, f64)) -> bool {
+        let mut inside = false;
+        for ((x0, y0), (x1, y1)) in self.edges() {
+            if (y0 > py) != (y1 > py) && px < x0 + (py - y0) / (y1 - y0) * (x1 - x0) {
+                inside = !inside;
+            }
+        }
+        inside
+    }
+
+    /// Check if any polygon edge passes through the open rectangle
+    fn edge_crosses_rect(&self, min: [f64; 3], max: [f64; 3]) -> bool {
+        self.edges()
+            .any(|(a, b)| segment_crosses_rect(a, b, (min[0], min[1]), (max[0], max[1])))
+    }
+}
+
+impl Region for PolygonPrism {
+    fn bounds(&self) -> ([f64; 3], [f64; 3]) {
+        let mut min = [f64::INFINITY, f64::INFINITY, self.min_z];
+        let mut max = [f64::NEG_INFINITY, f64::NEG_INFINITY, self.max_z];
+        for &(x, y) in &self.vertices {
+            min[0] = min[0].min(x);
+            min[1] = min[1].min(y);
+            max[0] = max[0].max(x);
+            max[1] = max[1].max(y);
+        }
+        (min, max)
+    }
+
+    fn contains_box(&self, min: [f64; 3], max: [f64; 3]) -> bool {
+        self.min_z <= min[2]
+            && max[2] <= self.max_z
+            && self.contains_point((0.5 * (min[0] + max[0]), 0.5 * (min[1] + max[1])))
+            && !self.edge_crosses_rect(min, max)
+    }
+
+    fn intersects_box(&self, min: [f64; 3], max: [f64; 3]) -> bool {
+        if self.vertices.len() < 3 || self.max_z <= min[2] || max[2] <= self.min_z {
+            return false;
+        }
+        self.contains_point((0.5 * (min[0] + max[0]), 0.5 * (min[1] + max[1])))
+            || self.edge_crosses_rect(min, max)
+    }
+}
+
+/// Liang–Barsky test of a segment against an open rectangle
+fn segment_crosses_rect(a: (f64, f64), b: (f64, f64), min: (f64, f64), max: (f64, f64)) -> bool {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let (mut t0, mut t1) = (0.0f64, 1.0f64);
+    for (p, q) in [
+        (-dx, a.0 - min.0),
+        (dx, max.0 - a.0),
+        (-dy, a.1 - min.1),
+        (dy, max.1 - a.1),
+    ] {
+        if p == 0.0 {
+            if q <= 0.0 {
+                return false;
+            }
+            continue;
+        }
+        let t = q / p;
+        if p < 0.0 {
+            t0 = t0.max(t);
+        } else {
+            t1 = t1.min(t);
+        }
+        if t0 >= t1 {
+            return false;
+        }
+    }
+    true
+}
+
+/// Computes mixed-LOD cell coverings of regions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegionCoverer {
+    /// Frame of the produced cells
+    pub frame: FrameId,
+    /// Scale tier of the produced cells
+    pub tier: u8,
+    /// Coarsest LOD to emit
+    pub min_lod: u8,
+    /// Finest LOD to emit; region coordinates are cell coordinates at this LOD
+    pub max_lod: u8,
+    /// Soft limit on the number of cells
+    ///
+    /// Exceeded only when the region needs more `min_lod` cells than this.
+    pub max_cells: usize,
+}
+
+impl Default for RegionCoverer {
+    fn default() -> Self {
+        Self {
+            frame: 0,
+            tier: 0,
+            min_lod: 0,
+            max_lod: 15,
+            max_cells: 8,
+        }
+    }
+}
+
+/// Candidate cell in the refinement queue, coarsest first
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct Candidate {
+    size_rank: std::cmp::Reverse<u8>,
+    cell: Index64,
+}
+
+impl RegionCoverer {
+    /// Cells covering the whole region, sorted in Morton order
+    ///
+    /// Every point of the region lies in some returned cell; cells are
+    /// disjoint and no cell lies entirely outside the region.
+    pub fn covering(&self, region: &dyn Region) -> Result<Vec<Index64>> {
+        self.cover(region, false)
+    }
+
+    /// Cells entirely inside the region, sorted in Morton order
+    ///
+    /// The counterpart of [`covering`](Self::covering): the cells are a
+    /// subset of the region, so thin regions may have an empty interior.
+    pub fn interior_covering(&self, region: &dyn Region) -> Result<Vec<Index64>> {
+        self.cover(region, true)
+    }
+
+    /// Box spanned by a cell, in `max_lod` coordinates
+    fn cell_box(&self, cell: Index64) -> ([f64; 3], [f64; 3]) {
+        let size = (1u64 << (self.max_lod - cell.lod())) as f64;
+        let (x, y, z) = cell.decode_coords();
+        let min = [x as f64 * size, y as f64 * size, z as f64 * size];
+        (min, [min[0] + size, min[1] + size, min[2] + size])
+    }
+
+    fn cover(&self, region: &dyn Region, interior: bool) -> Result<Vec<Index64>> {
+        if self.max_lod > 15 || self.min_lod > self.max_lod {
+            return Err(Error::InvalidLOD(format!(
+                "need min_lod <= max_lod <= 15, got {}..={}",
+                self.min_lod, self.max_lod
+            )));
+        }
+
+        // Start from the min_lod cells overlapping the bounding box
+        let shift = self.max_lod - self.min_lod;
+        let limit = (u16::MAX >> shift) as f64;
+        let (lo, hi) = region.bounds();
+        let range = |axis: usize| -> Option<(u16, u16)> {
+            let size = (1u64 << shift) as f64;
+            let a = (lo[axis] / size).floor().max(0.0);
+            let b = (hi[axis] / size).floor().min(limit);
+            (a <= b).then_some((a as u16, b as u16))
+        };
+        let (Some(rx), Some(ry), Some(rz)) = (range(0), range(1), range(2)) else {
+            return Ok(Vec::new());
+        };
+
+        let mut result = Vec::new();
+        let mut queue = BinaryHeap::new();
+        let visit = |cell: Index64, queue: &mut BinaryHeap<Candidate>, result: &mut Vec<_>| {
+            let (min, max) = self.cell_box(cell);
+            if !region.intersects_box(min, max) {
+                return;
+            }
+            if region.contains_box(min, max) {
+                result.push(cell);
+            } else if cell.lod() < self.max_lod {
+                queue.push(Candidate {
+                    size_rank: std::cmp::Reverse(cell.lod()),
+                    cell,
+                });
+            } else if !interior {
+                result.push(cell);
+            }
+        };
+
+        for z in rz.0..=rz.1 {
+            for y in ry.0..=ry.1 {
+                for x in rx.0..=rx.1 {
+                    let cell = Index64::new(self.frame, self.tier, self.min_lod, x, y, z)?;
+                    visit(cell, &mut queue, &mut result);
+                }
+            }
+        }
+
+        // Refine boundary cells, coarsest first, while the budget allows
+        while let Some(Candidate { cell, .. }) = queue.pop() {
+            let children = cell.children();
+            if result.len() + queue.len() + children.len() > self.max_cells {
+                if !interior {
+                    result.push(cell);
+                }
+                continue;
+            }
+            for child in children {
+                visit(child, &mut queue, &mut result);
+            }
+        }
+
+        result.sort_unstable_by_key(|c| c.raw());
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CellSet;
+
+    fn leaf(x: u16, y: u16, z: u16, lod: u8) -> Index64 {
+        Index64::new(0, 0, lod, x, y, z).unwrap()
+    }
+
+    #[test]
+    fn test_sphere_covering_contains_every_inside_cell() -> Result<()> {
+        let coverer = RegionCoverer {
+            min_lod: 2,
+            max_lod: 6,
+            max_cells: 40,
+            ..Default::default()
+        };
+        let sphere = Sphere::new([30.0, 28.0, 33.0], 11.5);
+        let cells = coverer.covering(&sphere)?;
+        assert!(cells.len() <= 40);
+        assert!(cells.iter().all(|c| (2..=6).contains(&c.lod())));
+
+        let covering = CellSet::from_cells(cells.iter().copied());
+        let interior = CellSet::from_cells(coverer.interior_covering(&sphere)?);
+        assert!(covering.contains_set(&interior));
+
+        for x in 10..50u16 {
+            for y in 10..50u16 {
+                for z in 10..50u16 {
+                    let p = [x as f64 + 0.5, y as f64 + 0.5, z as f64 + 0.5];
+                    let d2: f64 = (0..3).map(|i| (p[i] - sphere.center[i]).powi(2)).sum();
+                    let r2 = sphere.radius * sphere.radius;
+                    if d2 < r2 {
+                        assert!(covering.contains(leaf(x, y, z, 6)));
+                    }
+                    if interior.contains(leaf(x, y, z, 6)) {
+                        assert!(d2 < r2);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_aligned_box_uses_one_coarse_cell() -> Result<()> {
+        let coverer = RegionCoverer {
+            min_lod: 0,
+            max_lod: 4,
+            ..Default::default()
+        };
+        let aligned = Aabb::new([16.0, 0.0, 16.0], [32.0, 16.0, 32.0]);
+        assert_eq!(coverer.covering(&aligned)?, vec![leaf(1, 0, 1, 0)]);
+
+        // A budget of one coarsens a misaligned box to its enclosing cells
+        let tight = RegionCoverer {
+            max_cells: 1,
+            ..coverer
+        };
+        let shifted = Aabb::new([17.0, 1.0, 17.0], [31.0, 15.0, 31.0]);
+        assert_eq!(tight.covering(&shifted)?, vec![leaf(1, 0, 1, 0)]);
+        assert!(tight.interior_covering(&shifted)?.is_empty());
+
+        let bad = RegionCoverer {
+            min_lod: 5,
+            max_lod: 4,
+            ..Default::default()
+        };
+        assert!(bad.covering(&aligned).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_concave_prism() -> Result<()> {
+        // L-shaped footprint; the notch [8, 16) x [8, 16) is outside
+        let prism = PolygonPrism::new(
+            vec![
+                (0.0, 0.0),
+                (16.0, 0.0),
+                (16.0, 8.0),
+                (8.0, 8.0),
+                (8.0, 16.0),
+                (0.0, 16.0),
+            ],
+            0.0,
+            8.0,
+        );
+        let coverer = RegionCoverer {
+            min_lod: 0,
+            max_lod: 4,
+            max_cells: 100,
+            ..Default::default()
+        };
+        let cells = coverer.covering(&prism)?;
+        assert_eq!(cells, coverer.interior_covering(&prism)?);
+        assert_eq!(cells.len(), 3);
+        let set = CellSet::from_cells(cells);
+        assert!(set.contains(leaf(3, 3, 3, 4)));
+        assert!(!set.contains(leaf(12, 12, 3, 4)));
+        assert!(!set.contains(leaf(3, 3, 8, 4)));
+        Ok(())
+    }
+}
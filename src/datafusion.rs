@@ -0,0 +1,458 @@
+//! DataFusion table provider over container cell blocks
+//!
+//! Enabled with the `datafusion` feature, which needs Rust 1.88 or newer. A [`CellTableProvider`] exposes
+//! one container written with
+//! [`StaticMap::write_container`](crate::layers::StaticMap::write_container)
+//! as a table with one row per cell, with the columns of the SQLite table
+//! in [`sqlite`](crate::sqlite) when both features are enabled:
+//!
+//! ```text
+//! id UInt64, frame UInt8, tier UInt8, lod UInt8, x UInt16, y UInt16,
+//! z UInt16, morton UInt64, tsdf Float32, esdf Float32, occupancy Float32,
+//! color Float32, intensity Float32, derived Float32
+//! ```
+//!
+//! Layer columns are null where a cell has no value. Comparisons of
+//! `morton`, `x`, `y` and `z` with literals and equality on `frame` and
+//! `lod` are pushed down into the scan: blocks that cannot hold a matching
+//! cell are never read or decompressed. The remaining blocks are spread
+//! over the session's target partitions and decoded as they are polled.
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! # use octaindex3d::container_v2::{ContainerWriterV2, StreamConfig};
+//! # use octaindex3d::layers::{LayeredMap, OccupancyLayer, StaticMap};
+//! # use octaindex3d::Index64;
+//! use datafusion::prelude::SessionContext;
+//! use octaindex3d::container_v2::ContainerReaderV2;
+//! use octaindex3d::datafusion::CellTableProvider;
+//! use std::sync::Arc;
+//!
+//! # let mut occupancy = OccupancyLayer::new();
+//! # for x in 0..64 {
+//! #     occupancy.update_occupancy(Index64::new(0, 0, 10, x, 0, 0)?, x % 2 == 0, 0.9);
+//! # }
+//! # let mut map = LayeredMap::new();
+//! # map.add_occupancy_layer(occupancy);
+//! # let mut bytes = Vec::new();
+//! # let mut writer = ContainerWriterV2::new(std::io::Cursor::new(&mut bytes), StreamConfig::default())?;
+//! # StaticMap::from(&map).write_container(&mut writer)?;
+//! # writer.finish()?;
+//! let reader = ContainerReaderV2::from_bytes(bytes)?;
+//! let ctx = SessionContext::new();
+//! ctx.register_table("cells", Arc::new(CellTableProvider::new(reader)))?;
+//!
+//! # tokio::runtime::Builder::new_current_thread().build()?.block_on(async {
+//! let occupied = ctx
+//!     .sql("SELECT count(*) FROM cells WHERE x BETWEEN 8 AND 23 AND occupancy > 0.5")
+//!     .await?
+//!     .collect()
+//!     .await?;
+//! # assert_eq!(datafusion::arrow::util::pretty::pretty_format_batches(&occupied)?.to_string().lines().nth(3), Some("| 8        |"));
+//! # Ok::<_, datafusion::error::DataFusionError>(())
+//! # })?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::container_v2::ContainerReaderV2;
+use crate::error::Result;
+use crate::layers::scan::{BoundColumn, BoundsBuilder};
+use crate::layers::static_map::{latest_blocks, CellBlock};
+use crate::layers::{CellBounds, LayerType};
+use crate::Index64;
+use async_trait::async_trait;
+use datafusion::arrow::array::{ArrayRef, Float32Array, UInt16Array, UInt64Array, UInt8Array};
+use datafusion::arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::catalog::{Session, TableProvider};
+use datafusion::common::ScalarValue;
+use datafusion::error::{DataFusionError, Result as DataFusionResult};
+use datafusion::execution::{SendableRecordBatchStream, TaskContext};
+use datafusion::logical_expr::{
+    BinaryExpr, Expr, Operator, TableProviderFilterPushDown, TableType,
+};
+use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+use datafusion::physical_plan::streaming::{PartitionStream, StreamingTableExec};
+use datafusion::physical_plan::ExecutionPlan;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Layer columns in tag order, after the cell columns
+const LAYER_COLUMNS: [(LayerType, &str); 6] = [
+    (LayerType::TSDF, "tsdf"),
+    (LayerType::ESDF, "esdf"),
+    (LayerType::Occupancy, "occupancy"),
+    (LayerType::Color, "color"),
+    (LayerType::Intensity, "intensity"),
+    (LayerType::Derived, "derived"),
+];
+
+/// Table of the cells of one container; see the [module docs](self)
+pub struct CellTableProvider {
+    reader: Arc<ContainerReaderV2>,
+    schema: SchemaRef,
+}
+
+impl CellTableProvider {
+    /// Table over the cell blocks of `reader`
+    pub fn new(reader: ContainerReaderV2) -> Self {
+        Self::from_shared(Arc::new(reader))
+    }
+
+    /// Table over a reader shared with other tables or scans
+    pub fn from_shared(reader: Arc<ContainerReaderV2>) -> Self {
+        Self {
+            reader,
+            schema: schema(),
+        }
+    }
+
+    /// Table over the container at `path`
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self::new(ContainerReaderV2::open(path)?))
+    }
+
+    /// Bounds the scan of a query with `filters` is pruned to
+    ///
+    /// Filters that cannot be pushed down are ignored here and applied by
+    /// DataFusion to the scanned rows.
+    pub fn bounds(filters: &[Expr]) -> CellBounds {
+        let mut bounds = BoundsBuilder::new();
+        for filter in filters {
+            if let Some((column, op, v)) = comparison(filter) {
+                bounds.narrow(column, op, v);
+            }
+        }
+        bounds.build()
+    }
+}
+
+impl fmt::Debug for CellTableProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CellTableProvider")
+            .field("frames", &self.reader.frame_count())
+            .finish()
+    }
+}
+
+#[async_trait]
+impl TableProvider for CellTableProvider {
+    fn schema(&self) -> SchemaRef {
+        Arc::clone(&self.schema)
+    }
+
+    fn table_type(&self) -> TableType {
+        TableType::Base
+    }
+
+    fn supports_filters_pushdown(
+        &self,
+        filters: &[&Expr],
+    ) -> DataFusionResult<Vec<TableProviderFilterPushDown>> {
+        // Pruning is by block, so matching rows are filtered again
+        Ok(filters
+            .iter()
+            .map(|filter| match comparison(filter) {
+                Some(_) => TableProviderFilterPushDown::Inexact,
+                None => TableProviderFilterPushDown::Unsupported,
+            })
+            .collect())
+    }
+
+    async fn scan(
+        &self,
+        state: &dyn Session,
+        projection: Option<&Vec<usize>>,
+        filters: &[Expr],
+        limit: Option<usize>,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        let bounds = Self::bounds(filters);
+        let mut blocks = latest_blocks(&self.reader);
+        blocks.retain(|&(key, _)| bounds.may_contain_block(key));
+
+        // Contiguous runs of blocks, so partitions stay spatially compact
+        let partitions = state.config().target_partitions().max(1);
+        let per_partition = blocks.len().div_ceil(partitions).max(1);
+        let streams: Vec<Arc<dyn PartitionStream>> = blocks
+            .chunks(per_partition)
+            .map(|blocks| {
+                Arc::new(BlockPartition {
+                    reader: Arc::clone(&self.reader),
+                    schema: Arc::clone(&self.schema),
+                    bounds,
+                    blocks: blocks.to_vec(),
+                }) as Arc<dyn PartitionStream>
+            })
+            .collect();
+        Ok(Arc::new(StreamingTableExec::try_new(
+            Arc::clone(&self.schema),
+            streams,
+            projection,
+            [],
+            false,
+            limit,
+        )?))
+    }
+}
+
+/// Blocks scanned by one partition of a query
+struct BlockPartition {
+    reader: Arc<ContainerReaderV2>,
+    schema: SchemaRef,
+    bounds: CellBounds,
+    /// Block keys with the frame holding each
+    blocks: Vec<(Index64, usize)>,
+}
+
+impl fmt::Debug for BlockPartition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlockPartition")
+            .field("blocks", &self.blocks.len())
+            .finish()
+    }
+}
+
+impl PartitionStream for BlockPartition {
+    fn schema(&self) -> &SchemaRef {
+        &self.schema
+    }
+
+    fn execute(&self, _ctx: Arc<TaskContext>) -> SendableRecordBatchStream {
+        let (reader, schema, bounds) = (
+            Arc::clone(&self.reader),
+            Arc::clone(&self.schema),
+            self.bounds,
+        );
+        let batches = self.blocks.clone().into_iter().map(move |(key, frame)| {
+            block_batch(&reader, &schema, &bounds, key, frame)
+                .map_err(|e| DataFusionError::External(Box::new(e)))
+        });
+        Box::pin(RecordBatchStreamAdapter::new(
+            Arc::clone(&self.schema),
+            futures::stream::iter(batches),
+        ))
+    }
+}
+
+/// Columns of a cell table
+fn schema() -> SchemaRef {
+    let mut fields = vec![
+        Field::new("id", DataType::UInt64, false),
+        Field::new("frame", DataType::UInt8, false),
+        Field::new("tier", DataType::UInt8, false),
+        Field::new("lod", DataType::UInt8, false),
+        Field::new("x", DataType::UInt16, false),
+        Field::new("y", DataType::UInt16, false),
+        Field::new("z", DataType::UInt16, false),
+        Field::new("morton", DataType::UInt64, false),
+    ];
+    fields.extend(
+        LAYER_COLUMNS
+            .iter()
+            .map(|(_, name)| Field::new(*name, DataType::Float32, true)),
+    );
+    Arc::new(Schema::new(fields))
+}
+
+/// Rows of the cells of one block inside `bounds`
+fn block_batch(
+    reader: &ContainerReaderV2,
+    schema: &SchemaRef,
+    bounds: &CellBounds,
+    key: Index64,
+    frame: usize,
+) -> Result<RecordBatch> {
+    let rows = match CellBlock::decode(key, &reader.frame_data(frame)?)? {
+        Some(block) => {
+            let columns: Vec<Option<usize>> = LAYER_COLUMNS
+                .iter()
+                .map(|(layer_type, _)| block.layers.iter().position(|l| l == layer_type))
+                .collect();
+            block
+                .rows
+                .into_iter()
+                .filter(|(idx, _)| bounds.contains(*idx))
+                .map(|(idx, values)| {
+                    let values = columns
+                        .iter()
+                        .map(|column| column.map(|c| values[c]).filter(|value| !value.is_nan()));
+                    (idx, values.collect::<Vec<_>>())
+                })
+                .collect()
+        }
+        None => Vec::new(),
+    };
+
+    let cells = |f: fn(&Index64) -> u64| rows.iter().map(|(idx, _)| f(idx)).collect::<Vec<_>>();
+    let coords: Vec<(u16, u16, u16)> = rows.iter().map(|(idx, _)| idx.decode_coords()).collect();
+    let mut columns: Vec<ArrayRef> = vec![
+        Arc::new(UInt64Array::from(cells(|idx| idx.raw()))),
+        Arc::new(UInt8Array::from_iter_values(
+            rows.iter().map(|(idx, _)| idx.frame_id()),
+        )),
+        Arc::new(UInt8Array::from_iter_values(
+            rows.iter().map(|(idx, _)| idx.scale_tier()),
+        )),
+        Arc::new(UInt8Array::from_iter_values(
+            rows.iter().map(|(idx, _)| idx.lod()),
+        )),
+        Arc::new(UInt16Array::from_iter_values(coords.iter().map(|c| c.0))),
+        Arc::new(UInt16Array::from_iter_values(coords.iter().map(|c| c.1))),
+        Arc::new(UInt16Array::from_iter_values(coords.iter().map(|c| c.2))),
+        Arc::new(UInt64Array::from(cells(|idx| idx.morton()))),
+    ];
+    for layer in 0..LAYER_COLUMNS.len() {
+        columns.push(Arc::new(Float32Array::from_iter(
+            rows.iter().map(|(_, values)| values[layer]),
+        )));
+    }
+    RecordBatch::try_new(Arc::clone(schema), columns)
+        .map_err(|e| crate::error::Error::InvalidFormat(e.to_string()))
+}
+
+/// A filter `column <op> literal` the scan can prune by, with the operator
+/// code of [`BoundsBuilder::narrow`]
+fn comparison(filter: &Expr) -> Option<(BoundColumn, char, f64)> {
+    let Expr::BinaryExpr(BinaryExpr { left, op, right }) = filter else {
+        return None;
+    };
+    let (column, op, literal) = match (column_of(left), column_of(right)) {
+        (Some(column), None) => (column, *op, right),
+        // Literal on the left: mirror the comparison
+        (None, Some(column)) => (column, op.swap()?, left),
+        _ => return None,
+    };
+    let Expr::Literal(value, _) = literal.as_ref() else {
+        return None;
+    };
+    let v = match value.cast_to(&DataType::Float64).ok()? {
+        ScalarValue::Float64(v) => v.unwrap_or(f64::NAN),
+        _ => return None,
+    };
+    let code = match op {
+        Operator::Eq => '=',
+        Operator::Lt => '<',
+        Operator::LtEq => 'l',
+        Operator::Gt => '>',
+        Operator::GtEq => 'g',
+        _ => return None,
+    };
+    // Frames and LODs only bound the scan when fixed to one value
+    match column {
+        BoundColumn::Frame | BoundColumn::Lod if code != '=' => None,
+        _ => Some((column, code, v)),
+    }
+}
+
+/// Prunable column named by an expression, looking through casts
+fn column_of(expr: &Expr) -> Option<BoundColumn> {
+    match expr {
+        Expr::Column(column) => Some(match column.name.as_str() {
+            "frame" => BoundColumn::Frame,
+            "lod" => BoundColumn::Lod,
+            "x" => BoundColumn::Coord(0),
+            "y" => BoundColumn::Coord(1),
+            "z" => BoundColumn::Coord(2),
+            "morton" => BoundColumn::Morton,
+            _ => return None,
+        }),
+        Expr::Cast(cast) => column_of(&cast.expr),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::container_v2::{ContainerWriterV2, StreamConfig};
+    use crate::layers::{LayeredMap, OccupancyLayer, StaticMap, TSDFLayer};
+    use datafusion::arrow::array::{Array, AsArray};
+    use datafusion::arrow::datatypes::Int64Type;
+    use datafusion::prelude::{col, lit, SessionContext};
+
+    fn container() -> ContainerReaderV2 {
+        let mut occupancy = OccupancyLayer::new();
+        let mut tsdf = TSDFLayer::new(0.1);
+        for x in 0..32u16 {
+            for y in 0..32u16 {
+                // BCC points only: all even or all odd
+                let z = x % 2;
+                if y % 2 != z {
+                    continue;
+                }
+                let idx = Index64::new(0, 0, 10, x, y, z).unwrap();
+                occupancy.update_occupancy(idx, x < 16, 0.8);
+                if y < 8 {
+                    tsdf.batch_update(&[(idx, 0.01, 1.0)]).unwrap();
+                }
+            }
+        }
+        let mut map = LayeredMap::new();
+        map.add_occupancy_layer(occupancy);
+        map.add_tsdf_layer(tsdf);
+        let mut bytes = std::io::Cursor::new(Vec::new());
+        let mut writer = ContainerWriterV2::new(&mut bytes, StreamConfig::default()).unwrap();
+        StaticMap::from(&map).write_container(&mut writer).unwrap();
+        writer.finish().unwrap();
+        ContainerReaderV2::from_bytes(bytes.into_inner()).unwrap()
+    }
+
+    fn query(ctx: &SessionContext, sql: &str) -> Vec<RecordBatch> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime
+            .block_on(async { ctx.sql(sql).await?.collect().await })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_sql_with_pruned_scan() {
+        let ctx = SessionContext::new();
+        let table = Arc::new(CellTableProvider::new(container()));
+        ctx.register_table("cells", table).unwrap();
+
+        let count = |sql: &str| {
+            let batches = query(&ctx, sql);
+            batches[0].column(0).as_primitive::<Int64Type>().value(0)
+        };
+        assert_eq!(count("SELECT count(*) FROM cells"), 512);
+        assert_eq!(count("SELECT count(tsdf) FROM cells"), 128);
+        assert_eq!(
+            count("SELECT count(*) FROM cells WHERE x >= 6 AND x <= 9 AND 9 >= y AND y > 5"),
+            8
+        );
+        assert_eq!(
+            count("SELECT count(*) FROM cells WHERE lod = 10 AND occupancy > 0.5"),
+            256
+        );
+        assert_eq!(count("SELECT count(*) FROM cells WHERE lod = 9"), 0);
+
+        let rows = query(
+            &ctx,
+            "SELECT x, y, z, tsdf, esdf FROM cells WHERE morton = 0",
+        );
+        assert_eq!(rows[0].num_rows(), 1);
+        assert!(rows[0].column(3).is_valid(0) && rows[0].column(4).is_null(0));
+
+        // Pushed filters prune whole blocks
+        let reader = container();
+        let all = latest_blocks(&reader).len();
+        let bounds = CellTableProvider::bounds(&[
+            col("x").lt_eq(lit(9)),
+            lit(6).lt_eq(col("x")),
+            col("occupancy").gt(lit(0.5)),
+            col("frame").gt(lit(0)),
+        ]);
+        assert_eq!(bounds.coords, Some(([6, 0, 0], [9, u16::MAX, u16::MAX])));
+        assert_eq!(bounds.frame, None);
+        let kept = latest_blocks(&reader)
+            .iter()
+            .filter(|(key, _)| bounds.may_contain_block(*key))
+            .count();
+        assert!(kept < all);
+        assert!(CellTableProvider::bounds(&[col("lod").eq(lit(20))]).is_empty());
+    }
+}
@@ -35,6 +35,23 @@ pub enum Error {
     #[error("Frame conflict: frame {0} already registered with different descriptor")]
     FrameConflict(u8),
 
+    /// Frames without a common root frame
+    #[error("No transform from frame {from} to frame {to}")]
+    NoFrameTransform {
+        /// Source frame
+        from: u8,
+        /// Target frame
+        to: u8,
+    },
+
+    /// Time-independent query of a frame that moves over time
+    #[error("Frame {0} moves; query it at a timestamp")]
+    MovingFrame(u8),
+
+    /// Frame parent change that would make a frame its own ancestor
+    #[error("Frame {0} would become its own ancestor")]
+    FrameCycle(u8),
+
     /// Invalid LOD (level of detail) value
     #[error("Invalid LOD: {0}")]
     InvalidLOD(String),
@@ -146,6 +163,48 @@ pub enum Error {
     /// SHA-256 hash mismatch
     #[error("SHA-256 mismatch")]
     Sha256Mismatch,
+
+    /// No map registered under a name
+    #[error("No map named {0}")]
+    UnknownMap(String),
+
+    /// A map is already registered under a name
+    #[error("A map named {0} is already registered")]
+    DuplicateMap(String),
+
+    /// A map uses more than its quota allows
+    #[error("Map {name} is over its quota: {detail}")]
+    QuotaExceeded {
+        /// Map name
+        name: String,
+        /// The limit exceeded
+        detail: String,
+    },
+
+    /// A map handle does not grant an access
+    #[error("Access denied: {0}")]
+    AccessDenied(String),
+
+    /// No landmark matches a name
+    #[error("No landmark matches {0}")]
+    UnknownLandmark(String),
+
+    /// Several landmarks match a name equally well
+    #[error("Landmark name {query} is ambiguous: {candidates:?}")]
+    AmbiguousLandmark {
+        /// Name looked up
+        query: String,
+        /// Names of the equally good matches
+        candidates: Vec<String>,
+    },
+
+    /// No submap has an id
+    #[error("No submap {0}")]
+    UnknownSubmap(u32),
+
+    /// A submap frame has no pose in a parent frame
+    #[error("Submap frame {0} has no parent pose")]
+    UnanchoredSubmap(String),
 }
 
 impl From<std::io::Error> for Error {
@@ -189,3 +248,11 @@ impl From<serde_json::Error> for Error {
         Error::Io(err.to_string())
     }
 }
+
+#[cfg(feature = "kv_redb")]
+impl From<redb::Error> for Error {
+    /// Convert from redb error
+    fn from(err: redb::Error) -> Self {
+        Error::Io(err.to_string())
+    }
+}
@@ -0,0 +1,308 @@
+//! Spatio-temporal indexing of discrete events
+//!
+//! [`EventIndex`] stores point events (detections, lightning strikes, ship
+//! positions, ...) under `(cell, time bucket)` keys. Keys sort by cell first,
+//! so a box query becomes a few Morton range scans (see
+//! [`Index64::box_ranges`]), and within each cell the time buckets bound
+//! the scan to the queried interval.
+
+use crate::error::{Error, Result};
+use crate::ids::{FrameId, Index64};
+use crate::neighbors::CoordBox;
+use std::collections::BTreeMap;
+use std::ops::Bound;
+
+/// A point event
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event<T> {
+    /// Cell containing the event
+    pub cell: Index64,
+    /// Event time (seconds in any consistent epoch)
+    pub time: f64,
+    /// User data
+    pub payload: T,
+}
+
+/// Index of point events by cell and time
+///
+/// # Example
+///
+/// ```
+/// use octaindex3d::events::EventIndex;
+/// use octaindex3d::Index64;
+///
+/// # fn main() -> octaindex3d::Result<()> {
+/// // One-minute buckets
+/// let mut strikes = EventIndex::new(60.0)?;
+/// strikes.insert(Index64::new(0, 0, 8, 100, 100, 2)?, 30.0, "cg")?;
+/// strikes.insert(Index64::new(0, 0, 8, 104, 101, 3)?, 95.0, "ic")?;
+/// strikes.insert(Index64::new(0, 0, 8, 900, 900, 2)?, 40.0, "cg")?;
+///
+/// let nearby = strikes.query_box(0, 0, 8, ((90, 90, 0), (110, 110, 10)), 0.0, 120.0)?;
+/// assert_eq!(nearby.len(), 2);
+/// assert_eq!(nearby[0].payload, "cg");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct EventIndex<T> {
+    bucket_width: f64,
+    buckets: BTreeMap<(Index64, i64), Vec<Event<T>>>,
+    len: usize,
+}
+
+impl<T> EventIndex<T> {
+    /// Create an empty index with time buckets of `bucket_width` seconds
+    ///
+    /// Pick a width near the typical query interval: much narrower makes
+    /// long queries visit many buckets, much wider makes short queries
+    /// filter many events.
+    pub fn new(bucket_width: f64) -> Result<Self> {
+        if !bucket_width.is_finite() || bucket_width <= 0.0 {
+            return Err(Error::OutOfRange(format!(
+                "bucket width must be positive and finite, got {}",
+                bucket_width
+            )));
+        }
+        Ok(Self {
+            bucket_width,
+            buckets: BTreeMap::new(),
+            len: 0,
+        })
+    }
+
+    /// Time bucket width in seconds
+    pub fn bucket_width(&self) -> f64 {
+        self.bucket_width
+    }
+
+    /// Add an event
+    pub fn insert(&mut self, cell: Index64, time: f64, payload: T) -> Result<()> {
+        if !time.is_finite() {
+            return Err(Error::OutOfRange(format!(
+                "event time must be finite, got {}",
+                time
+            )));
+        }
+        let key = (cell, self.bucket(time));
+        self.buckets.entry(key).or_default().push(Event {
+            cell,
+            time,
+            payload,
+        });
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Events in one cell with `t0 <= time <= t1`, oldest first
+    pub fn query_cell(&self, cell: Index64, t0: f64, t1: f64) -> Vec<&Event<T>> {
+        let mut hits = Vec::new();
+        if t0 <= t1 {
+            self.scan(cell..=cell, t0, t1, |_| true, &mut hits);
+        }
+        sort_by_time(&mut hits);
+        hits
+    }
+
+    /// Events in any of `cells` with `t0 <= time <= t1`, oldest first
+    ///
+    /// Cells are matched exactly, so give them at the LOD events were
+    /// inserted at.
+    pub fn query_cells<I>(&self, cells: I, t0: f64, t1: f64) -> Vec<&Event<T>>
+    where
+        I: IntoIterator<Item = Index64>,
+    {
+        let mut cells: Vec<Index64> = cells.into_iter().collect();
+        cells.sort_unstable();
+        cells.dedup();
+        let mut hits = Vec::new();
+        if t0 <= t1 {
+            for cell in cells {
+                self.scan(cell..=cell, t0, t1, |_| true, &mut hits);
+            }
+        }
+        sort_by_time(&mut hits);
+        hits
+    }
+
+    /// Events in an inclusive coordinate box with `t0 <= time <= t1`,
+    /// oldest first
+    pub fn query_box(
+        &self,
+        frame: FrameId,
+        tier: u8,
+        lod: u8,
+        bounds: CoordBox,
+        t0: f64,
+        t1: f64,
+    ) -> Result<Vec<&Event<T>>> {
+        let (min, max) = bounds;
+        let inside = |cell: Index64| {
+            let (x, y, z) = cell.decode_coords();
+            (min.0..=max.0).contains(&x)
+                && (min.1..=max.1).contains(&y)
+                && (min.2..=max.2).contains(&z)
+        };
+        let mut hits = Vec::new();
+        if t0 <= t1 {
+            for range in Index64::box_ranges(frame, tier, lod, min, max, 64)? {
+                self.scan(range, t0, t1, inside, &mut hits);
+            }
+        }
+        sort_by_time(&mut hits);
+        Ok(hits)
+    }
+
+    /// Number of events in an inclusive coordinate box and time interval
+    pub fn count_box(
+        &self,
+        frame: FrameId,
+        tier: u8,
+        lod: u8,
+        bounds: CoordBox,
+        t0: f64,
+        t1: f64,
+    ) -> Result<usize> {
+        Ok(self.query_box(frame, tier, lod, bounds, t0, t1)?.len())
+    }
+
+    /// Drop events before `t`, returning how many were removed
+    pub fn remove_before(&mut self, t: f64) -> usize {
+        let cutoff = self.bucket(t);
+        let mut removed = 0;
+        self.buckets.retain(|&(_, key), events| {
+            if key < cutoff {
+                removed += events.len();
+                return false;
+            }
+            if key == cutoff {
+                let before = events.len();
+                events.retain(|e| e.time >= t);
+                removed += before - events.len();
+            }
+            !events.is_empty()
+        });
+        self.len -= removed;
+        removed
+    }
+
+    /// Number of events
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Check if the index holds no events
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Iterate over all events, by cell then time bucket
+    pub fn iter(&self) -> impl Iterator<Item = &Event<T>> + '_ {
+        self.buckets.values().flatten()
+    }
+
+    fn bucket(&self, time: f64) -> i64 {
+        (time / self.bucket_width).floor() as i64
+    }
+
+    /// Collect events of cells in `cells` passing `keep` within `[t0, t1]`
+    fn scan<'a, F>(
+        &'a self,
+        cells: std::ops::RangeInclusive<Index64>,
+        t0: f64,
+        t1: f64,
+        keep: F,
+        hits: &mut Vec<&'a Event<T>>,
+    ) where
+        F: Fn(Index64) -> bool,
+    {
+        let (k0, k1) = (self.bucket(t0), self.bucket(t1));
+        let mut lower = Bound::Included((*cells.start(), k0));
+        while let Some((&(cell, key), events)) =
+            self.buckets.range((lower, Bound::Unbounded)).next()
+        {
+            if cell > *cells.end() {
+                break;
+            }
+            if key < k0 {
+                // Jump over this cell's earlier buckets
+                lower = Bound::Included((cell, k0));
+                continue;
+            }
+            if key > k1 {
+                // Jump to the next cell
+                lower = Bound::Excluded((cell, i64::MAX));
+                continue;
+            }
+            if keep(cell) {
+                hits.extend(events.iter().filter(|e| e.time >= t0 && e.time <= t1));
+            }
+            lower = Bound::Excluded((cell, key));
+        }
+    }
+}
+
+fn sort_by_time<T>(hits: &mut [&Event<T>]) {
+    hits.sort_by(|a, b| a.time.total_cmp(&b.time).then(a.cell.cmp(&b.cell)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(x: u16, y: u16, z: u16) -> Index64 {
+        Index64::new(0, 0, 6, x, y, z).unwrap()
+    }
+
+    #[test]
+    fn test_spatio_temporal_queries() -> Result<()> {
+        let mut index = EventIndex::new(10.0)?;
+        for i in 0..100u16 {
+            // A ship moving along x, one fix per second
+            index.insert(cell(i, 50, 0), i as f64, i)?;
+        }
+        index.insert(cell(20, 50, 0), 500.0, 999)?;
+        index.insert(cell(20, 80, 0), 25.0, 1000)?;
+        assert_eq!(index.len(), 102);
+
+        // Box x in 10..=30, y = 50, first minute
+        let hits = index.query_box(0, 0, 6, ((10, 50, 0), (30, 50, 0)), 0.0, 59.0)?;
+        let ids: Vec<u16> = hits.iter().map(|e| e.payload).collect();
+        assert_eq!(ids, (10..=30).collect::<Vec<_>>());
+
+        // Narrow time window inside the box
+        let hits = index.query_box(0, 0, 6, ((0, 0, 0), (99, 99, 0)), 24.5, 25.5)?;
+        let ids: Vec<u16> = hits.iter().map(|e| e.payload).collect();
+        assert_eq!(ids, vec![25, 1000]);
+        assert_eq!(
+            index.count_box(0, 0, 6, ((0, 0, 0), (99, 99, 0)), 0.0, 1e9)?,
+            102
+        );
+        assert!(index
+            .query_box(0, 0, 6, ((0, 0, 0), (99, 99, 0)), 9.0, 1.0)?
+            .is_empty());
+
+        let at = index.query_cell(cell(20, 50, 0), 0.0, 1000.0);
+        assert_eq!(
+            at.iter().map(|e| e.payload).collect::<Vec<_>>(),
+            vec![20, 999]
+        );
+        let some = index.query_cells([cell(5, 50, 0), cell(20, 80, 0)], 0.0, 100.0);
+        assert_eq!(some.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_retention() -> Result<()> {
+        let mut index = EventIndex::new(10.0)?;
+        for t in 0..30 {
+            index.insert(cell(1, 1, 1), t as f64, ())?;
+        }
+        assert_eq!(index.remove_before(15.0), 15);
+        assert_eq!(index.len(), 15);
+        assert_eq!(index.iter().map(|e| e.time).fold(f64::MAX, f64::min), 15.0);
+        assert!(index.insert(cell(1, 1, 1), f64::NAN, ()).is_err());
+        assert!(EventIndex::<()>::new(0.0).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,548 @@
+//! C ABI for core ID and neighbor operations
+//!
+//! Enabled with the `ffi` feature. Build a shared library with
+//!
+//! ```text
+//! cargo rustc --release --features ffi --crate-type cdylib
+//! ```
+//!
+//! and include `include/octaindex3d.h`, generated from this module by
+//! cbindgen during the build.
+//!
+//! IDs cross the boundary as their raw `uint64_t` values. Every function
+//! returns an `OI3D_*` status code and writes results through out
+//! pointers, which are left untouched on failure; [`oi3d_status_message`]
+//! describes a code. No function allocates memory the caller must free.
+
+use crate::error::Error;
+use crate::ids::{Index64, Route64};
+use crate::neighbors::{neighbors_index64, neighbors_route64};
+use std::ffi::{c_char, CStr};
+
+/// Success
+pub const OI3D_OK: i32 = 0;
+/// A required pointer argument was null
+pub const OI3D_ERR_NULL_POINTER: i32 = -1;
+/// A coordinate, tier or LOD is outside its valid range
+pub const OI3D_ERR_OUT_OF_RANGE: i32 = -2;
+/// Coordinates do not share the same parity (not a BCC lattice point)
+pub const OI3D_ERR_INVALID_PARITY: i32 = -3;
+/// A raw value or string is not a valid encoded ID
+pub const OI3D_ERR_DECODE: i32 = -4;
+/// The output buffer is too small; see `OI3D_BECH32M_BUFFER_LEN`
+pub const OI3D_ERR_BUFFER_TOO_SMALL: i32 = -5;
+/// The cell has no parent (LOD 0) or no children (LOD 15)
+pub const OI3D_ERR_NO_RELATIVE: i32 = -6;
+/// Any other failure
+pub const OI3D_ERR_OTHER: i32 = -99;
+
+/// Maximum number of neighbors of a cell
+pub const OI3D_NEIGHBOR_COUNT: usize = 14;
+/// Number of children of a cell
+pub const OI3D_CHILD_COUNT: usize = 8;
+/// Buffer size that fits any bech32m ID string and its terminating NUL
+pub const OI3D_BECH32M_BUFFER_LEN: usize = 64;
+
+fn status(error: &Error) -> i32 {
+    match error {
+        Error::InvalidParity { .. } => OI3D_ERR_INVALID_PARITY,
+        Error::OutOfRange(_)
+        | Error::CoordinateOverflow
+        | Error::InvalidLOD(_)
+        | Error::InvalidScaleTier(_) => OI3D_ERR_OUT_OF_RANGE,
+        Error::InvalidBech32 { .. } | Error::Bech32Error(_) | Error::DecodingError(_) => {
+            OI3D_ERR_DECODE
+        }
+        Error::NoParent | Error::NoChildren => OI3D_ERR_NO_RELATIVE,
+        _ => OI3D_ERR_OTHER,
+    }
+}
+
+/// Run `f` and store its value in `out`, translating errors to status codes
+///
+/// # Safety
+///
+/// `out` must be null or valid for writes.
+unsafe fn write_result<T, F>(out: *mut T, f: F) -> i32
+where
+    F: FnOnce() -> crate::Result<T>,
+{
+    if out.is_null() {
+        return OI3D_ERR_NULL_POINTER;
+    }
+    match f() {
+        Ok(value) => {
+            out.write(value);
+            OI3D_OK
+        }
+        Err(e) => status(&e),
+    }
+}
+
+/// Copy IDs into a caller buffer, reporting the count through `out_len`
+///
+/// # Safety
+///
+/// `out` must be valid for `capacity` writes and `out_len` for one write.
+unsafe fn write_ids(ids: &[u64], out: *mut u64, capacity: usize, out_len: *mut usize) -> i32 {
+    if out.is_null() || out_len.is_null() {
+        return OI3D_ERR_NULL_POINTER;
+    }
+    out_len.write(ids.len());
+    if ids.len() > capacity {
+        return OI3D_ERR_BUFFER_TOO_SMALL;
+    }
+    std::ptr::copy_nonoverlapping(ids.as_ptr(), out, ids.len());
+    OI3D_OK
+}
+
+/// Copy a string into a caller buffer with a terminating NUL
+///
+/// # Safety
+///
+/// `buf` must be valid for `capacity` writes.
+unsafe fn write_str(text: crate::Result<String>, buf: *mut c_char, capacity: usize) -> i32 {
+    if buf.is_null() {
+        return OI3D_ERR_NULL_POINTER;
+    }
+    let text = match text {
+        Ok(text) => text,
+        Err(e) => return status(&e),
+    };
+    if text.len() + 1 > capacity {
+        return OI3D_ERR_BUFFER_TOO_SMALL;
+    }
+    std::ptr::copy_nonoverlapping(text.as_ptr().cast::<c_char>(), buf, text.len());
+    buf.add(text.len()).write(0);
+    OI3D_OK
+}
+
+/// Borrow a NUL-terminated UTF-8 string
+///
+/// # Safety
+///
+/// `s` must be null or point to a NUL-terminated string.
+unsafe fn read_str<'a>(s: *const c_char) -> Result<&'a str, i32> {
+    if s.is_null() {
+        return Err(OI3D_ERR_NULL_POINTER);
+    }
+    CStr::from_ptr(s).to_str().map_err(|_| OI3D_ERR_DECODE)
+}
+
+/// Library version as a static NUL-terminated string
+#[no_mangle]
+pub extern "C" fn oi3d_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr().cast()
+}
+
+/// Static NUL-terminated description of a status code
+#[no_mangle]
+pub extern "C" fn oi3d_status_message(status: i32) -> *const c_char {
+    let message: &'static str = match status {
+        OI3D_OK => "ok\0",
+        OI3D_ERR_NULL_POINTER => "null pointer argument\0",
+        OI3D_ERR_OUT_OF_RANGE => "value out of range\0",
+        OI3D_ERR_INVALID_PARITY => "coordinates must all have the same parity\0",
+        OI3D_ERR_DECODE => "invalid encoded ID\0",
+        OI3D_ERR_BUFFER_TOO_SMALL => "output buffer too small\0",
+        OI3D_ERR_NO_RELATIVE => "cell has no parent or children at this LOD\0",
+        OI3D_ERR_OTHER => "internal error\0",
+        _ => "unknown status code\0",
+    };
+    message.as_ptr().cast()
+}
+
+// =============================================================================
+// Index64
+// =============================================================================
+
+/// Encode an Index64 from frame, scale tier (0-3), LOD (0-15) and coordinates
+///
+/// # Safety
+///
+/// `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn oi3d_index64_new(
+    frame: u8,
+    tier: u8,
+    lod: u8,
+    x: u16,
+    y: u16,
+    z: u16,
+    out: *mut u64,
+) -> i32 {
+    write_result(out, || Ok(Index64::new(frame, tier, lod, x, y, z)?.raw()))
+}
+
+/// Decode an Index64 into its fields; any out pointer may be null to skip it
+///
+/// # Safety
+///
+/// Non-null pointers must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn oi3d_index64_decode(
+    value: u64,
+    frame: *mut u8,
+    tier: *mut u8,
+    lod: *mut u8,
+    x: *mut u16,
+    y: *mut u16,
+    z: *mut u16,
+) -> i32 {
+    let index = match Index64::from_value(value) {
+        Ok(index) => index,
+        Err(e) => return status(&e),
+    };
+    let (cx, cy, cz) = index.decode_coords();
+    for (ptr, v) in [
+        (frame, index.frame_id()),
+        (tier, index.scale_tier()),
+        (lod, index.lod()),
+    ] {
+        if !ptr.is_null() {
+            ptr.write(v);
+        }
+    }
+    for (ptr, v) in [(x, cx), (y, cy), (z, cz)] {
+        if !ptr.is_null() {
+            ptr.write(v);
+        }
+    }
+    OI3D_OK
+}
+
+/// Parent of an Index64 (one LOD coarser)
+///
+/// # Safety
+///
+/// `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn oi3d_index64_parent(value: u64, out: *mut u64) -> i32 {
+    write_result(out, || {
+        Index64::from_value(value)?
+            .parent()
+            .map(|p| p.raw())
+            .ok_or(Error::NoParent)
+    })
+}
+
+/// The `OI3D_CHILD_COUNT` children of an Index64 (one LOD finer)
+///
+/// # Safety
+///
+/// `out` must be valid for `OI3D_CHILD_COUNT` writes.
+#[no_mangle]
+pub unsafe extern "C" fn oi3d_index64_children(value: u64, out: *mut u64) -> i32 {
+    if out.is_null() {
+        return OI3D_ERR_NULL_POINTER;
+    }
+    let children = match Index64::from_value(value) {
+        Ok(index) => index.children(),
+        Err(e) => return status(&e),
+    };
+    if children.len() != OI3D_CHILD_COUNT {
+        return OI3D_ERR_NO_RELATIVE;
+    }
+    for (i, child) in children.iter().enumerate() {
+        out.add(i).write(child.raw());
+    }
+    OI3D_OK
+}
+
+/// BCC neighbors of an Index64 within the 16-bit coordinate range
+///
+/// Writes up to `capacity` IDs to `out` and the neighbor count to
+/// `out_len`; a capacity of `OI3D_NEIGHBOR_COUNT` always suffices.
+///
+/// # Safety
+///
+/// `out` must be valid for `capacity` writes and `out_len` for one write.
+#[no_mangle]
+pub unsafe extern "C" fn oi3d_index64_neighbors(
+    value: u64,
+    out: *mut u64,
+    capacity: usize,
+    out_len: *mut usize,
+) -> i32 {
+    let index = match Index64::from_value(value) {
+        Ok(index) => index,
+        Err(e) => return status(&e),
+    };
+    let ids: Vec<u64> = neighbors_index64(index).iter().map(Index64::raw).collect();
+    write_ids(&ids, out, capacity, out_len)
+}
+
+/// Write the bech32m string of an Index64, NUL-terminated
+///
+/// # Safety
+///
+/// `buf` must be valid for `capacity` writes.
+#[no_mangle]
+pub unsafe extern "C" fn oi3d_index64_to_bech32m(
+    value: u64,
+    buf: *mut c_char,
+    capacity: usize,
+) -> i32 {
+    write_str(
+        Index64::from_value(value).and_then(|index| index.to_bech32m()),
+        buf,
+        capacity,
+    )
+}
+
+/// Parse an Index64 from its bech32m string
+///
+/// # Safety
+///
+/// `s` must be a NUL-terminated string and `out` valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn oi3d_index64_from_bech32m(s: *const c_char, out: *mut u64) -> i32 {
+    match read_str(s) {
+        Ok(s) => write_result(out, || Ok(Index64::from_bech32m(s)?.raw())),
+        Err(code) => code,
+    }
+}
+
+// =============================================================================
+// Route64
+// =============================================================================
+
+/// Encode a Route64 from scale tier (0-3) and signed BCC coordinates
+///
+/// # Safety
+///
+/// `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn oi3d_route64_new(tier: u8, x: i32, y: i32, z: i32, out: *mut u64) -> i32 {
+    write_result(out, || Ok(Route64::new(tier, x, y, z)?.raw()))
+}
+
+/// Decode a Route64 into its fields; any out pointer may be null to skip it
+///
+/// # Safety
+///
+/// Non-null pointers must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn oi3d_route64_decode(
+    value: u64,
+    tier: *mut u8,
+    x: *mut i32,
+    y: *mut i32,
+    z: *mut i32,
+) -> i32 {
+    let route = match Route64::from_value(value) {
+        Ok(route) => route,
+        Err(e) => return status(&e),
+    };
+    if !tier.is_null() {
+        tier.write(route.scale_tier());
+    }
+    for (ptr, v) in [(x, route.x()), (y, route.y()), (z, route.z())] {
+        if !ptr.is_null() {
+            ptr.write(v);
+        }
+    }
+    OI3D_OK
+}
+
+/// BCC neighbors of a Route64 within the coordinate range
+///
+/// Same buffer contract as `oi3d_index64_neighbors`.
+///
+/// # Safety
+///
+/// `out` must be valid for `capacity` writes and `out_len` for one write.
+#[no_mangle]
+pub unsafe extern "C" fn oi3d_route64_neighbors(
+    value: u64,
+    out: *mut u64,
+    capacity: usize,
+    out_len: *mut usize,
+) -> i32 {
+    let route = match Route64::from_value(value) {
+        Ok(route) => route,
+        Err(e) => return status(&e),
+    };
+    let ids: Vec<u64> = neighbors_route64(route).iter().map(Route64::raw).collect();
+    write_ids(&ids, out, capacity, out_len)
+}
+
+/// Write the bech32m string of a Route64, NUL-terminated
+///
+/// # Safety
+///
+/// `buf` must be valid for `capacity` writes.
+#[no_mangle]
+pub unsafe extern "C" fn oi3d_route64_to_bech32m(
+    value: u64,
+    buf: *mut c_char,
+    capacity: usize,
+) -> i32 {
+    write_str(
+        Route64::from_value(value).and_then(|route| route.to_bech32m()),
+        buf,
+        capacity,
+    )
+}
+
+/// Parse a Route64 from its bech32m string
+///
+/// # Safety
+///
+/// `s` must be a NUL-terminated string and `out` valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn oi3d_route64_from_bech32m(s: *const c_char, out: *mut u64) -> i32 {
+    match read_str(s) {
+        Ok(s) => write_result(out, || Ok(Route64::from_bech32m(s)?.raw())),
+        Err(code) => code,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ptr::null_mut;
+
+    #[test]
+    fn test_index64_roundtrip() {
+        unsafe {
+            let mut raw = 0u64;
+            assert_eq!(oi3d_index64_new(3, 1, 9, 100, 200, 300, &mut raw), OI3D_OK);
+            let (mut lod, mut x, mut z) = (0u8, 0u16, 0u16);
+            let code = oi3d_index64_decode(
+                raw,
+                null_mut(),
+                null_mut(),
+                &mut lod,
+                &mut x,
+                null_mut(),
+                &mut z,
+            );
+            assert_eq!((code, lod, x, z), (OI3D_OK, 9, 100, 300));
+            assert_eq!(
+                oi3d_index64_new(0, 4, 0, 0, 0, 0, &mut raw),
+                OI3D_ERR_OUT_OF_RANGE
+            );
+            assert_eq!(
+                oi3d_index64_decode(
+                    0,
+                    null_mut(),
+                    null_mut(),
+                    null_mut(),
+                    null_mut(),
+                    null_mut(),
+                    null_mut()
+                ),
+                OI3D_ERR_DECODE
+            );
+
+            let index = Index64::new(0, 0, 5, 10, 10, 10).unwrap();
+            let mut buf = [0 as c_char; OI3D_BECH32M_BUFFER_LEN];
+            assert_eq!(
+                oi3d_index64_to_bech32m(index.raw(), buf.as_mut_ptr(), buf.len()),
+                OI3D_OK
+            );
+            let text = CStr::from_ptr(buf.as_ptr()).to_str().unwrap();
+            assert_eq!(text, index.to_bech32m().unwrap());
+            assert_eq!(
+                oi3d_index64_to_bech32m(index.raw(), buf.as_mut_ptr(), text.len()),
+                OI3D_ERR_BUFFER_TOO_SMALL
+            );
+            let mut parsed = 0u64;
+            assert_eq!(
+                oi3d_index64_from_bech32m(buf.as_ptr(), &mut parsed),
+                OI3D_OK
+            );
+            assert_eq!(parsed, index.raw());
+            assert_eq!(
+                oi3d_index64_from_bech32m(c"i3d1oops".as_ptr(), &mut parsed),
+                OI3D_ERR_DECODE
+            );
+
+            let mut parent = 0u64;
+            assert_eq!(oi3d_index64_parent(index.raw(), &mut parent), OI3D_OK);
+            assert_eq!(parent, index.parent().unwrap().raw());
+            let root = Index64::new(0, 0, 0, 1, 1, 1).unwrap();
+            assert_eq!(
+                oi3d_index64_parent(root.raw(), &mut parent),
+                OI3D_ERR_NO_RELATIVE
+            );
+            let mut children = [0u64; OI3D_CHILD_COUNT];
+            assert_eq!(
+                oi3d_index64_children(index.raw(), children.as_mut_ptr()),
+                OI3D_OK
+            );
+            assert!(children
+                .iter()
+                .all(|&c| Index64::from_value(c).unwrap().parent() == Some(index)));
+        }
+    }
+
+    #[test]
+    fn test_neighbors_and_route64() {
+        unsafe {
+            let mut route = 0u64;
+            assert_eq!(
+                oi3d_route64_new(0, 1, 2, 3, &mut route),
+                OI3D_ERR_INVALID_PARITY
+            );
+            assert_eq!(oi3d_route64_new(0, -3, 5, 7, &mut route), OI3D_OK);
+            let (mut x, mut y) = (0i32, 0i32);
+            assert_eq!(
+                oi3d_route64_decode(route, null_mut(), &mut x, &mut y, null_mut()),
+                OI3D_OK
+            );
+            assert_eq!((x, y), (-3, 5));
+
+            let mut out = [0u64; OI3D_NEIGHBOR_COUNT];
+            let mut len = 0usize;
+            assert_eq!(
+                oi3d_route64_neighbors(route, out.as_mut_ptr(), out.len(), &mut len),
+                OI3D_OK
+            );
+            assert_eq!(len, 14);
+            assert_eq!(
+                oi3d_route64_neighbors(route, out.as_mut_ptr(), 4, &mut len),
+                OI3D_ERR_BUFFER_TOO_SMALL
+            );
+            assert_eq!(len, 14);
+
+            // Corner cell: neighbors outside the u16 range are dropped
+            let corner = Index64::new(0, 0, 5, 0, 0, 0).unwrap();
+            assert_eq!(
+                oi3d_index64_neighbors(corner.raw(), out.as_mut_ptr(), out.len(), &mut len),
+                OI3D_OK
+            );
+            assert_eq!(len, neighbors_index64(corner).len());
+            assert!(len < 14);
+            assert_eq!(
+                oi3d_index64_neighbors(corner.raw(), null_mut(), 0, &mut len),
+                OI3D_ERR_NULL_POINTER
+            );
+
+            let mut buf = [0 as c_char; OI3D_BECH32M_BUFFER_LEN];
+            assert_eq!(
+                oi3d_route64_to_bech32m(route, buf.as_mut_ptr(), buf.len()),
+                OI3D_OK
+            );
+            let mut parsed = 0u64;
+            assert_eq!(
+                oi3d_route64_from_bech32m(buf.as_ptr(), &mut parsed),
+                OI3D_OK
+            );
+            assert_eq!(parsed, route);
+            let message = CStr::from_ptr(oi3d_status_message(OI3D_ERR_INVALID_PARITY));
+            assert!(message.to_str().unwrap().contains("parity"));
+        }
+    }
+
+    #[test]
+    fn test_header_up_to_date() {
+        let generated = include_str!(concat!(env!("OUT_DIR"), "/octaindex3d.h"));
+        let committed = include_str!("../include/octaindex3d.h");
+        assert_eq!(
+            committed, generated,
+            "include/octaindex3d.h is stale; copy the header generated in OUT_DIR"
+        );
+    }
+}
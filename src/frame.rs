@@ -1,7 +1,40 @@
 //! Frame registry for coordinate reference systems
+//!
+//! A frame may be placed in a parent frame with a [`FrameTransform`]
+//! (rotation, translation and uniform scale), and moved under another with
+//! [`set_frame_parent`]. Frames connected through parents form a tree;
+//! [`resolve`] composes the poses along the path between any two of its
+//! frames, and [`transform_point`], [`transform_index`] and
+//! [`transform_route`] move data with the result.
+//!
+//! A frame on a moving platform gets a [`FrameTrajectory`] of timestamped
+//! poses relative to its parent, set with [`set_frame_trajectory`] and
+//! extended with [`push_frame_pose`]. It is then queried at a time with
+//! [`get_frame_at`], [`frame_transform_at`] and [`transform_point_at`],
+//! which interpolate between the surrounding samples. Time-independent
+//! queries through a moving frame fail with [`Error::MovingFrame`].
+//!
+//! ```
+//! use octaindex3d::frame::{self, FrameDescriptor, FrameTransform};
+//!
+//! # fn main() -> octaindex3d::Result<()> {
+//! // A robot frame 10 m east of the site origin, turned 90° about z
+//! let site = FrameDescriptor::new("site", "local", "Site grid", true, 1.0);
+//! frame::register_frame(40, site)?;
+//! let pose = FrameTransform::from_axis_angle([0.0, 0.0, 1.0], std::f64::consts::FRAC_PI_2)
+//!     .with_translation([10.0, 0.0, 0.0]);
+//! let robot = FrameDescriptor::new("robot", "local", "Robot body", true, 1.0).with_parent(40, pose);
+//! frame::register_frame(41, robot)?;
+//!
+//! // One meter ahead of the robot is one meter north of it on site
+//! let (x, y, _) = frame::transform_point((1.0, 0.0, 0.0), 41, 40)?;
+//! assert!((x - 10.0).abs() < 1e-12 && (y - 1.0).abs() < 1e-12);
+//! # Ok(())
+//! # }
+//! ```
 
 use crate::error::{Error, Result};
-use crate::ids::FrameId;
+use crate::ids::{FrameId, Index64, Route64};
 use once_cell::sync::Lazy;
 use parking_lot::RwLock;
 use std::collections::HashMap;
@@ -20,6 +53,9 @@ pub struct FrameDescriptor {
     pub right_handed: bool,
     /// Base unit scale at tier 0 (meters)
     pub base_unit: f64,
+    /// Parent frame and the transform from this frame's coordinates into
+    /// the parent's, or `None` for a root frame
+    pub parent: Option<(FrameId, FrameTransform)>,
 }
 
 impl FrameDescriptor {
@@ -37,9 +73,74 @@ impl FrameDescriptor {
             description: description.into(),
             right_handed,
             base_unit,
+            parent: None,
         }
     }
 
+    /// Local tangent-plane frame anchored at a WGS84 position
+    ///
+    /// Axes point east, north and up at the origin, and one frame unit is
+    /// `cell_size` meters, so lattice coordinates (for example of a
+    /// [`Route64`]) count cells from the origin. The frame's parent is
+    /// [`ECEF_FRAME`]. Register it under an id of your choice:
+    ///
+    /// ```
+    /// use octaindex3d::frame::{self, FrameDescriptor};
+    ///
+    /// # fn main() -> octaindex3d::Result<()> {
+    /// let site = FrameDescriptor::local_enu(47.378, 8.540, 408.0, 0.25)?;
+    /// frame::register_frame(50, site)?;
+    ///
+    /// // 40 cells up is 10 m above the origin
+    /// let ecef = frame::transform_point((0.0, 0.0, 40.0), 50, frame::ECEF_FRAME)?;
+    /// let (_, _, alt) = octaindex3d::geodetic::ecef_to_wgs84(ecef);
+    /// assert!((alt - 418.0).abs() < 1e-6);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// The plane is tangent at the origin only: at 1 km the ellipsoid drops
+    /// about 8 cm below it.
+    pub fn local_enu(lat_deg: f64, lon_deg: f64, alt_m: f64, cell_size: f64) -> Result<Self> {
+        if !(cell_size.is_finite() && cell_size > 0.0) {
+            return Err(Error::OutOfRange(format!(
+                "cell_size must be positive and finite, got {}",
+                cell_size
+            )));
+        }
+        if !(lat_deg.abs() <= 90.0 && lon_deg.is_finite() && alt_m.is_finite()) {
+            return Err(Error::OutOfRange(format!(
+                "invalid geodetic origin ({}, {}, {})",
+                lat_deg, lon_deg, alt_m
+            )));
+        }
+        let (x, y, z) = crate::geodetic::wgs84_to_ecef(lat_deg, lon_deg, alt_m);
+        // Tilt up to the latitude, then turn to face east at the longitude
+        let tilt = FrameTransform::from_axis_angle([1.0, 0.0, 0.0], (90.0 - lat_deg).to_radians());
+        let turn = FrameTransform::from_axis_angle([0.0, 0.0, 1.0], (90.0 + lon_deg).to_radians());
+        let pose = tilt
+            .then(&turn)
+            .with_scale(cell_size)
+            .with_translation([x, y, z]);
+        Ok(Self::new(
+            "ENU",
+            "WGS-84",
+            format!(
+                "East-North-Up at ({:.7}, {:.7}, {:.3} m), {} m cells",
+                lat_deg, lon_deg, alt_m, cell_size
+            ),
+            true,
+            cell_size,
+        )
+        .with_parent(ECEF_FRAME, pose))
+    }
+
+    /// Place the frame in `parent`, mapping its coordinates with `transform`
+    pub fn with_parent(mut self, parent: FrameId, transform: FrameTransform) -> Self {
+        self.parent = Some((parent, transform));
+        self
+    }
+
     /// Compute hash for conflict detection
     fn compute_hash(&self) -> u64 {
         use std::collections::hash_map::DefaultHasher;
@@ -50,10 +151,37 @@ impl FrameDescriptor {
         self.datum.hash(&mut hasher);
         self.right_handed.hash(&mut hasher);
         self.base_unit.to_bits().hash(&mut hasher);
+        if let Some((parent, transform)) = &self.parent {
+            parent.hash(&mut hasher);
+            for value in transform
+                .rotation
+                .iter()
+                .chain(&transform.translation)
+                .chain([&transform.scale])
+            {
+                value.to_bits().hash(&mut hasher);
+            }
+        }
         hasher.finish()
     }
 }
 
+/// Built-in Earth-Centered Earth-Fixed frame, in meters
+pub const ECEF_FRAME: FrameId = 0;
+
+/// Built-in lattice frame covering the globe
+///
+/// Its coordinates are ECEF in units of 512 m, shifted so that the 16-bit
+/// range spans a cube of ±2^24 m (about 16,800 km) around Earth's center.
+/// See [`crate::geodetic`] for placing WGS84 positions in it.
+///
+/// Id 1 is registered in every process, so [`register_frame`] refuses a
+/// different descriptor under it with [`Error::FrameConflict`].
+pub const ECEF_GRID_FRAME: FrameId = 1;
+
+/// Edge of an [`ECEF_GRID_FRAME`] unit, in meters
+const ECEF_GRID_UNIT: f64 = 512.0;
+
 /// Global frame registry
 static FRAME_REGISTRY: Lazy<RwLock<FrameRegistry>> =
     Lazy::new(|| RwLock::new(FrameRegistry::new()));
@@ -61,19 +189,37 @@ static FRAME_REGISTRY: Lazy<RwLock<FrameRegistry>> =
 /// Frame registry implementation
 struct FrameRegistry {
     frames: HashMap<FrameId, (Arc<FrameDescriptor>, u64)>,
+    /// Poses over time of moving frames, relative to their parents
+    trajectories: HashMap<FrameId, FrameTrajectory>,
 }
 
 impl FrameRegistry {
     fn new() -> Self {
         let mut registry = Self {
             frames: HashMap::new(),
+            trajectories: HashMap::new(),
         };
 
         // Register default frames
         let ecef = FrameDescriptor::new("ECEF", "WGS-84", "Earth-Centered Earth-Fixed", true, 1.0);
-        registry
-            .frames
-            .insert(0, (Arc::new(ecef.clone()), ecef.compute_hash()));
+        let half_extent = ECEF_GRID_UNIT * 32768.0;
+        let grid = FrameDescriptor::new(
+            "ECEF-GRID",
+            "WGS-84",
+            "Earth-Centered Earth-Fixed lattice, 512 m units",
+            true,
+            ECEF_GRID_UNIT,
+        )
+        .with_parent(
+            ECEF_FRAME,
+            FrameTransform::IDENTITY
+                .with_scale(ECEF_GRID_UNIT)
+                .with_translation([-half_extent; 3]),
+        );
+        for (id, desc) in [(ECEF_FRAME, ecef), (ECEF_GRID_FRAME, grid)] {
+            let hash = desc.compute_hash();
+            registry.frames.insert(id, (Arc::new(desc), hash));
+        }
 
         registry
     }
@@ -91,10 +237,108 @@ impl FrameRegistry {
             }
         }
 
+        // Parents must exist first, which also rules out cycles
+        if let Some((parent, _)) = desc.parent {
+            if parent == id || !self.frames.contains_key(&parent) {
+                return Err(Error::InvalidFrameID(parent));
+            }
+        }
+
         self.frames.insert(id, (Arc::new(desc), hash));
         Ok(())
     }
 
+    /// Transform from a frame into its parent, at `time` for moving frames
+    fn parent_pose(
+        &self,
+        id: FrameId,
+        time: Option<f64>,
+    ) -> Result<Option<(FrameId, FrameTransform)>> {
+        let (desc, _) = self.frames.get(&id).ok_or(Error::InvalidFrameID(id))?;
+        let Some((parent, pose)) = desc.parent else {
+            return Ok(None);
+        };
+        match (self.trajectories.get(&id), time) {
+            (None, _) => Ok(Some((parent, pose))),
+            (Some(trajectory), Some(time)) => Ok(Some((parent, trajectory.at(time)?))),
+            (Some(_), None) => Err(Error::MovingFrame(id)),
+        }
+    }
+
+    /// A frame followed by its ancestors up to the root
+    fn lineage(&self, mut id: FrameId) -> Result<Vec<FrameId>> {
+        let mut lineage = vec![id];
+        loop {
+            let (desc, _) = self.frames.get(&id).ok_or(Error::InvalidFrameID(id))?;
+            let Some((parent, _)) = desc.parent else {
+                return Ok(lineage);
+            };
+            // More steps than there are frame ids means a loop
+            if lineage.len() > FrameId::MAX as usize {
+                return Err(Error::FrameCycle(id));
+            }
+            lineage.push(parent);
+            id = parent;
+        }
+    }
+
+    /// Compose the poses through the lowest common ancestor of two frames
+    ///
+    /// Only the frames below that ancestor are visited, so frames above it
+    /// may move without a time being given.
+    fn resolve(&self, from: FrameId, to: FrameId, time: Option<f64>) -> Result<FrameChain> {
+        let up = self.lineage(from)?;
+        let down = self.lineage(to)?;
+        let (rise, fall) = up
+            .iter()
+            .enumerate()
+            .find_map(|(i, frame)| Some((i, down.iter().position(|f| f == frame)?)))
+            .ok_or(Error::NoFrameTransform { from, to })?;
+        let into_ancestor = |frames: &[FrameId]| {
+            frames
+                .iter()
+                .try_fold(FrameTransform::IDENTITY, |transform, &id| {
+                    let (_, step) = self
+                        .parent_pose(id, time)?
+                        .expect("frames below a common ancestor have parents");
+                    Ok::<_, Error>(transform.then(&step))
+                })
+        };
+        let transform = into_ancestor(&up[..rise])?.then(&into_ancestor(&down[..fall])?.inverse());
+        let mut path = up[..=rise].to_vec();
+        path.extend(down[..fall].iter().rev());
+        Ok(FrameChain { path, transform })
+    }
+
+    /// Move a frame under another parent, refusing to create a loop
+    fn set_parent(
+        &mut self,
+        id: FrameId,
+        parent: FrameId,
+        transform: FrameTransform,
+    ) -> Result<()> {
+        if id == ECEF_FRAME || id == ECEF_GRID_FRAME || !self.frames.contains_key(&id) {
+            return Err(Error::InvalidFrameID(id));
+        }
+        if self.lineage(parent)?.contains(&id) {
+            return Err(Error::FrameCycle(id));
+        }
+        let (desc, hash) = self.frames.get_mut(&id).expect("movable frame exists");
+        let mut updated = FrameDescriptor::clone(desc);
+        updated.parent = Some((parent, transform));
+        *hash = updated.compute_hash();
+        *desc = Arc::new(updated);
+        Ok(())
+    }
+
+    /// Only frames with a parent can move relative to it
+    fn check_movable(&self, id: FrameId) -> Result<()> {
+        match self.frames.get(&id) {
+            Some((desc, _)) if desc.parent.is_some() => Ok(()),
+            _ => Err(Error::InvalidFrameID(id)),
+        }
+    }
+
     fn get(&self, id: FrameId) -> Result<Arc<FrameDescriptor>> {
         self.frames
             .get(&id)
@@ -125,6 +369,703 @@ pub fn list_frames() -> Vec<(FrameId, Arc<FrameDescriptor>)> {
     FRAME_REGISTRY.read().list()
 }
 
+/// Version byte of the [`export_frames`] encoding; version 1 records,
+/// without trajectories, are still read
+const EXPORT_VERSION: u8 = 2;
+
+/// Serialize frames, with their ancestors and trajectories, for storage
+/// next to data that uses them
+///
+/// Built-in frames are left out since every process registers them, as are
+/// ids that are not registered. Parents come before their children, so
+/// [`import_frames`] can register the records in order.
+pub fn export_frames(ids: impl IntoIterator<Item = FrameId>) -> Vec<u8> {
+    let registry = FRAME_REGISTRY.read();
+    let mut chosen: HashMap<FrameId, usize> = HashMap::new();
+    for id in ids {
+        // Walk up to the root, recording each frame's depth
+        let mut chain = Vec::new();
+        let mut next = Some(id);
+        while let Some(id) = next {
+            let Some((desc, _)) = registry.frames.get(&id) else {
+                break;
+            };
+            chain.push(id);
+            next = desc.parent.map(|(parent, _)| parent);
+        }
+        for (height, &id) in chain.iter().enumerate() {
+            chosen.insert(id, chain.len() - height);
+        }
+    }
+    let mut ids: Vec<_> = chosen
+        .into_iter()
+        .filter(|&(id, _)| id != ECEF_FRAME && id != ECEF_GRID_FRAME)
+        .collect();
+    ids.sort_unstable_by_key(|&(id, depth)| (depth, id));
+
+    let mut out = vec![EXPORT_VERSION];
+    out.extend_from_slice(&(ids.len() as u16).to_le_bytes());
+    for (id, _) in ids {
+        let (desc, _) = &registry.frames[&id];
+        out.push(id);
+        for text in [&desc.name, &desc.datum, &desc.description] {
+            out.extend_from_slice(&(text.len() as u32).to_le_bytes());
+            out.extend_from_slice(text.as_bytes());
+        }
+        out.push(desc.right_handed as u8);
+        out.extend_from_slice(&desc.base_unit.to_le_bytes());
+        match &desc.parent {
+            None => out.push(0),
+            Some((parent, transform)) => {
+                out.push(1);
+                out.push(*parent);
+                write_transform(&mut out, transform);
+            }
+        }
+        let samples = registry
+            .trajectories
+            .get(&id)
+            .map_or(&[][..], |trajectory| &trajectory.samples[..]);
+        out.extend_from_slice(&(samples.len() as u32).to_le_bytes());
+        for (time, pose) in samples {
+            out.extend_from_slice(&time.to_le_bytes());
+            write_transform(&mut out, pose);
+        }
+    }
+    out
+}
+
+fn write_transform(out: &mut Vec<u8>, t: &FrameTransform) {
+    for value in t.rotation.iter().chain(&t.translation).chain([&t.scale]) {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+/// Register the frames in an [`export_frames`] record, returning their ids
+///
+/// Frames already registered with an identical descriptor are accepted;
+/// a different descriptor or trajectory under a stored id fails with
+/// [`Error::FrameConflict`], before any frame is registered. Stored
+/// trajectories are set on their frames.
+pub fn import_frames(bytes: &[u8]) -> Result<Vec<FrameId>> {
+    let mut reader = ExportReader { bytes, pos: 0 };
+    let version = reader.take(1)?[0];
+    if !(1..=EXPORT_VERSION).contains(&version) {
+        return Err(Error::InvalidFormat(
+            "unsupported frame registry record".to_string(),
+        ));
+    }
+    let count = u16::from_le_bytes(reader.array()?);
+    let mut frames = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let id = reader.take(1)?[0];
+        let mut text = || -> Result<String> {
+            let len = u32::from_le_bytes(reader.array()?) as usize;
+            String::from_utf8(reader.take(len)?.to_vec())
+                .map_err(|_| Error::InvalidFormat("frame name is not UTF-8".to_string()))
+        };
+        let (name, datum, description) = (text()?, text()?, text()?);
+        let right_handed = reader.take(1)?[0] != 0;
+        let base_unit = f64::from_le_bytes(reader.array()?);
+        let mut desc = FrameDescriptor::new(name, datum, description, right_handed, base_unit);
+        if reader.take(1)?[0] != 0 {
+            let parent = reader.take(1)?[0];
+            desc = desc.with_parent(parent, reader.transform()?);
+        }
+        let mut trajectory = None;
+        if version >= 2 {
+            let samples = u32::from_le_bytes(reader.array()?);
+            if samples > 0 {
+                let mut moving = FrameTrajectory::new();
+                for _ in 0..samples {
+                    let time = f64::from_le_bytes(reader.array()?);
+                    moving
+                        .push(time, reader.transform()?)
+                        .map_err(|e| Error::InvalidFormat(e.to_string()))?;
+                }
+                if desc.parent.is_none() {
+                    return Err(Error::InvalidFormat(format!(
+                        "frame {} has a trajectory but no parent",
+                        id
+                    )));
+                }
+                trajectory = Some(moving);
+            }
+        }
+        frames.push((id, desc, trajectory));
+    }
+    if reader.pos != bytes.len() {
+        return Err(Error::InvalidFormat(
+            "trailing bytes after frame registry record".to_string(),
+        ));
+    }
+
+    let mut registry = FRAME_REGISTRY.write();
+    // Check for conflicts first so a failed import registers nothing
+    for (id, desc, trajectory) in &frames {
+        if let Some((_, hash)) = registry.frames.get(id) {
+            if *hash != desc.compute_hash() {
+                return Err(Error::FrameConflict(*id));
+            }
+        }
+        let registered = registry.trajectories.get(id);
+        if trajectory.is_some() && registered.is_some() && registered != trajectory.as_ref() {
+            return Err(Error::FrameConflict(*id));
+        }
+    }
+    frames
+        .into_iter()
+        .map(|(id, desc, trajectory)| {
+            registry.register(id, desc)?;
+            if let Some(trajectory) = trajectory {
+                registry.trajectories.insert(id, trajectory);
+            }
+            Ok(id)
+        })
+        .collect()
+}
+
+struct ExportReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ExportReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| Error::InvalidFormat("truncated frame registry record".to_string()))?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self.take(N)?.try_into().expect("length checked"))
+    }
+
+    fn transform(&mut self) -> Result<FrameTransform> {
+        let mut values = [0.0; 8];
+        for value in &mut values {
+            *value = f64::from_le_bytes(self.array()?);
+        }
+        Ok(FrameTransform {
+            rotation: [values[0], values[1], values[2], values[3]],
+            translation: [values[4], values[5], values[6]],
+            scale: values[7],
+        })
+    }
+}
+
+/// Similarity transform between frames: `p' = scale * R * p + translation`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameTransform {
+    /// Rotation as a unit quaternion `[w, x, y, z]`
+    pub rotation: [f64; 4],
+    /// Translation, applied after rotation and scale
+    pub translation: [f64; 3],
+    /// Uniform scale
+    pub scale: f64,
+}
+
+impl FrameTransform {
+    /// The transform that leaves points unchanged
+    pub const IDENTITY: Self = Self {
+        rotation: [1.0, 0.0, 0.0, 0.0],
+        translation: [0.0; 3],
+        scale: 1.0,
+    };
+
+    /// Rotation about `axis` by `angle` radians (right-handed)
+    pub fn from_axis_angle(axis: [f64; 3], angle: f64) -> Self {
+        let norm = (axis[0] * axis[0] + axis[1] * axis[1] + axis[2] * axis[2]).sqrt();
+        if norm == 0.0 {
+            return Self::IDENTITY;
+        }
+        let (sin, cos) = (angle / 2.0).sin_cos();
+        let k = sin / norm;
+        Self {
+            rotation: [cos, axis[0] * k, axis[1] * k, axis[2] * k],
+            ..Self::IDENTITY
+        }
+    }
+
+    /// Rotation from a quaternion `[w, x, y, z]`, normalized
+    pub fn from_quaternion(rotation: [f64; 4]) -> Result<Self> {
+        let norm = rotation.iter().map(|c| c * c).sum::<f64>().sqrt();
+        if !norm.is_finite() || norm == 0.0 {
+            return Err(Error::OutOfRange(format!(
+                "rotation quaternion {:?} cannot be normalized",
+                rotation
+            )));
+        }
+        Ok(Self {
+            rotation: rotation.map(|c| c / norm),
+            ..Self::IDENTITY
+        })
+    }
+
+    /// Builder form setting the translation
+    pub fn with_translation(mut self, translation: [f64; 3]) -> Self {
+        self.translation = translation;
+        self
+    }
+
+    /// Builder form setting the scale
+    pub fn with_scale(mut self, scale: f64) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Map a point
+    pub fn apply(&self, point: (f64, f64, f64)) -> (f64, f64, f64) {
+        let [x, y, z] = self.rotate([point.0, point.1, point.2]);
+        (
+            self.scale * x + self.translation[0],
+            self.scale * y + self.translation[1],
+            self.scale * z + self.translation[2],
+        )
+    }
+
+    /// The transform undoing this one
+    pub fn inverse(&self) -> Self {
+        let [w, x, y, z] = self.rotation;
+        let rotation = [w, -x, -y, -z];
+        let scale = 1.0 / self.scale;
+        let inverse = Self {
+            rotation,
+            translation: [0.0; 3],
+            scale,
+        };
+        let t = inverse.rotate(self.translation);
+        Self {
+            translation: [-scale * t[0], -scale * t[1], -scale * t[2]],
+            ..inverse
+        }
+    }
+
+    /// Blend towards `other` by `s` in `0..=1`: linear in translation and
+    /// scale, along the shorter arc in rotation
+    pub fn interpolate(&self, other: &Self, s: f64) -> Self {
+        let lerp = |a: f64, b: f64| a + (b - a) * s;
+        let a = self.rotation;
+        let mut b = other.rotation;
+        let mut dot: f64 = a.iter().zip(&b).map(|(x, y)| x * y).sum();
+        if dot < 0.0 {
+            // q and -q are the same rotation; take the shorter way round
+            b = b.map(|c| -c);
+            dot = -dot;
+        }
+        let rotation = if dot > 0.999_999 {
+            // Nearly parallel: normalized lerp avoids dividing by sin(0)
+            let q = [0, 1, 2, 3].map(|i| lerp(a[i], b[i]));
+            let norm = q.iter().map(|c| c * c).sum::<f64>().sqrt();
+            q.map(|c| c / norm)
+        } else {
+            let theta = dot.acos();
+            let (wa, wb) = (
+                ((1.0 - s) * theta).sin() / theta.sin(),
+                (s * theta).sin() / theta.sin(),
+            );
+            [0, 1, 2, 3].map(|i| wa * a[i] + wb * b[i])
+        };
+        Self {
+            rotation,
+            translation: [0, 1, 2].map(|i| lerp(self.translation[i], other.translation[i])),
+            scale: lerp(self.scale, other.scale),
+        }
+    }
+
+    /// This transform followed by `next`
+    pub fn then(&self, next: &Self) -> Self {
+        let t = next.rotate(self.translation);
+        Self {
+            rotation: quaternion_mul(next.rotation, self.rotation),
+            translation: [
+                next.scale * t[0] + next.translation[0],
+                next.scale * t[1] + next.translation[1],
+                next.scale * t[2] + next.translation[2],
+            ],
+            scale: next.scale * self.scale,
+        }
+    }
+
+    fn rotate(&self, v: [f64; 3]) -> [f64; 3] {
+        // v + 2w (q × v) + 2 q × (q × v)
+        let [w, qx, qy, qz] = self.rotation;
+        let cross = |a: [f64; 3], b: [f64; 3]| {
+            [
+                a[1] * b[2] - a[2] * b[1],
+                a[2] * b[0] - a[0] * b[2],
+                a[0] * b[1] - a[1] * b[0],
+            ]
+        };
+        let q = [qx, qy, qz];
+        let c = cross(q, v);
+        let cc = cross(q, c);
+        [
+            v[0] + 2.0 * (w * c[0] + cc[0]),
+            v[1] + 2.0 * (w * c[1] + cc[1]),
+            v[2] + 2.0 * (w * c[2] + cc[2]),
+        ]
+    }
+}
+
+impl Default for FrameTransform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+fn quaternion_mul(a: [f64; 4], b: [f64; 4]) -> [f64; 4] {
+    [
+        a[0] * b[0] - a[1] * b[1] - a[2] * b[2] - a[3] * b[3],
+        a[0] * b[1] + a[1] * b[0] + a[2] * b[3] - a[3] * b[2],
+        a[0] * b[2] - a[1] * b[3] + a[2] * b[0] + a[3] * b[1],
+        a[0] * b[3] + a[1] * b[2] - a[2] * b[1] + a[3] * b[0],
+    ]
+}
+
+/// Transform between two frames and the frames it passes through
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameChain {
+    /// Frames from the source up to the lowest common ancestor, then down
+    /// to the target; a single frame when both are the same
+    pub path: Vec<FrameId>,
+    /// Transform taking source coordinates to target coordinates
+    pub transform: FrameTransform,
+}
+
+/// Resolve the transform from frame `from` to frame `to` through the frame
+/// graph
+///
+/// The poses along the path through the frames' lowest common ancestor
+/// are composed; frames above that ancestor are not consulted, so they may
+/// move. Fails if the frames are unrelated or the path crosses a moving
+/// frame; see [`resolve_at`].
+///
+/// ```
+/// use octaindex3d::frame::{self, FrameDescriptor, FrameTransform};
+///
+/// # fn main() -> octaindex3d::Result<()> {
+/// // Two cameras on one rig
+/// let rig = FrameDescriptor::new("rig", "local", "Sensor rig", true, 1.0);
+/// frame::register_frame(42, rig)?;
+/// let left = FrameTransform::IDENTITY.with_translation([0.0, 0.1, 0.0]);
+/// let right = FrameTransform::IDENTITY.with_translation([0.0, -0.1, 0.0]);
+/// let cam = |name: &str| FrameDescriptor::new(name, "local", "Camera", true, 1.0);
+/// frame::register_frame(43, cam("left").with_parent(42, left))?;
+/// frame::register_frame(44, cam("right").with_parent(42, right))?;
+///
+/// let chain = frame::resolve(43, 44)?;
+/// assert_eq!(chain.path, vec![43, 42, 44]);
+/// let (_, y, _) = chain.transform.apply((0.0, 0.0, 0.0));
+/// assert!((y - 0.2).abs() < 1e-12);
+/// # Ok(())
+/// # }
+/// ```
+pub fn resolve(from: FrameId, to: FrameId) -> Result<FrameChain> {
+    FRAME_REGISTRY.read().resolve(from, to, None)
+}
+
+/// [`resolve`] at `time`, for paths crossing moving frames
+pub fn resolve_at(from: FrameId, to: FrameId, time: f64) -> Result<FrameChain> {
+    FRAME_REGISTRY.read().resolve(from, to, Some(time))
+}
+
+/// Place a registered frame under a new parent
+///
+/// Fails with [`Error::FrameCycle`] if `parent` is the frame itself or one
+/// of its descendants. Built-in frames cannot be moved. A trajectory set
+/// on the frame keeps applying, relative to the new parent.
+pub fn set_frame_parent(id: FrameId, parent: FrameId, transform: FrameTransform) -> Result<()> {
+    FRAME_REGISTRY.write().set_parent(id, parent, transform)
+}
+
+/// Transform taking coordinates in frame `from` to frame `to`
+///
+/// Both frames must be registered and connected through parents; see
+/// [`resolve`].
+pub fn frame_transform(from: FrameId, to: FrameId) -> Result<FrameTransform> {
+    Ok(FRAME_REGISTRY.read().resolve(from, to, None)?.transform)
+}
+
+/// [`frame_transform`] at `time`, for trees containing moving frames
+pub fn frame_transform_at(from: FrameId, to: FrameId, time: f64) -> Result<FrameTransform> {
+    Ok(FRAME_REGISTRY
+        .read()
+        .resolve(from, to, Some(time))?
+        .transform)
+}
+
+/// [`transform_point`] at `time`, for trees containing moving frames
+pub fn transform_point_at(
+    point: (f64, f64, f64),
+    from: FrameId,
+    to: FrameId,
+    time: f64,
+) -> Result<(f64, f64, f64)> {
+    Ok(frame_transform_at(from, to, time)?.apply(point))
+}
+
+/// Give frame `id` a pose relative to its parent that varies over time
+///
+/// Replaces any earlier trajectory. The frame must have a parent; its
+/// descriptor's static transform is no longer used.
+pub fn set_frame_trajectory(id: FrameId, trajectory: FrameTrajectory) -> Result<()> {
+    let mut registry = FRAME_REGISTRY.write();
+    registry.check_movable(id)?;
+    registry.trajectories.insert(id, trajectory);
+    Ok(())
+}
+
+/// Append a pose sample to the trajectory of frame `id`, creating the
+/// trajectory on the first sample
+pub fn push_frame_pose(id: FrameId, time: f64, pose: FrameTransform) -> Result<()> {
+    let mut registry = FRAME_REGISTRY.write();
+    registry.check_movable(id)?;
+    let trajectory = registry.trajectories.entry(id).or_default();
+    let pushed = trajectory.push(time, pose);
+    if trajectory.is_empty() {
+        // A rejected first sample leaves the frame static
+        registry.trajectories.remove(&id);
+    }
+    pushed
+}
+
+/// The trajectory of a moving frame, if it has one
+pub fn frame_trajectory(id: FrameId) -> Option<FrameTrajectory> {
+    FRAME_REGISTRY.read().trajectories.get(&id).cloned()
+}
+
+/// Frame descriptor with its parent transform as of `time`
+///
+/// For a static frame this is [`get_frame`].
+pub fn get_frame_at(id: FrameId, time: f64) -> Result<Arc<FrameDescriptor>> {
+    let registry = FRAME_REGISTRY.read();
+    let desc = registry.get(id)?;
+    match (registry.trajectories.contains_key(&id), desc.parent) {
+        (true, Some(_)) => {
+            let (parent, pose) = registry
+                .parent_pose(id, Some(time))?
+                .expect("moving frames have parents");
+            Ok(Arc::new((*desc).clone().with_parent(parent, pose)))
+        }
+        _ => Ok(desc),
+    }
+}
+
+/// Timestamped poses of a frame relative to its parent
+///
+/// Poses between samples interpolate linearly in translation and scale and
+/// spherically (slerp) in rotation. Times outside the sampled span fail
+/// rather than extrapolate.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FrameTrajectory {
+    samples: Vec<(f64, FrameTransform)>,
+}
+
+impl FrameTrajectory {
+    /// Create an empty trajectory
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trajectory through `samples`, sorted by time
+    pub fn from_samples(samples: impl IntoIterator<Item = (f64, FrameTransform)>) -> Result<Self> {
+        let mut samples: Vec<_> = samples.into_iter().collect();
+        samples.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let mut trajectory = Self::new();
+        for (time, pose) in samples {
+            trajectory.push(time, pose)?;
+        }
+        Ok(trajectory)
+    }
+
+    /// Append a sample later than every existing one
+    pub fn push(&mut self, time: f64, pose: FrameTransform) -> Result<()> {
+        let after_last = self.samples.last().map_or(true, |&(last, _)| time > last);
+        if !time.is_finite() || !after_last {
+            return Err(Error::OutOfRange(format!(
+                "pose time {} must be finite and after the last sample",
+                time
+            )));
+        }
+        self.samples.push((time, pose));
+        Ok(())
+    }
+
+    /// Number of samples
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Whether the trajectory has no samples
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// First and last sample times
+    pub fn time_range(&self) -> Option<(f64, f64)> {
+        Some((self.samples.first()?.0, self.samples.last()?.0))
+    }
+
+    /// Pose at `time`, interpolated between the surrounding samples
+    pub fn at(&self, time: f64) -> Result<FrameTransform> {
+        let after = self.samples.partition_point(|&(t, _)| t < time);
+        match (
+            after.checked_sub(1).map(|i| self.samples[i]),
+            self.samples.get(after),
+        ) {
+            (_, Some(&(t, pose))) if t == time => Ok(pose),
+            (Some((t0, a)), Some(&(t1, b))) => Ok(a.interpolate(&b, (time - t0) / (t1 - t0))),
+            _ => Err(Error::OutOfRange(format!(
+                "time {} outside the trajectory's span {:?}",
+                time,
+                self.time_range()
+            ))),
+        }
+    }
+}
+
+/// Express a point of frame `from` in frame `to`
+pub fn transform_point(
+    point: (f64, f64, f64),
+    from: FrameId,
+    to: FrameId,
+) -> Result<(f64, f64, f64)> {
+    Ok(frame_transform(from, to)?.apply(point))
+}
+
+/// Batched [`transform_point`], resolving the frames once
+pub fn transform_points(
+    points: &[(f64, f64, f64)],
+    from: FrameId,
+    to: FrameId,
+) -> Result<Vec<(f64, f64, f64)>> {
+    let transform = frame_transform(from, to)?;
+    Ok(points.iter().map(|&p| transform.apply(p)).collect())
+}
+
+/// Move a cell into frame `to`, keeping its tier and LOD
+///
+/// The cell's lattice point is placed in frame units (see [`cell_extent`]),
+/// transformed, and snapped to the nearest BCC lattice point at the same
+/// LOD of `to`; cells landing outside the 16-bit coordinate range fail.
+/// Meters per frame unit (`base_unit`) differ between frames only through
+/// the poses' scale, as in [`FrameDescriptor::local_enu`].
+pub fn transform_index(index: Index64, to: FrameId) -> Result<Index64> {
+    let transform = frame_transform(index.frame_id(), to)?;
+    move_index(index, to, &transform)
+}
+
+/// Batched [`transform_index`] for cells of one frame
+pub fn transform_indices(indices: &[Index64], to: FrameId) -> Result<Vec<Index64>> {
+    let Some(first) = indices.first() else {
+        return Ok(Vec::new());
+    };
+    let from = first.frame_id();
+    let transform = frame_transform(from, to)?;
+    indices
+        .iter()
+        .map(|&index| {
+            if index.frame_id() != from {
+                return Err(Error::InvalidFrameID(index.frame_id()));
+            }
+            move_index(index, to, &transform)
+        })
+        .collect()
+}
+
+/// Finest LOD; a cell at this LOD spans one frame unit
+const FINEST_LOD: u8 = 15;
+
+/// Edge of a cell at `lod`, in frame units
+///
+/// Index coordinates at LOD 15 are frame coordinates, and each coarser LOD
+/// doubles the cell edge, matching [`Index64::parent`].
+pub fn cell_extent(lod: u8) -> Result<f64> {
+    if lod > FINEST_LOD {
+        return Err(Error::InvalidLOD(format!("lod must be 0-15, got {}", lod)));
+    }
+    Ok((1u32 << (FINEST_LOD - lod)) as f64)
+}
+
+/// [`transform_index`] with the transform already resolved
+pub(crate) fn move_index(
+    index: Index64,
+    to: FrameId,
+    transform: &FrameTransform,
+) -> Result<Index64> {
+    let extent = cell_extent(index.lod())?;
+    let (x, y, z) = index.decode_coords();
+    let (x, y, z) = transform.apply((x as f64 * extent, y as f64 * extent, z as f64 * extent));
+    let (x, y, z) = nearest_bcc((x / extent, y / extent, z / extent))?;
+    let coord = |v: i32| -> Result<u16> {
+        u16::try_from(v).map_err(|_| {
+            Error::OutOfRange(format!("transformed coordinate {} outside 0..=65535", v))
+        })
+    };
+    Index64::new(
+        to,
+        index.scale_tier(),
+        index.lod(),
+        coord(x)?,
+        coord(y)?,
+        coord(z)?,
+    )
+}
+
+/// Express a lattice route cell of frame `from` in frame `to`
+///
+/// Route coordinates are frame units, like LOD 15 cells; the transformed
+/// position snaps to the nearest BCC lattice point.
+pub fn transform_route(route: Route64, from: FrameId, to: FrameId) -> Result<Route64> {
+    let transform = frame_transform(from, to)?;
+    move_route(route, &transform)
+}
+
+/// Batched [`transform_route`]
+pub fn transform_routes(routes: &[Route64], from: FrameId, to: FrameId) -> Result<Vec<Route64>> {
+    let transform = frame_transform(from, to)?;
+    routes
+        .iter()
+        .map(|&route| move_route(route, &transform))
+        .collect()
+}
+
+fn move_route(route: Route64, transform: &FrameTransform) -> Result<Route64> {
+    let p = transform.apply((route.x() as f64, route.y() as f64, route.z() as f64));
+    let (x, y, z) = nearest_bcc(p)?;
+    Route64::new(route.scale_tier(), x, y, z)
+}
+
+/// Closest point of the BCC lattice: all-even or all-odd coordinates
+pub(crate) fn nearest_bcc(p: (f64, f64, f64)) -> Result<(i32, i32, i32)> {
+    let round_to = |v: f64, odd: bool| -> f64 {
+        let shift = if odd { 1.0 } else { 0.0 };
+        ((v - shift) / 2.0).round() * 2.0 + shift
+    };
+    let candidate = |odd: bool| {
+        let c = (round_to(p.0, odd), round_to(p.1, odd), round_to(p.2, odd));
+        let d = (c.0 - p.0).powi(2) + (c.1 - p.1).powi(2) + (c.2 - p.2).powi(2);
+        (c, d)
+    };
+    let (even, d_even) = candidate(false);
+    let (odd, d_odd) = candidate(true);
+    let (x, y, z) = if d_odd < d_even { odd } else { even };
+    let coord = |v: f64| -> Result<i32> {
+        if (i32::MIN as f64..=i32::MAX as f64).contains(&v) {
+            Ok(v as i32)
+        } else {
+            Err(Error::CoordinateOverflow)
+        }
+    };
+    Ok((coord(x)?, coord(y)?, coord(z)?))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -159,10 +1100,177 @@ mod tests {
         register_frame(102, desc).unwrap();
     }
 
+    #[test]
+    fn test_transform_chain() {
+        let site = FrameDescriptor::new("SITE", "local", "Test site", true, 1.0);
+        register_frame(103, site).unwrap();
+        // 90° about z, then 2x scale and an offset
+        let arm = FrameDescriptor::new("ARM", "local", "Test arm", true, 0.5).with_parent(
+            103,
+            FrameTransform::from_axis_angle([0.0, 0.0, 1.0], std::f64::consts::FRAC_PI_2)
+                .with_scale(2.0)
+                .with_translation([100.0, 200.0, 300.0]),
+        );
+        register_frame(104, arm).unwrap();
+        let tool = FrameDescriptor::new("TOOL", "local", "Test tool", true, 0.5).with_parent(
+            104,
+            FrameTransform::IDENTITY.with_translation([0.0, 0.0, 5.0]),
+        );
+        register_frame(105, tool).unwrap();
+
+        let (x, y, z) = transform_point((1.0, 0.0, 0.0), 105, 103).unwrap();
+        assert!((x - 100.0).abs() < 1e-9 && (y - 202.0).abs() < 1e-9 && (z - 310.0).abs() < 1e-9);
+        let back = transform_points(&[(x, y, z)], 103, 105).unwrap()[0];
+        assert!((back.0 - 1.0).abs() < 1e-9 && back.1.abs() < 1e-9 && back.2.abs() < 1e-9);
+
+        let cell = Index64::new(104, 0, 15, 10, 0, 0).unwrap();
+        let moved = transform_index(cell, 103).unwrap();
+        assert_eq!(moved.frame_id(), 103);
+        assert_eq!(moved.decode_coords(), (100, 220, 300));
+        assert_eq!(transform_indices(&[moved], 104).unwrap(), vec![cell]);
+        // A coarser cell spans more units, so the offset is fewer cells
+        let coarse = Index64::new(104, 0, 14, 10, 0, 0).unwrap();
+        let moved = transform_index(coarse, 103).unwrap();
+        assert_eq!((moved.lod(), moved.decode_coords()), (14, (50, 120, 150)));
+        // Off-lattice results snap to the nearest BCC point
+        let moved = transform_index(Index64::new(104, 0, 8, 10, 0, 0).unwrap(), 103).unwrap();
+        assert_eq!(moved.decode_coords(), (1, 21, 3));
+
+        let route = Route64::new(0, 2, 2, 2).unwrap();
+        let moved = transform_route(route, 104, 103).unwrap();
+        assert_eq!((moved.x(), moved.y(), moved.z()), (96, 204, 304));
+
+        // Unregistered parents and unconnected frames fail
+        let orphan = FrameDescriptor::new("ORPHAN", "local", "", true, 1.0)
+            .with_parent(250, FrameTransform::IDENTITY);
+        assert!(register_frame(106, orphan).is_err());
+        assert_eq!(
+            frame_transform(105, 0).unwrap_err(),
+            Error::NoFrameTransform { from: 105, to: 0 }
+        );
+    }
+
     #[test]
     fn test_default_frame() {
         // Frame 0 (ECEF) should be registered by default
-        let frame = get_frame(0).unwrap();
+        let frame = get_frame(ECEF_FRAME).unwrap();
         assert_eq!(frame.name, "ECEF");
+        let (x, y, z) = transform_point((32768.0, 32768.0, 32768.0), ECEF_GRID_FRAME, 0).unwrap();
+        assert_eq!((x, y, z), (0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_moving_frame() {
+        let world = FrameDescriptor::new("MOVE-WORLD", "local", "", true, 1.0);
+        register_frame(107, world).unwrap();
+        let body = FrameDescriptor::new("MOVE-BODY", "local", "", true, 1.0)
+            .with_parent(107, FrameTransform::IDENTITY);
+        register_frame(108, body).unwrap();
+        let sensor = FrameDescriptor::new("MOVE-SENSOR", "local", "", true, 1.0).with_parent(
+            108,
+            FrameTransform::IDENTITY.with_translation([1.0, 0.0, 0.0]),
+        );
+        register_frame(109, sensor).unwrap();
+
+        // The body drives 10 m along x while turning 90° left
+        let turn = FrameTransform::from_axis_angle([0.0, 0.0, 1.0], std::f64::consts::FRAC_PI_2);
+        push_frame_pose(108, 0.0, FrameTransform::IDENTITY).unwrap();
+        push_frame_pose(108, 2.0, turn.with_translation([10.0, 0.0, 0.0])).unwrap();
+        assert!(push_frame_pose(108, 1.0, FrameTransform::IDENTITY).is_err());
+
+        // Halfway: 5 m along and turned 45°
+        let (x, y, _) = transform_point_at((0.0, 0.0, 0.0), 109, 107, 1.0).unwrap();
+        let half = std::f64::consts::FRAC_1_SQRT_2;
+        assert!((x - (5.0 + half)).abs() < 1e-12 && (y - half).abs() < 1e-12);
+        let frame = get_frame_at(108, 1.0).unwrap();
+        let (_, pose) = frame.parent.unwrap();
+        assert!((pose.translation[0] - 5.0).abs() < 1e-12);
+        let end = frame_transform_at(109, 107, 2.0).unwrap();
+        let (x, y, _) = end.apply((0.0, 0.0, 0.0));
+        assert!((x - 10.0).abs() < 1e-12 && (y - 1.0).abs() < 1e-12);
+
+        // No extrapolation, and static queries need a time
+        assert!(frame_transform_at(109, 107, 2.5).is_err());
+        assert_eq!(frame_transform(109, 107), Err(Error::MovingFrame(108)));
+        assert_eq!(
+            frame_trajectory(108).unwrap().time_range(),
+            Some((0.0, 2.0))
+        );
+        // Static frames ignore the time
+        assert_eq!(get_frame_at(107, 5.0).unwrap(), get_frame(107).unwrap());
+
+        // Exports carry the trajectory, and a diverged one conflicts
+        let record = export_frames([109]);
+        assert_eq!(import_frames(&record).unwrap(), vec![107, 108, 109]);
+        push_frame_pose(108, 3.0, turn.with_translation([10.0, 5.0, 0.0])).unwrap();
+        assert_eq!(import_frames(&record), Err(Error::FrameConflict(108)));
+    }
+
+    #[test]
+    fn test_frame_graph() {
+        let frame = |name: &str| FrameDescriptor::new(name, "local", "", true, 1.0);
+        let shift = |x: f64| FrameTransform::IDENTITY.with_translation([x, 0.0, 0.0]);
+        register_frame(111, frame("GRAPH-BASE")).unwrap();
+        register_frame(112, frame("GRAPH-ARM").with_parent(111, shift(1.0))).unwrap();
+        register_frame(113, frame("GRAPH-TOOL").with_parent(112, shift(2.0))).unwrap();
+        register_frame(114, frame("GRAPH-PART").with_parent(111, shift(10.0))).unwrap();
+
+        let chain = resolve(113, 114).unwrap();
+        assert_eq!(chain.path, vec![113, 112, 111, 114]);
+        assert_eq!(chain.transform.apply((0.0, 0.0, 0.0)), (-7.0, 0.0, 0.0));
+        assert_eq!(resolve(112, 112).unwrap().path, vec![112]);
+        assert!(matches!(
+            resolve(113, ECEF_FRAME),
+            Err(Error::NoFrameTransform { .. })
+        ));
+
+        // The tool picks up the part; loops are refused
+        set_frame_parent(114, 113, shift(0.5)).unwrap();
+        assert_eq!(resolve(114, 111).unwrap().path, vec![114, 113, 112, 111]);
+        assert_eq!(
+            transform_point((0.0, 0.0, 0.0), 114, 111).unwrap(),
+            (3.5, 0.0, 0.0)
+        );
+        assert_eq!(
+            set_frame_parent(111, 114, shift(0.0)),
+            Err(Error::FrameCycle(111))
+        );
+        assert_eq!(
+            set_frame_parent(113, 113, shift(0.0)),
+            Err(Error::FrameCycle(113))
+        );
+        assert!(set_frame_parent(ECEF_GRID_FRAME, 111, shift(0.0)).is_err());
+
+        // Frames above the common ancestor may move
+        push_frame_pose(112, 0.0, shift(1.0)).unwrap();
+        assert!(resolve(114, 113).is_ok());
+        assert_eq!(resolve(114, 111), Err(Error::MovingFrame(112)));
+    }
+
+    #[test]
+    fn test_local_enu() {
+        use crate::geodetic::{ecef_to_wgs84, wgs84_to_ecef};
+
+        let (lat, lon, alt) = (-33.857, 151.215, 12.0);
+        let enu = FrameDescriptor::local_enu(lat, lon, alt, 0.5).unwrap();
+        register_frame(110, enu).unwrap();
+
+        let at = |p| ecef_to_wgs84(transform_point(p, 110, ECEF_FRAME).unwrap());
+        let origin = transform_point((0.0, 0.0, 0.0), 110, ECEF_FRAME).unwrap();
+        let expected = wgs84_to_ecef(lat, lon, alt);
+        assert!((origin.0 - expected.0).abs() < 1e-6 && (origin.2 - expected.2).abs() < 1e-6);
+
+        // 20 cells = 10 m along each axis
+        let (_, east_lon, _) = at((20.0, 0.0, 0.0));
+        let (north_lat, _, _) = at((0.0, 20.0, 0.0));
+        let (up_lat, up_lon, up_alt) = at((0.0, 0.0, 20.0));
+        assert!(east_lon > lon && north_lat > lat);
+        assert!((up_lat - lat).abs() < 1e-9 && (up_lon - lon).abs() < 1e-9);
+        assert!((up_alt - (alt + 10.0)).abs() < 1e-6);
+        // 10 m north is about 9e-5 degrees of latitude
+        assert!((north_lat - lat - 9.0e-5).abs() < 1e-6);
+
+        assert!(FrameDescriptor::local_enu(lat, lon, alt, 0.0).is_err());
+        assert!(FrameDescriptor::local_enu(91.0, lon, alt, 1.0).is_err());
     }
 }
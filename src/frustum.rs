@@ -0,0 +1,488 @@
+//! View-frustum culling of lattice cells
+//!
+//! A [`Frustum`] is six inward-facing planes. Cells are tested with their
+//! true BCC Voronoi cell, the truncated octahedron, rather than their center
+//! point, so a cell whose center lies just outside the frustum but whose
+//! volume pokes into view is kept. This avoids cells popping in and out at
+//! the screen edges.
+//!
+//! Cells are tested in the lattice units of their own coordinates: the
+//! truncated octahedron around `(x, y, z)` has square faces at `±1` along
+//! each axis and hexagonal faces at `|x| + |y| + |z| = 1.5`. Express the
+//! frustum in the coordinates of the LOD being culled.
+//!
+//! # Example
+//!
+//! ```
+//! use octaindex3d::frustum::Frustum;
+//! use octaindex3d::Index64;
+//!
+//! # fn main() -> octaindex3d::Result<()> {
+//! // Camera at (100, 100, 100) looking down +x
+//! let frustum = Frustum::perspective(
+//!     [100.0, 100.0, 100.0],
+//!     [1.0, 0.0, 0.0],
+//!     [0.0, 0.0, 1.0],
+//!     1.0,
+//!     1.5,
+//!     1.0,
+//!     50.0,
+//! );
+//! assert!(frustum.is_cell_visible(Index64::new(0, 0, 5, 120, 100, 100)?));
+//! assert!(!frustum.is_cell_visible(Index64::new(0, 0, 5, 80, 100, 100)?));
+//!
+//! let visible: Vec<Index64> = frustum.cells(0, 0, 5).collect();
+//! assert!(visible.contains(&Index64::new(0, 0, 5, 120, 100, 100)?));
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::ids::{FrameId, Index64};
+
+/// Plane `normal · p + offset = 0`; the positive side is inside
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Plane {
+    /// Normal pointing into the kept half-space (need not be unit length)
+    pub normal: [f64; 3],
+    /// Offset from the origin
+    pub offset: f64,
+}
+
+impl Plane {
+    /// Create a plane
+    pub fn new(normal: [f64; 3], offset: f64) -> Self {
+        Self { normal, offset }
+    }
+
+    /// Plane through `point` with the given inward normal
+    pub fn from_point_normal(point: [f64; 3], normal: [f64; 3]) -> Self {
+        Self {
+            normal,
+            offset: -dot(normal, point),
+        }
+    }
+
+    /// Signed distance scaled by the normal length
+    pub fn signed_distance(&self, p: [f64; 3]) -> f64 {
+        dot(self.normal, p) + self.offset
+    }
+}
+
+/// Position of a cell or box relative to a frustum
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Containment {
+    /// Entirely outside
+    Outside,
+    /// Partly inside
+    Intersects,
+    /// Entirely inside
+    Inside,
+}
+
+/// Six-plane view frustum
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frustum {
+    /// Planes in the order left, right, bottom, top, near, far
+    pub planes: [Plane; 6],
+}
+
+impl Frustum {
+    /// Create a frustum from planes ordered left, right, bottom, top, near, far
+    pub fn new(planes: [Plane; 6]) -> Self {
+        Self { planes }
+    }
+
+    /// Perspective frustum of a camera
+    ///
+    /// `forward` and `up` need not be normalized or orthogonal;
+    /// `vertical_fov` is in radians and `aspect` is width over height.
+    pub fn perspective(
+        eye: [f64; 3],
+        forward: [f64; 3],
+        up: [f64; 3],
+        vertical_fov: f64,
+        aspect: f64,
+        near: f64,
+        far: f64,
+    ) -> Self {
+        let f = normalize(forward);
+        let r = normalize(cross(f, up));
+        let u = cross(r, f);
+        let ty = (0.5 * vertical_fov).tan();
+        let tx = ty * aspect;
+        let side = |a: [f64; 3], t: f64| Plane::from_point_normal(eye, add(a, scale(f, t)));
+        Self::new([
+            side(r, tx),
+            side(scale(r, -1.0), tx),
+            side(u, ty),
+            side(scale(u, -1.0), ty),
+            Plane::from_point_normal(add(eye, scale(f, near)), f),
+            Plane::from_point_normal(add(eye, scale(f, far)), scale(f, -1.0)),
+        ])
+    }
+
+    /// Extract the planes of a row-major view-projection matrix
+    ///
+    /// Uses the OpenGL clip convention (`-w ≤ x, y, z ≤ w`) with column
+    /// vectors: `clip = m · [x, y, z, 1]`.
+    pub fn from_view_projection(m: [[f64; 4]; 4]) -> Self {
+        let plane = |sign: f64, row: usize| {
+            Plane::new(
+                [
+                    m[3][0] + sign * m[row][0],
+                    m[3][1] + sign * m[row][1],
+                    m[3][2] + sign * m[row][2],
+                ],
+                m[3][3] + sign * m[row][3],
+            )
+        };
+        Self::new([
+            plane(1.0, 0),
+            plane(-1.0, 0),
+            plane(1.0, 1),
+            plane(-1.0, 1),
+            plane(1.0, 2),
+            plane(-1.0, 2),
+        ])
+    }
+
+    /// Check if a point is inside
+    pub fn contains_point(&self, p: [f64; 3]) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.signed_distance(p) >= 0.0)
+    }
+
+    /// Classify an axis-aligned box
+    pub fn classify_box(&self, min: [f64; 3], max: [f64; 3]) -> Containment {
+        let center = scale(add(min, max), 0.5);
+        let half = scale([max[0] - min[0], max[1] - min[1], max[2] - min[2]], 0.5);
+        self.classify(center, |n| {
+            n[0].abs() * half[0] + n[1].abs() * half[1] + n[2].abs() * half[2]
+        })
+    }
+
+    /// Classify the truncated-octahedron cell of an index
+    pub fn classify_cell(&self, idx: Index64) -> Containment {
+        let (x, y, z) = idx.decode_coords();
+        self.classify([x as f64, y as f64, z as f64], truncated_octahedron_support)
+    }
+
+    /// Check if any part of a cell is inside
+    pub fn is_cell_visible(&self, idx: Index64) -> bool {
+        self.classify_cell(idx) != Containment::Outside
+    }
+
+    /// Keep only the cells with any part inside the frustum
+    ///
+    /// Works on any cell source: a [`CellSet`](crate::CellSet) iterator,
+    /// the keys of a layer, or the cells listed by a block index.
+    pub fn cull<'a, I>(&'a self, cells: I) -> impl Iterator<Item = Index64> + 'a
+    where
+        I: IntoIterator<Item = Index64>,
+        I::IntoIter: 'a,
+    {
+        cells.into_iter().filter(|&idx| self.is_cell_visible(idx))
+    }
+
+    /// All BCC lattice points whose cells are at least partly inside
+    ///
+    /// Walks the frustum's bounding box, clamped to the 16-bit coordinate
+    /// range, skipping whole blocks that are outside or inside. Yields cells
+    /// in the given frame, scale tier and LOD.
+    pub fn cells(&self, frame: FrameId, tier: u8, lod: u8) -> FrustumCells {
+        let mut stack = Vec::new();
+        if let Some((lo, hi)) = self.bounding_box() {
+            let clamp = |v: f64| v.clamp(0.0, u16::MAX as f64) as i32;
+            // Cells reach one unit past their center
+            let lo = [lo[0], lo[1], lo[2]].map(|v| clamp(v.floor() - 1.0));
+            let hi = [hi[0], hi[1], hi[2]].map(|v| clamp(v.ceil() + 1.0));
+            if lo.iter().zip(&hi).all(|(a, b)| a <= b) {
+                stack.push((lo, hi));
+            }
+        }
+        FrustumCells {
+            frustum: *self,
+            frame,
+            tier,
+            lod,
+            stack,
+            ready: Vec::new(),
+        }
+    }
+
+    /// Corners of the frustum, if its planes meet in the usual layout
+    pub fn corners(&self) -> Option<[[f64; 3]; 8]> {
+        let mut corners = [[0.0; 3]; 8];
+        for (i, corner) in corners.iter_mut().enumerate() {
+            let a = self.planes[i & 1];
+            let b = self.planes[2 + ((i >> 1) & 1)];
+            let c = self.planes[4 + ((i >> 2) & 1)];
+            *corner = intersect_planes(a, b, c)?;
+        }
+        Some(corners)
+    }
+
+    fn bounding_box(&self) -> Option<([f64; 3], [f64; 3])> {
+        let corners = self.corners()?;
+        let mut lo = [f64::INFINITY; 3];
+        let mut hi = [f64::NEG_INFINITY; 3];
+        for c in corners {
+            for i in 0..3 {
+                lo[i] = lo[i].min(c[i]);
+                hi[i] = hi[i].max(c[i]);
+            }
+        }
+        Some((lo, hi))
+    }
+
+    /// Classify a convex shape from its center and support function
+    fn classify(&self, center: [f64; 3], support: impl Fn([f64; 3]) -> f64) -> Containment {
+        let mut result = Containment::Inside;
+        for plane in &self.planes {
+            let s = plane.signed_distance(center);
+            let h = support(plane.normal);
+            if s + h < 0.0 {
+                return Containment::Outside;
+            }
+            if s - h < 0.0 {
+                result = Containment::Intersects;
+            }
+        }
+        result
+    }
+}
+
+/// Iterator over the cells inside a frustum, from [`Frustum::cells`]
+#[derive(Debug, Clone)]
+pub struct FrustumCells {
+    frustum: Frustum,
+    frame: FrameId,
+    tier: u8,
+    lod: u8,
+    /// Inclusive integer boxes still to examine
+    stack: Vec<([i32; 3], [i32; 3])>,
+    ready: Vec<Index64>,
+}
+
+impl FrustumCells {
+    /// Queue the BCC points of a box, optionally testing each cell
+    fn emit(&mut self, lo: [i32; 3], hi: [i32; 3], test: bool) {
+        for z in lo[2]..=hi[2] {
+            for y in lo[1]..=hi[1] {
+                if (y ^ z) & 1 != 0 {
+                    continue;
+                }
+                let start = lo[0] + ((lo[0] ^ y) & 1);
+                for x in (start..=hi[0]).step_by(2) {
+                    let Ok(idx) = Index64::new(
+                        self.frame, self.tier, self.lod, x as u16, y as u16, z as u16,
+                    ) else {
+                        continue;
+                    };
+                    if !test || self.frustum.is_cell_visible(idx) {
+                        self.ready.push(idx);
+                    }
+                }
+            }
+        }
+        self.ready.reverse();
+    }
+}
+
+impl Iterator for FrustumCells {
+    type Item = Index64;
+
+    fn next(&mut self) -> Option<Index64> {
+        loop {
+            if let Some(idx) = self.ready.pop() {
+                return Some(idx);
+            }
+            let (lo, hi) = self.stack.pop()?;
+            // Cells of the points in the box stay within one unit of it
+            let min = [lo[0] as f64 - 1.0, lo[1] as f64 - 1.0, lo[2] as f64 - 1.0];
+            let max = [hi[0] as f64 + 1.0, hi[1] as f64 + 1.0, hi[2] as f64 + 1.0];
+            match self.frustum.classify_box(min, max) {
+                Containment::Outside => {}
+                Containment::Inside => self.emit(lo, hi, false),
+                Containment::Intersects => {
+                    let extent = [hi[0] - lo[0], hi[1] - lo[1], hi[2] - lo[2]];
+                    let axis = (0..3).max_by_key(|&i| extent[i]).unwrap_or(0);
+                    if extent[axis] < 4 {
+                        self.emit(lo, hi, true);
+                    } else {
+                        let mid = lo[axis] + extent[axis] / 2;
+                        let (mut left_hi, mut right_lo) = (hi, lo);
+                        left_hi[axis] = mid;
+                        right_lo[axis] = mid + 1;
+                        // Pop the lower half first so output follows the axes
+                        self.stack.push((right_lo, hi));
+                        self.stack.push((lo, left_hi));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Support function of the unit BCC Voronoi cell (truncated octahedron)
+///
+/// The maximum of `n · v` over its vertices, the permutations of
+/// `(±1, ±1/2, 0)`: the largest `|n_i|` plus half the second largest.
+fn truncated_octahedron_support(n: [f64; 3]) -> f64 {
+    let mut a = [n[0].abs(), n[1].abs(), n[2].abs()];
+    a.sort_by(|x, y| y.total_cmp(x));
+    a[0] + 0.5 * a[1]
+}
+
+fn intersect_planes(a: Plane, b: Plane, c: Plane) -> Option<[f64; 3]> {
+    let bc = cross(b.normal, c.normal);
+    let det = dot(a.normal, bc);
+    if det.abs() < 1e-12 {
+        return None;
+    }
+    let ca = cross(c.normal, a.normal);
+    let ab = cross(a.normal, b.normal);
+    let p = add(
+        add(scale(bc, -a.offset), scale(ca, -b.offset)),
+        scale(ab, -c.offset),
+    );
+    Some(scale(p, 1.0 / det))
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn add(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn scale(a: [f64; 3], s: f64) -> [f64; 3] {
+    [a[0] * s, a[1] * s, a[2] * s]
+}
+
+fn normalize(a: [f64; 3]) -> [f64; 3] {
+    let len = dot(a, a).sqrt();
+    if len > 0.0 {
+        scale(a, 1.0 / len)
+    } else {
+        a
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Result;
+
+    fn camera() -> Frustum {
+        Frustum::perspective(
+            [50.0, 50.0, 50.0],
+            [1.0, 0.3, -0.2],
+            [0.0, 0.0, 1.0],
+            0.9,
+            1.6,
+            2.0,
+            30.0,
+        )
+    }
+
+    #[test]
+    fn test_cells_matches_brute_force() -> Result<()> {
+        let frustum = camera();
+        let fast: Vec<Index64> = frustum.cells(0, 0, 5).collect();
+
+        let mut slow = Vec::new();
+        for z in 0..120u16 {
+            for y in 0..120u16 {
+                for x in 0..120u16 {
+                    if x % 2 == y % 2 && y % 2 == z % 2 {
+                        let idx = Index64::new(0, 0, 5, x, y, z)?;
+                        if frustum.is_cell_visible(idx) {
+                            slow.push(idx);
+                        }
+                    }
+                }
+            }
+        }
+        let mut fast_sorted = fast.clone();
+        fast_sorted.sort_unstable();
+        slow.sort_unstable();
+        assert_eq!(fast_sorted, slow);
+        assert!(fast.len() > 100);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cell_bounds_not_centers() -> Result<()> {
+        // Near plane at x = 10.5: the cell at x = 10 reaches x = 11
+        let frustum = Frustum::perspective(
+            [0.0, 20.0, 20.0],
+            [1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0],
+            1.0,
+            1.0,
+            10.5,
+            40.0,
+        );
+        let cell = Index64::new(0, 0, 5, 10, 20, 20)?;
+        assert!(!frustum.contains_point([10.0, 20.0, 20.0]));
+        assert_eq!(frustum.classify_cell(cell), Containment::Intersects);
+        // Beyond the square face at x + 1
+        assert!(!frustum.is_cell_visible(Index64::new(0, 0, 5, 8, 20, 20)?));
+        assert_eq!(
+            frustum.classify_cell(Index64::new(0, 0, 5, 20, 20, 20)?),
+            Containment::Inside
+        );
+
+        let cells = [cell, Index64::new(0, 0, 5, 8, 20, 20)?];
+        assert_eq!(frustum.cull(cells).collect::<Vec<_>>(), vec![cell]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_matrix_planes_match_perspective() {
+        // OpenGL perspective looking down -z from the origin
+        let (near, far, f) = (1.0, 10.0, 1.0 / (0.5f64).tan());
+        let m = [
+            [f, 0.0, 0.0, 0.0],
+            [0.0, f, 0.0, 0.0],
+            [
+                0.0,
+                0.0,
+                (far + near) / (near - far),
+                2.0 * far * near / (near - far),
+            ],
+            [0.0, 0.0, -1.0, 0.0],
+        ];
+        let from_matrix = Frustum::from_view_projection(m);
+        let direct = Frustum::perspective(
+            [0.0; 3],
+            [0.0, 0.0, -1.0],
+            [0.0, 1.0, 0.0],
+            1.0,
+            1.0,
+            near,
+            far,
+        );
+        for p in [
+            [0.0, 0.0, -5.0],
+            [3.0, 0.0, -5.0],
+            [0.0, 0.0, -0.5],
+            [0.0, 0.0, -11.0],
+        ] {
+            assert_eq!(from_matrix.contains_point(p), direct.contains_point(p));
+        }
+        let corners = from_matrix.corners().unwrap();
+        assert!(corners.iter().all(|c| (-10.5..=-0.5).contains(&c[2])));
+    }
+}
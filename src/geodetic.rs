@@ -0,0 +1,222 @@
+//! WGS84 geodetic coordinates, ECEF and the lattice
+//!
+//! Converts between geodetic positions (latitude and longitude in degrees,
+//! height above the ellipsoid in meters), Earth-Centered Earth-Fixed
+//! Cartesian coordinates, and [`Index64`] cells of any frame whose parent
+//! chain reaches [`ECEF_FRAME`](crate::frame::ECEF_FRAME).
+//!
+//! ## Cells and LODs
+//!
+//! Index coordinates are frame coordinates: at LOD 15 a cell spans one
+//! frame unit, and each coarser LOD doubles the cell edge, matching
+//! [`Index64::parent`]. A position snaps to the cell containing it, and a
+//! cell converts back to its center. Cells are created at tier 0.
+//!
+//! The built-in [`ECEF_GRID_FRAME`](crate::frame::ECEF_GRID_FRAME) covers
+//! the whole globe with 512 m units, so its LOD 15 cells are 512 m wide.
+//! Finer maps register a local frame, such as one built by
+//! [`FrameDescriptor::local_enu`](crate::FrameDescriptor::local_enu), and
+//! index in that frame.
+//!
+//! ```
+//! use octaindex3d::frame::ECEF_GRID_FRAME;
+//! use octaindex3d::geodetic;
+//!
+//! # fn main() -> octaindex3d::Result<()> {
+//! // Zurich main station, in 4 km cells
+//! let cell = geodetic::geodetic_to_index(ECEF_GRID_FRAME, 12, 47.378, 8.540, 408.0)?;
+//! let (lat, lon, _) = geodetic::index_to_geodetic(cell)?;
+//! assert!((lat - 47.378).abs() < 0.05 && (lon - 8.540).abs() < 0.05);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{Error, Result};
+pub use crate::frame::cell_extent;
+use crate::frame::{frame_transform, ECEF_FRAME};
+use crate::ids::{FrameId, Index64};
+
+/// Reference ellipsoid
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ellipsoid {
+    /// Semi-major axis (meters)
+    pub a: f64,
+    /// Flattening
+    pub f: f64,
+}
+
+/// World Geodetic System 1984 ellipsoid
+pub const WGS84: Ellipsoid = Ellipsoid {
+    a: 6_378_137.0,
+    f: 1.0 / 298.257_223_563,
+};
+
+impl Ellipsoid {
+    /// Semi-minor axis (meters)
+    pub fn b(&self) -> f64 {
+        self.a * (1.0 - self.f)
+    }
+
+    /// First eccentricity squared
+    pub fn e2(&self) -> f64 {
+        self.f * (2.0 - self.f)
+    }
+
+    /// ECEF coordinates of a geodetic position
+    pub fn to_ecef(&self, lat_deg: f64, lon_deg: f64, alt_m: f64) -> (f64, f64, f64) {
+        let (sin_lat, cos_lat) = lat_deg.to_radians().sin_cos();
+        let (sin_lon, cos_lon) = lon_deg.to_radians().sin_cos();
+        let e2 = self.e2();
+        // Prime vertical radius of curvature
+        let n = self.a / (1.0 - e2 * sin_lat * sin_lat).sqrt();
+        (
+            (n + alt_m) * cos_lat * cos_lon,
+            (n + alt_m) * cos_lat * sin_lon,
+            (n * (1.0 - e2) + alt_m) * sin_lat,
+        )
+    }
+
+    /// Geodetic position `(lat_deg, lon_deg, alt_m)` of ECEF coordinates
+    ///
+    /// Iterates on latitude until it settles, which takes a handful of
+    /// steps for points within a few thousand kilometers of the surface.
+    /// Results are accurate to well below a millimeter there.
+    pub fn from_ecef(&self, ecef: (f64, f64, f64)) -> (f64, f64, f64) {
+        let (x, y, z) = ecef;
+        let e2 = self.e2();
+        let p = x.hypot(y);
+        let lon = y.atan2(x);
+
+        let mut lat = z.atan2(p * (1.0 - e2));
+        for _ in 0..16 {
+            let sin_lat = lat.sin();
+            let n = self.a / (1.0 - e2 * sin_lat * sin_lat).sqrt();
+            let next = (z + e2 * n * sin_lat).atan2(p);
+            let settled = (next - lat).abs() < 1e-15;
+            lat = next;
+            if settled {
+                break;
+            }
+        }
+
+        // Height along the normal, well conditioned at the poles too
+        let (sin_lat, cos_lat) = lat.sin_cos();
+        let alt = p * cos_lat + z * sin_lat - self.a * (1.0 - e2 * sin_lat * sin_lat).sqrt();
+        (lat.to_degrees(), lon.to_degrees(), alt)
+    }
+}
+
+/// [`Ellipsoid::to_ecef`] on [`WGS84`]
+pub fn wgs84_to_ecef(lat_deg: f64, lon_deg: f64, alt_m: f64) -> (f64, f64, f64) {
+    WGS84.to_ecef(lat_deg, lon_deg, alt_m)
+}
+
+/// [`Ellipsoid::from_ecef`] on [`WGS84`]
+pub fn ecef_to_wgs84(ecef: (f64, f64, f64)) -> (f64, f64, f64) {
+    WGS84.from_ecef(ecef)
+}
+
+/// Cell of `frame` at `lod` containing an ECEF position
+pub fn ecef_to_index(frame: FrameId, lod: u8, ecef: (f64, f64, f64)) -> Result<Index64> {
+    let extent = cell_extent(lod)?;
+    let (x, y, z) = frame_transform(ECEF_FRAME, frame)?.apply(ecef);
+    let coord = |v: f64| -> Result<u16> {
+        let cell = (v / extent).floor();
+        if (0.0..=u16::MAX as f64).contains(&cell) {
+            Ok(cell as u16)
+        } else {
+            Err(Error::OutOfRange(format!(
+                "position {} lies outside frame {} at lod {}",
+                v, frame, lod
+            )))
+        }
+    };
+    Index64::new(frame, 0, lod, coord(x)?, coord(y)?, coord(z)?)
+}
+
+/// ECEF position of a cell's center
+pub fn index_to_ecef(index: Index64) -> Result<(f64, f64, f64)> {
+    let extent = cell_extent(index.lod())?;
+    let (x, y, z) = index.decode_coords();
+    let center = |c: u16| (c as f64 + 0.5) * extent;
+    Ok(frame_transform(index.frame_id(), ECEF_FRAME)?.apply((center(x), center(y), center(z))))
+}
+
+/// Cell of `frame` at `lod` containing a WGS84 position
+pub fn geodetic_to_index(
+    frame: FrameId,
+    lod: u8,
+    lat_deg: f64,
+    lon_deg: f64,
+    alt_m: f64,
+) -> Result<Index64> {
+    ecef_to_index(frame, lod, wgs84_to_ecef(lat_deg, lon_deg, alt_m))
+}
+
+/// WGS84 position `(lat_deg, lon_deg, alt_m)` of a cell's center
+pub fn index_to_geodetic(index: Index64) -> Result<(f64, f64, f64)> {
+    Ok(ecef_to_wgs84(index_to_ecef(index)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::{register_frame, FrameDescriptor, FrameTransform, ECEF_GRID_FRAME};
+
+    #[test]
+    fn test_ecef_round_trip() {
+        // Equator at the prime meridian and the north pole
+        let (x, y, z) = wgs84_to_ecef(0.0, 0.0, 0.0);
+        assert_eq!((x, y, z), (WGS84.a, 0.0, 0.0));
+        let (_, _, z) = wgs84_to_ecef(90.0, 0.0, 100.0);
+        assert!((z - (WGS84.b() + 100.0)).abs() < 1e-6);
+
+        for &(lat, lon, alt) in &[
+            (47.378, 8.540, 408.0),
+            (-33.857, 151.215, -30.0),
+            (89.999_9, -120.0, 10_000.0),
+            (-90.0, 0.0, 0.0),
+            (12.0, 179.999, 400_000.0),
+        ] {
+            let (lat2, lon2, alt2) = ecef_to_wgs84(wgs84_to_ecef(lat, lon, alt));
+            assert!((lat2 - lat).abs() < 1e-10, "{} {}", lat, lat2);
+            if lat.abs() < 90.0 {
+                assert!((lon2 - lon).abs() < 1e-10, "{} {}", lon, lon2);
+            }
+            assert!((alt2 - alt).abs() < 1e-6, "{} {}", alt, alt2);
+        }
+    }
+
+    #[test]
+    fn test_lattice_snapping() {
+        // Global grid: the cell center is within half a cell diagonal
+        let cell = geodetic_to_index(ECEF_GRID_FRAME, 15, -33.857, 151.215, 0.0).unwrap();
+        assert_eq!(cell.lod(), 15);
+        let center = index_to_ecef(cell).unwrap();
+        let p = wgs84_to_ecef(-33.857, 151.215, 0.0);
+        let d =
+            ((center.0 - p.0).powi(2) + (center.1 - p.1).powi(2) + (center.2 - p.2).powi(2)).sqrt();
+        assert!(d <= 512.0 * 3f64.sqrt() / 2.0);
+
+        // A local frame in meters centered on a site gives 1 m cells at LOD 15
+        let origin = wgs84_to_ecef(47.378, 8.540, 408.0);
+        let site = FrameDescriptor::new("GEO-SITE", "WGS-84", "Test site", true, 1.0).with_parent(
+            ECEF_FRAME,
+            FrameTransform::IDENTITY.with_translation([
+                origin.0 - 32768.0,
+                origin.1 - 32768.0,
+                origin.2 - 32768.0,
+            ]),
+        );
+        register_frame(120, site).unwrap();
+        let cell = geodetic_to_index(120, 15, 47.378, 8.540, 408.0).unwrap();
+        assert_eq!(cell.decode_coords(), (32768, 32768, 32768));
+        let (lat, lon, alt) = index_to_geodetic(cell).unwrap();
+        assert!((lat - 47.378).abs() < 1e-5 && (lon - 8.540).abs() < 1e-5);
+        assert!((alt - 408.0).abs() < 1.0);
+
+        // Positions outside the frame and invalid LODs fail
+        assert!(geodetic_to_index(120, 15, 0.0, 0.0, 0.0).is_err());
+        assert!(geodetic_to_index(ECEF_GRID_FRAME, 16, 0.0, 0.0, 0.0).is_err());
+    }
+}
@@ -0,0 +1,447 @@
+//! Geofences and no-fly zones
+//!
+//! A [`Fence`] is a region of space with an [`FenceAction`]: no-fly zones
+//! block every cell they touch, restricted zones make travel through them
+//! more expensive. Fences are defined in the physical frame of a
+//! [`BccGrid`] and compiled with [`FenceMap::compile`] into a blocked cell
+//! set plus per-cell zone tags at that grid's resolution; compile once per
+//! level of detail by passing the grid for that level.
+//!
+//! A compiled [`FenceMap`] is enforced by planners through
+//! [`CompositeCost::with_fences`](crate::routing::CompositeCost::with_fences),
+//! and existing paths can be checked with [`FenceMap::validate_path`].
+//!
+//! # Example
+//!
+//! ```
+//! use octaindex3d::geofence::{Fence, FenceMap};
+//! use octaindex3d::grid::BccGrid;
+//! use octaindex3d::routing::{astar, CompositeCost, EuclideanCost};
+//!
+//! # fn main() -> octaindex3d::Result<()> {
+//! let grid = BccGrid::new(1.0)?;
+//! // A 4 m tall tower footprint between start and goal
+//! let tower = Fence::no_fly_polygon(
+//!     "tower",
+//!     vec![(4.0, -3.0), (6.0, -3.0), (6.0, 3.0), (4.0, 3.0)],
+//!     -1.0,
+//!     4.0,
+//! );
+//! let fences = FenceMap::compile(&[tower], &grid, 0.0)?;
+//!
+//! let cost = CompositeCost::new(EuclideanCost).with_fences(&fences);
+//! let start = grid.cell_at(0.0, 0.0, 0.0)?;
+//! let goal = grid.cell_at(10.0, 0.0, 0.0)?;
+//! let path = astar(start, goal, &cost)?;
+//!
+//! assert!(fences.validate_path(&path.cells).is_empty());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{Error, Result};
+use crate::grid::BccGrid;
+use crate::ids::Route64;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+/// Geometry of a fence, in the physical frame of the grid it is compiled on
+#[derive(Debug, Clone, PartialEq)]
+pub enum FenceShape {
+    /// Vertical prism: a horizontal polygon extruded over an altitude band
+    Polygon {
+        /// Polygon vertices `(x, y)`, in order (closing edge implied)
+        vertices: Vec<(f64, f64)>,
+        /// Bottom of the altitude band (z)
+        min_altitude: f64,
+        /// Top of the altitude band (z)
+        max_altitude: f64,
+    },
+    /// Ball around a point
+    Sphere {
+        /// Center `(x, y, z)`
+        center: (f64, f64, f64),
+        /// Radius
+        radius: f64,
+    },
+}
+
+impl FenceShape {
+    /// Check if a point lies within `margin` of the shape
+    pub fn contains(&self, point: (f64, f64, f64), margin: f64) -> bool {
+        let (x, y, z) = point;
+        match self {
+            FenceShape::Polygon {
+                vertices,
+                min_altitude,
+                max_altitude,
+            } => {
+                z >= min_altitude - margin
+                    && z <= max_altitude + margin
+                    && (point_in_polygon(vertices, x, y)
+                        || (margin > 0.0 && distance_to_boundary(vertices, x, y) <= margin))
+            }
+            FenceShape::Sphere { center, radius } => {
+                let (dx, dy, dz) = (x - center.0, y - center.1, z - center.2);
+                (dx * dx + dy * dy + dz * dz).sqrt() <= radius + margin
+            }
+        }
+    }
+
+    /// Axis-aligned bounding box `(min, max)`
+    pub fn bounds(&self) -> ((f64, f64, f64), (f64, f64, f64)) {
+        match self {
+            FenceShape::Polygon {
+                vertices,
+                min_altitude,
+                max_altitude,
+            } => {
+                let mut min = (f64::INFINITY, f64::INFINITY, *min_altitude);
+                let mut max = (f64::NEG_INFINITY, f64::NEG_INFINITY, *max_altitude);
+                for &(x, y) in vertices {
+                    min.0 = min.0.min(x);
+                    min.1 = min.1.min(y);
+                    max.0 = max.0.max(x);
+                    max.1 = max.1.max(y);
+                }
+                (min, max)
+            }
+            FenceShape::Sphere { center, radius } => (
+                (center.0 - radius, center.1 - radius, center.2 - radius),
+                (center.0 + radius, center.1 + radius, center.2 + radius),
+            ),
+        }
+    }
+}
+
+/// How planners treat cells inside a fence
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FenceAction {
+    /// Cells are impassable
+    NoFly,
+    /// Cells are passable at an extra cost per lattice unit travelled
+    Restricted {
+        /// Additional cost per lattice unit, on top of the base cost
+        penalty: f64,
+    },
+}
+
+/// A named fence
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fence {
+    /// Human-readable name, reported in violations
+    pub name: String,
+    /// Fence geometry
+    pub shape: FenceShape,
+    /// Effect on planning
+    pub action: FenceAction,
+}
+
+impl Fence {
+    /// Create a fence
+    pub fn new(name: impl Into<String>, shape: FenceShape, action: FenceAction) -> Self {
+        Self {
+            name: name.into(),
+            shape,
+            action,
+        }
+    }
+
+    /// No-fly prism over a polygon footprint
+    pub fn no_fly_polygon(
+        name: impl Into<String>,
+        vertices: Vec<(f64, f64)>,
+        min_altitude: f64,
+        max_altitude: f64,
+    ) -> Self {
+        Self::new(
+            name,
+            FenceShape::Polygon {
+                vertices,
+                min_altitude,
+                max_altitude,
+            },
+            FenceAction::NoFly,
+        )
+    }
+
+    /// No-fly sphere
+    pub fn no_fly_sphere(name: impl Into<String>, center: (f64, f64, f64), radius: f64) -> Self {
+        Self::new(
+            name,
+            FenceShape::Sphere { center, radius },
+            FenceAction::NoFly,
+        )
+    }
+}
+
+/// A path cell that enters a no-fly zone
+#[derive(Debug, Clone, PartialEq)]
+pub struct FenceViolation {
+    /// Index of the cell in the path
+    pub index: usize,
+    /// The offending cell
+    pub cell: Route64,
+    /// Name of the violated fence
+    pub fence: String,
+}
+
+/// Fences compiled to cells of a [`BccGrid`]
+#[derive(Debug, Clone)]
+pub struct FenceMap {
+    fences: Vec<Fence>,
+    blocked: FxHashSet<Route64>,
+    zones: FxHashMap<Route64, Vec<u16>>,
+}
+
+impl FenceMap {
+    /// Compile fences to the cells of `grid`
+    ///
+    /// A cell belongs to a fence when its center lies within `margin` of
+    /// the fence shape. Use a margin of half the cell size or more to also
+    /// capture cells that only partially overlap a fence. Compilation visits
+    /// every cell in each fence's bounding box, so very large fences on fine
+    /// grids are expensive.
+    pub fn compile(fences: &[Fence], grid: &BccGrid, margin: f64) -> Result<Self> {
+        if fences.len() > u16::MAX as usize {
+            return Err(Error::OutOfRange(format!(
+                "at most {} fences supported, got {}",
+                u16::MAX,
+                fences.len()
+            )));
+        }
+        let margin = margin.max(0.0);
+        let unit = grid.cell_size() / 2.0;
+        let mut blocked = FxHashSet::default();
+        let mut zones: FxHashMap<Route64, Vec<u16>> = FxHashMap::default();
+
+        for (tag, fence) in fences.iter().enumerate() {
+            if let FenceShape::Polygon { vertices, .. } = &fence.shape {
+                if vertices.len() < 3 {
+                    return Err(Error::InvalidFormat(format!(
+                        "fence '{}' has fewer than 3 vertices",
+                        fence.name
+                    )));
+                }
+            }
+
+            let (min, max) = fence.shape.bounds();
+            let lo = |v: f64| ((v - margin) / unit).floor() as i32;
+            let hi = |v: f64| ((v + margin) / unit).ceil() as i32;
+
+            for x in lo(min.0)..=hi(max.0) {
+                // BCC lattice points have all-even or all-odd coordinates
+                let parity = x.rem_euclid(2);
+                let first = |start: i32| start + (start - parity).rem_euclid(2);
+                for y in (first(lo(min.1))..=hi(max.1)).step_by(2) {
+                    for z in (first(lo(min.2))..=hi(max.2)).step_by(2) {
+                        let cell = Route64::new(0, x, y, z)?;
+                        if !fence.shape.contains(grid.center_of(cell), margin) {
+                            continue;
+                        }
+                        if fence.action == FenceAction::NoFly {
+                            blocked.insert(cell);
+                        }
+                        zones.entry(cell).or_default().push(tag as u16);
+                    }
+                }
+            }
+        }
+
+        Ok(Self {
+            fences: fences.to_vec(),
+            blocked,
+            zones,
+        })
+    }
+
+    /// The compiled fences
+    pub fn fences(&self) -> &[Fence] {
+        &self.fences
+    }
+
+    /// Check if a cell is inside a no-fly zone
+    pub fn is_blocked(&self, cell: Route64) -> bool {
+        self.blocked.contains(&cell)
+    }
+
+    /// Number of blocked cells
+    pub fn blocked_count(&self) -> usize {
+        self.blocked.len()
+    }
+
+    /// Fences containing a cell (zone tags)
+    pub fn zones_at(&self, cell: Route64) -> impl Iterator<Item = &Fence> + '_ {
+        self.zones
+            .get(&cell)
+            .into_iter()
+            .flatten()
+            .map(|&tag| &self.fences[tag as usize])
+    }
+
+    /// Total restricted-zone penalty per lattice unit at a cell
+    pub fn penalty(&self, cell: Route64) -> f64 {
+        self.zones_at(cell)
+            .map(|fence| match fence.action {
+                FenceAction::Restricted { penalty } => penalty,
+                FenceAction::NoFly => 0.0,
+            })
+            .sum()
+    }
+
+    /// Check if any fence is a restricted (penalty) zone
+    pub fn has_penalties(&self) -> bool {
+        self.fences
+            .iter()
+            .any(|f| matches!(f.action, FenceAction::Restricted { .. }))
+    }
+
+    /// Report every path cell that enters a no-fly zone
+    ///
+    /// Returns one violation per (cell, fence) pair; an empty result means
+    /// the path respects all no-fly zones.
+    pub fn validate_path(&self, path: &[Route64]) -> Vec<FenceViolation> {
+        let mut violations = Vec::new();
+        for (index, &cell) in path.iter().enumerate() {
+            if !self.is_blocked(cell) {
+                continue;
+            }
+            for fence in self.zones_at(cell) {
+                if fence.action == FenceAction::NoFly {
+                    violations.push(FenceViolation {
+                        index,
+                        cell,
+                        fence: fence.name.clone(),
+                    });
+                }
+            }
+        }
+        violations
+    }
+}
+
+/// Even-odd rule point-in-polygon test
+fn point_in_polygon(vertices: &[(f64, f64)], x: f64, y: f64) -> bool {
+    let mut inside = false;
+    let mut j = vertices.len() - 1;
+    for i in 0..vertices.len() {
+        let (xi, yi) = vertices[i];
+        let (xj, yj) = vertices[j];
+        if (yi > y) != (yj > y) && x < (xj - xi) * (y - yi) / (yj - yi) + xi {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+/// Distance from a point to the nearest polygon edge
+fn distance_to_boundary(vertices: &[(f64, f64)], x: f64, y: f64) -> f64 {
+    let mut best = f64::INFINITY;
+    let mut j = vertices.len() - 1;
+    for i in 0..vertices.len() {
+        let (ax, ay) = vertices[j];
+        let (bx, by) = vertices[i];
+        let (ex, ey) = (bx - ax, by - ay);
+        let len2 = ex * ex + ey * ey;
+        let t = if len2 > 0.0 {
+            (((x - ax) * ex + (y - ay) * ey) / len2).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let (px, py) = (ax + t * ex - x, ay + t * ey - y);
+        best = best.min((px * px + py * py).sqrt());
+        j = i;
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::routing::{astar, CompositeCost, Cost, EuclideanCost};
+
+    fn wall(action: FenceAction) -> Fence {
+        // Slab across the straight line from start to goal, open above z = 6
+        Fence::new(
+            "wall",
+            FenceShape::Polygon {
+                vertices: vec![(4.0, -20.0), (6.0, -20.0), (6.0, 20.0), (4.0, 20.0)],
+                min_altitude: -20.0,
+                max_altitude: 6.0,
+            },
+            action,
+        )
+    }
+
+    #[test]
+    fn test_compile_shapes() -> Result<()> {
+        let grid = BccGrid::new(1.0)?;
+        let sphere = Fence::no_fly_sphere("ball", (0.0, 0.0, 0.0), 1.5);
+        let fences = FenceMap::compile(&[sphere], &grid, 0.0)?;
+
+        assert!(fences.is_blocked(grid.cell_at(0.0, 0.0, 0.0)?));
+        assert!(fences.is_blocked(grid.cell_at(1.0, 0.0, 0.0)?));
+        assert!(!fences.is_blocked(grid.cell_at(2.0, 0.0, 0.0)?));
+        assert_eq!(
+            fences
+                .zones_at(grid.cell_at(0.5, 0.5, 0.5)?)
+                .next()
+                .map(|f| f.name.as_str()),
+            Some("ball")
+        );
+
+        let degenerate = Fence::no_fly_polygon("line", vec![(0.0, 0.0), (1.0, 0.0)], 0.0, 1.0);
+        assert!(FenceMap::compile(&[degenerate], &grid, 0.0).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_fly_enforced_by_composite_cost() -> Result<()> {
+        let grid = BccGrid::new(1.0)?;
+        let fences = FenceMap::compile(&[wall(FenceAction::NoFly)], &grid, 0.0)?;
+        let start = grid.cell_at(0.0, 0.0, 0.0)?;
+        let goal = grid.cell_at(10.0, 0.0, 0.0)?;
+
+        let direct = astar(start, goal, &EuclideanCost)?;
+        let violations = fences.validate_path(&direct.cells);
+        assert!(!violations.is_empty());
+        assert!(violations.iter().all(|v| v.fence == "wall"));
+
+        let cost = CompositeCost::new(EuclideanCost).with_fences(&fences);
+        assert!(cost.is_uniform());
+        let fenced = astar(start, goal, &cost)?;
+        assert!(fences.validate_path(&fenced.cells).is_empty());
+        assert!(fenced.cost > direct.cost);
+        Ok(())
+    }
+
+    #[test]
+    fn test_restricted_zone_penalty() -> Result<()> {
+        let grid = BccGrid::new(1.0)?;
+        let start = grid.cell_at(0.0, 0.0, 0.0)?;
+        let goal = grid.cell_at(10.0, 0.0, 0.0)?;
+
+        // A small penalty is cheaper to pay than the detour over the wall
+        let cheap = FenceMap::compile(
+            &[wall(FenceAction::Restricted { penalty: 0.1 })],
+            &grid,
+            0.0,
+        )?;
+        let cost = CompositeCost::new(EuclideanCost).with_fences(&cheap);
+        assert!(!cost.is_uniform());
+        let path = astar(start, goal, &cost)?;
+        assert!(cheap.validate_path(&path.cells).is_empty());
+        assert!(path.cells.iter().any(|&c| cheap.penalty(c) > 0.0));
+
+        // A large penalty makes the planner go around
+        let costly = FenceMap::compile(
+            &[wall(FenceAction::Restricted { penalty: 100.0 })],
+            &grid,
+            0.0,
+        )?;
+        let cost = CompositeCost::new(EuclideanCost).with_fences(&costly);
+        let path = astar(start, goal, &cost)?;
+        assert!(path.cells.iter().all(|&c| costly.penalty(c) == 0.0));
+        Ok(())
+    }
+}
@@ -3,7 +3,8 @@
 //! Converts Galactic128 IDs to GeoJSON format with WGS84 coordinates.
 
 use crate::error::Result;
-use crate::frame::get_frame;
+use crate::frame::{frame_transform, get_frame, ECEF_FRAME};
+use crate::geodetic::ecef_to_wgs84;
 use crate::ids::Galactic128;
 use serde_json::{json, Value};
 use std::fs::File;
@@ -149,31 +150,17 @@ fn id_to_geojson_point(id: &Galactic128, opts: &GeoJsonOptions) -> Result<Value>
 
 /// Convert Galactic128 coordinates to WGS84 (lon, lat, alt)
 fn id_to_wgs84(id: &Galactic128) -> Result<(f64, f64, f64)> {
-    // Get frame descriptor
-    let frame = get_frame(id.frame_id())?;
-
-    // Get coordinates
-    let x = id.x() as f64;
-    let y = id.y() as f64;
-    let z = id.z() as f64;
-
-    // Simple conversion for frame 0 (ECEF-like)
-    // For v0.3.1, we assume a simple ENU-to-WGS84 approximation
-    // More complex CRS transforms deferred to later versions
-
-    if frame.name == "ECEF" {
-        // Simplified ECEF to WGS84 conversion
-        // In real implementation, this would use proper geodetic conversion
-        let lon = (x / 111320.0).to_radians(); // approximate meters to degrees
-        let lat = (y / 110540.0).to_radians();
-        let alt = z;
-        Ok((lon.to_degrees(), lat.to_degrees(), alt))
-    } else {
-        // For other frames, assume local ENU coordinates
-        // Scale by base unit
-        let scale = frame.base_unit;
-        Ok((x * scale, y * scale, z * scale))
+    let point = (id.x() as f64, id.y() as f64, id.z() as f64);
+
+    // Frames placed in ECEF get a proper geodetic conversion
+    if let Ok(transform) = frame_transform(id.frame_id(), ECEF_FRAME) {
+        let (lat, lon, alt) = ecef_to_wgs84(transform.apply(point));
+        return Ok((lon, lat, alt));
     }
+
+    // Other frames are treated as local ENU coordinates, scaled by base unit
+    let scale = get_frame(id.frame_id())?.base_unit;
+    Ok((point.0 * scale, point.1 * scale, point.2 * scale))
 }
 
 fn round_coordinate(value: f64, precision: u8) -> f64 {
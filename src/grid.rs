@@ -292,7 +292,7 @@ impl BccGrid {
 
 /// Euclidean distance between two cells in lattice units
 #[inline]
-fn lattice_distance(a: Route64, b: Route64) -> f64 {
+pub(crate) fn lattice_distance(a: Route64, b: Route64) -> f64 {
     let dx = (a.x() - b.x()) as f64;
     let dy = (a.y() - b.y()) as f64;
     let dz = (a.z() - b.z()) as f64;
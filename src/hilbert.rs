@@ -2,9 +2,12 @@
 //!
 //! Provides 64-bit Hilbert curve keys with better spatial locality than Morton codes.
 //! Uses table-driven Butz/Skilling algorithm for efficient encode/decode.
+//! [`box_ranges`] turns a 3D box into the Hilbert ranges covering it.
 
 use crate::error::{Error, Result};
 use crate::ids::{FrameId, Index64};
+use std::collections::BinaryHeap;
+use std::ops::RangeInclusive;
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
@@ -126,6 +129,19 @@ impl Hilbert64 {
         self.value
     }
 
+    /// Create from raw u64 value
+    pub fn from_value(value: u64) -> Result<Self> {
+        let header = value >> 62;
+        if header != Self::HDR {
+            return Err(Error::DecodingError(format!(
+                "Invalid Hilbert64 header: expected 0x{:02x}, got 0x{:02x}",
+                Self::HDR,
+                header
+            )));
+        }
+        Ok(Self { value })
+    }
+
     /// Batch encode multiple coordinates
     pub fn encode_batch(
         coords: &[(u16, u16, u16)],
@@ -138,6 +154,43 @@ impl Hilbert64 {
             .map(|&(x, y, z)| Self::new(frame, tier, lod, x, y, z))
             .collect()
     }
+
+    /// Contiguous `Hilbert64` ranges covering an inclusive coordinate box
+    ///
+    /// Turns a box query over Hilbert-sorted data into a handful of range
+    /// scans; see [`box_ranges`] for the decomposition and the meaning of
+    /// `max_ranges`.
+    ///
+    /// ```
+    /// use octaindex3d::Hilbert64;
+    ///
+    /// # fn main() -> octaindex3d::Result<()> {
+    /// let ranges = Hilbert64::box_ranges(0, 0, 5, (10, 10, 10), (13, 17, 10), usize::MAX)?;
+    /// let key = Hilbert64::new(0, 0, 5, 12, 15, 10)?;
+    /// assert!(ranges.iter().any(|r| r.contains(&key)));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn box_ranges(
+        frame: FrameId,
+        tier: u8,
+        lod: u8,
+        min: (u16, u16, u16),
+        max: (u16, u16, u16),
+        max_ranges: usize,
+    ) -> Result<Vec<RangeInclusive<Self>>> {
+        let header = Self::new(frame, tier, lod, 0, 0, 0)?.value & !0xFFFFFFFFFFFF;
+        Ok(box_ranges(min, max, max_ranges)
+            .into_iter()
+            .map(|r| {
+                Self {
+                    value: header | r.start(),
+                }..=Self {
+                    value: header | r.end(),
+                }
+            })
+            .collect())
+    }
 }
 
 /// Encode 3D coordinates to Hilbert curve index
@@ -196,6 +249,145 @@ fn gray_to_binary_3bit(gray: u8) -> u8 {
     binary & 0x7
 }
 
+/// Cover an inclusive 3D box with contiguous Hilbert code ranges
+///
+/// Every aligned octree cell maps to one contiguous run of codes (its
+/// prefix), so the box is decomposed by descending the octree from the
+/// root: cells inside the box become ranges, cells outside are dropped and
+/// cells crossing the boundary are split into their 8 children. The result
+/// is sorted, with adjacent ranges merged, so a box query over
+/// Hilbert-sorted data becomes one range scan per entry.
+///
+/// At most `max_ranges` ranges are returned. When the exact cover needs
+/// more, the cells holding the most codes outside the box are left
+/// unsplit, so ranges may then include cells outside the box and results
+/// must still be filtered. Pass `usize::MAX` for an exact cover.
+///
+/// Corners are normalized, so `min` and `max` may be given in any order.
+///
+/// # Example
+///
+/// ```
+/// use octaindex3d::hilbert::box_ranges;
+///
+/// let ranges = box_ranges((1, 1, 1), (2, 2, 2), usize::MAX);
+/// let covered: u64 = ranges.iter().map(|r| r.end() - r.start() + 1).sum();
+/// assert_eq!(covered, 8);
+///
+/// // A budget of one range spans the whole box with some waste
+/// let coarse = box_ranges((1, 1, 1), (2, 2, 2), 1);
+/// assert_eq!(coarse.len(), 1);
+/// ```
+pub fn box_ranges(
+    min: (u16, u16, u16),
+    max: (u16, u16, u16),
+    max_ranges: usize,
+) -> Vec<RangeInclusive<u64>> {
+    let lo = (min.0.min(max.0), min.1.min(max.1), min.2.min(max.2));
+    let hi = (min.0.max(max.0), min.1.max(max.1), min.2.max(max.2));
+    let max_ranges = max_ranges.max(1);
+
+    // Cells still to split, most wasted codes first
+    let mut pending = BinaryHeap::new();
+    let mut done: Vec<RangeInclusive<u64>> = Vec::new();
+    pending.push(OctreeCell::root(lo, hi));
+
+    while let Some(cell) = pending.pop() {
+        let children: Vec<OctreeCell> = if cell.waste == 0 {
+            Vec::new()
+        } else {
+            cell.children(lo, hi).collect()
+        };
+        // Splitting replaces one range with one per child
+        if children.is_empty() || done.len() + pending.len() + children.len() > max_ranges {
+            done.push(cell.codes());
+            continue;
+        }
+        pending.extend(children);
+    }
+
+    done.sort_unstable_by_key(|r| *r.start());
+    let mut merged: Vec<RangeInclusive<u64>> = Vec::with_capacity(done.len());
+    for range in done {
+        match merged.last_mut() {
+            Some(last) if *last.end() + 1 == *range.start() => {
+                *last = *last.start()..=*range.end();
+            }
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+/// Octree cell awaiting decomposition in [`box_ranges`]
+#[derive(Debug, PartialEq, Eq)]
+struct OctreeCell {
+    /// Hilbert digits above `shift`
+    prefix: u64,
+    /// Bits of the code below the prefix, 3 per octree level
+    shift: u32,
+    /// Codes of the cell outside the box
+    waste: u64,
+}
+
+impl OctreeCell {
+    fn root(lo: (u16, u16, u16), hi: (u16, u16, u16)) -> Self {
+        Self::new(0, 48, lo, hi).expect("root cell contains the box")
+    }
+
+    /// The cell, or `None` when it misses the box
+    fn new(prefix: u64, shift: u32, lo: (u16, u16, u16), hi: (u16, u16, u16)) -> Option<Self> {
+        // Trailing digits of zero decode to the cell's minimum corner
+        let (x, y, z) = hilbert3d_decode(prefix << shift);
+        let side = 1u32 << (shift / 3);
+        let overlap = |start: u16, lo: u16, hi: u16| -> u64 {
+            let end = start as u32 + side - 1;
+            let from = (start as u32).max(lo as u32);
+            let to = end.min(hi as u32);
+            if from > to {
+                0
+            } else {
+                (to - from + 1) as u64
+            }
+        };
+        let volume = overlap(x, lo.0, hi.0) * overlap(y, lo.1, hi.1) * overlap(z, lo.2, hi.2);
+        (volume > 0).then(|| Self {
+            prefix,
+            shift,
+            waste: (1u64 << shift) - volume,
+        })
+    }
+
+    fn children(
+        &self,
+        lo: (u16, u16, u16),
+        hi: (u16, u16, u16),
+    ) -> impl Iterator<Item = OctreeCell> + '_ {
+        (0..8)
+            .filter_map(move |digit| Self::new((self.prefix << 3) | digit, self.shift - 3, lo, hi))
+    }
+
+    fn codes(&self) -> RangeInclusive<u64> {
+        let start = self.prefix << self.shift;
+        start..=start + ((1u64 << self.shift) - 1)
+    }
+}
+
+impl Ord for OctreeCell {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.waste
+            .cmp(&other.waste)
+            .then_with(|| other.prefix.cmp(&self.prefix))
+            .then_with(|| self.shift.cmp(&other.shift))
+    }
+}
+
+impl PartialOrd for OctreeCell {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 // Conversion from Index64
 impl TryFrom<Index64> for Hilbert64 {
     type Error = Error;
@@ -272,6 +464,32 @@ mod tests {
         assert_eq!((x1, y1, z1), (x2, y2, z2));
     }
 
+    #[test]
+    fn test_box_ranges_cover_box() {
+        let (lo, hi) = ((3u16, 5u16, 0u16), (9u16, 6u16, 4u16));
+        let inside = |(x, y, z): (u16, u16, u16)| {
+            (lo.0..=hi.0).contains(&x) && (lo.1..=hi.1).contains(&y) && (lo.2..=hi.2).contains(&z)
+        };
+
+        let exact = box_ranges(hi, lo, usize::MAX);
+        let codes: Vec<u64> = exact.iter().flat_map(|r| r.clone()).collect();
+        assert_eq!(codes.len(), 7 * 2 * 5);
+        assert!(codes.iter().all(|&c| inside(hilbert3d_decode(c))));
+        assert!(exact.windows(2).all(|w| w[0].end() + 1 < *w[1].start()));
+
+        // A tighter budget still covers every cell
+        let coarse = box_ranges(lo, hi, 4);
+        assert!(coarse.len() <= 4);
+        for x in lo.0..=hi.0 {
+            for y in lo.1..=hi.1 {
+                for z in lo.2..=hi.2 {
+                    let code = hilbert3d_encode(x, y, z);
+                    assert!(coarse.iter().any(|r| r.contains(&code)));
+                }
+            }
+        }
+    }
+
     #[test]
     fn test_hilbert_batch_encode() {
         let coords = vec![(0, 0, 0), (1, 1, 1), (2, 2, 2)];
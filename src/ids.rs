@@ -10,6 +10,8 @@ use crate::lattice::{LatticeCoord, Parity};
 use crate::morton;
 use bech32::{Bech32m, Hrp};
 use std::fmt;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
@@ -148,7 +150,7 @@ impl Galactic128 {
     /// Decode from Bech32m string
     pub fn from_bech32m(s: &str) -> Result<Self> {
         let (hrp, data) = bech32::decode(s)?;
-        if hrp.as_str() != HRP_GALACTIC {
+        if !hrp.as_str().eq_ignore_ascii_case(HRP_GALACTIC) {
             return Err(Error::InvalidBech32 {
                 kind: format!("Wrong HRP: expected {}, got {}", HRP_GALACTIC, hrp),
             });
@@ -190,10 +192,102 @@ impl Galactic128 {
     pub fn raw(&self) -> u128 {
         self.value
     }
+
+    /// 96-bit Morton code of the coordinates
+    ///
+    /// Each coordinate's sign bit is flipped before interleaving, so the code
+    /// increases along the Z-order curve across negative and positive values.
+    pub fn morton(&self) -> u128 {
+        let biased = |c: i32| (c as u32) ^ 0x8000_0000;
+        morton::morton_encode128(biased(self.x()), biased(self.y()), biased(self.z()))
+    }
+
+    /// Sort key: the 32 header bits (scale, LOD, frame, attributes) above
+    /// the [`morton`](Self::morton) code
+    ///
+    /// `Galactic128`'s `Ord` compares these keys, so sorted IDs are grouped
+    /// by scale, LOD and frame and follow the Z-order curve within a group,
+    /// the order [`Index64`] values sort in.
+    pub fn morton_key(&self) -> u128 {
+        (self.value >> 96) << 96 | self.morton()
+    }
+
+    /// Inverse of [`morton_key`](Self::morton_key)
+    pub fn from_morton_key(key: u128) -> Result<Self> {
+        let (x, y, z) = morton::morton_decode128(key);
+        let unbias = |c: u32| (c ^ 0x8000_0000) as u128;
+        Self::from_value((key >> 96) << 96 | unbias(x) << 64 | unbias(y) << 32 | unbias(z))
+    }
+
+    /// Convert to an [`Index64`] cell at `lod`, keeping frame and scale tier
+    ///
+    /// Coordinates are rescaled between LODs the way [`Index64::parent`]
+    /// does, halving per level up and doubling per level down. Fails rather
+    /// than lose information: when coarsening would drop non-zero low bits,
+    /// when a rescaled coordinate is outside `0..=65535`, when `lod > 15`,
+    /// or when the scale mantissa is non-zero (Index64 has no mantissa).
+    /// User attribute bits are not carried over.
+    pub fn to_index64(&self, lod: u8) -> Result<Index64> {
+        if self.scale_mant() != 0 {
+            return Err(Error::InvalidScaleTier(format!(
+                "Index64 cannot represent scale mantissa {}",
+                self.scale_mant()
+            )));
+        }
+        if lod > 15 {
+            return Err(Error::InvalidLOD(format!("lod must be 0-15, got {}", lod)));
+        }
+        let own = self.lod();
+        let rescale = |axis: &str, c: i32| -> Result<u16> {
+            let c = c as i64;
+            let scaled = if lod >= own {
+                c << (lod - own)
+            } else {
+                let shift = own - lod;
+                if c & ((1i64 << shift) - 1) != 0 {
+                    return Err(Error::OutOfRange(format!(
+                        "{}={} is not representable at LOD {} without losing precision",
+                        axis, c, lod
+                    )));
+                }
+                c >> shift
+            };
+            u16::try_from(scaled).map_err(|_| {
+                Error::OutOfRange(format!(
+                    "{}={} outside the Index64 range 0..=65535 at LOD {}",
+                    axis, scaled, lod
+                ))
+            })
+        };
+        Index64::new(
+            self.frame_id(),
+            self.scale_tier(),
+            lod,
+            rescale("x", self.x())?,
+            rescale("y", self.y())?,
+            rescale("z", self.z())?,
+        )
+    }
+}
+
+impl Ord for Galactic128 {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.morton_key().cmp(&other.morton_key())
+    }
+}
+
+impl PartialOrd for Galactic128 {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
 }
 
+/// Bech32m string (`g3d1...`); the alternate form `{:#}` lists the fields
 impl fmt::Display for Galactic128 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !f.alternate() {
+            return f.write_str(&self.to_bech32m().map_err(|_| fmt::Error)?);
+        }
         write!(
             f,
             "G128(f={}, t={}:{}, lod={}, {},{},{})",
@@ -208,6 +302,15 @@ impl fmt::Display for Galactic128 {
     }
 }
 
+/// Parse the bech32m form produced by `Display`
+impl FromStr for Galactic128 {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::from_bech32m(s)
+    }
+}
+
 // =============================================================================
 // Index64
 // =============================================================================
@@ -321,6 +424,166 @@ impl Index64 {
         children
     }
 
+    /// Get the ancestor at a coarser (or equal) LOD
+    ///
+    /// Each level up halves the coordinates, as in [`Index64::parent`].
+    /// Returns `None` if `lod` is finer than this cell's LOD.
+    pub fn ancestor_at_lod(&self, lod: u8) -> Option<Self> {
+        let own = self.lod();
+        if lod > own {
+            return None;
+        }
+
+        let morton = self.morton() >> (3 * (own - lod) as u32);
+
+        let mut value = self.value;
+        value &= !0xFFFFFFFFFFFFFu64;
+        value |= (lod as u64) << 48;
+        value |= morton;
+
+        Some(Self { value })
+    }
+
+    /// Check if `other` is this cell or one of its descendants
+    ///
+    /// Both cells must share frame and scale tier.
+    pub fn contains(&self, other: Index64) -> bool {
+        self.frame_id() == other.frame_id()
+            && self.scale_tier() == other.scale_tier()
+            && other.ancestor_at_lod(self.lod()) == Some(*self)
+    }
+
+    /// Iterate over every cell in an inclusive coordinate box
+    ///
+    /// Cells are yielded lazily in Morton (storage) order; see
+    /// [`morton::box_range`]. The iterator covers all integer coordinates in
+    /// the box; filter with [`crate::layers::is_valid_bcc`] if only BCC
+    /// lattice points are wanted.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use octaindex3d::Index64;
+    ///
+    /// # fn main() -> octaindex3d::Result<()> {
+    /// let cells: Vec<Index64> = Index64::iter_box(0, 0, 5, (10, 10, 10), (11, 12, 10))?.collect();
+    /// assert_eq!(cells.len(), 6);
+    /// assert!(cells.windows(2).all(|w| w[0] < w[1]));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn iter_box(
+        frame: FrameId,
+        tier: u8,
+        lod: u8,
+        min: (u16, u16, u16),
+        max: (u16, u16, u16),
+    ) -> Result<impl Iterator<Item = Self>> {
+        // Validates tier and LOD, and provides the header bits
+        let header = Self::new(frame, tier, lod, 0, 0, 0)?.value;
+        Ok(morton::box_range(min, max).map(move |code| Self {
+            value: header | code,
+        }))
+    }
+
+    /// Iterate over every BCC lattice cell at one LOD, in Morton order
+    ///
+    /// A cell at `lod` spans `2^(15 - lod)` cells per axis at LOD 15, so the
+    /// level covers coordinates `0..2^(lod + 1)` on each axis: exactly the
+    /// cells whose full subdivision fits the 16-bit coordinate space. Only
+    /// points with uniform coordinate parity are yielded; see
+    /// [`Index64::iter_lod_bounded`] to restrict the enumeration to a box.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use octaindex3d::Index64;
+    ///
+    /// # fn main() -> octaindex3d::Result<()> {
+    /// // 4 x 4 x 4 coordinates, a quarter of which are lattice points
+    /// assert_eq!(Index64::iter_lod(0, 0, 1)?.count(), 16);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn iter_lod(frame: FrameId, tier: u8, lod: u8) -> Result<impl Iterator<Item = Self>> {
+        if lod > 15 {
+            return Err(Error::InvalidLOD(format!("lod must be 0-15, got {}", lod)));
+        }
+        let max = ((1u32 << (lod + 1)) - 1) as u16;
+        Self::iter_lod_bounded(frame, tier, lod, (0, 0, 0), (max, max, max))
+    }
+
+    /// Iterate over the BCC lattice cells at one LOD inside an inclusive
+    /// coordinate box, in Morton order
+    ///
+    /// Like [`Index64::iter_box`] with the parity filter applied, but
+    /// without visiting the three quarters of coordinates that are not
+    /// lattice points: the low three Morton bits hold the parity of each
+    /// axis, so every aligned 2×2×2 block holds exactly two lattice points,
+    /// at offsets `0b000` and `0b111`.
+    pub fn iter_lod_bounded(
+        frame: FrameId,
+        tier: u8,
+        lod: u8,
+        min: (u16, u16, u16),
+        max: (u16, u16, u16),
+    ) -> Result<impl Iterator<Item = Self>> {
+        let header = Self::new(frame, tier, lod, 0, 0, 0)?.value;
+        let lo = (min.0.min(max.0), min.1.min(max.1), min.2.min(max.2));
+        let hi = (min.0.max(max.0), min.1.max(max.1), min.2.max(max.2));
+        let inside = move |code: u64| {
+            let (x, y, z) = morton::morton_decode(code);
+            (lo.0..=hi.0).contains(&x) && (lo.1..=hi.1).contains(&y) && (lo.2..=hi.2).contains(&z)
+        };
+        let blocks = morton::box_range(
+            (lo.0 >> 1, lo.1 >> 1, lo.2 >> 1),
+            (hi.0 >> 1, hi.1 >> 1, hi.2 >> 1),
+        );
+        Ok(blocks
+            .flat_map(|block| [block << 3, (block << 3) | 0b111])
+            .filter(move |&code| inside(code))
+            .map(move |code| Self {
+                value: header | code,
+            }))
+    }
+
+    /// Contiguous `Index64` ranges covering an inclusive coordinate box
+    ///
+    /// Turns a box query over Morton-sorted data into a handful of range
+    /// scans; see [`morton::box_ranges`] for the decomposition and the
+    /// meaning of `max_ranges`.
+    ///
+    /// ```
+    /// use octaindex3d::Index64;
+    ///
+    /// # fn main() -> octaindex3d::Result<()> {
+    /// let ranges = Index64::box_ranges(0, 0, 5, (10, 10, 10), (13, 17, 10), usize::MAX)?;
+    /// let cell = Index64::new(0, 0, 5, 12, 15, 10)?;
+    /// assert!(ranges.iter().any(|r| r.contains(&cell)));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn box_ranges(
+        frame: FrameId,
+        tier: u8,
+        lod: u8,
+        min: (u16, u16, u16),
+        max: (u16, u16, u16),
+        max_ranges: usize,
+    ) -> Result<Vec<RangeInclusive<Self>>> {
+        let header = Self::new(frame, tier, lod, 0, 0, 0)?.value;
+        Ok(morton::box_ranges(min, max, max_ranges)
+            .into_iter()
+            .map(|r| {
+                Self {
+                    value: header | r.start(),
+                }..=Self {
+                    value: header | r.end(),
+                }
+            })
+            .collect())
+    }
+
     /// Encode to Bech32m string
     pub fn to_bech32m(&self) -> Result<String> {
         let hrp = Hrp::parse(HRP_INDEX)?;
@@ -332,7 +595,7 @@ impl Index64 {
     /// Decode from Bech32m string
     pub fn from_bech32m(s: &str) -> Result<Self> {
         let (hrp, data) = bech32::decode(s)?;
-        if hrp.as_str() != HRP_INDEX {
+        if !hrp.as_str().eq_ignore_ascii_case(HRP_INDEX) {
             return Err(Error::InvalidBech32 {
                 kind: format!("Wrong HRP: expected {}, got {}", HRP_INDEX, hrp),
             });
@@ -367,10 +630,33 @@ impl Index64 {
     pub fn raw(&self) -> u64 {
         self.value
     }
+
+    /// Convert to a [`Galactic128`] at the same frame, scale tier and LOD
+    ///
+    /// The conversion is exact: [`Galactic128::to_index64`] at this LOD
+    /// returns the original cell. Fails if the coordinates are not a BCC
+    /// lattice point (mixed parity), which Galactic128 requires.
+    pub fn to_galactic128(&self) -> Result<Galactic128> {
+        let (x, y, z) = self.decode_coords();
+        Galactic128::new(
+            self.frame_id(),
+            0,
+            self.scale_tier(),
+            self.lod(),
+            0,
+            x as i32,
+            y as i32,
+            z as i32,
+        )
+    }
 }
 
+/// Bech32m string (`i3d1...`); the alternate form `{:#}` lists the fields
 impl fmt::Display for Index64 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !f.alternate() {
+            return f.write_str(&self.to_bech32m().map_err(|_| fmt::Error)?);
+        }
         let (x, y, z) = self.decode_coords();
         write!(
             f,
@@ -386,6 +672,15 @@ impl fmt::Display for Index64 {
     }
 }
 
+/// Parse the bech32m form produced by `Display`
+impl FromStr for Index64 {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::from_bech32m(s)
+    }
+}
+
 // =============================================================================
 // Route64
 // =============================================================================
@@ -481,6 +776,44 @@ impl Route64 {
         LatticeCoord::new(self.x(), self.y(), self.z())
     }
 
+    /// Move by a lattice offset, keeping the scale tier
+    ///
+    /// Fails if a coordinate overflows the 20-bit range or the offset breaks
+    /// BCC parity (`dx`, `dy` and `dz` must all be even or all be odd).
+    pub fn translate(&self, dx: i32, dy: i32, dz: i32) -> Result<Self> {
+        let add = |c: i32, d: i32| c.checked_add(d).ok_or(Error::CoordinateOverflow);
+        Self::new(
+            self.scale_tier(),
+            add(self.x(), dx)?,
+            add(self.y(), dy)?,
+            add(self.z(), dz)?,
+        )
+    }
+
+    /// Offset from this route to `other`, so that
+    /// `self.translate(dx, dy, dz) == Ok(other)` for routes of the same tier
+    pub fn offset_to(&self, other: &Route64) -> (i32, i32, i32) {
+        (
+            other.x() - self.x(),
+            other.y() - self.y(),
+            other.z() - self.z(),
+        )
+    }
+
+    /// Manhattan (L1) distance to `other` in lattice units
+    pub fn manhattan_distance_to(&self, other: &Route64) -> u32 {
+        let (dx, dy, dz) = self.offset_to(other);
+        dx.unsigned_abs() + dy.unsigned_abs() + dz.unsigned_abs()
+    }
+
+    /// Chebyshev (L∞) distance to `other` in lattice units
+    pub fn chebyshev_distance_to(&self, other: &Route64) -> u32 {
+        let (dx, dy, dz) = self.offset_to(other);
+        dx.unsigned_abs()
+            .max(dy.unsigned_abs())
+            .max(dz.unsigned_abs())
+    }
+
     /// Encode to Bech32m string
     pub fn to_bech32m(&self) -> Result<String> {
         let hrp = Hrp::parse(HRP_ROUTE)?;
@@ -492,7 +825,7 @@ impl Route64 {
     /// Decode from Bech32m string
     pub fn from_bech32m(s: &str) -> Result<Self> {
         let (hrp, data) = bech32::decode(s)?;
-        if hrp.as_str() != HRP_ROUTE {
+        if !hrp.as_str().eq_ignore_ascii_case(HRP_ROUTE) {
             return Err(Error::InvalidBech32 {
                 kind: format!("Wrong HRP: expected {}, got {}", HRP_ROUTE, hrp),
             });
@@ -577,8 +910,12 @@ fn sign_extend_20(val: u32) -> i32 {
     }
 }
 
+/// Bech32m string (`r3d1...`); the alternate form `{:#}` lists the fields
 impl fmt::Display for Route64 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !f.alternate() {
+            return f.write_str(&self.to_bech32m().map_err(|_| fmt::Error)?);
+        }
         write!(
             f,
             "R64(t={}, {},{},{})",
@@ -590,6 +927,15 @@ impl fmt::Display for Route64 {
     }
 }
 
+/// Parse the bech32m form produced by `Display`
+impl FromStr for Route64 {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::from_bech32m(s)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -636,6 +982,135 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_index64_ancestors() {
+        let cell = Index64::new(3, 1, 9, 1000, 2001, 37).unwrap();
+        assert_eq!(cell.ancestor_at_lod(9), Some(cell));
+        assert_eq!(cell.ancestor_at_lod(10), None);
+        assert_eq!(
+            cell.ancestor_at_lod(8),
+            Some(cell.parent().unwrap()),
+            "one level up is the parent"
+        );
+
+        let root = cell.ancestor_at_lod(0).unwrap();
+        assert_eq!(root.lod(), 0);
+        assert_eq!(root.decode_coords(), (1000 >> 9, 2001 >> 9, 37 >> 9));
+        assert_eq!((root.frame_id(), root.scale_tier()), (3, 1));
+
+        let mid = cell.ancestor_at_lod(4).unwrap();
+        assert_eq!(mid.decode_coords(), (31, 62, 1));
+        assert!(mid.contains(cell));
+        assert!(mid.contains(mid));
+        assert!(root.contains(mid));
+        assert!(!cell.contains(mid));
+        for child in mid.children() {
+            assert!(mid.contains(child));
+        }
+
+        let sibling = Index64::new(3, 1, 4, 32, 62, 1).unwrap();
+        assert!(!sibling.contains(cell));
+        let other_frame = Index64::new(4, 1, 9, 1000, 2001, 37).unwrap();
+        assert!(!mid.contains(other_frame));
+    }
+
+    #[test]
+    fn test_index64_iter_lod() {
+        let bcc = |c: &Index64| {
+            let (x, y, z) = c.decode_coords();
+            x % 2 == y % 2 && y % 2 == z % 2
+        };
+        let level: Vec<Index64> = Index64::iter_lod(2, 1, 3).unwrap().collect();
+        assert_eq!(level.len(), 16 * 16 * 16 / 4);
+        assert!(level.windows(2).all(|w| w[0] < w[1]));
+        assert!(level
+            .iter()
+            .all(|c| bcc(c) && c.lod() == 3 && c.frame_id() == 2));
+        assert_eq!(level[0].decode_coords(), (0, 0, 0));
+        assert_eq!(level.last().unwrap().decode_coords(), (15, 15, 15));
+        assert!(Index64::iter_lod(0, 0, 16).is_err());
+
+        // Odd bounds: matches filtering the full box
+        let (min, max) = ((3, 0, 5), (10, 7, 6));
+        let bounded: Vec<Index64> = Index64::iter_lod_bounded(0, 0, 9, min, max)
+            .unwrap()
+            .collect();
+        let expected: Vec<Index64> = Index64::iter_box(0, 0, 9, min, max)
+            .unwrap()
+            .filter(bcc)
+            .collect();
+        assert_eq!(bounded, expected);
+        assert_eq!(
+            Index64::iter_lod_bounded(0, 0, 15, (u16::MAX, 0, 0), (u16::MAX, 0, 0))
+                .unwrap()
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_galactic128_morton_order() {
+        let cell = |lod, x, y, z| Galactic128::new(3, 0, 1, lod, 2, x, y, z).unwrap();
+        let mut cells = vec![
+            cell(4, 2, 2, 2),
+            cell(4, -1, -1, -1),
+            cell(4, 0, 0, 0),
+            cell(2, 100, 100, 100),
+            cell(4, -2, 0, 0),
+            cell(4, 1, 1, 1),
+        ];
+        cells.sort();
+        let expected = vec![
+            cell(2, 100, 100, 100),
+            cell(4, -1, -1, -1),
+            cell(4, -2, 0, 0),
+            cell(4, 0, 0, 0),
+            cell(4, 1, 1, 1),
+            cell(4, 2, 2, 2),
+        ];
+        assert_eq!(cells, expected);
+
+        for g in &cells {
+            assert_eq!(Galactic128::from_morton_key(g.morton_key()).unwrap(), *g);
+        }
+        // Same-LOD cells sort like Index64 keys where both are defined
+        let a = cell(4, 2, 0, 0);
+        let b = cell(4, 0, 2, 0);
+        let ia = Index64::new(3, 1, 4, 2, 0, 0).unwrap();
+        let ib = Index64::new(3, 1, 4, 0, 2, 0).unwrap();
+        assert_eq!(a.cmp(&b), ia.cmp(&ib));
+    }
+
+    #[test]
+    fn test_galactic128_index64_conversion() {
+        let index = Index64::new(7, 2, 10, 1000, 2002, 30).unwrap();
+        let galactic = index.to_galactic128().unwrap();
+        assert_eq!(
+            (galactic.frame_id(), galactic.scale_tier(), galactic.lod()),
+            (7, 2, 10)
+        );
+        assert_eq!((galactic.x(), galactic.y(), galactic.z()), (1000, 2002, 30));
+        assert_eq!(galactic.to_index64(10).unwrap(), index);
+
+        // Refining doubles, coarsening halves when no bits are lost
+        let fine = galactic.to_index64(12).unwrap();
+        assert_eq!(fine.decode_coords(), (4000, 8008, 120));
+        assert_eq!(fine.ancestor_at_lod(10), Some(index));
+        assert_eq!(galactic.to_index64(9).unwrap(), index.parent().unwrap());
+        assert!(galactic.to_index64(8).is_err()); // 1000 / 4 ok, 2002 / 4 not
+        assert_eq!(galactic.to_index64(15).unwrap().decode_coords().1, 64064);
+        let wide = Galactic128::new(0, 0, 0, 10, 0, 40000, 0, 0).unwrap();
+        assert!(wide.to_index64(11).is_err()); // 80000 > 65535
+        assert!(galactic.to_index64(16).is_err());
+
+        let negative = Galactic128::new(0, 0, 0, 5, 0, -2, 4, 6).unwrap();
+        assert!(negative.to_index64(5).is_err());
+        let mantissa = Galactic128::new(0, 3, 0, 5, 0, 2, 4, 6).unwrap();
+        assert!(mantissa.to_index64(5).is_err());
+        let mixed = Index64::new(0, 0, 5, 1, 2, 3).unwrap();
+        assert!(mixed.to_galactic128().is_err());
+    }
+
     #[test]
     fn test_route64_signed() {
         // Positive coordinates
@@ -657,6 +1132,27 @@ mod tests {
         assert!(Route64::new(0, 0, 1, 0).is_err());
     }
 
+    #[test]
+    fn test_route64_arithmetic() {
+        let a = Route64::new(2, 10, -20, 30).unwrap();
+        let b = a.translate(1, -3, 5).unwrap();
+        assert_eq!((b.x(), b.y(), b.z(), b.scale_tier()), (11, -23, 35, 2));
+        assert_eq!(a.offset_to(&b), (1, -3, 5));
+        assert_eq!(b.offset_to(&a), (-1, 3, -5));
+        assert_eq!(a.manhattan_distance_to(&b), 9);
+        assert_eq!(a.chebyshev_distance_to(&b), 5);
+        assert_eq!(a.chebyshev_distance_to(&a), 0);
+
+        // Parity-breaking offsets and range overflow are rejected
+        assert!(a.translate(1, 0, 0).is_err());
+        let edge = Route64::new(0, 524286, 0, 0).unwrap();
+        assert!(edge.translate(2, 0, 0).is_err());
+        assert!(edge.translate(i32::MAX - 1, 0, 0).is_err());
+        let far = edge.translate(-1_048_574, 0, 0).unwrap();
+        assert_eq!(far.x(), -524288);
+        assert_eq!(edge.manhattan_distance_to(&far), 1_048_574);
+    }
+
     #[test]
     fn test_bech32m_roundtrip() {
         let g = Galactic128::new(0, 5, 1, 10, 3, 2, 4, 6).unwrap();
@@ -675,6 +1171,30 @@ mod tests {
         assert_eq!(r, decoded);
     }
 
+    #[test]
+    fn test_display_parse_roundtrip() {
+        let g = Galactic128::new(3, 5, 1, 10, 3, 2, 4, 6).unwrap();
+        let idx = Index64::new(0, 0, 5, 100, 200, 300).unwrap();
+        let r = Route64::new(0, -100, 200, 300).unwrap();
+
+        let (gs, is, rs) = (g.to_string(), idx.to_string(), r.to_string());
+        assert!(gs.starts_with("g3d11") && is.starts_with("i3d11") && rs.starts_with("r3d11"));
+        assert_eq!(is, idx.to_bech32m().unwrap());
+        assert_eq!(gs.parse::<Galactic128>().unwrap(), g);
+        assert_eq!(is.parse::<Index64>().unwrap(), idx);
+        assert_eq!(rs.parse::<Route64>().unwrap(), r);
+        assert_eq!(is.to_uppercase().parse::<Index64>().unwrap(), idx);
+
+        // Each type only accepts its own HRP
+        assert!(is.parse::<Route64>().is_err());
+        assert!(rs.parse::<Index64>().is_err());
+        assert!(is.parse::<Galactic128>().is_err());
+        assert!("i3d1".parse::<Index64>().is_err());
+
+        assert_eq!(format!("{:#}", r), "R64(t=0, -100,200,300)");
+        assert!(format!("{:#}", idx).starts_with("I64(f=0, t=0, lod=5,"));
+    }
+
     #[test]
     fn test_bech32m_rejects_invalid_raw_payloads() {
         let invalid_galactic =
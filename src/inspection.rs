@@ -0,0 +1,546 @@
+//! Inspection paths around structures
+//!
+//! [`orbit_path`] plans how a camera drone inspects an object — a bridge
+//! pier, a tower, a tank — given the cells the object occupies. Viewpoints
+//! are placed at a standoff distance along the outward normal of the
+//! object's surface, spaced so that neighbouring camera footprints overlap
+//! by the requested fraction, then ordered into a short tour and connected
+//! by collision-free routes that keep half the standoff from the object.
+//!
+//! Coordinates are lattice units of the object cells (the coordinates of
+//! their [`Index64`] keys), as in [`visibility`](crate::visibility).
+//!
+//! # Example
+//!
+//! ```
+//! use octaindex3d::inspection::orbit_path;
+//! use octaindex3d::Index64;
+//!
+//! # fn main() -> octaindex3d::Result<()> {
+//! // A 4 x 4 x 8 column
+//! let mut column = Vec::new();
+//! for x in (100..=104).step_by(2) {
+//!     for y in (100..=104).step_by(2) {
+//!         for z in (100..=108).step_by(2) {
+//!             column.push(Index64::new(0, 0, 8, x, y, z)?);
+//!             column.push(Index64::new(0, 0, 8, x + 1, y + 1, z + 1)?);
+//!         }
+//!     }
+//! }
+//!
+//! let plan = orbit_path(&column, 6.0, 0.3)?;
+//! assert!(plan.uncovered.is_empty());
+//! assert_eq!(plan.route.first(), Some(&plan.viewpoints[0].position));
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{Error, Result};
+use crate::lattice::BCC_NEIGHBORS_14;
+use crate::routing::{astar_with_config, PredicateCost, SearchConfig};
+use crate::{Index64, Route64};
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::cell::RefCell;
+
+type Point = (f64, f64, f64);
+
+/// Settings for [`orbit_path_with`]
+#[derive(Debug, Clone)]
+pub struct InspectionConfig {
+    /// Full camera field of view (radians, default: 90°)
+    pub field_of_view: f64,
+    /// Node expansion limit for each route between viewpoints
+    pub max_expansions: usize,
+}
+
+impl Default for InspectionConfig {
+    fn default() -> Self {
+        Self {
+            field_of_view: std::f64::consts::FRAC_PI_2,
+            max_expansions: 100_000,
+        }
+    }
+}
+
+/// Camera pose of an inspection plan
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewpoint {
+    /// Cell the camera is placed at
+    pub position: Index64,
+    /// Surface cell the camera is aimed at
+    pub target: Index64,
+    /// Unit viewing direction, from `position` towards `target`
+    pub direction: Point,
+}
+
+/// Result of [`orbit_path`]
+#[derive(Debug, Clone)]
+pub struct InspectionPlan {
+    /// Viewpoints in visiting order
+    pub viewpoints: Vec<Viewpoint>,
+    /// Cells from the first viewpoint through every other one to the last
+    pub route: Vec<Index64>,
+    /// Length of the route in lattice units
+    pub length: f64,
+    /// Surface cells no viewpoint could be placed for, e.g. at the bottom of
+    /// recesses narrower than the standoff
+    pub uncovered: Vec<Index64>,
+}
+
+/// Plan viewpoints covering the surface of an object and a route through them
+///
+/// `standoff` is the camera distance from the surface in lattice units and
+/// `overlap` the fraction in `[0, 1)` by which neighbouring footprints
+/// overlap. Uses a 90° field of view; see [`orbit_path_with`].
+pub fn orbit_path(object_cells: &[Index64], standoff: f64, overlap: f64) -> Result<InspectionPlan> {
+    orbit_path_with(
+        object_cells,
+        standoff,
+        overlap,
+        &InspectionConfig::default(),
+    )
+}
+
+/// [`orbit_path`] with explicit settings
+///
+/// A surface cell is an object cell with a lattice neighbor outside the
+/// object. Surface cells are visited in index order; each one not yet seen
+/// gets a viewpoint on its outward normal, which then accounts for every
+/// surface cell facing it within the footprint spacing. The camera
+/// footprint has radius `standoff × tan(fov / 2)`, and footprints are
+/// spaced so that neighbours overlap by `overlap` of their width, but never
+/// further apart than one radius, which keeps every accounted cell inside
+/// a footprint. Viewpoints are ordered by a nearest-neighbor tour refined
+/// with 2-opt, then joined by A* routes.
+///
+/// All cells must share the frame, tier and LOD of the first one; mixed
+/// LODs fail with [`Error::InvalidLOD`]. Returns
+/// [`Error::NoPathFound`] or [`Error::SearchLimitExceeded`] when two
+/// viewpoints cannot be connected.
+pub fn orbit_path_with(
+    object_cells: &[Index64],
+    standoff: f64,
+    overlap: f64,
+    config: &InspectionConfig,
+) -> Result<InspectionPlan> {
+    let Some(&template) = object_cells.first() else {
+        return Err(Error::Pathfinding("object has no cells".to_string()));
+    };
+    for &cell in object_cells {
+        if cell.lod() != template.lod() {
+            return Err(Error::InvalidLOD(format!(
+                "object cells mix LOD {} and {}",
+                template.lod(),
+                cell.lod()
+            )));
+        }
+        if (cell.frame_id(), cell.scale_tier()) != (template.frame_id(), template.scale_tier()) {
+            return Err(Error::Pathfinding(
+                "object cells must share one frame and tier".to_string(),
+            ));
+        }
+    }
+    if !(standoff.is_finite() && standoff > 0.0) {
+        return Err(Error::Pathfinding(format!(
+            "standoff must be positive, got {}",
+            standoff
+        )));
+    }
+    if !(0.0..1.0).contains(&overlap) {
+        return Err(Error::Pathfinding(format!(
+            "overlap must be in [0, 1), got {}",
+            overlap
+        )));
+    }
+
+    let coords = |idx: Index64| {
+        let (x, y, z) = idx.decode_coords();
+        (x as i32, y as i32, z as i32)
+    };
+    let cell_at = |(x, y, z): (i32, i32, i32)| {
+        let (Ok(x), Ok(y), Ok(z)) = (u16::try_from(x), u16::try_from(y), u16::try_from(z)) else {
+            return None;
+        };
+        Index64::new(
+            template.frame_id(),
+            template.scale_tier(),
+            template.lod(),
+            x,
+            y,
+            z,
+        )
+        .ok()
+    };
+    let object: FxHashSet<(i32, i32, i32)> = object_cells.iter().map(|&c| coords(c)).collect();
+
+    // Surface cells with their outward normals
+    let mut surface: Vec<Index64> = object_cells
+        .iter()
+        .copied()
+        .filter(|&c| {
+            let (x, y, z) = coords(c);
+            BCC_NEIGHBORS_14
+                .iter()
+                .any(|&(dx, dy, dz)| !object.contains(&(x + dx, y + dy, z + dz)))
+        })
+        .collect();
+    surface.sort_unstable();
+    surface.dedup();
+    let centroid = {
+        let n = object.len() as f64;
+        let sum = object.iter().fold((0.0, 0.0, 0.0), |acc, &(x, y, z)| {
+            (acc.0 + x as f64, acc.1 + y as f64, acc.2 + z as f64)
+        });
+        (sum.0 / n, sum.1 / n, sum.2 / n)
+    };
+    let normals: Vec<Point> = surface
+        .iter()
+        .map(|&c| {
+            let (x, y, z) = coords(c);
+            let mut normal = (0.0, 0.0, 0.0);
+            for &(dx, dy, dz) in BCC_NEIGHBORS_14.iter() {
+                if !object.contains(&(x + dx, y + dy, z + dz)) {
+                    let length = ((dx * dx + dy * dy + dz * dz) as f64).sqrt();
+                    normal.0 += dx as f64 / length;
+                    normal.1 += dy as f64 / length;
+                    normal.2 += dz as f64 / length;
+                }
+            }
+            if norm(normal) < 1e-9 {
+                // Free neighbors on opposite sides: point away from the middle
+                normal = (
+                    x as f64 - centroid.0,
+                    y as f64 - centroid.1,
+                    z as f64 - centroid.2,
+                );
+            }
+            unit(normal).unwrap_or((0.0, 0.0, 1.0))
+        })
+        .collect();
+
+    let footprint = standoff * (config.field_of_view / 2.0).tan();
+    let spacing = (2.0 * footprint * (1.0 - overlap)).min(footprint);
+    let clearance = standoff / 2.0;
+
+    let points: Vec<Point> = surface.iter().map(|&c| to_point(coords(c))).collect();
+    let grid = PointGrid::new(&points, spacing.max(clearance));
+    let mut accounted = vec![false; surface.len()];
+    let mut viewpoints = Vec::new();
+    let mut uncovered = Vec::new();
+    for i in 0..surface.len() {
+        if accounted[i] {
+            continue;
+        }
+        let target = points[i];
+        let normal = normals[i];
+        let placed = snap(add(target, scale(normal, standoff)));
+        let eye = to_point(placed);
+        let position = cell_at(placed).filter(|_| {
+            grid.near(eye, clearance)
+                .all(|j| distance(points[j], eye) >= clearance)
+        });
+        let Some(position) = position else {
+            uncovered.push(surface[i]);
+            accounted[i] = true;
+            continue;
+        };
+
+        for j in grid.near(target, spacing) {
+            let faces = dot(normals[j], sub(eye, points[j])) > 0.0;
+            if !accounted[j] && faces && distance(points[j], target) <= spacing {
+                accounted[j] = true;
+            }
+        }
+        accounted[i] = true;
+        viewpoints.push(Viewpoint {
+            position,
+            target: surface[i],
+            direction: scale(normal, -1.0),
+        });
+    }
+
+    let order = tour(
+        &viewpoints
+            .iter()
+            .map(|v| to_point(coords(v.position)))
+            .collect::<Vec<_>>(),
+    );
+    let viewpoints: Vec<Viewpoint> = order.into_iter().map(|i| viewpoints[i]).collect();
+
+    // Connect consecutive viewpoints, keeping clear of the object
+    let reach = clearance.ceil() as i32;
+    let near_object = |(x, y, z): (i32, i32, i32)| {
+        for dx in -reach..=reach {
+            for dy in -reach..=reach {
+                for dz in -reach..=reach {
+                    if ((dx * dx + dy * dy + dz * dz) as f64).sqrt() < clearance
+                        && object.contains(&(x + dx, y + dy, z + dz))
+                    {
+                        return true;
+                    }
+                }
+            }
+        }
+        false
+    };
+    let blocked: RefCell<FxHashMap<Route64, bool>> = RefCell::new(FxHashMap::default());
+    let search = SearchConfig {
+        max_expansions: config.max_expansions,
+        ..Default::default()
+    };
+    let mut route: Vec<Index64> = viewpoints.first().map(|v| v.position).into_iter().collect();
+    let mut length = 0.0;
+    for leg in viewpoints.windows(2) {
+        let to_route = |idx: Index64| {
+            let (x, y, z) = coords(idx);
+            Route64::new(0, x, y, z)
+        };
+        let (from, to) = (to_route(leg[0].position)?, to_route(leg[1].position)?);
+        let cost = PredicateCost::new(|c: Route64| {
+            if c == from || c == to {
+                return true;
+            }
+            let cached = blocked.borrow().get(&c).copied();
+            let is_blocked = cached.unwrap_or_else(|| {
+                let value = near_object((c.x(), c.y(), c.z()));
+                blocked.borrow_mut().insert(c, value);
+                value
+            });
+            !is_blocked
+        });
+        let path = astar_with_config(from, to, &cost, &search)?;
+        length += path.cost;
+        for cell in path.cells.into_iter().skip(1) {
+            let idx = cell_at((cell.x(), cell.y(), cell.z())).ok_or_else(|| {
+                Error::Pathfinding("route leaves the representable extent".to_string())
+            })?;
+            route.push(idx);
+        }
+    }
+
+    Ok(InspectionPlan {
+        viewpoints,
+        route,
+        length,
+        uncovered,
+    })
+}
+
+/// Uniform grid of points, for finding those near a position without
+/// scanning them all
+struct PointGrid {
+    bucket: f64,
+    buckets: FxHashMap<(i64, i64, i64), Vec<usize>>,
+}
+
+impl PointGrid {
+    fn new(points: &[Point], bucket: f64) -> Self {
+        let mut grid = Self {
+            bucket: bucket.max(1.0),
+            buckets: FxHashMap::default(),
+        };
+        for (i, &p) in points.iter().enumerate() {
+            let key = grid.key(p);
+            grid.buckets.entry(key).or_default().push(i);
+        }
+        grid
+    }
+
+    fn key(&self, p: Point) -> (i64, i64, i64) {
+        let b = |c: f64| (c / self.bucket).floor() as i64;
+        (b(p.0), b(p.1), b(p.2))
+    }
+
+    /// Indices of the points in buckets overlapping the box of half-width
+    /// `radius` around `p`, a superset of those within `radius`
+    fn near(&self, p: Point, radius: f64) -> impl Iterator<Item = usize> + '_ {
+        let lo = self.key(sub(p, (radius, radius, radius)));
+        let hi = self.key(add(p, (radius, radius, radius)));
+        (lo.0..=hi.0)
+            .flat_map(move |x| (lo.1..=hi.1).map(move |y| (x, y)))
+            .flat_map(move |(x, y)| (lo.2..=hi.2).map(move |z| (x, y, z)))
+            .filter_map(|key| self.buckets.get(&key))
+            .flatten()
+            .copied()
+    }
+}
+
+/// Visiting order: nearest-neighbor tour from the first point, then 2-opt
+fn tour(points: &[Point]) -> Vec<usize> {
+    let mut order: Vec<usize> = Vec::with_capacity(points.len());
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    if !remaining.is_empty() {
+        order.push(remaining.remove(0));
+    }
+    while !remaining.is_empty() {
+        let last = points[*order.last().expect("tour is not empty")];
+        let (k, _) = remaining
+            .iter()
+            .enumerate()
+            .min_by(|a, b| distance(last, points[*a.1]).total_cmp(&distance(last, points[*b.1])))
+            .expect("remaining is not empty");
+        order.push(remaining.remove(k));
+    }
+
+    // 2-opt on the open path: reverse order[i..=j] when that shortens it
+    let mut improved = true;
+    while improved {
+        improved = false;
+        for i in 1..order.len().saturating_sub(1) {
+            for j in i + 1..order.len() {
+                let before = distance(points[order[i - 1]], points[order[i]])
+                    + order
+                        .get(j + 1)
+                        .map_or(0.0, |&n| distance(points[order[j]], points[n]));
+                let after = distance(points[order[i - 1]], points[order[j]])
+                    + order
+                        .get(j + 1)
+                        .map_or(0.0, |&n| distance(points[order[i]], points[n]));
+                if after + 1e-9 < before {
+                    order[i..=j].reverse();
+                    improved = true;
+                }
+            }
+        }
+    }
+    order
+}
+
+/// Nearest BCC lattice point: all coordinates even or all odd
+fn snap(p: Point) -> (i32, i32, i32) {
+    let round_to = |v: f64, odd: bool| {
+        let shift = if odd { 1.0 } else { 0.0 };
+        (((v - shift) / 2.0).round() * 2.0 + shift) as i32
+    };
+    let even = (
+        round_to(p.0, false),
+        round_to(p.1, false),
+        round_to(p.2, false),
+    );
+    let odd = (
+        round_to(p.0, true),
+        round_to(p.1, true),
+        round_to(p.2, true),
+    );
+    if distance(p, to_point(even)) <= distance(p, to_point(odd)) {
+        even
+    } else {
+        odd
+    }
+}
+
+fn to_point((x, y, z): (i32, i32, i32)) -> Point {
+    (x as f64, y as f64, z as f64)
+}
+
+fn add(a: Point, b: Point) -> Point {
+    (a.0 + b.0, a.1 + b.1, a.2 + b.2)
+}
+
+fn sub(a: Point, b: Point) -> Point {
+    (a.0 - b.0, a.1 - b.1, a.2 - b.2)
+}
+
+fn scale(a: Point, s: f64) -> Point {
+    (a.0 * s, a.1 * s, a.2 * s)
+}
+
+fn dot(a: Point, b: Point) -> f64 {
+    a.0 * b.0 + a.1 * b.1 + a.2 * b.2
+}
+
+fn norm(a: Point) -> f64 {
+    dot(a, a).sqrt()
+}
+
+fn unit(a: Point) -> Option<Point> {
+    let n = norm(a);
+    (n > 1e-9).then(|| scale(a, 1.0 / n))
+}
+
+fn distance(a: Point, b: Point) -> f64 {
+    norm(sub(a, b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lattice cells of a box spanning `[lo, hi]` on each axis
+    fn block(lo: u16, hi: (u16, u16, u16)) -> Vec<Index64> {
+        let mut cells = Vec::new();
+        for x in lo..=hi.0 {
+            for y in lo..=hi.1 {
+                for z in lo..=hi.2 {
+                    if x % 2 == y % 2 && y % 2 == z % 2 {
+                        cells.push(Index64::new(0, 0, 8, x, y, z).unwrap());
+                    }
+                }
+            }
+        }
+        cells
+    }
+
+    #[test]
+    fn test_orbit_covers_block_at_standoff() {
+        let object = block(100, (110, 106, 114));
+        let standoff = 6.0;
+        let plan = orbit_path(&object, standoff, 0.3).unwrap();
+        assert!(plan.uncovered.is_empty());
+
+        let point = |idx: Index64| {
+            let (x, y, z) = idx.decode_coords();
+            (x as f64, y as f64, z as f64)
+        };
+        // Every surface cell lies in some footprint
+        let footprint = standoff * (std::f64::consts::FRAC_PI_2 / 2.0).tan();
+        let object_set: FxHashSet<Index64> = object.iter().copied().collect();
+        for &cell in &object {
+            let (x, y, z) = cell.decode_coords();
+            let on_surface = BCC_NEIGHBORS_14.iter().any(|&(dx, dy, dz)| {
+                let n = Index64::new(
+                    0,
+                    0,
+                    8,
+                    (x as i32 + dx) as u16,
+                    (y as i32 + dy) as u16,
+                    (z as i32 + dz) as u16,
+                )
+                .unwrap();
+                !object_set.contains(&n)
+            });
+            if on_surface {
+                assert!(plan
+                    .viewpoints
+                    .iter()
+                    .any(|v| distance(point(v.target), point(cell)) <= footprint));
+            }
+        }
+
+        // The route visits the viewpoints in order without touching the object
+        assert_eq!(plan.route.first(), Some(&plan.viewpoints[0].position));
+        assert_eq!(
+            plan.route.last(),
+            Some(&plan.viewpoints.last().unwrap().position)
+        );
+        assert!(plan.route.iter().all(|c| !object_set.contains(c)));
+        let mut next = 0;
+        for cell in &plan.route {
+            if next < plan.viewpoints.len() && *cell == plan.viewpoints[next].position {
+                next += 1;
+            }
+        }
+        assert_eq!(next, plan.viewpoints.len());
+        assert!(plan.length > 0.0);
+
+        let dense = orbit_path(&object, standoff, 0.8).unwrap();
+        assert!(dense.viewpoints.len() > plan.viewpoints.len());
+
+        let mut mixed = object.clone();
+        mixed.push(Index64::new(0, 0, 7, 50, 50, 50).unwrap());
+        assert!(matches!(
+            orbit_path(&mixed, standoff, 0.3),
+            Err(Error::InvalidLOD(_))
+        ));
+    }
+}
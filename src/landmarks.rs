@@ -0,0 +1,444 @@
+//! Named landmarks bound to cells
+//!
+//! A [`LandmarkRegistry`] maps names such as `"dock-3"` or `"charger-1"`
+//! to cells of a map, optionally with the pose a robot should take there,
+//! so applications and operators can address places by name instead of
+//! raw coordinates.
+//!
+//! [`LandmarkRegistry::lookup`] tolerates how people type names: case,
+//! spaces and punctuation are ignored, a unique prefix is enough and small
+//! typos are forgiven. [`LandmarkRegistry::route`] plans between two named
+//! landmarks over a [`TopologicalGraph`].
+//!
+//! With the `container_v2` feature the registry is stored as a metadata
+//! entry of a v2 container (see [`LandmarkRegistry::write_container`]).
+//! The entry holds, little endian:
+//!
+//! - the tag `OLM1` and the landmark count (varint),
+//! - per landmark in name order, the name length (varint) and UTF-8 bytes,
+//!   the cell (u64) and a pose flag (u8), followed for flag 1 by position
+//!   x, y, z and orientation x, y, z, w (f64 each).
+
+use crate::compression::{read_varint, write_varint};
+#[cfg(feature = "container_v2")]
+use crate::container_v2::{ContainerReaderV2, ContainerWriterV2, MetadataValue};
+use crate::error::{Error, Result};
+use crate::layers::ros2_bridge::{Point, Pose, Quaternion};
+use crate::topology::TopologicalGraph;
+use crate::Index64;
+use std::collections::BTreeMap;
+
+/// Leading bytes of an encoded registry
+const LANDMARKS_TAG: &[u8; 4] = b"OLM1";
+
+/// Container metadata key of the registry
+pub const LANDMARKS_METADATA_KEY: &str = "landmarks";
+
+/// A named place in the map
+#[derive(Debug, Clone)]
+pub struct Landmark {
+    /// Name, unique within a registry
+    pub name: String,
+    /// Cell the landmark is bound to
+    pub cell: Index64,
+    /// Pose to take at the landmark, e.g. to dock
+    pub pose: Option<Pose>,
+}
+
+impl Landmark {
+    /// Landmark `name` at `cell`, without a pose
+    pub fn new(name: impl Into<String>, cell: Index64) -> Self {
+        Self {
+            name: name.into(),
+            cell,
+            pose: None,
+        }
+    }
+
+    /// Set the pose to take at the landmark
+    pub fn with_pose(mut self, pose: Pose) -> Self {
+        self.pose = Some(pose);
+        self
+    }
+}
+
+/// Landmarks by name
+///
+/// # Example
+/// ```
+/// use octaindex3d::landmarks::{Landmark, LandmarkRegistry};
+/// use octaindex3d::Index64;
+///
+/// # fn main() -> octaindex3d::Result<()> {
+/// let mut landmarks = LandmarkRegistry::new();
+/// landmarks.insert(Landmark::new("dock-3", Index64::new(0, 0, 8, 100, 100, 100)?));
+/// landmarks.insert(Landmark::new("charger-1", Index64::new(0, 0, 8, 140, 120, 100)?));
+/// landmarks.insert(Landmark::new("charger-2", Index64::new(0, 0, 8, 160, 120, 100)?));
+///
+/// assert_eq!(landmarks.lookup("Dock 3")?.name, "dock-3");
+/// assert_eq!(landmarks.lookup("chargr-1")?.name, "charger-1");
+/// // "charger" could be either
+/// assert!(landmarks.lookup("charger").is_err());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct LandmarkRegistry {
+    landmarks: BTreeMap<String, Landmark>,
+}
+
+impl LandmarkRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a landmark, returning the one it replaces under the same name
+    pub fn insert(&mut self, landmark: Landmark) -> Option<Landmark> {
+        self.landmarks.insert(landmark.name.clone(), landmark)
+    }
+
+    /// Remove the landmark named exactly `name`
+    pub fn remove(&mut self, name: &str) -> Option<Landmark> {
+        self.landmarks.remove(name)
+    }
+
+    /// Landmark named exactly `name`
+    pub fn get(&self, name: &str) -> Option<&Landmark> {
+        self.landmarks.get(name)
+    }
+
+    /// Number of landmarks
+    pub fn len(&self) -> usize {
+        self.landmarks.len()
+    }
+
+    /// Whether the registry has no landmarks
+    pub fn is_empty(&self) -> bool {
+        self.landmarks.is_empty()
+    }
+
+    /// Landmarks in name order
+    pub fn iter(&self) -> impl Iterator<Item = &Landmark> + '_ {
+        self.landmarks.values()
+    }
+
+    /// Landmarks bound to `cell`
+    pub fn at(&self, cell: Index64) -> impl Iterator<Item = &Landmark> + '_ {
+        self.iter().filter(move |l| l.cell == cell)
+    }
+
+    /// Landmarks matching a loosely typed name, best first
+    ///
+    /// Names are compared on their lowercase letters and digits. An equal
+    /// name ranks first, then names the query is a prefix of, then names
+    /// within a quarter of the query's length in edits (at least one).
+    pub fn search(&self, query: &str) -> Vec<&Landmark> {
+        let query = normalize(query);
+        let mut ranked: Vec<(usize, &Landmark)> = self
+            .iter()
+            .filter_map(|l| Some((rank(&query, &normalize(&l.name))?, l)))
+            .collect();
+        ranked.sort_by_key(|&(rank, _)| rank);
+        ranked.into_iter().map(|(_, l)| l).collect()
+    }
+
+    /// The landmark a loosely typed name refers to
+    ///
+    /// An exact name always wins; otherwise the best match of
+    /// [`search`](Self::search) must be unique.
+    pub fn lookup(&self, query: &str) -> Result<&Landmark> {
+        if let Some(landmark) = self.get(query) {
+            return Ok(landmark);
+        }
+        let normalized = normalize(query);
+        let ranked: Vec<(usize, &Landmark)> = self
+            .iter()
+            .filter_map(|l| Some((rank(&normalized, &normalize(&l.name))?, l)))
+            .collect();
+        let Some(best) = ranked.iter().map(|&(rank, _)| rank).min() else {
+            return Err(Error::UnknownLandmark(query.to_string()));
+        };
+        let mut matches = ranked.into_iter().filter(|&(rank, _)| rank == best);
+        match (matches.next(), matches.next()) {
+            (Some((_, landmark)), None) => Ok(landmark),
+            (Some((_, first)), Some((_, second))) => Err(Error::AmbiguousLandmark {
+                query: query.to_string(),
+                candidates: [first, second]
+                    .into_iter()
+                    .chain(matches.map(|(_, l)| l))
+                    .map(|l| l.name.clone())
+                    .collect(),
+            }),
+            (None, _) => unreachable!("the best rank comes from a match"),
+        }
+    }
+
+    /// Regions of `graph` to pass from landmark `from` to landmark `to`
+    ///
+    /// Names are resolved with [`lookup`](Self::lookup). `None` if either
+    /// landmark lies outside the graph's regions or no route joins them.
+    pub fn route(
+        &self,
+        graph: &TopologicalGraph,
+        from: &str,
+        to: &str,
+    ) -> Result<Option<Vec<usize>>> {
+        let (from, to) = (self.lookup(from)?, self.lookup(to)?);
+        let (Some(start), Some(goal)) = (graph.region_of(from.cell), graph.region_of(to.cell))
+        else {
+            return Ok(None);
+        };
+        Ok(graph.route(start, goal))
+    }
+
+    /// Serialized registry, in the layout of the [module docs](self)
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = LANDMARKS_TAG.to_vec();
+        write_varint(&mut bytes, self.landmarks.len() as u64);
+        for landmark in self.iter() {
+            write_varint(&mut bytes, landmark.name.len() as u64);
+            bytes.extend_from_slice(landmark.name.as_bytes());
+            bytes.extend_from_slice(&landmark.cell.raw().to_le_bytes());
+            match &landmark.pose {
+                None => bytes.push(0),
+                Some(Pose {
+                    position,
+                    orientation,
+                }) => {
+                    bytes.push(1);
+                    let values = [
+                        position.x,
+                        position.y,
+                        position.z,
+                        orientation.x,
+                        orientation.y,
+                        orientation.z,
+                        orientation.w,
+                    ];
+                    for value in values {
+                        bytes.extend_from_slice(&value.to_le_bytes());
+                    }
+                }
+            }
+        }
+        bytes
+    }
+
+    /// Registry from [`encode`](Self::encode) output
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        if !bytes.starts_with(LANDMARKS_TAG) {
+            return Err(Error::InvalidFormat(
+                "landmarks do not start with OLM1".to_string(),
+            ));
+        }
+        let truncated = || Error::InvalidFormat("landmarks are truncated".to_string());
+        let mut pos = LANDMARKS_TAG.len();
+        let take = |pos: &mut usize, len: usize| -> Result<&[u8]> {
+            let field = bytes.get(*pos..*pos + len).ok_or_else(truncated)?;
+            *pos += len;
+            Ok(field)
+        };
+
+        let count = read_varint(bytes, &mut pos)?;
+        let mut registry = Self::new();
+        for _ in 0..count {
+            let len = read_varint(bytes, &mut pos)? as usize;
+            let name = std::str::from_utf8(take(&mut pos, len)?)
+                .map_err(|e| Error::InvalidFormat(format!("landmark name: {e}")))?
+                .to_string();
+            let cell =
+                Index64::from_value(u64::from_le_bytes(take(&mut pos, 8)?.try_into().unwrap()))?;
+            let pose = match take(&mut pos, 1)?[0] {
+                0 => None,
+                1 => {
+                    let mut values = [0.0; 7];
+                    for value in &mut values {
+                        *value = f64::from_le_bytes(take(&mut pos, 8)?.try_into().unwrap());
+                    }
+                    let [x, y, z, qx, qy, qz, qw] = values;
+                    Some(Pose {
+                        position: Point { x, y, z },
+                        orientation: Quaternion {
+                            x: qx,
+                            y: qy,
+                            z: qz,
+                            w: qw,
+                        },
+                    })
+                }
+                flag => {
+                    return Err(Error::InvalidFormat(format!(
+                        "unknown landmark pose flag {flag}"
+                    )))
+                }
+            };
+            registry.insert(Landmark { name, cell, pose });
+        }
+        if pos != bytes.len() {
+            return Err(Error::InvalidFormat(
+                "trailing bytes after landmarks".to_string(),
+            ));
+        }
+        Ok(registry)
+    }
+}
+
+#[cfg(feature = "container_v2")]
+impl LandmarkRegistry {
+    /// Store the registry as metadata entry [`LANDMARKS_METADATA_KEY`]
+    ///
+    /// Like all metadata it is written at
+    /// [`ContainerWriterV2::finish`], replacing an earlier registry.
+    pub fn write_container<W: std::io::Write + std::io::Seek>(
+        &self,
+        writer: &mut ContainerWriterV2<W>,
+    ) {
+        writer.set_metadata(LANDMARKS_METADATA_KEY, self.encode());
+    }
+
+    /// Registry stored in a container, empty if it has none
+    pub fn read_container(reader: &ContainerReaderV2) -> Result<Self> {
+        match reader.metadata_value(LANDMARKS_METADATA_KEY) {
+            None => Ok(Self::new()),
+            Some(MetadataValue::Bytes(bytes)) => Self::decode(bytes),
+            Some(_) => Err(Error::InvalidFormat(format!(
+                "metadata entry {LANDMARKS_METADATA_KEY} is not binary"
+            ))),
+        }
+    }
+}
+
+/// Lowercase letters and digits of a name
+fn normalize(name: &str) -> Vec<char> {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Rank of `name` as a match for `query`, lower is better
+fn rank(query: &[char], name: &[char]) -> Option<usize> {
+    if query.is_empty() {
+        return None;
+    }
+    if query == name {
+        return Some(0);
+    }
+    if name.starts_with(query) {
+        return Some(1);
+    }
+    let edits = edit_distance(query, name);
+    (edits <= (query.len() / 4).max(1)).then_some(1 + edits)
+}
+
+/// Levenshtein distance
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitute = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitute.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> Result<LandmarkRegistry> {
+        let mut landmarks = LandmarkRegistry::new();
+        landmarks.insert(Landmark::new(
+            "dock-3",
+            Index64::new(0, 0, 8, 100, 100, 100)?,
+        ));
+        landmarks.insert(Landmark::new(
+            "dock-30",
+            Index64::new(0, 0, 8, 102, 100, 100)?,
+        ));
+        let pose = Pose {
+            position: Point {
+                x: 1.5,
+                y: -2.0,
+                z: 0.25,
+            },
+            orientation: Quaternion {
+                x: 0.0,
+                y: 0.0,
+                z: 0.6,
+                w: 0.8,
+            },
+        };
+        landmarks.insert(
+            Landmark::new("Charger 1", Index64::new(0, 0, 8, 140, 120, 100)?).with_pose(pose),
+        );
+        Ok(landmarks)
+    }
+
+    #[test]
+    fn test_fuzzy_lookup() -> Result<()> {
+        let landmarks = registry()?;
+        assert_eq!(landmarks.lookup("DOCK_3")?.name, "dock-3");
+        assert_eq!(landmarks.lookup("dock 30")?.name, "dock-30");
+        assert_eq!(landmarks.lookup("charger-1")?.name, "Charger 1");
+        assert_eq!(landmarks.lookup("chager1")?.name, "Charger 1");
+        assert_eq!(landmarks.lookup("char")?.name, "Charger 1");
+        assert_eq!(
+            landmarks.lookup("doc").unwrap_err(),
+            Error::AmbiguousLandmark {
+                query: "doc".to_string(),
+                candidates: vec!["dock-3".to_string(), "dock-30".to_string()],
+            }
+        );
+        assert_eq!(
+            landmarks.lookup("elevator").unwrap_err(),
+            Error::UnknownLandmark("elevator".to_string())
+        );
+        let names: Vec<&str> = landmarks
+            .search("dock3")
+            .iter()
+            .map(|l| l.name.as_str())
+            .collect();
+        assert_eq!(names, ["dock-3", "dock-30"]);
+        let cell = Index64::new(0, 0, 8, 100, 100, 100)?;
+        assert_eq!(landmarks.at(cell).count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_round_trip() -> Result<()> {
+        let landmarks = registry()?;
+        let bytes = landmarks.encode();
+        let decoded = LandmarkRegistry::decode(&bytes)?;
+        assert_eq!(decoded.encode(), bytes);
+        assert_eq!(decoded.len(), 3);
+        let pose = decoded.get("Charger 1").unwrap().pose.as_ref().unwrap();
+        assert_eq!((pose.position.y, pose.orientation.w), (-2.0, 0.8));
+        assert!(decoded.get("dock-3").unwrap().pose.is_none());
+        assert!(LandmarkRegistry::decode(&bytes[..bytes.len() - 1]).is_err());
+        Ok(())
+    }
+
+    #[cfg(feature = "container_v2")]
+    #[test]
+    fn test_container_round_trip() -> Result<()> {
+        use crate::container_v2::StreamConfig;
+        use std::io::Cursor;
+
+        let landmarks = registry()?;
+        let mut bytes = Vec::new();
+        let mut writer = ContainerWriterV2::new(Cursor::new(&mut bytes), StreamConfig::default())?;
+        landmarks.write_container(&mut writer);
+        writer.finish()?;
+
+        let reader = ContainerReaderV2::from_bytes(bytes)?;
+        let restored = LandmarkRegistry::read_container(&reader)?;
+        assert_eq!(restored.encode(), landmarks.encode());
+        Ok(())
+    }
+}
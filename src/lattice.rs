@@ -264,6 +264,133 @@ impl Lattice {
     }
 }
 
+/// A cell pierced by a ray, with the ray parameter interval inside it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayCell {
+    /// Lattice point whose truncated-octahedral cell the ray crosses
+    pub coord: LatticeCoord,
+    /// Distance along the ray where it enters the cell
+    pub t_enter: f64,
+    /// Distance along the ray where it leaves the cell
+    pub t_exit: f64,
+}
+
+/// Iterator over the cells pierced by a ray, created by [`raycast`]
+#[derive(Debug, Clone)]
+pub struct Raycast {
+    origin: (f64, f64, f64),
+    direction: (f64, f64, f64),
+    max_dist: f64,
+    current: Option<LatticeCoord>,
+    t: f64,
+}
+
+/// Walk the truncated-octahedral cells pierced by a ray, in order
+///
+/// Coordinates are lattice units at resolution 0 (the frame of
+/// [`LatticeCoord::to_physical`]). `direction` need not be normalized; the
+/// ray parameters reported in [`RayCell`] are Euclidean distances from
+/// `origin`. The walk starts in the cell containing `origin` and ends with
+/// the cell containing the point at `max_dist`.
+///
+/// Each BCC Voronoi cell is bounded by the bisector planes to its 14
+/// neighbors, so the next cell is the neighbor whose bisector the ray
+/// crosses first — the BCC analogue of a 3D-DDA step. A ray passing exactly
+/// through an edge or vertex moves to one of the touching cells; cells it
+/// only grazes are not reported.
+///
+/// # Example
+///
+/// ```
+/// use octaindex3d::lattice::raycast;
+///
+/// # fn main() -> octaindex3d::Result<()> {
+/// let cells: Vec<_> = raycast((0.0, 0.0, 0.0), (1.0, 0.0, 0.0), 10.0)?.collect();
+/// assert_eq!(cells.first().map(|c| c.coord.x), Some(0));
+/// assert!(cells.last().unwrap().t_exit >= 10.0);
+/// # Ok(())
+/// # }
+/// ```
+pub fn raycast(
+    origin: (f64, f64, f64),
+    direction: (f64, f64, f64),
+    max_dist: f64,
+) -> Result<Raycast> {
+    let (dx, dy, dz) = direction;
+    let norm = (dx * dx + dy * dy + dz * dz).sqrt();
+    if !norm.is_finite() || norm == 0.0 {
+        return Err(Error::InvalidFormat(format!(
+            "ray direction must be non-zero and finite, got {:?}",
+            direction
+        )));
+    }
+    if max_dist.is_nan() {
+        return Err(Error::InvalidFormat("max_dist is NaN".to_string()));
+    }
+    let start = Lattice::physical_to_lattice(origin.0, origin.1, origin.2, 0)?;
+
+    Ok(Raycast {
+        origin,
+        direction: (dx / norm, dy / norm, dz / norm),
+        max_dist,
+        current: Some(start),
+        t: 0.0,
+    })
+}
+
+impl Iterator for Raycast {
+    type Item = RayCell;
+
+    fn next(&mut self) -> Option<RayCell> {
+        let coord = self.current.take()?;
+        if self.t > self.max_dist {
+            return None;
+        }
+
+        let (o, d) = (self.origin, self.direction);
+        let rel = (
+            o.0 - coord.x as f64,
+            o.1 - coord.y as f64,
+            o.2 - coord.z as f64,
+        );
+
+        // The exit face is the first bisector plane ahead of the ray
+        let mut exit = (f64::INFINITY, None);
+        for &(nx, ny, nz) in BCC_NEIGHBORS_14 {
+            let (nx, ny, nz) = (nx as f64, ny as f64, nz as f64);
+            let speed = d.0 * nx + d.1 * ny + d.2 * nz;
+            if speed <= 0.0 {
+                continue;
+            }
+            let half = 0.5 * (nx * nx + ny * ny + nz * nz);
+            let t = (half - (rel.0 * nx + rel.1 * ny + rel.2 * nz)) / speed;
+            if t < exit.0 {
+                exit = (t, Some((nx as i32, ny as i32, nz as i32)));
+            }
+        }
+
+        let (t_exit, step) = exit;
+        let t_exit = t_exit.max(self.t);
+        let cell = RayCell {
+            coord,
+            t_enter: self.t,
+            t_exit,
+        };
+
+        if t_exit < self.max_dist {
+            self.current = step.and_then(|(sx, sy, sz)| {
+                Some(LatticeCoord::new_unchecked(
+                    coord.x.checked_add(sx)?,
+                    coord.y.checked_add(sy)?,
+                    coord.z.checked_add(sz)?,
+                ))
+            });
+            self.t = t_exit;
+        }
+        Some(cell)
+    }
+}
+
 /// Round to the nearest even integer
 #[inline]
 fn round_to_even(v: f64) -> i32 {
@@ -493,4 +620,60 @@ mod tests {
         let dist = a.distance_to(&c);
         assert!((dist - 3.0_f64.sqrt()).abs() < 1e-10);
     }
+
+    #[test]
+    fn test_raycast_matches_sampling() {
+        let rays = [
+            ((0.3, -0.2, 0.1), (1.0, 0.0, 0.0)),
+            ((0.0, 0.0, 0.0), (1.0, 1.0, 1.0)),
+            ((-3.7, 2.2, 5.1), (0.3, -0.8, 0.5)),
+            ((10.0, 10.0, 10.0), (-0.1, 0.05, -1.0)),
+        ];
+        for (origin, direction) in rays {
+            let cells: Vec<RayCell> = raycast(origin, direction, 20.0).unwrap().collect();
+
+            // Consecutive cells are lattice neighbors with contiguous intervals
+            for pair in cells.windows(2) {
+                let (a, b) = (pair[0].coord, pair[1].coord);
+                let delta = (b.x - a.x, b.y - a.y, b.z - a.z);
+                assert!(BCC_NEIGHBORS_14.contains(&delta));
+                assert_eq!(pair[0].t_exit, pair[1].t_enter);
+            }
+            assert_eq!(cells[0].t_enter, 0.0);
+            assert!(cells.last().unwrap().t_exit >= 20.0);
+
+            // Dense samples fall into the reported cell for their distance
+            let norm =
+                (direction.0 * direction.0 + direction.1 * direction.1 + direction.2 * direction.2)
+                    .sqrt();
+            for i in 0..2000 {
+                let t = 20.0 * (i as f64 + 0.5) / 2000.0;
+                let p = (
+                    origin.0 + t * direction.0 / norm,
+                    origin.1 + t * direction.1 / norm,
+                    origin.2 + t * direction.2 / norm,
+                );
+                let expected = Lattice::physical_to_lattice(p.0, p.1, p.2, 0).unwrap();
+                let cell = cells
+                    .iter()
+                    .find(|c| c.t_enter <= t && t <= c.t_exit)
+                    .unwrap();
+                // Samples on a shared face may snap to either side
+                if cell.coord != expected {
+                    let dist = |c: &LatticeCoord| {
+                        (c.x as f64 - p.0).powi(2)
+                            + (c.y as f64 - p.1).powi(2)
+                            + (c.z as f64 - p.2).powi(2)
+                    };
+                    assert!((dist(&cell.coord) - dist(&expected)).abs() < 1e-6);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_raycast_invalid_direction() {
+        assert!(raycast((0.0, 0.0, 0.0), (0.0, 0.0, 0.0), 1.0).is_err());
+        assert!(raycast((0.0, 0.0, 0.0), (f64::NAN, 0.0, 0.0), 1.0).is_err());
+    }
 }
@@ -0,0 +1,391 @@
+//! Capability handles restricting what a component may do with a map
+//!
+//! Components that should not see or change the whole map get a handle
+//! instead of the [`LayeredMap`]:
+//!
+//! - [`ReadOnlyMap`] reads any layer and writes nothing
+//! - [`RegionScopedMap`] reads and writes only what its [`AccessPolicy`]
+//!   allows, usually a [`RegionGrant`] over a box of cells and a set of layers
+//!
+//! Checks run on every call, so a handle cannot be used to reach cells
+//! outside its grant; denied calls fail with [`Error::AccessDenied`].
+
+use super::{Layer, LayerType, LayeredMap, Measurement, VoxelBounds};
+use crate::error::{Error, Result};
+use crate::ids::FrameId;
+use crate::Index64;
+
+/// Kind of access asked of a handle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Access {
+    /// Query cells
+    Read,
+    /// Update cells
+    Write,
+}
+
+/// Decides which cells of which layers a handle may read or write
+///
+/// Closures `Fn(Access, LayerType, Index64) -> bool` are policies too.
+pub trait AccessPolicy {
+    /// Whether `access` to cell `idx` of `layer_type` is allowed
+    fn allows(&self, access: Access, layer_type: LayerType, idx: Index64) -> bool;
+}
+
+impl<F: Fn(Access, LayerType, Index64) -> bool> AccessPolicy for F {
+    fn allows(&self, access: Access, layer_type: LayerType, idx: Index64) -> bool {
+        self(access, layer_type, idx)
+    }
+}
+
+/// Box of cells at one scale tier and LOD
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GrantRegion {
+    /// Scale tier of the cells covered
+    pub tier: u8,
+    /// LOD the bounds are given at
+    pub lod: u8,
+    /// Inclusive coordinate bounds at `lod`
+    pub bounds: VoxelBounds,
+}
+
+impl GrantRegion {
+    /// Whether `idx`, at any LOD, lies entirely inside the box
+    ///
+    /// A coarser cell is inside only if every cell it covers at the
+    /// region's LOD is, so coarse writes cannot reach past the box. A region
+    /// past the deepest LOD covers nothing.
+    fn covers(&self, idx: Index64) -> bool {
+        if idx.scale_tier() != self.tier || self.lod > 15 {
+            return false;
+        }
+        let (x, y, z) = idx.decode_coords();
+        let (min, max) = self.bounds;
+        let axis = |c: u16, min: u16, max: u16| {
+            let (lo, hi) = if idx.lod() <= self.lod {
+                let shift = (self.lod - idx.lod()) as u32;
+                let lo = (c as u64) << shift;
+                (lo, lo + (1u64 << shift) - 1)
+            } else {
+                let c = (c >> (idx.lod() - self.lod).min(16)) as u64;
+                (c, c)
+            };
+            min as u64 <= lo && hi <= max as u64
+        };
+        axis(x, min.0, max.0) && axis(y, min.1, max.1) && axis(z, min.2, max.2)
+    }
+}
+
+/// Access to some layers of one frame, optionally within a box of cells
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RegionGrant {
+    /// Frame whose cells are covered; cells of other frames never are
+    pub frame: FrameId,
+    /// Cells covered; `None` covers every cell of the frame
+    pub region: Option<GrantRegion>,
+    /// Layers that may be read
+    pub readable: Vec<LayerType>,
+    /// Layers that may be written; writing does not imply reading
+    pub writable: Vec<LayerType>,
+}
+
+impl RegionGrant {
+    /// Grant nothing over the cells of `frame`
+    pub fn new(frame: FrameId) -> Self {
+        Self {
+            frame,
+            ..Self::default()
+        }
+    }
+
+    /// Restrict the grant to a box of cells of `tier`, with `bounds` in
+    /// coordinates at `lod`
+    ///
+    /// Finer cells are covered when their ancestor at `lod` is; coarser
+    /// cells only when all of their extent is. Fails like [`Index64::new`]
+    /// if `tier` is above 3 or `lod` above 15.
+    pub fn within(mut self, tier: u8, lod: u8, bounds: VoxelBounds) -> Result<Self> {
+        if tier > 3 {
+            return Err(Error::InvalidScaleTier(format!(
+                "tier must be 0-3, got {}",
+                tier
+            )));
+        }
+        if lod > 15 {
+            return Err(Error::InvalidLOD(format!("lod must be 0-15, got {}", lod)));
+        }
+        self.region = Some(GrantRegion { tier, lod, bounds });
+        Ok(self)
+    }
+
+    /// Also allow reading `layers`
+    pub fn read(mut self, layers: &[LayerType]) -> Self {
+        self.readable.extend_from_slice(layers);
+        self
+    }
+
+    /// Also allow writing `layers`
+    pub fn write(mut self, layers: &[LayerType]) -> Self {
+        self.writable.extend_from_slice(layers);
+        self
+    }
+}
+
+impl AccessPolicy for RegionGrant {
+    fn allows(&self, access: Access, layer_type: LayerType, idx: Index64) -> bool {
+        let layers = match access {
+            Access::Read => &self.readable,
+            Access::Write => &self.writable,
+        };
+        layers.contains(&layer_type)
+            && idx.frame_id() == self.frame
+            && self.region.map_or(true, |region| region.covers(idx))
+    }
+}
+
+/// Handle reading any layer of a map and writing none
+#[derive(Clone, Copy)]
+pub struct ReadOnlyMap<'a> {
+    map: &'a LayeredMap,
+}
+
+impl<'a> ReadOnlyMap<'a> {
+    /// Read-only handle to `map`
+    pub fn new(map: &'a LayeredMap) -> Self {
+        Self { map }
+    }
+
+    /// Value of a cell in one layer
+    pub fn query(&self, layer_type: LayerType, idx: Index64) -> Option<f32> {
+        self.map
+            .layer(layer_type)
+            .and_then(|layer| layer.query(idx))
+    }
+
+    /// A layer, for reading
+    pub fn layer(&self, layer_type: LayerType) -> Option<&'a dyn Layer> {
+        self.map.layer(layer_type)
+    }
+
+    /// Whether the map has a layer
+    pub fn has_layer(&self, layer_type: LayerType) -> bool {
+        self.map.has_layer(layer_type)
+    }
+
+    /// Types of the map's layers
+    pub fn layer_types(&self) -> Vec<LayerType> {
+        self.map.layer_types()
+    }
+
+    /// Voxels across all layers
+    pub fn total_voxels(&self) -> usize {
+        self.map.total_voxels()
+    }
+}
+
+/// Handle reading and writing only what a policy allows
+///
+/// # Example
+///
+/// ```
+/// use octaindex3d::layers::{LayerType, LayeredMap, Measurement, OccupancyLayer, RegionGrant};
+/// use octaindex3d::Index64;
+///
+/// # fn main() -> octaindex3d::Result<()> {
+/// let mut map = LayeredMap::new();
+/// map.add_occupancy_layer(OccupancyLayer::new());
+///
+/// // The team mapping the loading dock may only touch its corner of the site
+/// let dock = RegionGrant::new(0)
+///     .within(0, 8, ((0, 0, 0), (99, 99, 20)))?
+///     .read(&[LayerType::Occupancy])
+///     .write(&[LayerType::Occupancy]);
+/// let mut scoped = map.scoped(dock);
+///
+/// let inside = Index64::new(0, 0, 8, 10, 10, 10)?;
+/// let outside = Index64::new(0, 0, 8, 500, 10, 10)?;
+/// scoped.update(LayerType::Occupancy, inside, &Measurement::occupied(0.9))?;
+/// assert!(scoped.update(LayerType::Occupancy, outside, &Measurement::occupied(0.9)).is_err());
+/// assert!(scoped.query(LayerType::Occupancy, inside)?.is_some());
+/// # Ok(())
+/// # }
+/// ```
+pub struct RegionScopedMap<'a, P: AccessPolicy = RegionGrant> {
+    map: &'a mut LayeredMap,
+    policy: P,
+}
+
+impl<'a, P: AccessPolicy> RegionScopedMap<'a, P> {
+    /// Handle to `map` limited by `policy`
+    pub fn new(map: &'a mut LayeredMap, policy: P) -> Self {
+        Self { map, policy }
+    }
+
+    /// The policy enforced
+    pub fn policy(&self) -> &P {
+        &self.policy
+    }
+
+    fn check(&self, access: Access, layer_type: LayerType, idx: Index64) -> Result<()> {
+        if self.policy.allows(access, layer_type, idx) {
+            return Ok(());
+        }
+        let verb = match access {
+            Access::Read => "read",
+            Access::Write => "write",
+        };
+        Err(Error::AccessDenied(format!(
+            "{} of {} cell {:?} is not granted",
+            verb,
+            layer_type.name(),
+            idx.decode_coords()
+        )))
+    }
+
+    /// Value of a cell in one layer, if reading it is allowed
+    pub fn query(&self, layer_type: LayerType, idx: Index64) -> Result<Option<f32>> {
+        self.check(Access::Read, layer_type, idx)?;
+        Ok(self
+            .map
+            .layer(layer_type)
+            .and_then(|layer| layer.query(idx)))
+    }
+
+    /// Update a cell in one layer, if writing it is allowed
+    pub fn update(
+        &mut self,
+        layer_type: LayerType,
+        idx: Index64,
+        measurement: &Measurement,
+    ) -> Result<()> {
+        self.check(Access::Write, layer_type, idx)?;
+        self.map.update(layer_type, idx, measurement)
+    }
+
+    /// Cells of a layer that may be read, in index order
+    pub fn cells(&self, layer_type: LayerType) -> Vec<Index64> {
+        let Some(layer) = self.map.layer(layer_type) else {
+            return Vec::new();
+        };
+        let mut cells: Vec<Index64> = layer
+            .indices()
+            .filter(|&idx| self.policy.allows(Access::Read, layer_type, idx))
+            .collect();
+        cells.sort_unstable();
+        cells
+    }
+}
+
+impl LayeredMap {
+    /// Handle that can read this map but not change it
+    pub fn read_only(&self) -> ReadOnlyMap<'_> {
+        ReadOnlyMap::new(self)
+    }
+
+    /// Handle that can only read and write what `policy` allows
+    pub fn scoped<P: AccessPolicy>(&mut self, policy: P) -> RegionScopedMap<'_, P> {
+        RegionScopedMap::new(self, policy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layers::{OccupancyLayer, TSDFLayer};
+
+    #[test]
+    fn test_scoped_map_enforces_grant() {
+        let mut map = LayeredMap::new();
+        map.add_occupancy_layer(OccupancyLayer::new());
+        map.add_tsdf_layer(TSDFLayer::new(0.1));
+        let inside = Index64::new(0, 0, 8, 5, 5, 5).unwrap();
+        let outside = Index64::new(0, 0, 8, 50, 5, 5).unwrap();
+        map.update_occupancy(outside, &Measurement::occupied(0.9))
+            .unwrap();
+
+        let grant = RegionGrant::new(0)
+            .within(0, 8, ((0, 0, 0), (10, 10, 10)))
+            .unwrap()
+            .read(&[LayerType::Occupancy])
+            .write(&[LayerType::Occupancy, LayerType::TSDF]);
+        let mut scoped = map.scoped(grant);
+        scoped
+            .update(LayerType::Occupancy, inside, &Measurement::occupied(0.9))
+            .unwrap();
+        scoped
+            .update(LayerType::TSDF, inside, &Measurement::depth(0.05, 1.0))
+            .unwrap();
+        assert!(matches!(
+            scoped.query(LayerType::TSDF, inside),
+            Err(Error::AccessDenied(_))
+        ));
+        assert!(matches!(
+            scoped.query(LayerType::Occupancy, outside),
+            Err(Error::AccessDenied(_))
+        ));
+        assert!(matches!(
+            scoped.update(LayerType::Occupancy, outside, &Measurement::free(0.9)),
+            Err(Error::AccessDenied(_))
+        ));
+        assert_eq!(scoped.cells(LayerType::Occupancy), vec![inside]);
+
+        // A closure hook allowing only reads
+        let reader = map.scoped(|access, _, _| access == Access::Read);
+        assert!(
+            reader
+                .query(LayerType::Occupancy, outside)
+                .unwrap()
+                .unwrap()
+                > 0.5
+        );
+        let read_only = map.read_only();
+        assert_eq!(read_only.total_voxels(), 3);
+        assert!(read_only.query(LayerType::TSDF, inside).is_some());
+    }
+
+    #[test]
+    fn test_grant_rejects_other_frames_and_coarser_cells() {
+        let grant = RegionGrant::new(0)
+            .within(0, 8, ((0, 0, 0), (99, 99, 99)))
+            .unwrap()
+            .write(&[LayerType::Occupancy]);
+        let allows = |idx| grant.allows(Access::Write, LayerType::Occupancy, idx);
+        assert!(allows(Index64::new(0, 0, 8, 10, 10, 10).unwrap()));
+        // Same coordinates in another frame or tier
+        assert!(!allows(Index64::new(7, 0, 8, 10, 10, 10).unwrap()));
+        assert!(!allows(Index64::new(0, 1, 8, 10, 10, 10).unwrap()));
+        // A LOD-2 cell spans 64 LOD-8 cells per axis, far past the box
+        assert!(!allows(Index64::new(0, 0, 2, 10, 10, 10).unwrap()));
+        // A LOD-6 cell covering LOD-8 coordinates 96..=99 fits; 100.. does not
+        assert!(allows(Index64::new(0, 0, 6, 24, 0, 0).unwrap()));
+        assert!(!allows(Index64::new(0, 0, 6, 25, 0, 0).unwrap()));
+        // Finer cells follow their ancestor at the grant's LOD
+        assert!(allows(Index64::new(0, 0, 10, 399, 0, 0).unwrap()));
+        assert!(!allows(Index64::new(0, 0, 10, 400, 0, 0).unwrap()));
+    }
+
+    #[test]
+    fn test_grant_rejects_invalid_region() {
+        let bounds = ((0, 0, 0), (9, 9, 9));
+        assert!(matches!(
+            RegionGrant::new(0).within(0, 16, bounds),
+            Err(Error::InvalidLOD(_))
+        ));
+        assert!(matches!(
+            RegionGrant::new(0).within(4, 8, bounds),
+            Err(Error::InvalidScaleTier(_))
+        ));
+        // A hand-built region past the deepest LOD denies instead of
+        // overflowing the shift to its coordinates
+        let grant = RegionGrant {
+            region: Some(GrantRegion {
+                tier: 0,
+                lod: 200,
+                bounds,
+            }),
+            ..RegionGrant::new(0).write(&[LayerType::Occupancy])
+        };
+        let idx = Index64::new(0, 0, 0, 0, 0, 0).unwrap();
+        assert!(!grant.allows(Access::Write, LayerType::Occupancy, idx));
+    }
+}
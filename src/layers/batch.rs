@@ -0,0 +1,36 @@
+//! Grouping of batched layer updates by voxel
+//!
+//! [`Layer::update_batch`](super::Layer::update_batch) overrides fold each
+//! voxel's measurements independently, which parallelizes across voxels.
+//! Measurements of one voxel are folded in batch order, so a batch gives
+//! the same bits as calling [`Layer::update`](super::Layer::update) in
+//! turn.
+
+use crate::Index64;
+
+/// Fold the items of each voxel, in input order, into one value per voxel
+///
+/// Returns `(voxel, folded)` pairs sorted by voxel. Voxels are folded in
+/// parallel with the `parallel` feature.
+pub(crate) fn fold_by_voxel<T, S, F>(mut items: Vec<(Index64, T)>, fold: F) -> Vec<(Index64, S)>
+where
+    T: Sync,
+    S: Send,
+    F: Fn(Index64, &[(Index64, T)]) -> S + Sync,
+{
+    // Stable, so each voxel's items keep their order
+    items.sort_by_key(|(idx, _)| *idx);
+    let groups: Vec<&[(Index64, T)]> = items.chunk_by(|a, b| a.0 == b.0).collect();
+    let apply = |group: &&[(Index64, T)]| (group[0].0, fold(group[0].0, group));
+
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        groups.par_iter().map(apply).collect()
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    {
+        groups.iter().map(apply).collect()
+    }
+}
@@ -0,0 +1,256 @@
+//! Per-voxel RGB color layer
+//!
+//! Fuses color observations the way [`TSDFLayer`](super::TSDFLayer) fuses
+//! distances: each measurement contributes its confidence as weight to a
+//! running weighted average, and the accumulated weight is capped at
+//! `max_weight` so colors keep adapting to lighting changes. Means are
+//! stored rounded to 8 bits per channel.
+//!
+//! Meshes extracted with
+//! [`extract_colored_mesh`](super::mesh::extract_colored_mesh) take their
+//! vertex colors from this layer.
+
+use super::state::{decode_voxels, encode_voxels};
+use super::{DirtyTracker, Layer, LayerConfig, LayerType, Measurement, MeasurementType};
+use crate::error::{Error, Result};
+use crate::Index64;
+use std::collections::HashMap;
+
+/// Schema version of [`LayerConfig`] records written by this layer
+const CONFIG_VERSION: u16 = 1;
+
+/// Voxel data in color layer
+#[derive(Debug, Clone, Copy)]
+struct ColorVoxel {
+    /// Fused color
+    rgb: [u8; 3],
+    /// Cumulative weight for averaging
+    weight: f32,
+}
+
+impl ColorVoxel {
+    fn pack(&self) -> [u32; 2] {
+        let [r, g, b] = self.rgb;
+        [u32::from_le_bytes([r, g, b, 0]), self.weight.to_bits()]
+    }
+
+    fn unpack([rgb, weight]: [u32; 2]) -> Self {
+        let [r, g, b, _] = rgb.to_le_bytes();
+        Self {
+            rgb: [r, g, b],
+            weight: f32::from_bits(weight),
+        }
+    }
+}
+
+/// Color layer storing fused RGB per voxel
+pub struct ColorLayer {
+    /// Voxel storage
+    voxels: HashMap<Index64, ColorVoxel>,
+    /// Maximum weight (for clamping)
+    max_weight: f32,
+    /// Chunks changed since consumers last looked
+    dirty: DirtyTracker,
+}
+
+impl ColorLayer {
+    /// Create a color layer with the TSDF default maximum weight of 100
+    pub fn new() -> Self {
+        Self::with_max_weight(100.0)
+    }
+
+    /// Create a color layer capping accumulated weights at `max_weight`
+    ///
+    /// Use the same value as the TSDF layer the colors belong to.
+    pub fn with_max_weight(max_weight: f32) -> Self {
+        Self {
+            voxels: HashMap::new(),
+            max_weight,
+            dirty: DirtyTracker::default(),
+        }
+    }
+
+    /// Maximum accumulated weight
+    pub fn max_weight(&self) -> f32 {
+        self.max_weight
+    }
+
+    /// Fuse an RGB observation of `idx` with weight `confidence`
+    ///
+    /// # Example
+    /// ```
+    /// use octaindex3d::layers::ColorLayer;
+    /// use octaindex3d::Index64;
+    ///
+    /// # fn main() -> octaindex3d::Result<()> {
+    /// let mut color = ColorLayer::new();
+    /// let idx = Index64::new(0, 0, 5, 100, 100, 100)?;
+    /// color.integrate(idx, [200, 0, 0], 1.0);
+    /// color.integrate(idx, [100, 0, 50], 1.0);
+    /// assert_eq!(color.get_color(idx), Some([150, 0, 25]));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn integrate(&mut self, idx: Index64, rgb: [u8; 3], confidence: f32) {
+        self.dirty.mark(idx);
+        let max_weight = self.max_weight;
+        let voxel = self
+            .voxels
+            .entry(idx)
+            .or_insert(ColorVoxel { rgb, weight: 0.0 });
+
+        // Incremental weighted average, as in TSDF integration
+        let new_weight = (voxel.weight + confidence).min(max_weight);
+        if voxel.weight > 0.0 && new_weight > 0.0 {
+            for (channel, &observed) in voxel.rgb.iter_mut().zip(&rgb) {
+                let mean = (*channel as f32 * voxel.weight + observed as f32 * confidence)
+                    / (voxel.weight + confidence);
+                *channel = mean.round().clamp(0.0, 255.0) as u8;
+            }
+        } else {
+            voxel.rgb = rgb;
+        }
+        voxel.weight = new_weight;
+    }
+
+    /// Fused color of a voxel
+    pub fn get_color(&self, idx: Index64) -> Option<[u8; 3]> {
+        self.voxels.get(&idx).map(|v| v.rgb)
+    }
+
+    /// Accumulated weight of a voxel
+    pub fn get_weight(&self, idx: Index64) -> Option<f32> {
+        self.voxels.get(&idx).map(|v| v.weight)
+    }
+
+    fn merge_records(&mut self, records: Vec<(Index64, [u32; 2])>) {
+        for (idx, record) in records {
+            self.dirty.mark(idx);
+            self.voxels.insert(idx, ColorVoxel::unpack(record));
+        }
+    }
+}
+
+impl Default for ColorLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Layer for ColorLayer {
+    fn layer_type(&self) -> LayerType {
+        LayerType::Color
+    }
+
+    fn update(&mut self, idx: Index64, measurement: &Measurement) -> Result<()> {
+        match measurement.measurement_type {
+            MeasurementType::Color => {
+                let (r, g, b) = measurement.as_rgb()?;
+                self.integrate(idx, [r, g, b], measurement.confidence);
+                Ok(())
+            }
+            _ => Err(Error::InvalidFormat(
+                "Color layer requires color measurements".to_string(),
+            )),
+        }
+    }
+
+    /// Relative luminance (Rec. 709) of the fused color, in `[0, 1]`
+    fn query(&self, idx: Index64) -> Option<f32> {
+        self.get_color(idx)
+            .map(|[r, g, b]| (0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32) / 255.0)
+    }
+
+    fn voxel_count(&self) -> usize {
+        self.voxels.len()
+    }
+
+    fn indices(&self) -> Box<dyn Iterator<Item = Index64> + '_> {
+        Box::new(self.voxels.keys().copied())
+    }
+
+    fn clear(&mut self) {
+        self.dirty.mark_all(self.voxels.keys().copied());
+        self.voxels.clear();
+    }
+
+    fn dirty_tracker(&self) -> Option<&DirtyTracker> {
+        Some(&self.dirty)
+    }
+
+    fn dirty_tracker_mut(&mut self) -> Option<&mut DirtyTracker> {
+        Some(&mut self.dirty)
+    }
+
+    fn encode_state(&self) -> Option<Vec<u8>> {
+        let records = self
+            .voxels
+            .iter()
+            .map(|(&idx, voxel)| (idx, voxel.pack()))
+            .collect();
+        Some(encode_voxels(records))
+    }
+
+    fn encode_cells(&self, cells: &[Index64]) -> Option<Vec<u8>> {
+        let records = cells
+            .iter()
+            .filter_map(|&idx| Some((idx, self.voxels.get(&idx)?.pack())))
+            .collect();
+        Some(encode_voxels(records))
+    }
+
+    fn decode_state(&mut self, bytes: &[u8]) -> Result<()> {
+        let records = decode_voxels(bytes)?;
+        self.clear();
+        self.merge_records(records);
+        Ok(())
+    }
+
+    fn merge_state(&mut self, bytes: &[u8]) -> Result<()> {
+        self.merge_records(decode_voxels(bytes)?);
+        Ok(())
+    }
+
+    fn config(&self) -> Option<LayerConfig> {
+        Some(
+            LayerConfig::new(LayerType::Color, CONFIG_VERSION)
+                .with("max_weight", self.max_weight as f64),
+        )
+    }
+
+    fn apply_config(&mut self, config: &LayerConfig) -> Result<()> {
+        self.max_weight = config.get_or("max_weight", self.max_weight as f64) as f32;
+        Ok(())
+    }
+
+    fn memory_usage(&self) -> usize {
+        // Rough estimate: 24 bytes per entry (HashMap overhead) + 8 bytes (Index64) + 8 bytes (ColorVoxel)
+        self.voxels.len() * 40
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_weighted_fusion_and_state() -> Result<()> {
+        let mut color = ColorLayer::with_max_weight(4.0);
+        let idx = Index64::new(0, 0, 5, 100, 100, 100)?;
+        color.integrate(idx, [0, 0, 255], 3.0);
+        color.update(idx, &Measurement::color(255, 0, 0, 1.0))?;
+        assert_eq!(color.get_color(idx), Some([64, 0, 191]));
+        assert_eq!(color.get_weight(idx), Some(4.0));
+
+        // Capped weight: a new observation still moves the color
+        color.integrate(idx, [255, 0, 0], 4.0);
+        assert_eq!(color.get_color(idx), Some([160, 0, 96]));
+        assert!(color.update(idx, &Measurement::depth(1.0, 1.0)).is_err());
+
+        let mut restored = ColorLayer::new();
+        restored.decode_state(&color.encode_state().unwrap())?;
+        assert_eq!(restored.get_color(idx), color.get_color(idx));
+        assert_eq!(restored.encode_state(), color.encode_state());
+        Ok(())
+    }
+}
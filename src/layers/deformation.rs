@@ -0,0 +1,292 @@
+//! Post-hoc drift correction by deforming a map
+//!
+//! When a loop closure corrects the trajectory, the map built along the old
+//! trajectory is off by the accumulated drift. Rather than re-integrating
+//! the raw scans, a [`Deformation`] moves the map: anchors along the
+//! trajectory carry the correction of their pose, every chunk of cells
+//! takes the correction interpolated at its origin, and each chunk is
+//! shifted rigidly by it.
+//!
+//! Chunks keep their orientation, so rotations are followed by the chunk
+//! layout rather than inside a chunk; keep chunks small against the
+//! distance over which the correction changes. Derived layers such as ESDF
+//! should be recomputed afterwards.
+
+use super::state::{decode_packed, decode_voxels, encode_packed, encode_voxels, is_packed};
+use super::{LayerType, LayeredMap};
+use crate::error::Result;
+use crate::frame::FrameTransform;
+use crate::Index64;
+use std::collections::{BTreeMap, HashMap};
+
+/// Correction of one trajectory pose
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeformationAnchor {
+    /// Position of the pose before correction, in lattice coordinates at
+    /// the deformation's LOD
+    pub position: [f64; 3],
+    /// Transform taking points seen from the old pose to where the
+    /// corrected pose puts them
+    pub correction: FrameTransform,
+}
+
+/// Cells moved by [`Deformation::apply`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeformationStats {
+    /// Chunks shifted by a nonzero offset
+    pub chunks_moved: usize,
+    /// Cells shifted by a nonzero offset
+    pub cells_moved: usize,
+    /// Cells shifted off the coordinate range or to no valid cell, and
+    /// dropped
+    pub cells_dropped: usize,
+    /// Cells that landed on a cell already holding data, which they replaced
+    pub cells_merged: usize,
+    /// Layers left as they were because they cannot export their state
+    pub layers_skipped: Vec<LayerType>,
+}
+
+/// Pose-graph correction applied to a map by shifting its chunks
+///
+/// # Example
+///
+/// ```
+/// use octaindex3d::frame::FrameTransform;
+/// use octaindex3d::layers::{Deformation, LayeredMap, Measurement, OccupancyLayer};
+/// use octaindex3d::Index64;
+///
+/// # fn main() -> octaindex3d::Result<()> {
+/// let mut map = LayeredMap::new();
+/// map.add_occupancy_layer(OccupancyLayer::new());
+/// let wall = Index64::new(0, 0, 8, 200, 10, 10)?;
+/// map.update_occupancy(wall, &Measurement::occupied(0.9))?;
+///
+/// // Loop closure: the robot was 4 cells further along x than odometry said
+/// // by the end of the corridor, and exactly where it thought at the start
+/// let mut deformation = Deformation::new(8, 5);
+/// deformation.add_anchor([0.0, 10.0, 10.0], FrameTransform::IDENTITY);
+/// deformation.add_anchor(
+///     [200.0, 10.0, 10.0],
+///     FrameTransform::IDENTITY.with_translation([4.0, 0.0, 0.0]),
+/// );
+/// let stats = deformation.apply(&mut map)?;
+///
+/// assert_eq!(stats.cells_moved, 1);
+/// assert!(map.query_occupancy(wall).is_none());
+/// assert!(map.query_occupancy(Index64::new(0, 0, 8, 204, 10, 10)?).unwrap() > 0.5);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Deformation {
+    lod: u8,
+    chunk_lod: u8,
+    anchors: Vec<DeformationAnchor>,
+}
+
+impl Deformation {
+    /// Create a deformation with anchors in lattice coordinates at `lod`,
+    /// moving chunks of cells that share an ancestor at `chunk_lod`
+    pub fn new(lod: u8, chunk_lod: u8) -> Self {
+        Self {
+            lod,
+            chunk_lod,
+            anchors: Vec::new(),
+        }
+    }
+
+    /// Add the correction of the pose at `position`
+    pub fn add_anchor(&mut self, position: [f64; 3], correction: FrameTransform) {
+        self.anchors.push(DeformationAnchor {
+            position,
+            correction,
+        });
+    }
+
+    /// Anchors in the order added
+    pub fn anchors(&self) -> &[DeformationAnchor] {
+        &self.anchors
+    }
+
+    /// Correction of a point at the deformation's LOD
+    ///
+    /// Anchor displacements are blended with inverse-square distance
+    /// weights, so the field passes exactly through every anchor and
+    /// follows the nearest ones in between. Without anchors it is zero.
+    pub fn displacement(&self, point: [f64; 3]) -> [f64; 3] {
+        let mut sum = [0.0; 3];
+        let mut total = 0.0;
+        for anchor in &self.anchors {
+            let (x, y, z) = anchor.correction.apply((point[0], point[1], point[2]));
+            let offset = [x - point[0], y - point[1], z - point[2]];
+            let d2: f64 = (0..3)
+                .map(|i| (point[i] - anchor.position[i]).powi(2))
+                .sum();
+            if d2 == 0.0 {
+                return offset;
+            }
+            for i in 0..3 {
+                sum[i] += offset[i] / d2;
+            }
+            total += 1.0 / d2;
+        }
+        if total == 0.0 {
+            return [0.0; 3];
+        }
+        sum.map(|s| s / total)
+    }
+
+    /// Lattice offset of the cells of `chunk` at `lod`
+    ///
+    /// The correction is taken at the chunk's origin and rounded to even
+    /// offsets, so BCC cells stay on the lattice.
+    pub fn chunk_offset(&self, chunk: Index64, lod: u8) -> [i64; 3] {
+        let (x, y, z) = chunk.decode_coords();
+        // Chunk coordinates to the deformation's LOD, and offsets back to the cells'
+        let to_ref = 2f64.powi(self.lod as i32 - chunk.lod() as i32);
+        let to_cells = 2f64.powi(lod as i32 - self.lod as i32);
+        let origin = [x, y, z].map(|c| c as f64 * to_ref);
+        self.displacement(origin)
+            .map(|d| (d * to_cells / 2.0).round() as i64 * 2)
+    }
+
+    /// Shift every chunk of every layer of `map` by its offset
+    ///
+    /// Layers are rewritten through their exported state, so each keeps its
+    /// own cell data. Layers that do not export state are skipped and
+    /// listed in the stats.
+    pub fn apply(&self, map: &mut LayeredMap) -> Result<DeformationStats> {
+        let mut stats = DeformationStats::default();
+        let mut moved_chunks = std::collections::HashSet::new();
+        for layer_type in map.layer_types() {
+            let layer = map.layer_mut(layer_type).expect("listed layer");
+            let Some(state) = layer.encode_state() else {
+                stats.layers_skipped.push(layer_type);
+                continue;
+            };
+            let mut offsets = HashMap::new();
+            let mut shift = |idx: Index64, stats: &mut DeformationStats| -> Option<Index64> {
+                let chunk = idx.ancestor_at_lod(self.chunk_lod).unwrap_or(idx);
+                let offset = *offsets
+                    .entry((chunk, idx.lod()))
+                    .or_insert_with(|| self.chunk_offset(chunk, idx.lod()));
+                if offset == [0; 3] {
+                    return Some(idx);
+                }
+                let (x, y, z) = idx.decode_coords();
+                let coords = [x, y, z]
+                    .iter()
+                    .zip(offset)
+                    .map(|(&c, d)| u16::try_from(c as i64 + d).ok())
+                    .collect::<Option<Vec<u16>>>();
+                let moved = coords.and_then(|c| {
+                    Index64::new(
+                        idx.frame_id(),
+                        idx.scale_tier(),
+                        idx.lod(),
+                        c[0],
+                        c[1],
+                        c[2],
+                    )
+                    .ok()
+                });
+                let Some(moved) = moved else {
+                    stats.cells_dropped += 1;
+                    return None;
+                };
+                moved_chunks.insert(chunk);
+                stats.cells_moved += 1;
+                Some(moved)
+            };
+            let state = if is_packed(&state) {
+                let (width, records) = decode_packed(&state)?;
+                let records = relocate(records, &mut stats, &mut shift);
+                encode_packed(width, records)
+            } else {
+                let records = relocate(decode_voxels(&state)?, &mut stats, &mut shift);
+                encode_voxels(records)
+            };
+            layer.decode_state(&state)?;
+        }
+        stats.chunks_moved = moved_chunks.len();
+        Ok(stats)
+    }
+}
+
+/// Records with their cells shifted; a moved cell replaces one that stayed
+fn relocate<T>(
+    records: Vec<(Index64, T)>,
+    stats: &mut DeformationStats,
+    shift: &mut impl FnMut(Index64, &mut DeformationStats) -> Option<Index64>,
+) -> Vec<(Index64, T)> {
+    let mut out: BTreeMap<Index64, T> = BTreeMap::new();
+    let mut moved = Vec::new();
+    for (idx, data) in records {
+        match shift(idx, stats) {
+            Some(to) if to == idx => {
+                out.insert(idx, data);
+            }
+            Some(to) => moved.push((to, data)),
+            None => {}
+        }
+    }
+    for (to, data) in moved {
+        if out.insert(to, data).is_some() {
+            stats.cells_merged += 1;
+        }
+    }
+    out.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layers::{Measurement, OccupancyLayer, TSDFLayer};
+
+    #[test]
+    fn test_corridor_bends_with_correction() {
+        let mut map = LayeredMap::new();
+        map.add_occupancy_layer(OccupancyLayer::new());
+        map.add_tsdf_layer(TSDFLayer::new(0.1));
+        let cell = |x| Index64::new(0, 0, 8, x, 100, 100).unwrap();
+        for x in (0..=256).step_by(64) {
+            map.update_occupancy(cell(x), &Measurement::occupied(0.9))
+                .unwrap();
+        }
+        map.update_tsdf(cell(256), &Measurement::depth(0.05, 1.0))
+            .unwrap();
+        let before = map.query_tsdf(cell(256)).unwrap();
+
+        // Drift grows along the corridor to 8 cells up in y at its end
+        let mut deformation = Deformation::new(8, 4);
+        deformation.add_anchor([0.0, 100.0, 100.0], FrameTransform::IDENTITY);
+        deformation.add_anchor(
+            [256.0, 100.0, 100.0],
+            FrameTransform::IDENTITY.with_translation([0.0, 8.0, 0.0]),
+        );
+        assert_eq!(deformation.displacement([128.0, 100.0, 100.0])[1], 4.0);
+        let stats = deformation.apply(&mut map).unwrap();
+        assert_eq!(stats.cells_dropped, 0);
+        assert!(stats.layers_skipped.is_empty());
+
+        let occupied: Vec<(u16, u16, u16)> = {
+            let mut cells: Vec<_> = map
+                .layer(LayerType::Occupancy)
+                .unwrap()
+                .indices()
+                .map(|idx| idx.decode_coords())
+                .collect();
+            cells.sort();
+            cells
+        };
+        // The start stays, the end moves fully, cells between in part
+        assert_eq!(occupied.len(), 5);
+        assert_eq!(occupied[0], (0, 100, 100));
+        assert_eq!(occupied[4], (256, 108, 100));
+        assert!(occupied.windows(2).all(|w| w[0].1 <= w[1].1));
+        assert_eq!(
+            map.query_tsdf(Index64::new(0, 0, 8, 256, 108, 100).unwrap()),
+            Some(before)
+        );
+    }
+}
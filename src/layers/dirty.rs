@@ -0,0 +1,196 @@
+//! Per-chunk change tracking
+//!
+//! Layers record which chunks changed in a [`DirtyTracker`]. A chunk is the
+//! ancestor of a cell at the tracker's chunk LOD, the same keys
+//! [`crate::streaming::ChunkManager`] loads, so renderers, sync and
+//! incremental algorithms can all key off one mechanism.
+//!
+//! [`DirtyTracker::take_dirty`] drains the changes for the single common
+//! consumer. Systems that need their own view hold a [`DirtyCursor`] and
+//! call [`DirtyTracker::take_dirty_since`]; cursors never interfere with
+//! each other or with `take_dirty`.
+
+use crate::Index64;
+use std::collections::HashMap;
+
+/// Chunk LOD used by layers unless configured otherwise; matches
+/// [`crate::streaming::StreamingConfig`]'s default
+pub const DEFAULT_CHUNK_LOD: u8 = 4;
+
+/// Position of one consumer in a tracker's change history
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DirtyCursor {
+    seen: u64,
+}
+
+/// Set of chunks changed since each consumer last looked
+///
+/// # Example
+///
+/// ```
+/// use octaindex3d::layers::{Layer, OccupancyLayer};
+/// use octaindex3d::Index64;
+///
+/// # fn main() -> octaindex3d::Result<()> {
+/// let mut occupancy = OccupancyLayer::new();
+/// let mut mesher = occupancy.dirty().cursor();
+///
+/// occupancy.update_occupancy(Index64::new(0, 0, 8, 100, 100, 100)?, true, 0.9);
+/// occupancy.update_occupancy(Index64::new(0, 0, 8, 101, 101, 101)?, true, 0.9);
+///
+/// // Both cells fall in the same LOD-4 chunk
+/// let chunk = Index64::new(0, 0, 4, 6, 6, 6)?;
+/// assert_eq!(occupancy.take_dirty(), vec![chunk]);
+/// assert!(occupancy.take_dirty().is_empty());
+/// // The mesher's cursor is independent of `take_dirty`
+/// assert_eq!(occupancy.dirty().take_dirty_since(&mut mesher), vec![chunk]);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DirtyTracker {
+    chunk_lod: u8,
+    generation: u64,
+    /// Generation of each chunk's latest change
+    modified: HashMap<Index64, u64>,
+    /// Cursor drained by `take_dirty`
+    taken: DirtyCursor,
+}
+
+impl DirtyTracker {
+    /// Create a tracker grouping cells into chunks at `chunk_lod`
+    pub fn new(chunk_lod: u8) -> Self {
+        Self {
+            chunk_lod: chunk_lod.min(15),
+            generation: 0,
+            modified: HashMap::new(),
+            taken: DirtyCursor::default(),
+        }
+    }
+
+    /// LOD of chunk keys
+    pub fn chunk_lod(&self) -> u8 {
+        self.chunk_lod
+    }
+
+    /// Chunk containing a cell
+    ///
+    /// Cells coarser than the chunk LOD are their own chunk.
+    pub fn chunk_of(&self, idx: Index64) -> Index64 {
+        idx.ancestor_at_lod(self.chunk_lod).unwrap_or(idx)
+    }
+
+    /// Record a change to a cell
+    pub fn mark(&mut self, idx: Index64) {
+        self.generation += 1;
+        let chunk = self.chunk_of(idx);
+        self.modified.insert(chunk, self.generation);
+    }
+
+    /// Record changes to several cells
+    pub fn mark_all<I: IntoIterator<Item = Index64>>(&mut self, cells: I) {
+        for idx in cells {
+            self.mark(idx);
+        }
+    }
+
+    /// Check if a chunk changed since the last [`take_dirty`](Self::take_dirty)
+    pub fn is_dirty(&self, chunk: Index64) -> bool {
+        self.modified
+            .get(&chunk)
+            .is_some_and(|&g| g > self.taken.seen)
+    }
+
+    /// Chunks changed since the last [`take_dirty`](Self::take_dirty),
+    /// without consuming them, sorted
+    pub fn peek_dirty(&self) -> Vec<Index64> {
+        self.since(self.taken)
+    }
+
+    /// Number of chunks changed since the last [`take_dirty`](Self::take_dirty)
+    pub fn dirty_count(&self) -> usize {
+        self.modified
+            .values()
+            .filter(|&&g| g > self.taken.seen)
+            .count()
+    }
+
+    /// Drain the chunks changed since the previous call, sorted
+    pub fn take_dirty(&mut self) -> Vec<Index64> {
+        let mut cursor = self.taken;
+        let chunks = self.take_dirty_since(&mut cursor);
+        self.taken = cursor;
+        chunks
+    }
+
+    /// Cursor positioned at the present: later changes will be reported
+    pub fn cursor(&self) -> DirtyCursor {
+        DirtyCursor {
+            seen: self.generation,
+        }
+    }
+
+    /// Chunks changed since `cursor`, sorted; advances the cursor
+    pub fn take_dirty_since(&self, cursor: &mut DirtyCursor) -> Vec<Index64> {
+        let chunks = self.since(*cursor);
+        cursor.seen = self.generation;
+        chunks
+    }
+
+    /// Mark every chunk ever changed as clean for `take_dirty`
+    pub fn clear_dirty(&mut self) {
+        self.taken = self.cursor();
+    }
+
+    fn since(&self, cursor: DirtyCursor) -> Vec<Index64> {
+        let mut chunks: Vec<Index64> = self
+            .modified
+            .iter()
+            .filter(|&(_, &g)| g > cursor.seen)
+            .map(|(&chunk, _)| chunk)
+            .collect();
+        chunks.sort_unstable();
+        chunks
+    }
+}
+
+impl Default for DirtyTracker {
+    fn default() -> Self {
+        Self::new(DEFAULT_CHUNK_LOD)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_independent_consumers() {
+        let mut tracker = DirtyTracker::new(2);
+        let cell = |x| Index64::new(0, 0, 5, x, 0, 0).unwrap();
+        let mut sync = tracker.cursor();
+
+        tracker.mark_all([cell(0), cell(7), cell(8)]);
+        let chunks = vec![
+            cell(0).ancestor_at_lod(2).unwrap(),
+            cell(8).ancestor_at_lod(2).unwrap(),
+        ];
+        assert_eq!(tracker.dirty_count(), 2);
+        assert_eq!(tracker.take_dirty(), chunks);
+        assert!(!tracker.is_dirty(chunks[0]));
+
+        tracker.mark(cell(1));
+        assert_eq!(tracker.take_dirty(), vec![chunks[0]]);
+        // The sync cursor saw nothing yet and gets everything once
+        assert_eq!(tracker.take_dirty_since(&mut sync), chunks);
+        assert!(tracker.take_dirty_since(&mut sync).is_empty());
+
+        // Coarse cells are their own chunk
+        let coarse = Index64::new(0, 0, 1, 1, 1, 1).unwrap();
+        tracker.mark(coarse);
+        assert!(tracker.is_dirty(coarse));
+        tracker.clear_dirty();
+        assert!(tracker.peek_dirty().is_empty());
+        assert_eq!(tracker.take_dirty_since(&mut sync), vec![coarse]);
+    }
+}
@@ -0,0 +1,436 @@
+//! Change Rate and Dynamics Classification Layer
+//!
+//! Tracks how often each cell flips between occupied and free across
+//! successive observations and classifies it as static (walls), semi-static
+//! (doors, furniture) or dynamic (people, vehicles). Planners can consult the
+//! classification to, for example, treat a closed door as potentially
+//! passable while never routing through a wall.
+//!
+//! Observations are binary occupied/free samples with caller-supplied
+//! timestamps, in time order per cell. The update history of a
+//! [`TemporalOccupancyLayer`] is fed in with
+//! [`DynamicsLayer::observe_temporal`], and whole-layer snapshots of an
+//! [`OccupancyLayer`] with [`DynamicsLayer::observe_occupancy`].
+
+use super::occupancy::{OccupancyLayer, OccupancyState};
+use super::occupancy_temporal::TemporalOccupancyLayer;
+use crate::Index64;
+use std::collections::HashMap;
+
+/// Dynamics classification of a cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DynamicsClass {
+    /// Not enough observations to classify
+    Unknown,
+    /// Rarely or never changes (walls, floors)
+    Static,
+    /// Changes occasionally (doors, furniture)
+    SemiStatic,
+    /// Changes frequently (people, vehicles)
+    Dynamic,
+}
+
+/// Thresholds for dynamics classification
+///
+/// The flip ratio of a cell is the fraction of consecutive observation pairs
+/// in which its occupancy changed.
+#[derive(Debug, Clone)]
+pub struct DynamicsConfig {
+    /// Minimum observations before a cell is classified
+    pub min_observations: u32,
+    /// Flip ratio at or above which a cell is semi-static
+    pub semi_static_ratio: f32,
+    /// Flip ratio at or above which a cell is dynamic
+    pub dynamic_ratio: f32,
+}
+
+impl Default for DynamicsConfig {
+    fn default() -> Self {
+        Self {
+            min_observations: 4,     // Need a few samples to judge
+            semi_static_ratio: 0.05, // 1 flip in 20 observations
+            dynamic_ratio: 0.3,      // ~1 flip in 3 observations
+        }
+    }
+}
+
+/// Per-cell change history
+#[derive(Debug, Clone, Copy)]
+struct ChangeRecord {
+    /// Last observed state
+    last_occupied: bool,
+    /// Number of observations
+    observations: u32,
+    /// Number of state changes between consecutive observations
+    flips: u32,
+    /// Time of first observation (seconds)
+    first_seen: f64,
+    /// Time of most recent observation (seconds)
+    last_seen: f64,
+}
+
+/// Layer tracking per-cell occupancy flip frequency
+///
+/// # Example
+///
+/// ```rust
+/// use octaindex3d::layers::{DynamicsClass, DynamicsLayer};
+/// use octaindex3d::Index64;
+///
+/// # fn example() -> octaindex3d::Result<()> {
+/// let mut dynamics = DynamicsLayer::new();
+/// let wall = Index64::new(0, 0, 5, 10, 10, 10)?;
+/// let door = Index64::new(0, 0, 5, 20, 20, 20)?;
+///
+/// for t in 0..10 {
+///     dynamics.observe(wall, true, t as f64);
+///     dynamics.observe(door, t % 2 == 0, t as f64);
+/// }
+///
+/// assert_eq!(dynamics.classify(wall), DynamicsClass::Static);
+/// assert_eq!(dynamics.classify(door), DynamicsClass::Dynamic);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DynamicsLayer {
+    records: HashMap<Index64, ChangeRecord>,
+    config: DynamicsConfig,
+}
+
+impl DynamicsLayer {
+    /// Create a dynamics layer with default thresholds
+    pub fn new() -> Self {
+        Self::with_config(DynamicsConfig::default())
+    }
+
+    /// Create a dynamics layer with custom thresholds
+    pub fn with_config(config: DynamicsConfig) -> Self {
+        Self {
+            records: HashMap::new(),
+            config,
+        }
+    }
+
+    /// Get the classification thresholds
+    pub fn config(&self) -> &DynamicsConfig {
+        &self.config
+    }
+
+    /// Record a binary occupancy observation of `idx` at time `t`
+    ///
+    /// Flips are counted between observations consecutive in time, so an
+    /// observation older than the cell's latest one is ignored. Returns
+    /// whether it was recorded.
+    pub fn observe(&mut self, idx: Index64, occupied: bool, t: f64) -> bool {
+        let record = self.records.entry(idx).or_insert(ChangeRecord {
+            last_occupied: occupied,
+            observations: 0,
+            flips: 0,
+            first_seen: t,
+            last_seen: t,
+        });
+        if t < record.last_seen {
+            return false;
+        }
+
+        if record.observations > 0 && record.last_occupied != occupied {
+            record.flips += 1;
+        }
+        record.last_occupied = occupied;
+        record.observations += 1;
+        record.last_seen = t;
+        true
+    }
+
+    /// Record the latest update of every occupied or free voxel of a
+    /// temporal occupancy layer
+    ///
+    /// Each voxel is observed in its state right after its last update, at
+    /// that update's clock time, unless the cell already has an observation
+    /// that recent; ingesting the same layer twice adds nothing. Updates
+    /// made between two calls collapse into the latest, so call this after
+    /// every batch of updates. Returns the number of observations recorded.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use octaindex3d::clock::SimClock;
+    /// use octaindex3d::layers::{DynamicsClass, DynamicsLayer, TemporalOccupancyLayer};
+    /// use octaindex3d::Index64;
+    /// use std::sync::Arc;
+    ///
+    /// # fn example() -> octaindex3d::Result<()> {
+    /// let clock = SimClock::new(0.0);
+    /// let mut occupancy = TemporalOccupancyLayer::new().with_clock(Arc::new(clock.clone()));
+    /// let mut dynamics = DynamicsLayer::new();
+    /// let door = Index64::new(0, 0, 5, 20, 20, 20)?;
+    ///
+    /// for t in 0..8 {
+    ///     occupancy.update_occupancy(door, t % 2 == 0, 0.99);
+    ///     occupancy.update_occupancy(door, t % 2 == 0, 0.99);
+    ///     dynamics.observe_temporal(&occupancy);
+    ///     clock.advance(1.0);
+    /// }
+    /// assert_eq!(dynamics.classify(door), DynamicsClass::Dynamic);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn observe_temporal(&mut self, occupancy: &TemporalOccupancyLayer) -> usize {
+        let mut recorded = 0;
+        for (idx, state, t) in occupancy.last_updates() {
+            let occupied = match state {
+                OccupancyState::Occupied => true,
+                OccupancyState::Free => false,
+                OccupancyState::Unknown => continue,
+            };
+            let seen = self
+                .records
+                .get(&idx)
+                .is_some_and(|r| r.observations > 0 && r.last_seen >= t);
+            if !seen && self.observe(idx, occupied, t) {
+                recorded += 1;
+            }
+        }
+        recorded
+    }
+
+    /// Record a snapshot of every classified (occupied or free) cell of an
+    /// occupancy layer at time `t`
+    ///
+    /// Cells in the unknown band are skipped so that uncertainty is not
+    /// mistaken for change.
+    pub fn observe_occupancy(&mut self, occupancy: &OccupancyLayer, t: f64) {
+        for idx in occupancy.get_occupied_voxels() {
+            self.observe(idx, true, t);
+        }
+        for idx in occupancy.get_free_voxels() {
+            self.observe(idx, false, t);
+        }
+    }
+
+    /// Number of observations recorded for a cell
+    pub fn observation_count(&self, idx: Index64) -> u32 {
+        self.records.get(&idx).map(|r| r.observations).unwrap_or(0)
+    }
+
+    /// Number of occupied/free flips recorded for a cell
+    pub fn flip_count(&self, idx: Index64) -> u32 {
+        self.records.get(&idx).map(|r| r.flips).unwrap_or(0)
+    }
+
+    /// Fraction of consecutive observation pairs in which the cell flipped
+    pub fn flip_ratio(&self, idx: Index64) -> Option<f32> {
+        self.records.get(&idx).and_then(|r| {
+            if r.observations < 2 {
+                None
+            } else {
+                Some(r.flips as f32 / (r.observations - 1) as f32)
+            }
+        })
+    }
+
+    /// Flips per second over the cell's observed time span
+    pub fn flip_rate(&self, idx: Index64) -> Option<f64> {
+        self.records.get(&idx).and_then(|r| {
+            let span = r.last_seen - r.first_seen;
+            if span > 0.0 {
+                Some(r.flips as f64 / span)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Classify a cell from its flip history
+    pub fn classify(&self, idx: Index64) -> DynamicsClass {
+        match self.records.get(&idx) {
+            Some(r) if r.observations >= self.config.min_observations.max(2) => {
+                let ratio = r.flips as f32 / (r.observations - 1) as f32;
+                if ratio >= self.config.dynamic_ratio {
+                    DynamicsClass::Dynamic
+                } else if ratio >= self.config.semi_static_ratio {
+                    DynamicsClass::SemiStatic
+                } else {
+                    DynamicsClass::Static
+                }
+            }
+            _ => DynamicsClass::Unknown,
+        }
+    }
+
+    /// All tracked cells with the given classification
+    pub fn cells_with_class(&self, class: DynamicsClass) -> Vec<Index64> {
+        let mut cells: Vec<Index64> = self
+            .records
+            .keys()
+            .copied()
+            .filter(|&idx| self.classify(idx) == class)
+            .collect();
+        cells.sort_unstable();
+        cells
+    }
+
+    /// Count of tracked cells per classification
+    pub fn stats(&self) -> DynamicsStats {
+        let mut stats = DynamicsStats {
+            total_cells: self.records.len(),
+            ..Default::default()
+        };
+        for &idx in self.records.keys() {
+            match self.classify(idx) {
+                DynamicsClass::Unknown => stats.unknown_count += 1,
+                DynamicsClass::Static => stats.static_count += 1,
+                DynamicsClass::SemiStatic => stats.semi_static_count += 1,
+                DynamicsClass::Dynamic => stats.dynamic_count += 1,
+            }
+        }
+        stats
+    }
+
+    /// Number of tracked cells
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Check if no cells are tracked
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Clear all change history
+    pub fn clear(&mut self) {
+        self.records.clear();
+    }
+}
+
+/// Statistics for a dynamics layer
+#[derive(Debug, Clone, Default)]
+pub struct DynamicsStats {
+    /// Total number of tracked cells
+    pub total_cells: usize,
+    /// Cells without enough observations
+    pub unknown_count: usize,
+    /// Cells classified as static
+    pub static_count: usize,
+    /// Cells classified as semi-static
+    pub semi_static_count: usize,
+    /// Cells classified as dynamic
+    pub dynamic_count: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Result;
+    use crate::layers::Layer;
+
+    #[test]
+    fn test_classification_thresholds() -> Result<()> {
+        let mut layer = DynamicsLayer::new();
+        let wall = Index64::new(0, 0, 5, 0, 0, 0)?;
+        let door = Index64::new(0, 0, 5, 2, 2, 2)?;
+        let person = Index64::new(0, 0, 5, 4, 4, 4)?;
+
+        for t in 0..21 {
+            let t_f = t as f64;
+            layer.observe(wall, true, t_f);
+            // Door opens once in 20 transitions
+            layer.observe(door, t < 10, t_f);
+            layer.observe(person, t % 2 == 0, t_f);
+        }
+
+        assert_eq!(layer.classify(wall), DynamicsClass::Static);
+        assert_eq!(layer.classify(door), DynamicsClass::SemiStatic);
+        assert_eq!(layer.classify(person), DynamicsClass::Dynamic);
+        assert_eq!(layer.flip_count(person), 20);
+        assert_eq!(layer.flip_ratio(person), Some(1.0));
+        assert_eq!(layer.flip_rate(person), Some(1.0));
+
+        let stats = layer.stats();
+        assert_eq!(stats.static_count, 1);
+        assert_eq!(stats.semi_static_count, 1);
+        assert_eq!(stats.dynamic_count, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_until_enough_observations() -> Result<()> {
+        let mut layer = DynamicsLayer::new();
+        let idx = Index64::new(0, 0, 5, 0, 0, 0)?;
+
+        layer.observe(idx, true, 0.0);
+        layer.observe(idx, false, 1.0);
+        assert_eq!(layer.classify(idx), DynamicsClass::Unknown);
+        assert_eq!(layer.cells_with_class(DynamicsClass::Unknown), vec![idx]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_out_of_order_observations_are_ignored() -> Result<()> {
+        let mut layer = DynamicsLayer::new();
+        let idx = Index64::new(0, 0, 5, 0, 0, 0)?;
+
+        assert!(layer.observe(idx, true, 0.0));
+        assert!(layer.observe(idx, true, 2.0));
+        // A late sample from between the two would count two false flips
+        assert!(!layer.observe(idx, false, 1.0));
+        assert!(layer.observe(idx, true, 3.0));
+        assert_eq!(layer.flip_count(idx), 0);
+        assert_eq!(layer.observation_count(idx), 3);
+        assert_eq!(layer.flip_rate(idx), Some(0.0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_observe_temporal_history() -> Result<()> {
+        use crate::clock::SimClock;
+        use std::sync::Arc;
+
+        let clock = SimClock::new(0.0);
+        let mut occupancy = TemporalOccupancyLayer::new().with_clock(Arc::new(clock.clone()));
+        let mut layer = DynamicsLayer::new();
+        let wall = Index64::new(0, 0, 5, 10, 10, 10)?;
+        let door = Index64::new(0, 0, 5, 12, 10, 10)?;
+
+        for t in 0..20 {
+            for _ in 0..2 {
+                occupancy.update_occupancy(wall, true, 0.99);
+                // The door opens once, halfway through
+                occupancy.update_occupancy(door, t < 10, 0.99);
+            }
+            assert_eq!(layer.observe_temporal(&occupancy), 2);
+            // The same history is not counted twice
+            assert_eq!(layer.observe_temporal(&occupancy), 0);
+            clock.advance(1.0);
+        }
+
+        assert_eq!(layer.observation_count(door), 20);
+        assert_eq!(layer.flip_count(door), 1);
+        assert_eq!(layer.classify(wall), DynamicsClass::Static);
+        assert_eq!(layer.classify(door), DynamicsClass::SemiStatic);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_observe_occupancy_snapshots() -> Result<()> {
+        let mut occupancy = OccupancyLayer::new();
+        let mut layer = DynamicsLayer::new();
+        let idx = Index64::new(0, 0, 5, 10, 10, 10)?;
+
+        for t in 0..6 {
+            occupancy.clear();
+            occupancy.update_occupancy(idx, t % 2 == 0, 0.95);
+            layer.observe_occupancy(&occupancy, t as f64);
+        }
+
+        assert_eq!(layer.observation_count(idx), 6);
+        assert_eq!(layer.classify(idx), DynamicsClass::Dynamic);
+
+        Ok(())
+    }
+}
@@ -14,15 +14,49 @@
 //! - **Better isotropy**: 14 neighbors vs 6/26 cubic → more accurate distances
 //! - **Natural edge lengths**: BCC has 2 edge types (√3 and 2) vs cubic's 3 (1, √2, √3)
 //! - **Fewer distance artifacts**: More uniform propagation in all directions
+//!
+//! Results are bit-reproducible across platforms; see
+//! [the layers module](super#reproducibility).
+//!
+//! ## From occupancy
+//!
+//! [`ESDFLayer::compute_from_occupancy`] builds an unsigned field by a
+//! brushfire from occupied voxels: every voxel inherits the nearest
+//! obstacle of the neighbor that reached it and stores the Euclidean
+//! distance to that obstacle. [`ESDFLayer::update_from_occupancy`] then
+//! follows the occupancy layer's changes: new obstacles start a lowering
+//! wavefront, and removed ones clear the voxels they were nearest to before
+//! the surrounding field propagates back in (Lau et al., "Efficient
+//! grid-based spatial representations for robot navigation in dynamic
+//! environments", 2013).
+//!
+//! ## Accuracy
+//!
+//! Both paths are approximations and never report less than the exact
+//! distance. Fast marching sums lattice edge lengths, so its distances run
+//! up to about a quarter over the straight-line distance. The brushfire
+//! stores exact distances to the obstacle it hands on, which in thin
+//! Voronoi regions may not be the nearest one, overestimating by a fraction
+//! of a voxel.
 
-use super::{Layer, LayerType};
-use crate::error::Result;
+use super::soa::SoaStore;
+use super::state::{decode_packed, decode_voxels, encode_packed, encode_voxels, is_packed};
+use super::{
+    DirtyCursor, DirtyTracker, Layer, LayerConfig, LayerType, OccupancyLayer, OccupancyState,
+    StorageLayout, ValueQuantization,
+};
+use crate::error::{Error, Result};
 use crate::neighbors::neighbors_index64;
 use crate::Index64;
 use ordered_float::OrderedFloat;
 use std::cmp::Reverse;
 use std::collections::{BinaryHeap, HashMap, HashSet};
 
+/// Schema version of [`LayerConfig`] records written by this layer
+///
+/// Version 2 added `quantization` and `quantization_scale`.
+const CONFIG_VERSION: u16 = 2;
+
 /// Voxel data in ESDF layer
 #[derive(Debug, Clone, Copy)]
 struct ESDFVoxel {
@@ -41,13 +75,119 @@ impl Default for ESDFVoxel {
     }
 }
 
+/// Encoding of the fixed flag column in SoA storage: one byte, 0 or 1
+const FLAG_ENCODING: ValueQuantization = ValueQuantization::I8 { scale: 1.0 };
+
+/// Voxel storage in one of the [`StorageLayout`]s
+enum Voxels {
+    Map(HashMap<Index64, ESDFVoxel>),
+    /// Distances in the value column, fixed flags in the weight column
+    Soa(SoaStore),
+}
+
+impl Voxels {
+    /// Empty storage; the SoA layout holds distances as codes of
+    /// `distances`
+    fn new(layout: StorageLayout, distances: ValueQuantization) -> Self {
+        match layout {
+            StorageLayout::Map => Self::Map(HashMap::new()),
+            StorageLayout::Soa => Self::Soa(SoaStore::new(distances, FLAG_ENCODING)),
+        }
+    }
+
+    fn layout(&self) -> StorageLayout {
+        match self {
+            Self::Map(_) => StorageLayout::Map,
+            Self::Soa(_) => StorageLayout::Soa,
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Self::Map(map) => map.len(),
+            Self::Soa(store) => store.len(),
+        }
+    }
+
+    fn get(&self, idx: Index64) -> Option<ESDFVoxel> {
+        match self {
+            Self::Map(map) => map.get(&idx).copied(),
+            Self::Soa(store) => store.get(idx).map(|(distance, fixed)| ESDFVoxel {
+                distance,
+                fixed: fixed != 0.0,
+            }),
+        }
+    }
+
+    fn contains(&self, idx: Index64) -> bool {
+        self.get(idx).is_some()
+    }
+
+    fn insert(&mut self, idx: Index64, voxel: ESDFVoxel) {
+        match self {
+            Self::Map(map) => {
+                map.insert(idx, voxel);
+            }
+            Self::Soa(store) => store.insert(idx, voxel.distance, voxel.fixed as u8 as f32),
+        }
+    }
+
+    fn remove(&mut self, idx: Index64) {
+        match self {
+            Self::Map(map) => {
+                map.remove(&idx);
+            }
+            Self::Soa(store) => {
+                store.remove(idx);
+            }
+        }
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (Index64, ESDFVoxel)> + '_> {
+        match self {
+            Self::Map(map) => Box::new(map.iter().map(|(&idx, &voxel)| (idx, voxel))),
+            Self::Soa(store) => Box::new(store.iter().map(|(idx, distance, fixed)| {
+                let voxel = ESDFVoxel {
+                    distance,
+                    fixed: fixed != 0.0,
+                };
+                (idx, voxel)
+            })),
+        }
+    }
+
+    fn indices(&self) -> impl Iterator<Item = Index64> + '_ {
+        self.iter().map(|(idx, _)| idx)
+    }
+
+    fn clear(&mut self) {
+        match self {
+            Self::Map(map) => map.clear(),
+            Self::Soa(store) => store.clear(),
+        }
+    }
+
+    /// Snap every distance to `distances`; SoA blocks switch to holding
+    /// their codes
+    fn quantize(&mut self, distances: ValueQuantization) {
+        match self {
+            Self::Map(map) => {
+                for voxel in map.values_mut() {
+                    voxel.distance = distances.quantize(voxel.distance);
+                }
+            }
+            Self::Soa(store) => store.requantize(distances, FLAG_ENCODING),
+        }
+    }
+}
+
 /// Euclidean Signed Distance Field layer
 ///
 /// Stores full signed distance to nearest surface.
 /// Computed from TSDF using Fast Marching Method on BCC lattice.
 pub struct ESDFLayer {
     /// Voxel data indexed by Index64
-    voxels: HashMap<Index64, ESDFVoxel>,
+    voxels: Voxels,
 
     /// Voxel size (meters per voxel)
     voxel_size: f32,
@@ -60,8 +200,31 @@ pub struct ESDFLayer {
     /// - Diagonal edges (8 neighbors): √3
     /// - Axial edges (6 neighbors): 2
     edge_lengths: EdgeLengths,
+
+    /// Encoding distances are rounded to
+    quantization: ValueQuantization,
+
+    /// Chunks changed since consumers last looked
+    dirty: DirtyTracker,
+
+    /// Nearest obstacles, for fields built from occupancy
+    sites: Option<ObstacleSites>,
 }
 
+/// Nearest-obstacle bookkeeping of a field built from occupancy
+#[derive(Debug, Clone, Default)]
+struct ObstacleSites {
+    /// Nearest obstacle of every voxel in the field
+    nearest: HashMap<Index64, Index64>,
+    /// Voxels of the field by nearest obstacle; keys are the obstacles
+    members: HashMap<Index64, HashSet<Index64>>,
+    /// Position in the occupancy layer's changes
+    cursor: DirtyCursor,
+}
+
+/// Min-heap of voxels by distance for wavefront propagation
+type Wavefront = BinaryHeap<Reverse<(OrderedFloat<f32>, Index64)>>;
+
 /// Precomputed edge lengths for BCC lattice
 #[derive(Debug, Clone, Copy)]
 struct EdgeLengths {
@@ -95,11 +258,80 @@ impl ESDFLayer {
     /// ```
     pub fn new(voxel_size: f32, max_distance: f32) -> Self {
         Self {
-            voxels: HashMap::new(),
+            voxels: Voxels::new(StorageLayout::Map, ValueQuantization::F32),
             voxel_size,
             max_distance,
             edge_lengths: EdgeLengths::default(),
+            quantization: ValueQuantization::F32,
+            dirty: DirtyTracker::default(),
+            sites: None,
+        }
+    }
+
+    /// Round distances to a reduced precision
+    ///
+    /// Distances are rounded to the encoding as the field is computed, so
+    /// propagation works from stored values, and checkpoints store the
+    /// narrow codes. In the [`StorageLayout::Soa`] layout the blocks hold
+    /// the codes too; the map layout keeps rounded `f32` values. `I8` should
+    /// cover the maximum distance, e.g.
+    /// `ValueQuantization::i8_for_range(max_distance)`. Existing voxels are
+    /// rounded too.
+    pub fn with_quantization(mut self, quantization: ValueQuantization) -> Self {
+        self.set_quantization(quantization);
+        self
+    }
+
+    /// Encoding distances are rounded to
+    pub fn quantization(&self) -> ValueQuantization {
+        self.quantization
+    }
+
+    /// Store voxels in `layout`, moving existing ones
+    ///
+    /// As for [`TSDFLayer::with_layout`](super::TSDFLayer::with_layout),
+    /// the layout only affects memory use and speed. [`StorageLayout::Soa`]
+    /// suits the dense fields around obstacles, and holds quantized
+    /// distances as their codes.
+    ///
+    /// ```
+    /// use octaindex3d::layers::{ESDFLayer, StorageLayout, ValueQuantization};
+    ///
+    /// let esdf = ESDFLayer::new(0.1, 2.0)
+    ///     .with_layout(StorageLayout::Soa)
+    ///     .with_quantization(ValueQuantization::F16);
+    /// assert_eq!(esdf.layout(), StorageLayout::Soa);
+    /// ```
+    pub fn with_layout(mut self, layout: StorageLayout) -> Self {
+        if layout != self.voxels.layout() {
+            let mut voxels = Voxels::new(layout, self.quantization);
+            for (idx, voxel) in self.voxels.iter() {
+                voxels.insert(idx, voxel);
+            }
+            self.voxels = voxels;
         }
+        self
+    }
+
+    /// Storage layout of the voxels
+    pub fn layout(&self) -> StorageLayout {
+        self.voxels.layout()
+    }
+
+    fn set_quantization(&mut self, quantization: ValueQuantization) {
+        self.quantization = quantization;
+        self.voxels.quantize(quantization);
+    }
+
+    /// Chunks changed since the last `take_dirty`
+    pub fn dirty(&self) -> &DirtyTracker {
+        &self.dirty
+    }
+
+    /// Mutable access to the change tracker, e.g. to replace it with one
+    /// using another chunk LOD
+    pub fn dirty_mut(&mut self) -> &mut DirtyTracker {
+        &mut self.dirty
     }
 
     /// Get voxel size
@@ -114,7 +346,7 @@ impl ESDFLayer {
 
     /// Get distance value for a voxel
     pub fn get_distance(&self, idx: Index64) -> Option<f32> {
-        self.voxels.get(&idx).map(|v| v.distance)
+        self.voxels.get(idx).map(|v| v.distance)
     }
 
     /// Compute ESDF from TSDF using Fast Marching Method
@@ -127,6 +359,9 @@ impl ESDFLayer {
     /// 3. Propagate using priority queue (Dijkstra-like)
     /// 4. Use BCC 14-neighbor connectivity for better isotropy
     ///
+    /// Distances are path lengths along lattice edges, up to about 25% over
+    /// the Euclidean distance (see [Accuracy](self#accuracy)).
+    ///
     /// # Arguments
     /// * `tsdf` - Source TSDF layer
     /// * `surface_threshold` - Distance threshold for surface detection (meters)
@@ -136,9 +371,10 @@ impl ESDFLayer {
         surface_threshold: f32,
     ) -> Result<()> {
         // Clear existing data
-        self.voxels.clear();
+        self.clear();
 
-        // Get surface voxels from TSDF (zero-crossings)
+        // Get surface voxels from TSDF (zero-crossings), in index order so
+        // the wavefront does not depend on hash iteration order
         let surface_voxels = tsdf.get_surface_voxels(surface_threshold);
 
         if surface_voxels.is_empty() {
@@ -152,7 +388,9 @@ impl ESDFLayer {
 
         // Initialize surface voxels
         for &idx in &surface_voxels {
-            let dist = tsdf.get_distance(idx).unwrap_or(0.0);
+            let dist = self
+                .quantization
+                .quantize(tsdf.get_distance(idx).unwrap_or(0.0));
 
             self.voxels.insert(
                 idx,
@@ -165,7 +403,7 @@ impl ESDFLayer {
             // Add neighbors to open list
             let neighbors = neighbors_index64(idx);
             for neighbor_idx in neighbors {
-                if !self.voxels.contains_key(&neighbor_idx) && pending.insert(neighbor_idx) {
+                if !self.voxels.contains(neighbor_idx) && pending.insert(neighbor_idx) {
                     open.push(Reverse((OrderedFloat(dist.abs()), neighbor_idx)));
                 }
             }
@@ -178,7 +416,7 @@ impl ESDFLayer {
             // Skip if already processed
             if self
                 .voxels
-                .get(&current_idx)
+                .get(current_idx)
                 .map(|v| v.fixed)
                 .unwrap_or(false)
             {
@@ -189,7 +427,9 @@ impl ESDFLayer {
             let new_distance = self.compute_distance_from_neighbors(current_idx);
 
             // Clamp to max distance
-            let clamped_distance = new_distance.clamp(-self.max_distance, self.max_distance);
+            let clamped_distance = self
+                .quantization
+                .quantize(new_distance.clamp(-self.max_distance, self.max_distance));
 
             // Mark as fixed
             self.voxels.insert(
@@ -205,7 +445,7 @@ impl ESDFLayer {
             for neighbor_idx in neighbors {
                 if !self
                     .voxels
-                    .get(&neighbor_idx)
+                    .get(neighbor_idx)
                     .map(|v| v.fixed)
                     .unwrap_or(false)
                 {
@@ -219,9 +459,203 @@ impl ESDFLayer {
             }
         }
 
+        self.dirty.mark_all(self.voxels.indices());
         Ok(())
     }
 
+    /// Compute an unsigned distance field to the occupied voxels of
+    /// `occupancy`
+    ///
+    /// Occupied voxels hold distance 0 and free or unknown space holds the
+    /// Euclidean distance to the nearest of them, up to the maximum
+    /// distance, possibly overestimated where obstacles nearly tie (see
+    /// [Accuracy](self#accuracy)). Later changes of the occupancy layer are applied with
+    /// [`update_from_occupancy`](Self::update_from_occupancy).
+    ///
+    /// # Example
+    /// ```
+    /// use octaindex3d::layers::{ESDFLayer, OccupancyLayer};
+    /// use octaindex3d::Index64;
+    ///
+    /// # fn main() -> octaindex3d::Result<()> {
+    /// let mut occupancy = OccupancyLayer::new();
+    /// occupancy.update_occupancy(Index64::new(0, 0, 5, 20, 20, 20)?, true, 0.9);
+    ///
+    /// let mut esdf = ESDFLayer::new(0.1, 1.0);
+    /// esdf.compute_from_occupancy(&occupancy)?;
+    /// let distance = esdf.get_distance(Index64::new(0, 0, 5, 24, 20, 20)?).unwrap();
+    /// assert!((distance - 0.4).abs() < 1e-6);
+    ///
+    /// // A second obstacle only updates the voxels now closer to it
+    /// occupancy.update_occupancy(Index64::new(0, 0, 5, 26, 20, 20)?, true, 0.9);
+    /// assert_eq!(esdf.update_from_occupancy(&occupancy)?, 1);
+    /// let distance = esdf.get_distance(Index64::new(0, 0, 5, 24, 20, 20)?).unwrap();
+    /// assert!((distance - 0.2).abs() < 1e-6);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn compute_from_occupancy(&mut self, occupancy: &OccupancyLayer) -> Result<()> {
+        self.clear();
+        self.sites = Some(ObstacleSites {
+            cursor: occupancy.dirty().cursor(),
+            ..ObstacleSites::default()
+        });
+
+        let mut obstacles = occupancy.get_occupied_voxels();
+        obstacles.sort_unstable();
+        self.apply_obstacle_changes(&obstacles, &[]);
+        Ok(())
+    }
+
+    /// Bring a field built by [`compute_from_occupancy`](Self::compute_from_occupancy)
+    /// up to date with the changes made to `occupancy` since
+    /// (or since the previous update)
+    ///
+    /// Only voxels of changed chunks are compared, and only the part of the
+    /// field whose nearest obstacle appeared or disappeared is recomputed.
+    /// Fields that were not built from occupancy, or were replaced since
+    /// by loading or merging state, are computed from scratch.
+    ///
+    /// Returns the number of voxels that became or stopped being obstacles.
+    pub fn update_from_occupancy(&mut self, occupancy: &OccupancyLayer) -> Result<usize> {
+        let Some(sites) = &mut self.sites else {
+            self.compute_from_occupancy(occupancy)?;
+            return Ok(self.sites.as_ref().map_or(0, |s| s.members.len()));
+        };
+
+        let tracker = occupancy.dirty();
+        let chunks: HashSet<Index64> = tracker
+            .take_dirty_since(&mut sites.cursor)
+            .into_iter()
+            .collect();
+        if chunks.is_empty() {
+            return Ok(0);
+        }
+        let changed = |idx: &Index64| chunks.contains(&tracker.chunk_of(*idx));
+
+        let mut inserted: Vec<Index64> = occupancy
+            .get_occupied_voxels()
+            .into_iter()
+            .filter(|idx| changed(idx) && !sites.members.contains_key(idx))
+            .collect();
+        let mut removed: Vec<Index64> = sites
+            .members
+            .keys()
+            .copied()
+            .filter(|idx| changed(idx) && occupancy.get_state(*idx) != OccupancyState::Occupied)
+            .collect();
+        inserted.sort_unstable();
+        removed.sort_unstable();
+
+        self.apply_obstacle_changes(&inserted, &removed);
+        Ok(inserted.len() + removed.len())
+    }
+
+    /// Raise the field around `removed` obstacles, then lower it around
+    /// `inserted` ones
+    fn apply_obstacle_changes(&mut self, inserted: &[Index64], removed: &[Index64]) {
+        let Some(sites) = &mut self.sites else {
+            return;
+        };
+        let mut open = Wavefront::new();
+
+        // Clear every voxel whose nearest obstacle disappeared
+        let mut cleared = HashSet::new();
+        for obstacle in removed {
+            for idx in sites.members.remove(obstacle).unwrap_or_default() {
+                sites.nearest.remove(&idx);
+                self.voxels.remove(idx);
+                self.dirty.mark(idx);
+                cleared.insert(idx);
+            }
+        }
+        // The intact field around the cleared region propagates back in
+        for &idx in &cleared {
+            for neighbor in neighbors_index64(idx) {
+                if let Some(voxel) = self.voxels.get(neighbor) {
+                    if sites.nearest.contains_key(&neighbor) {
+                        open.push(Reverse((OrderedFloat(voxel.distance), neighbor)));
+                    }
+                }
+            }
+        }
+
+        for &obstacle in inserted {
+            self.voxels.insert(
+                obstacle,
+                ESDFVoxel {
+                    distance: 0.0,
+                    fixed: true,
+                },
+            );
+            self.dirty.mark(obstacle);
+            if let Some(old) = sites.nearest.insert(obstacle, obstacle) {
+                if let Some(members) = sites.members.get_mut(&old) {
+                    members.remove(&obstacle);
+                }
+            }
+            sites.members.entry(obstacle).or_default().insert(obstacle);
+            open.push(Reverse((OrderedFloat(0.0), obstacle)));
+        }
+
+        self.lower(open);
+    }
+
+    /// Brushfire: pass each voxel's nearest obstacle on to neighbors it is
+    /// closer to than their current one
+    fn lower(&mut self, mut open: Wavefront) {
+        let Some(sites) = &mut self.sites else {
+            return;
+        };
+        while let Some(Reverse((OrderedFloat(distance), idx))) = open.pop() {
+            // Skip entries superseded by a shorter distance
+            if self.voxels.get(idx).map(|v| v.distance) != Some(distance) {
+                continue;
+            }
+            let Some(&obstacle) = sites.nearest.get(&idx) else {
+                continue;
+            };
+            let (ox, oy, oz) = obstacle.decode_coords();
+
+            for neighbor in neighbors_index64(idx) {
+                let (x, y, z) = neighbor.decode_coords();
+                let (dx, dy, dz) = (
+                    x as f32 - ox as f32,
+                    y as f32 - oy as f32,
+                    z as f32 - oz as f32,
+                );
+                let candidate = (dx * dx + dy * dy + dz * dz).sqrt() * self.voxel_size;
+                if candidate > self.max_distance {
+                    continue;
+                }
+                let candidate = self.quantization.quantize(candidate);
+                if self
+                    .voxels
+                    .get(neighbor)
+                    .is_some_and(|v| v.distance <= candidate)
+                {
+                    continue;
+                }
+
+                self.voxels.insert(
+                    neighbor,
+                    ESDFVoxel {
+                        distance: candidate,
+                        fixed: true,
+                    },
+                );
+                self.dirty.mark(neighbor);
+                if let Some(old) = sites.nearest.insert(neighbor, obstacle) {
+                    if let Some(members) = sites.members.get_mut(&old) {
+                        members.remove(&neighbor);
+                    }
+                }
+                sites.members.entry(obstacle).or_default().insert(neighbor);
+                open.push(Reverse((OrderedFloat(candidate), neighbor)));
+            }
+        }
+    }
+
     /// Compute distance for a voxel from its neighbors
     ///
     /// Uses minimum distance + edge length across all 14 BCC neighbors
@@ -230,7 +664,7 @@ impl ESDFLayer {
         let mut min_distance = f32::MAX;
 
         for (i, neighbor_idx) in neighbors.iter().enumerate() {
-            if let Some(neighbor) = self.voxels.get(neighbor_idx) {
+            if let Some(neighbor) = self.voxels.get(*neighbor_idx) {
                 if neighbor.fixed {
                     // Determine edge length based on neighbor type
                     // First 8 neighbors are diagonal (√3), next 6 are axial (2)
@@ -261,14 +695,14 @@ impl ESDFLayer {
         self.voxels
             .iter()
             .filter(|(_, v)| v.distance.abs() <= threshold)
-            .map(|(&idx, v)| (idx, v.distance))
+            .map(|(idx, v)| (idx, v.distance))
             .collect()
     }
 
     /// Check if a voxel is in free space (positive distance, above threshold)
     pub fn is_free_space(&self, idx: Index64, threshold: f32) -> bool {
         self.voxels
-            .get(&idx)
+            .get(idx)
             .map(|v| v.distance > threshold)
             .unwrap_or(false)
     }
@@ -328,7 +762,7 @@ impl ESDFLayer {
         let mut obstacle_voxels = 0;
         let mut free_voxels = 0;
 
-        for voxel in self.voxels.values() {
+        for (_, voxel) in self.voxels.iter() {
             min_distance = min_distance.min(voxel.distance);
             max_distance = max_distance.max(voxel.distance);
 
@@ -355,6 +789,61 @@ impl ESDFLayer {
             },
         }
     }
+
+    /// State encoding of `voxels`, in the layout of [`Layer::encode_state`]
+    fn encode_records(&self, voxels: impl Iterator<Item = (Index64, ESDFVoxel)>) -> Vec<u8> {
+        if self.quantization == ValueQuantization::F32 {
+            let records = voxels
+                .map(|(idx, v)| (idx, [v.distance.to_bits(), v.fixed as u32]))
+                .collect();
+            return encode_voxels(records);
+        }
+        // Distance code, then one byte for the fixed flag
+        let bits = 8 * self.quantization.value_bytes();
+        let records = voxels
+            .map(|(idx, v)| {
+                let data = self.quantization.encode(v.distance) | ((v.fixed as u32) << bits);
+                (idx, data)
+            })
+            .collect();
+        encode_packed(self.quantization.value_bytes() + 1, records)
+    }
+
+    /// Voxels of a state encoding, as this layer's storage holds them
+    fn decode_records(&self, bytes: &[u8]) -> Result<Vec<(Index64, ESDFVoxel)>> {
+        if is_packed(bytes) {
+            let (width, records) = decode_packed(bytes)?;
+            let value_bytes = self.quantization.value_bytes();
+            if self.quantization == ValueQuantization::F32 || width != value_bytes + 1 {
+                return Err(Error::InvalidFormat(format!(
+                    "packed ESDF state of width {} does not match {:?} storage",
+                    width, self.quantization
+                )));
+            }
+            let bits = 8 * value_bytes;
+            return Ok(records
+                .into_iter()
+                .map(|(idx, data)| {
+                    let voxel = ESDFVoxel {
+                        distance: self.quantization.decode(data & ((1 << bits) - 1)),
+                        fixed: data >> bits != 0,
+                    };
+                    (idx, voxel)
+                })
+                .collect());
+        }
+        // Full precision state loaded into a quantized layer is rounded
+        Ok(decode_voxels(bytes)?
+            .into_iter()
+            .map(|(idx, [distance, fixed])| {
+                let voxel = ESDFVoxel {
+                    distance: self.quantization.quantize(f32::from_bits(distance)),
+                    fixed: fixed != 0,
+                };
+                (idx, voxel)
+            })
+            .collect())
+    }
 }
 
 /// ESDF statistics
@@ -391,13 +880,86 @@ impl Layer for ESDFLayer {
         self.voxels.len()
     }
 
+    fn indices(&self) -> Box<dyn Iterator<Item = Index64> + '_> {
+        Box::new(self.voxels.indices())
+    }
+
     fn clear(&mut self) {
+        self.dirty.mark_all(self.voxels.indices());
         self.voxels.clear();
+        self.sites = None;
+    }
+
+    fn dirty_tracker(&self) -> Option<&DirtyTracker> {
+        Some(&self.dirty)
+    }
+
+    fn dirty_tracker_mut(&mut self) -> Option<&mut DirtyTracker> {
+        Some(&mut self.dirty)
+    }
+
+    fn encode_state(&self) -> Option<Vec<u8>> {
+        Some(self.encode_records(self.voxels.iter()))
+    }
+
+    fn encode_cells(&self, cells: &[Index64]) -> Option<Vec<u8>> {
+        let voxels = cells
+            .iter()
+            .filter_map(|&idx| Some((idx, self.voxels.get(idx)?)));
+        Some(self.encode_records(voxels))
+    }
+
+    fn decode_state(&mut self, bytes: &[u8]) -> Result<()> {
+        let voxels = self.decode_records(bytes)?;
+        self.clear();
+        for (idx, voxel) in voxels {
+            self.dirty.mark(idx);
+            self.voxels.insert(idx, voxel);
+        }
+        Ok(())
+    }
+
+    fn merge_state(&mut self, bytes: &[u8]) -> Result<()> {
+        // Merged voxels have no known nearest obstacle
+        self.sites = None;
+        for (idx, voxel) in self.decode_records(bytes)? {
+            self.dirty.mark(idx);
+            self.voxels.insert(idx, voxel);
+        }
+        Ok(())
+    }
+
+    fn config(&self) -> Option<LayerConfig> {
+        let (quantization, scale) = self.quantization.to_fields();
+        Some(
+            LayerConfig::new(LayerType::ESDF, CONFIG_VERSION)
+                .with("voxel_size", self.voxel_size as f64)
+                .with("max_distance", self.max_distance as f64)
+                .with("quantization", quantization)
+                .with("quantization_scale", scale),
+        )
+    }
+
+    fn apply_config(&mut self, config: &LayerConfig) -> Result<()> {
+        let (quantization, scale) = self.quantization.to_fields();
+        let quantization = ValueQuantization::from_fields(
+            config.get_or("quantization", quantization),
+            config.get_or("quantization_scale", scale),
+        )?;
+        self.voxel_size = config.get_or("voxel_size", self.voxel_size as f64) as f32;
+        self.max_distance = config.get_or("max_distance", self.max_distance as f64) as f32;
+        self.set_quantization(quantization);
+        Ok(())
     }
 
     fn memory_usage(&self) -> usize {
-        // HashMap overhead + voxel data
-        self.voxels.len() * 40
+        // HashMap overhead + voxel data, or the SoA blocks, plus the nearest
+        // obstacle and reverse entry of fields built from occupancy
+        let voxels = match &self.voxels {
+            Voxels::Map(map) => map.len() * 40,
+            Voxels::Soa(store) => store.memory_usage(),
+        };
+        voxels + self.sites.as_ref().map_or(0, |s| s.nearest.len() * 64)
     }
 }
 
@@ -439,6 +1001,178 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_esdf_bit_reproducible() -> Result<()> {
+        // Two uneven parallel walls: cells between them are reached from
+        // several surface cells with different distances
+        let mut cells = Vec::new();
+        for y in 0..6u16 {
+            for z in 0..6u16 {
+                let d = 0.05 * ((y * 7 + z * 3) % 5) as f32;
+                cells.push((Index64::new(0, 0, 8, 40, 40 + 2 * y, 40 + 2 * z)?, d));
+                cells.push((Index64::new(0, 0, 8, 52, 40 + 2 * y, 40 + 2 * z)?, -d));
+            }
+        }
+        let build = |order: &[(Index64, f32)]| -> Result<Vec<u8>> {
+            let mut tsdf = TSDFLayer::new(0.5);
+            for &(idx, d) in order {
+                tsdf.update(idx, &Measurement::depth(d, 1.0))?;
+            }
+            let mut esdf = ESDFLayer::new(0.05, 0.8);
+            esdf.compute_from_tsdf(&tsdf, 0.25)?;
+            Ok(esdf.encode_state().unwrap())
+        };
+
+        let forward = build(&cells)?;
+        cells.reverse();
+        assert_eq!(build(&cells)?, forward);
+        // Golden value, identical on every platform CI runs on
+        assert_eq!(crc32fast::hash(&forward), 2408520630);
+        Ok(())
+    }
+
+    #[test]
+    fn test_incremental_occupancy_matches_batch() -> Result<()> {
+        let cell = |x, y, z| Index64::new(0, 0, 5, x, y, z);
+        let mut occupancy = OccupancyLayer::new();
+        for y in (10..=30).step_by(2) {
+            for z in (10..=30).step_by(2) {
+                occupancy.update_occupancy(cell(10, y, z)?, true, 0.9);
+            }
+        }
+        let post = cell(20, 20, 20)?;
+        occupancy.update_occupancy(post, true, 0.9);
+
+        let mut esdf = ESDFLayer::new(0.1, 0.8);
+        esdf.compute_from_occupancy(&occupancy)?;
+        assert_eq!(esdf.get_distance(post), Some(0.0));
+        assert!((esdf.get_distance(cell(16, 20, 20)?).unwrap() - 0.4).abs() < 1e-6);
+        assert_eq!(esdf.update_from_occupancy(&occupancy)?, 0);
+
+        // The post moves away and a new obstacle appears
+        occupancy.update_occupancy(post, false, 0.9);
+        occupancy.update_occupancy(post, false, 0.9);
+        occupancy.update_occupancy(cell(25, 25, 25)?, true, 0.9);
+        assert_eq!(esdf.update_from_occupancy(&occupancy)?, 2);
+        // Beyond the wall's reach and no longer near the post
+        assert_eq!(esdf.get_distance(cell(20, 20, 20)?), None);
+        assert!((esdf.get_distance(cell(16, 20, 20)?).unwrap() - 0.6).abs() < 1e-6);
+
+        let mut batch = ESDFLayer::new(0.1, 0.8);
+        batch.compute_from_occupancy(&occupancy)?;
+        assert_eq!(esdf.voxel_count(), batch.voxel_count());
+        for idx in batch.indices() {
+            assert_eq!(esdf.get_distance(idx), batch.get_distance(idx), "{idx:?}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_quantized_soa_storage_shrinks() -> Result<()> {
+        let cell = |x, y, z| Index64::new(0, 0, 5, x, y, z);
+        let mut occupancy = OccupancyLayer::new();
+        for y in (10..=30).step_by(2) {
+            for z in (10..=30).step_by(2) {
+                occupancy.update_occupancy(cell(10, y, z)?, true, 0.9);
+            }
+        }
+        let post = cell(20, 20, 20)?;
+        occupancy.update_occupancy(post, true, 0.9);
+
+        let f16 = ValueQuantization::F16;
+        let mut layers = [
+            ESDFLayer::new(0.1, 0.8).with_quantization(f16),
+            ESDFLayer::new(0.1, 0.8)
+                .with_layout(StorageLayout::Soa)
+                .with_quantization(f16),
+            ESDFLayer::new(0.1, 0.8).with_layout(StorageLayout::Soa),
+        ];
+        for esdf in &mut layers {
+            esdf.compute_from_occupancy(&occupancy)?;
+        }
+        let [map, soa, full] = &mut layers;
+        assert_eq!(soa.encode_state(), map.encode_state());
+        // Without the obstacle bookkeeping, the blocks use two distance
+        // bytes and a flag byte per cell instead of five
+        let stored = |esdf: &ESDFLayer| -> Result<usize> {
+            let mut copy = ESDFLayer::new(0.1, 0.8)
+                .with_layout(StorageLayout::Soa)
+                .with_quantization(esdf.quantization());
+            copy.decode_state(&esdf.encode_state().unwrap())?;
+            Ok(copy.memory_usage())
+        };
+        assert!(stored(soa)? * 100 / stored(full)? <= 62);
+
+        // Removing an obstacle clears cells from the blocks
+        occupancy.update_occupancy(post, false, 0.9);
+        occupancy.update_occupancy(post, false, 0.9);
+        map.update_from_occupancy(&occupancy)?;
+        soa.update_from_occupancy(&occupancy)?;
+        assert_eq!(soa.get_distance(post), None);
+        assert_eq!(soa.encode_state(), map.encode_state());
+        Ok(())
+    }
+
+    #[test]
+    fn test_esdf_error_against_exact_distances() -> Result<()> {
+        use rand::rngs::StdRng;
+        use rand::{RngExt, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut obstacles = Vec::new();
+        while obstacles.len() < 60 {
+            let (x, y, z) = (
+                rng.random_range(20..60u16),
+                rng.random_range(20..60u16),
+                rng.random_range(20..60u16),
+            );
+            if crate::layers::is_valid_bcc(x as i32, y as i32, z as i32) {
+                obstacles.push(Index64::new(0, 0, 5, x, y, z)?);
+            }
+        }
+        let exact = |idx: Index64| {
+            let (x, y, z) = idx.decode_coords();
+            let square = |a: u16, b: u16| (a as f32 - b as f32).powi(2);
+            obstacles
+                .iter()
+                .map(|o| {
+                    let (ox, oy, oz) = o.decode_coords();
+                    (square(x, ox) + square(y, oy) + square(z, oz)).sqrt() * 0.1
+                })
+                .fold(f32::MAX, f32::min)
+        };
+
+        let mut occupancy = OccupancyLayer::new();
+        let mut tsdf = TSDFLayer::new(0.5);
+        for &o in &obstacles {
+            occupancy.update_occupancy(o, true, 0.9);
+            tsdf.update(o, &Measurement::depth(0.0, 1.0))?;
+        }
+        let mut brushfire = ESDFLayer::new(0.1, 1.2);
+        brushfire.compute_from_occupancy(&occupancy)?;
+        let mut marching = ESDFLayer::new(0.1, 1.2);
+        marching.compute_from_tsdf(&tsdf, 0.05)?;
+
+        // Stored distances lie in [exact, exact * ratio + slack], and every
+        // cell whose bound stays within the maximum distance is stored
+        for (esdf, ratio, slack) in [(&brushfire, 1.0, 0.1), (&marching, 1.25, 0.0)] {
+            for idx in esdf.indices() {
+                let (distance, exact) = (esdf.get_distance(idx).unwrap(), exact(idx));
+                assert!(distance >= exact - 1e-4, "{idx:?}: {distance} < {exact}");
+                assert!(
+                    distance <= exact * ratio + slack + 1e-4,
+                    "{idx:?}: {distance}"
+                );
+            }
+            for idx in Index64::iter_lod_bounded(0, 0, 5, (8, 8, 8), (72, 72, 72))? {
+                if exact(idx) * ratio + slack <= 1.2 {
+                    assert!(esdf.get_distance(idx).is_some(), "{idx:?} missing");
+                }
+            }
+        }
+        Ok(())
+    }
+
     #[test]
     fn test_edge_lengths() {
         let edge_lengths = EdgeLengths::default();
@@ -4,13 +4,20 @@
 //! - Frontier detection (boundaries between known and unknown)
 //! - Information gain estimation from viewpoints
 //! - Viewpoint candidate generation
+//! - Multi-robot frontier assignment
 //!
 //! These primitives enable users to implement exploration strategies
 //! like Next-Best-View (NBV) planning without prescribing a specific policy.
-
-use super::occupancy::OccupancyLayer;
-use crate::error::Result;
-use crate::Index64;
+//!
+//! Scoring thousands of candidate viewpoints is dominated by ray casting.
+//! [`OccupancyLayer::score_viewpoints`] copies the relevant region into a
+//! dense [`OccupancySnapshot`] and scores the whole batch at once, on the GPU
+//! when a backend is available and on the CPU otherwise.
+
+use super::bcc_utils::snap_to_nearest_bcc;
+use super::occupancy::{OccupancyLayer, OccupancyState};
+use crate::error::{Error, Result};
+use crate::{FrameId, Index64};
 use std::collections::{HashSet, VecDeque};
 
 /// A frontier: boundary between explored and unexplored space
@@ -113,6 +120,15 @@ pub struct InformationGainConfig {
     pub ray_resolution: f32,
     /// Weight for unknown voxels
     pub unknown_weight: f32,
+    /// Voxel size of the occupancy layer (meters)
+    pub voxel_size: f32,
+    /// Frame of the voxels the sensor sees
+    pub frame: FrameId,
+    /// Scale tier of the voxels the sensor sees
+    pub tier: u8,
+    /// LOD of the voxels the sensor sees; voxels of other frames, tiers
+    /// or LODs read as unknown
+    pub lod: u8,
 }
 
 impl Default for InformationGainConfig {
@@ -122,8 +138,449 @@ impl Default for InformationGainConfig {
             sensor_fov: std::f32::consts::PI / 3.0, // 60° FOV
             ray_resolution: 5.0,                    // 5° between rays
             unknown_weight: 1.0,                    // 1 bit per unknown voxel
+            voxel_size: 0.1,                        // 10cm voxels
+            frame: 0,
+            tier: 0,
+            lod: 5,
+        }
+    }
+}
+
+/// Snapshot cell code for unknown space
+pub(crate) const SNAPSHOT_UNKNOWN: u8 = 0;
+/// Snapshot cell code for free space
+pub(crate) const SNAPSHOT_FREE: u8 = 1;
+/// Snapshot cell code for occupied space
+pub(crate) const SNAPSHOT_OCCUPIED: u8 = 2;
+
+/// Largest snapshot (in cells) that will be allocated
+const MAX_SNAPSHOT_CELLS: usize = 1 << 28;
+
+/// Dense copy of an occupancy region for batch ray casting
+///
+/// Stores one byte per voxel coordinate (0 = unknown, 1 = free,
+/// 2 = occupied) in x-fastest order. Coordinates outside the box read as
+/// unknown. This is the layout uploaded to GPU ray casting kernels.
+#[derive(Debug, Clone)]
+pub struct OccupancySnapshot {
+    min: (i32, i32, i32),
+    dims: (u32, u32, u32),
+    cells: Vec<u8>,
+}
+
+impl OccupancySnapshot {
+    /// Copy the inclusive voxel box `[min, max]` of an occupancy layer,
+    /// taking only voxels of the config's frame, tier and LOD
+    pub fn from_layer(
+        layer: &OccupancyLayer,
+        config: &InformationGainConfig,
+        min: (i32, i32, i32),
+        max: (i32, i32, i32),
+    ) -> Result<Self> {
+        if max.0 < min.0 || max.1 < min.1 || max.2 < min.2 {
+            return Err(Error::OutOfRange(
+                "Snapshot max corner must not be below min corner".to_string(),
+            ));
+        }
+
+        let dims = (
+            (max.0 as i64 - min.0 as i64 + 1) as u32,
+            (max.1 as i64 - min.1 as i64 + 1) as u32,
+            (max.2 as i64 - min.2 as i64 + 1) as u32,
+        );
+        let total = dims.0 as usize * dims.1 as usize * dims.2 as usize;
+        if total > MAX_SNAPSHOT_CELLS {
+            return Err(Error::OutOfRange(format!(
+                "Snapshot of {} cells exceeds limit of {}",
+                total, MAX_SNAPSHOT_CELLS
+            )));
+        }
+
+        let mut snapshot = Self {
+            min,
+            dims,
+            cells: vec![SNAPSHOT_UNKNOWN; total],
+        };
+
+        for (voxels, code) in [
+            (layer.get_free_voxels(), SNAPSHOT_FREE),
+            (layer.get_occupied_voxels(), SNAPSHOT_OCCUPIED),
+        ] {
+            for idx in voxels {
+                if (idx.frame_id(), idx.scale_tier(), idx.lod())
+                    != (config.frame, config.tier, config.lod)
+                {
+                    continue;
+                }
+                let (x, y, z) = idx.decode_coords();
+                if let Some(i) = snapshot.linear_index(x as i32, y as i32, z as i32) {
+                    snapshot.cells[i] = code;
+                }
+            }
+        }
+
+        Ok(snapshot)
+    }
+
+    /// Minimum voxel corner of the snapshot
+    pub fn min(&self) -> (i32, i32, i32) {
+        self.min
+    }
+
+    /// Extent of the snapshot in voxels along each axis
+    pub fn dims(&self) -> (u32, u32, u32) {
+        self.dims
+    }
+
+    /// Raw cell codes in x-fastest order
+    pub fn cells(&self) -> &[u8] {
+        &self.cells
+    }
+
+    /// Occupancy state at a voxel coordinate
+    pub fn state(&self, x: i32, y: i32, z: i32) -> OccupancyState {
+        match self.linear_index(x, y, z).map(|i| self.cells[i]) {
+            Some(SNAPSHOT_FREE) => OccupancyState::Free,
+            Some(SNAPSHOT_OCCUPIED) => OccupancyState::Occupied,
+            _ => OccupancyState::Unknown,
+        }
+    }
+
+    /// Information gain of one viewpoint given its sensor ray directions
+    ///
+    /// This is the CPU reference for the GPU scoring kernels.
+    pub fn viewpoint_gain(
+        &self,
+        origin: (f32, f32, f32),
+        ray_directions: &[(f32, f32, f32)],
+        config: &InformationGainConfig,
+    ) -> f32 {
+        let mut observed = HashSet::new();
+        ray_directions
+            .iter()
+            .map(|&dir| {
+                march_sensor_ray(origin, dir, config, &mut observed, |x, y, z| {
+                    self.state(x, y, z)
+                })
+            })
+            .sum()
+    }
+
+    fn linear_index(&self, x: i32, y: i32, z: i32) -> Option<usize> {
+        let dx = x as i64 - self.min.0 as i64;
+        let dy = y as i64 - self.min.1 as i64;
+        let dz = z as i64 - self.min.2 as i64;
+        if dx < 0
+            || dy < 0
+            || dz < 0
+            || dx >= self.dims.0 as i64
+            || dy >= self.dims.1 as i64
+            || dz >= self.dims.2 as i64
+        {
+            return None;
+        }
+        Some(
+            ((dz as usize * self.dims.1 as usize) + dy as usize) * self.dims.0 as usize
+                + dx as usize,
+        )
+    }
+}
+
+/// Flattened batch of viewpoints and their sensor rays
+///
+/// Every viewpoint casts the same number of rays; the directions for
+/// viewpoint `i` are `ray_directions[i * rays_per_viewpoint..][..rays_per_viewpoint]`.
+#[derive(Debug, Clone)]
+pub struct ViewpointBatch {
+    /// Viewpoint positions (meters)
+    pub origins: Vec<(f32, f32, f32)>,
+    /// Unit ray directions, grouped by viewpoint
+    pub ray_directions: Vec<(f32, f32, f32)>,
+    /// Number of rays cast from each viewpoint
+    pub rays_per_viewpoint: usize,
+}
+
+/// March one sensor ray, summing the weight of newly observed unknown voxels
+///
+/// Samples every half voxel out to the sensor range and stops at the first
+/// occupied voxel. `observed` de-duplicates voxels across rays of the same
+/// viewpoint.
+fn march_sensor_ray<F>(
+    origin: (f32, f32, f32),
+    dir: (f32, f32, f32),
+    config: &InformationGainConfig,
+    observed: &mut HashSet<(i32, i32, i32)>,
+    state_at: F,
+) -> f32
+where
+    F: Fn(i32, i32, i32) -> OccupancyState,
+{
+    let step_size = config.voxel_size * 0.5;
+    if step_size <= 0.0 {
+        return 0.0;
+    }
+    let num_steps = (config.sensor_range / step_size) as usize;
+
+    let mut gain = 0.0;
+    for i in 0..num_steps {
+        let t = i as f32 * step_size;
+        let voxel = snap_to_nearest_bcc(
+            ((origin.0 + dir.0 * t) / config.voxel_size).round() as i32,
+            ((origin.1 + dir.1 * t) / config.voxel_size).round() as i32,
+            ((origin.2 + dir.2 * t) / config.voxel_size).round() as i32,
+        );
+
+        match state_at(voxel.0, voxel.1, voxel.2) {
+            OccupancyState::Occupied => break, // Sensor cannot see past obstacles
+            OccupancyState::Unknown => {
+                if observed.insert(voxel) {
+                    gain += config.unknown_weight;
+                }
+            }
+            OccupancyState::Free => {}
         }
     }
+    gain
+}
+
+/// Strategy used by [`assign_frontiers_with`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AssignmentStrategy {
+    /// Hungarian algorithm: minimizes the total travel cost (O(n²m))
+    #[default]
+    Optimal,
+    /// Sequential auction: the cheapest remaining robot/frontier pair wins
+    /// each round. Faster on large teams, but not guaranteed optimal.
+    GreedyAuction,
+}
+
+/// A robot assigned to a frontier
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrontierAssignment {
+    /// Index into the robots slice
+    pub robot: usize,
+    /// Index into the frontiers slice
+    pub frontier: usize,
+    /// Travel cost of this assignment
+    pub cost: f64,
+}
+
+/// Assign robots to distinct frontiers minimizing total travel cost
+///
+/// `cost_matrix[i][j]` is the cost for robot `i` to reach frontier `j`
+/// (`f64::INFINITY` if unreachable), typically computed with
+/// [`routing::travel_costs`](crate::routing::travel_costs). If `None`, the
+/// straight-line distance from each robot position to each frontier centroid
+/// is used. Each robot receives at most one frontier and vice versa; robots
+/// that cannot reach any free frontier are left unassigned.
+///
+/// Results are sorted by robot index.
+///
+/// # Example
+///
+/// ```rust
+/// use octaindex3d::layers::{assign_frontiers, Frontier};
+///
+/// # fn main() -> octaindex3d::Result<()> {
+/// let frontier = |x: f32| Frontier {
+///     centroid: (x, 0.0, 0.0),
+///     voxels: Vec::new(),
+///     information_gain: 0.0,
+///     size: 0,
+/// };
+/// let robots = [(0.0, 0.0, 0.0), (10.0, 0.0, 0.0)];
+/// let frontiers = [frontier(9.0), frontier(1.0)];
+///
+/// let assignments = assign_frontiers(&robots, &frontiers, None)?;
+/// assert_eq!(assignments[0].frontier, 1);
+/// assert_eq!(assignments[1].frontier, 0);
+/// # Ok(())
+/// # }
+/// ```
+pub fn assign_frontiers(
+    robots: &[(f32, f32, f32)],
+    frontiers: &[Frontier],
+    cost_matrix: Option<&[Vec<f64>]>,
+) -> Result<Vec<FrontierAssignment>> {
+    assign_frontiers_with(robots, frontiers, cost_matrix, AssignmentStrategy::Optimal)
+}
+
+/// [`assign_frontiers`] with an explicit assignment strategy
+pub fn assign_frontiers_with(
+    robots: &[(f32, f32, f32)],
+    frontiers: &[Frontier],
+    cost_matrix: Option<&[Vec<f64>]>,
+    strategy: AssignmentStrategy,
+) -> Result<Vec<FrontierAssignment>> {
+    let costs: Vec<Vec<f64>> = match cost_matrix {
+        Some(matrix) => {
+            if matrix.len() != robots.len() || matrix.iter().any(|row| row.len() != frontiers.len())
+            {
+                return Err(Error::InvalidFormat(format!(
+                    "Cost matrix must be {} x {} (robots x frontiers)",
+                    robots.len(),
+                    frontiers.len()
+                )));
+            }
+            matrix.to_vec()
+        }
+        None => robots
+            .iter()
+            .map(|r| {
+                frontiers
+                    .iter()
+                    .map(|f| {
+                        let d = (f.centroid.0 - r.0, f.centroid.1 - r.1, f.centroid.2 - r.2);
+                        ((d.0 * d.0 + d.1 * d.1 + d.2 * d.2) as f64).sqrt()
+                    })
+                    .collect()
+            })
+            .collect(),
+    };
+
+    if costs.iter().flatten().any(|c| c.is_nan() || *c < 0.0) {
+        return Err(Error::InvalidFormat(
+            "Costs must be non-negative (use INFINITY for unreachable)".to_string(),
+        ));
+    }
+
+    let pairs = match strategy {
+        AssignmentStrategy::Optimal => hungarian(&costs, frontiers.len()),
+        AssignmentStrategy::GreedyAuction => greedy_auction(&costs, frontiers.len()),
+    };
+
+    let mut assignments: Vec<FrontierAssignment> = pairs
+        .into_iter()
+        .filter(|&(robot, frontier)| costs[robot][frontier].is_finite())
+        .map(|(robot, frontier)| FrontierAssignment {
+            robot,
+            frontier,
+            cost: costs[robot][frontier],
+        })
+        .collect();
+    assignments.sort_by_key(|a| a.robot);
+    Ok(assignments)
+}
+
+/// Minimum-cost assignment for a rectangular cost matrix
+///
+/// Shortest augmenting path formulation with potentials. Unreachable pairs
+/// are replaced by a finite penalty larger than any feasible total so that
+/// they are only chosen when unavoidable (and then filtered by the caller).
+fn hungarian(costs: &[Vec<f64>], cols: usize) -> Vec<(usize, usize)> {
+    let rows = costs.len();
+    if rows == 0 || cols == 0 {
+        return Vec::new();
+    }
+
+    let finite_sum: f64 = costs.iter().flatten().filter(|c| c.is_finite()).sum();
+    let penalty = finite_sum + 1.0;
+    let transpose = rows > cols;
+    let (n, m) = if transpose {
+        (cols, rows)
+    } else {
+        (rows, cols)
+    };
+    let a = |i: usize, j: usize| {
+        let c = if transpose { costs[j][i] } else { costs[i][j] };
+        if c.is_finite() {
+            c
+        } else {
+            penalty
+        }
+    };
+
+    // 1-based arrays; p[j] is the row matched to column j
+    let mut u = vec![0.0; n + 1];
+    let mut v = vec![0.0; m + 1];
+    let mut p = vec![0usize; m + 1];
+    let mut way = vec![0usize; m + 1];
+
+    for i in 1..=n {
+        p[0] = i;
+        let mut j0 = 0;
+        let mut minv = vec![f64::INFINITY; m + 1];
+        let mut used = vec![false; m + 1];
+        loop {
+            used[j0] = true;
+            let i0 = p[j0];
+            let mut delta = f64::INFINITY;
+            let mut j1 = 0;
+            for j in 1..=m {
+                if !used[j] {
+                    let cur = a(i0 - 1, j - 1) - u[i0] - v[j];
+                    if cur < minv[j] {
+                        minv[j] = cur;
+                        way[j] = j0;
+                    }
+                    if minv[j] < delta {
+                        delta = minv[j];
+                        j1 = j;
+                    }
+                }
+            }
+            for j in 0..=m {
+                if used[j] {
+                    u[p[j]] += delta;
+                    v[j] -= delta;
+                } else {
+                    minv[j] -= delta;
+                }
+            }
+            j0 = j1;
+            if p[j0] == 0 {
+                break;
+            }
+        }
+        // Augment along the alternating path
+        loop {
+            let j1 = way[j0];
+            p[j0] = p[j1];
+            j0 = j1;
+            if j0 == 0 {
+                break;
+            }
+        }
+    }
+
+    (1..=m)
+        .filter(|&j| p[j] != 0)
+        .map(|j| {
+            let (row, col) = (p[j] - 1, j - 1);
+            if transpose {
+                (col, row)
+            } else {
+                (row, col)
+            }
+        })
+        .collect()
+}
+
+/// Greedy sequential auction: repeatedly award the cheapest remaining pair
+fn greedy_auction(costs: &[Vec<f64>], cols: usize) -> Vec<(usize, usize)> {
+    let mut bids: Vec<(f64, usize, usize)> = costs
+        .iter()
+        .enumerate()
+        .flat_map(|(robot, row)| {
+            row.iter()
+                .enumerate()
+                .filter(|(_, c)| c.is_finite())
+                .map(move |(frontier, &c)| (c, robot, frontier))
+        })
+        .collect();
+    bids.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)).then(a.2.cmp(&b.2)));
+
+    let mut robot_taken = vec![false; costs.len()];
+    let mut frontier_taken = vec![false; cols];
+    let mut pairs = Vec::new();
+    for (_, robot, frontier) in bids {
+        if !robot_taken[robot] && !frontier_taken[frontier] {
+            robot_taken[robot] = true;
+            frontier_taken[frontier] = true;
+            pairs.push((robot, frontier));
+        }
+    }
+    pairs
 }
 
 impl OccupancyLayer {
@@ -193,39 +650,44 @@ impl OccupancyLayer {
             direction.2 / dir_len,
         );
 
-        let mut gain = 0.0;
         let mut observed = HashSet::new();
+        Self::sensor_ray_directions(dir, config)
+            .into_iter()
+            .map(|ray_dir| self.ray_information_gain(viewpoint, ray_dir, &mut observed, config))
+            .sum()
+    }
 
-        // Cast rays in sensor FOV
-        let ray_count = (config.sensor_fov.to_degrees() / config.ray_resolution).ceil() as i32;
-
-        for i in -ray_count..=ray_count {
-            for j in -ray_count..=ray_count {
-                let angle_h = i as f32 * config.ray_resolution.to_radians();
-                let angle_v = j as f32 * config.ray_resolution.to_radians();
-
-                // Skip rays outside FOV
-                if angle_h.abs() > config.sensor_fov / 2.0
-                    || angle_v.abs() > config.sensor_fov / 2.0
-                {
-                    continue;
-                }
-
-                // Calculate ray direction (simplified rotation)
-                let ray_dir = Self::rotate_direction(dir, angle_h, angle_v);
-
-                // Cast ray and count unknown voxels
-                let endpoint = (
-                    viewpoint.0 + ray_dir.0 * config.sensor_range,
-                    viewpoint.1 + ray_dir.1 * config.sensor_range,
-                    viewpoint.2 + ray_dir.2 * config.sensor_range,
-                );
-
-                gain += self.ray_information_gain(viewpoint, endpoint, &mut observed, config);
-            }
+    /// Score a batch of viewpoints, writing each one's `information_gain`
+    ///
+    /// The region around the viewpoints is copied into an
+    /// [`OccupancySnapshot`] and scored in one pass. With the `gpu-metal`
+    /// feature on macOS, batches of enough rays are scored by the Metal
+    /// kernel; CUDA has no scoring kernel. Otherwise, if the GPU fails, or
+    /// if the batch's de-duplication tables would be too large for it, the
+    /// CPU path is used (parallel with the `parallel` feature). CPU
+    /// results match [`Self::information_gain_from`] for each viewpoint.
+    pub fn score_viewpoints(
+        &self,
+        viewpoints: &mut [Viewpoint],
+        config: &InformationGainConfig,
+    ) -> Result<()> {
+        if viewpoints.is_empty() {
+            return Ok(());
+        }
+        if config.voxel_size <= 0.0 {
+            return Err(Error::OutOfRange("Voxel size must be positive".to_string()));
         }
 
-        gain
+        let batch = Self::viewpoint_batch(viewpoints, config);
+        let snapshot = self.snapshot_for(&batch.origins, config)?;
+        let gains = match Self::score_batch_gpu(&snapshot, &batch, config) {
+            Some(gains) => gains,
+            None => Self::score_batch_cpu(&snapshot, &batch, config),
+        };
+        for (viewpoint, gain) in viewpoints.iter_mut().zip(gains) {
+            viewpoint.information_gain = gain;
+        }
+        Ok(())
     }
 
     /// Generate viewpoint candidates for frontiers
@@ -261,19 +723,25 @@ impl OccupancyLayer {
                         frontier.centroid.2 - position.2,
                     );
 
-                    // Calculate information gain
-                    let ig = self.information_gain_from(position, dir, ig_config);
-
                     candidates.push(Viewpoint {
                         position,
                         direction: Self::normalize(dir),
-                        information_gain: ig,
+                        information_gain: 0.0, // Scored below in one batch
                         frontier_id,
                     });
                 }
             }
         }
 
+        // Calculate information gain, one viewpoint at a time if the batch
+        // region is too large to snapshot
+        if self.score_viewpoints(&mut candidates, ig_config).is_err() {
+            for candidate in &mut candidates {
+                candidate.information_gain =
+                    self.information_gain_from(candidate.position, candidate.direction, ig_config);
+            }
+        }
+
         // Sort by information gain (highest first)
         candidates.sort_by(|a, b| {
             b.information_gain
@@ -362,15 +830,173 @@ impl OccupancyLayer {
     /// Calculate information gain along a ray
     fn ray_information_gain(
         &self,
-        _origin: (f32, f32, f32),
-        _endpoint: (f32, f32, f32),
-        _observed: &mut HashSet<Index64>,
+        origin: (f32, f32, f32),
+        dir: (f32, f32, f32),
+        observed: &mut HashSet<(i32, i32, i32)>,
         config: &InformationGainConfig,
     ) -> f32 {
-        // Would cast ray and count unknown voxels
-        // For now, placeholder
-        let unknown_count = 0;
-        unknown_count as f32 * config.unknown_weight
+        march_sensor_ray(origin, dir, config, observed, |x, y, z| {
+            if (0..=u16::MAX as i32).contains(&x)
+                && (0..=u16::MAX as i32).contains(&y)
+                && (0..=u16::MAX as i32).contains(&z)
+            {
+                Index64::new(
+                    config.frame,
+                    config.tier,
+                    config.lod,
+                    x as u16,
+                    y as u16,
+                    z as u16,
+                )
+                .map(|idx| self.get_state(idx))
+                .unwrap_or(OccupancyState::Unknown)
+            } else {
+                OccupancyState::Unknown
+            }
+        })
+    }
+
+    /// Sensor ray directions within the field of view around `dir`
+    fn sensor_ray_directions(
+        dir: (f32, f32, f32),
+        config: &InformationGainConfig,
+    ) -> Vec<(f32, f32, f32)> {
+        let ray_count = (config.sensor_fov.to_degrees() / config.ray_resolution).ceil() as i32;
+        let mut rays = Vec::new();
+
+        for i in -ray_count..=ray_count {
+            for j in -ray_count..=ray_count {
+                let angle_h = i as f32 * config.ray_resolution.to_radians();
+                let angle_v = j as f32 * config.ray_resolution.to_radians();
+
+                // Skip rays outside FOV
+                if angle_h.abs() > config.sensor_fov / 2.0
+                    || angle_v.abs() > config.sensor_fov / 2.0
+                {
+                    continue;
+                }
+
+                // Calculate ray direction (simplified rotation)
+                rays.push(Self::rotate_direction(dir, angle_h, angle_v));
+            }
+        }
+
+        rays
+    }
+
+    /// Flatten viewpoints into origins and per-viewpoint ray directions
+    fn viewpoint_batch(viewpoints: &[Viewpoint], config: &InformationGainConfig) -> ViewpointBatch {
+        let mut origins = Vec::with_capacity(viewpoints.len());
+        let mut ray_directions = Vec::new();
+        let mut rays_per_viewpoint = 0;
+
+        for viewpoint in viewpoints {
+            let rays = Self::sensor_ray_directions(Self::normalize(viewpoint.direction), config);
+            rays_per_viewpoint = rays.len();
+            origins.push(viewpoint.position);
+            ray_directions.extend(rays);
+        }
+
+        ViewpointBatch {
+            origins,
+            ray_directions,
+            rays_per_viewpoint,
+        }
+    }
+
+    /// Snapshot covering every voxel reachable from `origins` within sensor range
+    fn snapshot_for(
+        &self,
+        origins: &[(f32, f32, f32)],
+        config: &InformationGainConfig,
+    ) -> Result<OccupancySnapshot> {
+        let reach = config.sensor_range.max(0.0) / config.voxel_size + 2.0; // Rounding + BCC snap margin
+        let mut min = (f32::INFINITY, f32::INFINITY, f32::INFINITY);
+        let mut max = (f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY);
+        for &(x, y, z) in origins {
+            let v = (
+                x / config.voxel_size,
+                y / config.voxel_size,
+                z / config.voxel_size,
+            );
+            min = (min.0.min(v.0), min.1.min(v.1), min.2.min(v.2));
+            max = (max.0.max(v.0), max.1.max(v.1), max.2.max(v.2));
+        }
+
+        // Layer indices are unsigned 16-bit, so clamp to that range plus a margin
+        let lo = -2.0;
+        let hi = u16::MAX as f32 + 2.0;
+        let corner = |v: f32| v.clamp(lo, hi) as i32;
+        OccupancySnapshot::from_layer(
+            self,
+            config,
+            (
+                corner((min.0 - reach).floor()),
+                corner((min.1 - reach).floor()),
+                corner((min.2 - reach).floor()),
+            ),
+            (
+                corner((max.0 + reach).ceil()),
+                corner((max.1 + reach).ceil()),
+                corner((max.2 + reach).ceil()),
+            ),
+        )
+    }
+
+    /// Score a batch on the GPU, if a scoring backend is usable and the
+    /// batch has enough rays to be worth the transfer
+    #[cfg(all(feature = "gpu-metal", target_os = "macos"))]
+    fn score_batch_gpu(
+        snapshot: &OccupancySnapshot,
+        batch: &ViewpointBatch,
+        config: &InformationGainConfig,
+    ) -> Option<Vec<f32>> {
+        use super::occupancy_gpu::GpuRayCaster;
+        use std::sync::OnceLock;
+
+        // Device and pipelines are set up once per process
+        static CASTER: OnceLock<Option<GpuRayCaster>> = OnceLock::new();
+        let caster = CASTER
+            .get_or_init(|| GpuRayCaster::for_viewpoint_scoring().ok())
+            .as_ref()?;
+        if batch.ray_directions.len() < caster.min_batch_size() {
+            return None;
+        }
+        caster.score_viewpoints(snapshot, batch, config).ok()
+    }
+
+    /// Score a batch on the GPU; no backend here has a scoring kernel
+    #[cfg(not(all(feature = "gpu-metal", target_os = "macos")))]
+    fn score_batch_gpu(
+        _snapshot: &OccupancySnapshot,
+        _batch: &ViewpointBatch,
+        _config: &InformationGainConfig,
+    ) -> Option<Vec<f32>> {
+        None
+    }
+
+    /// Score a viewpoint batch on the CPU
+    fn score_batch_cpu(
+        snapshot: &OccupancySnapshot,
+        batch: &ViewpointBatch,
+        config: &InformationGainConfig,
+    ) -> Vec<f32> {
+        let score = |(i, &origin): (usize, &(f32, f32, f32))| {
+            let start = i * batch.rays_per_viewpoint;
+            let rays = &batch.ray_directions[start..start + batch.rays_per_viewpoint];
+            snapshot.viewpoint_gain(origin, rays, config)
+        };
+
+        #[cfg(feature = "parallel")]
+        {
+            use rayon::prelude::*;
+            batch.origins.par_iter().enumerate().map(score).collect()
+        }
+
+        #[cfg(not(feature = "parallel"))]
+        {
+            batch.origins.iter().enumerate().map(score).collect()
+        }
     }
 
     /// Rotate direction vector
@@ -440,4 +1066,238 @@ mod tests {
             .sqrt();
         assert!((len - 1.0).abs() < 0.001);
     }
+
+    /// Occupancy layer with free space around the origin region and a wall
+    /// at x = 30 (voxels), leaving everything else unknown
+    fn walled_layer() -> Result<OccupancyLayer> {
+        let mut layer = OccupancyLayer::new();
+        add_walled_region(&mut layer, 5)?;
+        Ok(layer)
+    }
+
+    fn add_walled_region(layer: &mut OccupancyLayer, lod: u8) -> Result<()> {
+        for x in (0..10u16).step_by(2) {
+            for y in (0..10u16).step_by(2) {
+                for z in (0..10u16).step_by(2) {
+                    layer.update_occupancy(Index64::new(0, 0, lod, x, y, z)?, false, 0.9);
+                }
+            }
+        }
+        for y in 0..80u16 {
+            for z in 0..80u16 {
+                for x in [30u16, 31] {
+                    layer.update_occupancy(Index64::new(0, 0, lod, x, y, z)?, true, 0.9);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_information_gain_counts_unknown_and_stops_at_walls() -> Result<()> {
+        let layer = walled_layer()?;
+        let config = InformationGainConfig {
+            sensor_range: 5.0,
+            ..Default::default()
+        };
+
+        // Looking towards the nearby wall sees less unknown space than
+        // looking away from it
+        let origin = (2.0, 4.0, 4.0);
+        let towards_wall = layer.information_gain_from(origin, (1.0, 0.0, 0.0), &config);
+        let open_space = layer.information_gain_from(origin, (-1.0, 0.0, 0.0), &config);
+        assert!(towards_wall > 0.0);
+        assert!(open_space > towards_wall);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_score_viewpoints_matches_single_viewpoint() -> Result<()> {
+        let layer = walled_layer()?;
+        let config = InformationGainConfig::default();
+
+        let mut viewpoints: Vec<Viewpoint> = [(1.0, 0.0, 0.0), (0.0, 1.0, 0.0), (0.0, 0.0, 1.0)]
+            .iter()
+            .enumerate()
+            .map(|(i, &direction)| Viewpoint {
+                position: (0.5 + i as f32 * 0.3, 0.5, 0.5),
+                direction,
+                information_gain: 0.0,
+                frontier_id: 0,
+            })
+            .collect();
+
+        layer.score_viewpoints(&mut viewpoints, &config)?;
+        for viewpoint in &viewpoints {
+            let expected =
+                layer.information_gain_from(viewpoint.position, viewpoint.direction, &config);
+            assert!(expected > 0.0);
+            assert_eq!(viewpoint.information_gain, expected);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_score_viewpoints_cpu_fallback() -> Result<()> {
+        let layer = walled_layer()?;
+        let config = InformationGainConfig::default();
+
+        // Far more rays than any GPU batch threshold
+        let mut viewpoints: Vec<Viewpoint> = (0..40)
+            .map(|i| Viewpoint {
+                position: (0.2 + (i % 8) as f32 * 0.2, 0.5 + (i / 8) as f32 * 0.2, 0.5),
+                direction: (1.0, (i % 3) as f32 - 1.0, 0.0),
+                information_gain: 0.0,
+                frontier_id: i,
+            })
+            .collect();
+        let batch = OccupancyLayer::viewpoint_batch(&viewpoints, &config);
+        let snapshot = layer.snapshot_for(&batch.origins, &config)?;
+        assert!(batch.ray_directions.len() > 1000);
+
+        layer.score_viewpoints(&mut viewpoints, &config)?;
+        let cpu = OccupancyLayer::score_batch_cpu(&snapshot, &batch, &config);
+        if OccupancyLayer::score_batch_gpu(&snapshot, &batch, &config).is_none() {
+            let scored: Vec<f32> = viewpoints.iter().map(|v| v.information_gain).collect();
+            assert_eq!(scored, cpu);
+        }
+        for (viewpoint, gain) in viewpoints.iter().zip(&cpu) {
+            let expected =
+                layer.information_gain_from(viewpoint.position, viewpoint.direction, &config);
+            assert_eq!(*gain, expected);
+        }
+
+        let invalid = InformationGainConfig {
+            voxel_size: 0.0,
+            ..config
+        };
+        assert!(layer.score_viewpoints(&mut viewpoints, &invalid).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_score_viewpoints_matches_at_other_lod() -> Result<()> {
+        // The same region at LOD 7, under a LOD 5 wall right next to the
+        // viewpoints that the sensor must not see
+        let mut layer = OccupancyLayer::new();
+        add_walled_region(&mut layer, 7)?;
+        for y in 0..20u16 {
+            for z in 0..20u16 {
+                layer.update_occupancy(Index64::new(0, 0, 5, 12, y, z)?, true, 0.9);
+            }
+        }
+        let config = InformationGainConfig {
+            lod: 7,
+            ..Default::default()
+        };
+
+        let mut viewpoints: Vec<Viewpoint> = (0..6)
+            .map(|i| Viewpoint {
+                position: (0.4 + i as f32 * 0.1, 0.5, 0.5),
+                direction: (1.0, (i % 3) as f32 - 1.0, 0.0),
+                information_gain: 0.0,
+                frontier_id: i,
+            })
+            .collect();
+        layer.score_viewpoints(&mut viewpoints, &config)?;
+        for viewpoint in &viewpoints {
+            let expected =
+                layer.information_gain_from(viewpoint.position, viewpoint.direction, &config);
+            assert_eq!(viewpoint.information_gain, expected);
+        }
+
+        // Ignoring the LOD 5 wall means seeing further than at LOD 5
+        let lod5 = InformationGainConfig::default();
+        let viewpoint = &viewpoints[0];
+        assert!(
+            viewpoint.information_gain
+                > layer.information_gain_from(viewpoint.position, viewpoint.direction, &lod5)
+        );
+        Ok(())
+    }
+
+    fn frontier_at(x: f32) -> Frontier {
+        Frontier {
+            centroid: (x, 0.0, 0.0),
+            voxels: Vec::new(),
+            information_gain: 0.0,
+            size: 0,
+        }
+    }
+
+    #[test]
+    fn test_assign_frontiers_optimal_beats_greedy() -> Result<()> {
+        let robots = [(0.0, 0.0, 0.0); 2];
+        let frontiers = [frontier_at(0.0), frontier_at(0.0)];
+        // Greedy grabs the 1.0 pair and is left with 100.0; optimal is 2 + 3
+        let costs = vec![vec![1.0, 2.0], vec![3.0, 100.0]];
+
+        let optimal = assign_frontiers(&robots, &frontiers, Some(&costs))?;
+        let total: f64 = optimal.iter().map(|a| a.cost).sum();
+        assert_eq!(total, 5.0);
+        assert_eq!(optimal[0].frontier, 1);
+
+        let greedy = assign_frontiers_with(
+            &robots,
+            &frontiers,
+            Some(&costs),
+            AssignmentStrategy::GreedyAuction,
+        )?;
+        let total: f64 = greedy.iter().map(|a| a.cost).sum();
+        assert_eq!(total, 101.0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_assign_frontiers_rectangular_and_unreachable() -> Result<()> {
+        let robots = [(0.0, 0.0, 0.0), (5.0, 0.0, 0.0), (9.0, 0.0, 0.0)];
+        let frontiers = [frontier_at(10.0), frontier_at(4.0)];
+
+        // More robots than frontiers: the farthest robot stays idle
+        let assignments = assign_frontiers(&robots, &frontiers, None)?;
+        assert_eq!(assignments.len(), 2);
+        assert_eq!(
+            assignments
+                .iter()
+                .map(|a| (a.robot, a.frontier))
+                .collect::<Vec<_>>(),
+            vec![(1, 1), (2, 0)]
+        );
+
+        // Robot 1 cannot reach anything
+        let inf = f64::INFINITY;
+        let costs = vec![vec![1.0, 2.0], vec![inf, inf], vec![3.0, 1.0]];
+        let assignments = assign_frontiers(&robots, &frontiers, Some(&costs))?;
+        assert!(assignments.iter().all(|a| a.robot != 1));
+        assert_eq!(assignments.len(), 2);
+
+        // Dimension mismatch
+        assert!(assign_frontiers(&robots, &frontiers, Some(&costs[..2])).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_snapshot_states() -> Result<()> {
+        let layer = walled_layer()?;
+        let config = InformationGainConfig::default();
+        let snapshot = OccupancySnapshot::from_layer(&layer, &config, (0, 0, 0), (40, 40, 40))?;
+
+        assert_eq!(snapshot.dims(), (41, 41, 41));
+        assert_eq!(snapshot.state(2, 2, 2), OccupancyState::Free);
+        assert_eq!(snapshot.state(30, 0, 0), OccupancyState::Occupied);
+        assert_eq!(snapshot.state(20, 20, 20), OccupancyState::Unknown);
+        assert_eq!(snapshot.state(-5, 0, 0), OccupancyState::Unknown);
+
+        assert!(OccupancySnapshot::from_layer(&layer, &config, (1, 1, 1), (0, 0, 0)).is_err());
+
+        // Voxels of another LOD are not part of the snapshot
+        let other = InformationGainConfig { lod: 6, ..config };
+        let snapshot = OccupancySnapshot::from_layer(&layer, &other, (0, 0, 0), (40, 40, 40))?;
+        assert_eq!(snapshot.state(30, 0, 0), OccupancyState::Unknown);
+        Ok(())
+    }
 }
@@ -1,10 +1,10 @@
-//! Mesh export formats (PLY, OBJ)
+//! Mesh export formats (PLY, OBJ, STL, IFC)
 //!
 //! Implements standard mesh file format writers with no proprietary dependencies.
 //! All formats are documented open standards.
 
 use super::mesh::Mesh;
-use crate::error::Result;
+use crate::error::{Error, Result};
 use std::fs::File;
 use std::io::{BufWriter, Write};
 use std::path::Path;
@@ -59,41 +59,55 @@ pub fn export_mesh_ply(mesh: &Mesh, path: impl AsRef<Path>, binary: bool) -> Res
         writeln!(writer, "property float nz")?;
     }
 
+    // Add color properties if mesh has vertex colors
+    let has_colors = mesh.vertices.iter().any(|v| v.color.is_some());
+    if has_colors {
+        writeln!(writer, "property uchar red")?;
+        writeln!(writer, "property uchar green")?;
+        writeln!(writer, "property uchar blue")?;
+    }
+
     writeln!(writer, "element face {}", mesh.triangles.len())?;
     writeln!(writer, "property list uchar int vertex_indices")?;
     writeln!(writer, "end_header")?;
 
     if binary {
         // Write binary data
-        write_ply_binary(&mut writer, mesh, has_normals)?;
+        write_ply_binary(&mut writer, mesh, has_normals, has_colors)?;
     } else {
         // Write ASCII data
-        write_ply_ascii(&mut writer, mesh, has_normals)?;
+        write_ply_ascii(&mut writer, mesh, has_normals, has_colors)?;
     }
 
     Ok(())
 }
 
 /// Write PLY vertices and faces in ASCII format
-fn write_ply_ascii(writer: &mut BufWriter<File>, mesh: &Mesh, has_normals: bool) -> Result<()> {
+fn write_ply_ascii(
+    writer: &mut BufWriter<File>,
+    mesh: &Mesh,
+    has_normals: bool,
+    has_colors: bool,
+) -> Result<()> {
     // Write vertices
     for vertex in &mesh.vertices {
         let pos = vertex.position;
+        write!(writer, "{} {} {}", pos[0], pos[1], pos[2])?;
 
         if has_normals {
             if let Some(normal) = vertex.normal {
-                writeln!(
-                    writer,
-                    "{} {} {} {} {} {}",
-                    pos[0], pos[1], pos[2], normal[0], normal[1], normal[2]
-                )?;
+                write!(writer, " {} {} {}", normal[0], normal[1], normal[2])?;
             } else {
                 // No normal for this vertex, use default (0, 0, 1)
-                writeln!(writer, "{} {} {} 0.0 0.0 1.0", pos[0], pos[1], pos[2])?;
+                write!(writer, " 0.0 0.0 1.0")?;
             }
-        } else {
-            writeln!(writer, "{} {} {}", pos[0], pos[1], pos[2])?;
         }
+        if has_colors {
+            // Uncolored vertices are written white
+            let [r, g, b] = vertex.color.unwrap_or([255; 3]);
+            write!(writer, " {} {} {}", r, g, b)?;
+        }
+        writeln!(writer)?;
     }
 
     // Write faces
@@ -106,7 +120,12 @@ fn write_ply_ascii(writer: &mut BufWriter<File>, mesh: &Mesh, has_normals: bool)
 }
 
 /// Write PLY vertices and faces in binary format
-fn write_ply_binary(writer: &mut BufWriter<File>, mesh: &Mesh, has_normals: bool) -> Result<()> {
+fn write_ply_binary(
+    writer: &mut BufWriter<File>,
+    mesh: &Mesh,
+    has_normals: bool,
+    has_colors: bool,
+) -> Result<()> {
     // Write vertices
     for vertex in &mesh.vertices {
         let pos = vertex.position;
@@ -128,6 +147,9 @@ fn write_ply_binary(writer: &mut BufWriter<File>, mesh: &Mesh, has_normals: bool
                 writer.write_all(&1.0f32.to_le_bytes())?;
             }
         }
+        if has_colors {
+            writer.write_all(&vertex.color.unwrap_or([255; 3]))?;
+        }
     }
 
     // Write faces
@@ -176,10 +198,21 @@ pub fn export_mesh_obj(mesh: &Mesh, path: impl AsRef<Path>) -> Result<()> {
     writeln!(writer, "# Faces: {}", mesh.triangles.len())?;
     writeln!(writer)?;
 
-    // Write vertices
+    // Write vertices, with colors as the widely supported `v x y z r g b`
+    // extension when present
+    let has_colors = mesh.vertices.iter().any(|v| v.color.is_some());
     for vertex in &mesh.vertices {
         let pos = vertex.position;
-        writeln!(writer, "v {} {} {}", pos[0], pos[1], pos[2])?;
+        if has_colors {
+            let [r, g, b] = vertex.color.unwrap_or([255; 3]).map(|c| c as f32 / 255.0);
+            writeln!(
+                writer,
+                "v {} {} {} {} {} {}",
+                pos[0], pos[1], pos[2], r, g, b
+            )?;
+        } else {
+            writeln!(writer, "v {} {} {}", pos[0], pos[1], pos[2])?;
+        }
     }
 
     writeln!(writer)?;
@@ -343,6 +376,145 @@ fn write_stl_binary(writer: &mut BufWriter<File>, mesh: &Mesh) -> Result<()> {
     Ok(())
 }
 
+/// Export mesh to IFC4 (Industry Foundation Classes) for BIM tools
+///
+/// Writes a minimal IFC shell: a project with a site holding the mesh as
+/// one `IfcBuildingElementProxy` with an `IfcTriangulatedFaceSet` body.
+/// Positions are taken as meters. Global ids are derived from the entity
+/// numbers, so the same mesh always produces the same file. IFC face sets
+/// need at least one triangle, so empty meshes are rejected.
+/// Specification: <https://standards.buildingsmart.org/IFC/RELEASE/IFC4/ADD2_TC1/HTML/>
+///
+/// # Arguments
+/// * `mesh` - Mesh to export
+/// * `path` - Output file path
+pub fn export_mesh_ifc(mesh: &Mesh, path: impl AsRef<Path>) -> Result<()> {
+    if mesh.triangles.is_empty() {
+        return Err(Error::InvalidFormat(
+            "IFC export needs at least one triangle".to_string(),
+        ));
+    }
+    let file = File::create(path)?;
+    let mut writer = BufWriter::new(file);
+
+    writeln!(writer, "ISO-10303-21;")?;
+    writeln!(writer, "HEADER;")?;
+    writeln!(
+        writer,
+        "FILE_DESCRIPTION(('ViewDefinition [ReferenceView_V1.2]'),'2;1');"
+    )?;
+    writeln!(
+        writer,
+        "FILE_NAME('','',(''),(''),'OctaIndex3D','OctaIndex3D','');"
+    )?;
+    writeln!(writer, "FILE_SCHEMA(('IFC4'));")?;
+    writeln!(writer, "ENDSEC;")?;
+    writeln!(writer, "DATA;")?;
+
+    // Spatial structure, units and placement
+    writeln!(
+        writer,
+        "#1=IFCPROJECT('{}',$,'OctaIndex3D export',$,$,$,$,(#4),#2);",
+        ifc_guid(1)
+    )?;
+    writeln!(writer, "#2=IFCUNITASSIGNMENT((#3));")?;
+    writeln!(writer, "#3=IFCSIUNIT(*,.LENGTHUNIT.,$,.METRE.);")?;
+    writeln!(
+        writer,
+        "#4=IFCGEOMETRICREPRESENTATIONCONTEXT($,'Model',3,0.00001,#6,$);"
+    )?;
+    writeln!(writer, "#5=IFCCARTESIANPOINT((0.,0.,0.));")?;
+    writeln!(writer, "#6=IFCAXIS2PLACEMENT3D(#5,$,$);")?;
+    writeln!(
+        writer,
+        "#7=IFCSITE('{}',$,'Site',$,$,#8,$,$,.ELEMENT.,$,$,$,$,$);",
+        ifc_guid(7)
+    )?;
+    writeln!(writer, "#8=IFCLOCALPLACEMENT($,#6);")?;
+    writeln!(
+        writer,
+        "#9=IFCRELAGGREGATES('{}',$,$,$,#1,(#7));",
+        ifc_guid(9)
+    )?;
+
+    // Geometry
+    let points: Vec<String> = mesh
+        .vertices
+        .iter()
+        .map(|v| {
+            let [x, y, z] = v.position.map(step_real);
+            format!("({x},{y},{z})")
+        })
+        .collect();
+    writeln!(
+        writer,
+        "#10=IFCCARTESIANPOINTLIST3D(({}));",
+        points.join(",")
+    )?;
+    // IFC indices are 1-based
+    let faces: Vec<String> = mesh
+        .triangles
+        .iter()
+        .map(|t| {
+            let [a, b, c] = t.indices.map(|i| i + 1);
+            format!("({a},{b},{c})")
+        })
+        .collect();
+    writeln!(
+        writer,
+        "#11=IFCTRIANGULATEDFACESET(#10,$,$,({}),$);",
+        faces.join(",")
+    )?;
+    writeln!(
+        writer,
+        "#12=IFCSHAPEREPRESENTATION(#4,'Body','Tessellation',(#11));"
+    )?;
+    writeln!(writer, "#13=IFCPRODUCTDEFINITIONSHAPE($,$,(#12));")?;
+    writeln!(
+        writer,
+        "#14=IFCBUILDINGELEMENTPROXY('{}',$,'Scanned geometry',$,$,#15,#13,$,$);",
+        ifc_guid(14)
+    )?;
+    writeln!(writer, "#15=IFCLOCALPLACEMENT(#8,#6);")?;
+    writeln!(
+        writer,
+        "#16=IFCRELCONTAINEDINSPATIALSTRUCTURE('{}',$,$,$,(#14),#7);",
+        ifc_guid(16)
+    )?;
+
+    writeln!(writer, "ENDSEC;")?;
+    writeln!(writer, "END-ISO-10303-21;")?;
+    Ok(())
+}
+
+/// STEP real literal, which always needs a decimal point
+fn step_real(value: f32) -> String {
+    let text = value.to_string();
+    if text.contains('.') {
+        text
+    } else {
+        text + "."
+    }
+}
+
+/// Deterministic IFC global id for entity `n`
+///
+/// IFC encodes a 128-bit GUID as 22 characters of its own base-64
+/// alphabet, the first carrying only the top two bits.
+fn ifc_guid(n: u64) -> String {
+    const ALPHABET: &[u8; 64] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz_$";
+    // Spread the entity number over all bits (splitmix64 finalizer)
+    let mix = |mut z: u64| {
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    };
+    let value = (mix(n) as u128) << 64 | mix(!n) as u128;
+    (0..22)
+        .map(|k| ALPHABET[((value >> (6 * (21 - k))) & 0x3F) as usize] as char)
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::super::mesh::{Mesh, Triangle, Vertex};
@@ -374,6 +546,36 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_ply_vertex_colors() -> Result<()> {
+        let mut mesh = Mesh::new();
+        let v0 = mesh.add_vertex(Vertex::new(0.0, 0.0, 0.0).with_color([255, 0, 0]));
+        let v1 = mesh.add_vertex(Vertex::new(1.0, 0.0, 0.0));
+        let v2 = mesh.add_vertex(Vertex::new(0.0, 1.0, 0.0).with_color([0, 0, 255]));
+        mesh.add_triangle(Triangle::new(v0, v1, v2));
+
+        let temp_path = std::env::temp_dir().join("test_mesh_colors.ply");
+        export_mesh_ply(&mesh, &temp_path, false)?;
+        let contents = std::fs::read_to_string(&temp_path)?;
+        std::fs::remove_file(&temp_path).ok();
+
+        assert!(contents.contains("property uchar red"));
+        assert!(contents.contains("0 0 0 255 0 0\n"));
+        assert!(contents.contains("1 0 0 255 255 255\n"));
+
+        export_mesh_ply(&mesh, &temp_path, true)?;
+        let bytes = std::fs::read(&temp_path)?;
+        std::fs::remove_file(&temp_path).ok();
+        let header_end = bytes
+            .windows(11)
+            .position(|w| w == b"end_header\n")
+            .unwrap()
+            + 11;
+        // 3 vertices of 3 floats and 3 color bytes, then one 13-byte face
+        assert_eq!(bytes.len() - header_end, 3 * 15 + 13);
+        Ok(())
+    }
+
     #[test]
     fn test_obj_export() -> Result<()> {
         let mut mesh = Mesh::new();
@@ -398,4 +600,35 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_ifc_export() -> Result<()> {
+        let mut mesh = Mesh::new();
+        let v0 = mesh.add_vertex(Vertex::new(0.0, 0.0, 0.0));
+        let v1 = mesh.add_vertex(Vertex::new(1.5, 0.0, 0.0));
+        let v2 = mesh.add_vertex(Vertex::new(0.0, -2.0, 0.25));
+        mesh.add_triangle(Triangle::new(v0, v1, v2));
+
+        let temp_path = std::env::temp_dir().join("test_mesh.ifc");
+        export_mesh_ifc(&mesh, &temp_path)?;
+        let contents = std::fs::read_to_string(&temp_path)?;
+        std::fs::remove_file(&temp_path).ok();
+
+        assert!(contents.starts_with("ISO-10303-21;\n"));
+        assert!(contents.contains("FILE_SCHEMA(('IFC4'));"));
+        assert!(
+            contents.contains("IFCCARTESIANPOINTLIST3D(((0.,0.,0.),(1.5,0.,0.),(0.,-2.,0.25)));")
+        );
+        assert!(contents.contains("IFCTRIANGULATEDFACESET(#10,$,$,((1,2,3)),$);"));
+        assert!(contents.ends_with("END-ISO-10303-21;\n"));
+
+        let guid = ifc_guid(1);
+        assert_eq!(guid.len(), 22);
+        assert!(guid.starts_with(['0', '1', '2', '3']));
+        assert_ne!(guid, ifc_guid(7));
+
+        assert!(export_mesh_ifc(&Mesh::new(), &temp_path).is_err());
+        assert!(!temp_path.exists());
+        Ok(())
+    }
 }
@@ -0,0 +1,390 @@
+//! Load-aware admission of scans for integration
+//!
+//! Sensors deliver scans at their own rate whatever the mapping process
+//! can sustain. An [`IntegrationGovernor`] sits between the two: arrivals
+//! are [offered](IntegrationGovernor::offer) and the mapping loop takes
+//! them with [`integrate`](IntegrationGovernor::integrate), which times
+//! each integration. A [`GovernorPolicy`] decides what waits and what is
+//! dropped, so under load the map is built from fewer, recent scans instead
+//! of falling further and further behind.
+//!
+//! When the producer and the mapper run on different threads, share the
+//! governor behind a lock and have the mapper take a scan with
+//! [`next_scan`](IntegrationGovernor::next_scan), integrate it without
+//! the lock and report the time with
+//! [`record_integration`](IntegrationGovernor::record_integration), so
+//! arrivals are never blocked by an integration in progress.
+//!
+//! Arrival and waiting times come from a [`Clock`], so governed pipelines
+//! behave the same in simulation and replay. Integration cost is processor
+//! time, so [`integrate`](IntegrationGovernor::integrate) measures it with
+//! [`Instant`].
+
+use crate::clock::{Clock, WallClock};
+use crate::error::Result;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// What an [`IntegrationGovernor`] does with scans arriving faster than
+/// they are integrated
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GovernorPolicy {
+    /// Keep only the newest waiting scan; an arrival replaces it
+    LatestWins,
+    /// Queue up to `capacity` scans, dropping the oldest when full
+    Queue {
+        /// Scans that may wait
+        capacity: usize,
+    },
+    /// Admit only every k-th arrival into a queue of `capacity`, with k
+    /// chosen so integration takes at most `target_load` of the time
+    ///
+    /// The load is the measured integration time per scan over the time
+    /// between arrivals, both smoothed. k stays within `1..=max_factor`.
+    Decimate {
+        /// Scans that may wait
+        capacity: usize,
+        /// Fraction of time integration may take, in `(0, 1]`
+        target_load: f64,
+        /// Largest decimation factor
+        max_factor: u32,
+    },
+}
+
+/// Settings of an [`IntegrationGovernor`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GovernorConfig {
+    /// Admission and queueing policy
+    pub policy: GovernorPolicy,
+    /// Scans waiting longer than this are dropped instead of integrated
+    /// (seconds)
+    pub max_latency: Option<f64>,
+    /// Weight of the newest sample in the smoothed integration time and
+    /// arrival interval, in `(0, 1]`
+    pub smoothing: f64,
+}
+
+impl Default for GovernorConfig {
+    fn default() -> Self {
+        Self {
+            policy: GovernorPolicy::LatestWins,
+            max_latency: None,
+            smoothing: 0.2,
+        }
+    }
+}
+
+/// Scans handled by an [`IntegrationGovernor`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GovernorStats {
+    /// Scans offered
+    pub offered: u64,
+    /// Scans integrated
+    pub integrated: u64,
+    /// Waiting scans replaced by a newer one
+    pub superseded: u64,
+    /// Scans dropped from a full queue
+    pub overflowed: u64,
+    /// Arrivals skipped by decimation
+    pub decimated: u64,
+    /// Scans dropped for waiting longer than the maximum latency
+    pub expired: u64,
+}
+
+impl GovernorStats {
+    /// Scans dropped for any reason
+    pub fn dropped(&self) -> u64 {
+        self.superseded + self.overflowed + self.decimated + self.expired
+    }
+}
+
+/// Admits, queues and drops scans according to a [`GovernorPolicy`]
+///
+/// # Example
+/// ```
+/// use octaindex3d::clock::SimClock;
+/// use octaindex3d::layers::{GovernorConfig, GovernorPolicy, IntegrationGovernor};
+/// use std::sync::Arc;
+///
+/// # fn main() -> octaindex3d::Result<()> {
+/// let clock = SimClock::new(0.0);
+/// let config = GovernorConfig {
+///     policy: GovernorPolicy::LatestWins,
+///     ..GovernorConfig::default()
+/// };
+/// let mut governor = IntegrationGovernor::new(config).with_clock(Arc::new(clock.clone()));
+///
+/// // Three scans arrive while the mapper is busy
+/// for scan in 1..=3 {
+///     governor.offer(scan);
+///     clock.advance(0.1);
+/// }
+/// // Only the latest is integrated
+/// let integrated = governor.integrate(|scan| Ok(scan))?;
+/// assert_eq!(integrated, Some(3));
+/// assert_eq!(governor.stats().superseded, 2);
+/// # Ok(())
+/// # }
+/// ```
+pub struct IntegrationGovernor<T> {
+    config: GovernorConfig,
+    clock: Arc<dyn Clock>,
+    /// Waiting scans with their arrival time
+    queue: VecDeque<(f64, T)>,
+    stats: GovernorStats,
+    /// Time of the previous arrival
+    last_arrival: Option<f64>,
+    /// Smoothed time between arrivals (seconds)
+    interval: Option<f64>,
+    /// Smoothed integration time per scan (seconds)
+    cost: Option<f64>,
+    /// Current decimation factor
+    factor: u32,
+}
+
+impl<T> IntegrationGovernor<T> {
+    /// Create a governor reading the wall clock
+    pub fn new(config: GovernorConfig) -> Self {
+        Self {
+            config,
+            clock: Arc::new(WallClock::new()),
+            queue: VecDeque::new(),
+            stats: GovernorStats::default(),
+            last_arrival: None,
+            interval: None,
+            cost: None,
+            factor: 1,
+        }
+    }
+
+    /// Read the time from `clock` instead of the wall clock
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Settings
+    pub fn config(&self) -> &GovernorConfig {
+        &self.config
+    }
+
+    /// Scans handled so far
+    pub fn stats(&self) -> GovernorStats {
+        self.stats
+    }
+
+    /// Number of scans waiting
+    pub fn pending(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Current decimation factor, 1 outside [`GovernorPolicy::Decimate`]
+    pub fn decimation(&self) -> u32 {
+        self.factor
+    }
+
+    /// Smoothed fraction of time spent integrating, once measured
+    pub fn load(&self) -> Option<f64> {
+        Some(self.cost? / self.interval?.max(f64::EPSILON))
+    }
+
+    /// Hand over an arriving scan, returning whether it was admitted
+    ///
+    /// An admitted scan may still be dropped later, when superseded,
+    /// pushed out of a full queue or expired.
+    pub fn offer(&mut self, scan: T) -> bool {
+        let now = self.clock.now();
+        self.stats.offered += 1;
+        if let Some(last) = self.last_arrival {
+            self.interval = Some(self.smooth(self.interval, now - last));
+        }
+        self.last_arrival = Some(now);
+
+        let capacity = match self.config.policy {
+            GovernorPolicy::LatestWins => 1,
+            GovernorPolicy::Queue { capacity } => capacity,
+            GovernorPolicy::Decimate { capacity, .. } => {
+                // Arrivals are numbered from 0, so the first one is admitted
+                if (self.stats.offered - 1) % self.factor as u64 != 0 {
+                    self.stats.decimated += 1;
+                    return false;
+                }
+                capacity
+            }
+        };
+        if capacity == 0 {
+            self.stats.overflowed += 1;
+            return false;
+        }
+        while self.queue.len() >= capacity {
+            self.queue.pop_front();
+            match self.config.policy {
+                GovernorPolicy::LatestWins => self.stats.superseded += 1,
+                _ => self.stats.overflowed += 1,
+            }
+        }
+        self.queue.push_back((now, scan));
+        true
+    }
+
+    /// Take the next scan to integrate, dropping expired ones
+    ///
+    /// Report how long integrating it took with
+    /// [`record_integration`](Self::record_integration), or use
+    /// [`integrate`](Self::integrate), which does both.
+    pub fn next_scan(&mut self) -> Option<T> {
+        let now = self.clock.now();
+        while let Some((arrival, scan)) = self.queue.pop_front() {
+            if self
+                .config
+                .max_latency
+                .is_some_and(|latency| now - arrival > latency)
+            {
+                self.stats.expired += 1;
+                continue;
+            }
+            return Some(scan);
+        }
+        None
+    }
+
+    /// Integrate the next scan with `integrate`, if one is waiting
+    ///
+    /// The time `integrate` takes, measured with [`Instant`], is passed to
+    /// [`record_integration`](Self::record_integration). Errors are
+    /// returned as they are; the scan counts as integrated either way.
+    pub fn integrate<R, F>(&mut self, integrate: F) -> Result<Option<R>>
+    where
+        F: FnOnce(T) -> Result<R>,
+    {
+        let Some(scan) = self.next_scan() else {
+            return Ok(None);
+        };
+        let start = Instant::now();
+        let result = integrate(scan);
+        self.record_integration(start.elapsed());
+        result.map(Some)
+    }
+
+    /// Count a scan taken with [`next_scan`](Self::next_scan) as
+    /// integrated in `elapsed`
+    ///
+    /// Updates the load estimate and, under [`GovernorPolicy::Decimate`],
+    /// the decimation factor.
+    pub fn record_integration(&mut self, elapsed: Duration) {
+        self.cost = Some(self.smooth(self.cost, elapsed.as_secs_f64()));
+        self.stats.integrated += 1;
+        self.adapt();
+    }
+
+    /// Exponential moving average of `previous` and `sample`
+    fn smooth(&self, previous: Option<f64>, sample: f64) -> f64 {
+        let alpha = self.config.smoothing.clamp(f64::EPSILON, 1.0);
+        match previous {
+            Some(previous) => previous + alpha * (sample - previous),
+            None => sample,
+        }
+    }
+
+    /// Choose the decimation factor for the measured load
+    fn adapt(&mut self) {
+        let GovernorPolicy::Decimate {
+            target_load,
+            max_factor,
+            ..
+        } = self.config.policy
+        else {
+            return;
+        };
+        let Some(load) = self.load() else {
+            return;
+        };
+        // Each admitted scan may take `target_load` of k arrival intervals
+        let factor = (load / target_load.max(f64::EPSILON)).ceil();
+        self.factor = (factor.min(max_factor.max(1) as f64) as u32).max(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SimClock;
+
+    fn governor(config: GovernorConfig) -> (SimClock, IntegrationGovernor<u32>) {
+        let clock = SimClock::new(0.0);
+        let governor = IntegrationGovernor::new(config).with_clock(Arc::new(clock.clone()));
+        (clock, governor)
+    }
+
+    #[test]
+    fn test_queue_overflow_and_expiry() -> Result<()> {
+        let (clock, mut governor) = governor(GovernorConfig {
+            policy: GovernorPolicy::Queue { capacity: 3 },
+            max_latency: Some(0.25),
+            ..GovernorConfig::default()
+        });
+        for scan in 0..5 {
+            assert!(governor.offer(scan));
+            clock.advance(0.1);
+        }
+        // Scans 0 and 1 were pushed out; scan 2 has waited 0.3 s
+        assert_eq!(governor.pending(), 3);
+        assert_eq!(governor.integrate(Ok)?, Some(3));
+        assert_eq!(governor.integrate(Ok)?, Some(4));
+        assert_eq!(governor.integrate(Ok)?, None);
+
+        let stats = governor.stats();
+        assert_eq!(
+            (stats.overflowed, stats.expired, stats.integrated),
+            (2, 1, 2)
+        );
+        assert_eq!(stats.dropped() + stats.integrated, stats.offered);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decimation_follows_load() -> Result<()> {
+        let (clock, mut governor) = governor(GovernorConfig {
+            policy: GovernorPolicy::Decimate {
+                capacity: 4,
+                target_load: 0.8,
+                max_factor: 8,
+            },
+            smoothing: 1.0,
+            ..GovernorConfig::default()
+        });
+        let integrate = |governor: &mut IntegrationGovernor<u32>, seconds: f64| {
+            let scan = governor.next_scan();
+            governor.record_integration(Duration::from_secs_f64(seconds));
+            scan
+        };
+
+        // Integration three times slower than arrivals
+        governor.offer(0);
+        clock.advance(0.1);
+        governor.offer(1);
+        assert_eq!(integrate(&mut governor, 0.3), Some(0));
+        // ceil(3.0 / 0.8)
+        assert_eq!(governor.decimation(), 4);
+        for scan in 2..10 {
+            clock.advance(0.1);
+            governor.offer(scan);
+        }
+        // Only arrivals 4 and 8 were admitted after the factor changed
+        assert_eq!(governor.stats().decimated, 6);
+        assert_eq!(governor.pending(), 3);
+
+        // Cheap integration brings the factor back down
+        assert_eq!(integrate(&mut governor, 0.05), Some(1));
+        assert_eq!(governor.decimation(), 1);
+
+        // Integration time is measured, not read from the clock
+        governor.integrate(|scan| {
+            clock.advance(100.0);
+            Ok(scan)
+        })?;
+        assert_eq!(governor.decimation(), 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,422 @@
+//! Occupancy ingestion from sensor message streams
+//!
+//! [`SensorIngest`] turns ROS 2 messages, CDR encoded as DDS and Zenoh
+//! carry them, into occupancy updates: a
+//! [`PoseStamped`](super::ros2::PoseStamped) sets the sensor pose, and each
+//! [`PointCloud2`](super::ros2::PointCloud2) in the sensor frame is moved
+//! by the latest pose and integrated with
+//! [`OccupancyLayer::integrate_point_cloud`].
+//!
+//! With the `zenoh` feature, [`ZenohIngest`] subscribes to a point cloud
+//! and a pose key expression, for example the topics a
+//! `zenoh-bridge-ros2dds` forwards from a DDS robot, and feeds the samples
+//! it has received to a [`SensorIngest`] on each poll.
+//!
+//! When clouds arrive faster than they can be integrated, put an
+//! [`IntegrationGovernor`](super::IntegrationGovernor) in front of
+//! [`SensorIngest::integrate_cloud`] to drop stale clouds instead of
+//! queueing them without bound.
+
+use super::occupancy::OccupancyLayer;
+use super::ros2_bridge::{Header, PointCloud2, Pose, PoseStamped};
+use crate::error::Result;
+
+/// Ray integration settings of a [`SensorIngest`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IngestConfig {
+    /// Voxel edge in meters
+    pub voxel_size: f32,
+    /// Confidence of free observations along each ray
+    pub free_confidence: f32,
+    /// Confidence of the occupied observation at each hit
+    pub occupied_confidence: f32,
+    /// Points farther than this from the sensor are ignored (meters)
+    pub max_range: f32,
+}
+
+impl Default for IngestConfig {
+    fn default() -> Self {
+        Self {
+            voxel_size: 0.1,
+            free_confidence: 0.7,
+            occupied_confidence: 0.9,
+            max_range: 30.0,
+        }
+    }
+}
+
+/// Messages handled by a [`SensorIngest`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IngestStats {
+    /// Poses received
+    pub poses: u64,
+    /// Point clouds integrated
+    pub clouds: u64,
+    /// Points integrated as rays
+    pub points: u64,
+    /// Point clouds dropped because no pose had arrived yet
+    pub dropped_clouds: u64,
+}
+
+/// Integrates posed point clouds into an occupancy layer
+///
+/// # Example
+///
+/// ```
+/// use octaindex3d::layers::ingest::SensorIngest;
+/// use octaindex3d::layers::ros2::{Header, Point, PointCloud2, Pose, Quaternion, Time};
+/// use octaindex3d::layers::OccupancyLayer;
+///
+/// # fn main() -> octaindex3d::Result<()> {
+/// let mut ingest = SensorIngest::default();
+/// let mut layer = OccupancyLayer::new();
+/// ingest.set_pose(Pose {
+///     position: Point { x: 5.0, y: 5.0, z: 5.0 },
+///     orientation: Quaternion { x: 0.0, y: 0.0, z: 0.0, w: 1.0 },
+/// });
+///
+/// // Wire bytes as received from a middleware
+/// let cloud = PointCloud2::from_occupied_voxels(vec![(2.0, 0.0, 0.0)], "lidar");
+/// assert_eq!(ingest.handle_cloud(&cloud.to_cdr(), &mut layer)?, 1);
+/// assert!(layer.stats().occupied_count > 0);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SensorIngest {
+    config: IngestConfig,
+    pose: Option<Pose>,
+    stats: IngestStats,
+}
+
+impl SensorIngest {
+    /// Create with no pose yet
+    pub fn new(config: IngestConfig) -> Self {
+        Self {
+            config,
+            pose: None,
+            stats: IngestStats::default(),
+        }
+    }
+
+    /// Integration settings
+    pub fn config(&self) -> &IngestConfig {
+        &self.config
+    }
+
+    /// Latest sensor pose
+    pub fn pose(&self) -> Option<&Pose> {
+        self.pose.as_ref()
+    }
+
+    /// Messages handled so far
+    pub fn stats(&self) -> IngestStats {
+        self.stats
+    }
+
+    /// Set the sensor pose used for later clouds
+    pub fn set_pose(&mut self, pose: Pose) {
+        self.pose = Some(pose);
+        self.stats.poses += 1;
+    }
+
+    /// Decode a CDR `geometry_msgs/PoseStamped` and make it the sensor pose
+    pub fn handle_pose(&mut self, cdr: &[u8]) -> Result<()> {
+        self.set_pose(PoseStamped::from_cdr(cdr)?.pose);
+        Ok(())
+    }
+
+    /// Decode a CDR `sensor_msgs/PointCloud2` and integrate it, returning
+    /// the number of points integrated
+    pub fn handle_cloud(&mut self, cdr: &[u8], layer: &mut OccupancyLayer) -> Result<usize> {
+        self.integrate_cloud(&PointCloud2::from_cdr(cdr)?, layer)
+    }
+
+    /// Apply poses and integrate clouds in stamp order, returning the
+    /// number of points integrated
+    ///
+    /// For messages collected from separate streams, whose relative
+    /// arrival order is lost: each cloud is placed at the latest pose
+    /// stamped at or before it. Messages with equal stamps keep their
+    /// order, and a pose goes before a cloud with its stamp.
+    pub fn integrate_in_order(
+        &mut self,
+        mut poses: Vec<PoseStamped>,
+        mut clouds: Vec<PointCloud2>,
+        layer: &mut OccupancyLayer,
+    ) -> Result<usize> {
+        let stamp = |header: &Header| (header.stamp.sec, header.stamp.nanosec);
+        poses.sort_by_key(|pose| stamp(&pose.header));
+        clouds.sort_by_key(|cloud| stamp(&cloud.header));
+        let mut poses = poses.into_iter().peekable();
+        let mut points = 0;
+        for cloud in &clouds {
+            while let Some(pose) = poses.next_if(|p| stamp(&p.header) <= stamp(&cloud.header)) {
+                self.set_pose(pose.pose);
+            }
+            points += self.integrate_cloud(cloud, layer)?;
+        }
+        for pose in poses {
+            self.set_pose(pose.pose);
+        }
+        Ok(points)
+    }
+
+    /// Integrate a cloud in the sensor frame at the latest pose, returning
+    /// the number of points integrated
+    ///
+    /// The points are moved into the map frame and integrated with
+    /// [`OccupancyLayer::integrate_point_cloud`], so each voxel is updated
+    /// once per cloud. Without a pose the cloud is dropped and counted in
+    /// [`IngestStats::dropped_clouds`].
+    pub fn integrate_cloud(
+        &mut self,
+        cloud: &PointCloud2,
+        layer: &mut OccupancyLayer,
+    ) -> Result<usize> {
+        let Some(pose) = &self.pose else {
+            self.stats.dropped_clouds += 1;
+            return Ok(0);
+        };
+        let origin = (
+            pose.position.x as f32,
+            pose.position.y as f32,
+            pose.position.z as f32,
+        );
+        let points: Vec<(f32, f32, f32)> = cloud
+            .points()?
+            .into_iter()
+            .map(|(x, y, z)| {
+                let (wx, wy, wz) = pose.transform_point((x as f64, y as f64, z as f64));
+                (wx as f32, wy as f32, wz as f32)
+            })
+            .collect();
+        let integrated = layer.integrate_point_cloud(&points, origin, &self.config)?;
+        self.stats.clouds += 1;
+        self.stats.points += integrated as u64;
+        Ok(integrated)
+    }
+}
+
+/// Zenoh subscriber feeding a [`SensorIngest`]
+///
+/// Samples queue in the subscribers until [`Self::poll`]; a poll applies
+/// the queued poses and integrates the queued clouds in stamp order, as
+/// [`SensorIngest::integrate_in_order`] does.
+#[cfg(feature = "zenoh")]
+pub struct ZenohIngest {
+    ingest: SensorIngest,
+    clouds: SampleSubscriber,
+    poses: SampleSubscriber,
+    _session: zenoh::Session,
+}
+
+#[cfg(feature = "zenoh")]
+type SampleSubscriber =
+    zenoh::pubsub::Subscriber<zenoh::handlers::FifoChannelHandler<zenoh::sample::Sample>>;
+
+#[cfg(feature = "zenoh")]
+impl ZenohIngest {
+    /// Open a session and subscribe to point clouds on `cloud_key` and
+    /// poses on `pose_key`
+    pub fn open(
+        config: zenoh::Config,
+        cloud_key: &str,
+        pose_key: &str,
+        ingest: IngestConfig,
+    ) -> Result<Self> {
+        use zenoh::Wait;
+
+        let session = zenoh::open(config).wait().map_err(zenoh_error)?;
+        let clouds = session
+            .declare_subscriber(cloud_key.to_string())
+            .wait()
+            .map_err(zenoh_error)?;
+        let poses = session
+            .declare_subscriber(pose_key.to_string())
+            .wait()
+            .map_err(zenoh_error)?;
+        Ok(Self {
+            ingest: SensorIngest::new(ingest),
+            clouds,
+            poses,
+            _session: session,
+        })
+    }
+
+    /// Integrate everything received since the last poll, returning the
+    /// number of points integrated
+    pub fn poll(&mut self, layer: &mut OccupancyLayer) -> Result<usize> {
+        let mut poses = Vec::new();
+        while let Some(sample) = self.poses.try_recv().map_err(zenoh_error)? {
+            poses.push(PoseStamped::from_cdr(&sample.payload().to_bytes())?);
+        }
+        let mut clouds = Vec::new();
+        while let Some(sample) = self.clouds.try_recv().map_err(zenoh_error)? {
+            clouds.push(PointCloud2::from_cdr(&sample.payload().to_bytes())?);
+        }
+        self.ingest.integrate_in_order(poses, clouds, layer)
+    }
+
+    /// The ingestion state
+    pub fn ingest(&self) -> &SensorIngest {
+        &self.ingest
+    }
+}
+
+#[cfg(feature = "zenoh")]
+fn zenoh_error(e: zenoh::Error) -> crate::error::Error {
+    crate::error::Error::Io(format!("zenoh: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layers::ros2::{Header, Point, Quaternion, Time};
+
+    #[test]
+    fn test_clouds_follow_poses() {
+        let mut ingest = SensorIngest::new(IngestConfig {
+            max_range: 10.0,
+            ..IngestConfig::default()
+        });
+        let mut layer = OccupancyLayer::new();
+        let cloud = PointCloud2::from_occupied_voxels(
+            vec![(1.0, 0.0, 0.0), (0.0, 2.0, 0.0), (50.0, 0.0, 0.0)],
+            "lidar",
+        )
+        .to_cdr();
+
+        // Clouds before the first pose cannot be placed
+        assert_eq!(ingest.handle_cloud(&cloud, &mut layer).unwrap(), 0);
+        assert_eq!(layer.stats().total_voxels, 0);
+
+        let pose = PoseStamped {
+            header: Header {
+                stamp: Time::new(1, 0),
+                frame_id: "map".to_string(),
+            },
+            pose: Pose {
+                position: Point {
+                    x: 3.0,
+                    y: 3.0,
+                    z: 3.0,
+                },
+                orientation: Quaternion {
+                    x: 0.0,
+                    y: 0.0,
+                    z: 0.0,
+                    w: 1.0,
+                },
+            },
+        };
+        ingest.handle_pose(&pose.to_cdr()).unwrap();
+        // The far point is out of range
+        assert_eq!(ingest.handle_cloud(&cloud, &mut layer).unwrap(), 2);
+        assert!(layer.stats().occupied_count > 0);
+        assert_eq!(
+            ingest.stats(),
+            IngestStats {
+                poses: 1,
+                clouds: 1,
+                points: 2,
+                dropped_clouds: 1,
+            }
+        );
+        assert!(ingest.handle_pose(&pose.to_cdr()[..20]).is_err());
+    }
+
+    #[cfg(feature = "zenoh")]
+    #[test]
+    fn test_zenoh_loopback() {
+        use zenoh::Wait;
+
+        let config = zenoh::Config::from_json5(
+            r#"{ mode: "peer", listen: { endpoints: [] }, scouting: { multicast: { enabled: false } } }"#,
+        )
+        .unwrap();
+        let mut ingest =
+            ZenohIngest::open(config, "robot/cloud", "robot/pose", IngestConfig::default())
+                .unwrap();
+
+        let pose = Pose {
+            position: Point {
+                x: 3.0,
+                y: 3.0,
+                z: 3.0,
+            },
+            orientation: Quaternion {
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+                w: 1.0,
+            },
+        };
+        let pose = PoseStamped {
+            header: Header {
+                stamp: Time::new(1, 0),
+                frame_id: "map".to_string(),
+            },
+            pose,
+        };
+        let cloud = PointCloud2::from_occupied_voxels(vec![(1.0, 1.0, 0.0)], "lidar");
+        let session = &ingest._session;
+        session.put("robot/pose", pose.to_cdr()).wait().unwrap();
+        session.put("robot/cloud", cloud.to_cdr()).wait().unwrap();
+
+        let mut layer = OccupancyLayer::new();
+        let mut points = 0;
+        for _ in 0..100 {
+            points += ingest.poll(&mut layer).unwrap();
+            if points > 0 {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert_eq!(points, 1);
+        assert_eq!(ingest.ingest().stats().poses, 1);
+    }
+
+    #[test]
+    fn test_interleaved_streams_follow_stamps() {
+        let pose_at = |sec: i32, x: f64| PoseStamped {
+            header: Header {
+                stamp: Time::new(sec, 0),
+                frame_id: "map".to_string(),
+            },
+            pose: Pose {
+                position: Point { x, y: 3.0, z: 3.0 },
+                orientation: Quaternion {
+                    x: 0.0,
+                    y: 0.0,
+                    z: 0.0,
+                    w: 1.0,
+                },
+            },
+        };
+        let cloud_at = |sec: i32| {
+            let mut cloud = PointCloud2::from_occupied_voxels(vec![(1.0, 0.0, 0.0)], "lidar");
+            cloud.header.stamp = Time::new(sec, 0);
+            cloud
+        };
+
+        // Each stream in its own order, as two subscribers deliver them
+        let mut ingest = SensorIngest::default();
+        let mut layer = OccupancyLayer::new();
+        let points = ingest
+            .integrate_in_order(
+                vec![pose_at(3, 6.0), pose_at(1, 3.0)],
+                vec![cloud_at(4), cloud_at(2), cloud_at(0)],
+                &mut layer,
+            )
+            .unwrap();
+        assert_eq!(points, 2);
+        // The clouds hit x = 4 and x = 7, not twice at the last pose
+        assert_eq!(layer.stats().occupied_count, 2);
+        assert_eq!(ingest.pose().unwrap().position.x, 6.0);
+        assert_eq!(
+            (ingest.stats().poses, ingest.stats().dropped_clouds),
+            (2, 1)
+        );
+    }
+}
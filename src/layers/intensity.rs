@@ -0,0 +1,235 @@
+//! Per-voxel LiDAR intensity layer
+//!
+//! Accumulates the reflectivity returned by each voxel: a confidence
+//! weighted running mean and the strongest return seen. Intensities are
+//! normalized reflectivity in `[0, 1]`; values outside are clamped.
+//!
+//! The layer shares its indices with occupancy, so reflectivity features
+//! for localization can be read for exactly the occupied voxels (see
+//! [`LayeredMap::query_intensity`](super::LayeredMap::query_intensity)).
+//! Checkpoints store mean and maximum with 16-bit precision.
+
+use super::state::{decode_voxels, encode_voxels};
+use super::{DirtyTracker, Layer, LayerType, Measurement, MeasurementType};
+use crate::error::{Error, Result};
+use crate::Index64;
+use std::collections::HashMap;
+
+/// Voxel data in intensity layer
+#[derive(Debug, Clone, Copy)]
+struct IntensityVoxel {
+    /// Weighted mean intensity
+    mean: f32,
+    /// Strongest return
+    max: f32,
+    /// Cumulative weight for averaging
+    weight: f32,
+}
+
+impl IntensityVoxel {
+    fn pack(&self) -> [u32; 2] {
+        let quantize = |v: f32| (v * u16::MAX as f32).round() as u32;
+        [
+            quantize(self.mean) | quantize(self.max) << 16,
+            self.weight.to_bits(),
+        ]
+    }
+
+    fn unpack([values, weight]: [u32; 2]) -> Self {
+        let restore = |v: u32| (v & 0xFFFF) as f32 / u16::MAX as f32;
+        Self {
+            mean: restore(values),
+            max: restore(values >> 16),
+            weight: f32::from_bits(weight),
+        }
+    }
+}
+
+/// Intensity layer storing mean and maximum LiDAR reflectivity per voxel
+pub struct IntensityLayer {
+    /// Voxel storage
+    voxels: HashMap<Index64, IntensityVoxel>,
+    /// Chunks changed since consumers last looked
+    dirty: DirtyTracker,
+}
+
+impl IntensityLayer {
+    /// Create an empty intensity layer
+    pub fn new() -> Self {
+        Self {
+            voxels: HashMap::new(),
+            dirty: DirtyTracker::default(),
+        }
+    }
+
+    /// Fuse an intensity return of `idx` with weight `confidence`
+    ///
+    /// # Example
+    /// ```
+    /// use octaindex3d::layers::IntensityLayer;
+    /// use octaindex3d::Index64;
+    ///
+    /// # fn main() -> octaindex3d::Result<()> {
+    /// let mut intensity = IntensityLayer::new();
+    /// let idx = Index64::new(0, 0, 5, 100, 100, 100)?;
+    /// intensity.integrate(idx, 0.2, 1.0);
+    /// intensity.integrate(idx, 0.6, 1.0);
+    /// assert!((intensity.get_mean(idx).unwrap() - 0.4).abs() < 1e-6);
+    /// assert_eq!(intensity.get_max(idx), Some(0.6));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn integrate(&mut self, idx: Index64, value: f32, confidence: f32) {
+        let value = value.clamp(0.0, 1.0);
+        self.dirty.mark(idx);
+        let voxel = self.voxels.entry(idx).or_insert(IntensityVoxel {
+            mean: value,
+            max: value,
+            weight: 0.0,
+        });
+
+        let new_weight = voxel.weight + confidence;
+        if voxel.weight > 0.0 && new_weight > 0.0 {
+            voxel.mean = (voxel.mean * voxel.weight + value * confidence) / new_weight;
+        } else {
+            voxel.mean = value;
+        }
+        voxel.max = voxel.max.max(value);
+        voxel.weight = new_weight;
+    }
+
+    /// Mean intensity of a voxel
+    pub fn get_mean(&self, idx: Index64) -> Option<f32> {
+        self.voxels.get(&idx).map(|v| v.mean)
+    }
+
+    /// Strongest return of a voxel
+    pub fn get_max(&self, idx: Index64) -> Option<f32> {
+        self.voxels.get(&idx).map(|v| v.max)
+    }
+
+    /// Accumulated weight of a voxel
+    pub fn get_weight(&self, idx: Index64) -> Option<f32> {
+        self.voxels.get(&idx).map(|v| v.weight)
+    }
+
+    fn merge_records(&mut self, records: Vec<(Index64, [u32; 2])>) {
+        for (idx, record) in records {
+            self.dirty.mark(idx);
+            self.voxels.insert(idx, IntensityVoxel::unpack(record));
+        }
+    }
+}
+
+impl Default for IntensityLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Layer for IntensityLayer {
+    fn layer_type(&self) -> LayerType {
+        LayerType::Intensity
+    }
+
+    fn update(&mut self, idx: Index64, measurement: &Measurement) -> Result<()> {
+        match measurement.measurement_type {
+            MeasurementType::Intensity => {
+                let value = measurement.as_intensity()?;
+                self.integrate(idx, value, measurement.confidence);
+                Ok(())
+            }
+            _ => Err(Error::InvalidFormat(
+                "Intensity layer requires intensity measurements".to_string(),
+            )),
+        }
+    }
+
+    /// Mean intensity
+    fn query(&self, idx: Index64) -> Option<f32> {
+        self.get_mean(idx)
+    }
+
+    fn voxel_count(&self) -> usize {
+        self.voxels.len()
+    }
+
+    fn indices(&self) -> Box<dyn Iterator<Item = Index64> + '_> {
+        Box::new(self.voxels.keys().copied())
+    }
+
+    fn clear(&mut self) {
+        self.dirty.mark_all(self.voxels.keys().copied());
+        self.voxels.clear();
+    }
+
+    fn dirty_tracker(&self) -> Option<&DirtyTracker> {
+        Some(&self.dirty)
+    }
+
+    fn dirty_tracker_mut(&mut self) -> Option<&mut DirtyTracker> {
+        Some(&mut self.dirty)
+    }
+
+    fn encode_state(&self) -> Option<Vec<u8>> {
+        let records = self
+            .voxels
+            .iter()
+            .map(|(&idx, voxel)| (idx, voxel.pack()))
+            .collect();
+        Some(encode_voxels(records))
+    }
+
+    fn encode_cells(&self, cells: &[Index64]) -> Option<Vec<u8>> {
+        let records = cells
+            .iter()
+            .filter_map(|&idx| Some((idx, self.voxels.get(&idx)?.pack())))
+            .collect();
+        Some(encode_voxels(records))
+    }
+
+    fn decode_state(&mut self, bytes: &[u8]) -> Result<()> {
+        let records = decode_voxels(bytes)?;
+        self.clear();
+        self.merge_records(records);
+        Ok(())
+    }
+
+    fn merge_state(&mut self, bytes: &[u8]) -> Result<()> {
+        self.merge_records(decode_voxels(bytes)?);
+        Ok(())
+    }
+
+    fn memory_usage(&self) -> usize {
+        // Rough estimate: 24 bytes per entry (HashMap overhead) + 8 bytes (Index64) + 12 bytes (IntensityVoxel)
+        self.voxels.len() * 44
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_running_mean_max_and_state() -> Result<()> {
+        let mut intensity = IntensityLayer::new();
+        let idx = Index64::new(0, 0, 5, 100, 100, 100)?;
+        intensity.integrate(idx, 0.8, 1.0);
+        intensity.update(idx, &Measurement::intensity(0.2, 0.5))?;
+        intensity.integrate(idx, 1.7, 0.5);
+        // (0.8 * 1 + 0.2 * 0.5 + 1.0 * 0.5) / 2
+        assert!((intensity.query(idx).unwrap() - 0.7).abs() < 1e-6);
+        assert_eq!(intensity.get_max(idx), Some(1.0));
+        assert_eq!(intensity.get_weight(idx), Some(2.0));
+        assert!(intensity
+            .update(idx, &Measurement::color(1, 2, 3, 1.0))
+            .is_err());
+
+        let mut restored = IntensityLayer::new();
+        restored.decode_state(&intensity.encode_state().unwrap())?;
+        assert!((restored.get_mean(idx).unwrap() - 0.7).abs() < 1e-4);
+        assert_eq!(restored.get_max(idx), Some(1.0));
+        assert_eq!(restored.encode_state(), intensity.encode_state());
+        Ok(())
+    }
+}
@@ -0,0 +1,133 @@
+//! Expanding-shell k-nearest-neighbor search over layer voxels
+//!
+//! Visits BCC lattice points around the query in shells of growing
+//! Chebyshev radius, keeping the `k` best hits in a bounded max-heap.
+//! Every point of shell `r` is at least `r` lattice units from the query,
+//! so the search stops as soon as the k-th best distance is within the next
+//! shell's radius.
+
+use crate::Index64;
+use ordered_float::OrderedFloat;
+use std::collections::BinaryHeap;
+
+/// Bounded max-heap of the `k` nearest hits seen so far
+pub(crate) struct KnnHeap {
+    k: usize,
+    heap: BinaryHeap<(OrderedFloat<f32>, Index64)>,
+}
+
+impl KnnHeap {
+    pub(crate) fn new(k: usize) -> Self {
+        Self {
+            k,
+            heap: BinaryHeap::with_capacity(k + 1),
+        }
+    }
+
+    pub(crate) fn offer(&mut self, idx: Index64, distance: f32) {
+        if self.k == 0 {
+            return;
+        }
+        if self.heap.len() < self.k {
+            self.heap.push((OrderedFloat(distance), idx));
+        } else if let Some(&(worst, _)) = self.heap.peek() {
+            if OrderedFloat(distance) < worst {
+                self.heap.pop();
+                self.heap.push((OrderedFloat(distance), idx));
+            }
+        }
+    }
+
+    fn kth_distance(&self) -> Option<f32> {
+        if self.heap.len() < self.k {
+            None
+        } else {
+            self.heap.peek().map(|e| e.0 .0)
+        }
+    }
+
+    /// Hits sorted by increasing distance (ties by index)
+    pub(crate) fn into_sorted(self) -> Vec<(Index64, f32)> {
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|(d, idx)| (idx, d.0))
+            .collect()
+    }
+}
+
+/// Euclidean distance between two voxels in lattice units
+pub(crate) fn voxel_distance(a: Index64, b: Index64) -> f32 {
+    let (ax, ay, az) = a.decode_coords();
+    let (bx, by, bz) = b.decode_coords();
+    let dx = ax as f32 - bx as f32;
+    let dy = ay as f32 - by as f32;
+    let dz = az as f32 - bz as f32;
+    (dx * dx + dy * dy + dz * dz).sqrt()
+}
+
+/// Shell search around `query` for voxels accepted by `is_hit`
+///
+/// Searches up to `max_radius` lattice units and stops early once more than
+/// `budget` lattice points have been probed. Returns the hits and whether
+/// the search completed (i.e. the result is exact within `max_radius`).
+pub(crate) fn shell_search<F>(
+    query: Index64,
+    k: usize,
+    max_radius: u32,
+    budget: usize,
+    mut is_hit: F,
+) -> (Vec<(Index64, f32)>, bool)
+where
+    F: FnMut(Index64) -> bool,
+{
+    let mut best = KnnHeap::new(k);
+    if k == 0 {
+        return (Vec::new(), true);
+    }
+
+    let (qx, qy, qz) = query.decode_coords();
+    let (qx, qy, qz) = (qx as i32, qy as i32, qz as i32);
+    let coord = |v: i32| u16::try_from(v).ok();
+    let mut probed = 0usize;
+
+    for r in 0..=max_radius.min(u16::MAX as u32) as i32 {
+        if best.kth_distance().is_some_and(|d| d <= r as f32) {
+            return (best.into_sorted(), true);
+        }
+
+        for dx in -r..=r {
+            for dy in -r..=r {
+                let on_face = dx.abs() == r || dy.abs() == r;
+                let dz_step = if on_face { 1 } else { (2 * r).max(1) as usize };
+                for dz in (-r..=r).step_by(dz_step) {
+                    // BCC offsets have all-even or all-odd components
+                    if (dx - dy) % 2 != 0 || (dy - dz) % 2 != 0 {
+                        continue;
+                    }
+                    let (Some(x), Some(y), Some(z)) =
+                        (coord(qx + dx), coord(qy + dy), coord(qz + dz))
+                    else {
+                        continue;
+                    };
+                    let Ok(idx) =
+                        Index64::new(query.frame_id(), query.scale_tier(), query.lod(), x, y, z)
+                    else {
+                        continue;
+                    };
+
+                    probed += 1;
+                    if is_hit(idx) {
+                        best.offer(idx, ((dx * dx + dy * dy + dz * dz) as f32).sqrt());
+                    }
+                }
+            }
+        }
+
+        if probed > budget {
+            return (best.into_sorted(), false);
+        }
+    }
+
+    (best.into_sorted(), true)
+}
@@ -0,0 +1,424 @@
+//! Embedded key-value persistence for continuously mutated maps
+//!
+//! [`KvMap`] wraps a [`LayeredMap`] whose voxels live in a [`ChunkStore`],
+//! one value per chunk of 8×8×8 cells. Integration calls note the chunks
+//! they touch and [`KvMap::commit`] rewrites only those, in a single
+//! transaction, so services that mutate a map all day never rewrite the
+//! whole map the way container save/load or [`WalMap`](super::WalMap)
+//! checkpoints do.
+//!
+//! Stores:
+//!
+//! - [`MemoryChunkStore`]: a sorted in-memory map, for tests and caches
+//! - [`RedbChunkStore`]: a [redb](https://docs.rs/redb) database file,
+//!   behind the `kv_redb` feature
+//!
+//! ## Layout
+//!
+//! A chunk's key is the layer tag followed by the raw index of the chunk's
+//! first cell, big endian so each layer's chunks are contiguous and in
+//! Morton order. Its value is the layer's
+//! [`encode_cells`](super::Layer::encode_cells) state for the chunk's
+//! voxels; empty chunks are deleted. Layer configurations are stored under
+//! `[0xFF, layer tag]` as [`LayerConfig::to_bytes`].
+
+use super::soa::{join, split, BLOCK_CELLS};
+use super::{ConfigUpgrades, LayerConfig, LayerType, LayeredMap, Measurement};
+use crate::error::{Error, Result};
+use crate::Index64;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Leading key byte of layer configurations; chunk keys lead with the
+/// layer tag instead
+const CONFIG_KEY: u8 = 0xFF;
+
+/// Puts (`Some`) and deletes (`None`) applied together
+pub type WriteBatch = Vec<(Vec<u8>, Option<Vec<u8>>)>;
+
+/// Ordered byte key-value store holding map chunks
+pub trait ChunkStore {
+    /// Value stored under `key`
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>>;
+
+    /// Entries whose key starts with `prefix`, in key order
+    fn scan(&self, prefix: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>>;
+
+    /// Apply a batch atomically: after a crash either all of it or none of
+    /// it is visible
+    fn write_batch(&mut self, batch: WriteBatch) -> Result<()>;
+}
+
+/// [`ChunkStore`] in memory
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryChunkStore {
+    entries: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+impl MemoryChunkStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of stored entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether nothing is stored
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl ChunkStore for MemoryChunkStore {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        Ok(self.entries.get(key).cloned())
+    }
+
+    fn scan(&self, prefix: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        Ok(self
+            .entries
+            .range(prefix.to_vec()..)
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect())
+    }
+
+    fn write_batch(&mut self, batch: WriteBatch) -> Result<()> {
+        for (key, value) in batch {
+            match value {
+                Some(value) => self.entries.insert(key, value),
+                None => self.entries.remove(&key),
+            };
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "kv_redb")]
+const REDB_TABLE: redb::TableDefinition<&[u8], &[u8]> = redb::TableDefinition::new("chunks");
+
+/// [`ChunkStore`] in a redb database file
+#[cfg(feature = "kv_redb")]
+pub struct RedbChunkStore {
+    db: redb::Database,
+}
+
+#[cfg(feature = "kv_redb")]
+impl RedbChunkStore {
+    /// Open or create a database file
+    pub fn open<P: AsRef<std::path::Path>>(path: P) -> Result<Self> {
+        let db = redb::Database::create(path).map_err(redb::Error::from)?;
+        Ok(Self { db })
+    }
+
+    /// Use an already opened database; chunks go to its `chunks` table
+    pub fn from_database(db: redb::Database) -> Self {
+        Self { db }
+    }
+}
+
+#[cfg(feature = "kv_redb")]
+impl ChunkStore for RedbChunkStore {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let txn = self.db.begin_read().map_err(redb::Error::from)?;
+        let table = match txn.open_table(REDB_TABLE) {
+            Ok(table) => table,
+            Err(redb::TableError::TableDoesNotExist(_)) => return Ok(None),
+            Err(e) => return Err(redb::Error::from(e).into()),
+        };
+        let value = table.get(key).map_err(redb::Error::from)?;
+        Ok(value.map(|v| v.value().to_vec()))
+    }
+
+    fn scan(&self, prefix: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let txn = self.db.begin_read().map_err(redb::Error::from)?;
+        let table = match txn.open_table(REDB_TABLE) {
+            Ok(table) => table,
+            Err(redb::TableError::TableDoesNotExist(_)) => return Ok(Vec::new()),
+            Err(e) => return Err(redb::Error::from(e).into()),
+        };
+        let mut entries = Vec::new();
+        for entry in table.range(prefix..).map_err(redb::Error::from)? {
+            let (key, value) = entry.map_err(redb::Error::from)?;
+            if !key.value().starts_with(prefix) {
+                break;
+            }
+            entries.push((key.value().to_vec(), value.value().to_vec()));
+        }
+        Ok(entries)
+    }
+
+    fn write_batch(&mut self, batch: WriteBatch) -> Result<()> {
+        let txn = self.db.begin_write().map_err(redb::Error::from)?;
+        {
+            let mut table = txn.open_table(REDB_TABLE).map_err(redb::Error::from)?;
+            for (key, value) in &batch {
+                match value {
+                    Some(value) => table.insert(key.as_slice(), value.as_slice()),
+                    None => table.remove(key.as_slice()),
+                }
+                .map_err(redb::Error::from)?;
+            }
+        }
+        txn.commit().map_err(redb::Error::from)?;
+        Ok(())
+    }
+}
+
+/// [`LayeredMap`] persisted chunk by chunk in a [`ChunkStore`]
+///
+/// # Example
+///
+/// ```
+/// use octaindex3d::layers::{KvMap, LayeredMap, Measurement, MemoryChunkStore, OccupancyLayer};
+/// use octaindex3d::Index64;
+///
+/// # fn main() -> octaindex3d::Result<()> {
+/// let template = || {
+///     let mut map = LayeredMap::new();
+///     map.add_occupancy_layer(OccupancyLayer::new());
+///     map
+/// };
+/// let idx = Index64::new(0, 0, 8, 10, 10, 10)?;
+///
+/// let mut live = KvMap::open(MemoryChunkStore::new(), template())?;
+/// live.update_occupancy(idx, &Measurement::occupied(0.9))?;
+/// assert_eq!(live.commit()?, 1);
+///
+/// let live = KvMap::open(live.into_store()?, template())?;
+/// assert!(live.map().query_occupancy(idx).unwrap() > 0.5);
+/// # Ok(())
+/// # }
+/// ```
+pub struct KvMap<S: ChunkStore> {
+    map: LayeredMap,
+    store: S,
+    /// Layer tag and block base of chunks changed since the last commit
+    dirty: BTreeSet<(u8, u64)>,
+}
+
+impl<S: ChunkStore> KvMap<S> {
+    /// Load a map from `store`
+    ///
+    /// `template` supplies the layers and their parameters. Stored
+    /// configurations are applied to it, then every stored chunk of its
+    /// layers is merged in; chunks of layers the template lacks stay in the
+    /// store untouched. Configurations of layers new to the store are
+    /// written immediately. Fails if a layer cannot encode cell state.
+    pub fn open(mut store: S, mut template: LayeredMap) -> Result<Self> {
+        let upgrades = ConfigUpgrades::new();
+        let mut configured = BTreeSet::new();
+        for (_, value) in store.scan(&[CONFIG_KEY])? {
+            let config = LayerConfig::from_bytes(&value)?;
+            if let Some(layer) = template.layer_mut(config.layer_type()) {
+                configured.insert(config.layer_type().tag());
+                upgrades.apply(layer, config)?;
+            }
+        }
+
+        let mut types = template.layer_types();
+        types.sort_by_key(LayerType::tag);
+        let mut batch = WriteBatch::new();
+        for layer_type in types {
+            let layer = template.layer_mut(layer_type).expect("listed layer exists");
+            if layer.encode_cells(&[]).is_none() {
+                return Err(Error::InvalidFormat(format!(
+                    "{} layer cannot be stored in chunks",
+                    layer_type.name()
+                )));
+            }
+            for (_, state) in store.scan(&[layer_type.tag()])? {
+                layer.merge_state(&state)?;
+            }
+            if let Some(config) = layer.config() {
+                if !configured.contains(&layer_type.tag()) {
+                    batch.push((vec![CONFIG_KEY, layer_type.tag()], Some(config.to_bytes())));
+                }
+            }
+        }
+        if !batch.is_empty() {
+            store.write_batch(batch)?;
+        }
+
+        Ok(Self {
+            map: template,
+            store,
+            dirty: BTreeSet::new(),
+        })
+    }
+
+    /// Apply a measurement to a layer, to be stored by the next commit
+    pub fn update(
+        &mut self,
+        layer_type: LayerType,
+        idx: Index64,
+        measurement: &Measurement,
+    ) -> Result<()> {
+        self.map.update(layer_type, idx, measurement)?;
+        self.mark(layer_type, idx);
+        Ok(())
+    }
+
+    /// Apply a TSDF measurement
+    pub fn update_tsdf(&mut self, idx: Index64, measurement: &Measurement) -> Result<()> {
+        self.update(LayerType::TSDF, idx, measurement)
+    }
+
+    /// Apply an occupancy measurement
+    pub fn update_occupancy(&mut self, idx: Index64, measurement: &Measurement) -> Result<()> {
+        self.update(LayerType::Occupancy, idx, measurement)
+    }
+
+    /// Note a cell changed through [`map_mut`](Self::map_mut), so the next
+    /// commit stores its chunk
+    pub fn mark(&mut self, layer_type: LayerType, idx: Index64) {
+        self.dirty.insert((layer_type.tag(), split(idx).0));
+    }
+
+    /// Write every changed chunk in one transaction, returning how many
+    /// were written
+    ///
+    /// On error nothing is written and the changes stay pending.
+    pub fn commit(&mut self) -> Result<usize> {
+        if self.dirty.is_empty() {
+            return Ok(0);
+        }
+        let mut batch = WriteBatch::with_capacity(self.dirty.len());
+        for &(tag, base) in &self.dirty {
+            let mut key = vec![tag];
+            key.extend_from_slice(&base.to_be_bytes());
+            let layer = LayerType::from_tag(tag).and_then(|t| self.map.layer(t));
+            let cells: Vec<Index64> = match layer {
+                Some(layer) => (0..BLOCK_CELLS)
+                    .map(|slot| join(base, slot))
+                    .filter(|&idx| layer.contains(idx))
+                    .collect(),
+                // Removed since it was marked
+                None => Vec::new(),
+            };
+            let state = match layer {
+                Some(layer) if !cells.is_empty() => layer.encode_cells(&cells),
+                _ => None,
+            };
+            batch.push((key, state));
+        }
+        self.store.write_batch(batch)?;
+        let written = self.dirty.len();
+        self.dirty.clear();
+        Ok(written)
+    }
+
+    /// Number of changed chunks not yet committed
+    pub fn pending(&self) -> usize {
+        self.dirty.len()
+    }
+
+    /// The map as of the last update
+    pub fn map(&self) -> &LayeredMap {
+        &self.map
+    }
+
+    /// Mutable access to the map; changed cells must be passed to
+    /// [`mark`](Self::mark) to be stored
+    pub fn map_mut(&mut self) -> &mut LayeredMap {
+        &mut self.map
+    }
+
+    /// The underlying store
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Commit and return the map
+    pub fn into_map(mut self) -> Result<LayeredMap> {
+        self.commit()?;
+        Ok(self.map)
+    }
+
+    /// Commit and return the store
+    pub fn into_store(mut self) -> Result<S> {
+        self.commit()?;
+        Ok(self.store)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layers::{OccupancyLayer, TSDFLayer};
+
+    fn template() -> LayeredMap {
+        let mut map = LayeredMap::new();
+        map.add_tsdf_layer(TSDFLayer::new(0.1));
+        map.add_occupancy_layer(OccupancyLayer::new());
+        map
+    }
+
+    #[test]
+    fn test_commit_writes_only_changed_chunks() {
+        let mut live = KvMap::open(MemoryChunkStore::new(), template()).unwrap();
+        // Two configurations
+        assert_eq!(live.store().len(), 2);
+
+        // 20 cells along x span three 8-cell blocks
+        for x in 0..20u16 {
+            let idx = Index64::new(0, 0, 10, x, 0, 0).unwrap();
+            live.update_occupancy(idx, &Measurement::occupied(0.8))
+                .unwrap();
+            live.update_tsdf(idx, &Measurement::depth(0.05, 1.0))
+                .unwrap();
+        }
+        assert_eq!(live.commit().unwrap(), 6);
+        assert_eq!(live.store().len(), 8);
+        assert_eq!(live.commit().unwrap(), 0);
+
+        let far = Index64::new(0, 0, 10, 5000, 0, 0).unwrap();
+        live.update_occupancy(far, &Measurement::free(0.8)).unwrap();
+        assert_eq!(live.commit().unwrap(), 1);
+
+        // Chunks emptied through the map are deleted
+        let first = Index64::new(0, 0, 10, 0, 0, 0).unwrap();
+        live.map_mut().layer_mut(LayerType::TSDF).unwrap().clear();
+        for x in 0..20u16 {
+            live.mark(LayerType::TSDF, Index64::new(0, 0, 10, x, 0, 0).unwrap());
+        }
+        assert_eq!(live.commit().unwrap(), 3);
+        assert_eq!(live.store().len(), 6);
+
+        let store = live.into_store().unwrap();
+        let reopened = KvMap::open(store, template()).unwrap();
+        assert_eq!(reopened.map().query_tsdf(first), None);
+        assert_eq!(
+            reopened
+                .map()
+                .layer(LayerType::Occupancy)
+                .unwrap()
+                .voxel_count(),
+            21
+        );
+        assert!(reopened.map().query_occupancy(first).unwrap() > 0.5);
+        assert!(reopened.map().query_occupancy(far).unwrap() < 0.5);
+    }
+
+    #[cfg(feature = "kv_redb")]
+    #[test]
+    fn test_redb_store_round_trip() {
+        let path = std::env::temp_dir().join(format!("oi3d-kv-{}.redb", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let idx = Index64::new(0, 0, 9, 100, 100, 100).unwrap();
+        {
+            let store = RedbChunkStore::open(&path).unwrap();
+            let mut live = KvMap::open(store, template()).unwrap();
+            live.update_tsdf(idx, &Measurement::depth(-0.02, 1.0))
+                .unwrap();
+            live.commit().unwrap();
+        }
+        let store = RedbChunkStore::open(&path).unwrap();
+        assert_eq!(store.scan(&[CONFIG_KEY]).unwrap().len(), 2);
+        let live = KvMap::open(store, template()).unwrap();
+        assert_eq!(live.map().query_tsdf(idx), Some(-0.02));
+        std::fs::remove_file(&path).ok();
+    }
+}
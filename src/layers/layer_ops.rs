@@ -0,0 +1,271 @@
+//! Map algebra: element-wise operations between layers
+//!
+//! [`combine`] merges the scalar values of two layers cell by cell into a
+//! new [`ScalarLayer`], e.g. `risk = occupancy × proximity`. Results are
+//! themselves layers, so operations chain.
+
+use super::{Layer, LayerType, Measurement};
+use crate::error::Result;
+use crate::Index64;
+use std::collections::HashMap;
+
+/// Element-wise operation applied by [`combine`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CombineOp {
+    /// `a + b`; a cell observed in only one layer keeps that value
+    Add,
+    /// `a × b`, over cells observed in both layers
+    Multiply,
+    /// Larger of the two values; a cell observed in only one layer keeps that value
+    Max,
+    /// Smaller of the two values; a cell observed in only one layer keeps that value
+    Min,
+    /// `a` where `b` is observed and non-zero
+    Mask,
+    /// `a` where `b` is observed and at least the given threshold
+    Threshold(f32),
+}
+
+impl CombineOp {
+    /// Cells the result is defined over
+    pub fn domain(&self) -> Domain {
+        match self {
+            CombineOp::Add | CombineOp::Max | CombineOp::Min => Domain::Union,
+            CombineOp::Multiply | CombineOp::Mask | CombineOp::Threshold(_) => Domain::Intersection,
+        }
+    }
+
+    fn apply(&self, a: Option<f32>, b: Option<f32>) -> Option<f32> {
+        match (self, a, b) {
+            (CombineOp::Add, Some(a), Some(b)) => Some(a + b),
+            (CombineOp::Multiply, Some(a), Some(b)) => Some(a * b),
+            (CombineOp::Max, Some(a), Some(b)) => Some(a.max(b)),
+            (CombineOp::Min, Some(a), Some(b)) => Some(a.min(b)),
+            (CombineOp::Mask, Some(a), Some(b)) => (b != 0.0).then_some(a),
+            (CombineOp::Threshold(t), Some(a), Some(b)) => (b >= *t).then_some(a),
+            (CombineOp::Add | CombineOp::Max | CombineOp::Min, a, b) => a.or(b),
+            _ => None,
+        }
+    }
+}
+
+/// Set of cells visited by [`combine_with`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Domain {
+    /// Cells observed in either layer
+    Union,
+    /// Cells observed in both layers
+    Intersection,
+}
+
+/// Layer of derived scalar values
+///
+/// Holds the output of [`combine`]. Sensor measurements are ignored;
+/// values are written with [`ScalarLayer::set`] or computed from other
+/// layers.
+#[derive(Debug, Clone, Default)]
+pub struct ScalarLayer {
+    values: HashMap<Index64, f32>,
+}
+
+impl ScalarLayer {
+    /// Create an empty layer
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Copy the values of any layer
+    pub fn from_layer<L: Layer + ?Sized>(layer: &L) -> Self {
+        let values = layer
+            .indices()
+            .filter_map(|idx| layer.query(idx).map(|v| (idx, v)))
+            .collect();
+        Self { values }
+    }
+
+    /// Value of a cell
+    pub fn get(&self, idx: Index64) -> Option<f32> {
+        self.values.get(&idx).copied()
+    }
+
+    /// Set the value of a cell, returning the previous one
+    pub fn set(&mut self, idx: Index64, value: f32) -> Option<f32> {
+        self.values.insert(idx, value)
+    }
+
+    /// Remove a cell, returning its value
+    pub fn remove(&mut self, idx: Index64) -> Option<f32> {
+        self.values.remove(&idx)
+    }
+
+    /// Apply `f` to every value in place
+    pub fn map_values<F: FnMut(f32) -> f32>(&mut self, mut f: F) {
+        for value in self.values.values_mut() {
+            *value = f(*value);
+        }
+    }
+
+    /// Number of cells
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Check if the layer has no cells
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Iterate over `(cell, value)` pairs, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (Index64, f32)> + '_ {
+        self.values.iter().map(|(&idx, &v)| (idx, v))
+    }
+}
+
+impl Layer for ScalarLayer {
+    fn layer_type(&self) -> LayerType {
+        LayerType::Derived
+    }
+
+    fn update(&mut self, _idx: Index64, _measurement: &Measurement) -> Result<()> {
+        Ok(()) // Derived values are not fed by sensors
+    }
+
+    fn query(&self, idx: Index64) -> Option<f32> {
+        self.get(idx)
+    }
+
+    fn voxel_count(&self) -> usize {
+        self.values.len()
+    }
+
+    fn indices(&self) -> Box<dyn Iterator<Item = Index64> + '_> {
+        Box::new(self.values.keys().copied())
+    }
+
+    fn clear(&mut self) {
+        self.values.clear();
+    }
+
+    fn memory_usage(&self) -> usize {
+        // Each entry: Index64 (8 bytes) + f32 (4 bytes) + HashMap overhead (~20 bytes)
+        self.values.len() * 32
+    }
+}
+
+/// Combine two layers cell by cell
+///
+/// The result covers the union or intersection of observed cells depending
+/// on [`CombineOp::domain`]. Cells are matched by exact index, so both
+/// layers should share frame and LOD.
+///
+/// # Example
+///
+/// ```
+/// use octaindex3d::layers::{combine, CombineOp, Layer, OccupancyLayer, ScalarLayer};
+/// use octaindex3d::Index64;
+///
+/// # fn main() -> octaindex3d::Result<()> {
+/// let wall = Index64::new(0, 0, 5, 10, 10, 10)?;
+/// let mut occupancy = OccupancyLayer::new();
+/// occupancy.update_occupancy(wall, true, 1.0);
+///
+/// let mut proximity = ScalarLayer::new();
+/// proximity.set(wall, 0.5);
+///
+/// let risk = combine(&occupancy, &proximity, CombineOp::Multiply);
+/// assert_eq!(risk.len(), 1);
+/// assert!(risk.query(wall).unwrap() > 0.0);
+/// # Ok(())
+/// # }
+/// ```
+pub fn combine<A, B>(a: &A, b: &B, op: CombineOp) -> ScalarLayer
+where
+    A: Layer + ?Sized,
+    B: Layer + ?Sized,
+{
+    combine_with(a, b, op.domain(), |x, y| op.apply(x, y))
+}
+
+/// Combine two layers cell by cell with a custom function
+///
+/// `f` receives the value of each layer at a cell of `domain` (`None` where
+/// a layer has not observed the cell) and returns the output value, or
+/// `None` to leave the cell out.
+pub fn combine_with<A, B, F>(a: &A, b: &B, domain: Domain, mut f: F) -> ScalarLayer
+where
+    A: Layer + ?Sized,
+    B: Layer + ?Sized,
+    F: FnMut(Option<f32>, Option<f32>) -> Option<f32>,
+{
+    let mut out = ScalarLayer::new();
+    for idx in a.indices() {
+        let (va, vb) = (a.query(idx), b.query(idx));
+        if domain == Domain::Intersection && vb.is_none() {
+            continue;
+        }
+        if let Some(v) = f(va, vb) {
+            out.set(idx, v);
+        }
+    }
+    if domain == Domain::Union {
+        for idx in b.indices() {
+            if a.contains(idx) {
+                continue;
+            }
+            if let Some(v) = f(None, b.query(idx)) {
+                out.set(idx, v);
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{cell, layer};
+
+    #[test]
+    fn test_combine_ops() {
+        let a = layer(&[(0, 1.0), (2, 2.0), (4, 3.0)]);
+        let b = layer(&[(2, 0.5), (4, 0.0), (6, 4.0)]);
+
+        let sum = combine(&a, &b, CombineOp::Add);
+        assert_eq!(sum.len(), 4);
+        assert_eq!(sum.get(cell(2)), Some(2.5));
+        assert_eq!(sum.get(cell(6)), Some(4.0));
+
+        let product = combine(&a, &b, CombineOp::Multiply);
+        assert_eq!(product.len(), 2);
+        assert_eq!(product.get(cell(2)), Some(1.0));
+        assert_eq!(product.get(cell(0)), None);
+
+        assert_eq!(combine(&a, &b, CombineOp::Max).get(cell(4)), Some(3.0));
+        assert_eq!(combine(&a, &b, CombineOp::Min).get(cell(4)), Some(0.0));
+
+        let masked = combine(&a, &b, CombineOp::Mask);
+        assert_eq!(masked.iter().collect::<Vec<_>>(), vec![(cell(2), 2.0)]);
+
+        let thresholded = combine(&a, &b, CombineOp::Threshold(0.25));
+        assert_eq!(thresholded.len(), 1);
+        assert_eq!(thresholded.get(cell(2)), Some(2.0));
+    }
+
+    #[test]
+    fn test_combine_with_and_chaining() {
+        let a = layer(&[(0, 1.0), (2, 2.0)]);
+        let b = layer(&[(2, 3.0), (4, 5.0)]);
+        let only_a = combine_with(&a, &b, Domain::Union, |x, y| match (x, y) {
+            (Some(x), None) => Some(x),
+            _ => None,
+        });
+        assert_eq!(only_a.iter().collect::<Vec<_>>(), vec![(cell(0), 1.0)]);
+
+        let boxed: Box<dyn Layer> = Box::new(combine(&a, &b, CombineOp::Add));
+        let mut doubled = combine(boxed.as_ref(), &b, CombineOp::Multiply);
+        doubled.map_values(|v| v * 2.0);
+        assert_eq!(doubled.get(cell(2)), Some(30.0));
+        assert_eq!(doubled.get(cell(4)), Some(50.0));
+        assert_eq!(doubled.layer_type(), LayerType::Derived);
+    }
+}
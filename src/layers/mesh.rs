@@ -10,23 +10,28 @@
 //! 3. Build triangles from connected vertices
 //! 4. Compute normals from TSDF gradient
 //!
+//! [`extract_colored_mesh`] additionally colors each vertex from a
+//! [`ColorLayer`], interpolating between the two voxels of its edge.
+//!
 //! ## BCC Lattice Advantages
 //!
 //! - 14 neighbors per voxel → more accurate surface representation
 //! - Better isotropy → fewer triangle orientation artifacts
 //! - Natural truncated octahedral cells
 
-use super::TSDFLayer;
+use super::{ColorLayer, TSDFLayer};
 use crate::error::Result;
 use crate::Index64;
 
-/// 3D vertex with position and optional normal
+/// 3D vertex with position, optional normal and optional color
 #[derive(Debug, Clone, Copy)]
 pub struct Vertex {
     /// Position in 3D space
     pub position: [f32; 3],
     /// Surface normal (unit vector)
     pub normal: Option<[f32; 3]>,
+    /// RGB color
+    pub color: Option<[u8; 3]>,
 }
 
 impl Vertex {
@@ -35,6 +40,7 @@ impl Vertex {
         Self {
             position: [x, y, z],
             normal: None,
+            color: None,
         }
     }
 
@@ -43,8 +49,15 @@ impl Vertex {
         Self {
             position: [x, y, z],
             normal: Some([nx, ny, nz]),
+            color: None,
         }
     }
+
+    /// Builder form setting the vertex color
+    pub fn with_color(mut self, rgb: [u8; 3]) -> Self {
+        self.color = Some(rgb);
+        self
+    }
 }
 
 /// Triangle face (3 vertex indices)
@@ -99,6 +112,7 @@ impl Mesh {
             vertex_count: self.vertices.len(),
             triangle_count: self.triangles.len(),
             has_normals: self.vertices.iter().any(|v| v.normal.is_some()),
+            has_colors: self.vertices.iter().any(|v| v.color.is_some()),
         }
     }
 
@@ -166,6 +180,8 @@ pub struct MeshStats {
     pub triangle_count: usize,
     /// Whether vertex normals are present
     pub has_normals: bool,
+    /// Whether vertex colors are present
+    pub has_colors: bool,
 }
 
 /// Extract mesh from TSDF using zero-crossing interpolation
@@ -181,6 +197,40 @@ pub struct MeshStats {
 /// # Returns
 /// Mesh with vertices and triangles
 pub fn extract_mesh_from_tsdf(tsdf: &TSDFLayer) -> Result<Mesh> {
+    extract_mesh(tsdf, None)
+}
+
+/// Extract a mesh from TSDF with vertex colors from a color layer
+///
+/// Each vertex takes the colors of the two voxels of its zero-crossing
+/// edge, blended by its position along the edge; vertices whose voxels
+/// have no color are left uncolored.
+///
+/// # Example
+/// ```
+/// use octaindex3d::layers::{extract_colored_mesh, ColorLayer, Layer, Measurement, TSDFLayer};
+/// use octaindex3d::Index64;
+///
+/// # fn main() -> octaindex3d::Result<()> {
+/// let mut tsdf = TSDFLayer::new(0.1);
+/// let mut color = ColorLayer::new();
+/// let outside = Index64::new(0, 0, 5, 100, 100, 100)?;
+/// let inside = Index64::new(0, 0, 5, 102, 100, 100)?;
+/// tsdf.update(outside, &Measurement::depth(0.02, 1.0))?;
+/// tsdf.update(inside, &Measurement::depth(-0.02, 1.0))?;
+/// color.update(outside, &Measurement::color(255, 0, 0, 1.0))?;
+/// color.update(inside, &Measurement::color(255, 0, 0, 1.0))?;
+///
+/// let mesh = extract_colored_mesh(&tsdf, &color)?;
+/// assert_eq!(mesh.vertices[0].color, Some([255, 0, 0]));
+/// # Ok(())
+/// # }
+/// ```
+pub fn extract_colored_mesh(tsdf: &TSDFLayer, color: &ColorLayer) -> Result<Mesh> {
+    extract_mesh(tsdf, Some(color))
+}
+
+fn extract_mesh(tsdf: &TSDFLayer, color: Option<&ColorLayer>) -> Result<Mesh> {
     let mut mesh = Mesh::new();
     let voxel_size = tsdf.voxel_size();
 
@@ -235,11 +285,14 @@ pub fn extract_mesh_from_tsdf(tsdf: &TSDFLayer) -> Result<Mesh> {
         // For simplicity, use finite differences at idx1
         let normal = compute_normal(tsdf, idx1, voxel_size);
 
-        let vertex = if let Some(n) = normal {
+        let mut vertex = if let Some(n) = normal {
             Vertex::with_normal(pos[0], pos[1], pos[2], n[0], n[1], n[2])
         } else {
             Vertex::new(pos[0], pos[1], pos[2])
         };
+        if let Some(color) = color {
+            vertex.color = blend(color.get_color(idx1), color.get_color(idx2), t);
+        }
 
         let v_idx = mesh.add_vertex(vertex);
 
@@ -256,6 +309,16 @@ pub fn extract_mesh_from_tsdf(tsdf: &TSDFLayer) -> Result<Mesh> {
     Ok(mesh)
 }
 
+/// Color at parameter `t` between two voxel colors
+fn blend(c1: Option<[u8; 3]>, c2: Option<[u8; 3]>, t: f32) -> Option<[u8; 3]> {
+    match (c1, c2) {
+        (Some(a), Some(b)) => Some(std::array::from_fn(|i| {
+            (a[i] as f32 + t * (b[i] as f32 - a[i] as f32)).round() as u8
+        })),
+        (a, b) => a.or(b),
+    }
+}
+
 /// Compute normal at voxel using finite differences
 fn compute_normal(tsdf: &TSDFLayer, idx: Index64, voxel_size: f32) -> Option<[f32; 3]> {
     use crate::neighbors::neighbors_index64;
@@ -9,6 +9,23 @@
 //! - **LayeredMap**: Container for multiple layers sharing the same spatial index
 //! - **Measurement**: Sensor observations (depth, RGB, intensity, etc.)
 //!
+//! ## Reproducibility
+//!
+//! TSDF and ESDF maps are bit-identical across platforms (x86_64, aarch64,
+//! wasm32) for the same sequence of updates:
+//!
+//! - the distance math uses only `+ - * /` and `sqrt`, which IEEE 754
+//!   rounds exactly on every target; Rust never contracts these into fused
+//!   multiply-adds and the layers do not call `mul_add` or libm functions,
+//! - iteration that affects results runs in index order, never in hash
+//!   order ([`TSDFLayer::get_surface_voxels`] is sorted, so the ESDF
+//!   wavefront is seeded identically everywhere),
+//! - [`Layer::encode_state`] writes voxels sorted by index.
+//!
+//! Compare two maps by their encoded state. Keep these rules when changing
+//! the distance code; a golden checksum test in [`esdf`] runs on every CI
+//! platform.
+//!
 //! ## Example
 //!
 //! ```rust
@@ -33,29 +50,102 @@
 //! # }
 //! ```
 
+pub mod access;
+mod batch;
 pub mod bcc_utils;
+pub mod color;
+pub mod deformation;
+pub mod dirty;
+pub mod dynamics;
 pub mod esdf;
 pub mod exploration;
 pub mod export;
+pub mod governor;
+pub mod ingest;
+pub mod intensity;
+mod knn;
+pub mod kv;
+pub mod layer_ops;
 pub mod measurement;
 pub mod mesh;
 pub mod occupancy;
+pub mod occupancy_binary;
 pub mod occupancy_compressed;
 pub mod occupancy_gpu;
+pub mod occupancy_summary;
 pub mod occupancy_temporal;
+#[cfg(feature = "container_v2")]
+mod persist;
+pub mod quantize;
+#[cfg(feature = "container_v2")]
+pub mod reconcile;
+pub mod registry;
 pub mod ros2_bridge;
+#[cfg(feature = "container_v2")]
+pub mod scan;
+pub mod schema;
+pub mod soa;
+mod state;
+pub mod static_map;
+pub mod stitch;
+pub mod submap;
+pub mod terrain;
+pub mod time_series;
+pub mod timestamp;
 pub mod tsdf;
+pub mod uncertainty;
+pub mod virtual_layer;
+pub mod wal;
 
+pub use access::{Access, AccessPolicy, GrantRegion, ReadOnlyMap, RegionGrant, RegionScopedMap};
 pub use bcc_utils::{is_valid_bcc, physical_to_bcc_voxel, snap_to_nearest_bcc};
+pub use color::ColorLayer;
+pub use deformation::{Deformation, DeformationAnchor, DeformationStats};
+pub use dirty::{DirtyCursor, DirtyTracker, DEFAULT_CHUNK_LOD};
+pub use dynamics::{DynamicsClass, DynamicsConfig, DynamicsLayer, DynamicsStats};
 pub use esdf::ESDFLayer;
-pub use exploration::{Frontier, FrontierDetectionConfig, InformationGainConfig, Viewpoint};
-pub use export::{export_mesh_obj, export_mesh_ply, export_mesh_stl};
+pub use exploration::{
+    assign_frontiers, assign_frontiers_with, AssignmentStrategy, Frontier, FrontierAssignment,
+    FrontierDetectionConfig, InformationGainConfig, OccupancySnapshot, Viewpoint, ViewpointBatch,
+};
+pub use export::{export_mesh_ifc, export_mesh_obj, export_mesh_ply, export_mesh_stl};
+pub use governor::{GovernorConfig, GovernorPolicy, GovernorStats, IntegrationGovernor};
+#[cfg(feature = "zenoh")]
+pub use ingest::ZenohIngest;
+pub use ingest::{IngestConfig, IngestStats, SensorIngest};
+pub use intensity::IntensityLayer;
+#[cfg(feature = "kv_redb")]
+pub use kv::RedbChunkStore;
+pub use kv::{ChunkStore, KvMap, MemoryChunkStore, WriteBatch};
+pub use layer_ops::{combine, combine_with, CombineOp, Domain, ScalarLayer};
 pub use measurement::{Measurement, MeasurementType};
-pub use mesh::{extract_mesh_from_tsdf, Mesh, MeshStats, Triangle, Vertex};
-pub use occupancy::{OccupancyLayer, OccupancyState, OccupancyStats};
+pub use mesh::{extract_colored_mesh, extract_mesh_from_tsdf, Mesh, MeshStats, Triangle, Vertex};
+pub use occupancy::{
+    connected_components, connected_components_by, LineOfSight, OccupancyComponent, OccupancyLayer,
+    OccupancyState, OccupancyStats,
+};
+pub use occupancy_binary::BinaryOccupancyLayer;
 pub use occupancy_compressed::{CompressedOccupancyLayer, CompressionMethod, CompressionStats};
+pub use occupancy_summary::SubtreeOccupancy;
 pub use occupancy_temporal::{TemporalConfig, TemporalOccupancyLayer, TemporalStats};
-pub use tsdf::TSDFLayer;
+pub use quantize::ValueQuantization;
+#[cfg(feature = "container_v2")]
+pub use reconcile::{ContainerDiff, MergePolicy, MergeStats};
+pub use registry::{MapQuota, MapRegistry, MapUsage};
+#[cfg(feature = "container_v2")]
+pub use scan::{CellBounds, CellRow, ContainerScan};
+pub use schema::{ConfigUpgrades, LayerConfig, UpgradeHook};
+pub use soa::StorageLayout;
+pub use static_map::{StaticLayer, StaticMap};
+pub use stitch::{StitchStats, Submap, SubmapCollection};
+pub use submap::{descriptor, DescriptorMatcher, MapRegion, SubmapDescriptor};
+pub use terrain::TerrainLayer;
+pub use time_series::{Rollup, TimeSeriesLayer};
+pub use timestamp::{TimestampLayer, VoxelBounds};
+pub use tsdf::{integrate_depth_image, CameraIntrinsics, TSDFLayer};
+pub use uncertainty::{CellStats, PairStats, UncertaintyLayer};
+pub use virtual_layer::{LayerBindings, VirtualLayer};
+pub use wal::{WalConfig, WalMap};
 
 /// Re-export ROS2 types for robotics integration
 pub mod ros2 {
@@ -69,7 +159,9 @@ pub mod gpu {
 }
 
 use crate::error::{Error, Result};
+use crate::frustum::Frustum;
 use crate::Index64;
+use std::any::Any;
 use std::collections::HashMap;
 
 /// Layer type identifier
@@ -85,9 +177,15 @@ pub enum LayerType {
     Color,
     /// Intensity (LiDAR)
     Intensity,
+    /// Scalar values derived from other layers (map algebra)
+    Derived,
 }
 
 impl LayerType {
+    /// Number of layer types; tags run from 0 to `COUNT - 1`
+    #[cfg_attr(not(feature = "container_v2"), allow(dead_code))]
+    pub(crate) const COUNT: usize = 6;
+
     /// Get human-readable name
     pub fn name(&self) -> &'static str {
         match self {
@@ -96,8 +194,34 @@ impl LayerType {
             LayerType::Occupancy => "Occupancy",
             LayerType::Color => "Color",
             LayerType::Intensity => "Intensity",
+            LayerType::Derived => "Derived",
+        }
+    }
+
+    /// Stable one-byte tag used in persisted data
+    pub fn tag(&self) -> u8 {
+        match self {
+            LayerType::TSDF => 0,
+            LayerType::ESDF => 1,
+            LayerType::Occupancy => 2,
+            LayerType::Color => 3,
+            LayerType::Intensity => 4,
+            LayerType::Derived => 5,
         }
     }
+
+    /// Layer type of a persisted tag
+    pub fn from_tag(tag: u8) -> Option<Self> {
+        Some(match tag {
+            0 => LayerType::TSDF,
+            1 => LayerType::ESDF,
+            2 => LayerType::Occupancy,
+            3 => LayerType::Color,
+            4 => LayerType::Intensity,
+            5 => LayerType::Derived,
+            _ => return None,
+        })
+    }
 }
 
 /// Generic layer trait for spatial data
@@ -108,6 +232,18 @@ pub trait Layer: Send + Sync {
     /// Update a voxel from sensor measurement
     fn update(&mut self, idx: Index64, measurement: &Measurement) -> Result<()>;
 
+    /// Update many voxels from `(voxel, measurement)` pairs
+    ///
+    /// Equivalent to calling [`update`](Self::update) for each pair in
+    /// order, stopping at the first error. Layers override this to avoid
+    /// per-call overhead when integrating whole scans; overrides may check
+    /// the entire batch and reject it before applying any of it.
+    fn update_batch(&mut self, updates: &[(Index64, Measurement)]) -> Result<()> {
+        updates
+            .iter()
+            .try_for_each(|(idx, measurement)| self.update(*idx, measurement))
+    }
+
     /// Query voxel value (returns None if voxel not observed)
     fn query(&self, idx: Index64) -> Option<f32>;
 
@@ -119,21 +255,171 @@ pub trait Layer: Send + Sync {
     /// Get number of voxels in this layer
     fn voxel_count(&self) -> usize;
 
+    /// Iterate over the indices of all stored voxels, in no particular order
+    ///
+    /// The default yields nothing, so layers that do not override it are
+    /// skipped by index walks such as [`LayeredMap::frustum_cull`].
+    fn indices(&self) -> Box<dyn Iterator<Item = Index64> + '_> {
+        Box::new(std::iter::empty())
+    }
+
     /// Clear all data
     fn clear(&mut self);
 
     /// Get memory usage in bytes (approximate)
     fn memory_usage(&self) -> usize;
+
+    /// Chunk change tracker, for layers that record their changes
+    fn dirty_tracker(&self) -> Option<&DirtyTracker> {
+        None
+    }
+
+    /// Mutable chunk change tracker, for layers that record their changes
+    fn dirty_tracker_mut(&mut self) -> Option<&mut DirtyTracker> {
+        None
+    }
+
+    /// Drain the chunks changed since the previous call, sorted
+    ///
+    /// Layers without change tracking return nothing. See [`DirtyTracker`]
+    /// for per-consumer cursors.
+    fn take_dirty(&mut self) -> Vec<Index64> {
+        self.dirty_tracker_mut()
+            .map(DirtyTracker::take_dirty)
+            .unwrap_or_default()
+    }
+
+    /// Serialize the voxel state, for layers that support checkpoints
+    ///
+    /// Parameters (thresholds, voxel size, ...) are not included; they are
+    /// persisted separately through [`config`](Self::config).
+    fn encode_state(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// Replace the voxel state with one produced by
+    /// [`encode_state`](Self::encode_state)
+    fn decode_state(&mut self, _bytes: &[u8]) -> Result<()> {
+        Err(Error::InvalidFormat(format!(
+            "{} layer does not support state restore",
+            self.layer_type().name()
+        )))
+    }
+
+    /// Serialize the state of the listed cells, in the layout of
+    /// [`encode_state`](Self::encode_state)
+    ///
+    /// Cells without a voxel are skipped. Chunked backends such as
+    /// [`KvMap`] persist a map a piece at a time with this.
+    fn encode_cells(&self, _cells: &[Index64]) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// Insert the voxels of a state produced by
+    /// [`encode_cells`](Self::encode_cells) or
+    /// [`encode_state`](Self::encode_state), replacing those cells and
+    /// keeping all others
+    fn merge_state(&mut self, _bytes: &[u8]) -> Result<()> {
+        Err(Error::InvalidFormat(format!(
+            "{} layer does not support state merge",
+            self.layer_type().name()
+        )))
+    }
+
+    /// Combine the voxels of a state produced by
+    /// [`encode_state`](Self::encode_state) with the evidence already held
+    /// for those cells, as when fusing overlapping submaps
+    ///
+    /// Records for the same cell are fused in turn. Occupancy adds log-odds
+    /// and TSDF takes the weighted mean; the default replaces cells like
+    /// [`merge_state`](Self::merge_state).
+    fn fuse_state(&mut self, bytes: &[u8]) -> Result<()> {
+        self.merge_state(bytes)
+    }
+
+    /// Parameters as a versioned record, for layers whose configuration
+    /// persists alongside their state; see [`schema`]
+    fn config(&self) -> Option<LayerConfig> {
+        None
+    }
+
+    /// Adopt the parameters in `config`, already upgraded to this layer's
+    /// schema version
+    ///
+    /// Fields the record lacks keep their current values and unknown
+    /// fields are ignored. Use [`ConfigUpgrades::apply`] for records that
+    /// may come from another version.
+    fn apply_config(&mut self, _config: &LayerConfig) -> Result<()> {
+        Err(Error::InvalidFormat(format!(
+            "{} layer has no persisted configuration",
+            self.layer_type().name()
+        )))
+    }
+}
+
+/// Layer stored in a [`LayeredMap`], which can be downcast to its concrete type
+trait StoredLayer: Layer + Any {
+    fn as_layer(&self) -> &dyn Layer;
+    fn as_layer_mut(&mut self) -> &mut (dyn Layer + 'static);
+    fn into_layer(self: Box<Self>) -> Box<dyn Layer>;
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+}
+
+impl<L: Layer + 'static> StoredLayer for L {
+    fn as_layer(&self) -> &dyn Layer {
+        self
+    }
+
+    fn as_layer_mut(&mut self) -> &mut (dyn Layer + 'static) {
+        self
+    }
+
+    fn into_layer(self: Box<Self>) -> Box<dyn Layer> {
+        self
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
 }
 
 /// Multi-layer spatial map on BCC lattice
 ///
 /// Stores multiple data layers (TSDF, ESDF, Occupancy, etc.) on the same
 /// BCC lattice structure, sharing the spatial indexing infrastructure.
+/// Layers are keyed by [`LayerType`]; [`LayeredMap::get_layer`] returns a
+/// layer as its concrete type for layer-specific methods.
+///
+/// ```
+/// use octaindex3d::layers::{LayeredMap, Measurement, OccupancyLayer, TSDFLayer};
+/// use octaindex3d::Index64;
+///
+/// # fn main() -> octaindex3d::Result<()> {
+/// let mut map = LayeredMap::new();
+/// map.add_layer(OccupancyLayer::new());
+/// let idx = Index64::new(0, 0, 5, 100, 200, 300)?;
+/// map.update_occupancy(idx, &Measurement::occupied(0.9))?;
+///
+/// let occupancy = map.get_layer::<OccupancyLayer>().unwrap();
+/// assert_eq!(occupancy.get_measurement_count(idx), 1);
+/// assert!(map.get_layer::<TSDFLayer>().is_none());
+/// # Ok(())
+/// # }
+/// ```
 #[derive(Default)]
 pub struct LayeredMap {
     /// Active layers mapped by type
-    layers: HashMap<LayerType, Box<dyn Layer>>,
+    layers: HashMap<LayerType, Box<dyn StoredLayer>>,
 }
 
 impl LayeredMap {
@@ -159,6 +445,16 @@ impl LayeredMap {
         self.layers.insert(LayerType::Occupancy, Box::new(layer));
     }
 
+    /// Add a Color layer for per-voxel RGB
+    pub fn add_color_layer(&mut self, layer: ColorLayer) {
+        self.layers.insert(LayerType::Color, Box::new(layer));
+    }
+
+    /// Add an Intensity layer for LiDAR reflectivity
+    pub fn add_intensity_layer(&mut self, layer: IntensityLayer) {
+        self.layers.insert(LayerType::Intensity, Box::new(layer));
+    }
+
     /// Get reference to TSDF layer
     ///
     /// Note: This returns None if the layer doesn't exist.
@@ -167,15 +463,44 @@ impl LayeredMap {
         self.layers.contains_key(&LayerType::TSDF)
     }
 
-    /// Get TSDF layer (consumes the map, returns layer)
-    /// For most use cases, use update_tsdf and query_tsdf instead
+    /// Remove the TSDF layer and return it
+    ///
+    /// Shorthand for `take_layer::<TSDFLayer>()`.
     pub fn take_tsdf_layer(&mut self) -> Option<TSDFLayer> {
-        self.layers.remove(&LayerType::TSDF).map(|boxed| {
-            // SAFETY: We know this is a TSDFLayer because LayerType::TSDF
-            // can only be inserted via add_tsdf_layer
-            let any = Box::into_raw(boxed) as *mut TSDFLayer;
-            unsafe { *Box::from_raw(any) }
-        })
+        self.take_layer()
+    }
+
+    /// Add a layer of any type, replacing the layer of the same
+    /// [`LayerType`], which is returned
+    pub fn add_layer<L: Layer + 'static>(&mut self, layer: L) -> Option<Box<dyn Layer>> {
+        self.layers
+            .insert(layer.layer_type(), Box::new(layer))
+            .map(StoredLayer::into_layer)
+    }
+
+    /// The layer of concrete type `L`, if the map holds one
+    pub fn get_layer<L: Layer + 'static>(&self) -> Option<&L> {
+        self.layers
+            .values()
+            .find_map(|layer| layer.as_any().downcast_ref())
+    }
+
+    /// Mutable access to the layer of concrete type `L`
+    pub fn get_layer_mut<L: Layer + 'static>(&mut self) -> Option<&mut L> {
+        self.layers
+            .values_mut()
+            .find_map(|layer| layer.as_any_mut().downcast_mut())
+    }
+
+    /// Remove the layer of concrete type `L` and return it
+    pub fn take_layer<L: Layer + 'static>(&mut self) -> Option<L> {
+        let layer_type = self
+            .layers
+            .iter()
+            .find(|(_, layer)| layer.as_any().is::<L>())
+            .map(|(&layer_type, _)| layer_type)?;
+        let layer = self.layers.remove(&layer_type)?;
+        layer.into_any().downcast().ok().map(|layer| *layer)
     }
 
     /// Update TSDF layer with measurement
@@ -212,6 +537,26 @@ impl LayeredMap {
         }
     }
 
+    /// Update Color layer with measurement
+    pub fn update_color(&mut self, idx: Index64, measurement: &Measurement) -> Result<()> {
+        match self.layers.get_mut(&LayerType::Color) {
+            Some(layer) => layer.update(idx, measurement),
+            None => Err(Error::InvalidFormat(
+                "Color layer not initialized".to_string(),
+            )),
+        }
+    }
+
+    /// Update Intensity layer with measurement
+    pub fn update_intensity(&mut self, idx: Index64, measurement: &Measurement) -> Result<()> {
+        match self.layers.get_mut(&LayerType::Intensity) {
+            Some(layer) => layer.update(idx, measurement),
+            None => Err(Error::InvalidFormat(
+                "Intensity layer not initialized".to_string(),
+            )),
+        }
+    }
+
     /// Query Occupancy probability value
     pub fn query_occupancy(&self, idx: Index64) -> Option<f32> {
         self.layers
@@ -219,6 +564,96 @@ impl LayeredMap {
             .and_then(|layer| layer.query(idx))
     }
 
+    /// Query mean LiDAR intensity
+    ///
+    /// Pair with [`query_occupancy`](Self::query_occupancy) to read the
+    /// reflectivity of occupied voxels.
+    pub fn query_intensity(&self, idx: Index64) -> Option<f32> {
+        self.layers
+            .get(&LayerType::Intensity)
+            .and_then(|layer| layer.query(idx))
+    }
+
+    /// Find the `k` voxels of a layer nearest to `query` whose value passes
+    /// `accept`
+    ///
+    /// Generic counterpart of [`OccupancyLayer::knn`] for any layer, e.g.
+    /// `map.knn(LayerType::Occupancy, q, 5, 50, |p| p > 0.7)`. Searches the
+    /// cube of Chebyshev radius `max_radius` lattice units around the query
+    /// and returns `(voxel, distance)` pairs sorted by increasing Euclidean
+    /// distance; empty if the layer does not exist. Hits in the cube's
+    /// corners can be up to √3·`max_radius` away, while closer voxels just
+    /// outside a face are not found.
+    pub fn knn<F>(
+        &self,
+        layer_type: LayerType,
+        query: Index64,
+        k: usize,
+        max_radius: u32,
+        accept: F,
+    ) -> Vec<(Index64, f32)>
+    where
+        F: Fn(f32) -> bool,
+    {
+        let Some(layer) = self.layers.get(&layer_type) else {
+            return Vec::new();
+        };
+        let is_hit = |idx: Index64| layer.query(idx).is_some_and(&accept);
+        knn::shell_search(query, k, max_radius, usize::MAX, is_hit).0
+    }
+
+    /// Voxels of a layer whose cells are at least partly inside `frustum`
+    ///
+    /// Returns the visible voxels in ascending index order; empty if the
+    /// layer does not exist. See [`Frustum::cull`].
+    pub fn frustum_cull(&self, layer_type: LayerType, frustum: &Frustum) -> Vec<Index64> {
+        let Some(layer) = self.layers.get(&layer_type) else {
+            return Vec::new();
+        };
+        let mut visible: Vec<Index64> = frustum.cull(layer.indices()).collect();
+        visible.sort_unstable();
+        visible
+    }
+
+    /// Drain the chunks of a layer changed since the previous call
+    ///
+    /// Empty if the layer does not exist or does not track changes. See
+    /// [`Layer::take_dirty`].
+    pub fn take_dirty(&mut self, layer_type: LayerType) -> Vec<Index64> {
+        self.layers
+            .get_mut(&layer_type)
+            .map(|layer| layer.take_dirty())
+            .unwrap_or_default()
+    }
+
+    /// Update any layer with a measurement
+    pub fn update(
+        &mut self,
+        layer_type: LayerType,
+        idx: Index64,
+        measurement: &Measurement,
+    ) -> Result<()> {
+        match self.layers.get_mut(&layer_type) {
+            Some(layer) => layer.update(idx, measurement),
+            None => Err(Error::InvalidFormat(format!(
+                "{} layer not initialized",
+                layer_type.name()
+            ))),
+        }
+    }
+
+    /// Get a layer
+    pub fn layer(&self, layer_type: LayerType) -> Option<&dyn Layer> {
+        self.layers.get(&layer_type).map(|layer| layer.as_layer())
+    }
+
+    /// Get a layer mutably
+    pub fn layer_mut(&mut self, layer_type: LayerType) -> Option<&mut (dyn Layer + 'static)> {
+        self.layers
+            .get_mut(&layer_type)
+            .map(|layer| layer.as_layer_mut())
+    }
+
     /// Check if a layer exists
     pub fn has_layer(&self, layer_type: LayerType) -> bool {
         self.layers.contains_key(&layer_type)
@@ -226,7 +661,7 @@ impl LayeredMap {
 
     /// Remove a layer
     pub fn remove_layer(&mut self, layer_type: LayerType) -> Option<Box<dyn Layer>> {
-        self.layers.remove(&layer_type)
+        self.layers.remove(&layer_type).map(StoredLayer::into_layer)
     }
 
     /// Get all active layer types
@@ -234,6 +669,24 @@ impl LayeredMap {
         self.layers.keys().copied().collect()
     }
 
+    /// Add the crate's standard layer of `layer_type` with default
+    /// parameters, which a stored configuration then replaces
+    fn add_standard_layer(&mut self, layer_type: LayerType) -> Result<()> {
+        match layer_type {
+            LayerType::TSDF => self.add_tsdf_layer(TSDFLayer::new(0.1)),
+            LayerType::ESDF => self.add_esdf_layer(ESDFLayer::new(0.1, 5.0)),
+            LayerType::Occupancy => self.add_occupancy_layer(OccupancyLayer::new()),
+            LayerType::Color => self.add_color_layer(ColorLayer::new()),
+            LayerType::Intensity => self.add_intensity_layer(IntensityLayer::new()),
+            LayerType::Derived => {
+                return Err(Error::InvalidFormat(
+                    "derived layers cannot be loaded".to_string(),
+                ))
+            }
+        }
+        Ok(())
+    }
+
     /// Get total voxel count across all layers
     pub fn total_voxels(&self) -> usize {
         self.layers.values().map(|l| l.voxel_count()).sum()
@@ -263,10 +716,90 @@ mod tests {
         assert_eq!(map.total_voxels(), 0);
     }
 
+    #[test]
+    fn test_knn_generic() -> Result<()> {
+        let mut map = LayeredMap::new();
+        let mut occupancy = OccupancyLayer::new();
+        for x in [10u16, 14, 30] {
+            occupancy.update_occupancy(Index64::new(0, 0, 5, x, 10, 10)?, true, 0.95);
+        }
+        occupancy.update_occupancy(Index64::new(0, 0, 5, 12, 10, 10)?, false, 0.95);
+        map.add_occupancy_layer(occupancy);
+
+        let query = Index64::new(0, 0, 5, 12, 10, 10)?;
+        let hits = map.knn(LayerType::Occupancy, query, 2, 10, |p| p > 0.7);
+        assert_eq!(hits.len(), 2);
+        assert!(hits.iter().all(|&(_, d)| d == 2.0));
+
+        // Beyond the search radius
+        assert_eq!(
+            map.knn(LayerType::Occupancy, query, 3, 10, |p| p > 0.7)
+                .len(),
+            2
+        );
+        assert!(map.knn(LayerType::TSDF, query, 1, 10, |_| true).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_frustum_cull_layer() -> Result<()> {
+        let mut map = LayeredMap::new();
+        let mut occupancy = OccupancyLayer::new();
+        for x in [4u16, 20, 60] {
+            occupancy.update_occupancy(Index64::new(0, 0, 5, x, 10, 10)?, true, 0.95);
+        }
+        map.add_occupancy_layer(occupancy);
+
+        let frustum = Frustum::perspective(
+            [0.0, 10.0, 10.0],
+            [1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0],
+            1.0,
+            1.0,
+            5.0,
+            40.0,
+        );
+        // x = 4 pokes through the near plane at 5; x = 60 is beyond the far plane
+        let visible = map.frustum_cull(LayerType::Occupancy, &frustum);
+        assert_eq!(
+            visible,
+            vec![
+                Index64::new(0, 0, 5, 4, 10, 10)?,
+                Index64::new(0, 0, 5, 20, 10, 10)?
+            ]
+        );
+        assert!(map.frustum_cull(LayerType::TSDF, &frustum).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_typed_layer_access() -> Result<()> {
+        let mut map = LayeredMap::new();
+        map.add_tsdf_layer(TSDFLayer::new(0.1));
+        map.add_layer(OccupancyLayer::new());
+        let idx = Index64::new(0, 0, 5, 10, 10, 10)?;
+
+        map.get_layer_mut::<OccupancyLayer>()
+            .unwrap()
+            .update_occupancy(idx, true, 0.9);
+        assert!(map.query_occupancy(idx).unwrap() > 0.5);
+        assert!(map.get_layer::<ESDFLayer>().is_none());
+
+        map.update_tsdf(idx, &Measurement::depth(0.05, 1.0))?;
+        let tsdf = map.take_tsdf_layer().unwrap();
+        assert_eq!(tsdf.voxel_count(), 1);
+        assert!(!map.has_tsdf_layer());
+        assert!(map.take_layer::<TSDFLayer>().is_none());
+        assert_eq!(map.layer_types(), vec![LayerType::Occupancy]);
+        Ok(())
+    }
+
     #[test]
     fn test_layer_type_names() {
         assert_eq!(LayerType::TSDF.name(), "TSDF");
         assert_eq!(LayerType::ESDF.name(), "ESDF");
         assert_eq!(LayerType::Occupancy.name(), "Occupancy");
+        assert!((0..LayerType::COUNT as u8).all(|tag| LayerType::from_tag(tag).is_some()));
+        assert_eq!(LayerType::from_tag(LayerType::COUNT as u8), None);
     }
 }
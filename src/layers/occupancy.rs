@@ -23,11 +23,21 @@
 //! - Hornung et al., "OctoMap: An Efficient Probabilistic 3D Mapping Framework" (2013)
 //! - Moravec & Elfes, "High Resolution Maps from Wide Angle Sonar" (1985)
 
+use super::batch::fold_by_voxel;
+use super::ingest::IngestConfig;
+use super::knn;
 use super::measurement::MeasurementData;
-use super::{Layer, LayerType, Measurement};
-use crate::error::Result;
+use super::occupancy_summary::{OccupancySummary, SubtreeOccupancy};
+use super::state::{decode_voxels, encode_voxels};
+use super::{DirtyTracker, Layer, LayerConfig, LayerType, Measurement};
+use crate::error::{Error, Result};
+use crate::lattice::{raycast, LatticeCoord};
+use crate::neighbors::neighbors_index64;
 use crate::Index64;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+
+/// Schema version of [`LayerConfig`] records written by this layer
+const CONFIG_VERSION: u16 = 1;
 
 /// Occupancy state classification
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -103,6 +113,12 @@ pub struct OccupancyLayer {
     /// Default: ±3.5 (p ≈ 0.97 / 0.03)
     max_log_odds: f32,
     min_log_odds: f32,
+
+    /// Chunks changed since consumers last looked
+    dirty: DirtyTracker,
+
+    /// Per-parent occupied/free counts, if enabled
+    summary: Option<OccupancySummary>,
 }
 
 impl OccupancyLayer {
@@ -119,6 +135,8 @@ impl OccupancyLayer {
             free_threshold: -0.85,
             max_log_odds: 3.5,
             min_log_odds: -3.5,
+            dirty: DirtyTracker::default(),
+            summary: None,
         }
     }
 
@@ -140,6 +158,8 @@ impl OccupancyLayer {
             free_threshold,
             max_log_odds,
             min_log_odds,
+            dirty: DirtyTracker::default(),
+            summary: None,
         }
     }
 
@@ -152,36 +172,106 @@ impl OccupancyLayer {
     /// * `occupied` - True if sensor detected obstacle
     /// * `confidence` - Sensor confidence (0.5 - 1.0)
     pub fn update_occupancy(&mut self, idx: Index64, occupied: bool, confidence: f32) {
+        self.dirty.mark(idx);
+        let old_state = self.summary.as_ref().map(|_| self.get_state(idx));
         let voxel = self.voxels.entry(idx).or_default();
 
-        // Convert measurement to log-odds
-        let measurement_log_odds = if occupied {
-            prob_to_log_odds(confidence)
-        } else {
-            prob_to_log_odds(1.0 - confidence)
-        };
-
         // Bayesian update (addition in log-odds space)
-        let new_log_odds = voxel.log_odds + measurement_log_odds;
+        let new_log_odds = voxel.log_odds + observation_log_odds(occupied, confidence);
 
         // Clamp to prevent saturation
         voxel.log_odds = new_log_odds.clamp(self.min_log_odds, self.max_log_odds);
         voxel.measurement_count += 1;
+        let log_odds = voxel.log_odds;
+
+        if let Some(old_state) = old_state {
+            let new_state = self.classify(log_odds);
+            if let Some(summary) = &mut self.summary {
+                summary.apply(idx, old_state, new_state);
+            }
+        }
+    }
+
+    /// Maintain per-parent summaries of the cells at `leaf_lod`
+    ///
+    /// Every ancestor of a leaf then tracks how many of its leaves are
+    /// occupied and free, updated incrementally as leaves change state, so
+    /// [`subtree`](Self::subtree), [`any_occupied`](Self::any_occupied)
+    /// and [`all_free`](Self::all_free) answer in O(1) at any coarser LOD.
+    /// Each state change costs one hash update per LOD above the leaf.
+    /// Voxels at other LODs are not summarized.
+    pub fn with_summaries(mut self, leaf_lod: u8) -> Self {
+        self.summary = Some(OccupancySummary::new(leaf_lod));
+        self.rebuild_summary();
+        self
+    }
+
+    /// LOD of the summarized leaf cells, if summaries are enabled
+    pub fn summary_lod(&self) -> Option<u8> {
+        self.summary.as_ref().map(OccupancySummary::leaf_lod)
+    }
+
+    /// Occupancy of the leaves below `cell`
+    ///
+    /// `None` without summaries or for cells finer than the leaf LOD.
+    pub fn subtree(&self, cell: Index64) -> Option<SubtreeOccupancy> {
+        self.summary
+            .as_ref()?
+            .subtree(cell, |leaf| self.get_state(leaf))
+    }
+
+    /// Whether any leaf below `cell` is occupied
+    ///
+    /// Conservative: `true` when the subtree is not summarized.
+    pub fn any_occupied(&self, cell: Index64) -> bool {
+        self.subtree(cell).map_or(true, |s| s.any_occupied())
+    }
+
+    /// Whether every leaf below `cell` is known to be free
+    ///
+    /// Conservative: `false` when the subtree is not summarized.
+    pub fn all_free(&self, cell: Index64) -> bool {
+        self.subtree(cell).is_some_and(|s| s.all_free())
+    }
+
+    /// Recount the summaries from scratch, after a threshold change
+    fn rebuild_summary(&mut self) {
+        let Some(mut summary) = self.summary.take() else {
+            return;
+        };
+        summary.clear();
+        for (&idx, voxel) in &self.voxels {
+            summary.apply(idx, OccupancyState::Unknown, self.classify(voxel.log_odds));
+        }
+        self.summary = Some(summary);
+    }
+
+    /// Chunks changed since the last `take_dirty`
+    pub fn dirty(&self) -> &DirtyTracker {
+        &self.dirty
+    }
+
+    /// Mutable access to the change tracker, e.g. to replace it with one
+    /// using another chunk LOD
+    pub fn dirty_mut(&mut self) -> &mut DirtyTracker {
+        &mut self.dirty
     }
 
     /// Get occupancy state classification
     pub fn get_state(&self, idx: Index64) -> OccupancyState {
         match self.voxels.get(&idx) {
             None => OccupancyState::Unknown,
-            Some(voxel) => {
-                if voxel.log_odds > self.occupied_threshold {
-                    OccupancyState::Occupied
-                } else if voxel.log_odds < self.free_threshold {
-                    OccupancyState::Free
-                } else {
-                    OccupancyState::Unknown
-                }
-            }
+            Some(voxel) => self.classify(voxel.log_odds),
+        }
+    }
+
+    fn classify(&self, log_odds: f32) -> OccupancyState {
+        if log_odds > self.occupied_threshold {
+            OccupancyState::Occupied
+        } else if log_odds < self.free_threshold {
+            OccupancyState::Free
+        } else {
+            OccupancyState::Unknown
         }
     }
 
@@ -234,6 +324,38 @@ impl OccupancyLayer {
             .collect()
     }
 
+    /// Find the `k` occupied voxels nearest to `query`
+    ///
+    /// Returns `(voxel, distance)` pairs sorted by increasing Euclidean
+    /// distance in lattice units. Only voxels in the query's frame and LOD
+    /// are considered. The search expands shells around the query, so its
+    /// cost depends on the distance to the k-th neighbor rather than on the
+    /// layer size; when that distance is large compared to the number of
+    /// stored voxels it falls back to a single pass over the layer.
+    pub fn knn(&self, query: Index64, k: usize) -> Vec<(Index64, f32)> {
+        let is_occupied = |idx: Index64| {
+            self.voxels
+                .get(&idx)
+                .is_some_and(|v| v.log_odds > self.occupied_threshold)
+        };
+        let (hits, complete) =
+            knn::shell_search(query, k, u16::MAX as u32, self.voxels.len(), is_occupied);
+        if complete {
+            return hits;
+        }
+
+        let mut best = knn::KnnHeap::new(k);
+        for (&idx, voxel) in &self.voxels {
+            if voxel.log_odds > self.occupied_threshold
+                && idx.frame_id() == query.frame_id()
+                && idx.lod() == query.lod()
+            {
+                best.offer(idx, knn::voxel_distance(query, idx));
+            }
+        }
+        best.into_sorted()
+    }
+
     /// Get statistics about the occupancy layer
     pub fn stats(&self) -> OccupancyStats {
         let mut occupied_count = 0;
@@ -344,6 +466,174 @@ impl OccupancyLayer {
 
         Ok(())
     }
+
+    /// Integrate a point cloud, clearing free space along each ray
+    ///
+    /// Points and `sensor_origin` are in meters and map to lattice
+    /// coordinates through `config.voxel_size`. The voxel of each point is
+    /// observed occupied and every cell its ray from the sensor crosses
+    /// (walked exactly with [`raycast`]) is observed free, so the map forgets
+    /// objects that have moved away. As in OctoMap, a voxel is updated at
+    /// most once per cloud and a voxel hit by any point is never cleared by
+    /// another ray of the same cloud. Points beyond `config.max_range` only
+    /// clear free space up to that range.
+    ///
+    /// Returns the number of points integrated as hits.
+    ///
+    /// # Example
+    /// ```
+    /// use octaindex3d::layers::{IngestConfig, OccupancyLayer, OccupancyState};
+    /// use octaindex3d::Index64;
+    ///
+    /// # fn main() -> octaindex3d::Result<()> {
+    /// let mut occupancy = OccupancyLayer::new();
+    /// let config = IngestConfig::default();
+    /// let hits = occupancy.integrate_point_cloud(&[(3.0, 1.0, 1.0)], (1.0, 1.0, 1.0), &config)?;
+    /// assert_eq!(hits, 1);
+    ///
+    /// let wall = Index64::new(0, 0, 5, 30, 10, 10)?;
+    /// let between = Index64::new(0, 0, 5, 20, 10, 10)?;
+    /// assert_eq!(occupancy.get_state(wall), OccupancyState::Occupied);
+    /// assert!(occupancy.get_probability(between).unwrap() < 0.5);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn integrate_point_cloud(
+        &mut self,
+        points: &[(f32, f32, f32)],
+        sensor_origin: (f32, f32, f32),
+        config: &IngestConfig,
+    ) -> Result<usize> {
+        let scale = 1.0 / config.voxel_size as f64;
+        let lattice =
+            |p: (f32, f32, f32)| (p.0 as f64 * scale, p.1 as f64 * scale, p.2 as f64 * scale);
+        let voxel = |c: LatticeCoord| {
+            let (x, y, z) = (
+                u16::try_from(c.x).ok()?,
+                u16::try_from(c.y).ok()?,
+                u16::try_from(c.z).ok()?,
+            );
+            Index64::new(0, 0, 5, x, y, z).ok()
+        };
+        let origin = lattice(sensor_origin);
+        let max_range = config.max_range as f64 * scale;
+
+        let mut hits = HashSet::new();
+        let mut free = HashSet::new();
+        let mut integrated = 0;
+        for &point in points {
+            let end = lattice(point);
+            let delta = (end.0 - origin.0, end.1 - origin.1, end.2 - origin.2);
+            let length = (delta.0 * delta.0 + delta.1 * delta.1 + delta.2 * delta.2).sqrt();
+            if !length.is_finite() || length < 1e-9 {
+                continue;
+            }
+
+            let mut cells: Vec<LatticeCoord> = raycast(origin, delta, length.min(max_range))?
+                .map(|cell| cell.coord)
+                .collect();
+            if length <= max_range {
+                // The last cell holds the point itself
+                if let Some(idx) = cells.pop().and_then(voxel) {
+                    hits.insert(idx);
+                }
+                integrated += 1;
+            }
+            free.extend(cells.into_iter().filter_map(voxel));
+        }
+
+        let mut updates: Vec<(Index64, Measurement)> = free
+            .difference(&hits)
+            .map(|&idx| (idx, Measurement::free(config.free_confidence)))
+            .chain(
+                hits.iter()
+                    .map(|&idx| (idx, Measurement::occupied(config.occupied_confidence))),
+            )
+            .collect();
+        // Hash order is random; sort so summaries and dirty chunks are reproducible
+        updates.sort_by_key(|(idx, _)| *idx);
+        self.update_batch(&updates)?;
+        Ok(integrated)
+    }
+
+    /// Check if the straight segment between two voxel centers is unobstructed
+    ///
+    /// Walks the BCC cells pierced by the segment from `a` to `b` (see
+    /// [`raycast`]) and stops at the first voxel with occupancy probability
+    /// ≥ `occ_threshold`. The start voxel `a` is not tested, the end voxel
+    /// `b` is, so a target surface reports itself as the blocking cell.
+    /// Unobserved voxels do not block.
+    ///
+    /// Both voxels must share frame, scale tier and LOD.
+    pub fn line_of_sight(&self, a: Index64, b: Index64, occ_threshold: f32) -> Result<LineOfSight> {
+        if a.frame_id() != b.frame_id() || a.scale_tier() != b.scale_tier() || a.lod() != b.lod() {
+            return Err(Error::InvalidFormat(format!(
+                "line of sight endpoints must share frame, tier and LOD: {:?} and {:?}",
+                a, b
+            )));
+        }
+        if a == b {
+            return Ok(LineOfSight::Clear);
+        }
+
+        let (ax, ay, az) = a.decode_coords();
+        let (bx, by, bz) = b.decode_coords();
+        let origin = (ax as f64, ay as f64, az as f64);
+        let delta = (
+            bx as f64 - origin.0,
+            by as f64 - origin.1,
+            bz as f64 - origin.2,
+        );
+        let length = (delta.0 * delta.0 + delta.1 * delta.1 + delta.2 * delta.2).sqrt();
+
+        let blocks = |idx: Index64| {
+            self.voxels
+                .get(&idx)
+                .is_some_and(|v| log_odds_to_prob(v.log_odds) >= occ_threshold)
+        };
+        for cell in raycast(origin, delta, length)?.skip(1) {
+            let c = cell.coord;
+            let (Ok(x), Ok(y), Ok(z)) =
+                (u16::try_from(c.x), u16::try_from(c.y), u16::try_from(c.z))
+            else {
+                continue; // Outside the addressable range, so never occupied
+            };
+            let idx = Index64::new(a.frame_id(), a.scale_tier(), a.lod(), x, y, z)?;
+            if blocks(idx) {
+                return Ok(LineOfSight::Blocked(idx));
+            }
+            if idx == b {
+                break;
+            }
+        }
+        Ok(LineOfSight::Clear)
+    }
+
+    /// Insert encoded voxels, replacing cells already present or, with
+    /// `fuse`, adding to their log-odds and measurement counts
+    fn insert_state(&mut self, bytes: &[u8], fuse: bool) -> Result<()> {
+        for (idx, [log_odds, measurement_count]) in decode_voxels(bytes)? {
+            self.dirty.mark(idx);
+            let mut voxel = OccupancyVoxel {
+                log_odds: f32::from_bits(log_odds),
+                measurement_count,
+            };
+            if let Some(old) = self.voxels.get(&idx).filter(|_| fuse) {
+                voxel.log_odds =
+                    (old.log_odds + voxel.log_odds).clamp(self.min_log_odds, self.max_log_odds);
+                voxel.measurement_count = old.measurement_count.saturating_add(measurement_count);
+            }
+            let old_state = self.summary.as_ref().map(|_| self.get_state(idx));
+            self.voxels.insert(idx, voxel);
+            if let Some(old_state) = old_state {
+                let new_state = self.classify(voxel.log_odds);
+                if let Some(summary) = &mut self.summary {
+                    summary.apply(idx, old_state, new_state);
+                }
+            }
+        }
+        Ok(())
+    }
 }
 
 impl Default for OccupancyLayer {
@@ -367,6 +657,46 @@ impl Layer for OccupancyLayer {
         }
     }
 
+    /// Fuse a batch of occupancy measurements
+    ///
+    /// Non-occupancy measurements are ignored, as in
+    /// [`update`](Layer::update). Voxels are fused in parallel, each from
+    /// its measurements in batch order, so the result is bit-identical to
+    /// sequential updates.
+    fn update_batch(&mut self, updates: &[(Index64, Measurement)]) -> Result<()> {
+        let observations = updates
+            .iter()
+            .filter_map(|(idx, measurement)| match &measurement.data {
+                MeasurementData::Occupancy { occupied } => Some((
+                    *idx,
+                    observation_log_odds(*occupied, measurement.confidence),
+                )),
+                _ => None,
+            })
+            .collect();
+
+        let (min_log_odds, max_log_odds) = (self.min_log_odds, self.max_log_odds);
+        let voxels = &self.voxels;
+        let fused = fold_by_voxel(observations, |idx, observations| {
+            let mut voxel = voxels.get(&idx).copied().unwrap_or_default();
+            for &(_, log_odds) in observations {
+                voxel.log_odds = (voxel.log_odds + log_odds).clamp(min_log_odds, max_log_odds);
+                voxel.measurement_count += 1;
+            }
+            voxel
+        });
+        for (idx, voxel) in fused {
+            self.dirty.mark(idx);
+            let old_state = self.summary.as_ref().map(|_| self.get_state(idx));
+            let new_state = self.classify(voxel.log_odds);
+            self.voxels.insert(idx, voxel);
+            if let (Some(old_state), Some(summary)) = (old_state, &mut self.summary) {
+                summary.apply(idx, old_state, new_state);
+            }
+        }
+        Ok(())
+    }
+
     fn query(&self, idx: Index64) -> Option<f32> {
         self.get_probability(idx)
     }
@@ -375,13 +705,98 @@ impl Layer for OccupancyLayer {
         self.voxels.len()
     }
 
+    fn indices(&self) -> Box<dyn Iterator<Item = Index64> + '_> {
+        Box::new(self.voxels.keys().copied())
+    }
+
     fn clear(&mut self) {
+        self.dirty.mark_all(self.voxels.keys().copied());
         self.voxels.clear();
+        if let Some(summary) = &mut self.summary {
+            summary.clear();
+        }
+    }
+
+    fn dirty_tracker(&self) -> Option<&DirtyTracker> {
+        Some(&self.dirty)
+    }
+
+    fn dirty_tracker_mut(&mut self) -> Option<&mut DirtyTracker> {
+        Some(&mut self.dirty)
+    }
+
+    fn encode_state(&self) -> Option<Vec<u8>> {
+        let records = self
+            .voxels
+            .iter()
+            .map(|(&idx, v)| (idx, [v.log_odds.to_bits(), v.measurement_count]))
+            .collect();
+        Some(encode_voxels(records))
+    }
+
+    fn encode_cells(&self, cells: &[Index64]) -> Option<Vec<u8>> {
+        let records = cells
+            .iter()
+            .filter_map(|&idx| {
+                let v = self.voxels.get(&idx)?;
+                Some((idx, [v.log_odds.to_bits(), v.measurement_count]))
+            })
+            .collect();
+        Some(encode_voxels(records))
+    }
+
+    fn decode_state(&mut self, bytes: &[u8]) -> Result<()> {
+        let records = decode_voxels(bytes)?;
+        self.clear();
+        for (idx, [log_odds, measurement_count]) in records {
+            self.dirty.mark(idx);
+            self.voxels.insert(
+                idx,
+                OccupancyVoxel {
+                    log_odds: f32::from_bits(log_odds),
+                    measurement_count,
+                },
+            );
+        }
+        self.rebuild_summary();
+        Ok(())
+    }
+
+    fn merge_state(&mut self, bytes: &[u8]) -> Result<()> {
+        self.insert_state(bytes, false)
+    }
+
+    fn fuse_state(&mut self, bytes: &[u8]) -> Result<()> {
+        self.insert_state(bytes, true)
+    }
+
+    fn config(&self) -> Option<LayerConfig> {
+        Some(
+            LayerConfig::new(LayerType::Occupancy, CONFIG_VERSION)
+                .with("occupied_log_odds", self.occupied_threshold as f64)
+                .with("free_log_odds", self.free_threshold as f64)
+                .with("max_log_odds", self.max_log_odds as f64)
+                .with("min_log_odds", self.min_log_odds as f64),
+        )
+    }
+
+    fn apply_config(&mut self, config: &LayerConfig) -> Result<()> {
+        self.occupied_threshold =
+            config.get_or("occupied_log_odds", self.occupied_threshold as f64) as f32;
+        self.free_threshold = config.get_or("free_log_odds", self.free_threshold as f64) as f32;
+        self.max_log_odds = config.get_or("max_log_odds", self.max_log_odds as f64) as f32;
+        self.min_log_odds = config.get_or("min_log_odds", self.min_log_odds as f64) as f32;
+        self.rebuild_summary();
+        Ok(())
     }
 
     fn memory_usage(&self) -> usize {
         // Each entry: Index64 (8 bytes) + OccupancyVoxel (8 bytes) + HashMap overhead (~24 bytes)
         self.voxels.len() * 40
+            + self
+                .summary
+                .as_ref()
+                .map_or(0, OccupancySummary::memory_usage)
     }
 }
 
@@ -400,6 +815,129 @@ pub struct OccupancyStats {
     pub total_measurements: u32,
 }
 
+/// Result of [`OccupancyLayer::line_of_sight`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineOfSight {
+    /// No voxel along the segment reaches the threshold
+    Clear,
+    /// The segment is blocked, first at this voxel
+    Blocked(Index64),
+}
+
+impl LineOfSight {
+    /// Check if the segment is unobstructed
+    pub fn is_clear(&self) -> bool {
+        matches!(self, LineOfSight::Clear)
+    }
+
+    /// First blocking voxel, if any
+    pub fn blocking(&self) -> Option<Index64> {
+        match *self {
+            LineOfSight::Clear => None,
+            LineOfSight::Blocked(idx) => Some(idx),
+        }
+    }
+}
+
+/// A 14-connected group of voxels with summary statistics
+#[derive(Debug, Clone, PartialEq)]
+pub struct OccupancyComponent {
+    /// Member voxels in ascending index order
+    pub cells: Vec<Index64>,
+    /// Number of voxels
+    pub volume: usize,
+    /// Minimum voxel coordinates
+    pub min: (u16, u16, u16),
+    /// Maximum voxel coordinates
+    pub max: (u16, u16, u16),
+    /// Mean voxel coordinates
+    pub centroid: [f64; 3],
+}
+
+impl OccupancyComponent {
+    fn from_cells(mut cells: Vec<Index64>) -> Self {
+        cells.sort_unstable();
+        let mut min = (u16::MAX, u16::MAX, u16::MAX);
+        let mut max = (0, 0, 0);
+        let mut sum = [0.0f64; 3];
+        for cell in &cells {
+            let (x, y, z) = cell.decode_coords();
+            min = (min.0.min(x), min.1.min(y), min.2.min(z));
+            max = (max.0.max(x), max.1.max(y), max.2.max(z));
+            sum[0] += x as f64;
+            sum[1] += y as f64;
+            sum[2] += z as f64;
+        }
+        let n = cells.len() as f64;
+        Self {
+            volume: cells.len(),
+            min,
+            max,
+            centroid: [sum[0] / n, sum[1] / n, sum[2] / n],
+            cells,
+        }
+    }
+}
+
+/// Label the connected groups of voxels with occupancy probability ≥ `threshold`
+///
+/// Voxels are connected through the 14 BCC neighbors within the same frame
+/// and LOD. Components are returned largest first. Use
+/// [`connected_components_by`] for other selections, such as free-space
+/// pockets.
+pub fn connected_components(layer: &OccupancyLayer, threshold: f32) -> Vec<OccupancyComponent> {
+    connected_components_by(layer, |p| p >= threshold)
+}
+
+/// Label the connected groups of voxels whose probability satisfies `select`
+///
+/// Only voxels stored in the layer are considered, so unobserved space
+/// never joins a component. For example `|p| p < 0.3` segments observed
+/// free space into pockets.
+pub fn connected_components_by<F>(layer: &OccupancyLayer, select: F) -> Vec<OccupancyComponent>
+where
+    F: Fn(f32) -> bool,
+{
+    let mut remaining: HashSet<Index64> = layer
+        .voxels
+        .iter()
+        .filter(|(_, v)| select(log_odds_to_prob(v.log_odds)))
+        .map(|(&idx, _)| idx)
+        .collect();
+
+    let mut components = Vec::new();
+    let mut seeds: Vec<Index64> = remaining.iter().copied().collect();
+    seeds.sort_unstable();
+    for seed in seeds {
+        if !remaining.remove(&seed) {
+            continue;
+        }
+        let mut cells = vec![seed];
+        let mut stack = vec![seed];
+        while let Some(cell) = stack.pop() {
+            for neighbor in neighbors_index64(cell) {
+                if remaining.remove(&neighbor) {
+                    cells.push(neighbor);
+                    stack.push(neighbor);
+                }
+            }
+        }
+        components.push(OccupancyComponent::from_cells(cells));
+    }
+
+    components.sort_by(|a, b| b.volume.cmp(&a.volume).then(a.cells[0].cmp(&b.cells[0])));
+    components
+}
+
+/// Log-odds evidence of one observation
+fn observation_log_odds(occupied: bool, confidence: f32) -> f32 {
+    if occupied {
+        prob_to_log_odds(confidence)
+    } else {
+        prob_to_log_odds(1.0 - confidence)
+    }
+}
+
 /// Convert probability to log-odds
 ///
 /// L = log(p / (1-p))
@@ -434,6 +972,42 @@ mod tests {
         assert!((p - p2).abs() < 1e-5);
     }
 
+    #[test]
+    fn test_knn_matches_brute_force() -> Result<()> {
+        let mut layer = OccupancyLayer::new();
+        // Pseudo-random occupied BCC voxels plus free space around the query
+        let mut state = 12345u32;
+        for _ in 0..300 {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            let parity = (state >> 30) as u16 & 1;
+            let c = |shift: u32| ((state >> shift) as u16 % 40) * 2 + parity;
+            layer.update_occupancy(Index64::new(0, 0, 5, c(2), c(9), c(16))?, true, 0.95);
+        }
+        for x in 30..50u16 {
+            layer.update_occupancy(Index64::new(0, 0, 5, x, 40, 40)?, false, 0.95);
+        }
+
+        let query = Index64::new(0, 0, 5, 40, 40, 40)?;
+        let mut expected: Vec<f32> = layer
+            .get_occupied_voxels()
+            .into_iter()
+            .map(|idx| knn::voxel_distance(query, idx))
+            .collect();
+        expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        // Small k resolves in the shell search, large k falls back to a scan
+        for k in [1, 5, 250, 1000] {
+            let hits = layer.knn(query, k);
+            let distances: Vec<f32> = hits.iter().map(|&(_, d)| d).collect();
+            assert_eq!(distances, expected[..k.min(expected.len())]);
+            assert!(hits
+                .iter()
+                .all(|&(idx, _)| layer.get_state(idx) == OccupancyState::Occupied));
+        }
+        assert!(layer.knn(query, 0).is_empty());
+        Ok(())
+    }
+
     #[test]
     fn test_occupancy_layer_creation() {
         let layer = OccupancyLayer::new();
@@ -546,4 +1120,174 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_connected_components() -> Result<()> {
+        let mut layer = OccupancyLayer::new();
+        // A bar of five diagonal steps and a separate pair
+        for i in 0..5u16 {
+            layer.update_occupancy(Index64::new(0, 0, 5, 10 + i, 10 + i, 10 + i)?, true, 0.9);
+        }
+        layer.update_occupancy(Index64::new(0, 0, 5, 40, 40, 40)?, true, 0.9);
+        layer.update_occupancy(Index64::new(0, 0, 5, 42, 40, 40)?, true, 0.9);
+        // Free voxels bridging the two are not part of either
+        layer.update_occupancy(Index64::new(0, 0, 5, 15, 15, 15)?, false, 0.9);
+
+        let components = connected_components(&layer, 0.7);
+        assert_eq!(components.len(), 2);
+        assert_eq!(components[0].volume, 5);
+        assert_eq!(components[0].min, (10, 10, 10));
+        assert_eq!(components[0].max, (14, 14, 14));
+        assert_eq!(components[0].centroid, [12.0, 12.0, 12.0]);
+        assert_eq!(components[1].cells.len(), 2);
+        assert_eq!(components[1].centroid, [41.0, 40.0, 40.0]);
+
+        let free = connected_components_by(&layer, |p| p < 0.3);
+        assert_eq!(free.len(), 1);
+        assert_eq!(free[0].cells, vec![Index64::new(0, 0, 5, 15, 15, 15)?]);
+        Ok(())
+    }
+    #[test]
+    fn test_line_of_sight() -> Result<()> {
+        let cell = |x, y, z| Index64::new(0, 0, 5, x, y, z);
+        let mut layer = OccupancyLayer::new();
+        layer.update_occupancy(cell(20, 10, 10)?, true, 0.9);
+        layer.update_occupancy(cell(15, 15, 15)?, true, 0.9);
+        layer.update_occupancy(cell(16, 10, 10)?, false, 0.9);
+
+        let (a, b) = (cell(10, 10, 10)?, cell(30, 10, 10)?);
+        let blocked = layer.line_of_sight(a, b, 0.7)?;
+        assert_eq!(blocked, LineOfSight::Blocked(cell(20, 10, 10)?));
+        assert!(!blocked.is_clear());
+        // Below the voxel's probability the wall is see-through
+        assert!(layer.line_of_sight(a, b, 0.95)?.is_clear());
+
+        // Diagonal steps cross the hexagonal faces
+        let diagonal = layer.line_of_sight(a, cell(20, 20, 20)?, 0.7)?;
+        assert_eq!(diagonal.blocking(), Some(cell(15, 15, 15)?));
+        assert!(layer.line_of_sight(a, cell(10, 30, 10)?, 0.7)?.is_clear());
+
+        // The target is tested, the start is not
+        let wall = cell(20, 10, 10)?;
+        assert_eq!(layer.line_of_sight(a, wall, 0.7)?.blocking(), Some(wall));
+        assert!(layer.line_of_sight(wall, a, 0.7)?.is_clear());
+
+        assert!(layer
+            .line_of_sight(a, Index64::new(0, 0, 6, 30, 10, 10)?, 0.7)
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_point_cloud_clears_ghosts() -> Result<()> {
+        let cell = |x, y, z| Index64::new(0, 0, 5, x, y, z);
+        let config = IngestConfig::default();
+        let mut layer = OccupancyLayer::new();
+        // Something that has since moved away
+        let ghost = cell(20, 10, 10)?;
+        layer.update_occupancy(ghost, true, 0.9);
+
+        let wall: Vec<_> = (8..=12).map(|y| (3.0, y as f32 * 0.1, 1.0)).collect();
+        for _ in 0..5 {
+            assert_eq!(
+                layer.integrate_point_cloud(&wall, (1.0, 1.0, 1.0), &config)?,
+                5
+            );
+        }
+        assert_eq!(layer.get_state(ghost), OccupancyState::Free);
+        assert_eq!(layer.get_state(cell(30, 10, 10)?), OccupancyState::Occupied);
+        // One update per voxel and scan
+        assert_eq!(layer.get_measurement_count(cell(30, 10, 10)?), 5);
+
+        // A hit is not cleared by a longer ray through it in the same cloud
+        let mut layer = OccupancyLayer::new();
+        let cloud = [(2.0, 1.0, 1.0), (3.0, 1.0, 1.0)];
+        layer.integrate_point_cloud(&cloud, (1.0, 1.0, 1.0), &config)?;
+        assert_eq!(layer.get_state(ghost), OccupancyState::Occupied);
+
+        // Beyond the maximum range only free space is carved
+        let short = IngestConfig {
+            max_range: 1.5,
+            ..config
+        };
+        let mut layer = OccupancyLayer::new();
+        assert_eq!(
+            layer.integrate_point_cloud(&[(4.0, 1.0, 1.0)], (1.0, 1.0, 1.0), &short)?,
+            0
+        );
+        assert!(layer.get_probability(ghost).unwrap() < 0.5);
+        assert_eq!(layer.get_state(cell(30, 10, 10)?), OccupancyState::Unknown);
+        assert!(layer.get_occupied_voxels().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_subtree_summaries() -> Result<()> {
+        let cell = |lod, x, y, z| Index64::new(0, 0, lod, x, y, z);
+        let mut layer = OccupancyLayer::new().with_summaries(3);
+        assert_eq!(layer.summary_lod(), Some(3));
+
+        // Fill the 2×2×2 block under a LOD 2 cell with free leaves
+        let parent = cell(2, 1, 1, 1)?;
+        for child in parent.children() {
+            layer.update_occupancy(child, false, 0.9);
+        }
+        assert!(layer.all_free(parent));
+        assert!(!layer.any_occupied(parent));
+        assert!(!layer.all_free(parent.parent().unwrap()));
+
+        // One leaf turning occupied flips every ancestor
+        let leaf = parent.children()[5];
+        for _ in 0..3 {
+            layer.update_occupancy(leaf, true, 0.9);
+        }
+        let mut ancestor = leaf.parent();
+        while let Some(a) = ancestor {
+            assert!(layer.any_occupied(a) && !layer.all_free(a));
+            ancestor = a.parent();
+        }
+        let summary = layer.subtree(cell(0, 0, 0, 0)?).unwrap();
+        assert_eq!((summary.occupied, summary.free, summary.cells), (1, 7, 512));
+        assert!(!layer.any_occupied(cell(2, 0, 0, 0)?));
+
+        // Finer cells are not summarized
+        assert_eq!(layer.subtree(cell(4, 0, 0, 0)?), None);
+        assert!(layer.any_occupied(cell(4, 0, 0, 0)?));
+
+        // Threshold changes and state restores recount
+        let config = layer.config().unwrap().with("occupied_log_odds", 10.0);
+        layer.apply_config(&config)?;
+        assert!(!layer.any_occupied(parent));
+        let mut restored = OccupancyLayer::new().with_summaries(3);
+        restored.decode_state(&layer.encode_state().unwrap())?;
+        assert_eq!(restored.subtree(parent).unwrap().free, 7);
+        layer.clear();
+        assert!(!layer.any_occupied(cell(0, 0, 0, 0)?));
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_batch_matches_sequential() -> Result<()> {
+        let mut updates = Vec::new();
+        for k in 0..300u16 {
+            let idx = Index64::new(0, 0, 5, 100 + 2 * (k % 11), 100, 100)?;
+            let measurement = match k % 4 {
+                0 => Measurement::free(0.7),
+                3 => Measurement::depth(1.0, 1.0),
+                _ => Measurement::occupied(0.6 + (k % 5) as f32 * 0.08),
+            };
+            updates.push((idx, measurement));
+        }
+        let mut sequential = OccupancyLayer::new().with_summaries(5);
+        for (idx, measurement) in &updates {
+            sequential.update(*idx, measurement)?;
+        }
+        let mut batched = OccupancyLayer::new().with_summaries(5);
+        batched.update_batch(&updates)?;
+
+        assert_eq!(batched.encode_state(), sequential.encode_state());
+        let parent = updates[0].0.parent().unwrap();
+        assert_eq!(batched.subtree(parent), sequential.subtree(parent));
+        Ok(())
+    }
 }
@@ -0,0 +1,380 @@
+//! Bitset occupancy for binary free/blocked maps
+//!
+//! [`BinaryOccupancyLayer`] keeps one bit per cell in blocks of 8×8×8 cells
+//! (64 bytes each), with no probabilities, timestamps or change tracking.
+//! Set operations between layers work a block at a time on whole words,
+//! which makes them suitable for games and coarse planning where a cell is
+//! simply blocked or not.
+
+use super::measurement::MeasurementData;
+use super::soa::{join, split};
+use super::{Layer, LayerType, Measurement};
+use crate::error::{Error, Result};
+use crate::Index64;
+use std::collections::HashMap;
+
+const STATE_VERSION: u8 = 1;
+
+/// Words per block bitset
+const WORDS: usize = 8;
+
+type Block = [u64; WORDS];
+
+/// Binary occupancy map: a set of blocked cells
+///
+/// Cells not in the set are free. Unlike [`OccupancyLayer`](super::OccupancyLayer)
+/// there is no unknown state and no sensor fusion: the last write wins.
+///
+/// # Example
+///
+/// ```
+/// use octaindex3d::layers::BinaryOccupancyLayer;
+/// use octaindex3d::Index64;
+///
+/// # fn main() -> octaindex3d::Result<()> {
+/// let wall = Index64::new(0, 0, 8, 10, 10, 10)?;
+/// let door = Index64::new(0, 0, 8, 10, 11, 10)?;
+///
+/// let mut walls = BinaryOccupancyLayer::new();
+/// walls.insert(wall);
+/// walls.insert(door);
+///
+/// let mut open_doors = BinaryOccupancyLayer::new();
+/// open_doors.insert(door);
+///
+/// walls.difference_with(&open_doors);
+/// assert!(walls.is_blocked(wall));
+/// assert!(!walls.is_blocked(door));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BinaryOccupancyLayer {
+    /// Bitsets keyed by the raw index of each block's first cell; blocks
+    /// are never empty
+    blocks: HashMap<u64, Block>,
+    /// Blocked cells
+    len: usize,
+}
+
+impl BinaryOccupancyLayer {
+    /// Create an empty map, with every cell free
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of blocked cells
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether no cell is blocked
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Whether a cell is blocked
+    #[inline]
+    pub fn is_blocked(&self, idx: Index64) -> bool {
+        let (base, slot) = split(idx);
+        self.blocks
+            .get(&base)
+            .is_some_and(|block| block[slot / 64] & (1 << (slot % 64)) != 0)
+    }
+
+    /// Block a cell, returning whether it was free
+    pub fn insert(&mut self, idx: Index64) -> bool {
+        let (base, slot) = split(idx);
+        let word = &mut self.blocks.entry(base).or_default()[slot / 64];
+        let bit = 1 << (slot % 64);
+        let added = *word & bit == 0;
+        *word |= bit;
+        self.len += added as usize;
+        added
+    }
+
+    /// Free a cell, returning whether it was blocked
+    pub fn remove(&mut self, idx: Index64) -> bool {
+        let (base, slot) = split(idx);
+        let Some(block) = self.blocks.get_mut(&base) else {
+            return false;
+        };
+        let bit = 1 << (slot % 64);
+        let removed = block[slot / 64] & bit != 0;
+        block[slot / 64] &= !bit;
+        if removed {
+            self.len -= 1;
+            if block.iter().all(|&w| w == 0) {
+                self.blocks.remove(&base);
+            }
+        }
+        removed
+    }
+
+    /// Block or free a cell
+    pub fn set(&mut self, idx: Index64, blocked: bool) {
+        if blocked {
+            self.insert(idx);
+        } else {
+            self.remove(idx);
+        }
+    }
+
+    /// Blocked cells, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = Index64> + '_ {
+        self.blocks.iter().flat_map(|(&base, block)| {
+            block
+                .iter()
+                .enumerate()
+                .flat_map(move |(w, &word)| BitIter(word).map(move |bit| join(base, w * 64 + bit)))
+        })
+    }
+
+    /// Block every cell blocked in `other`
+    pub fn union_with(&mut self, other: &Self) {
+        for (&base, theirs) in &other.blocks {
+            let ours = self.blocks.entry(base).or_default();
+            let before = count(ours);
+            for (a, b) in ours.iter_mut().zip(theirs) {
+                *a |= b;
+            }
+            self.len += count(ours) - before;
+        }
+    }
+
+    /// Keep only cells also blocked in `other`
+    pub fn intersect_with(&mut self, other: &Self) {
+        let mut len = 0;
+        self.blocks.retain(|base, ours| {
+            let Some(theirs) = other.blocks.get(base) else {
+                return false;
+            };
+            for (a, b) in ours.iter_mut().zip(theirs) {
+                *a &= b;
+            }
+            len += count(ours);
+            ours.iter().any(|&w| w != 0)
+        });
+        self.len = len;
+    }
+
+    /// Free every cell blocked in `other`
+    pub fn difference_with(&mut self, other: &Self) {
+        let mut len = 0;
+        self.blocks.retain(|base, ours| {
+            if let Some(theirs) = other.blocks.get(base) {
+                for (a, b) in ours.iter_mut().zip(theirs) {
+                    *a &= !b;
+                }
+            }
+            len += count(ours);
+            ours.iter().any(|&w| w != 0)
+        });
+        self.len = len;
+    }
+
+    /// Whether every cell blocked here is blocked in `other`
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.blocks.iter().all(|(base, ours)| {
+            other
+                .blocks
+                .get(base)
+                .is_some_and(|theirs| ours.iter().zip(theirs).all(|(a, b)| a & !b == 0))
+        })
+    }
+}
+
+fn count(block: &Block) -> usize {
+    block.iter().map(|w| w.count_ones() as usize).sum()
+}
+
+/// Positions of the set bits of a word, lowest first
+struct BitIter(u64);
+
+impl Iterator for BitIter {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.0 == 0 {
+            return None;
+        }
+        let bit = self.0.trailing_zeros() as usize;
+        self.0 &= self.0 - 1;
+        Some(bit)
+    }
+}
+
+impl FromIterator<Index64> for BinaryOccupancyLayer {
+    fn from_iter<I: IntoIterator<Item = Index64>>(iter: I) -> Self {
+        let mut layer = Self::new();
+        for idx in iter {
+            layer.insert(idx);
+        }
+        layer
+    }
+}
+
+impl Layer for BinaryOccupancyLayer {
+    fn layer_type(&self) -> LayerType {
+        LayerType::Occupancy
+    }
+
+    /// Occupancy measurements block or free the cell, regardless of their
+    /// confidence; other measurements are ignored
+    fn update(&mut self, idx: Index64, measurement: &Measurement) -> Result<()> {
+        if let MeasurementData::Occupancy { occupied } = measurement.data {
+            self.set(idx, occupied);
+        }
+        Ok(())
+    }
+
+    /// `Some(1.0)` for blocked cells, `None` for free ones
+    fn query(&self, idx: Index64) -> Option<f32> {
+        self.is_blocked(idx).then_some(1.0)
+    }
+
+    fn voxel_count(&self) -> usize {
+        self.len
+    }
+
+    fn indices(&self) -> Box<dyn Iterator<Item = Index64> + '_> {
+        Box::new(self.iter())
+    }
+
+    fn clear(&mut self) {
+        self.blocks.clear();
+        self.len = 0;
+    }
+
+    fn memory_usage(&self) -> usize {
+        // Key, bitset and hash map overhead per block
+        self.blocks.len() * (8 + std::mem::size_of::<Block>() + 16)
+    }
+
+    /// Layout: version byte, block count (u64), then per block its base
+    /// index and 8 bitset words, sorted by base, all little endian
+    fn encode_state(&self) -> Option<Vec<u8>> {
+        let mut blocks: Vec<_> = self.blocks.iter().collect();
+        blocks.sort_unstable_by_key(|(&base, _)| base);
+        let mut out = Vec::with_capacity(9 + blocks.len() * 8 * (WORDS + 1));
+        out.push(STATE_VERSION);
+        out.extend_from_slice(&(blocks.len() as u64).to_le_bytes());
+        for (base, block) in blocks {
+            out.extend_from_slice(&base.to_le_bytes());
+            for word in block {
+                out.extend_from_slice(&word.to_le_bytes());
+            }
+        }
+        Some(out)
+    }
+
+    fn decode_state(&mut self, bytes: &[u8]) -> Result<()> {
+        if bytes.len() < 9 || bytes[0] != STATE_VERSION {
+            return Err(Error::InvalidFormat(
+                "unsupported binary occupancy state".to_string(),
+            ));
+        }
+        let block_count = u64::from_le_bytes(bytes[1..9].try_into().unwrap());
+        let body = &bytes[9..];
+        let record = 8 * (WORDS + 1);
+        if block_count.checked_mul(record as u64) != Some(body.len() as u64) {
+            return Err(Error::InvalidFormat(format!(
+                "binary occupancy state holds {} bytes for {} blocks",
+                body.len(),
+                block_count
+            )));
+        }
+        let mut blocks = HashMap::with_capacity(block_count as usize);
+        let mut len = 0;
+        for chunk in body.chunks_exact(record) {
+            let mut words = chunk
+                .chunks_exact(8)
+                .map(|w| u64::from_le_bytes(w.try_into().unwrap()));
+            let base = words.next().unwrap_or_default();
+            if split(Index64::from_value(base)?) != (base, 0) {
+                return Err(Error::InvalidFormat(format!(
+                    "binary occupancy block base {base:#x} is not a block's first cell"
+                )));
+            }
+            let mut block = [0u64; WORDS];
+            block.iter_mut().zip(words).for_each(|(b, w)| *b = w);
+            if block.iter().all(|&w| w == 0) {
+                continue;
+            }
+            len += count(&block);
+            if blocks.insert(base, block).is_some() {
+                return Err(Error::InvalidFormat(format!(
+                    "binary occupancy block base {base:#x} appears twice"
+                )));
+            }
+        }
+        self.blocks = blocks;
+        self.len = len;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(range: std::ops::Range<u16>) -> BinaryOccupancyLayer {
+        range
+            .map(|x| Index64::new(0, 0, 8, x, 5, 5).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let a = cells(0..100);
+        let b = cells(50..150);
+
+        let mut union = a.clone();
+        union.union_with(&b);
+        assert_eq!(union.len(), 150);
+
+        let mut both = a.clone();
+        both.intersect_with(&b);
+        assert_eq!(both.len(), 50);
+        assert!(both.is_subset(&a) && both.is_subset(&b));
+        assert!(!a.is_subset(&b));
+
+        let mut only_a = a.clone();
+        only_a.difference_with(&b);
+        assert_eq!(only_a, cells(0..50));
+
+        let mut sorted: Vec<_> = only_a.iter().collect();
+        sorted.sort();
+        assert_eq!(sorted.len(), 50);
+        assert_eq!(sorted[0].decode_coords(), (0, 5, 5));
+
+        // Emptied blocks are dropped
+        only_a.difference_with(&a);
+        assert!(only_a.is_empty());
+        assert_eq!(only_a.memory_usage(), 0);
+    }
+
+    #[test]
+    fn test_layer_state_round_trip() {
+        let mut layer = cells(10..700);
+        let idx = Index64::new(0, 0, 8, 10, 5, 5).unwrap();
+        layer.update(idx, &Measurement::free(0.9)).unwrap();
+        assert_eq!(layer.query(idx), None);
+        assert_eq!(layer.voxel_count(), 689);
+
+        let state = layer.encode_state().unwrap();
+        let mut restored = BinaryOccupancyLayer::new();
+        restored.decode_state(&state).unwrap();
+        assert_eq!(restored, layer);
+        assert!(restored.decode_state(&state[..state.len() - 1]).is_err());
+
+        // Block bases must be a block's first cell, each given once
+        let mut bad = state.clone();
+        bad[9] |= 1;
+        assert!(restored.decode_state(&bad).is_err());
+        let mut bad = state.clone();
+        bad.copy_within(9..81, 81);
+        assert!(restored.decode_state(&bad).is_err());
+        assert_eq!(restored, layer);
+    }
+}
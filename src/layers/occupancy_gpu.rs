@@ -1,8 +1,10 @@
 //! GPU-Accelerated Occupancy Operations
 //!
 //! Provides Metal and CUDA backends for massive parallel ray casting
-//! and occupancy updates on BCC lattice.
+//! and occupancy updates on BCC lattice, and batch information-gain scoring
+//! of exploration viewpoints.
 
+use super::exploration::{InformationGainConfig, OccupancySnapshot, ViewpointBatch};
 use super::occupancy::OccupancyLayer;
 use crate::error::{Error, Result};
 use crate::Index64;
@@ -40,12 +42,57 @@ pub trait RayCastBackend: Send + Sync {
         occupied_confidence: f32,
     ) -> Result<Vec<(Index64, bool, f32)>>;
 
+    /// Score a batch of exploration viewpoints against an occupancy snapshot
+    ///
+    /// Returns one information gain per viewpoint, matching
+    /// [`OccupancySnapshot::viewpoint_gain`]. Backends without a scoring
+    /// kernel return an error so callers fall back to the CPU path.
+    fn score_viewpoints(
+        &self,
+        _snapshot: &OccupancySnapshot,
+        _batch: &ViewpointBatch,
+        _config: &InformationGainConfig,
+    ) -> Result<Vec<f32>> {
+        Err(Error::InvalidFormat(format!(
+            "{} backend does not support viewpoint scoring",
+            self.name()
+        )))
+    }
+
     /// Get minimum batch size for efficient GPU usage
     fn min_batch_size(&self) -> usize {
         100 // Rays
     }
 }
 
+/// Largest de-duplication table buffer a scoring batch may use
+const MAX_SCORE_TABLE_BYTES: u64 = 256 << 20;
+
+/// Slots in each viewpoint's GPU de-duplication table
+///
+/// A power of two at least the number of samples a viewpoint's rays take,
+/// so the table can never fill up. `None` if the batch's tables would
+/// exceed [`MAX_SCORE_TABLE_BYTES`]; such batches are scored on the CPU.
+#[cfg_attr(not(all(feature = "gpu-metal", target_os = "macos")), allow(dead_code))]
+fn score_table_capacity(batch: &ViewpointBatch, config: &InformationGainConfig) -> Option<u32> {
+    // Same step count as the CPU ray march
+    let step_size = config.voxel_size * 0.5;
+    let steps = if step_size > 0.0 {
+        (config.sensor_range / step_size) as u64
+    } else {
+        0
+    };
+    let samples = (batch.rays_per_viewpoint as u64).checked_mul(steps)?;
+    let capacity = samples.max(1).checked_next_power_of_two()?;
+    let bytes = capacity
+        .checked_mul(batch.origins.len() as u64)?
+        .checked_mul(std::mem::size_of::<u32>() as u64)?;
+    if bytes > MAX_SCORE_TABLE_BYTES {
+        return None;
+    }
+    u32::try_from(capacity).ok()
+}
+
 impl GpuRayCaster {
     /// Create new GPU ray caster with best available backend
     pub fn new() -> Result<Self> {
@@ -53,10 +100,31 @@ impl GpuRayCaster {
         Ok(Self { backend })
     }
 
+    /// Create a GPU ray caster whose backend can score viewpoints
+    ///
+    /// Only Metal has a viewpoint scoring kernel.
+    pub fn for_viewpoint_scoring() -> Result<Self> {
+        #[cfg(all(feature = "gpu-metal", target_os = "macos"))]
+        {
+            if let Ok(backend) = MetalRayCaster::new() {
+                return Ok(Self {
+                    backend: Box::new(backend),
+                });
+            }
+        }
+
+        Err(Error::InvalidFormat(
+            "No GPU viewpoint scoring backend available".to_string(),
+        ))
+    }
+
     /// Get best available GPU backend
     fn best_backend() -> Result<Box<dyn RayCastBackend>> {
         // Try CUDA first (best for NVIDIA)
-        #[cfg(all(feature = "gpu-cuda", not(target_os = "windows")))]
+        #[cfg(all(
+            feature = "gpu-cuda",
+            not(any(target_os = "windows", target_arch = "wasm32"))
+        ))]
         {
             if let Ok(backend) = CudaRayCaster::new() {
                 return Ok(Box::new(backend));
@@ -100,11 +168,35 @@ impl GpuRayCaster {
         )
     }
 
+    /// Score exploration viewpoints on the GPU
+    ///
+    /// See [`OccupancyLayer::score_viewpoints`] for the high-level entry point
+    /// that falls back to the CPU automatically.
+    pub fn score_viewpoints(
+        &self,
+        snapshot: &OccupancySnapshot,
+        batch: &ViewpointBatch,
+        config: &InformationGainConfig,
+    ) -> Result<Vec<f32>> {
+        if batch.ray_directions.len() != batch.origins.len() * batch.rays_per_viewpoint {
+            return Err(Error::InvalidFormat(
+                "Ray directions must hold rays_per_viewpoint entries per origin".to_string(),
+            ));
+        }
+
+        self.backend.score_viewpoints(snapshot, batch, config)
+    }
+
     /// Get backend name
     pub fn backend_name(&self) -> &'static str {
         self.backend.name()
     }
 
+    /// Get minimum batch size for efficient GPU usage
+    pub fn min_batch_size(&self) -> usize {
+        self.backend.min_batch_size()
+    }
+
     /// Apply ray casting results to occupancy layer
     pub fn apply_to_layer(
         &self,
@@ -127,9 +219,27 @@ mod metal_impl {
 
     /// Ray-casting backend that runs occupancy updates on the GPU via Metal.
     pub struct MetalRayCaster {
-        _device: Arc<Device>,
-        _command_queue: Arc<CommandQueue>,
+        device: Arc<Device>,
+        command_queue: Arc<CommandQueue>,
         _pipeline: ComputePipelineState,
+        score_pipeline: ComputePipelineState,
+    }
+
+    /// Parameters for the `score_viewpoints` kernel (matches `ScoreParams`)
+    #[repr(C)]
+    struct ScoreParams {
+        min_x: i32,
+        min_y: i32,
+        min_z: i32,
+        dim_x: u32,
+        dim_y: u32,
+        dim_z: u32,
+        voxel_size: f32,
+        sensor_range: f32,
+        unknown_weight: f32,
+        viewpoint_count: u32,
+        rays_per_viewpoint: u32,
+        table_capacity: u32,
     }
 
     impl MetalRayCaster {
@@ -156,12 +266,36 @@ mod metal_impl {
                 .new_compute_pipeline_state_with_function(&kernel)
                 .map_err(|e| Error::InvalidFormat(format!("Failed to create pipeline: {}", e)))?;
 
+            let score_kernel = library
+                .get_function("score_viewpoints", None)
+                .map_err(|e| Error::InvalidFormat(format!("Failed to get kernel: {}", e)))?;
+
+            let score_pipeline = device
+                .new_compute_pipeline_state_with_function(&score_kernel)
+                .map_err(|e| Error::InvalidFormat(format!("Failed to create pipeline: {}", e)))?;
+
             Ok(Self {
-                _device: Arc::new(device),
-                _command_queue: Arc::new(command_queue),
+                device: Arc::new(device),
+                command_queue: Arc::new(command_queue),
                 _pipeline: pipeline,
+                score_pipeline,
             })
         }
+
+        fn shared_buffer<T>(&self, data: &[T]) -> Buffer {
+            // Metal rejects zero-length buffers
+            let len = std::mem::size_of_val(data).max(4) as u64;
+            if data.is_empty() {
+                self.device
+                    .new_buffer(len, MTLResourceOptions::StorageModeShared)
+            } else {
+                self.device.new_buffer_with_data(
+                    data.as_ptr() as *const std::ffi::c_void,
+                    len,
+                    MTLResourceOptions::StorageModeShared,
+                )
+            }
+        }
     }
 
     impl RayCastBackend for MetalRayCaster {
@@ -185,6 +319,92 @@ mod metal_impl {
             // For now, return empty (will be implemented in shader)
             Ok(Vec::new())
         }
+
+        fn score_viewpoints(
+            &self,
+            snapshot: &OccupancySnapshot,
+            batch: &ViewpointBatch,
+            config: &InformationGainConfig,
+        ) -> Result<Vec<f32>> {
+            let count = batch.origins.len();
+            if count == 0 {
+                return Ok(Vec::new());
+            }
+
+            let to_u32 = |v: usize| {
+                u32::try_from(v).map_err(|_| {
+                    Error::OutOfRange("Viewpoint batch too large for Metal dispatch".to_string())
+                })
+            };
+            let table_capacity = score_table_capacity(batch, config).ok_or_else(|| {
+                Error::OutOfRange("Viewpoint de-duplication tables too large for Metal".to_string())
+            })?;
+            let (min_x, min_y, min_z) = snapshot.min();
+            let (dim_x, dim_y, dim_z) = snapshot.dims();
+            let params = ScoreParams {
+                min_x,
+                min_y,
+                min_z,
+                dim_x,
+                dim_y,
+                dim_z,
+                voxel_size: config.voxel_size,
+                sensor_range: config.sensor_range,
+                unknown_weight: config.unknown_weight,
+                viewpoint_count: to_u32(count)?,
+                rays_per_viewpoint: to_u32(batch.rays_per_viewpoint)?,
+                table_capacity,
+            };
+
+            let cells_buffer = self.shared_buffer(snapshot.cells());
+            let origins_buffer = self.shared_buffer(&batch.origins);
+            let rays_buffer = self.shared_buffer(&batch.ray_directions);
+            let params_buffer = self.shared_buffer(std::slice::from_ref(&params));
+            let table_buffer = self.device.new_buffer(
+                count as u64 * table_capacity as u64 * std::mem::size_of::<u32>() as u64,
+                MTLResourceOptions::StorageModePrivate,
+            );
+            let gains_buffer = self.device.new_buffer(
+                (count * std::mem::size_of::<f32>()) as u64,
+                MTLResourceOptions::StorageModeShared,
+            );
+
+            let command_buffer = self.command_queue.new_command_buffer();
+
+            // De-duplication tables must start empty
+            let blit = command_buffer.new_blit_command_encoder();
+            blit.fill_buffer(&table_buffer, NSRange::new(0, table_buffer.length()), 0);
+            blit.end_encoding();
+
+            let encoder = command_buffer.new_compute_command_encoder();
+            encoder.set_compute_pipeline_state(&self.score_pipeline);
+            encoder.set_buffer(0, Some(&cells_buffer), 0);
+            encoder.set_buffer(1, Some(&origins_buffer), 0);
+            encoder.set_buffer(2, Some(&rays_buffer), 0);
+            encoder.set_buffer(3, Some(&params_buffer), 0);
+            encoder.set_buffer(4, Some(&table_buffer), 0);
+            encoder.set_buffer(5, Some(&gains_buffer), 0);
+
+            let thread_group_size = MTLSize {
+                width: 64,
+                height: 1,
+                depth: 1,
+            };
+            let thread_groups = MTLSize {
+                width: count.div_ceil(64) as u64,
+                height: 1,
+                depth: 1,
+            };
+            encoder.dispatch_thread_groups(thread_groups, thread_group_size);
+            encoder.end_encoding();
+
+            command_buffer.commit();
+            command_buffer.wait_until_completed();
+
+            let gains_ptr = gains_buffer.contents() as *const f32;
+            let gains = unsafe { std::slice::from_raw_parts(gains_ptr, count) };
+            Ok(gains.to_vec())
+        }
     }
 }
 
@@ -192,7 +412,10 @@ mod metal_impl {
 pub use metal_impl::MetalRayCaster;
 
 // CUDA backend for NVIDIA GPUs
-#[cfg(all(feature = "gpu-cuda", not(target_os = "windows")))]
+#[cfg(all(
+    feature = "gpu-cuda",
+    not(any(target_os = "windows", target_arch = "wasm32"))
+))]
 mod cuda_impl {
     use super::*;
 
@@ -232,11 +455,31 @@ mod cuda_impl {
     }
 }
 
-#[cfg(all(feature = "gpu-cuda", not(target_os = "windows")))]
+#[cfg(all(
+    feature = "gpu-cuda",
+    not(any(target_os = "windows", target_arch = "wasm32"))
+))]
 pub use cuda_impl::CudaRayCaster;
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
+    #[test]
+    fn test_score_table_holds_every_sample() {
+        let config = InformationGainConfig::default();
+        let batch = |viewpoints: usize| ViewpointBatch {
+            origins: vec![(0.0, 0.0, 0.0); viewpoints],
+            ray_directions: Vec::new(),
+            rays_per_viewpoint: 169,
+        };
+        // 169 rays of 100 half-voxel steps each
+        let capacity = score_table_capacity(&batch(10), &config).unwrap();
+        assert!(capacity.is_power_of_two() && capacity >= 16_900);
+        // Too many viewpoints at once fall back to the CPU
+        assert_eq!(score_table_capacity(&batch(100_000), &config), None);
+    }
+
     #[test]
     #[cfg(any(feature = "gpu-metal", feature = "gpu-cuda"))]
     fn test_gpu_ray_caster_creation() {
@@ -0,0 +1,121 @@
+//! Per-parent occupancy summaries across LODs
+//!
+//! An [`OccupancyLayer`](super::OccupancyLayer) with summaries enabled keeps,
+//! for every ancestor of its leaf cells, the number of occupied and free
+//! leaves below it. The counts change incrementally when a leaf changes
+//! state, one step per LOD, so asking whether a subtree holds any obstacle
+//! or is entirely free is a single hash lookup. Collision checks can stop
+//! at the first clear coarse cell and coarse planners can skip whole
+//! subtrees.
+
+use super::occupancy::OccupancyState;
+use crate::Index64;
+use std::collections::HashMap;
+
+/// Occupancy of the leaf cells below one cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubtreeOccupancy {
+    /// Leaves classified occupied
+    pub occupied: u64,
+    /// Leaves classified free
+    pub free: u64,
+    /// Leaves below the cell, observed or not
+    pub cells: u64,
+}
+
+impl SubtreeOccupancy {
+    /// Whether any leaf is occupied
+    pub fn any_occupied(&self) -> bool {
+        self.occupied > 0
+    }
+
+    /// Whether every leaf is known to be free
+    pub fn all_free(&self) -> bool {
+        self.free == self.cells
+    }
+}
+
+/// Occupied and free leaf counts for every ancestor of the leaf LOD
+#[derive(Debug, Clone)]
+pub(crate) struct OccupancySummary {
+    leaf_lod: u8,
+    counts: HashMap<Index64, [u32; 2]>,
+}
+
+impl OccupancySummary {
+    pub(crate) fn new(leaf_lod: u8) -> Self {
+        Self {
+            leaf_lod,
+            counts: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn leaf_lod(&self) -> u8 {
+        self.leaf_lod
+    }
+
+    /// Record a leaf moving from state `old` to `new`; cells at other
+    /// LODs are not summarized
+    pub(crate) fn apply(&mut self, idx: Index64, old: OccupancyState, new: OccupancyState) {
+        if old == new || idx.lod() != self.leaf_lod {
+            return;
+        }
+        let (old, new) = (slot(old), slot(new));
+        let mut cell = idx.parent();
+        while let Some(parent) = cell {
+            let counts = self.counts.entry(parent).or_default();
+            if let Some(s) = old {
+                counts[s] -= 1;
+            }
+            if let Some(s) = new {
+                counts[s] += 1;
+            }
+            if *counts == [0, 0] {
+                self.counts.remove(&parent);
+            }
+            cell = parent.parent();
+        }
+    }
+
+    /// Summary of a cell at or above the leaf LOD; `leaf` classifies a
+    /// single leaf cell
+    pub(crate) fn subtree(
+        &self,
+        cell: Index64,
+        leaf: impl FnOnce(Index64) -> OccupancyState,
+    ) -> Option<SubtreeOccupancy> {
+        let depth = self.leaf_lod.checked_sub(cell.lod())?;
+        let (occupied, free) = if depth == 0 {
+            match leaf(cell) {
+                OccupancyState::Occupied => (1, 0),
+                OccupancyState::Free => (0, 1),
+                OccupancyState::Unknown => (0, 0),
+            }
+        } else {
+            let [occupied, free] = self.counts.get(&cell).copied().unwrap_or_default();
+            (occupied as u64, free as u64)
+        };
+        Some(SubtreeOccupancy {
+            occupied,
+            free,
+            cells: 1 << (3 * depth as u32),
+        })
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.counts.clear();
+    }
+
+    pub(crate) fn memory_usage(&self) -> usize {
+        // Key, two counts and hash map overhead per ancestor
+        self.counts.len() * 32
+    }
+}
+
+fn slot(state: OccupancyState) -> Option<usize> {
+    match state {
+        OccupancyState::Occupied => Some(0),
+        OccupancyState::Free => Some(1),
+        OccupancyState::Unknown => None,
+    }
+}
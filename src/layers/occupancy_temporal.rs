@@ -1,26 +1,133 @@
 //! Temporal Filtering for Dynamic Environment Occupancy Mapping
 //!
 //! Implements time-aware occupancy tracking with decay for dynamic objects
+//!
+//! Time is read from a [`Clock`], the wall clock unless one is given with
+//! [`TemporalOccupancyLayer::with_clock`], so simulations can decay the
+//! map in simulated time.
+//!
+//! In the [`StorageLayout::Soa`] layout log-odds, measurement counts and
+//! update times are separate columns, so decay statistics and stale
+//! pruning scan contiguous arrays.
 
 use super::occupancy::OccupancyState;
+use super::soa::SoaStore;
+use super::{StorageLayout, ValueQuantization};
+use crate::clock::{Clock, WallClock};
 use crate::error::Result;
 use crate::Index64;
 use std::collections::HashMap;
-use std::time::{Duration, Instant};
+use std::sync::Arc;
 
 /// Voxel with temporal information
 #[derive(Debug, Clone)]
 struct TemporalVoxel {
     /// Log-odds value
     log_odds: f32,
-    /// Last update timestamp
-    last_update: Instant,
+    /// Clock time of the last update (seconds)
+    last_update: f64,
     /// Measurement count
     measurement_count: u32,
     /// Velocity estimate (if moving)
     velocity: Option<(f32, f32, f32)>,
 }
 
+/// Voxel storage in one of the [`StorageLayout`]s
+///
+/// SoA columns hold log-odds, the measurement count as a weight and the
+/// last update time; velocity estimates are not kept.
+enum Voxels {
+    Map(HashMap<Index64, TemporalVoxel>),
+    Soa(SoaStore),
+}
+
+impl Voxels {
+    fn new(layout: StorageLayout) -> Self {
+        match layout {
+            StorageLayout::Map => Self::Map(HashMap::new()),
+            StorageLayout::Soa => Self::Soa(
+                SoaStore::new(ValueQuantization::F32, ValueQuantization::F32).with_timestamps(),
+            ),
+        }
+    }
+
+    fn layout(&self) -> StorageLayout {
+        match self {
+            Self::Map(_) => StorageLayout::Map,
+            Self::Soa(_) => StorageLayout::Soa,
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Self::Map(map) => map.len(),
+            Self::Soa(store) => store.len(),
+        }
+    }
+
+    fn get(&self, idx: Index64) -> Option<TemporalVoxel> {
+        match self {
+            Self::Map(map) => map.get(&idx).cloned(),
+            Self::Soa(store) => store.get_timed(idx).map(from_columns),
+        }
+    }
+
+    /// Apply `f` to a voxel, inserting `new` first if absent
+    fn update(&mut self, idx: Index64, new: TemporalVoxel, f: impl FnOnce(&mut TemporalVoxel)) {
+        match self {
+            Self::Map(map) => f(map.entry(idx).or_insert(new)),
+            Self::Soa(store) => {
+                let mut voxel = store.get_timed(idx).map_or(new, from_columns);
+                f(&mut voxel);
+                self.insert(idx, voxel);
+            }
+        }
+    }
+
+    fn insert(&mut self, idx: Index64, voxel: TemporalVoxel) {
+        match self {
+            Self::Map(map) => {
+                map.insert(idx, voxel);
+            }
+            Self::Soa(store) => store.insert_timed(
+                idx,
+                voxel.log_odds,
+                voxel.measurement_count as f32,
+                voxel.last_update,
+            ),
+        }
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (Index64, TemporalVoxel)> + '_> {
+        match self {
+            Self::Map(map) => Box::new(map.iter().map(|(&idx, voxel)| (idx, voxel.clone()))),
+            Self::Soa(store) => {
+                Box::new(store.iter_timed().map(|(idx, log_odds, count, time)| {
+                    (idx, from_columns((log_odds, count, time)))
+                }))
+            }
+        }
+    }
+
+    /// Keep only voxels last updated at a time passing `keep`
+    fn retain_updated(&mut self, keep: impl Fn(f64) -> bool) {
+        match self {
+            Self::Map(map) => map.retain(|_, voxel| keep(voxel.last_update)),
+            Self::Soa(store) => store.retain(|_, _, time| keep(time)),
+        }
+    }
+}
+
+/// Voxel from its SoA columns
+fn from_columns((log_odds, count, last_update): (f32, f32, f64)) -> TemporalVoxel {
+    TemporalVoxel {
+        log_odds,
+        last_update,
+        measurement_count: count as u32,
+        velocity: None,
+    }
+}
+
 /// Temporal filtering configuration
 #[derive(Debug, Clone)]
 pub struct TemporalConfig {
@@ -58,7 +165,7 @@ impl Default for TemporalConfig {
 /// - Autonomous vehicles (moving obstacles)
 pub struct TemporalOccupancyLayer {
     /// Voxel data with timestamps
-    voxels: HashMap<Index64, TemporalVoxel>,
+    voxels: Voxels,
     /// Configuration
     config: TemporalConfig,
     /// Thresholds (same as OccupancyLayer)
@@ -68,6 +175,8 @@ pub struct TemporalOccupancyLayer {
     max_log_odds: f32,
     /// Minimum log-odds (clamping)
     min_log_odds: f32,
+    /// Source of the current time
+    clock: Arc<dyn Clock>,
 }
 
 impl TemporalOccupancyLayer {
@@ -79,52 +188,118 @@ impl TemporalOccupancyLayer {
     /// Create with custom configuration
     pub fn with_config(config: TemporalConfig) -> Self {
         Self {
-            voxels: HashMap::new(),
+            voxels: Voxels::new(StorageLayout::Map),
             config,
             occupied_threshold: 0.847, // log(5.67) ≈ p=0.7
             free_threshold: -1.099,    // log(0.333) ≈ p=0.25
             max_log_odds: 3.466,       // log(31.95) ≈ p=0.97
             min_log_odds: -3.466,      // log(0.0313) ≈ p=0.03
+            clock: Arc::new(WallClock::new()),
         }
     }
 
+    /// Read the time from `clock` instead of the wall clock
+    ///
+    /// Set the clock before the first update; ages are differences of its
+    /// readings.
+    ///
+    /// # Example
+    /// ```
+    /// use octaindex3d::clock::SimClock;
+    /// use octaindex3d::layers::{OccupancyState, TemporalOccupancyLayer};
+    /// use octaindex3d::Index64;
+    /// use std::sync::Arc;
+    ///
+    /// # fn main() -> octaindex3d::Result<()> {
+    /// let clock = SimClock::new(0.0);
+    /// let mut layer = TemporalOccupancyLayer::new().with_clock(Arc::new(clock.clone()));
+    /// let idx = Index64::new(0, 0, 5, 100, 100, 100)?;
+    /// layer.update_occupancy(idx, true, 0.9);
+    /// assert_eq!(layer.get_state(idx), OccupancyState::Occupied);
+    ///
+    /// // A minute of simulated time passes instantly
+    /// clock.advance(60.0);
+    /// assert_eq!(layer.get_state(idx), OccupancyState::Unknown);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Store voxels in `layout`, moving any already present
+    ///
+    /// Both layouts answer every query the same way, except that the
+    /// [`StorageLayout::Soa`] layout keeps no velocity estimates.
+    ///
+    /// ```
+    /// use octaindex3d::layers::{StorageLayout, TemporalOccupancyLayer};
+    ///
+    /// let layer = TemporalOccupancyLayer::new().with_layout(StorageLayout::Soa);
+    /// assert_eq!(layer.layout(), StorageLayout::Soa);
+    /// ```
+    pub fn with_layout(mut self, layout: StorageLayout) -> Self {
+        if layout != self.voxels.layout() {
+            let mut voxels = Voxels::new(layout);
+            for (idx, voxel) in self.voxels.iter() {
+                voxels.insert(idx, voxel);
+            }
+            self.voxels = voxels;
+        }
+        self
+    }
+
+    /// Storage layout of the voxels
+    pub fn layout(&self) -> StorageLayout {
+        self.voxels.layout()
+    }
+
+    /// Seconds since a voxel's last update
+    fn age(&self, voxel: &TemporalVoxel) -> f32 {
+        (self.clock.now() - voxel.last_update).max(0.0) as f32
+    }
+
     /// Update occupancy with temporal decay
     pub fn update_occupancy(&mut self, idx: Index64, occupied: bool, confidence: f32) {
-        let now = Instant::now();
+        let now = self.clock.now();
+        let decay_rate = self.config.decay_rate;
+        let (min_log_odds, max_log_odds) = (self.min_log_odds, self.max_log_odds);
 
         // Get or create voxel
-        let voxel = self.voxels.entry(idx).or_insert_with(|| TemporalVoxel {
+        let new = TemporalVoxel {
             log_odds: 0.0,
             last_update: now,
             measurement_count: 0,
             velocity: None,
-        });
-
-        // Apply temporal decay since last update
-        let dt = now.duration_since(voxel.last_update).as_secs_f32();
-        if dt > 0.0 {
-            // Exponential decay toward unknown (log-odds = 0)
-            let decay = (-self.config.decay_rate * dt).exp();
-            voxel.log_odds *= decay;
-        }
-
-        // Calculate log-odds update from measurement
-        let prob = if occupied {
-            confidence
-        } else {
-            1.0 - confidence
         };
-        let log_odds_update = (prob / (1.0 - prob)).ln();
+        self.voxels.update(idx, new, |voxel| {
+            // Apply temporal decay since last update
+            let dt = (now - voxel.last_update) as f32;
+            if dt > 0.0 {
+                // Exponential decay toward unknown (log-odds = 0)
+                let decay = (-decay_rate * dt).exp();
+                voxel.log_odds *= decay;
+            }
+
+            // Calculate log-odds update from measurement
+            let prob = if occupied {
+                confidence
+            } else {
+                1.0 - confidence
+            };
+            let log_odds_update = (prob / (1.0 - prob)).ln();
 
-        // Bayesian update
-        voxel.log_odds += log_odds_update;
+            // Bayesian update
+            voxel.log_odds += log_odds_update;
 
-        // Clamp to prevent saturation
-        voxel.log_odds = voxel.log_odds.clamp(self.min_log_odds, self.max_log_odds);
+            // Clamp to prevent saturation
+            voxel.log_odds = voxel.log_odds.clamp(min_log_odds, max_log_odds);
 
-        // Update metadata
-        voxel.last_update = now;
-        voxel.measurement_count += 1;
+            // Update metadata
+            voxel.last_update = now;
+            voxel.measurement_count += 1;
+        });
     }
 
     /// Integrate ray with temporal awareness
@@ -172,12 +347,10 @@ impl TemporalOccupancyLayer {
 
     /// Get occupancy state with temporal decay applied
     pub fn get_state(&self, idx: Index64) -> OccupancyState {
-        match self.voxels.get(&idx) {
+        match self.voxels.get(idx) {
             Some(voxel) => {
                 // Check if voxel is stale
-                let age = Instant::now()
-                    .duration_since(voxel.last_update)
-                    .as_secs_f32();
+                let age = self.age(&voxel);
 
                 if age > self.config.max_age {
                     return OccupancyState::Unknown;
@@ -201,35 +374,51 @@ impl TemporalOccupancyLayer {
 
     /// Get probability with temporal decay
     pub fn get_probability(&self, idx: Index64) -> Option<f32> {
-        self.voxels.get(&idx).map(|voxel| {
-            let age = Instant::now()
-                .duration_since(voxel.last_update)
-                .as_secs_f32();
+        self.voxels.get(idx).map(|voxel| {
+            let age = self.age(&voxel);
             let decay = (-self.config.decay_rate * age).exp();
             let current_log_odds = voxel.log_odds * decay;
             1.0 / (1.0 + (-current_log_odds).exp())
         })
     }
 
+    /// Every voxel's state right after its last update, with that
+    /// update's clock time
+    ///
+    /// Unlike [`get_state`](Self::get_state) no decay is applied, so this
+    /// is the history of what was last measured, as consumed by
+    /// [`DynamicsLayer::observe_temporal`](super::DynamicsLayer::observe_temporal).
+    pub fn last_updates(&self) -> impl Iterator<Item = (Index64, OccupancyState, f64)> + '_ {
+        self.voxels.iter().map(|(idx, voxel)| {
+            let state = if voxel.log_odds > self.occupied_threshold {
+                OccupancyState::Occupied
+            } else if voxel.log_odds < self.free_threshold {
+                OccupancyState::Free
+            } else {
+                OccupancyState::Unknown
+            };
+            (idx, state, voxel.last_update)
+        })
+    }
+
     /// Prune stale voxels older than max_age
     pub fn prune_stale(&mut self) {
-        let max_age = Duration::from_secs_f32(self.config.max_age);
-        let now = Instant::now();
+        let max_age = self.config.max_age as f64;
+        let now = self.clock.now();
 
         self.voxels
-            .retain(|_, voxel| now.duration_since(voxel.last_update) < max_age);
+            .retain_updated(|last_update| now - last_update < max_age);
     }
 
     /// Get statistics
     pub fn stats(&self) -> TemporalStats {
-        let now = Instant::now();
         let mut stats = TemporalStats {
             total_voxels: self.voxels.len(),
             ..Default::default()
         };
 
-        for voxel in self.voxels.values() {
-            let age = now.duration_since(voxel.last_update).as_secs_f32();
+        for (_, voxel) in self.voxels.iter() {
+            let age = self.age(&voxel);
             let decay = (-self.config.decay_rate * age).exp();
             let current_log_odds = voxel.log_odds * decay;
 
@@ -280,18 +469,30 @@ pub struct TemporalStats {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::clock::SimClock;
 
     #[test]
     fn test_temporal_decay() {
-        let mut layer = TemporalOccupancyLayer::new();
+        let clock = SimClock::new(100.0);
+        let mut layer = TemporalOccupancyLayer::new().with_clock(Arc::new(clock.clone()));
         let idx = Index64::new(0, 0, 5, 100, 100, 100).unwrap();
 
         // Initial update (occupied)
         layer.update_occupancy(idx, true, 0.9);
         assert_eq!(layer.get_state(idx), OccupancyState::Occupied);
+        let fresh = layer.get_probability(idx).unwrap();
 
-        // Decay should happen over time
-        // (In real test, would need to wait or mock time)
+        // Log-odds 2.197 decay below the 0.847 threshold after ln(2.59) / 0.5 s
+        clock.advance(1.0);
+        assert_eq!(layer.get_state(idx), OccupancyState::Occupied);
+        assert!(layer.get_probability(idx).unwrap() < fresh);
+        clock.advance(1.0);
+        assert_eq!(layer.get_state(idx), OccupancyState::Unknown);
+
+        clock.advance(4.0);
+        assert_eq!(layer.stats().stale_voxels, 1);
+        layer.prune_stale();
+        assert_eq!(layer.stats().total_voxels, 0);
     }
 
     #[test]
@@ -306,4 +507,46 @@ mod tests {
         // Voxel should still be there (not stale yet)
         assert_eq!(layer.stats().total_voxels, 1);
     }
+
+    #[test]
+    fn test_soa_layout_matches_map() {
+        let clock = SimClock::new(0.0);
+        let layer = |layout| {
+            TemporalOccupancyLayer::new()
+                .with_clock(Arc::new(clock.clone()))
+                .with_layout(layout)
+        };
+        let mut map = layer(StorageLayout::Map);
+        let mut soa = layer(StorageLayout::Soa);
+        let idx = |x| Index64::new(0, 0, 5, x, 100, 100).unwrap();
+        for step in 0..6u16 {
+            for layer in [&mut map, &mut soa] {
+                layer.update_occupancy(idx(step), step % 2 == 0, 0.9);
+                layer.update_occupancy(idx(0), true, 0.8);
+            }
+            clock.advance(1.5);
+        }
+
+        for x in 0..7 {
+            assert_eq!(soa.get_state(idx(x)), map.get_state(idx(x)));
+            assert_eq!(soa.get_probability(idx(x)), map.get_probability(idx(x)));
+        }
+        let history = |layer: &TemporalOccupancyLayer| {
+            let mut updates: Vec<_> = layer.last_updates().collect();
+            updates.sort_by_key(|&(idx, _, _)| idx);
+            updates
+        };
+        assert_eq!(history(&soa), history(&map));
+        assert_eq!(soa.stats().stale_voxels, map.stats().stale_voxels);
+
+        // Voxels last updated over max_age ago are pruned from both
+        soa.prune_stale();
+        map.prune_stale();
+        assert_eq!(soa.stats().total_voxels, 4);
+        assert_eq!(history(&soa), history(&map));
+
+        // Switching layouts keeps every voxel
+        let back = soa.with_layout(StorageLayout::Map);
+        assert_eq!(history(&back), history(&map));
+    }
 }
@@ -0,0 +1,205 @@
+//! Saving and loading a [`LayeredMap`] as a v2 container
+//!
+//! [`LayeredMap::write_container`] stores every layer in tag order as up to
+//! two frames: its [`LayerConfig`] if it has one, then its
+//! [`encode_state`](super::Layer::encode_state). Each frame's payload
+//! starts with the tag `OLY1`, the layer tag (u8) and the kind (u8): 0 for
+//! the configuration record, 1 for the state. Frames without the tag, such as cell blocks
+//! or metadata, may share the container and are skipped on load.
+
+use super::{ConfigUpgrades, LayerConfig, LayerType, LayeredMap};
+use crate::container_v2::{ContainerReaderV2, ContainerWriterV2, StreamConfig};
+use crate::error::{Error, Result};
+use std::fs::File;
+use std::path::Path;
+
+/// Leading bytes of a layer frame
+const LAYER_FRAME_TAG: &[u8; 4] = b"OLY1";
+/// Layer frame holding a [`LayerConfig`] record
+const KIND_CONFIG: u8 = 0;
+/// Layer frame holding a layer's state
+const KIND_STATE: u8 = 1;
+
+impl LayeredMap {
+    /// Save every layer to a new container at `path`
+    ///
+    /// Fails for layers without state encoding, such as derived layers.
+    ///
+    /// # Example
+    /// ```
+    /// use octaindex3d::layers::{LayeredMap, Measurement, OccupancyLayer, TSDFLayer};
+    /// use octaindex3d::Index64;
+    ///
+    /// # fn main() -> octaindex3d::Result<()> {
+    /// # let path = std::env::temp_dir().join(format!("oi3d-map-doc-{}.oct", std::process::id()));
+    /// let mut map = LayeredMap::new();
+    /// map.add_tsdf_layer(TSDFLayer::new(0.2));
+    /// map.add_occupancy_layer(OccupancyLayer::new());
+    /// let idx = Index64::new(0, 0, 5, 100, 100, 100)?;
+    /// map.update_tsdf(idx, &Measurement::depth(0.05, 1.0))?;
+    /// map.update_occupancy(idx, &Measurement::occupied(0.9))?;
+    /// map.save(&path)?;
+    ///
+    /// let loaded = LayeredMap::load(&path)?;
+    /// assert_eq!(loaded.query_tsdf(idx), map.query_tsdf(idx));
+    /// assert_eq!(loaded.get_layer::<TSDFLayer>().unwrap().truncation_distance(), 0.2);
+    /// assert!(loaded.query_occupancy(idx).unwrap() > 0.5);
+    /// # std::fs::remove_file(&path).ok();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut writer = ContainerWriterV2::new(File::create(path)?, StreamConfig::default())?;
+        self.write_container(&mut writer)?;
+        writer.finish()
+    }
+
+    /// Load a map saved with [`save`](Self::save)
+    ///
+    /// Layers are restored as the crate's standard layer of their type,
+    /// see [`restore_container`](Self::restore_container).
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::read_container(&ContainerReaderV2::open(path)?)
+    }
+
+    /// Write every layer's configuration and state as frames of `writer`
+    pub fn write_container<W: std::io::Write + std::io::Seek>(
+        &self,
+        writer: &mut ContainerWriterV2<W>,
+    ) -> Result<()> {
+        let mut types = self.layer_types();
+        types.sort_by_key(LayerType::tag);
+        for layer_type in types {
+            let layer = self.layer(layer_type).expect("listed layer exists");
+            let state = layer.encode_state().ok_or_else(|| {
+                Error::InvalidFormat(format!("{} layer cannot be saved", layer_type.name()))
+            })?;
+            if let Some(config) = layer.config() {
+                writer.write_frame(&frame(layer_type, KIND_CONFIG, &config.to_bytes()))?;
+            }
+            writer.write_frame(&frame(layer_type, KIND_STATE, &state))?;
+        }
+        Ok(())
+    }
+
+    /// Map of the layers stored in a container
+    pub fn read_container(reader: &ContainerReaderV2) -> Result<Self> {
+        let mut map = Self::new();
+        map.restore_container(reader)?;
+        Ok(map)
+    }
+
+    /// Restore the layers stored in a container into this map
+    ///
+    /// Stored layers replace the state of the map's layer of the same type
+    /// and adopt its stored configuration, upgraded with the built-in
+    /// [`ConfigUpgrades`]. Types the map lacks are added as
+    /// [`TSDFLayer`](super::TSDFLayer), [`ESDFLayer`](super::ESDFLayer),
+    /// [`OccupancyLayer`](super::OccupancyLayer),
+    /// [`ColorLayer`](super::ColorLayer) or
+    /// [`IntensityLayer`](super::IntensityLayer); add other implementations, such as
+    /// [`BinaryOccupancyLayer`](super::BinaryOccupancyLayer), to the map
+    /// before restoring.
+    pub fn restore_container(&mut self, reader: &ContainerReaderV2) -> Result<()> {
+        let upgrades = ConfigUpgrades::new();
+        for i in 0..reader.frame_count() {
+            if reader.frame_key(i).is_some() {
+                continue;
+            }
+            let data = reader.frame_data(i)?;
+            let Some(rest) = data.strip_prefix(LAYER_FRAME_TAG) else {
+                continue;
+            };
+            let [tag, kind, payload @ ..] = rest else {
+                return Err(Error::InvalidFormat("layer frame is truncated".to_string()));
+            };
+            let layer_type = LayerType::from_tag(*tag)
+                .ok_or_else(|| Error::InvalidFormat(format!("unknown layer tag {}", tag)))?;
+            if !self.has_layer(layer_type) {
+                self.add_standard_layer(layer_type)?;
+            }
+            let layer = self.layer_mut(layer_type).expect("layer was added");
+            match *kind {
+                KIND_CONFIG => upgrades.apply(layer, LayerConfig::from_bytes(payload)?)?,
+                KIND_STATE => layer.decode_state(payload)?,
+                _ => {
+                    return Err(Error::InvalidFormat(format!(
+                        "unknown layer frame kind {}",
+                        kind
+                    )))
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Payload of a layer frame
+fn frame(layer_type: LayerType, kind: u8, payload: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(LAYER_FRAME_TAG.len() + 2 + payload.len());
+    frame.extend_from_slice(LAYER_FRAME_TAG);
+    frame.extend_from_slice(&[layer_type.tag(), kind]);
+    frame.extend_from_slice(payload);
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layers::{
+        ColorLayer, ESDFLayer, IntensityLayer, Measurement, OccupancyLayer, TSDFLayer,
+        ValueQuantization,
+    };
+    use crate::Index64;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_round_trip_all_layers() -> Result<()> {
+        let mut map = LayeredMap::new();
+        map.add_tsdf_layer(
+            TSDFLayer::new(0.3).with_quantization(ValueQuantization::i8_for_range(0.3)?),
+        );
+        map.add_occupancy_layer(OccupancyLayer::with_thresholds(0.8, 0.2, 0.95));
+        map.add_color_layer(ColorLayer::new());
+        map.add_intensity_layer(IntensityLayer::new());
+        for i in 0..20u16 {
+            let idx = Index64::new(0, 0, 5, 100 + 2 * i, 100, 100)?;
+            map.update_tsdf(idx, &Measurement::depth(0.01 * i as f32 - 0.1, 1.0))?;
+            map.update_occupancy(idx, &Measurement::occupied(0.9))?;
+            map.update_color(idx, &Measurement::color(200, 10 * i as u8, 3, 1.0))?;
+            map.update_intensity(idx, &Measurement::intensity(0.05 * i as f32, 1.0))?;
+        }
+        let mut esdf = ESDFLayer::new(0.1, 0.5);
+        esdf.compute_from_tsdf(map.get_layer::<TSDFLayer>().unwrap(), 0.05)?;
+        map.add_esdf_layer(esdf);
+
+        let mut bytes = Vec::new();
+        let mut writer = ContainerWriterV2::new(Cursor::new(&mut bytes), StreamConfig::default())?;
+        writer.write_frame(b"unrelated frame")?;
+        map.write_container(&mut writer)?;
+        writer.finish()?;
+        let loaded = LayeredMap::read_container(&ContainerReaderV2::from_bytes(bytes)?)?;
+
+        assert_eq!(loaded.layer_types().len(), 5);
+        for layer_type in map.layer_types() {
+            let (saved, restored) = (map.layer(layer_type), loaded.layer(layer_type));
+            let (saved, restored) = (saved.unwrap(), restored.unwrap());
+            assert_eq!(
+                restored.encode_state(),
+                saved.encode_state(),
+                "{layer_type:?}"
+            );
+            assert_eq!(
+                restored.config().map(|c| c.to_bytes()),
+                saved.config().map(|c| c.to_bytes())
+            );
+        }
+
+        // Derived layers are not persisted
+        map.add_layer(crate::layers::ScalarLayer::new());
+        let mut bytes = Vec::new();
+        let mut writer = ContainerWriterV2::new(Cursor::new(&mut bytes), StreamConfig::default())?;
+        assert!(map.write_container(&mut writer).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,236 @@
+//! Reduced-precision values for distance layers
+//!
+//! [`ValueQuantization`] selects the precision of a layer's values: full
+//! `f32`, IEEE 754 half precision, or 8-bit integers with a fixed scale.
+//! Quantized layers round every value to the encoding when it is written,
+//! so what a layer computes with is exactly what it persists, and state
+//! checkpoints store the narrow codes instead of `f32` bit patterns.
+//!
+//! Layers in the [`StorageLayout::Soa`](super::StorageLayout::Soa) layout
+//! also hold the codes in their blocks and decode them on read, so `F16`
+//! roughly halves their memory. The hash map layout keeps rounded `f32`
+//! values, since narrower values would not shrink its entries.
+//!
+//! ## Accuracy
+//!
+//! | Encoding | Bytes | Range | Worst-case error |
+//! |----------|-------|-------|------------------|
+//! | `F32` | 4 | full | none added |
+//! | `F16` | 2 | ±65504 | `|v| * 2^-11`, at least `2^-25` |
+//! | `I8 { scale }` | 1 | ±127 · scale | `scale / 2`, values beyond the range saturate |
+//!
+//! [`ValueQuantization::max_error`] returns the bound for a given magnitude.
+//! The conversions use integer bit manipulation and correctly rounded
+//! arithmetic only, so quantized maps stay bit-reproducible across
+//! platforms.
+
+use crate::error::{Error, Result};
+
+/// Storage encoding of layer values
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ValueQuantization {
+    /// Full single precision
+    #[default]
+    F32,
+    /// IEEE 754 binary16, round to nearest even
+    F16,
+    /// Signed 8-bit multiples of `scale`, in `-127..=127`
+    I8 {
+        /// Value of one step
+        scale: f32,
+    },
+}
+
+impl ValueQuantization {
+    /// 8-bit quantization covering `±max_abs`
+    pub fn i8_for_range(max_abs: f32) -> Result<Self> {
+        if !(max_abs.is_finite() && max_abs > 0.0) {
+            return Err(Error::InvalidFormat(format!(
+                "i8 quantization range must be positive and finite, got {}",
+                max_abs
+            )));
+        }
+        Ok(Self::I8 {
+            scale: max_abs / 127.0,
+        })
+    }
+
+    /// Bytes per stored value
+    pub fn value_bytes(self) -> usize {
+        match self {
+            Self::F32 => 4,
+            Self::F16 => 2,
+            Self::I8 { .. } => 1,
+        }
+    }
+
+    /// `value` as stored: the closest value the encoding represents
+    pub fn quantize(self, value: f32) -> f32 {
+        self.decode(self.encode(value))
+    }
+
+    /// Encode `value` into the low `value_bytes()` bytes of a code
+    pub fn encode(self, value: f32) -> u32 {
+        match self {
+            Self::F32 => value.to_bits(),
+            Self::F16 => f32_to_f16(value) as u32,
+            Self::I8 { scale } => {
+                let steps = (value / scale).round().clamp(-127.0, 127.0);
+                // NaN saturates to zero through the cast
+                steps as i8 as u8 as u32
+            }
+        }
+    }
+
+    /// Decode a code produced by [`encode`](Self::encode)
+    pub fn decode(self, code: u32) -> f32 {
+        match self {
+            Self::F32 => f32::from_bits(code),
+            Self::F16 => f16_to_f32(code as u16),
+            Self::I8 { scale } => code as u8 as i8 as f32 * scale,
+        }
+    }
+
+    /// Largest error quantizing adds to a value of magnitude up to
+    /// `magnitude` inside the encoding's range
+    pub fn max_error(self, magnitude: f32) -> f32 {
+        match self {
+            Self::F32 => 0.0,
+            Self::F16 => (magnitude.abs() * (1.0 / 2048.0)).max(1.0 / 33_554_432.0),
+            Self::I8 { scale } => scale * 0.5,
+        }
+    }
+
+    /// Numeric tag and scale, for layer config records
+    pub(crate) fn to_fields(self) -> (f64, f64) {
+        match self {
+            Self::F32 => (0.0, 0.0),
+            Self::F16 => (1.0, 0.0),
+            Self::I8 { scale } => (2.0, scale as f64),
+        }
+    }
+
+    /// Inverse of [`to_fields`](Self::to_fields)
+    pub(crate) fn from_fields(tag: f64, scale: f64) -> Result<Self> {
+        match tag as u8 {
+            0 => Ok(Self::F32),
+            1 => Ok(Self::F16),
+            2 if scale > 0.0 => Ok(Self::I8 {
+                scale: scale as f32,
+            }),
+            _ => Err(Error::InvalidFormat(format!(
+                "unknown value quantization {} (scale {})",
+                tag, scale
+            ))),
+        }
+    }
+}
+
+/// Convert to binary16 bits, rounding to nearest even
+fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exp = ((bits >> 23) & 0xFF) as i32;
+    let man = bits & 0x7F_FFFF;
+
+    if exp == 0xFF {
+        // Infinity stays infinite, NaN stays NaN
+        return sign | 0x7C00 | if man != 0 { 0x200 } else { 0 };
+    }
+    let half_exp = exp - 127 + 15;
+    if half_exp >= 0x1F {
+        return sign | 0x7C00;
+    }
+    if half_exp <= 0 {
+        // Subnormal half: the mantissa with its implicit bit, in units of 2^-24
+        if half_exp < -10 {
+            return sign;
+        }
+        let man = man | 0x80_0000;
+        let shift = (14 - half_exp) as u32;
+        let halfway = 1 << (shift - 1);
+        let rem = man & ((1 << shift) - 1);
+        let mut code = man >> shift;
+        if rem > halfway || (rem == halfway && code & 1 == 1) {
+            code += 1;
+        }
+        return sign | code as u16;
+    }
+
+    let mut code = ((half_exp as u32) << 10) | (man >> 13);
+    let rem = man & 0x1FFF;
+    // A carry out of the mantissa correctly bumps the exponent, up to infinity
+    if rem > 0x1000 || (rem == 0x1000 && code & 1 == 1) {
+        code += 1;
+    }
+    sign | code as u16
+}
+
+/// Convert binary16 bits to `f32`, exactly
+fn f16_to_f32(code: u16) -> f32 {
+    let sign = ((code & 0x8000) as u32) << 16;
+    let exp = ((code >> 10) & 0x1F) as u32;
+    let man = (code & 0x3FF) as u32;
+    match exp {
+        0 => {
+            let magnitude = man as f32 * (1.0 / 16_777_216.0);
+            if sign != 0 {
+                -magnitude
+            } else {
+                magnitude
+            }
+        }
+        0x1F => f32::from_bits(sign | 0x7F80_0000 | (man << 13)),
+        _ => f32::from_bits(sign | ((exp + 112) << 23) | (man << 13)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_f16_conversion() {
+        for (value, code, decoded) in [
+            (0.0f32, 0x0000u16, 0.0f32),
+            (-0.0, 0x8000, -0.0),
+            (1.0, 0x3C00, 1.0),
+            (-2.5, 0xC100, -2.5),
+            (65504.0, 0x7BFF, 65504.0),
+            (1e6, 0x7C00, f32::INFINITY),
+            (5.960_464_5e-8, 0x0001, 5.960_464_5e-8),
+            (f32::INFINITY, 0x7C00, f32::INFINITY),
+        ] {
+            assert_eq!(f32_to_f16(value), code, "{}", value);
+            assert_eq!(f16_to_f32(code), decoded);
+        }
+        // Ties round to even: 1 + 2^-11 is halfway between 1 and 1 + 2^-10
+        assert_eq!(f32_to_f16(1.0 + 1.0 / 2048.0), 0x3C00);
+        assert_eq!(f32_to_f16(1.0 + 3.0 / 2048.0), 0x3C02);
+        assert!(f16_to_f32(f32_to_f16(f32::NAN)).is_nan());
+
+        // Every value stays within the documented bound
+        let q = ValueQuantization::F16;
+        for i in -2000..2000 {
+            let v = i as f32 * 0.013_7;
+            assert!((q.quantize(v) - v).abs() <= q.max_error(v));
+        }
+    }
+
+    #[test]
+    fn test_i8_quantization() {
+        let q = ValueQuantization::i8_for_range(0.5).unwrap();
+        assert_eq!(q.value_bytes(), 1);
+        for i in -100..100 {
+            let v = i as f32 * 0.004_9;
+            assert!((q.quantize(v) - v).abs() <= q.max_error(v) * 1.000_1);
+        }
+        // Out of range values saturate
+        assert_eq!(q.quantize(3.0), q.quantize(0.5));
+        assert_eq!(q.quantize(-3.0), -q.quantize(0.5));
+        assert!(ValueQuantization::i8_for_range(0.0).is_err());
+
+        let (tag, scale) = q.to_fields();
+        assert_eq!(ValueQuantization::from_fields(tag, scale).unwrap(), q);
+    }
+}
@@ -0,0 +1,361 @@
+//! Diff and merge of container cell blocks
+//!
+//! Maps written with [`StaticMap::write_container`](super::StaticMap::write_container)
+//! by two sessions or two robots are compared and combined block by block:
+//! [`diff`] lists the cells one container adds, removes or changes against
+//! the other, and [`merge`] writes the union of both to a third container,
+//! resolving cells held by both with a [`MergePolicy`]. Only one pair of
+//! blocks is decoded at a time, so neither map is ever loaded whole.
+//!
+//! Only cell blocks take part; other frames and blocks are ignored.
+
+use super::scan::CellRow;
+use super::static_map::{latest_blocks, CellBlock};
+use super::LayerType;
+use crate::container_v2::{ContainerReaderV2, ContainerWriterV2};
+use crate::error::Result;
+use crate::Index64;
+use std::collections::BTreeMap;
+use std::io::{Seek, Write};
+
+/// Cells that differ between two containers
+#[derive(Debug, Clone, Default)]
+pub struct ContainerDiff {
+    /// Cells only the second container holds
+    pub added: Vec<CellRow>,
+    /// Cells only the first container holds
+    pub removed: Vec<CellRow>,
+    /// Cells both hold with different values, as `(first, second)`
+    pub changed: Vec<(CellRow, CellRow)>,
+}
+
+impl ContainerDiff {
+    /// Whether the containers hold the same cells with the same values
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// Number of differing cells
+    pub fn len(&self) -> usize {
+        self.added.len() + self.removed.len() + self.changed.len()
+    }
+}
+
+/// How [`merge`] resolves a layer value both containers hold for a cell
+///
+/// A value only one container holds is always kept.
+#[derive(Debug, Clone, Copy)]
+pub enum MergePolicy {
+    /// The first container's value
+    KeepFirst,
+    /// The second container's value
+    KeepSecond,
+    /// The larger value, e.g. the more pessimistic occupancy
+    Max,
+    /// The smaller value
+    Min,
+    /// The mean of both values
+    Mean,
+    /// A custom function of the layer and the first and second values
+    Custom(fn(LayerType, f32, f32) -> f32),
+}
+
+impl MergePolicy {
+    /// Resolve the two values of a layer
+    pub fn resolve(self, layer_type: LayerType, first: f32, second: f32) -> f32 {
+        match self {
+            MergePolicy::KeepFirst => first,
+            MergePolicy::KeepSecond => second,
+            MergePolicy::Max => first.max(second),
+            MergePolicy::Min => first.min(second),
+            MergePolicy::Mean => (first + second) / 2.0,
+            MergePolicy::Custom(f) => f(layer_type, first, second),
+        }
+    }
+}
+
+/// Work done by [`merge`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MergeStats {
+    /// Cell blocks written
+    pub blocks: usize,
+    /// Cells written
+    pub cells: usize,
+    /// Cells both containers held with different values, resolved by the
+    /// policy
+    pub conflicts: usize,
+}
+
+/// Cells `b` adds, removes or changes against `a`
+///
+/// Blocks whose stored payloads are identical are skipped without
+/// decoding.
+///
+/// # Example
+///
+/// ```
+/// use octaindex3d::container_v2::{ContainerReaderV2, ContainerWriterV2, StreamConfig};
+/// use octaindex3d::layers::reconcile::{diff, merge, MergePolicy};
+/// use octaindex3d::layers::{LayeredMap, OccupancyLayer, StaticMap};
+/// use octaindex3d::Index64;
+/// use std::io::Cursor;
+///
+/// # fn main() -> octaindex3d::Result<()> {
+/// let survey = |xs: std::ops::Range<u16>| -> octaindex3d::Result<ContainerReaderV2> {
+///     let mut occupancy = OccupancyLayer::new();
+///     for x in xs {
+///         occupancy.update_occupancy(Index64::new(0, 0, 10, x, 0, 0)?, true, 0.9);
+///     }
+///     let mut map = LayeredMap::new();
+///     map.add_occupancy_layer(occupancy);
+///     let mut bytes = Vec::new();
+///     let mut writer = ContainerWriterV2::new(Cursor::new(&mut bytes), StreamConfig::default())?;
+///     StaticMap::from(&map).write_container(&mut writer)?;
+///     writer.finish()?;
+///     ContainerReaderV2::from_bytes(bytes)
+/// };
+/// let monday = survey(0..40)?;
+/// let tuesday = survey(30..50)?;
+///
+/// let changes = diff(&monday, &tuesday)?;
+/// assert_eq!((changes.added.len(), changes.removed.len()), (10, 30));
+///
+/// let mut bytes = Vec::new();
+/// let mut writer = ContainerWriterV2::new(Cursor::new(&mut bytes), StreamConfig::default())?;
+/// let stats = merge(&monday, &tuesday, MergePolicy::Max, &mut writer)?;
+/// writer.finish()?;
+/// assert_eq!(stats.cells, 50);
+/// # Ok(())
+/// # }
+/// ```
+pub fn diff(a: &ContainerReaderV2, b: &ContainerReaderV2) -> Result<ContainerDiff> {
+    let mut out = ContainerDiff::default();
+    for (key, (first, second)) in paired_blocks(a, b) {
+        if let (Some(i), Some(j)) = (first, second) {
+            if a.frame_data(i)? == b.frame_data(j)? {
+                continue;
+            }
+        }
+        let rows_a = rows(a, key, first)?;
+        let rows_b = rows(b, key, second)?;
+        join_rows(rows_a, rows_b, |row_a, row_b| match (row_a, row_b) {
+            (Some(row), None) => out.removed.push(row),
+            (None, Some(row)) => out.added.push(row),
+            (Some(row_a), Some(row_b)) => {
+                if !row_a.values().eq(row_b.values()) {
+                    out.changed.push((row_a, row_b));
+                }
+            }
+            (None, None) => {}
+        });
+    }
+    Ok(out)
+}
+
+/// Write the cells of `a` and `b` to `writer` as cell blocks, resolving
+/// values both hold with `policy`
+pub fn merge<W: Write + Seek>(
+    a: &ContainerReaderV2,
+    b: &ContainerReaderV2,
+    policy: MergePolicy,
+    writer: &mut ContainerWriterV2<W>,
+) -> Result<MergeStats> {
+    let mut stats = MergeStats::default();
+    for (key, (first, second)) in paired_blocks(a, b) {
+        let rows_a = rows(a, key, first)?;
+        let rows_b = rows(b, key, second)?;
+        let mut merged: Vec<(Index64, [Option<f32>; LayerType::COUNT])> = Vec::new();
+        join_rows(rows_a, rows_b, |row_a, row_b| {
+            let mut values = [None; LayerType::COUNT];
+            let index = row_a.or(row_b).map(|row| row.index);
+            for (layer_type, value) in row_a.iter().flat_map(CellRow::values) {
+                values[layer_type.tag() as usize] = Some(value);
+            }
+            let mut conflict = false;
+            for (layer_type, value) in row_b.iter().flat_map(CellRow::values) {
+                let slot = &mut values[layer_type.tag() as usize];
+                *slot = Some(match *slot {
+                    Some(first) => {
+                        conflict |= first != value;
+                        policy.resolve(layer_type, first, value)
+                    }
+                    None => value,
+                });
+            }
+            stats.conflicts += conflict as usize;
+            if let Some(index) = index {
+                merged.push((index, values));
+            }
+        });
+        if merged.is_empty() {
+            continue;
+        }
+
+        let layers: Vec<LayerType> = (0..LayerType::COUNT as u8)
+            .filter(|&tag| {
+                merged
+                    .iter()
+                    .any(|(_, values)| values[tag as usize].is_some())
+            })
+            .filter_map(LayerType::from_tag)
+            .collect();
+        let rows = merged
+            .into_iter()
+            .map(|(index, values)| {
+                let row = layers
+                    .iter()
+                    .map(|layer_type| values[layer_type.tag() as usize].unwrap_or(f32::NAN))
+                    .collect();
+                (index, row)
+            })
+            .collect::<Vec<_>>();
+        stats.blocks += 1;
+        stats.cells += rows.len();
+        writer.write_block(key, &CellBlock { layers, rows }.encode())?;
+    }
+    Ok(stats)
+}
+
+/// Latest frame of every block key in either container, in key order
+fn paired_blocks(
+    a: &ContainerReaderV2,
+    b: &ContainerReaderV2,
+) -> BTreeMap<Index64, (Option<usize>, Option<usize>)> {
+    let mut pairs: BTreeMap<Index64, (Option<usize>, Option<usize>)> = BTreeMap::new();
+    for (key, frame) in latest_blocks(a) {
+        pairs.entry(key).or_default().0 = Some(frame);
+    }
+    for (key, frame) in latest_blocks(b) {
+        pairs.entry(key).or_default().1 = Some(frame);
+    }
+    pairs
+}
+
+/// Cells of a block in index order; none if absent or not a cell block
+fn rows(reader: &ContainerReaderV2, key: Index64, frame: Option<usize>) -> Result<Vec<CellRow>> {
+    let Some(frame) = frame else {
+        return Ok(Vec::new());
+    };
+    let Some(block) = CellBlock::decode(key, &reader.frame_data(frame)?)? else {
+        return Ok(Vec::new());
+    };
+    Ok(block
+        .rows
+        .iter()
+        .map(|(index, values)| CellRow::new(*index, &block.layers, values))
+        .collect())
+}
+
+/// Visit the cells of two index-ordered lists, pairing equal indices
+fn join_rows(
+    a: Vec<CellRow>,
+    b: Vec<CellRow>,
+    mut visit: impl FnMut(Option<CellRow>, Option<CellRow>),
+) {
+    let mut a = a.into_iter().peekable();
+    let mut b = b.into_iter().peekable();
+    loop {
+        match (a.peek(), b.peek()) {
+            (Some(x), Some(y)) if x.index == y.index => visit(a.next(), b.next()),
+            (Some(x), Some(y)) if x.index < y.index => visit(a.next(), None),
+            (Some(_), None) => visit(a.next(), None),
+            (_, Some(_)) => visit(None, b.next()),
+            (None, None) => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::container_v2::StreamConfig;
+    use crate::layers::{LayeredMap, OccupancyLayer, StaticMap, TSDFLayer};
+    use std::io::Cursor;
+
+    fn container(map: &LayeredMap) -> ContainerReaderV2 {
+        let mut bytes = Vec::new();
+        let mut writer =
+            ContainerWriterV2::new(Cursor::new(&mut bytes), StreamConfig::default()).unwrap();
+        StaticMap::from(map).write_container(&mut writer).unwrap();
+        writer.finish().unwrap();
+        ContainerReaderV2::from_bytes(bytes).unwrap()
+    }
+
+    #[test]
+    fn test_diff_and_merge_overlapping_surveys() {
+        let cell = |x, y| Index64::new(0, 0, 10, x, y, 0).unwrap();
+        // First session maps occupancy of a 16×16 patch
+        let mut occupancy = OccupancyLayer::new();
+        for x in 0..16 {
+            for y in 0..16 {
+                occupancy.update_occupancy(cell(x, y), x == 0, 0.8);
+            }
+        }
+        let mut first = LayeredMap::new();
+        first.add_occupancy_layer(occupancy);
+
+        // Second session overlaps half of it, sees a new wall at x = 8 and
+        // adds a TSDF layer
+        let mut occupancy = OccupancyLayer::new();
+        let mut tsdf = TSDFLayer::new(0.1);
+        for x in 8..24 {
+            for y in 0..16 {
+                occupancy.update_occupancy(cell(x, y), x == 8, 0.8);
+                if x == 8 {
+                    tsdf.batch_update(&[(cell(x, y), 0.0, 1.0)]).unwrap();
+                }
+            }
+        }
+        let mut second = LayeredMap::new();
+        second.add_occupancy_layer(occupancy);
+        second.add_tsdf_layer(tsdf);
+
+        let a = container(&first);
+        let b = container(&second);
+        assert!(diff(&a, &a).unwrap().is_empty());
+        let changes = diff(&a, &b).unwrap();
+        assert_eq!(changes.removed.len(), 8 * 16);
+        assert_eq!(changes.added.len(), 8 * 16);
+        // Overlap cells: the wall row gained TSDF and occupancy, the rest match
+        assert_eq!(changes.changed.len(), 16);
+        assert!(changes
+            .changed
+            .iter()
+            .all(|(before, after)| before.index.decode_coords().0 == 8
+                && before.value(LayerType::TSDF).is_none()
+                && after.value(LayerType::TSDF) == Some(0.0)));
+
+        let mut bytes = Vec::new();
+        let mut writer =
+            ContainerWriterV2::new(Cursor::new(&mut bytes), StreamConfig::default()).unwrap();
+        let stats = merge(&a, &b, MergePolicy::Max, &mut writer).unwrap();
+        writer.finish().unwrap();
+        assert_eq!(stats.cells, 24 * 16);
+        assert_eq!(stats.conflicts, 16);
+        let merged =
+            StaticMap::read_container(&ContainerReaderV2::from_bytes(bytes).unwrap()).unwrap();
+        let wall = cell(8, 3);
+        assert_eq!(
+            merged.query(LayerType::Occupancy, wall),
+            second.query_occupancy(wall)
+        );
+        assert_eq!(merged.query(LayerType::TSDF, wall), Some(0.0));
+        assert_eq!(
+            merged.query(LayerType::Occupancy, cell(0, 3)),
+            first.query_occupancy(cell(0, 3))
+        );
+        assert_eq!(merged.query(LayerType::TSDF, cell(0, 3)), None);
+
+        // Keeping the first session's values leaves the overlap as it was
+        let mut bytes = Vec::new();
+        let mut writer =
+            ContainerWriterV2::new(Cursor::new(&mut bytes), StreamConfig::default()).unwrap();
+        merge(&a, &b, MergePolicy::KeepFirst, &mut writer).unwrap();
+        writer.finish().unwrap();
+        let kept = ContainerReaderV2::from_bytes(bytes).unwrap();
+        let changes = diff(&a, &kept).unwrap();
+        assert!(changes.removed.is_empty());
+        assert_eq!(changes.added.len(), 8 * 16);
+        assert_eq!(changes.changed.len(), 16); // only gained TSDF values
+    }
+}
@@ -0,0 +1,618 @@
+//! Many named maps served from one process
+//!
+//! A [`MapRegistry`] holds the maps of many sites or robots under names and
+//! can be shared between server threads. Two kinds of maps are served:
+//!
+//! - live [`LayeredMap`]s, read and updated under a per-map lock
+//! - read-only containers written with
+//!   [`StaticMap::write_container`](super::StaticMap::write_container),
+//!   with the `container_v2` feature. Their cell blocks are decoded on
+//!   demand into a block cache all containers share, so resident memory
+//!   follows the working set rather than the map sizes.
+//!
+//! Each map has a [`MapQuota`]. A live map over its memory quota refuses
+//! further writes until it shrinks; a container over its cache quota gives
+//! up its own least recently used blocks before anyone else's.
+
+use super::{LayerType, LayeredMap};
+use crate::error::{Error, Result};
+use crate::Index64;
+use parking_lot::{Mutex, RwLock};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+#[cfg(feature = "container_v2")]
+use super::static_map::{latest_blocks, CellBlock};
+#[cfg(feature = "container_v2")]
+use crate::container_v2::ContainerReaderV2;
+
+/// Resource limits of one map; `None` is unlimited
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MapQuota {
+    /// Memory a live map may use before writes are refused
+    pub max_memory_bytes: Option<usize>,
+    /// Share of the block cache a container map may hold
+    pub max_cache_bytes: Option<usize>,
+}
+
+/// Resources one map uses
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MapUsage {
+    /// Memory of a live map's layers
+    pub memory_bytes: usize,
+    /// Voxels of a live map
+    pub voxels: usize,
+    /// Cached blocks of a container map
+    pub cached_blocks: usize,
+    /// Bytes of those blocks
+    pub cached_bytes: usize,
+}
+
+/// What a name refers to
+enum MapSource {
+    Live(RwLock<LayeredMap>),
+    #[cfg(feature = "container_v2")]
+    Container {
+        reader: ContainerReaderV2,
+        /// Frame of the latest write of each block
+        blocks: HashMap<Index64, usize>,
+    },
+}
+
+struct Entry {
+    /// Cache owner id, never reused
+    id: u64,
+    quota: MapQuota,
+    source: MapSource,
+    /// Set under the cache lock once the map is unregistered, so calls
+    /// still holding it stop caching blocks
+    removed: AtomicBool,
+}
+
+/// Named maps with shared caches and per-map quotas
+///
+/// # Example
+///
+/// ```
+/// use octaindex3d::layers::{
+///     LayerType, LayeredMap, MapQuota, MapRegistry, Measurement, OccupancyLayer,
+/// };
+/// use octaindex3d::Index64;
+/// use std::sync::Arc;
+///
+/// # fn main() -> octaindex3d::Result<()> {
+/// let registry = Arc::new(MapRegistry::new(64 << 20));
+/// let mut map = LayeredMap::new();
+/// map.add_occupancy_layer(OccupancyLayer::new());
+/// let quota = MapQuota { max_memory_bytes: Some(16 << 20), ..MapQuota::default() };
+/// registry.insert_map("warehouse-3", map, quota)?;
+///
+/// let cell = Index64::new(0, 0, 8, 10, 10, 10)?;
+/// let shared = Arc::clone(&registry);
+/// std::thread::spawn(move || {
+///     shared.write("warehouse-3", |map| {
+///         map.update_occupancy(cell, &Measurement::occupied(0.9))
+///     })
+/// })
+/// .join()
+/// .unwrap()??;
+///
+/// assert!(registry.query("warehouse-3", LayerType::Occupancy, cell)?.unwrap() > 0.5);
+/// assert_eq!(registry.usage("warehouse-3")?.voxels, 1);
+/// # Ok(())
+/// # }
+/// ```
+pub struct MapRegistry {
+    maps: RwLock<HashMap<String, Arc<Entry>>>,
+    #[cfg_attr(not(feature = "container_v2"), allow(dead_code))]
+    cache: Mutex<BlockCache>,
+    next_id: AtomicU64,
+}
+
+impl MapRegistry {
+    /// Create an empty registry whose block cache holds up to
+    /// `cache_bytes` across all container maps
+    pub fn new(cache_bytes: usize) -> Self {
+        Self {
+            maps: RwLock::new(HashMap::new()),
+            cache: Mutex::new(BlockCache::new(cache_bytes)),
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    /// Register a live map under `name`
+    pub fn insert_map(
+        &self,
+        name: impl Into<String>,
+        map: LayeredMap,
+        quota: MapQuota,
+    ) -> Result<()> {
+        self.insert(name.into(), quota, MapSource::Live(RwLock::new(map)))
+    }
+
+    /// Register an opened container under `name`
+    #[cfg(feature = "container_v2")]
+    pub fn insert_container(
+        &self,
+        name: impl Into<String>,
+        reader: ContainerReaderV2,
+        quota: MapQuota,
+    ) -> Result<()> {
+        let blocks = latest_blocks(&reader).into_iter().collect();
+        self.insert(name.into(), quota, MapSource::Container { reader, blocks })
+    }
+
+    /// Open the container at `path` and register it under `name`
+    #[cfg(feature = "container_v2")]
+    pub fn open_container<P: AsRef<std::path::Path>>(
+        &self,
+        name: impl Into<String>,
+        path: P,
+        quota: MapQuota,
+    ) -> Result<()> {
+        self.insert_container(name, ContainerReaderV2::open(path)?, quota)
+    }
+
+    fn insert(&self, name: String, quota: MapQuota, source: MapSource) -> Result<()> {
+        let mut maps = self.maps.write();
+        if maps.contains_key(&name) {
+            return Err(Error::DuplicateMap(name));
+        }
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        maps.insert(
+            name,
+            Arc::new(Entry {
+                id,
+                quota,
+                source,
+                removed: AtomicBool::new(false),
+            }),
+        );
+        Ok(())
+    }
+
+    /// Unregister a map, releasing its cached blocks; false if unknown
+    ///
+    /// Calls already holding the map finish first.
+    pub fn remove(&self, name: &str) -> bool {
+        let Some(entry) = self.maps.write().remove(name) else {
+            return false;
+        };
+        let mut cache = self.cache.lock();
+        entry.removed.store(true, Ordering::Relaxed);
+        cache.release(entry.id);
+        true
+    }
+
+    /// Whether a map is registered under `name`
+    pub fn contains(&self, name: &str) -> bool {
+        self.maps.read().contains_key(name)
+    }
+
+    /// Registered names, sorted
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.maps.read().keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Number of registered maps
+    pub fn len(&self) -> usize {
+        self.maps.read().len()
+    }
+
+    /// Whether no map is registered
+    pub fn is_empty(&self) -> bool {
+        self.maps.read().is_empty()
+    }
+
+    fn entry(&self, name: &str) -> Result<Arc<Entry>> {
+        self.maps
+            .read()
+            .get(name)
+            .cloned()
+            .ok_or_else(|| Error::UnknownMap(name.to_string()))
+    }
+
+    /// Run `f` on the live map `name` under its read lock
+    pub fn read<R>(&self, name: &str, f: impl FnOnce(&LayeredMap) -> R) -> Result<R> {
+        let entry = self.entry(name)?;
+        let map = live(name, &entry)?.read();
+        Ok(f(&map))
+    }
+
+    /// Run `f` on the live map `name` under its write lock
+    ///
+    /// Fails without calling `f` while the map is over its memory quota.
+    pub fn write<R>(&self, name: &str, f: impl FnOnce(&mut LayeredMap) -> R) -> Result<R> {
+        let entry = self.entry(name)?;
+        let mut map = live(name, &entry)?.write();
+        if let Some(limit) = entry.quota.max_memory_bytes {
+            let used = map.total_memory_usage();
+            if used > limit {
+                return Err(Error::QuotaExceeded {
+                    name: name.to_string(),
+                    detail: format!("{} bytes in memory, limit {}", used, limit),
+                });
+            }
+        }
+        Ok(f(&mut map))
+    }
+
+    /// Value of a cell in one layer of map `name`, live or container
+    pub fn query(&self, name: &str, layer_type: LayerType, idx: Index64) -> Result<Option<f32>> {
+        let entry = self.entry(name)?;
+        match &entry.source {
+            MapSource::Live(map) => Ok(map.read().layer(layer_type).and_then(|l| l.query(idx))),
+            #[cfg(feature = "container_v2")]
+            MapSource::Container { reader, blocks } => {
+                let key = block_key(idx);
+                let Some(&frame) = blocks.get(&key) else {
+                    return Ok(None);
+                };
+                let block = self.cached_block(&entry, key, || {
+                    Ok(
+                        CellBlock::decode(key, &reader.frame_data(frame)?)?.unwrap_or(CellBlock {
+                            layers: Vec::new(),
+                            rows: Vec::new(),
+                        }),
+                    )
+                })?;
+                let Some(column) = block.layers.iter().position(|&l| l == layer_type) else {
+                    return Ok(None);
+                };
+                Ok(block
+                    .rows
+                    .binary_search_by_key(&idx, |(cell, _)| *cell)
+                    .ok()
+                    .map(|row| block.rows[row].1[column])
+                    .filter(|value| !value.is_nan()))
+            }
+        }
+    }
+
+    /// Block `key` of a container map from the cache, decoding it on a miss
+    #[cfg(feature = "container_v2")]
+    fn cached_block(
+        &self,
+        entry: &Entry,
+        key: Index64,
+        decode: impl FnOnce() -> Result<CellBlock>,
+    ) -> Result<Arc<CellBlock>> {
+        if let Some(block) = self.cache.lock().get(entry.id, key) {
+            return Ok(block);
+        }
+        // Decode outside the lock so other maps are not held up
+        let block = Arc::new(decode()?);
+        let bytes = 64
+            + block
+                .rows
+                .iter()
+                .map(|(_, values)| 32 + 4 * values.len())
+                .sum::<usize>();
+        let mut cache = self.cache.lock();
+        // A map removed meanwhile has released its blocks for good
+        if !entry.removed.load(Ordering::Relaxed) {
+            cache.insert(
+                entry.id,
+                key,
+                Arc::clone(&block),
+                bytes,
+                entry.quota.max_cache_bytes,
+            );
+        }
+        Ok(block)
+    }
+
+    /// Resources map `name` uses
+    pub fn usage(&self, name: &str) -> Result<MapUsage> {
+        let entry = self.entry(name)?;
+        let (cached_blocks, cached_bytes) = self.cache.lock().usage(entry.id);
+        let (memory_bytes, voxels) = match &entry.source {
+            MapSource::Live(map) => {
+                let map = map.read();
+                (map.total_memory_usage(), map.total_voxels())
+            }
+            #[cfg(feature = "container_v2")]
+            MapSource::Container { .. } => (0, 0),
+        };
+        Ok(MapUsage {
+            memory_bytes,
+            voxels,
+            cached_blocks,
+            cached_bytes,
+        })
+    }
+
+    /// Bytes held by the shared block cache
+    pub fn cache_bytes(&self) -> usize {
+        self.cache.lock().bytes
+    }
+}
+
+#[cfg_attr(not(feature = "container_v2"), allow(unused_variables))]
+fn live<'a>(name: &str, entry: &'a Entry) -> Result<&'a RwLock<LayeredMap>> {
+    match &entry.source {
+        MapSource::Live(map) => Ok(map),
+        #[cfg(feature = "container_v2")]
+        MapSource::Container { .. } => Err(Error::InvalidFormat(format!(
+            "map {} is a read-only container",
+            name
+        ))),
+    }
+}
+
+/// Key of the container cell block holding `idx`
+#[cfg(feature = "container_v2")]
+fn block_key(idx: Index64) -> Index64 {
+    let (base, _) = super::soa::split(idx);
+    super::soa::join(base, 0)
+}
+
+/// Least recently used cell blocks of all container maps
+#[cfg_attr(not(feature = "container_v2"), allow(dead_code))]
+struct BlockCache {
+    capacity: usize,
+    bytes: usize,
+    tick: u64,
+    #[cfg(feature = "container_v2")]
+    blocks: HashMap<(u64, Index64), CacheSlot>,
+    /// Slots by last use
+    order: std::collections::BTreeMap<u64, (u64, Index64)>,
+    /// Last uses and bytes of each owner's slots
+    owners: HashMap<u64, (std::collections::BTreeSet<u64>, usize)>,
+}
+
+#[cfg(feature = "container_v2")]
+struct CacheSlot {
+    block: Arc<CellBlock>,
+    bytes: usize,
+    used: u64,
+}
+
+#[cfg_attr(not(feature = "container_v2"), allow(dead_code))]
+impl BlockCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            bytes: 0,
+            tick: 0,
+            #[cfg(feature = "container_v2")]
+            blocks: HashMap::new(),
+            order: Default::default(),
+            owners: HashMap::new(),
+        }
+    }
+
+    /// Blocks and bytes cached for `owner`
+    fn usage(&self, owner: u64) -> (usize, usize) {
+        self.owners
+            .get(&owner)
+            .map_or((0, 0), |(uses, bytes)| (uses.len(), *bytes))
+    }
+
+    /// Drop every block of `owner`
+    fn release(&mut self, owner: u64) {
+        let uses = self
+            .owners
+            .get(&owner)
+            .map(|(uses, _)| uses.iter().copied().collect::<Vec<_>>())
+            .unwrap_or_default();
+        for used in uses {
+            self.evict(used);
+        }
+        self.owners.remove(&owner);
+    }
+
+    /// Drop the slot last used at `used`
+    fn evict(&mut self, used: u64) {
+        let Some((owner, key)) = self.order.remove(&used) else {
+            return;
+        };
+        #[cfg(feature = "container_v2")]
+        if let Some(slot) = self.blocks.remove(&(owner, key)) {
+            self.bytes -= slot.bytes;
+            if let Some((uses, bytes)) = self.owners.get_mut(&owner) {
+                uses.remove(&used);
+                *bytes -= slot.bytes;
+            }
+        }
+        #[cfg(not(feature = "container_v2"))]
+        let _ = (owner, key);
+    }
+
+    fn touch(&mut self, owner: u64, key: Index64, old: Option<u64>) -> u64 {
+        self.tick += 1;
+        let (uses, _) = self.owners.entry(owner).or_default();
+        if let Some(old) = old {
+            uses.remove(&old);
+            self.order.remove(&old);
+        }
+        uses.insert(self.tick);
+        self.order.insert(self.tick, (owner, key));
+        self.tick
+    }
+}
+
+#[cfg(feature = "container_v2")]
+impl BlockCache {
+    fn get(&mut self, owner: u64, key: Index64) -> Option<Arc<CellBlock>> {
+        let old = self.blocks.get(&(owner, key))?.used;
+        let used = self.touch(owner, key, Some(old));
+        let slot = self.blocks.get_mut(&(owner, key)).expect("present above");
+        slot.used = used;
+        Some(Arc::clone(&slot.block))
+    }
+
+    /// Cache a block, evicting the owner's oldest blocks past `quota`
+    /// and then the oldest of anyone's past the capacity
+    fn insert(
+        &mut self,
+        owner: u64,
+        key: Index64,
+        block: Arc<CellBlock>,
+        bytes: usize,
+        quota: Option<usize>,
+    ) {
+        let limit = quota.unwrap_or(usize::MAX).min(self.capacity);
+        if bytes > limit {
+            return;
+        }
+        if let Some(slot) = self.blocks.get(&(owner, key)) {
+            // Another thread decoded it first
+            let used = slot.used;
+            self.evict(used);
+        }
+        while self.usage(owner).1 + bytes > limit {
+            let oldest = self.owners[&owner].0.iter().next().copied();
+            match oldest {
+                Some(used) => self.evict(used),
+                None => break,
+            }
+        }
+        while self.bytes + bytes > self.capacity {
+            match self.order.keys().next().copied() {
+                Some(used) => self.evict(used),
+                None => break,
+            }
+        }
+        let used = self.touch(owner, key, None);
+        self.blocks
+            .insert((owner, key), CacheSlot { block, bytes, used });
+        self.bytes += bytes;
+        self.owners.get_mut(&owner).expect("touched").1 += bytes;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layers::{Measurement, OccupancyLayer};
+
+    #[test]
+    fn test_live_maps_and_quotas() {
+        let registry = MapRegistry::new(1 << 20);
+        let cell = Index64::new(0, 0, 8, 1, 2, 3).unwrap();
+        let quota = MapQuota {
+            max_memory_bytes: Some(1),
+            ..MapQuota::default()
+        };
+        for name in ["b", "a"] {
+            let mut map = LayeredMap::new();
+            map.add_occupancy_layer(OccupancyLayer::new());
+            registry.insert_map(name, map, quota).unwrap();
+        }
+        assert_eq!(registry.names(), vec!["a", "b"]);
+        assert!(matches!(
+            registry.insert_map("a", LayeredMap::new(), quota),
+            Err(Error::DuplicateMap(_))
+        ));
+        assert!(matches!(
+            registry.query("c", LayerType::Occupancy, cell),
+            Err(Error::UnknownMap(_))
+        ));
+
+        let update = |map: &mut LayeredMap| map.update_occupancy(cell, &Measurement::occupied(0.9));
+        // The first write takes the map over its quota, the next is refused
+        registry.write("a", update).unwrap().unwrap();
+        assert!(matches!(
+            registry.write("a", update),
+            Err(Error::QuotaExceeded { .. })
+        ));
+        assert!(registry
+            .query("a", LayerType::Occupancy, cell)
+            .unwrap()
+            .is_some());
+        assert_eq!(
+            registry.query("b", LayerType::Occupancy, cell).unwrap(),
+            None
+        );
+        assert_eq!(registry.usage("a").unwrap().voxels, 1);
+
+        assert!(registry.remove("a"));
+        assert!(!registry.remove("a"));
+        assert_eq!(registry.len(), 1);
+    }
+
+    #[cfg(feature = "container_v2")]
+    #[test]
+    fn test_containers_share_the_block_cache() {
+        use crate::container_v2::{ContainerWriterV2, StreamConfig};
+        use crate::layers::StaticMap;
+
+        let container = |value: bool| {
+            let mut occupancy = OccupancyLayer::new();
+            for x in 0..64u16 {
+                occupancy.update_occupancy(Index64::new(0, 0, 9, x, 0, 0).unwrap(), value, 0.9);
+            }
+            let mut map = LayeredMap::new();
+            map.add_occupancy_layer(occupancy);
+            let mut bytes = std::io::Cursor::new(Vec::new());
+            let mut writer = ContainerWriterV2::new(&mut bytes, StreamConfig::default()).unwrap();
+            StaticMap::from(&map).write_container(&mut writer).unwrap();
+            writer.finish().unwrap();
+            ContainerReaderV2::from_bytes(bytes.into_inner()).unwrap()
+        };
+        // Each block of 8 cells takes 64 + 8 * 36 bytes
+        let block_bytes = 64 + 8 * 36;
+        let registry = MapRegistry::new(5 * block_bytes);
+        let small = MapQuota {
+            max_cache_bytes: Some(2 * block_bytes),
+            ..MapQuota::default()
+        };
+        registry
+            .insert_container("site-a", container(true), small)
+            .unwrap();
+        registry
+            .insert_container("site-b", container(false), MapQuota::default())
+            .unwrap();
+
+        let cell = |x| Index64::new(0, 0, 9, x, 0, 0).unwrap();
+        for x in (0..64).step_by(8) {
+            assert!(
+                registry
+                    .query("site-a", LayerType::Occupancy, cell(x))
+                    .unwrap()
+                    .unwrap()
+                    > 0.5
+            );
+        }
+        // Site A keeps within its quota
+        assert_eq!(registry.usage("site-a").unwrap().cached_blocks, 2);
+        for x in (0..64).step_by(8) {
+            assert!(
+                registry
+                    .query("site-b", LayerType::Occupancy, cell(x))
+                    .unwrap()
+                    .unwrap()
+                    < 0.5
+            );
+        }
+        // Site B fills the rest, evicting site A's blocks first
+        assert_eq!(registry.usage("site-b").unwrap().cached_blocks, 5);
+        assert_eq!(registry.usage("site-a").unwrap().cached_blocks, 0);
+        assert_eq!(registry.cache_bytes(), 5 * block_bytes);
+        assert_eq!(
+            registry.query("site-b", LayerType::TSDF, cell(0)).unwrap(),
+            None
+        );
+        assert!(matches!(
+            registry.write("site-b", |_| ()),
+            Err(Error::InvalidFormat(_))
+        ));
+
+        // A query still holding a removed map does not cache its block
+        let held = registry.entry("site-b").unwrap();
+        registry.remove("site-b");
+        assert_eq!(registry.cache_bytes(), 0);
+        let empty = || {
+            Ok(CellBlock {
+                layers: Vec::new(),
+                rows: Vec::new(),
+            })
+        };
+        registry.cached_block(&held, cell(0), empty).unwrap();
+        assert_eq!(registry.cache_bytes(), 0);
+    }
+}
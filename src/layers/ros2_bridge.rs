@@ -21,11 +21,28 @@
 //! # Ok(())
 //! # }
 //! ```
+//!
+//! ## CDR
+//!
+//! [`PointCloud2`] and [`PoseStamped`] encode to and decode from the
+//! little endian CDR that ROS 2 middlewares put on the wire, so messages
+//! bridged from DDS (for example by Zenoh) can be read without a ROS 2
+//! installation.
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
 use super::occupancy::OccupancyLayer;
+use crate::clock::Clock;
+use crate::error::{Error, Result};
+
+/// Encapsulation header of little endian plain CDR
+const CDR_LE: [u8; 4] = [0x00, 0x01, 0x00, 0x00];
+
+/// PointField datatype of 32-bit floats
+const FLOAT32: u8 = 7;
+/// PointField datatype of 64-bit floats
+const FLOAT64: u8 = 8;
 
 /// ROS2 Header (std_msgs/Header)
 #[derive(Debug, Clone)]
@@ -65,6 +82,16 @@ impl Time {
             nanosec: duration.subsec_nanos(),
         }
     }
+
+    /// Current time of `clock`, e.g. simulated time
+    pub fn from_clock(clock: &dyn Clock) -> Self {
+        let now = clock.now();
+        let sec = now.floor();
+        Self {
+            sec: sec as i32,
+            nanosec: (((now - sec) * 1e9) as u32).min(999_999_999),
+        }
+    }
 }
 
 /// ROS2 OccupancyGrid (nav_msgs/OccupancyGrid)
@@ -133,6 +160,102 @@ pub struct Quaternion {
     pub w: f64,
 }
 
+/// PoseStamped (geometry_msgs/PoseStamped)
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PoseStamped {
+    /// Message header with timestamp and frame
+    pub header: Header,
+    /// Pose in the header's frame
+    pub pose: Pose,
+}
+
+impl Pose {
+    /// Map a point from the posed frame into its parent frame
+    pub fn transform_point(&self, p: (f64, f64, f64)) -> (f64, f64, f64) {
+        let Quaternion { x, y, z, w } = self.orientation;
+        // v + 2w (q × v) + 2 q × (q × v)
+        let (tx, ty, tz) = (
+            2.0 * (y * p.2 - z * p.1),
+            2.0 * (z * p.0 - x * p.2),
+            2.0 * (x * p.1 - y * p.0),
+        );
+        (
+            p.0 + w * tx + (y * tz - z * ty) + self.position.x,
+            p.1 + w * ty + (z * tx - x * tz) + self.position.y,
+            p.2 + w * tz + (x * ty - y * tx) + self.position.z,
+        )
+    }
+
+    /// Map a point from the parent frame into the posed frame
+    ///
+    /// Inverse of [`transform_point`](Self::transform_point) for a unit
+    /// orientation quaternion.
+    pub fn inverse_transform_point(&self, p: (f64, f64, f64)) -> (f64, f64, f64) {
+        let Quaternion { x, y, z, w } = self.orientation;
+        let inverse = Pose {
+            position: Point {
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+            },
+            orientation: Quaternion {
+                x: -x,
+                y: -y,
+                z: -z,
+                w,
+            },
+        };
+        inverse.transform_point((
+            p.0 - self.position.x,
+            p.1 - self.position.y,
+            p.2 - self.position.z,
+        ))
+    }
+}
+
+impl PoseStamped {
+    /// Encode as CDR
+    pub fn to_cdr(&self) -> Vec<u8> {
+        let mut w = CdrWriter::new();
+        w.header(&self.header);
+        let Pose {
+            position: p,
+            orientation: q,
+        } = &self.pose;
+        for v in [p.x, p.y, p.z, q.x, q.y, q.z, q.w] {
+            w.f64(v);
+        }
+        w.finish()
+    }
+
+    /// Decode from CDR
+    pub fn from_cdr(bytes: &[u8]) -> Result<Self> {
+        let mut r = CdrReader::new(bytes)?;
+        let header = r.header()?;
+        let mut v = [0.0; 7];
+        for x in &mut v {
+            *x = r.f64()?;
+        }
+        Ok(Self {
+            header,
+            pose: Pose {
+                position: Point {
+                    x: v[0],
+                    y: v[1],
+                    z: v[2],
+                },
+                orientation: Quaternion {
+                    x: v[3],
+                    y: v[4],
+                    z: v[5],
+                    w: v[6],
+                },
+            },
+        })
+    }
+}
+
 impl OccupancyGrid {
     /// Convert OctaIndex3D occupancy layer to ROS2 OccupancyGrid (2D projection)
     ///
@@ -180,7 +303,7 @@ impl OccupancyGrid {
 
     /// Convert to bytes for ROS2 publishing (CDR serialization)
     #[cfg(feature = "serde")]
-    pub fn to_cdr_bytes(&self) -> Result<Vec<u8>, String> {
+    pub fn to_cdr_bytes(&self) -> std::result::Result<Vec<u8>, String> {
         serde_json::to_vec(self).map_err(|e| e.to_string())
     }
 }
@@ -331,6 +454,232 @@ impl PointCloud2 {
     }
 }
 
+impl PointCloud2 {
+    /// Positions of the points with finite x, y and z, in row order
+    ///
+    /// Reads the `x`, `y` and `z` fields as FLOAT32 or FLOAT64 in the
+    /// cloud's byte order.
+    pub fn points(&self) -> Result<Vec<(f32, f32, f32)>> {
+        let field = |name: &str| -> Result<(usize, u8)> {
+            let f = self.fields.iter().find(|f| f.name == name).ok_or_else(|| {
+                Error::InvalidFormat(format!("point cloud has no {} field", name))
+            })?;
+            match f.datatype {
+                FLOAT32 | FLOAT64 => Ok((f.offset as usize, f.datatype)),
+                t => Err(Error::InvalidFormat(format!(
+                    "unsupported {} datatype {}",
+                    name, t
+                ))),
+            }
+        };
+        let axes = [field("x")?, field("y")?, field("z")?];
+        let (step, row_step) = (self.point_step as usize, self.row_step as usize);
+        if axes
+            .iter()
+            .any(|&(offset, t)| offset + if t == FLOAT32 { 4 } else { 8 } > step)
+            || (self.width as usize) * step > row_step
+            || (self.height as usize) * row_step > self.data.len()
+        {
+            return Err(Error::InvalidFormat(
+                "point cloud layout exceeds its data".to_string(),
+            ));
+        }
+
+        let read = |at: usize, t: u8| -> f32 {
+            let d = &self.data;
+            match (t, self.is_bigendian) {
+                (FLOAT32, false) => f32::from_le_bytes(d[at..at + 4].try_into().unwrap()),
+                (FLOAT32, true) => f32::from_be_bytes(d[at..at + 4].try_into().unwrap()),
+                (_, false) => f64::from_le_bytes(d[at..at + 8].try_into().unwrap()) as f32,
+                (_, true) => f64::from_be_bytes(d[at..at + 8].try_into().unwrap()) as f32,
+            }
+        };
+        let mut points = Vec::with_capacity((self.width * self.height) as usize);
+        for row in 0..self.height as usize {
+            for col in 0..self.width as usize {
+                let base = row * row_step + col * step;
+                let [x, y, z] = axes.map(|(offset, t)| read(base + offset, t));
+                if x.is_finite() && y.is_finite() && z.is_finite() {
+                    points.push((x, y, z));
+                }
+            }
+        }
+        Ok(points)
+    }
+
+    /// Encode as CDR
+    pub fn to_cdr(&self) -> Vec<u8> {
+        let mut w = CdrWriter::new();
+        w.header(&self.header);
+        w.u32(self.height);
+        w.u32(self.width);
+        w.u32(self.fields.len() as u32);
+        for f in &self.fields {
+            w.string(&f.name);
+            w.u32(f.offset);
+            w.u8(f.datatype);
+            w.u32(f.count);
+        }
+        w.u8(self.is_bigendian as u8);
+        w.u32(self.point_step);
+        w.u32(self.row_step);
+        w.u32(self.data.len() as u32);
+        w.buf.extend_from_slice(&self.data);
+        w.u8(self.is_dense as u8);
+        w.finish()
+    }
+
+    /// Decode from CDR
+    pub fn from_cdr(bytes: &[u8]) -> Result<Self> {
+        let mut r = CdrReader::new(bytes)?;
+        let header = r.header()?;
+        let height = r.u32()?;
+        let width = r.u32()?;
+        let field_count = r.u32()? as usize;
+        let mut fields = Vec::with_capacity(field_count.min(16));
+        for _ in 0..field_count {
+            fields.push(PointField {
+                name: r.string()?,
+                offset: r.u32()?,
+                datatype: r.u8()?,
+                count: r.u32()?,
+            });
+        }
+        let is_bigendian = r.u8()? != 0;
+        let point_step = r.u32()?;
+        let row_step = r.u32()?;
+        let len = r.u32()? as usize;
+        let data = r.take(len)?.to_vec();
+        let is_dense = r.u8()? != 0;
+        Ok(Self {
+            header,
+            height,
+            width,
+            fields,
+            is_bigendian,
+            point_step,
+            row_step,
+            data,
+            is_dense,
+        })
+    }
+}
+
+/// Little endian CDR encoder; alignment counts from after the
+/// encapsulation header
+struct CdrWriter {
+    buf: Vec<u8>,
+}
+
+impl CdrWriter {
+    fn new() -> Self {
+        Self {
+            buf: CDR_LE.to_vec(),
+        }
+    }
+
+    fn align(&mut self, n: usize) {
+        while (self.buf.len() - 4) % n != 0 {
+            self.buf.push(0);
+        }
+    }
+
+    fn u8(&mut self, v: u8) {
+        self.buf.push(v);
+    }
+
+    fn u32(&mut self, v: u32) {
+        self.align(4);
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn f64(&mut self, v: f64) {
+        self.align(8);
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn string(&mut self, s: &str) {
+        self.u32(s.len() as u32 + 1);
+        self.buf.extend_from_slice(s.as_bytes());
+        self.buf.push(0);
+    }
+
+    fn header(&mut self, h: &Header) {
+        self.u32(h.stamp.sec as u32);
+        self.u32(h.stamp.nanosec);
+        self.string(&h.frame_id);
+    }
+
+    fn finish(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+/// Little endian CDR decoder
+struct CdrReader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> CdrReader<'a> {
+    fn new(bytes: &'a [u8]) -> Result<Self> {
+        match bytes.get(..2) {
+            Some([0x00, 0x01]) => Ok(Self {
+                buf: &bytes[4.min(bytes.len())..],
+                pos: 0,
+            }),
+            _ => Err(Error::InvalidFormat(
+                "expected little endian CDR".to_string(),
+            )),
+        }
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(n)
+            .filter(|&end| end <= self.buf.len())
+            .ok_or_else(|| Error::InvalidFormat("truncated CDR message".to_string()))?;
+        let bytes = &self.buf[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn align(&mut self, n: usize) -> Result<()> {
+        let pad = (n - self.pos % n) % n;
+        self.take(pad).map(|_| ())
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        self.align(4)?;
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn f64(&mut self) -> Result<f64> {
+        self.align(8)?;
+        Ok(f64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let len = self.u32()? as usize;
+        let bytes = self.take(len)?;
+        let text = bytes.strip_suffix(&[0]).unwrap_or(bytes);
+        String::from_utf8(text.to_vec())
+            .map_err(|_| Error::InvalidFormat("CDR string is not UTF-8".to_string()))
+    }
+
+    fn header(&mut self) -> Result<Header> {
+        Ok(Header {
+            stamp: Time::new(self.u32()? as i32, self.u32()?),
+            frame_id: self.string()?,
+        })
+    }
+}
+
 /// Helper to extract occupied voxels from OccupancyLayer for ROS2 publishing
 pub fn extract_occupied_voxels(_layer: &OccupancyLayer, _voxel_size: f32) -> Vec<(f32, f32, f32)> {
     // This would iterate through the layer's voxels
@@ -346,6 +695,10 @@ mod tests {
     fn test_ros2_time() {
         let time = Time::now();
         assert!(time.sec > 0);
+
+        let sim = crate::clock::SimClock::new(12.25);
+        let time = Time::from_clock(&sim);
+        assert_eq!((time.sec, time.nanosec), (12, 250_000_000));
     }
 
     #[test]
@@ -358,6 +711,48 @@ mod tests {
         assert_eq!(cloud.data.len(), 24); // 2 points * 3 floats * 4 bytes
     }
 
+    #[test]
+    fn test_cdr_round_trip() {
+        let mut cloud = PointCloud2::from_occupied_voxels(
+            vec![(1.0, 2.0, 3.0), (f32::NAN, 0.0, 0.0), (4.0, 5.0, 6.0)],
+            "lidar",
+        );
+        cloud.header.stamp = Time::new(12, 34);
+        let bytes = cloud.to_cdr();
+        assert_eq!(&bytes[..4], &CDR_LE);
+        let decoded = PointCloud2::from_cdr(&bytes).unwrap();
+        assert_eq!(decoded.header.frame_id, "lidar");
+        assert_eq!(decoded.header.stamp.nanosec, 34);
+        assert_eq!(decoded.fields.len(), 3);
+        assert_eq!(
+            decoded.points().unwrap(),
+            vec![(1.0, 2.0, 3.0), (4.0, 5.0, 6.0)]
+        );
+        assert!(PointCloud2::from_cdr(&bytes[..bytes.len() - 2]).is_err());
+
+        // A quarter turn about z, then a shift
+        let half = std::f64::consts::FRAC_1_SQRT_2;
+        let pose = PoseStamped {
+            header: cloud.header.clone(),
+            pose: Pose {
+                position: Point {
+                    x: 10.0,
+                    y: 0.0,
+                    z: 1.0,
+                },
+                orientation: Quaternion {
+                    x: 0.0,
+                    y: 0.0,
+                    z: half,
+                    w: half,
+                },
+            },
+        };
+        let decoded = PoseStamped::from_cdr(&pose.to_cdr()).unwrap();
+        let (x, y, z) = decoded.pose.transform_point((1.0, 0.0, 0.0));
+        assert!((x - 10.0).abs() < 1e-12 && (y - 1.0).abs() < 1e-12 && (z - 1.0).abs() < 1e-12);
+    }
+
     #[test]
     #[cfg(feature = "serde")]
     fn test_occupancy_grid_serialization() {
@@ -0,0 +1,439 @@
+//! Pruned scans over container cell blocks
+//!
+//! [`ContainerScan`] streams the cells of a container written with
+//! [`StaticMap::write_container`](super::StaticMap::write_container) that
+//! fall inside [`CellBounds`]. Every cell block covers an aligned 8×8×8
+//! block of cells, so the bounds are first checked against each block's
+//! key: blocks that cannot hold a matching cell are never read or
+//! decompressed. Query engines map their filters on frame, LOD, Morton
+//! code and coordinates onto the bounds and treat the remaining blocks as
+//! partitions, as the `sqlite` and `datafusion` table providers do.
+
+use super::static_map::{latest_blocks, CellBlock};
+use super::LayerType;
+use crate::container_v2::ContainerReaderV2;
+use crate::error::Result;
+use crate::{FrameId, Index64};
+
+/// Low Morton bits addressing a cell within its block
+const BLOCK_MORTON_BITS: u64 = 0x1FF;
+
+/// Low coordinate bits addressing a cell within its block
+const BLOCK_COORD_BITS: u16 = 0x7;
+
+/// Cells selected by a [`ContainerScan`]
+///
+/// Each bound is optional; the default selects every cell. Ranges are
+/// inclusive.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CellBounds {
+    /// Only cells of this frame
+    pub frame: Option<FrameId>,
+    /// Only cells at this LOD
+    pub lod: Option<u8>,
+    /// Only cells with a Morton code in this range
+    pub morton: Option<(u64, u64)>,
+    /// Only cells with coordinates in this box, as `(min, max)`
+    pub coords: Option<([u16; 3], [u16; 3])>,
+}
+
+impl CellBounds {
+    /// Bounds selecting every cell
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    /// Restrict to one frame
+    pub fn with_frame(mut self, frame: FrameId) -> Self {
+        self.frame = Some(frame);
+        self
+    }
+
+    /// Restrict to one LOD
+    pub fn with_lod(mut self, lod: u8) -> Self {
+        self.lod = Some(lod);
+        self
+    }
+
+    /// Restrict to Morton codes in `lo..=hi`, intersecting any earlier range
+    pub fn with_morton(mut self, lo: u64, hi: u64) -> Self {
+        self.morton = Some(match self.morton {
+            Some((l, h)) => (l.max(lo), h.min(hi)),
+            None => (lo, hi),
+        });
+        self
+    }
+
+    /// Restrict to coordinates in the box `min..=max`
+    pub fn with_coords(mut self, min: [u16; 3], max: [u16; 3]) -> Self {
+        self.coords = Some((min, max));
+        self
+    }
+
+    /// Whether no cell can match
+    pub fn is_empty(&self) -> bool {
+        self.morton.is_some_and(|(lo, hi)| lo > hi)
+            || self
+                .coords
+                .is_some_and(|(min, max)| (0..3).any(|a| min[a] > max[a]))
+    }
+
+    /// Whether a cell matches
+    pub fn contains(&self, idx: Index64) -> bool {
+        let (x, y, z) = idx.decode_coords();
+        self.frame.map_or(true, |f| idx.frame_id() == f)
+            && self.lod.map_or(true, |l| idx.lod() == l)
+            && self
+                .morton
+                .map_or(true, |(lo, hi)| (lo..=hi).contains(&idx.morton()))
+            && self.coords.map_or(true, |(min, max)| {
+                [x, y, z]
+                    .iter()
+                    .enumerate()
+                    .all(|(a, c)| (min[a]..=max[a]).contains(c))
+            })
+    }
+
+    /// Whether the block keyed `key` may hold a matching cell
+    pub fn may_contain_block(&self, key: Index64) -> bool {
+        let (x, y, z) = key.decode_coords();
+        let first = key.morton() & !BLOCK_MORTON_BITS;
+        !self.is_empty()
+            && self.frame.map_or(true, |f| key.frame_id() == f)
+            && self.lod.map_or(true, |l| key.lod() == l)
+            && self.morton.map_or(true, |(lo, hi)| {
+                first <= hi && first | BLOCK_MORTON_BITS >= lo
+            })
+            && self.coords.map_or(true, |(min, max)| {
+                [x, y, z].iter().enumerate().all(|(a, &c)| {
+                    c & !BLOCK_COORD_BITS <= max[a] && c | BLOCK_COORD_BITS >= min[a]
+                })
+            })
+    }
+}
+
+#[cfg(any(feature = "sqlite", feature = "datafusion"))]
+pub(crate) use pushdown::{BoundColumn, BoundsBuilder};
+
+/// Filter pushdown shared by the SQL integrations
+#[cfg(any(feature = "sqlite", feature = "datafusion"))]
+mod pushdown {
+    use super::CellBounds;
+
+    /// Largest Morton code (48 bits)
+    const MORTON_MAX: u64 = (1 << 48) - 1;
+
+    /// Cell property a query engine filter can bound
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub(crate) enum BoundColumn {
+        Frame,
+        Lod,
+        /// Coordinate on axis 0, 1 or 2
+        Coord(usize),
+        Morton,
+    }
+
+    /// Inclusive range of one column, narrowed by comparisons
+    #[derive(Debug, Clone, Copy)]
+    struct ColumnRange {
+        lo: f64,
+        hi: f64,
+    }
+
+    impl ColumnRange {
+        fn new(max: u64) -> Self {
+            Self {
+                lo: 0.0,
+                hi: max as f64,
+            }
+        }
+
+        fn is_empty(&self) -> bool {
+            self.lo > self.hi
+        }
+
+        /// The single value left, if any
+        fn single(&self) -> Option<f64> {
+            (self.lo == self.hi).then_some(self.lo)
+        }
+    }
+
+    /// [`CellBounds`] collected from the comparisons of a query's filters
+    #[derive(Debug, Clone, Copy)]
+    pub(crate) struct BoundsBuilder {
+        frame: ColumnRange,
+        lod: ColumnRange,
+        coords: [ColumnRange; 3],
+        morton: ColumnRange,
+    }
+
+    impl BoundsBuilder {
+        pub(crate) fn new() -> Self {
+            Self {
+                frame: ColumnRange::new(u8::MAX as u64),
+                lod: ColumnRange::new(15),
+                coords: [ColumnRange::new(u16::MAX as u64); 3],
+                morton: ColumnRange::new(MORTON_MAX),
+            }
+        }
+
+        /// Narrow `column` by `column <op> v`, with `op` one of `=`, `>`,
+        /// `g` (>=), `<` and `l` (<=); NaN, as from NULL, matches nothing
+        pub(crate) fn narrow(&mut self, column: BoundColumn, op: char, v: f64) {
+            let range = match column {
+                BoundColumn::Frame => &mut self.frame,
+                BoundColumn::Lod => &mut self.lod,
+                BoundColumn::Coord(axis) => &mut self.coords[axis],
+                BoundColumn::Morton => &mut self.morton,
+            };
+            if v.is_nan() {
+                range.hi = -1.0;
+                return;
+            }
+            match op {
+                '=' => (range.lo, range.hi) = (range.lo.max(v.ceil()), range.hi.min(v.floor())),
+                '>' => range.lo = range.lo.max(v.floor() + 1.0),
+                'g' => range.lo = range.lo.max(v.ceil()),
+                '<' => range.hi = range.hi.min(v.ceil() - 1.0),
+                'l' => range.hi = range.hi.min(v.floor()),
+                _ => {}
+            }
+        }
+
+        /// Bounds selecting the cells that may satisfy every comparison
+        ///
+        /// Frame and LOD are bounded only when narrowed to one value.
+        pub(crate) fn build(&self) -> CellBounds {
+            let mut all = [self.frame, self.lod, self.morton]
+                .into_iter()
+                .chain(self.coords);
+            if all.any(|range| range.is_empty()) {
+                // An empty Morton range selects no block
+                return CellBounds::all().with_morton(1, 0);
+            }
+            let mut bounds =
+                CellBounds::all().with_morton(self.morton.lo as u64, self.morton.hi as u64);
+            if let Some(f) = self.frame.single() {
+                bounds = bounds.with_frame(f as u8);
+            }
+            if let Some(l) = self.lod.single() {
+                bounds = bounds.with_lod(l as u8);
+            }
+            bounds.with_coords(
+                self.coords.map(|r| r.lo as u16),
+                self.coords.map(|r| r.hi as u16),
+            )
+        }
+    }
+}
+
+/// One cell of a [`ContainerScan`] with its value in every layer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CellRow {
+    /// The cell
+    pub index: Index64,
+    /// Values by layer tag, NaN where absent
+    values: [f32; LayerType::COUNT],
+}
+
+impl CellRow {
+    /// Row of a decoded cell block: one value per layer, NaN where absent
+    pub(crate) fn new(index: Index64, layers: &[LayerType], values: &[f32]) -> Self {
+        let mut row = Self {
+            index,
+            values: [f32::NAN; LayerType::COUNT],
+        };
+        for (layer_type, &value) in layers.iter().zip(values) {
+            row.values[layer_type.tag() as usize] = value;
+        }
+        row
+    }
+
+    /// Value of the cell in a layer, if stored
+    pub fn value(&self, layer_type: LayerType) -> Option<f32> {
+        let value = self.values[layer_type.tag() as usize];
+        (!value.is_nan()).then_some(value)
+    }
+
+    /// Stored values of the cell, in layer tag order
+    pub fn values(&self) -> impl Iterator<Item = (LayerType, f32)> + '_ {
+        self.values
+            .iter()
+            .enumerate()
+            .filter(|(_, value)| !value.is_nan())
+            .filter_map(|(tag, &value)| Some((LayerType::from_tag(tag as u8)?, value)))
+    }
+}
+
+/// Cells of a container inside [`CellBounds`], in block key order
+///
+/// # Example
+///
+/// ```
+/// use octaindex3d::container_v2::{ContainerReaderV2, ContainerWriterV2, StreamConfig};
+/// use octaindex3d::layers::{CellBounds, ContainerScan, LayerType, LayeredMap, OccupancyLayer, StaticMap};
+/// use octaindex3d::Index64;
+/// use std::io::Cursor;
+///
+/// # fn main() -> octaindex3d::Result<()> {
+/// let mut occupancy = OccupancyLayer::new();
+/// for x in 0..64 {
+///     occupancy.update_occupancy(Index64::new(0, 0, 10, x, 0, 0)?, true, 0.9);
+/// }
+/// let mut map = LayeredMap::new();
+/// map.add_occupancy_layer(occupancy);
+///
+/// let mut bytes = Vec::new();
+/// let mut writer = ContainerWriterV2::new(Cursor::new(&mut bytes), StreamConfig::default())?;
+/// StaticMap::from(&map).write_container(&mut writer)?;
+/// writer.finish()?;
+///
+/// let reader = ContainerReaderV2::from_bytes(bytes)?;
+/// let bounds = CellBounds::all().with_coords([10, 0, 0], [12, 0, 0]);
+/// let scan = ContainerScan::new(&reader, bounds);
+/// assert_eq!(scan.block_count(), 1); // of 8 blocks
+/// let cells = scan.collect::<octaindex3d::Result<Vec<_>>>()?;
+/// assert_eq!(cells.len(), 3);
+/// assert!(cells[0].value(LayerType::Occupancy).unwrap() > 0.5);
+/// # Ok(())
+/// # }
+/// ```
+pub struct ContainerScan<'a> {
+    reader: &'a ContainerReaderV2,
+    bounds: CellBounds,
+    /// Latest frame of each block that may hold a matching cell
+    blocks: Vec<(Index64, usize)>,
+    next_block: usize,
+    rows: std::vec::IntoIter<CellRow>,
+}
+
+impl<'a> ContainerScan<'a> {
+    /// Scan the latest cell blocks of `reader` for cells inside `bounds`
+    pub fn new(reader: &'a ContainerReaderV2, bounds: CellBounds) -> Self {
+        let mut blocks = latest_blocks(reader);
+        blocks.retain(|&(key, _)| bounds.may_contain_block(key));
+        Self {
+            reader,
+            bounds,
+            blocks,
+            next_block: 0,
+            rows: Vec::new().into_iter(),
+        }
+    }
+
+    /// The bounds being scanned
+    pub fn bounds(&self) -> &CellBounds {
+        &self.bounds
+    }
+
+    /// Blocks left after pruning, including any already scanned
+    pub fn block_count(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Keys of the blocks left after pruning
+    pub fn block_keys(&self) -> impl Iterator<Item = Index64> + '_ {
+        self.blocks.iter().map(|&(key, _)| key)
+    }
+
+    /// Decode the next block, keeping its matching cells
+    fn load_block(&mut self, key: Index64, frame: usize) -> Result<()> {
+        let Some(block) = CellBlock::decode(key, &self.reader.frame_data(frame)?)? else {
+            return Ok(());
+        };
+        let rows: Vec<CellRow> = block
+            .rows
+            .into_iter()
+            .filter(|(idx, _)| self.bounds.contains(*idx))
+            .map(|(index, values)| CellRow::new(index, &block.layers, &values))
+            .collect();
+        self.rows = rows.into_iter();
+        Ok(())
+    }
+}
+
+impl Iterator for ContainerScan<'_> {
+    type Item = Result<CellRow>;
+
+    fn next(&mut self) -> Option<Result<CellRow>> {
+        loop {
+            if let Some(row) = self.rows.next() {
+                return Some(Ok(row));
+            }
+            let &(key, frame) = self.blocks.get(self.next_block)?;
+            self.next_block += 1;
+            if let Err(e) = self.load_block(key, frame) {
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::container_v2::{ContainerWriterV2, StreamConfig};
+    use crate::layers::{LayeredMap, OccupancyLayer, StaticMap, TSDFLayer};
+
+    #[test]
+    fn test_scan_prunes_blocks() {
+        let mut occupancy = OccupancyLayer::new();
+        let mut tsdf = TSDFLayer::new(0.1);
+        for x in 0..32u16 {
+            for y in 0..32u16 {
+                let idx = Index64::new(0, 0, 10, x, y, 0).unwrap();
+                occupancy.update_occupancy(idx, (x + y) % 2 == 0, 0.8);
+                if x < 4 {
+                    tsdf.batch_update(&[(idx, 0.01, 1.0)]).unwrap();
+                }
+            }
+        }
+        let mut map = LayeredMap::new();
+        map.add_occupancy_layer(occupancy);
+        map.add_tsdf_layer(tsdf);
+
+        let mut bytes = std::io::Cursor::new(Vec::new());
+        let mut writer = ContainerWriterV2::new(&mut bytes, StreamConfig::default()).unwrap();
+        writer.write_frame(b"not cells").unwrap();
+        assert_eq!(
+            StaticMap::from(&map).write_container(&mut writer).unwrap(),
+            16
+        );
+        writer.finish().unwrap();
+        let reader = ContainerReaderV2::from_bytes(bytes.into_inner()).unwrap();
+
+        let all: Vec<CellRow> = ContainerScan::new(&reader, CellBounds::all())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(all.len(), 1024);
+        assert_eq!(
+            all.iter().filter_map(|r| r.value(LayerType::TSDF)).count(),
+            128
+        );
+
+        // A box straddling four blocks reads only those
+        let bounds = CellBounds::all()
+            .with_lod(10)
+            .with_coords([6, 6, 0], [9, 9, 0]);
+        let scan = ContainerScan::new(&reader, bounds);
+        assert_eq!(scan.block_count(), 4);
+        let cells: Vec<CellRow> = scan.collect::<Result<_>>().unwrap();
+        assert_eq!(cells.len(), 16);
+        assert!(cells.iter().all(|r| bounds.contains(r.index)));
+
+        // Morton ranges prune by block and filter by cell
+        let (lo, hi) = (all[100].index.morton(), all[160].index.morton());
+        let bounds = CellBounds::all().with_morton(lo, hi);
+        let scan = ContainerScan::new(&reader, bounds);
+        assert!(scan.block_count() < 4);
+        assert_eq!(scan.count(), 61);
+
+        for empty in [
+            CellBounds::all().with_frame(3),
+            CellBounds::all().with_lod(9),
+            CellBounds::all().with_morton(10, 20).with_morton(30, 40),
+        ] {
+            assert_eq!(ContainerScan::new(&reader, empty).block_count(), 0);
+        }
+    }
+}
@@ -0,0 +1,259 @@
+//! Versioned layer configuration records
+//!
+//! A layer's parameters (thresholds, voxel size, ...) persist as a
+//! [`LayerConfig`]: the layer type, a schema version and named numeric
+//! fields. Records survive schema drift in both directions:
+//!
+//! - fields written by a newer version are ignored,
+//! - fields an older version did not write keep the layer's defaults,
+//! - [`ConfigUpgrades`] hooks migrate records whose fields changed meaning
+//!   (renames, unit changes) before they are applied.
+//!
+//! Layers produce and accept records through [`Layer::config`] and
+//! [`Layer::apply_config`]; [`ConfigUpgrades::apply`] runs the upgrade
+//! chain and applies the result.
+
+use super::{Layer, LayerType};
+use crate::error::{Error, Result};
+use std::collections::{BTreeMap, HashMap};
+
+const RECORD_VERSION: u8 = 1;
+
+/// Parameters of one layer, as persisted
+///
+/// # Example
+///
+/// ```
+/// use octaindex3d::layers::{ConfigUpgrades, Layer, LayerConfig, OccupancyLayer};
+///
+/// # fn main() -> octaindex3d::Result<()> {
+/// let saved = OccupancyLayer::with_thresholds(0.9, 0.2, 0.99).config().unwrap();
+/// let bytes = saved.to_bytes();
+///
+/// let mut restored = OccupancyLayer::new();
+/// ConfigUpgrades::new().apply(&mut restored, LayerConfig::from_bytes(&bytes)?)?;
+/// assert_eq!(restored.config(), Some(saved));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerConfig {
+    layer_type: LayerType,
+    version: u16,
+    fields: BTreeMap<String, f64>,
+}
+
+impl LayerConfig {
+    /// Create an empty record for `layer_type` at schema `version`
+    pub fn new(layer_type: LayerType, version: u16) -> Self {
+        Self {
+            layer_type,
+            version,
+            fields: BTreeMap::new(),
+        }
+    }
+
+    /// Builder form of [`set`](Self::set)
+    pub fn with(mut self, name: &str, value: f64) -> Self {
+        self.set(name, value);
+        self
+    }
+
+    /// Layer type the record belongs to
+    pub fn layer_type(&self) -> LayerType {
+        self.layer_type
+    }
+
+    /// Schema version the record was written with
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    /// Value of a field
+    pub fn get(&self, name: &str) -> Option<f64> {
+        self.fields.get(name).copied()
+    }
+
+    /// Value of a field, or `default` when the record lacks it
+    pub fn get_or(&self, name: &str, default: f64) -> f64 {
+        self.get(name).unwrap_or(default)
+    }
+
+    /// Set a field
+    pub fn set(&mut self, name: &str, value: f64) {
+        self.fields.insert(name.to_string(), value);
+    }
+
+    /// Remove a field, returning its value
+    pub fn remove(&mut self, name: &str) -> Option<f64> {
+        self.fields.remove(name)
+    }
+
+    /// Move a field to a new name, for upgrade hooks
+    pub fn rename(&mut self, from: &str, to: &str) {
+        if let Some(value) = self.remove(from) {
+            self.set(to, value);
+        }
+    }
+
+    /// Fields in name order
+    pub fn fields(&self) -> impl Iterator<Item = (&str, f64)> + '_ {
+        self.fields
+            .iter()
+            .map(|(name, &value)| (name.as_str(), value))
+    }
+
+    /// Serialize the record
+    ///
+    /// Layout: record format byte, layer tag, schema version (u16), field
+    /// count (u16), then per field a length-prefixed UTF-8 name and an f64,
+    /// all little endian.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = vec![RECORD_VERSION, self.layer_type.tag()];
+        out.extend_from_slice(&self.version.to_le_bytes());
+        out.extend_from_slice(&(self.fields.len() as u16).to_le_bytes());
+        for (name, value) in &self.fields {
+            let name = &name.as_bytes()[..name.len().min(u8::MAX as usize)];
+            out.push(name.len() as u8);
+            out.extend_from_slice(name);
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        out
+    }
+
+    /// Parse a record produced by [`to_bytes`](Self::to_bytes)
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut rest = bytes;
+        let mut take = |n: usize| take_bytes(&mut rest, n);
+
+        let header = take(6)?;
+        if header[0] != RECORD_VERSION {
+            return Err(Error::InvalidFormat(format!(
+                "unsupported layer config record format {}",
+                header[0]
+            )));
+        }
+        let layer_type = LayerType::from_tag(header[1])
+            .ok_or_else(|| Error::InvalidFormat(format!("unknown layer tag {}", header[1])))?;
+        let version = u16::from_le_bytes([header[2], header[3]]);
+        let count = u16::from_le_bytes([header[4], header[5]]);
+
+        let mut config = Self::new(layer_type, version);
+        for _ in 0..count {
+            let len = take(1)?[0] as usize;
+            let name = std::str::from_utf8(take(len)?)
+                .map_err(|_| Error::InvalidFormat("layer config field name".to_string()))?
+                .to_string();
+            let value = f64::from_le_bytes(take(8)?.try_into().unwrap());
+            config.fields.insert(name, value);
+        }
+        Ok(config)
+    }
+}
+
+fn take_bytes<'a>(rest: &mut &'a [u8], n: usize) -> Result<&'a [u8]> {
+    let head = rest
+        .get(..n)
+        .ok_or_else(|| Error::InvalidFormat("truncated layer config record".to_string()))?;
+    *rest = &rest[n..];
+    Ok(head)
+}
+
+/// Migration of a record from one schema version to the next
+pub type UpgradeHook = Box<dyn Fn(&mut LayerConfig) -> Result<()> + Send + Sync>;
+
+/// Upgrade hooks between layer config schema versions
+///
+/// A hook registered for `(layer_type, v)` turns a version `v` record into
+/// a version `v + 1` one. Steps without a hook only bump the version: the
+/// fields that version added are missing, so the layer keeps its defaults.
+#[derive(Default)]
+pub struct ConfigUpgrades {
+    hooks: HashMap<(LayerType, u16), UpgradeHook>,
+}
+
+impl ConfigUpgrades {
+    /// Create a registry with the crate's built-in hooks
+    ///
+    /// There are none yet: every layer is at its first schema version.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register the migration of `layer_type` records from `from_version`
+    /// to `from_version + 1`, replacing any previous hook for that step
+    pub fn register<F>(&mut self, layer_type: LayerType, from_version: u16, hook: F)
+    where
+        F: Fn(&mut LayerConfig) -> Result<()> + Send + Sync + 'static,
+    {
+        self.hooks
+            .insert((layer_type, from_version), Box::new(hook));
+    }
+
+    /// Bring `config` up to schema `target`
+    ///
+    /// Records already at or above `target` are returned unchanged; fields
+    /// a newer writer added are left for the layer to ignore.
+    pub fn upgrade(&self, mut config: LayerConfig, target: u16) -> Result<LayerConfig> {
+        while config.version < target {
+            if let Some(hook) = self.hooks.get(&(config.layer_type, config.version)) {
+                hook(&mut config)?;
+            }
+            config.version += 1;
+        }
+        Ok(config)
+    }
+
+    /// Upgrade `config` to the layer's current schema and apply it
+    pub fn apply(&self, layer: &mut dyn Layer, config: LayerConfig) -> Result<()> {
+        if config.layer_type != layer.layer_type() {
+            return Err(Error::InvalidFormat(format!(
+                "{} config cannot be applied to a {} layer",
+                config.layer_type.name(),
+                layer.layer_type().name()
+            )));
+        }
+        let current = layer.config().ok_or_else(|| {
+            Error::InvalidFormat(format!(
+                "{} layer has no persisted configuration",
+                layer.layer_type().name()
+            ))
+        })?;
+        let config = self.upgrade(config, current.version)?;
+        layer.apply_config(&config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layers::{OccupancyLayer, TSDFLayer};
+
+    #[test]
+    fn test_old_and_new_records_apply() {
+        // A version 0 record under an older field name, plus a field from
+        // some future version
+        let old = LayerConfig::new(LayerType::TSDF, 0)
+            .with("truncation", 0.3)
+            .with("future_knob", 7.0);
+        let bytes = old.to_bytes();
+        let parsed = LayerConfig::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed, old);
+        assert!(LayerConfig::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+
+        let mut upgrades = ConfigUpgrades::new();
+        upgrades.register(LayerType::TSDF, 0, |config| {
+            config.rename("truncation", "truncation_distance");
+            Ok(())
+        });
+        let mut tsdf = TSDFLayer::with_params(0.1, 50.0, 0.05);
+        upgrades.apply(&mut tsdf, parsed).unwrap();
+        assert_eq!(tsdf.truncation_distance(), 0.3);
+        // Fields the old record lacked keep their values
+        assert_eq!(tsdf.voxel_size(), 0.05);
+
+        let mut occupancy = OccupancyLayer::new();
+        let err = upgrades.apply(&mut occupancy, old).unwrap_err();
+        assert!(matches!(err, Error::InvalidFormat(_)));
+    }
+}
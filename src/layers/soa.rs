@@ -0,0 +1,428 @@
+//! Structure-of-arrays voxel storage
+//!
+//! The default layer storage is a hash map from [`Index64`] to a voxel
+//! struct. [`StorageLayout::Soa`] instead groups voxels into blocks of
+//! 8×8×8 cells (the low 9 Morton bits of the index) and keeps each field in
+//! its own contiguous column. Scans over all voxels then decode whole
+//! columns into `f32` arrays that the compiler vectorizes, and updates that
+//! hit the same block share one hash lookup's worth of cache lines.
+//!
+//! Columns hold values in a [`ValueQuantization`] encoding, so a quantized
+//! layer keeps its narrow codes in memory and decodes them on read. With
+//! full precision a block costs about 4 KiB, 8 bytes per cell, against
+//! roughly 40 bytes per hash map entry; `F16` values and weights halve
+//! that. Very sparse maps, with a few voxels per block, are smaller as a
+//! hash map.
+//!
+//! Layers that age their voxels also keep a timestamp column of `f64`
+//! clock times, 4 KiB more per block, which is never quantized.
+
+use super::ValueQuantization;
+use crate::Index64;
+use std::collections::HashMap;
+
+/// Cells per block
+pub(crate) const BLOCK_CELLS: usize = 512;
+
+/// Morton bits addressing a cell inside its block
+const SLOT_MASK: u64 = BLOCK_CELLS as u64 - 1;
+
+/// How a layer stores its voxels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StorageLayout {
+    /// One hash map entry per voxel
+    #[default]
+    Map,
+    /// Blocks of 8×8×8 cells with one column per field
+    Soa,
+}
+
+/// Block of two encoded columns and, if timed, a timestamp column
+#[derive(Debug, Clone)]
+struct SoaBlock {
+    present: [u64; BLOCK_CELLS / 64],
+    values: Box<[u8]>,
+    weights: Box<[u8]>,
+    timestamps: Box<[f64]>,
+}
+
+impl SoaBlock {
+    fn new(values: ValueQuantization, weights: ValueQuantization, timed: bool) -> Self {
+        Self {
+            present: [0; BLOCK_CELLS / 64],
+            values: vec![0; BLOCK_CELLS * values.value_bytes()].into_boxed_slice(),
+            weights: vec![0; BLOCK_CELLS * weights.value_bytes()].into_boxed_slice(),
+            timestamps: vec![0.0; if timed { BLOCK_CELLS } else { 0 }].into_boxed_slice(),
+        }
+    }
+
+    #[inline]
+    fn is_present(&self, slot: usize) -> bool {
+        self.present[slot / 64] & (1 << (slot % 64)) != 0
+    }
+
+    /// Mark `slot` absent and zero its codes
+    fn clear_slot(&mut self, slot: usize, values: ValueQuantization, weights: ValueQuantization) {
+        self.present[slot / 64] &= !(1 << (slot % 64));
+        write(&mut self.values, values, slot, 0.0);
+        write(&mut self.weights, weights, slot, 0.0);
+        if let Some(time) = self.timestamps.get_mut(slot) {
+            *time = 0.0;
+        }
+    }
+
+    /// Timestamp of `slot`, zero without a timestamp column
+    #[inline]
+    fn timestamp(&self, slot: usize) -> f64 {
+        self.timestamps.get(slot).copied().unwrap_or(0.0)
+    }
+
+    fn slots(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..BLOCK_CELLS).filter(|&slot| self.is_present(slot))
+    }
+}
+
+/// Value of `slot` in a column of `encoding` codes
+#[inline]
+fn read(column: &[u8], encoding: ValueQuantization, slot: usize) -> f32 {
+    let width = encoding.value_bytes();
+    let mut code = [0u8; 4];
+    code[..width].copy_from_slice(&column[slot * width..(slot + 1) * width]);
+    encoding.decode(u32::from_le_bytes(code))
+}
+
+/// Store `value` at `slot`, rounded to the column's encoding
+#[inline]
+fn write(column: &mut [u8], encoding: ValueQuantization, slot: usize, value: f32) {
+    let width = encoding.value_bytes();
+    column[slot * width..(slot + 1) * width]
+        .copy_from_slice(&encoding.encode(value).to_le_bytes()[..width]);
+}
+
+/// Every value of a column
+fn decode_column(column: &[u8], encoding: ValueQuantization, out: &mut [f32; BLOCK_CELLS]) {
+    let width = encoding.value_bytes();
+    for (value, code) in out.iter_mut().zip(column.chunks_exact(width)) {
+        let mut bytes = [0u8; 4];
+        bytes[..width].copy_from_slice(code);
+        *value = encoding.decode(u32::from_le_bytes(bytes));
+    }
+}
+
+/// Voxels with a value and a weight column, stored in SoA blocks
+///
+/// Absent cells hold zero codes, which decode to zero in every encoding.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SoaStore {
+    blocks: HashMap<u64, SoaBlock>,
+    len: usize,
+    values: ValueQuantization,
+    weights: ValueQuantization,
+    timed: bool,
+}
+
+/// Raw index of a cell's block and the cell's slot within it
+#[inline]
+pub(crate) fn split(idx: Index64) -> (u64, usize) {
+    (idx.raw() & !SLOT_MASK, (idx.raw() & SLOT_MASK) as usize)
+}
+
+/// Inverse of [`split`]
+#[inline]
+pub(crate) fn join(base: u64, slot: usize) -> Index64 {
+    Index64::from_value(base | slot as u64).expect("block base keeps the Index64 header")
+}
+
+impl SoaStore {
+    /// Empty store encoding its columns as `values` and `weights`
+    pub(crate) fn new(values: ValueQuantization, weights: ValueQuantization) -> Self {
+        Self {
+            values,
+            weights,
+            ..Self::default()
+        }
+    }
+
+    /// Also keep a timestamp per voxel; call before inserting any
+    pub(crate) fn with_timestamps(mut self) -> Self {
+        self.timed = true;
+        self
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(crate) fn get(&self, idx: Index64) -> Option<(f32, f32)> {
+        let (base, slot) = split(idx);
+        let block = self.blocks.get(&base)?;
+        block.is_present(slot).then(|| {
+            (
+                read(&block.values, self.values, slot),
+                read(&block.weights, self.weights, slot),
+            )
+        })
+    }
+
+    /// Value, weight and timestamp of a voxel; the timestamp is zero
+    /// without a timestamp column
+    pub(crate) fn get_timed(&self, idx: Index64) -> Option<(f32, f32, f64)> {
+        let (base, slot) = split(idx);
+        let block = self.blocks.get(&base)?;
+        block.is_present(slot).then(|| {
+            (
+                read(&block.values, self.values, slot),
+                read(&block.weights, self.weights, slot),
+                block.timestamp(slot),
+            )
+        })
+    }
+
+    /// Replace a voxel's value and weight by `f` of the current ones,
+    /// inserting it as zeros first if absent
+    pub(crate) fn update(&mut self, idx: Index64, f: impl FnOnce(f32, f32) -> (f32, f32)) {
+        let (base, slot) = split(idx);
+        let (values, weights, timed) = (self.values, self.weights, self.timed);
+        let block = self
+            .blocks
+            .entry(base)
+            .or_insert_with(|| SoaBlock::new(values, weights, timed));
+        if !block.is_present(slot) {
+            block.present[slot / 64] |= 1 << (slot % 64);
+            self.len += 1;
+        }
+        let (value, weight) = f(
+            read(&block.values, values, slot),
+            read(&block.weights, weights, slot),
+        );
+        write(&mut block.values, values, slot, value);
+        write(&mut block.weights, weights, slot, weight);
+    }
+
+    pub(crate) fn insert(&mut self, idx: Index64, value: f32, weight: f32) {
+        self.update(idx, |_, _| (value, weight));
+    }
+
+    /// Insert or replace a voxel with its timestamp, dropped without a
+    /// timestamp column
+    pub(crate) fn insert_timed(&mut self, idx: Index64, value: f32, weight: f32, time: f64) {
+        self.insert(idx, value, weight);
+        let (base, slot) = split(idx);
+        if let Some(stamp) = self
+            .blocks
+            .get_mut(&base)
+            .and_then(|block| block.timestamps.get_mut(slot))
+        {
+            *stamp = time;
+        }
+    }
+
+    /// Remove a voxel, returning whether it was present
+    pub(crate) fn remove(&mut self, idx: Index64) -> bool {
+        let (base, slot) = split(idx);
+        let Some(block) = self.blocks.get_mut(&base) else {
+            return false;
+        };
+        if !block.is_present(slot) {
+            return false;
+        }
+        block.clear_slot(slot, self.values, self.weights);
+        self.len -= 1;
+        if block.present.iter().all(|&word| word == 0) {
+            self.blocks.remove(&base);
+        }
+        true
+    }
+
+    /// Keep only the voxels for which `keep(value, weight, timestamp)`
+    pub(crate) fn retain(&mut self, mut keep: impl FnMut(f32, f32, f64) -> bool) {
+        let (values, weights) = (self.values, self.weights);
+        let mut removed = 0;
+        self.blocks.retain(|_, block| {
+            for slot in 0..BLOCK_CELLS {
+                if block.is_present(slot)
+                    && !keep(
+                        read(&block.values, values, slot),
+                        read(&block.weights, weights, slot),
+                        block.timestamp(slot),
+                    )
+                {
+                    block.clear_slot(slot, values, weights);
+                    removed += 1;
+                }
+            }
+            block.present.iter().any(|&word| word != 0)
+        });
+        self.len -= removed;
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.blocks.clear();
+        self.len = 0;
+    }
+
+    /// Present voxels as `(index, value, weight)`
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (Index64, f32, f32)> + '_ {
+        self.blocks.iter().flat_map(move |(&base, block)| {
+            block.slots().map(move |slot| {
+                (
+                    join(base, slot),
+                    read(&block.values, self.values, slot),
+                    read(&block.weights, self.weights, slot),
+                )
+            })
+        })
+    }
+
+    /// Present voxels as `(index, value, weight, timestamp)`
+    pub(crate) fn iter_timed(&self) -> impl Iterator<Item = (Index64, f32, f32, f64)> + '_ {
+        self.blocks.iter().flat_map(move |(&base, block)| {
+            block.slots().map(move |slot| {
+                (
+                    join(base, slot),
+                    read(&block.values, self.values, slot),
+                    read(&block.weights, self.weights, slot),
+                    block.timestamp(slot),
+                )
+            })
+        })
+    }
+
+    /// Append the indices of present voxels passing `keep`, which sees
+    /// whole decoded columns so it can be written as a branch-free loop
+    pub(crate) fn select(&self, keep: impl Fn(f32, f32) -> bool, out: &mut Vec<Index64>) {
+        let mut values = [0.0; BLOCK_CELLS];
+        let mut weights = [0.0; BLOCK_CELLS];
+        let mut mask = [false; BLOCK_CELLS];
+        for (&base, block) in &self.blocks {
+            decode_column(&block.values, self.values, &mut values);
+            decode_column(&block.weights, self.weights, &mut weights);
+            for ((m, &v), &w) in mask.iter_mut().zip(&values).zip(&weights) {
+                *m = keep(v, w);
+            }
+            for (slot, &m) in mask.iter().enumerate() {
+                if m && block.is_present(slot) {
+                    out.push(join(base, slot));
+                }
+            }
+        }
+    }
+
+    /// Re-encode both columns, rounding every value to the new encodings
+    pub(crate) fn requantize(&mut self, values: ValueQuantization, weights: ValueQuantization) {
+        for block in self.blocks.values_mut() {
+            let mut next = SoaBlock::new(values, weights, self.timed);
+            next.present = block.present;
+            next.timestamps = std::mem::take(&mut block.timestamps);
+            for slot in block.slots() {
+                write(
+                    &mut next.values,
+                    values,
+                    slot,
+                    read(&block.values, self.values, slot),
+                );
+                write(
+                    &mut next.weights,
+                    weights,
+                    slot,
+                    read(&block.weights, self.weights, slot),
+                );
+            }
+            *block = next;
+        }
+        self.values = values;
+        self.weights = weights;
+    }
+
+    pub(crate) fn memory_usage(&self) -> usize {
+        let timestamps = if self.timed { size_of::<f64>() } else { 0 };
+        let columns =
+            BLOCK_CELLS * (self.values.value_bytes() + self.weights.value_bytes() + timestamps);
+        self.blocks.len() * (std::mem::size_of::<SoaBlock>() + columns + 24)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_soa_store() {
+        let mut store = SoaStore::default();
+        let a = Index64::new(0, 0, 8, 3, 4, 5).unwrap();
+        let b = Index64::new(0, 0, 8, 300, 4, 5).unwrap();
+        store.insert(a, 1.5, 2.0);
+        store.insert(b, -0.5, 1.0);
+        store.update(a, |v, w| (v, w + 1.0));
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.get(a), Some((1.5, 3.0)));
+        assert_eq!(store.get(Index64::new(0, 0, 8, 3, 4, 6).unwrap()), None);
+
+        let mut all: Vec<_> = store.iter().collect();
+        all.sort_by_key(|(idx, _, _)| *idx);
+        assert_eq!(all, vec![(a, 1.5, 3.0), (b, -0.5, 1.0)]);
+
+        let mut negative = Vec::new();
+        store.select(|v, _| v < 0.0, &mut negative);
+        assert_eq!(negative, vec![b]);
+
+        assert!(store.remove(b) && !store.remove(b));
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.get(b), None);
+    }
+
+    #[test]
+    fn test_quantized_columns() {
+        let idx = |x| Index64::new(0, 0, 8, x, 4, 5).unwrap();
+        let mut store = SoaStore::default();
+        for x in 0..64 {
+            store.insert(idx(x), x as f32 * 0.013, 1.0);
+        }
+        let full = store.memory_usage();
+
+        // Values are held as their codes and decoded on read
+        let f16 = ValueQuantization::F16;
+        store.requantize(f16, f16);
+        assert!(store.memory_usage() * 100 / full <= 52);
+        assert_eq!(store.get(idx(7)), Some((f16.quantize(7.0 * 0.013), 1.0)));
+
+        let i8 = ValueQuantization::i8_for_range(1.0).unwrap();
+        store.requantize(i8, f16);
+        store.insert(idx(3), 0.3, 2.0);
+        assert_eq!(store.get(idx(3)), Some((i8.quantize(0.3), 2.0)));
+        assert!(store.memory_usage() < full / 2);
+    }
+
+    #[test]
+    fn test_timestamp_column() {
+        let idx = |x| Index64::new(0, 0, 8, x, 4, 5).unwrap();
+        let mut store = SoaStore::default().with_timestamps();
+        for x in 0..4 {
+            store.insert_timed(idx(x), x as f32, 1.0, 10.0 + x as f64);
+        }
+        store.update(idx(1), |v, w| (v, w + 1.0));
+        assert_eq!(store.get_timed(idx(1)), Some((1.0, 2.0, 11.0)));
+
+        // Timestamps follow their voxels through requantizing
+        store.requantize(ValueQuantization::F16, ValueQuantization::F16);
+        assert_eq!(store.get_timed(idx(3)), Some((3.0, 1.0, 13.0)));
+
+        store.retain(|_, _, time| time >= 12.0);
+        let mut kept: Vec<_> = store.iter_timed().map(|(i, _, _, t)| (i, t)).collect();
+        kept.sort_by_key(|&(i, _)| i);
+        assert_eq!(kept, vec![(idx(2), 12.0), (idx(3), 13.0)]);
+        store.retain(|_, _, _| false);
+        assert_eq!((store.len(), store.memory_usage()), (0, 0));
+
+        // Without the column the time is dropped and reads as zero
+        let mut plain = SoaStore::default();
+        plain.insert_timed(idx(0), 1.0, 1.0, 5.0);
+        assert_eq!(plain.get_timed(idx(0)), Some((1.0, 1.0, 0.0)));
+        let mut timed = SoaStore::default().with_timestamps();
+        timed.insert_timed(idx(0), 1.0, 1.0, 5.0);
+        assert_eq!(
+            timed.memory_usage() - plain.memory_usage(),
+            BLOCK_CELLS * size_of::<f64>()
+        );
+    }
+}
@@ -0,0 +1,104 @@
+//! Compact voxel state encoding shared by layer checkpoints
+//!
+//! Layout: version byte, record count (u64), then per voxel its raw
+//! `Index64` (u64) and two 32-bit words of layer-defined data, all little
+//! endian and sorted by index so equal layers encode identically.
+//!
+//! Layers with quantized values use the packed layout instead: version
+//! byte 2, data width in bytes (1 to 4), record count (u64), then per voxel
+//! its raw `Index64` and `width` bytes of data.
+
+use crate::error::{Error, Result};
+use crate::Index64;
+
+const STATE_VERSION: u8 = 1;
+const PACKED_VERSION: u8 = 2;
+const RECORD_LEN: usize = 16;
+
+/// Encode voxel records
+pub(crate) fn encode_voxels(mut records: Vec<(Index64, [u32; 2])>) -> Vec<u8> {
+    records.sort_unstable_by_key(|(idx, _)| *idx);
+    let mut out = Vec::with_capacity(9 + RECORD_LEN * records.len());
+    out.push(STATE_VERSION);
+    out.extend_from_slice(&(records.len() as u64).to_le_bytes());
+    for (idx, [a, b]) in records {
+        out.extend_from_slice(&idx.raw().to_le_bytes());
+        out.extend_from_slice(&a.to_le_bytes());
+        out.extend_from_slice(&b.to_le_bytes());
+    }
+    out
+}
+
+/// Decode records produced by [`encode_voxels`]
+pub(crate) fn decode_voxels(bytes: &[u8]) -> Result<Vec<(Index64, [u32; 2])>> {
+    if bytes.len() < 9 || bytes[0] != STATE_VERSION {
+        return Err(Error::InvalidFormat(
+            "unsupported layer state encoding".to_string(),
+        ));
+    }
+    let count = u64::from_le_bytes(bytes[1..9].try_into().unwrap());
+    let body = &bytes[9..];
+    if count.checked_mul(RECORD_LEN as u64) != Some(body.len() as u64) {
+        return Err(Error::InvalidFormat(format!(
+            "layer state holds {} bytes for {} voxels",
+            body.len(),
+            count
+        )));
+    }
+    body.chunks_exact(RECORD_LEN)
+        .map(|record| {
+            let word = |at: usize| u32::from_le_bytes(record[at..at + 4].try_into().unwrap());
+            let idx = Index64::from_value(u64::from_le_bytes(record[..8].try_into().unwrap()))?;
+            Ok((idx, [word(8), word(12)]))
+        })
+        .collect()
+}
+
+/// Whether `bytes` use the packed layout
+pub(crate) fn is_packed(bytes: &[u8]) -> bool {
+    bytes.first() == Some(&PACKED_VERSION)
+}
+
+/// Encode voxel records whose data fits in the low `width` bytes
+pub(crate) fn encode_packed(width: usize, mut records: Vec<(Index64, u32)>) -> Vec<u8> {
+    debug_assert!((1..=4).contains(&width));
+    records.sort_unstable_by_key(|(idx, _)| *idx);
+    let mut out = Vec::with_capacity(10 + (8 + width) * records.len());
+    out.push(PACKED_VERSION);
+    out.push(width as u8);
+    out.extend_from_slice(&(records.len() as u64).to_le_bytes());
+    for (idx, data) in records {
+        out.extend_from_slice(&idx.raw().to_le_bytes());
+        out.extend_from_slice(&data.to_le_bytes()[..width]);
+    }
+    out
+}
+
+/// Decode records produced by [`encode_packed`], with their width
+pub(crate) fn decode_packed(bytes: &[u8]) -> Result<(usize, Vec<(Index64, u32)>)> {
+    if bytes.len() < 10 || bytes[0] != PACKED_VERSION || !(1..=4).contains(&bytes[1]) {
+        return Err(Error::InvalidFormat(
+            "unsupported packed layer state encoding".to_string(),
+        ));
+    }
+    let width = bytes[1] as usize;
+    let count = u64::from_le_bytes(bytes[2..10].try_into().unwrap());
+    let body = &bytes[10..];
+    if count.checked_mul(8 + width as u64) != Some(body.len() as u64) {
+        return Err(Error::InvalidFormat(format!(
+            "packed layer state holds {} bytes for {} voxels",
+            body.len(),
+            count
+        )));
+    }
+    let records = body
+        .chunks_exact(8 + width)
+        .map(|record| {
+            let idx = Index64::from_value(u64::from_le_bytes(record[..8].try_into().unwrap()))?;
+            let mut data = [0u8; 4];
+            data[..width].copy_from_slice(&record[8..]);
+            Ok((idx, u32::from_le_bytes(data)))
+        })
+        .collect::<Result<_>>()?;
+    Ok((width, records))
+}
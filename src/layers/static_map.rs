@@ -0,0 +1,467 @@
+//! Immutable, query-optimized snapshot of a [`LayeredMap`]
+//!
+//! [`StaticMap`] freezes every layer's values into packed arrays for
+//! deployments where the map no longer changes. Each layer keeps:
+//!
+//! - a sorted array of block keys, one per 8×8×8 block of cells,
+//! - a 64-byte, cache-line aligned occupancy bitmask per block,
+//! - the values of present cells, densely packed in key order.
+//!
+//! A query binary-searches the block, then finds the cell's value by
+//! counting the mask bits before it, touching two or three cache lines
+//! instead of hashing and probing. The snapshot holds 4 bytes per value
+//! plus 76 bytes per block, against roughly 40 bytes per voxel in a hash
+//! map layer.
+//!
+//! ## Container cell blocks
+//!
+//! With the `container_v2` feature a snapshot is written to and read from
+//! a v2 container, one keyed block per 8×8×8 block of cells holding every
+//! layer's values (see [`StaticMap::write_container`]). The `sqlite`
+//! feature exposes such containers to SQL. A block's key is its first cell
+//! and its payload, little endian:
+//!
+//! - the tag `OCB1`, then a mask with bit `t` set for each layer tag `t`
+//!   present (u8) and the cell count (u16),
+//! - per cell in index order, its slot in the block (u16) and one f32 per
+//!   layer in tag order, NaN where the layer has no value.
+
+use super::soa::{join, split, BLOCK_CELLS};
+use super::{LayerType, LayeredMap};
+#[cfg(feature = "container_v2")]
+use crate::container_v2::{ContainerReaderV2, ContainerWriterV2};
+#[cfg(feature = "container_v2")]
+use crate::error::{Error, Result};
+use crate::Index64;
+#[cfg(feature = "container_v2")]
+use std::collections::BTreeMap;
+
+/// Leading bytes of a container cell block
+#[cfg(feature = "container_v2")]
+const CELL_BLOCK_TAG: &[u8; 4] = b"OCB1";
+
+/// Presence bits of one block's cells
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C, align(64))]
+struct BlockMask([u64; BLOCK_CELLS / 64]);
+
+impl BlockMask {
+    /// Position of `slot` among the block's present cells, if present
+    #[inline]
+    fn rank(&self, slot: usize) -> Option<usize> {
+        let (word, bit) = (slot / 64, slot % 64);
+        if self.0[word] & (1 << bit) == 0 {
+            return None;
+        }
+        let before: u32 = self.0[..word].iter().map(|w| w.count_ones()).sum();
+        let below = (self.0[word] & ((1 << bit) - 1)).count_ones();
+        Some((before + below) as usize)
+    }
+
+    fn slots(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..BLOCK_CELLS).filter(|&slot| self.0[slot / 64] & (1 << (slot % 64)) != 0)
+    }
+}
+
+/// One frozen layer: packed values addressed by sorted block keys
+#[derive(Debug, Clone)]
+pub struct StaticLayer {
+    layer_type: LayerType,
+    /// Raw index of each block's first cell, ascending
+    bases: Vec<u64>,
+    masks: Vec<BlockMask>,
+    /// Index of each block's first value in `values`
+    offsets: Vec<u32>,
+    values: Vec<f32>,
+}
+
+impl StaticLayer {
+    /// Freeze `(index, value)` pairs of one layer
+    ///
+    /// Later pairs for the same index replace earlier ones.
+    pub fn from_values(layer_type: LayerType, mut cells: Vec<(Index64, f32)>) -> Self {
+        cells.sort_by_key(|&(idx, _)| idx.raw());
+        let mut layer = Self {
+            layer_type,
+            bases: Vec::new(),
+            masks: Vec::new(),
+            offsets: Vec::new(),
+            values: Vec::with_capacity(cells.len()),
+        };
+        for (idx, value) in cells {
+            let (base, slot) = split(idx);
+            if layer.bases.last() != Some(&base) {
+                layer.bases.push(base);
+                layer.masks.push(BlockMask([0; BLOCK_CELLS / 64]));
+                layer.offsets.push(layer.values.len() as u32);
+            }
+            let mask = layer.masks.last_mut().expect("block pushed above");
+            if mask.0[slot / 64] & (1 << (slot % 64)) != 0 {
+                *layer.values.last_mut().expect("cell present") = value;
+                continue;
+            }
+            mask.0[slot / 64] |= 1 << (slot % 64);
+            layer.values.push(value);
+        }
+        layer
+    }
+
+    /// Type of the layer this was frozen from
+    pub fn layer_type(&self) -> LayerType {
+        self.layer_type
+    }
+
+    /// Value stored for a cell
+    #[inline]
+    pub fn query(&self, idx: Index64) -> Option<f32> {
+        let (base, slot) = split(idx);
+        let block = self.bases.binary_search(&base).ok()?;
+        let rank = self.masks[block].rank(slot)?;
+        Some(self.values[self.offsets[block] as usize + rank])
+    }
+
+    /// Whether a cell has a value
+    pub fn contains(&self, idx: Index64) -> bool {
+        self.query(idx).is_some()
+    }
+
+    /// Number of stored cells
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Whether no cell is stored
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Stored cells and values in ascending index order
+    pub fn iter(&self) -> impl Iterator<Item = (Index64, f32)> + '_ {
+        self.bases
+            .iter()
+            .zip(&self.masks)
+            .flat_map(|(&base, mask)| mask.slots().map(move |slot| join(base, slot)))
+            .zip(self.values.iter().copied())
+    }
+
+    /// Bytes held by the packed arrays
+    pub fn memory_usage(&self) -> usize {
+        self.bases.len() * (8 + std::mem::size_of::<BlockMask>() + 4) + self.values.len() * 4
+    }
+}
+
+/// Read-only snapshot of a [`LayeredMap`], optimized for query throughput
+///
+/// # Example
+///
+/// ```
+/// use octaindex3d::layers::{LayerType, LayeredMap, OccupancyLayer, StaticMap};
+/// use octaindex3d::Index64;
+///
+/// # fn main() -> octaindex3d::Result<()> {
+/// let wall = Index64::new(0, 0, 8, 10, 10, 10)?;
+/// let mut occupancy = OccupancyLayer::new();
+/// occupancy.update_occupancy(wall, true, 0.9);
+/// let mut map = LayeredMap::new();
+/// map.add_occupancy_layer(occupancy);
+///
+/// let frozen = StaticMap::from(&map);
+/// assert_eq!(frozen.query(LayerType::Occupancy, wall), map.query_occupancy(wall));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct StaticMap {
+    /// Layers sorted by type tag
+    layers: Vec<StaticLayer>,
+}
+
+impl StaticMap {
+    /// A frozen layer
+    pub fn layer(&self, layer_type: LayerType) -> Option<&StaticLayer> {
+        self.layers.iter().find(|l| l.layer_type == layer_type)
+    }
+
+    /// Value of a cell in a layer, as [`Layer::query`](super::Layer::query)
+    /// returned it when the map was frozen
+    #[inline]
+    pub fn query(&self, layer_type: LayerType, idx: Index64) -> Option<f32> {
+        self.layer(layer_type)?.query(idx)
+    }
+
+    /// Frozen layer types
+    pub fn layer_types(&self) -> Vec<LayerType> {
+        self.layers.iter().map(|l| l.layer_type).collect()
+    }
+
+    /// Stored cells across all layers
+    pub fn total_voxels(&self) -> usize {
+        self.layers.iter().map(StaticLayer::len).sum()
+    }
+
+    /// Bytes held across all layers
+    pub fn total_memory_usage(&self) -> usize {
+        self.layers.iter().map(StaticLayer::memory_usage).sum()
+    }
+}
+
+#[cfg(feature = "container_v2")]
+impl StaticMap {
+    /// Write the snapshot as cell blocks, returning the number of blocks
+    ///
+    /// Blocks are written in index order with
+    /// [`ContainerWriterV2::write_block`], so they supersede blocks of the
+    /// same cells written earlier.
+    pub fn write_container<W: std::io::Write + std::io::Seek>(
+        &self,
+        writer: &mut ContainerWriterV2<W>,
+    ) -> Result<usize> {
+        let layers: Vec<LayerType> = self.layers.iter().map(|l| l.layer_type).collect();
+        let mut cells: BTreeMap<Index64, Vec<f32>> = BTreeMap::new();
+        for (i, layer) in self.layers.iter().enumerate() {
+            for (idx, value) in layer.iter() {
+                cells
+                    .entry(idx)
+                    .or_insert_with(|| vec![f32::NAN; self.layers.len()])[i] = value;
+            }
+        }
+
+        let mut blocks = 0;
+        let mut cells = cells.into_iter().peekable();
+        while let Some(&(first, _)) = cells.peek() {
+            let (base, _) = split(first);
+            let mut rows = Vec::new();
+            while let Some(row) = cells.next_if(|(idx, _)| split(*idx).0 == base) {
+                rows.push(row);
+            }
+            let block = CellBlock {
+                layers: layers.clone(),
+                rows,
+            };
+            writer.write_block(join(base, 0), &block.encode())?;
+            blocks += 1;
+        }
+        Ok(blocks)
+    }
+
+    /// Read the cell blocks of a container, skipping other frames and
+    /// superseded blocks
+    pub fn read_container(reader: &ContainerReaderV2) -> Result<Self> {
+        let mut cells: BTreeMap<u8, Vec<(Index64, f32)>> = BTreeMap::new();
+        for (key, frame) in latest_blocks(reader) {
+            let Some(block) = CellBlock::decode(key, &reader.frame_data(frame)?)? else {
+                continue;
+            };
+            for (idx, values) in block.rows {
+                for (&layer_type, value) in block.layers.iter().zip(values) {
+                    if !value.is_nan() {
+                        cells
+                            .entry(layer_type.tag())
+                            .or_default()
+                            .push((idx, value));
+                    }
+                }
+            }
+        }
+        let layers = cells
+            .into_iter()
+            .map(|(tag, cells)| {
+                let layer_type = LayerType::from_tag(tag).expect("decoded layer tag");
+                StaticLayer::from_values(layer_type, cells)
+            })
+            .collect();
+        Ok(Self { layers })
+    }
+}
+
+/// Latest frame of each block key, in key order
+#[cfg(feature = "container_v2")]
+pub(crate) fn latest_blocks(reader: &ContainerReaderV2) -> Vec<(Index64, usize)> {
+    let latest: BTreeMap<Index64, usize> = (0..reader.frame_count())
+        .filter_map(|i| Some((reader.frame_key(i)?, i)))
+        .collect();
+    latest.into_iter().collect()
+}
+
+/// Decoded container cell block
+#[cfg(feature = "container_v2")]
+pub(crate) struct CellBlock {
+    /// Layers of the value columns, in tag order
+    pub(crate) layers: Vec<LayerType>,
+    /// Cells in index order with one value per layer, NaN where absent
+    pub(crate) rows: Vec<(Index64, Vec<f32>)>,
+}
+
+#[cfg(feature = "container_v2")]
+impl CellBlock {
+    /// Payload of the block; layers must be in tag order and rows in index
+    /// order, all in one block
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        let mask = self.layers.iter().fold(0u8, |m, l| m | 1 << l.tag());
+        let mut payload = Vec::with_capacity(7 + self.rows.len() * (2 + 4 * self.layers.len()));
+        payload.extend_from_slice(CELL_BLOCK_TAG);
+        payload.push(mask);
+        payload.extend_from_slice(&(self.rows.len() as u16).to_le_bytes());
+        for (idx, values) in &self.rows {
+            payload.extend_from_slice(&(split(*idx).1 as u16).to_le_bytes());
+            for value in values {
+                payload.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        payload
+    }
+
+    /// Decode the payload of the block keyed `key`; `None` if it is not a
+    /// cell block
+    pub(crate) fn decode(key: Index64, payload: &[u8]) -> Result<Option<Self>> {
+        let Some(body) = payload.strip_prefix(CELL_BLOCK_TAG.as_slice()) else {
+            return Ok(None);
+        };
+        let invalid = || Error::InvalidFormat(format!("truncated cell block {:?}", key));
+        let (&mask, body) = body.split_first().ok_or_else(invalid)?;
+        let count = u16::from_le_bytes(body.get(..2).ok_or_else(invalid)?.try_into().unwrap());
+        let layers: Vec<LayerType> = (0..8)
+            .filter(|t| mask & (1 << t) != 0)
+            .map(|t| {
+                LayerType::from_tag(t)
+                    .ok_or_else(|| Error::InvalidFormat(format!("unknown layer tag {}", t)))
+            })
+            .collect::<Result<_>>()?;
+        let record = 2 + 4 * layers.len();
+        let body = &body[2..];
+        if body.len() != count as usize * record {
+            return Err(invalid());
+        }
+        let (base, _) = split(key);
+        let rows = body
+            .chunks_exact(record)
+            .map(|r| {
+                let slot = u16::from_le_bytes([r[0], r[1]]) as usize % BLOCK_CELLS;
+                let values = r[2..]
+                    .chunks_exact(4)
+                    .map(|v| f32::from_le_bytes(v.try_into().unwrap()))
+                    .collect();
+                (join(base, slot), values)
+            })
+            .collect();
+        Ok(Some(Self { layers, rows }))
+    }
+}
+
+/// Freeze every layer, keeping the cells whose query returns a value
+impl From<&LayeredMap> for StaticMap {
+    fn from(map: &LayeredMap) -> Self {
+        let mut layer_types = map.layer_types();
+        layer_types.sort_by_key(LayerType::tag);
+        let layers = layer_types
+            .into_iter()
+            .filter_map(|layer_type| {
+                let layer = map.layer(layer_type)?;
+                let cells = layer
+                    .indices()
+                    .filter_map(|idx| Some((idx, layer.query(idx)?)))
+                    .collect();
+                Some(StaticLayer::from_values(layer_type, cells))
+            })
+            .collect();
+        Self { layers }
+    }
+}
+
+impl From<LayeredMap> for StaticMap {
+    fn from(map: LayeredMap) -> Self {
+        Self::from(&map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layers::{OccupancyLayer, TSDFLayer};
+
+    #[test]
+    fn test_static_map_matches_source() {
+        let mut map = LayeredMap::new();
+        let mut occupancy = OccupancyLayer::new();
+        let mut tsdf = TSDFLayer::new(0.1);
+        let mut cells = Vec::new();
+        for i in 0..2000u16 {
+            let idx = Index64::new(0, 0, 9, i % 16, i / 16 % 16, i / 256).unwrap();
+            occupancy.update_occupancy(idx, i % 3 == 0, 0.8);
+            cells.push(idx);
+        }
+        let updates: Vec<_> = cells
+            .iter()
+            .enumerate()
+            .map(|(i, &idx)| (idx, i as f32 * 4e-5, 1.0))
+            .collect();
+        tsdf.batch_update(&updates).unwrap();
+        map.add_occupancy_layer(occupancy);
+        map.add_tsdf_layer(tsdf);
+
+        let frozen = StaticMap::from(&map);
+        assert_eq!(
+            frozen.layer_types(),
+            vec![LayerType::TSDF, LayerType::Occupancy]
+        );
+        assert_eq!(frozen.total_voxels(), map.total_voxels());
+        assert!(frozen.total_memory_usage() < map.total_memory_usage());
+        for &idx in &cells {
+            assert_eq!(
+                frozen.query(LayerType::Occupancy, idx),
+                map.query_occupancy(idx)
+            );
+            assert_eq!(frozen.query(LayerType::TSDF, idx), map.query_tsdf(idx));
+        }
+        let absent = Index64::new(0, 0, 9, 100, 2, 3).unwrap();
+        assert_eq!(frozen.query(LayerType::TSDF, absent), None);
+        assert_eq!(frozen.query(LayerType::ESDF, cells[0]), None);
+
+        // Iteration is in index order and covers every cell
+        let tsdf = frozen.layer(LayerType::TSDF).unwrap();
+        let listed: Vec<_> = tsdf.iter().map(|(idx, _)| idx).collect();
+        let mut expected = cells.clone();
+        expected.sort();
+        expected.dedup();
+        assert_eq!(listed, expected);
+        assert!(tsdf.iter().all(|(idx, v)| map.query_tsdf(idx) == Some(v)));
+    }
+
+    #[cfg(feature = "container_v2")]
+    #[test]
+    fn test_container_round_trip() {
+        use crate::container_v2::StreamConfig;
+
+        let mut map = LayeredMap::new();
+        let mut occupancy = OccupancyLayer::new();
+        let mut tsdf = TSDFLayer::new(0.1);
+        for x in 0..40u16 {
+            let idx = Index64::new(0, 0, 9, x, 3, 3).unwrap();
+            occupancy.update_occupancy(idx, x % 2 == 0, 0.8);
+            if x < 10 {
+                tsdf.batch_update(&[(idx, 0.01 * x as f32, 1.0)]).unwrap();
+            }
+        }
+        map.add_occupancy_layer(occupancy);
+        map.add_tsdf_layer(tsdf);
+        let frozen = StaticMap::from(&map);
+
+        let mut bytes = std::io::Cursor::new(Vec::new());
+        let mut writer = ContainerWriterV2::new(&mut bytes, StreamConfig::default()).unwrap();
+        writer.write_frame(b"not cells").unwrap();
+        // Superseded by the snapshot's own block of the same cells
+        let stale = Index64::new(0, 0, 9, 0, 0, 0).unwrap();
+        writer.write_block(stale, b"OCB1\x04\x00\x00").unwrap();
+        assert_eq!(frozen.write_container(&mut writer).unwrap(), 5);
+        writer.finish().unwrap();
+
+        let reader = ContainerReaderV2::from_bytes(bytes.into_inner()).unwrap();
+        let restored = StaticMap::read_container(&reader).unwrap();
+        assert_eq!(restored.layer_types(), frozen.layer_types());
+        for layer_type in frozen.layer_types() {
+            let a: Vec<_> = frozen.layer(layer_type).unwrap().iter().collect();
+            let b: Vec<_> = restored.layer(layer_type).unwrap().iter().collect();
+            assert_eq!(a, b);
+        }
+    }
+}
@@ -0,0 +1,392 @@
+//! Submaps anchored to poses, stitched into a global map
+//!
+//! SLAM pipelines map in pieces: each [`Submap`] is a [`LayeredMap`] built
+//! in its own frame, whose [`FrameDescriptor`] places it in a global frame.
+//! A [`SubmapCollection`] renders every submap through its pose into one
+//! global map. When a loop closure refines the poses, update them with
+//! [`SubmapCollection::set_pose`] and stitch again; the submaps themselves
+//! are left untouched, so nothing has to be re-integrated from raw data.
+//!
+//! A pose maps the submap's frame units to the global frame's, as any
+//! [`FrameTransform`] does: a cell at LOD `l` spans
+//! [`cell_extent(l)`](crate::frame::cell_extent) units. Cells keep their
+//! tier and LOD, and land on the BCC lattice point nearest their
+//! transformed position, so rotated submaps are resampled by nearest
+//! neighbour. Cells landing on the same point, from one submap or several,
+//! are fused with [`Layer::fuse_state`]: occupancy evidence adds up and
+//! TSDF distances are averaged by weight. Derived layers such as ESDF
+//! should be recomputed from the stitched map.
+
+use super::state::{decode_packed, decode_voxels, encode_packed, encode_voxels, is_packed};
+use super::{Layer, LayerType, LayeredMap};
+use crate::error::{Error, Result};
+use crate::frame::{move_index, FrameDescriptor, FrameTransform};
+use crate::ids::FrameId;
+use crate::Index64;
+use std::collections::{BTreeMap, HashSet};
+
+/// Cells written by stitching
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StitchStats {
+    /// Submaps rendered
+    pub submaps: usize,
+    /// Cells written into the global map
+    pub cells_rendered: usize,
+    /// Cells that landed on a cell already holding data, and were fused
+    /// with it
+    pub cells_merged: usize,
+    /// Cells transformed off the coordinate range, and dropped
+    pub cells_dropped: usize,
+    /// Submap layers left out because they cannot export their state
+    pub layers_skipped: Vec<LayerType>,
+}
+
+/// Map fragment built in its own frame, placed by a pose in a parent frame
+pub struct Submap {
+    frame: FrameDescriptor,
+    map: LayeredMap,
+}
+
+impl Submap {
+    /// Submap of `map`, placed by the parent pose of `frame`
+    ///
+    /// Fails with [`Error::UnanchoredSubmap`] if `frame` has no parent.
+    pub fn new(frame: FrameDescriptor, map: LayeredMap) -> Result<Self> {
+        if frame.parent.is_none() {
+            return Err(Error::UnanchoredSubmap(frame.name));
+        }
+        Ok(Self { frame, map })
+    }
+
+    /// Frame of the submap, with its pose in the global frame
+    pub fn frame(&self) -> &FrameDescriptor {
+        &self.frame
+    }
+
+    /// Global frame the submap is placed in
+    pub fn parent(&self) -> FrameId {
+        self.anchor().0
+    }
+
+    /// Transform from the submap's lattice coordinates to the global frame's
+    pub fn pose(&self) -> FrameTransform {
+        self.anchor().1
+    }
+
+    /// Replace the pose, keeping the parent frame
+    pub fn set_pose(&mut self, pose: FrameTransform) {
+        self.frame.parent = Some((self.parent(), pose));
+    }
+
+    /// Map in the submap's own frame
+    pub fn map(&self) -> &LayeredMap {
+        &self.map
+    }
+
+    /// Mutable map, for integrating further data
+    pub fn map_mut(&mut self) -> &mut LayeredMap {
+        &mut self.map
+    }
+
+    /// Write every layer's cells, moved by the pose, into `global`
+    ///
+    /// Layers `global` lacks are added as the crate's standard layer of
+    /// their type with the submap layer's configuration. Cells already in
+    /// `global` are fused with the submap's.
+    pub fn render_into(&self, global: &mut LayeredMap) -> Result<StitchStats> {
+        let mut stats = StitchStats::default();
+        self.render(global, &mut stats)?;
+        Ok(stats)
+    }
+
+    fn anchor(&self) -> (FrameId, FrameTransform) {
+        self.frame.parent.expect("submaps are anchored")
+    }
+
+    fn render(&self, global: &mut LayeredMap, stats: &mut StitchStats) -> Result<()> {
+        let (parent, pose) = self.anchor();
+        let mut types = self.map.layer_types();
+        types.sort_by_key(LayerType::tag);
+        for layer_type in types {
+            let layer = self.map.layer(layer_type).expect("listed layer");
+            let Some(state) = layer.encode_state() else {
+                stats.layers_skipped.push(layer_type);
+                continue;
+            };
+            if !global.has_layer(layer_type) {
+                global.add_standard_layer(layer_type)?;
+                if let Some(config) = layer.config() {
+                    let target = global.layer_mut(layer_type).expect("layer was added");
+                    target.apply_config(&config)?;
+                }
+            }
+            let target = global.layer_mut(layer_type).expect("layer exists");
+            let state = if is_packed(&state) {
+                let (width, records) = decode_packed(&state)?;
+                encode_packed(width, place(records, parent, &pose, target, stats))
+            } else {
+                let records = decode_voxels(&state)?;
+                encode_voxels(place(records, parent, &pose, target, stats))
+            };
+            target.fuse_state(&state)?;
+        }
+        stats.submaps += 1;
+        Ok(())
+    }
+}
+
+/// Submaps by id, stitched into a global map
+///
+/// # Example
+///
+/// ```
+/// use octaindex3d::frame::{self, FrameDescriptor, FrameTransform};
+/// use octaindex3d::layers::{LayeredMap, Measurement, OccupancyLayer, Submap, SubmapCollection};
+/// use octaindex3d::Index64;
+///
+/// # fn main() -> octaindex3d::Result<()> {
+/// // A submap seeing a wall 10 cells ahead, placed 100 cells along x
+/// let cell = frame::cell_extent(8)?;
+/// let mut local = LayeredMap::new();
+/// local.add_occupancy_layer(OccupancyLayer::new());
+/// local.update_occupancy(Index64::new(0, 0, 8, 10, 0, 0)?, &Measurement::occupied(0.9))?;
+/// let odometry = FrameTransform::IDENTITY.with_translation([100.0 * cell, 0.0, 0.0]);
+/// let frame = FrameDescriptor::new("submap 7", "local", "", true, 1.0).with_parent(0, odometry);
+///
+/// let mut submaps = SubmapCollection::new();
+/// submaps.insert(7, Submap::new(frame, local)?);
+/// let mut global = LayeredMap::new();
+/// submaps.stitch(&mut global)?;
+/// assert!(global.query_occupancy(Index64::new(0, 0, 8, 110, 0, 0)?).unwrap() > 0.5);
+///
+/// // Loop closure: the submap was 4 cells further along
+/// submaps.set_pose(7, odometry.with_translation([104.0 * cell, 0.0, 0.0]))?;
+/// submaps.stitch(&mut global)?;
+/// assert!(global.query_occupancy(Index64::new(0, 0, 8, 110, 0, 0)?).is_none());
+/// assert!(global.query_occupancy(Index64::new(0, 0, 8, 114, 0, 0)?).unwrap() > 0.5);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+pub struct SubmapCollection {
+    submaps: BTreeMap<u32, Submap>,
+}
+
+impl SubmapCollection {
+    /// Create an empty collection
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a submap, returning the one it replaces
+    pub fn insert(&mut self, id: u32, submap: Submap) -> Option<Submap> {
+        self.submaps.insert(id, submap)
+    }
+
+    /// Remove a submap
+    pub fn remove(&mut self, id: u32) -> Option<Submap> {
+        self.submaps.remove(&id)
+    }
+
+    /// Submap by id
+    pub fn get(&self, id: u32) -> Option<&Submap> {
+        self.submaps.get(&id)
+    }
+
+    /// Mutable submap by id
+    pub fn get_mut(&mut self, id: u32) -> Option<&mut Submap> {
+        self.submaps.get_mut(&id)
+    }
+
+    /// Number of submaps
+    pub fn len(&self) -> usize {
+        self.submaps.len()
+    }
+
+    /// Whether the collection has no submaps
+    pub fn is_empty(&self) -> bool {
+        self.submaps.is_empty()
+    }
+
+    /// Submaps in id order
+    pub fn iter(&self) -> impl Iterator<Item = (u32, &Submap)> {
+        self.submaps.iter().map(|(&id, submap)| (id, submap))
+    }
+
+    /// Replace the pose of a submap, such as after a loop closure
+    pub fn set_pose(&mut self, id: u32, pose: FrameTransform) -> Result<()> {
+        self.submaps
+            .get_mut(&id)
+            .ok_or(Error::UnknownSubmap(id))?
+            .set_pose(pose);
+        Ok(())
+    }
+
+    /// Render every submap into `global` at its current pose
+    ///
+    /// The layers of `global` that any submap has are cleared first, then
+    /// submaps are rendered in id order and fused where they overlap.
+    /// Other layers are left as they are.
+    pub fn stitch(&self, global: &mut LayeredMap) -> Result<StitchStats> {
+        for submap in self.submaps.values() {
+            for layer_type in submap.map.layer_types() {
+                if let Some(layer) = global.layer_mut(layer_type) {
+                    layer.clear();
+                }
+            }
+        }
+        let mut stats = StitchStats::default();
+        for submap in self.submaps.values() {
+            submap.render(global, &mut stats)?;
+        }
+        stats.layers_skipped.sort_by_key(LayerType::tag);
+        stats.layers_skipped.dedup();
+        Ok(stats)
+    }
+}
+
+/// Records moved into `parent` by `pose`, keeping records that land on
+/// the same cell for the layer to fuse
+fn place<T>(
+    records: Vec<(Index64, T)>,
+    parent: FrameId,
+    pose: &FrameTransform,
+    target: &dyn Layer,
+    stats: &mut StitchStats,
+) -> Vec<(Index64, T)> {
+    let mut placed = HashSet::new();
+    let mut out = Vec::with_capacity(records.len());
+    for (idx, data) in records {
+        let Ok(to) = move_index(idx, parent, pose) else {
+            stats.cells_dropped += 1;
+            continue;
+        };
+        stats.cells_rendered += 1;
+        if !placed.insert(to) || target.contains(to) {
+            stats.cells_merged += 1;
+        }
+        out.push((to, data));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layers::{Measurement, OccupancyLayer, TSDFLayer};
+
+    /// Frame units per cell at LOD 8
+    const CELL: f64 = 128.0;
+
+    fn submap(parent: FrameId, pose: FrameTransform, cells: &[(u16, u16, u16)]) -> Submap {
+        let mut map = LayeredMap::new();
+        map.add_occupancy_layer(OccupancyLayer::new());
+        map.add_tsdf_layer(TSDFLayer::new(0.3));
+        for &(x, y, z) in cells {
+            let idx = Index64::new(5, 0, 8, x, y, z).unwrap();
+            map.update_occupancy(idx, &Measurement::occupied(0.9))
+                .unwrap();
+            map.update_tsdf(idx, &Measurement::depth(0.05, 1.0))
+                .unwrap();
+        }
+        let frame =
+            FrameDescriptor::new("submap", "local", "", true, 1.0).with_parent(parent, pose);
+        Submap::new(frame, map).unwrap()
+    }
+
+    fn occupied(map: &LayeredMap) -> Vec<(u8, (u16, u16, u16))> {
+        let layer = map.layer(LayerType::Occupancy).unwrap();
+        let mut cells: Vec<_> = layer
+            .indices()
+            .map(|idx| (idx.frame_id(), idx.decode_coords()))
+            .collect();
+        cells.sort();
+        cells
+    }
+
+    #[test]
+    fn test_restitch_after_pose_refinement() -> Result<()> {
+        let quarter = FrameTransform::from_axis_angle([0.0, 0.0, 1.0], std::f64::consts::FRAC_PI_2);
+        let mut submaps = SubmapCollection::new();
+        submaps.insert(
+            1,
+            submap(2, FrameTransform::IDENTITY, &[(10, 20, 30), (12, 20, 30)]),
+        );
+        // Turned a quarter about z, 1000 cells along x
+        submaps.insert(
+            2,
+            submap(
+                2,
+                quarter.with_translation([1000.0 * CELL, 0.0, 0.0]),
+                &[(10, 20, 30), (11, 21, 31)],
+            ),
+        );
+
+        let mut global = LayeredMap::new();
+        let stats = submaps.stitch(&mut global)?;
+        assert_eq!((stats.submaps, stats.cells_rendered), (2, 8));
+        assert_eq!((stats.cells_merged, stats.cells_dropped), (0, 0));
+        assert_eq!(
+            occupied(&global),
+            vec![
+                (2, (10, 20, 30)),
+                (2, (12, 20, 30)),
+                (2, (979, 11, 31)),
+                (2, (980, 10, 30)),
+            ]
+        );
+        let tsdf = global.get_layer::<TSDFLayer>().unwrap();
+        assert_eq!(tsdf.truncation_distance(), 0.3);
+
+        // Refining the second pose onto the first submap overlaps one cell,
+        // and pushing it off the lattice drops both
+        submaps.set_pose(2, FrameTransform::IDENTITY)?;
+        let stats = submaps.stitch(&mut global)?;
+        assert_eq!(stats.cells_merged, 2);
+        assert_eq!(occupied(&global).len(), 3);
+        // Both submaps' evidence is kept where they overlap
+        let overlap = Index64::new(2, 0, 8, 10, 20, 30)?;
+        assert!(global.query_occupancy(overlap).unwrap() > 0.95);
+        let tsdf = global.get_layer::<TSDFLayer>().unwrap();
+        assert_eq!(tsdf.get_weight(overlap), Some(2.0));
+        submaps.set_pose(
+            2,
+            FrameTransform::IDENTITY.with_translation([-50.0 * CELL, 0.0, 0.0]),
+        )?;
+        let stats = submaps.stitch(&mut global)?;
+        assert_eq!(stats.cells_dropped, 4);
+        assert_eq!(occupied(&global).len(), 2);
+
+        assert_eq!(
+            submaps.set_pose(3, FrameTransform::IDENTITY),
+            Err(Error::UnknownSubmap(3))
+        );
+        let unanchored = FrameDescriptor::new("loose", "local", "", true, 1.0);
+        assert!(Submap::new(unanchored, LayeredMap::new()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cells_of_one_submap_landing_together_are_fused() -> Result<()> {
+        // Halving the scale sends both cells to (10, 10, 10)
+        let mut submaps = SubmapCollection::new();
+        submaps.insert(
+            1,
+            submap(
+                3,
+                FrameTransform::IDENTITY.with_scale(0.5),
+                &[(20, 20, 20), (21, 21, 21)],
+            ),
+        );
+        let mut global = LayeredMap::new();
+        let stats = submaps.stitch(&mut global)?;
+        assert_eq!((stats.cells_rendered, stats.cells_merged), (4, 2));
+        assert_eq!(occupied(&global), vec![(3, (10, 10, 10))]);
+        let tsdf = global.get_layer::<TSDFLayer>().unwrap();
+        assert_eq!(
+            tsdf.get_weight(Index64::new(3, 0, 8, 10, 10, 10)?),
+            Some(2.0)
+        );
+        Ok(())
+    }
+}
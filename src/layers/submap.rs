@@ -0,0 +1,340 @@
+//! Submap descriptors for place recognition
+//!
+//! Submaps themselves, anchored to poses and stitched into a global map,
+//! are in [`stitch`](super::stitch).
+//!
+//! A [`SubmapDescriptor`] summarizes the cells around a point as histograms
+//! over concentric lattice shells: for each shell, the share of its cells
+//! that are occupied, free, and inside or outside a TSDF surface. Shells
+//! depend only on distance from the centre, so the descriptor does not
+//! change when the place is revisited facing another way, and a
+//! [`DescriptorMatcher`] can propose loop closures by comparing them.
+
+use super::{LayerType, LayeredMap};
+use crate::Index64;
+
+/// Values per shell: occupied, free, inside a surface, outside a surface
+const FEATURES_PER_SHELL: usize = 4;
+
+/// Ball of cells around a centre cell of a map
+#[derive(Clone, Copy)]
+pub struct MapRegion<'a> {
+    /// Map to describe
+    pub map: &'a LayeredMap,
+    /// Centre cell; the region holds cells of its frame, tier and LOD
+    pub center: Index64,
+    /// Radius in lattice units at the centre's LOD
+    pub radius: u16,
+    /// Number of equal-width shells the radius is divided into
+    pub shells: usize,
+}
+
+impl<'a> MapRegion<'a> {
+    /// Region of `radius` lattice units around `center`, in 8 shells
+    pub fn new(map: &'a LayeredMap, center: Index64, radius: u16) -> Self {
+        Self {
+            map,
+            center,
+            radius,
+            shells: 8,
+        }
+    }
+
+    /// Builder form setting the number of shells
+    pub fn with_shells(mut self, shells: usize) -> Self {
+        self.shells = shells.max(1);
+        self
+    }
+}
+
+/// Rotation-invariant summary of a map region
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubmapDescriptor {
+    /// Centre cell of the region described
+    pub center: Index64,
+    /// Number of shells
+    pub shells: usize,
+    /// Per shell, the shares of its lattice cells that are occupied, free,
+    /// inside a surface and outside one
+    pub features: Vec<f32>,
+}
+
+impl SubmapDescriptor {
+    /// Mean absolute difference of the features, from 0 for identical
+    /// regions to 1; infinite for descriptors with different shell counts
+    pub fn distance(&self, other: &SubmapDescriptor) -> f32 {
+        if self.shells != other.shells || self.features.is_empty() {
+            return f32::INFINITY;
+        }
+        let sum: f32 = self
+            .features
+            .iter()
+            .zip(&other.features)
+            .map(|(a, b)| (a - b).abs())
+            .sum();
+        sum / self.features.len() as f32
+    }
+
+    /// Share of the region's cells holding any data, for skipping regions
+    /// too empty to match reliably
+    pub fn coverage(&self) -> f32 {
+        let observed: f32 = self
+            .features
+            .chunks_exact(FEATURES_PER_SHELL)
+            .map(|shell| (shell[0] + shell[1]).max(shell[2] + shell[3]))
+            .sum();
+        observed / self.shells as f32
+    }
+}
+
+/// Describe the cells of a region with shell histograms
+///
+/// Occupancy cells count as occupied above probability 0.5 and free below;
+/// TSDF cells as inside a surface when negative. Shells are weighted by
+/// the lattice cells they contain, so a sparse outer shell counts as much
+/// as a dense inner one.
+///
+/// # Example
+///
+/// ```
+/// use octaindex3d::layers::submap::{descriptor, MapRegion};
+/// use octaindex3d::layers::{LayeredMap, Measurement, OccupancyLayer};
+/// use octaindex3d::Index64;
+///
+/// # fn main() -> octaindex3d::Result<()> {
+/// let mut map = LayeredMap::new();
+/// map.add_occupancy_layer(OccupancyLayer::new());
+/// // An L-shaped corner, and the same corner rotated a quarter turn
+/// for i in 0..10u16 {
+///     for (x, y) in [(100 + 2 * i, 100), (100, 100 + 2 * i)] {
+///         map.update_occupancy(Index64::new(0, 0, 8, x, y, 100)?, &Measurement::occupied(0.9))?;
+///     }
+///     for (x, y) in [(300 - 2 * i, 100), (300, 100 + 2 * i)] {
+///         map.update_occupancy(Index64::new(0, 0, 8, x, y, 100)?, &Measurement::occupied(0.9))?;
+///     }
+/// }
+/// let a = descriptor(&MapRegion::new(&map, Index64::new(0, 0, 8, 100, 100, 100)?, 24));
+/// let b = descriptor(&MapRegion::new(&map, Index64::new(0, 0, 8, 300, 100, 100)?, 24));
+/// assert_eq!(a.distance(&b), 0.0);
+/// # Ok(())
+/// # }
+/// ```
+pub fn descriptor(region: &MapRegion<'_>) -> SubmapDescriptor {
+    let shells = region.shells.max(1);
+    let mut counts = vec![[0u32; FEATURES_PER_SHELL]; shells];
+    let mut totals = vec![0u32; shells];
+    let occupancy = region.map.layer(LayerType::Occupancy);
+    let tsdf = region.map.layer(LayerType::TSDF);
+
+    let center = region.center;
+    let (cx, cy, cz) = center.decode_coords();
+    let r = region.radius as i32;
+    let r2 = (r * r) as i64;
+    // Offsets keeping all three coordinates' parity stay on the BCC lattice
+    for dz in -r..=r {
+        for dy in -r..=r {
+            for dx in -r..=r {
+                if (dx ^ dy) & 1 != 0 || (dy ^ dz) & 1 != 0 {
+                    continue;
+                }
+                let d2 = (dx * dx + dy * dy + dz * dz) as i64;
+                if d2 > r2 {
+                    continue;
+                }
+                let shell = if r == 0 {
+                    0
+                } else {
+                    (((d2 as f64).sqrt() / r as f64 * shells as f64) as usize).min(shells - 1)
+                };
+                totals[shell] += 1;
+                let (Ok(x), Ok(y), Ok(z)) = (
+                    u16::try_from(cx as i32 + dx),
+                    u16::try_from(cy as i32 + dy),
+                    u16::try_from(cz as i32 + dz),
+                ) else {
+                    continue;
+                };
+                let Ok(idx) = Index64::new(
+                    center.frame_id(),
+                    center.scale_tier(),
+                    center.lod(),
+                    x,
+                    y,
+                    z,
+                ) else {
+                    continue;
+                };
+                if let Some(p) = occupancy.and_then(|layer| layer.query(idx)) {
+                    if p > 0.5 {
+                        counts[shell][0] += 1;
+                    } else if p < 0.5 {
+                        counts[shell][1] += 1;
+                    }
+                }
+                if let Some(d) = tsdf.and_then(|layer| layer.query(idx)) {
+                    counts[shell][if d < 0.0 { 2 } else { 3 }] += 1;
+                }
+            }
+        }
+    }
+
+    let features = counts
+        .iter()
+        .zip(&totals)
+        .flat_map(|(shell, &total)| {
+            shell.map(|count| {
+                if total == 0 {
+                    0.0
+                } else {
+                    count as f32 / total as f32
+                }
+            })
+        })
+        .collect();
+    SubmapDescriptor {
+        center,
+        shells,
+        features,
+    }
+}
+
+/// Store of descriptors of visited places, queried for loop closures
+#[derive(Debug, Clone)]
+pub struct DescriptorMatcher<K> {
+    entries: Vec<(K, SubmapDescriptor)>,
+}
+
+impl<K> Default for DescriptorMatcher<K> {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl<K: Clone> DescriptorMatcher<K> {
+    /// Create an empty matcher
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remember the descriptor of place `key`
+    pub fn insert(&mut self, key: K, descriptor: SubmapDescriptor) {
+        self.entries.push((key, descriptor));
+    }
+
+    /// Number of descriptors stored
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether no descriptor is stored
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Up to `k` stored places closest to `query` within `max_distance`,
+    /// nearest first
+    pub fn best_matches(
+        &self,
+        query: &SubmapDescriptor,
+        k: usize,
+        max_distance: f32,
+    ) -> Vec<(K, f32)> {
+        let mut matches: Vec<(K, f32)> = self
+            .entries
+            .iter()
+            .map(|(key, descriptor)| (key.clone(), query.distance(descriptor)))
+            .filter(|(_, distance)| *distance <= max_distance)
+            .collect();
+        matches.sort_by(|a, b| a.1.total_cmp(&b.1));
+        matches.truncate(k);
+        matches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layers::{Measurement, OccupancyLayer, TSDFLayer};
+
+    /// A room with two walls and a pillar, rotated by `turn` quarter turns
+    /// about z and placed at `origin`
+    fn room(map: &mut LayeredMap, origin: (u16, u16, u16), turn: usize) {
+        let rotate = |x: i32, y: i32| match turn % 4 {
+            0 => (x, y),
+            1 => (-y, x),
+            2 => (-x, -y),
+            _ => (y, -x),
+        };
+        let mut put = |x: i32, y: i32, z: i32, occupied: bool| {
+            let (x, y) = rotate(x, y);
+            let idx = Index64::new(
+                0,
+                0,
+                8,
+                (origin.0 as i32 + x) as u16,
+                (origin.1 as i32 + y) as u16,
+                (origin.2 as i32 + z) as u16,
+            )
+            .unwrap();
+            let measurement = if occupied {
+                Measurement::occupied(0.9)
+            } else {
+                Measurement::free(0.9)
+            };
+            map.update_occupancy(idx, &measurement).unwrap();
+            map.update_tsdf(
+                idx,
+                &Measurement::depth(if occupied { -0.05 } else { 0.2 }, 1.0),
+            )
+            .unwrap();
+        };
+        for i in (-12..=12).step_by(2) {
+            for z in [0, 2, 4] {
+                put(i, 12, z, true);
+                put(12, i, z, true);
+                put(i, 0, z, false);
+            }
+        }
+        for z in [0, 2, 4] {
+            put(-6, -6, z, true);
+        }
+    }
+
+    #[test]
+    fn test_rotated_revisit_matches() {
+        let mut map = LayeredMap::new();
+        map.add_occupancy_layer(OccupancyLayer::new());
+        map.add_tsdf_layer(TSDFLayer::new(0.1));
+        room(&mut map, (100, 100, 100), 0);
+        room(&mut map, (300, 300, 100), 1);
+        room(&mut map, (500, 100, 100), 2);
+        // A different place: one wall only
+        for i in (-12..=12).step_by(2) {
+            map.update_occupancy(
+                Index64::new(0, 0, 8, (700 + i) as u16, 112, 100).unwrap(),
+                &Measurement::occupied(0.9),
+            )
+            .unwrap();
+        }
+
+        let describe = |x, y| {
+            let center = Index64::new(0, 0, 8, x, y, 100).unwrap();
+            descriptor(&MapRegion::new(&map, center, 20).with_shells(6))
+        };
+        let mut matcher = DescriptorMatcher::new();
+        matcher.insert("quarter turn", describe(300, 300));
+        matcher.insert("half turn", describe(500, 100));
+        matcher.insert("other place", describe(700, 100));
+        assert_eq!(matcher.len(), 3);
+
+        let query = describe(100, 100);
+        assert!(query.coverage() > 0.0);
+        let matches = matcher.best_matches(&query, 2, 0.05);
+        assert_eq!(matches.len(), 2);
+        assert!(matches.iter().all(|(_, d)| *d == 0.0));
+        assert!(matches.iter().all(|(key, _)| *key != "other place"));
+        assert!(query.distance(&describe(700, 100)) > 0.0);
+    }
+}
@@ -0,0 +1,220 @@
+//! Terrain Elevation Layer (2.5D)
+//!
+//! Stores a regular grid of ground elevations, typically imported from a
+//! digital elevation model (DEM), for terrain-following queries such as
+//! height above ground level (AGL). Elevations are sampled with bilinear
+//! interpolation between grid posts.
+//!
+//! Unlike the voxel layers, terrain is a height field over the horizontal
+//! plane, so it does not implement [`Layer`](super::Layer). Coordinates are
+//! in the same physical frame (meters) as the [`BccGrid`](crate::grid::BccGrid)
+//! used for planning.
+
+use crate::error::{Error, Result};
+
+/// Regular-grid ground elevation model
+///
+/// # Example
+///
+/// ```rust
+/// use octaindex3d::layers::TerrainLayer;
+///
+/// # fn example() -> octaindex3d::Result<()> {
+/// let dem = "ncols 2\nnrows 2\nxllcenter 0\nyllcenter 0\ncellsize 10\n\
+///            NODATA_value -9999\n20 30\n0 10\n";
+/// let terrain = TerrainLayer::from_esri_ascii(dem)?;
+///
+/// // Bilinear interpolation between the four posts
+/// assert_eq!(terrain.elevation_at(5.0, 5.0), Some(15.0));
+/// assert_eq!(terrain.height_above_ground(5.0, 5.0, 40.0), Some(25.0));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct TerrainLayer {
+    /// Position of the south-west post (x = east, y = north)
+    origin: (f64, f64),
+    /// Post spacing
+    resolution: f64,
+    cols: usize,
+    rows: usize,
+    /// Row-major elevations, row 0 is the southernmost; NaN marks no data
+    heights: Vec<f32>,
+}
+
+impl TerrainLayer {
+    /// Create a terrain layer from row-major elevations
+    ///
+    /// `heights[row * cols + col]` is the elevation of the post at
+    /// `origin + (col, row) * resolution`, with row 0 the southernmost.
+    /// Use `f32::NAN` for posts without data.
+    pub fn new(
+        origin: (f64, f64),
+        resolution: f64,
+        cols: usize,
+        rows: usize,
+        heights: Vec<f32>,
+    ) -> Result<Self> {
+        if !resolution.is_finite() || resolution <= 0.0 {
+            return Err(Error::OutOfRange(format!(
+                "terrain resolution must be positive and finite, got {}",
+                resolution
+            )));
+        }
+        if cols == 0 || rows == 0 || heights.len() != cols * rows {
+            return Err(Error::InvalidFormat(format!(
+                "expected {}x{} elevations, got {}",
+                cols,
+                rows,
+                heights.len()
+            )));
+        }
+        Ok(Self {
+            origin,
+            resolution,
+            cols,
+            rows,
+            heights,
+        })
+    }
+
+    /// Import an ESRI ASCII grid (`.asc`) DEM
+    ///
+    /// Supports both `xllcorner`/`yllcorner` (posts at cell centers) and
+    /// `xllcenter`/`yllcenter` headers. `NODATA_value` posts become gaps.
+    pub fn from_esri_ascii(text: &str) -> Result<Self> {
+        let mut tokens = text.split_whitespace().peekable();
+        let mut header = std::collections::HashMap::new();
+
+        while let Some(&key) = tokens.peek() {
+            if key.parse::<f64>().is_ok() {
+                break;
+            }
+            tokens.next();
+            let value = tokens
+                .next()
+                .and_then(|v| v.parse::<f64>().ok())
+                .ok_or_else(|| Error::InvalidFormat(format!("DEM header {} has no value", key)))?;
+            header.insert(key.to_ascii_lowercase(), value);
+        }
+
+        let get = |key: &str| {
+            header
+                .get(key)
+                .copied()
+                .ok_or_else(|| Error::InvalidFormat(format!("DEM header is missing {}", key)))
+        };
+        let cols = get("ncols")? as usize;
+        let rows = get("nrows")? as usize;
+        let resolution = get("cellsize")?;
+        let nodata = header.get("nodata_value").copied();
+
+        // Corner registration puts posts half a cell inside the corner
+        let origin = match (header.get("xllcenter"), header.get("yllcenter")) {
+            (Some(&x), Some(&y)) => (x, y),
+            _ => (
+                get("xllcorner")? + resolution / 2.0,
+                get("yllcorner")? + resolution / 2.0,
+            ),
+        };
+
+        let values = tokens
+            .map(|t| {
+                t.parse::<f64>()
+                    .map_err(|_| Error::InvalidFormat(format!("invalid DEM value {}", t)))
+            })
+            .collect::<Result<Vec<f64>>>()?;
+        if values.len() != cols * rows {
+            return Err(Error::InvalidFormat(format!(
+                "expected {}x{} DEM values, got {}",
+                cols,
+                rows,
+                values.len()
+            )));
+        }
+
+        // ESRI rows run north to south
+        let mut heights = Vec::with_capacity(values.len());
+        for row in values.chunks(cols.max(1)).rev() {
+            heights.extend(row.iter().map(|&v| {
+                if Some(v) == nodata {
+                    f32::NAN
+                } else {
+                    v as f32
+                }
+            }));
+        }
+
+        Self::new(origin, resolution, cols, rows, heights)
+    }
+
+    /// Post spacing
+    pub fn resolution(&self) -> f64 {
+        self.resolution
+    }
+
+    /// Horizontal extent as `((min_x, min_y), (max_x, max_y))`
+    pub fn bounds(&self) -> ((f64, f64), (f64, f64)) {
+        (
+            self.origin,
+            (
+                self.origin.0 + (self.cols - 1) as f64 * self.resolution,
+                self.origin.1 + (self.rows - 1) as f64 * self.resolution,
+            ),
+        )
+    }
+
+    /// Ground elevation at a horizontal position
+    ///
+    /// Returns `None` outside the grid or next to a no-data post.
+    pub fn elevation_at(&self, x: f64, y: f64) -> Option<f64> {
+        let u = (x - self.origin.0) / self.resolution;
+        let v = (y - self.origin.1) / self.resolution;
+        let max_u = (self.cols - 1) as f64;
+        let max_v = (self.rows - 1) as f64;
+        if !(0.0..=max_u).contains(&u) || !(0.0..=max_v).contains(&v) {
+            return None;
+        }
+
+        let c0 = (u.floor() as usize).min(self.cols.saturating_sub(2));
+        let r0 = (v.floor() as usize).min(self.rows.saturating_sub(2));
+        let c1 = (c0 + 1).min(self.cols - 1);
+        let r1 = (r0 + 1).min(self.rows - 1);
+        let (fu, fv) = (u - c0 as f64, v - r0 as f64);
+
+        let h = |r: usize, c: usize| self.heights[r * self.cols + c] as f64;
+        let south = h(r0, c0) * (1.0 - fu) + h(r0, c1) * fu;
+        let north = h(r1, c0) * (1.0 - fu) + h(r1, c1) * fu;
+        let z = south * (1.0 - fv) + north * fv;
+        (!z.is_nan()).then_some(z)
+    }
+
+    /// Height of a point above the ground (negative below ground)
+    pub fn height_above_ground(&self, x: f64, y: f64, z: f64) -> Option<f64> {
+        self.elevation_at(x, y).map(|ground| z - ground)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_esri_ascii_import() -> Result<()> {
+        let dem = "NCOLS 3\nNROWS 2\nXLLCORNER 100\nYLLCORNER 200\nCELLSIZE 2\n\
+                   NODATA_VALUE -9999\n5 6 -9999\n1 2 3\n";
+        let terrain = TerrainLayer::from_esri_ascii(dem)?;
+
+        // Corner registration: first post at the center of the first cell
+        assert_eq!(terrain.bounds(), ((101.0, 201.0), (105.0, 203.0)));
+        assert_eq!(terrain.elevation_at(101.0, 201.0), Some(1.0));
+        assert_eq!(terrain.elevation_at(101.0, 203.0), Some(5.0));
+        assert_eq!(terrain.elevation_at(102.0, 202.0), Some(3.5));
+        // Next to the no-data post and outside the grid
+        assert_eq!(terrain.elevation_at(104.5, 202.5), None);
+        assert_eq!(terrain.elevation_at(99.0, 202.0), None);
+
+        assert!(TerrainLayer::from_esri_ascii("ncols 2\nnrows 2\ncellsize 1\n1 2 3").is_err());
+        Ok(())
+    }
+}
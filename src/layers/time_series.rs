@@ -0,0 +1,547 @@
+//! Per-cell Time Series Layer
+//!
+//! Stores the history of a scalar field (temperature, concentration,
+//! signal strength, ...) as `(timestamp, value)` samples per cell, for users
+//! who need the evolution of a field rather than its latest value.
+//!
+//! Timestamps are supplied by the caller (seconds in any consistent epoch),
+//! as in [`TimestampLayer`](super::TimestampLayer). Old history can be
+//! compacted into coarser buckets or dropped to bound memory.
+//!
+//! ## Rollups
+//!
+//! Optional per-bucket aggregates (count, sum, min, max) at fixed widths such
+//! as one hour and one day are maintained as samples arrive. They outlive
+//! compaction and retention of the raw samples, so dashboards over months of
+//! data read a handful of buckets instead of every sample.
+
+use super::timestamp::{in_bounds, VoxelBounds};
+use crate::error::{Error, Result};
+use crate::Index64;
+use std::collections::{BTreeMap, HashMap};
+
+/// Aggregate of the samples in one time bucket
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rollup {
+    /// Number of samples
+    pub count: u32,
+    /// Sum of the sample values
+    pub sum: f64,
+    /// Smallest sample value
+    pub min: f32,
+    /// Largest sample value
+    pub max: f32,
+}
+
+impl Rollup {
+    fn new(value: f32) -> Self {
+        Self {
+            count: 1,
+            sum: value as f64,
+            min: value,
+            max: value,
+        }
+    }
+
+    /// Mean of the sample values
+    pub fn mean(&self) -> f64 {
+        self.sum / self.count.max(1) as f64
+    }
+
+    /// Combine with the aggregate of another set of samples
+    pub fn merge(&mut self, other: &Rollup) {
+        self.count += other.count;
+        self.sum += other.sum;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+    }
+}
+
+/// Rollups of one bucket width: bucket number per cell
+type RollupTable = HashMap<Index64, BTreeMap<i64, Rollup>>;
+
+/// Layer storing timestamped scalar samples per cell
+///
+/// # Example
+///
+/// ```rust
+/// use octaindex3d::layers::TimeSeriesLayer;
+/// use octaindex3d::Index64;
+///
+/// # fn example() -> octaindex3d::Result<()> {
+/// let mut series = TimeSeriesLayer::new();
+/// let idx = Index64::new(0, 0, 5, 10, 10, 10)?;
+///
+/// for minute in 0..60 {
+///     series.record(idx, minute as f64 * 60.0, 20.0 + minute as f32 * 0.1);
+/// }
+///
+/// // The first ten minutes
+/// assert_eq!(series.values_between(idx, 0.0, 599.0).len(), 10);
+///
+/// // Keep the last ten minutes at full rate, older data as 10-minute means
+/// series.compact(3000.0, 600.0);
+/// assert_eq!(series.samples(idx).len(), 5 + 10);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TimeSeriesLayer {
+    /// Samples per cell, sorted by timestamp
+    series: HashMap<Index64, Vec<(f64, f32)>>,
+    /// Rollups by bucket width (seconds), narrowest first
+    rollups: Vec<(f64, RollupTable)>,
+}
+
+impl TimeSeriesLayer {
+    /// Create an empty time series layer
+    pub fn new() -> Self {
+        Self {
+            series: HashMap::new(),
+            rollups: Vec::new(),
+        }
+    }
+
+    /// Create an empty layer maintaining rollups at the given bucket widths
+    ///
+    /// ```rust
+    /// use octaindex3d::layers::TimeSeriesLayer;
+    /// use octaindex3d::Index64;
+    ///
+    /// # fn example() -> octaindex3d::Result<()> {
+    /// const HOUR: f64 = 3600.0;
+    /// let mut series = TimeSeriesLayer::with_rollups(&[HOUR, 24.0 * HOUR])?;
+    /// let idx = Index64::new(0, 0, 5, 10, 10, 10)?;
+    /// for minute in 0..(3 * 24 * 60) {
+    ///     series.record(idx, minute as f64 * 60.0, (minute % 60) as f32);
+    /// }
+    /// series.remove_older_than(f64::INFINITY); // Raw samples are gone...
+    ///
+    /// // ...but the daily aggregates remain
+    /// let days = series.rollups_between(idx, 24.0 * HOUR, 0.0, 3.0 * 24.0 * HOUR)?;
+    /// assert_eq!(days.len(), 3);
+    /// assert_eq!(days[0].1.count, 24 * 60);
+    /// assert_eq!((days[0].1.min, days[0].1.max), (0.0, 59.0));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_rollups(bucket_widths: &[f64]) -> Result<Self> {
+        let mut layer = Self::new();
+        for &width in bucket_widths {
+            layer.add_rollup(width)?;
+        }
+        Ok(layer)
+    }
+
+    /// Start maintaining rollups of `bucket_width` seconds
+    ///
+    /// Buckets are aligned to multiples of the width. Existing samples are
+    /// aggregated immediately; adding a width twice is a no-op.
+    pub fn add_rollup(&mut self, bucket_width: f64) -> Result<()> {
+        if !bucket_width.is_finite() || bucket_width <= 0.0 {
+            return Err(Error::InvalidAggregation(format!(
+                "rollup bucket width must be positive and finite, got {}",
+                bucket_width
+            )));
+        }
+        if self.rollups.iter().any(|(w, _)| *w == bucket_width) {
+            return Ok(());
+        }
+
+        let mut table = RollupTable::new();
+        for (&idx, samples) in &self.series {
+            let buckets = table.entry(idx).or_default();
+            for &(t, v) in samples {
+                add_to_bucket(buckets, bucket_key(t, bucket_width), v);
+            }
+        }
+        let at = self.rollups.partition_point(|(w, _)| *w < bucket_width);
+        self.rollups.insert(at, (bucket_width, table));
+        Ok(())
+    }
+
+    /// Bucket widths with maintained rollups, narrowest first
+    pub fn rollup_widths(&self) -> Vec<f64> {
+        self.rollups.iter().map(|(w, _)| *w).collect()
+    }
+
+    /// Record the value of `idx` at time `t`
+    ///
+    /// Samples may arrive out of order; a sample with the same timestamp as
+    /// an existing one replaces it. NaN timestamps are ignored.
+    pub fn record(&mut self, idx: Index64, t: f64, value: f32) {
+        if t.is_nan() {
+            return;
+        }
+        let samples = self.series.entry(idx).or_default();
+        // Appending in order is the common case
+        let replaced = if samples.last().map_or(true, |&(last, _)| last < t) {
+            samples.push((t, value));
+            None
+        } else {
+            match samples.binary_search_by(|probe| probe.0.total_cmp(&t)) {
+                Ok(i) => Some(std::mem::replace(&mut samples[i].1, value)),
+                Err(i) => {
+                    samples.insert(i, (t, value));
+                    None
+                }
+            }
+        };
+
+        for (width, table) in &mut self.rollups {
+            let buckets = table.entry(idx).or_default();
+            let key = bucket_key(t, *width);
+            match (replaced, buckets.get_mut(&key)) {
+                // Min and max cannot shrink without the other samples
+                (Some(old), Some(rollup)) => {
+                    rollup.sum += value as f64 - old as f64;
+                    rollup.min = rollup.min.min(value);
+                    rollup.max = rollup.max.max(value);
+                }
+                _ => add_to_bucket(buckets, key, value),
+            }
+        }
+    }
+
+    /// Record values of many cells at the same time `t`
+    pub fn record_all<I>(&mut self, values: I, t: f64)
+    where
+        I: IntoIterator<Item = (Index64, f32)>,
+    {
+        for (idx, value) in values {
+            self.record(idx, t, value);
+        }
+    }
+
+    /// All samples of a cell, oldest first
+    pub fn samples(&self, idx: Index64) -> &[(f64, f32)] {
+        self.series.get(&idx).map_or(&[], |s| s.as_slice())
+    }
+
+    /// Samples of a cell with `t0 <= t <= t1`, oldest first
+    pub fn values_between(&self, idx: Index64, t0: f64, t1: f64) -> &[(f64, f32)] {
+        let samples = self.samples(idx);
+        let start = samples.partition_point(|&(t, _)| t < t0);
+        let end = samples.partition_point(|&(t, _)| t <= t1);
+        &samples[start..end.max(start)]
+    }
+
+    /// Most recent sample of a cell
+    pub fn latest(&self, idx: Index64) -> Option<(f64, f32)> {
+        self.samples(idx).last().copied()
+    }
+
+    /// Value of a cell at time `t`, linearly interpolated between samples
+    ///
+    /// Returns `None` outside the recorded time span.
+    pub fn value_at(&self, idx: Index64, t: f64) -> Option<f32> {
+        let samples = self.samples(idx);
+        let i = samples.partition_point(|&(s, _)| s < t);
+        match (i.checked_sub(1).map(|j| samples[j]), samples.get(i)) {
+            (_, Some(&(s, v))) if s == t => Some(v),
+            (Some((t0, v0)), Some(&(t1, v1))) => {
+                let w = ((t - t0) / (t1 - t0)) as f32;
+                Some(v0 + w * (v1 - v0))
+            }
+            _ => None,
+        }
+    }
+
+    /// Latest value of every cell with a sample in `[t0, t1]`, optionally
+    /// restricted to an inclusive voxel bounding box
+    ///
+    /// Results are sorted by cell index.
+    pub fn snapshot_between(
+        &self,
+        t0: f64,
+        t1: f64,
+        region: Option<VoxelBounds>,
+    ) -> Vec<(Index64, f64, f32)> {
+        let mut cells: Vec<(Index64, f64, f32)> = self
+            .series
+            .keys()
+            .filter(|&&idx| in_bounds(idx, region))
+            .filter_map(|&idx| {
+                let &(t, v) = self.values_between(idx, t0, t1).last()?;
+                Some((idx, t, v))
+            })
+            .collect();
+        cells.sort_by_key(|&(idx, _, _)| idx);
+        cells
+    }
+
+    /// Rollups of a cell whose buckets overlap `[t0, t1]`, oldest first
+    ///
+    /// Returns `(bucket start time, rollup)` pairs. Fails if no rollup of
+    /// `bucket_width` is maintained.
+    pub fn rollups_between(
+        &self,
+        idx: Index64,
+        bucket_width: f64,
+        t0: f64,
+        t1: f64,
+    ) -> Result<Vec<(f64, Rollup)>> {
+        let table = self.rollup_table(bucket_width)?;
+        let Some(buckets) = table.get(&idx) else {
+            return Ok(Vec::new());
+        };
+        let (first, last) = (bucket_key(t0, bucket_width), bucket_key(t1, bucket_width));
+        if first > last {
+            return Ok(Vec::new());
+        }
+        Ok(buckets
+            .range(first..=last)
+            .map(|(&key, &rollup)| (key as f64 * bucket_width, rollup))
+            .collect())
+    }
+
+    /// Single aggregate over the buckets of a cell overlapping `[t0, t1]`
+    ///
+    /// Whole buckets are counted, so pick a width that divides the query
+    /// range for exact results.
+    pub fn rollup_between(
+        &self,
+        idx: Index64,
+        bucket_width: f64,
+        t0: f64,
+        t1: f64,
+    ) -> Result<Option<Rollup>> {
+        Ok(self
+            .rollups_between(idx, bucket_width, t0, t1)?
+            .into_iter()
+            .map(|(_, rollup)| rollup)
+            .reduce(|mut acc, rollup| {
+                acc.merge(&rollup);
+                acc
+            }))
+    }
+
+    /// Drop rollup buckets that end before `t`, returning how many were removed
+    pub fn remove_rollups_older_than(&mut self, t: f64) -> usize {
+        let mut removed = 0;
+        for (width, table) in &mut self.rollups {
+            // Bucket k covers [k * width, (k + 1) * width)
+            let keep_from = bucket_key(t, *width);
+            table.retain(|_, buckets| {
+                let kept = buckets.split_off(&keep_from);
+                removed += buckets.len();
+                *buckets = kept;
+                !buckets.is_empty()
+            });
+        }
+        removed
+    }
+
+    fn rollup_table(&self, bucket_width: f64) -> Result<&RollupTable> {
+        self.rollups
+            .iter()
+            .find(|(w, _)| *w == bucket_width)
+            .map(|(_, table)| table)
+            .ok_or_else(|| {
+                Error::InvalidAggregation(format!(
+                    "no rollup with bucket width {} is maintained",
+                    bucket_width
+                ))
+            })
+    }
+
+    /// Merge samples older than `before` into per-bucket means
+    ///
+    /// Buckets are aligned to multiples of `bucket` seconds; each non-empty
+    /// bucket is replaced by one sample at the mean timestamp with the mean
+    /// value. Samples at or after `before` are kept at full rate. Returns
+    /// the number of samples removed.
+    pub fn compact(&mut self, before: f64, bucket: f64) -> usize {
+        if bucket.is_nan() || bucket <= 0.0 {
+            return 0;
+        }
+        let mut removed = 0;
+        for samples in self.series.values_mut() {
+            let split = samples.partition_point(|&(t, _)| t < before);
+            if split < 2 {
+                continue;
+            }
+            let mut merged: Vec<(f64, f32)> = Vec::new();
+            let mut current: Option<(f64, f64, f64, usize)> = None; // key, Σt, Σv, n
+            for &(t, v) in &samples[..split] {
+                let key = (t / bucket).floor();
+                match current.as_mut() {
+                    Some(acc) if acc.0 == key => {
+                        acc.1 += t;
+                        acc.2 += v as f64;
+                        acc.3 += 1;
+                    }
+                    _ => {
+                        if let Some((_, st, sv, n)) = current {
+                            merged.push((st / n as f64, (sv / n as f64) as f32));
+                        }
+                        current = Some((key, t, v as f64, 1));
+                    }
+                }
+            }
+            if let Some((_, st, sv, n)) = current {
+                merged.push((st / n as f64, (sv / n as f64) as f32));
+            }
+            removed += split - merged.len();
+            samples.splice(..split, merged);
+        }
+        removed
+    }
+
+    /// Drop samples older than `t`, returning how many were removed
+    ///
+    /// Cells left without samples are forgotten. Rollups are kept; see
+    /// [`TimeSeriesLayer::remove_rollups_older_than`].
+    pub fn remove_older_than(&mut self, t: f64) -> usize {
+        let mut removed = 0;
+        self.series.retain(|_, samples| {
+            let split = samples.partition_point(|&(s, _)| s < t);
+            samples.drain(..split);
+            removed += split;
+            !samples.is_empty()
+        });
+        removed
+    }
+
+    /// Number of cells with at least one sample
+    pub fn len(&self) -> usize {
+        self.series.len()
+    }
+
+    /// Check if no cells have been recorded
+    pub fn is_empty(&self) -> bool {
+        self.series.is_empty()
+    }
+
+    /// Total number of samples across all cells
+    pub fn sample_count(&self) -> usize {
+        self.series.values().map(Vec::len).sum()
+    }
+
+    /// Clear all samples and rollups
+    pub fn clear(&mut self) {
+        self.series.clear();
+        for (_, table) in &mut self.rollups {
+            table.clear();
+        }
+    }
+
+    /// Iterate over all cells and their samples in arbitrary order
+    pub fn iter(&self) -> impl Iterator<Item = (Index64, &[(f64, f32)])> + '_ {
+        self.series.iter().map(|(&idx, s)| (idx, s.as_slice()))
+    }
+}
+
+/// Number of the bucket containing `t`
+fn bucket_key(t: f64, width: f64) -> i64 {
+    (t / width).floor() as i64
+}
+
+fn add_to_bucket(buckets: &mut BTreeMap<i64, Rollup>, key: i64, value: f32) {
+    match buckets.get_mut(&key) {
+        Some(rollup) => rollup.merge(&Rollup::new(value)),
+        None => {
+            buckets.insert(key, Rollup::new(value));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Result;
+
+    #[test]
+    fn test_range_queries() -> Result<()> {
+        let mut layer = TimeSeriesLayer::new();
+        let idx = Index64::new(0, 0, 5, 10, 10, 10)?;
+        layer.record(idx, 3.0, 30.0);
+        layer.record(idx, 1.0, 10.0); // Out of order
+        layer.record(idx, 2.0, 20.0);
+        layer.record(idx, 2.0, 25.0); // Replaces
+
+        assert_eq!(layer.samples(idx), &[(1.0, 10.0), (2.0, 25.0), (3.0, 30.0)]);
+        assert_eq!(
+            layer.values_between(idx, 1.5, 3.0),
+            &[(2.0, 25.0), (3.0, 30.0)]
+        );
+        assert!(layer.values_between(idx, 3.5, 9.0).is_empty());
+        assert!(layer.values_between(idx, 3.0, 1.0).is_empty());
+        assert_eq!(layer.latest(idx), Some((3.0, 30.0)));
+        assert_eq!(layer.value_at(idx, 2.5), Some(27.5));
+        assert_eq!(layer.value_at(idx, 1.0), Some(10.0));
+        assert_eq!(layer.value_at(idx, 0.5), None);
+
+        let other = Index64::new(0, 0, 5, 40, 40, 40)?;
+        layer.record(other, 5.0, 1.0);
+        assert_eq!(
+            layer.snapshot_between(0.0, 4.0, None),
+            vec![(idx, 3.0, 30.0)]
+        );
+        assert_eq!(layer.snapshot_between(0.0, 9.0, None).len(), 2);
+        let region = ((30, 30, 30), (50, 50, 50));
+        assert_eq!(
+            layer.snapshot_between(0.0, 9.0, Some(region)),
+            vec![(other, 5.0, 1.0)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_compaction_and_retention() -> Result<()> {
+        let mut layer = TimeSeriesLayer::new();
+        let idx = Index64::new(0, 0, 5, 2, 2, 2)?;
+        for t in 0..20 {
+            layer.record(idx, t as f64, t as f32);
+        }
+
+        // 0..10 into buckets of 5: means at t = 2 and t = 7
+        assert_eq!(layer.compact(10.0, 5.0), 8);
+        assert_eq!(
+            &layer.samples(idx)[..3],
+            &[(2.0, 2.0), (7.0, 7.0), (10.0, 10.0)]
+        );
+        assert_eq!(layer.sample_count(), 12);
+
+        assert_eq!(layer.remove_older_than(15.0), 7);
+        assert_eq!(layer.samples(idx).first(), Some(&(15.0, 15.0)));
+        assert_eq!(layer.remove_older_than(100.0), 5);
+        assert!(layer.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_rollups_maintained_on_ingest() -> Result<()> {
+        let mut layer = TimeSeriesLayer::with_rollups(&[10.0])?;
+        let idx = Index64::new(0, 0, 5, 4, 4, 4)?;
+        for t in 0..25 {
+            layer.record(idx, t as f64, t as f32);
+        }
+        layer.record(idx, 3.0, 13.0); // Replace 3 with 13
+
+        let buckets = layer.rollups_between(idx, 10.0, 0.0, 24.0)?;
+        assert_eq!(buckets.len(), 3);
+        assert_eq!(buckets[0].0, 0.0);
+        assert_eq!(buckets[0].1.count, 10);
+        assert_eq!(buckets[0].1.sum, 55.0);
+        assert_eq!((buckets[0].1.min, buckets[0].1.max), (0.0, 13.0));
+        assert_eq!(buckets[2].1.count, 5);
+        assert_eq!(buckets[2].1.mean(), 22.0);
+
+        // Backfilled from existing samples when added later
+        layer.add_rollup(5.0)?;
+        assert_eq!(layer.rollup_widths(), vec![5.0, 10.0]);
+        let total = layer.rollup_between(idx, 5.0, 0.0, 24.0)?.unwrap();
+        assert_eq!((total.count, total.sum), (25, 310.0));
+        assert!(layer.rollups_between(idx, 60.0, 0.0, 24.0).is_err());
+        assert!(layer.add_rollup(0.0).is_err());
+
+        // Retention of raw data leaves rollups intact
+        layer.remove_older_than(100.0);
+        assert_eq!(layer.sample_count(), 0);
+        assert_eq!(layer.rollups_between(idx, 10.0, 0.0, 24.0)?.len(), 3);
+        assert_eq!(layer.remove_rollups_older_than(10.0), 1 + 2);
+        assert_eq!(layer.rollups_between(idx, 10.0, 0.0, 24.0)?[0].0, 10.0);
+        Ok(())
+    }
+}
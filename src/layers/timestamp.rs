@@ -0,0 +1,220 @@
+//! Per-cell Timestamp Layer and Staleness Queries
+//!
+//! Records the last time each cell was observed so that exploration and
+//! mapping code can plan re-observation of stale regions or apply their own
+//! decay policies.
+//!
+//! Unlike [`TemporalOccupancyLayer`](super::TemporalOccupancyLayer), which
+//! reads a [`Clock`](crate::clock::Clock) internally, timestamps here are
+//! supplied by the caller (seconds in any consistent epoch, such as a
+//! clock's `now()`). This keeps queries deterministic for recorded data and
+//! simulation.
+
+use crate::Index64;
+use std::collections::HashMap;
+
+/// Inclusive voxel-coordinate bounding box used to restrict staleness queries
+pub type VoxelBounds = ((u16, u16, u16), (u16, u16, u16));
+
+/// Layer recording the last update time of each cell
+///
+/// # Example
+///
+/// ```rust
+/// use octaindex3d::layers::TimestampLayer;
+/// use octaindex3d::Index64;
+///
+/// # fn example() -> octaindex3d::Result<()> {
+/// let mut stamps = TimestampLayer::new();
+/// let a = Index64::new(0, 0, 5, 10, 10, 10)?;
+/// let b = Index64::new(0, 0, 5, 20, 20, 20)?;
+///
+/// stamps.touch(a, 1.0);
+/// stamps.touch(b, 8.0);
+///
+/// // Cells not seen since t = 5.0 need re-observation
+/// assert_eq!(stamps.cells_older_than(5.0, None), vec![a]);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TimestampLayer {
+    /// Last update time per cell (seconds)
+    stamps: HashMap<Index64, f64>,
+}
+
+impl TimestampLayer {
+    /// Create an empty timestamp layer
+    pub fn new() -> Self {
+        Self {
+            stamps: HashMap::new(),
+        }
+    }
+
+    /// Record an observation of `idx` at time `t`
+    ///
+    /// Out-of-order updates never move a cell's timestamp backwards.
+    pub fn touch(&mut self, idx: Index64, t: f64) {
+        let stamp = self.stamps.entry(idx).or_insert(t);
+        if t > *stamp {
+            *stamp = t;
+        }
+    }
+
+    /// Record observations of many cells at the same time `t`
+    pub fn touch_all<I>(&mut self, cells: I, t: f64)
+    where
+        I: IntoIterator<Item = Index64>,
+    {
+        for idx in cells {
+            self.touch(idx, t);
+        }
+    }
+
+    /// Get the last update time of a cell
+    pub fn last_update(&self, idx: Index64) -> Option<f64> {
+        self.stamps.get(&idx).copied()
+    }
+
+    /// Time elapsed since a cell was last updated
+    pub fn age(&self, idx: Index64, now: f64) -> Option<f64> {
+        self.last_update(idx).map(|t| now - t)
+    }
+
+    /// Cells whose last update is strictly before `t`, optionally restricted
+    /// to an inclusive voxel bounding box
+    ///
+    /// Results are sorted by timestamp (oldest first) for stable
+    /// re-observation ordering.
+    pub fn cells_older_than(&self, t: f64, region: Option<VoxelBounds>) -> Vec<Index64> {
+        self.collect_sorted(|stamp| stamp < t, region)
+    }
+
+    /// Cells updated at or after `t`, optionally restricted to a region
+    pub fn cells_updated_since(&self, t: f64, region: Option<VoxelBounds>) -> Vec<Index64> {
+        self.collect_sorted(|stamp| stamp >= t, region)
+    }
+
+    /// The least recently updated cell and its timestamp
+    pub fn oldest(&self) -> Option<(Index64, f64)> {
+        self.stamps
+            .iter()
+            .min_by(|a, b| a.1.total_cmp(b.1).then_with(|| a.0.cmp(b.0)))
+            .map(|(&idx, &t)| (idx, t))
+    }
+
+    /// Forget cells last updated before `t`, returning how many were removed
+    pub fn remove_older_than(&mut self, t: f64) -> usize {
+        let before = self.stamps.len();
+        self.stamps.retain(|_, stamp| *stamp >= t);
+        before - self.stamps.len()
+    }
+
+    /// Number of cells with a recorded timestamp
+    pub fn len(&self) -> usize {
+        self.stamps.len()
+    }
+
+    /// Check if no cells have been recorded
+    pub fn is_empty(&self) -> bool {
+        self.stamps.is_empty()
+    }
+
+    /// Clear all timestamps
+    pub fn clear(&mut self) {
+        self.stamps.clear();
+    }
+
+    /// Iterate over all (cell, timestamp) pairs in arbitrary order
+    pub fn iter(&self) -> impl Iterator<Item = (Index64, f64)> + '_ {
+        self.stamps.iter().map(|(&idx, &t)| (idx, t))
+    }
+
+    fn collect_sorted<F>(&self, keep: F, region: Option<VoxelBounds>) -> Vec<Index64>
+    where
+        F: Fn(f64) -> bool,
+    {
+        let mut cells: Vec<(Index64, f64)> = self
+            .stamps
+            .iter()
+            .filter(|(idx, &stamp)| keep(stamp) && in_bounds(**idx, region))
+            .map(|(&idx, &stamp)| (idx, stamp))
+            .collect();
+        cells.sort_by(|a, b| a.1.total_cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+        cells.into_iter().map(|(idx, _)| idx).collect()
+    }
+}
+
+/// Check whether a cell lies inside an optional inclusive voxel bounding box
+#[inline]
+pub(super) fn in_bounds(idx: Index64, region: Option<VoxelBounds>) -> bool {
+    match region {
+        None => true,
+        Some((min, max)) => {
+            let (x, y, z) = idx.decode_coords();
+            (min.0..=max.0).contains(&x)
+                && (min.1..=max.1).contains(&y)
+                && (min.2..=max.2).contains(&z)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Result;
+
+    #[test]
+    fn test_touch_is_monotonic() -> Result<()> {
+        let mut layer = TimestampLayer::new();
+        let idx = Index64::new(0, 0, 5, 10, 10, 10)?;
+
+        layer.touch(idx, 5.0);
+        layer.touch(idx, 3.0); // Late-arriving older observation
+        assert_eq!(layer.last_update(idx), Some(5.0));
+        assert_eq!(layer.age(idx, 7.5), Some(2.5));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_staleness_queries() -> Result<()> {
+        let mut layer = TimestampLayer::new();
+        let cells: Vec<Index64> = (0..5)
+            .map(|i| Index64::new(0, 0, 5, i * 10, 0, 0))
+            .collect::<Result<_>>()?;
+
+        for (i, &idx) in cells.iter().enumerate() {
+            layer.touch(idx, i as f64);
+        }
+
+        // Oldest first
+        assert_eq!(layer.cells_older_than(3.0, None), cells[..3].to_vec());
+        assert_eq!(layer.cells_updated_since(3.0, None), cells[3..].to_vec());
+        assert_eq!(layer.oldest(), Some((cells[0], 0.0)));
+
+        // Region restricts to x in [10, 20]
+        let region = ((10, 0, 0), (20, 0, 0));
+        assert_eq!(
+            layer.cells_older_than(10.0, Some(region)),
+            vec![cells[1], cells[2]]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_remove_older_than() -> Result<()> {
+        let mut layer = TimestampLayer::new();
+        layer.touch(Index64::new(0, 0, 5, 0, 0, 0)?, 1.0);
+        layer.touch(Index64::new(0, 0, 5, 2, 2, 2)?, 10.0);
+
+        assert_eq!(layer.remove_older_than(5.0), 1);
+        assert_eq!(layer.len(), 1);
+
+        layer.clear();
+        assert!(layer.is_empty());
+
+        Ok(())
+    }
+}
@@ -21,10 +21,23 @@
 //! 2. **Efficient storage**: Morton encoding enables fast spatial queries
 //! 3. **Natural hierarchy**: Parent-child relationships for multi-resolution
 
-use super::{Layer, LayerType, Measurement, MeasurementType};
+use super::batch::fold_by_voxel;
+use super::bcc_utils::physical_to_bcc_voxel;
+use super::ros2_bridge::Pose;
+use super::soa::SoaStore;
+use super::state::{decode_packed, decode_voxels, encode_packed, encode_voxels, is_packed};
+use super::{
+    DirtyTracker, Layer, LayerConfig, LayerType, Measurement, MeasurementType, StorageLayout,
+    ValueQuantization,
+};
 use crate::error::{Error, Result};
 use crate::Index64;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+
+/// Schema version of [`LayerConfig`] records written by this layer
+///
+/// Version 2 added `quantization` and `quantization_scale`.
+const CONFIG_VERSION: u16 = 2;
 
 /// Voxel data in TSDF layer
 #[derive(Debug, Clone, Copy)]
@@ -44,13 +57,138 @@ impl Default for TSDFVoxel {
     }
 }
 
+/// Voxel storage in one of the [`StorageLayout`]s
+enum Voxels {
+    Map(HashMap<Index64, TSDFVoxel>),
+    Soa(SoaStore),
+}
+
+impl Voxels {
+    /// Empty storage; the SoA layout holds distances and weights as codes
+    /// of the given encodings
+    fn new(
+        layout: StorageLayout,
+        distances: ValueQuantization,
+        weights: ValueQuantization,
+    ) -> Self {
+        match layout {
+            StorageLayout::Map => Self::Map(HashMap::new()),
+            StorageLayout::Soa => Self::Soa(SoaStore::new(distances, weights)),
+        }
+    }
+
+    fn layout(&self) -> StorageLayout {
+        match self {
+            Self::Map(_) => StorageLayout::Map,
+            Self::Soa(_) => StorageLayout::Soa,
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Self::Map(map) => map.len(),
+            Self::Soa(store) => store.len(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn get(&self, idx: Index64) -> Option<TSDFVoxel> {
+        match self {
+            Self::Map(map) => map.get(&idx).copied(),
+            Self::Soa(store) => store
+                .get(idx)
+                .map(|(distance, weight)| TSDFVoxel { distance, weight }),
+        }
+    }
+
+    /// Replace a voxel by `f` of its current value, created empty if absent
+    fn update(&mut self, idx: Index64, f: impl FnOnce(TSDFVoxel) -> TSDFVoxel) {
+        match self {
+            Self::Map(map) => {
+                let voxel = map.entry(idx).or_default();
+                *voxel = f(*voxel);
+            }
+            Self::Soa(store) => store.update(idx, |distance, weight| {
+                let voxel = f(TSDFVoxel { distance, weight });
+                (voxel.distance, voxel.weight)
+            }),
+        }
+    }
+
+    fn insert(&mut self, idx: Index64, voxel: TSDFVoxel) {
+        match self {
+            Self::Map(map) => {
+                map.insert(idx, voxel);
+            }
+            Self::Soa(store) => store.insert(idx, voxel.distance, voxel.weight),
+        }
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (Index64, TSDFVoxel)> + '_> {
+        match self {
+            Self::Map(map) => Box::new(map.iter().map(|(&idx, &voxel)| (idx, voxel))),
+            Self::Soa(store) => Box::new(
+                store
+                    .iter()
+                    .map(|(idx, distance, weight)| (idx, TSDFVoxel { distance, weight })),
+            ),
+        }
+    }
+
+    fn clear(&mut self) {
+        match self {
+            Self::Map(map) => map.clear(),
+            Self::Soa(store) => store.clear(),
+        }
+    }
+
+    /// Snap every voxel to the given encodings; SoA blocks switch to
+    /// holding their codes
+    fn quantize(&mut self, distances: ValueQuantization, weights: ValueQuantization) {
+        match self {
+            Self::Map(map) => {
+                for voxel in map.values_mut() {
+                    voxel.distance = distances.quantize(voxel.distance);
+                    voxel.weight = weights.quantize(voxel.weight);
+                }
+            }
+            Self::Soa(store) => store.requantize(distances, weights),
+        }
+    }
+}
+
+/// Incremental weighted average (Curless & Levoy 1996), stored in the
+/// given encodings
+fn fuse(
+    voxel: TSDFVoxel,
+    sdf: f32,
+    confidence: f32,
+    max_weight: f32,
+    distances: ValueQuantization,
+    weights: ValueQuantization,
+) -> TSDFVoxel {
+    let new_weight = (voxel.weight + confidence).min(max_weight);
+    let new_distance = if voxel.weight > 0.0 {
+        (voxel.distance * voxel.weight + sdf * confidence) / new_weight
+    } else {
+        sdf
+    };
+    TSDFVoxel {
+        distance: distances.quantize(new_distance),
+        weight: weights.quantize(new_weight),
+    }
+}
+
 /// Truncated Signed Distance Field layer
 ///
 /// Stores signed distance to nearest surface with incremental updates.
 /// Optimized for BCC lattice with 14-neighbor connectivity.
 pub struct TSDFLayer {
     /// Voxel data indexed by Index64
-    voxels: HashMap<Index64, TSDFVoxel>,
+    voxels: Voxels,
 
     /// Truncation distance (meters)
     /// Voxels farther than this from surface are not updated
@@ -61,6 +199,12 @@ pub struct TSDFLayer {
 
     /// Voxel size (meters per voxel)
     voxel_size: f32,
+
+    /// Encoding distances are rounded to
+    quantization: ValueQuantization,
+
+    /// Chunks changed since consumers last looked
+    dirty: DirtyTracker,
 }
 
 impl TSDFLayer {
@@ -77,20 +221,101 @@ impl TSDFLayer {
     /// ```
     pub fn new(truncation_distance: f32) -> Self {
         Self {
-            voxels: HashMap::new(),
+            voxels: Voxels::new(
+                StorageLayout::Map,
+                ValueQuantization::F32,
+                ValueQuantization::F32,
+            ),
             truncation_distance,
             max_weight: 100.0,
             voxel_size: 0.02, // Default 2cm voxels
+            quantization: ValueQuantization::F32,
+            dirty: DirtyTracker::default(),
         }
     }
 
     /// Create TSDF layer with custom parameters
     pub fn with_params(truncation_distance: f32, max_weight: f32, voxel_size: f32) -> Self {
         Self {
-            voxels: HashMap::new(),
+            voxels: Voxels::new(
+                StorageLayout::Map,
+                ValueQuantization::F32,
+                ValueQuantization::F32,
+            ),
             truncation_distance,
             max_weight,
             voxel_size,
+            quantization: ValueQuantization::F32,
+            dirty: DirtyTracker::default(),
+        }
+    }
+
+    /// Round distances to a reduced precision
+    ///
+    /// Distances are rounded to `quantization`, and checkpoints store the
+    /// narrow codes. In the [`StorageLayout::Soa`] layout the blocks hold
+    /// the codes too, which halves their memory with `F16`; the map layout
+    /// keeps rounded `f32` values. Weights are kept as `f16` whenever
+    /// distances are quantized, which is exact for the integer weights of
+    /// unit-confidence updates up to 2048. `I8` should cover the truncation
+    /// distance, e.g. `ValueQuantization::i8_for_range(truncation)`.
+    /// Existing voxels are rounded too.
+    ///
+    /// ```
+    /// use octaindex3d::layers::{Layer, TSDFLayer, ValueQuantization};
+    ///
+    /// let tsdf = TSDFLayer::new(0.1).with_quantization(ValueQuantization::F16);
+    /// assert_eq!(tsdf.quantization(), ValueQuantization::F16);
+    /// ```
+    pub fn with_quantization(mut self, quantization: ValueQuantization) -> Self {
+        self.set_quantization(quantization);
+        self
+    }
+
+    /// Encoding distances are rounded to
+    pub fn quantization(&self) -> ValueQuantization {
+        self.quantization
+    }
+
+    /// Store voxels in `layout`, moving existing ones
+    ///
+    /// The layout only affects memory use and speed: queries, statistics
+    /// and encoded state are identical. [`StorageLayout::Soa`] suits dense
+    /// maps and speeds up whole-map scans such as
+    /// [`get_surface_voxels`](Self::get_surface_voxels).
+    ///
+    /// ```
+    /// use octaindex3d::layers::{StorageLayout, TSDFLayer};
+    ///
+    /// let tsdf = TSDFLayer::new(0.1).with_layout(StorageLayout::Soa);
+    /// assert_eq!(tsdf.layout(), StorageLayout::Soa);
+    /// ```
+    pub fn with_layout(mut self, layout: StorageLayout) -> Self {
+        if layout != self.voxels.layout() {
+            let mut voxels = Voxels::new(layout, self.quantization, self.weight_quantization());
+            for (idx, voxel) in self.voxels.iter() {
+                voxels.insert(idx, voxel);
+            }
+            self.voxels = voxels;
+        }
+        self
+    }
+
+    /// Storage layout of the voxels
+    pub fn layout(&self) -> StorageLayout {
+        self.voxels.layout()
+    }
+
+    fn set_quantization(&mut self, quantization: ValueQuantization) {
+        self.quantization = quantization;
+        let weights = self.weight_quantization();
+        self.voxels.quantize(quantization, weights);
+    }
+
+    fn weight_quantization(&self) -> ValueQuantization {
+        match self.quantization {
+            ValueQuantization::F32 => ValueQuantization::F32,
+            _ => ValueQuantization::F16,
         }
     }
 
@@ -104,6 +329,17 @@ impl TSDFLayer {
         self.voxel_size
     }
 
+    /// Chunks changed since the last `take_dirty`
+    pub fn dirty(&self) -> &DirtyTracker {
+        &self.dirty
+    }
+
+    /// Mutable access to the change tracker, e.g. to replace it with one
+    /// using another chunk LOD
+    pub fn dirty_mut(&mut self) -> &mut DirtyTracker {
+        &mut self.dirty
+    }
+
     /// Get truncation distance
     pub fn truncation_distance(&self) -> f32 {
         self.truncation_distance
@@ -125,18 +361,19 @@ impl TSDFLayer {
         let truncated_sdf = sdf_value.clamp(-self.truncation_distance, self.truncation_distance);
 
         // Get or create voxel
-        let voxel = self.voxels.entry(idx).or_default();
-
-        // Incremental weighted average (Curless & Levoy 1996)
-        let new_weight = (voxel.weight + confidence).min(self.max_weight);
-        let new_distance = if voxel.weight > 0.0 {
-            (voxel.distance * voxel.weight + truncated_sdf * confidence) / new_weight
-        } else {
-            truncated_sdf
-        };
-
-        voxel.distance = new_distance;
-        voxel.weight = new_weight;
+        self.dirty.mark(idx);
+        let weights = self.weight_quantization();
+        let (distances, max_weight) = (self.quantization, self.max_weight);
+        self.voxels.update(idx, |voxel| {
+            fuse(
+                voxel,
+                truncated_sdf,
+                confidence,
+                max_weight,
+                distances,
+                weights,
+            )
+        });
 
         Ok(())
     }
@@ -174,12 +411,12 @@ impl TSDFLayer {
 
     /// Get distance value for a voxel
     pub fn get_distance(&self, idx: Index64) -> Option<f32> {
-        self.voxels.get(&idx).map(|v| v.distance)
+        self.voxels.get(idx).map(|v| v.distance)
     }
 
     /// Get weight for a voxel
     pub fn get_weight(&self, idx: Index64) -> Option<f32> {
-        self.voxels.get(&idx).map(|v| v.weight)
+        self.voxels.get(idx).map(|v| v.weight)
     }
 
     /// Check if voxel is near surface (distance close to zero)
@@ -189,13 +426,24 @@ impl TSDFLayer {
             .unwrap_or(false)
     }
 
-    /// Get all voxels near surface (for mesh extraction)
+    /// Get all voxels near surface (for mesh extraction), sorted
     pub fn get_surface_voxels(&self, threshold: f32) -> Vec<Index64> {
-        self.voxels
-            .iter()
-            .filter(|(_, v)| v.distance.abs() < threshold && v.weight > 0.0)
-            .map(|(idx, _)| *idx)
-            .collect()
+        let near = |distance: f32, weight: f32| distance.abs() < threshold && weight > 0.0;
+        let mut surface: Vec<Index64> = match &self.voxels {
+            Voxels::Map(map) => map
+                .iter()
+                .filter(|(_, v)| near(v.distance, v.weight))
+                .map(|(idx, _)| *idx)
+                .collect(),
+            Voxels::Soa(store) => {
+                let mut surface = Vec::new();
+                store.select(near, &mut surface);
+                surface
+            }
+        };
+        // Sorted so consumers such as the ESDF seed in a reproducible order
+        surface.sort_unstable();
+        surface
     }
 
     /// Get all zero-crossing edges (where sign changes between neighbors)
@@ -207,7 +455,7 @@ impl TSDFLayer {
 
         let mut edges = Vec::new();
 
-        for (&idx, voxel) in &self.voxels {
+        for (idx, voxel) in self.voxels.iter() {
             if voxel.weight == 0.0 {
                 continue;
             }
@@ -215,7 +463,7 @@ impl TSDFLayer {
             // Check all 14 neighbors
             let neighbors = neighbors_index64(idx);
             for neighbor_idx in neighbors {
-                if let Some(neighbor_voxel) = self.voxels.get(&neighbor_idx) {
+                if let Some(neighbor_voxel) = self.voxels.get(neighbor_idx) {
                     if neighbor_voxel.weight == 0.0 {
                         continue;
                     }
@@ -251,7 +499,7 @@ impl TSDFLayer {
         let mut total_weight = 0.0;
         let mut surface_voxels = 0;
 
-        for voxel in self.voxels.values() {
+        for (_, voxel) in self.voxels.iter() {
             if voxel.weight > 0.0 {
                 min_distance = min_distance.min(voxel.distance);
                 max_distance = max_distance.max(voxel.distance);
@@ -283,6 +531,64 @@ impl TSDFLayer {
             },
         }
     }
+
+    /// State encoding of `voxels`, in the layout of [`Layer::encode_state`]
+    fn encode_records(&self, voxels: impl Iterator<Item = (Index64, TSDFVoxel)>) -> Vec<u8> {
+        if self.quantization == ValueQuantization::F32 {
+            let records = voxels
+                .map(|(idx, v)| (idx, [v.distance.to_bits(), v.weight.to_bits()]))
+                .collect();
+            return encode_voxels(records);
+        }
+        // Distance code, then the f16 weight
+        let bits = 8 * self.quantization.value_bytes();
+        let weights = self.weight_quantization();
+        let records = voxels
+            .map(|(idx, v)| {
+                let data =
+                    self.quantization.encode(v.distance) | (weights.encode(v.weight) << bits);
+                (idx, data)
+            })
+            .collect();
+        encode_packed(self.quantization.value_bytes() + 2, records)
+    }
+
+    /// Voxels of a state encoding, as this layer's storage holds them
+    fn decode_records(&self, bytes: &[u8]) -> Result<Vec<(Index64, TSDFVoxel)>> {
+        let weights = self.weight_quantization();
+        if is_packed(bytes) {
+            let (width, records) = decode_packed(bytes)?;
+            let value_bytes = self.quantization.value_bytes();
+            if self.quantization == ValueQuantization::F32 || width != value_bytes + 2 {
+                return Err(Error::InvalidFormat(format!(
+                    "packed TSDF state of width {} does not match {:?} storage",
+                    width, self.quantization
+                )));
+            }
+            let bits = 8 * value_bytes;
+            return Ok(records
+                .into_iter()
+                .map(|(idx, data)| {
+                    let voxel = TSDFVoxel {
+                        distance: self.quantization.decode(data & ((1 << bits) - 1)),
+                        weight: weights.decode(data >> bits),
+                    };
+                    (idx, voxel)
+                })
+                .collect());
+        }
+        // Full precision state loaded into a quantized layer is rounded
+        Ok(decode_voxels(bytes)?
+            .into_iter()
+            .map(|(idx, [distance, weight])| {
+                let voxel = TSDFVoxel {
+                    distance: self.quantization.quantize(f32::from_bits(distance)),
+                    weight: weights.quantize(f32::from_bits(weight)),
+                };
+                (idx, voxel)
+            })
+            .collect())
+    }
 }
 
 /// TSDF statistics
@@ -300,6 +606,189 @@ pub struct TSDFStats {
     pub average_weight: f32,
 }
 
+/// Pinhole camera model of a depth image
+///
+/// Pixel `(u, v)` at depth `d` is the camera-frame point
+/// `((u - cx) d / fx, (v - cy) d / fy, d)`: x right, y down, z along the
+/// optical axis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraIntrinsics {
+    /// Image width in pixels
+    pub width: u32,
+    /// Image height in pixels
+    pub height: u32,
+    /// Focal length along x, in pixels
+    pub fx: f32,
+    /// Focal length along y, in pixels
+    pub fy: f32,
+    /// Principal point x, in pixels
+    pub cx: f32,
+    /// Principal point y, in pixels
+    pub cy: f32,
+}
+
+impl CameraIntrinsics {
+    /// Create a camera model
+    pub fn new(width: u32, height: u32, fx: f32, fy: f32, cx: f32, cy: f32) -> Self {
+        Self {
+            width,
+            height,
+            fx,
+            fy,
+            cx,
+            cy,
+        }
+    }
+
+    /// Camera-frame point seen at pixel `(u, v)` with depth `depth`
+    fn unproject(&self, u: u32, v: u32, depth: f32) -> (f32, f32, f32) {
+        (
+            (u as f32 - self.cx) * depth / self.fx,
+            (v as f32 - self.cy) * depth / self.fy,
+            depth,
+        )
+    }
+
+    /// Pixel a camera-frame point projects to, if inside the image
+    fn project(&self, (x, y, z): (f32, f32, f32)) -> Option<(u32, u32)> {
+        if z <= 0.0 {
+            return None;
+        }
+        let u = (x * self.fx / z + self.cx).round();
+        let v = (y * self.fy / z + self.cy).round();
+        let inside =
+            (0.0..self.width as f32).contains(&u) && (0.0..self.height as f32).contains(&v);
+        inside.then_some((u as u32, v as u32))
+    }
+}
+
+/// Integrate a depth image into a TSDF layer (KinectFusion)
+///
+/// `depth` holds `intrinsics.width * intrinsics.height` depths in meters,
+/// row by row; zero and non-finite depths mark missing pixels. `pose` maps
+/// the camera frame (see [`CameraIntrinsics`]) into the world frame, whose
+/// coordinates divided by the layer's voxel size are lattice coordinates.
+///
+/// Every pixel's ray is traversed through the truncation band around its
+/// depth to collect the voxels the image can observe. Each of them is then
+/// projected back into the image and updated once, with unit confidence,
+/// by the signed distance along its pixel ray between the measured
+/// surface and the voxel; voxels farther than the truncation distance from
+/// the surface, including those hidden behind it, are left alone. Updates
+/// go through
+/// [`Layer::update_batch`]. Returns the number of voxels updated.
+///
+/// # Example
+/// ```
+/// use octaindex3d::layers::ros2::{Point, Pose, Quaternion};
+/// use octaindex3d::layers::tsdf::{integrate_depth_image, CameraIntrinsics};
+/// use octaindex3d::layers::TSDFLayer;
+///
+/// # fn main() -> octaindex3d::Result<()> {
+/// let mut tsdf = TSDFLayer::with_params(0.1, 100.0, 0.02);
+/// let camera = CameraIntrinsics::new(32, 24, 20.0, 20.0, 16.0, 12.0);
+/// // A wall 1 m in front of a camera at (2, 2, 1) looking along +z
+/// let depth = vec![1.0; 32 * 24];
+/// let pose = Pose {
+///     position: Point { x: 2.0, y: 2.0, z: 1.0 },
+///     orientation: Quaternion { x: 0.0, y: 0.0, z: 0.0, w: 1.0 },
+/// };
+/// let updated = integrate_depth_image(&mut tsdf, &depth, &camera, &pose)?;
+/// assert!(updated > 0);
+/// # Ok(())
+/// # }
+/// ```
+pub fn integrate_depth_image(
+    tsdf: &mut TSDFLayer,
+    depth: &[f32],
+    intrinsics: &CameraIntrinsics,
+    pose: &Pose,
+) -> Result<usize> {
+    let (width, height) = (intrinsics.width, intrinsics.height);
+    if depth.len() != width as usize * height as usize {
+        return Err(Error::InvalidFormat(format!(
+            "depth image holds {} pixels, expected {} x {}",
+            depth.len(),
+            width,
+            height
+        )));
+    }
+    let depth_at = |u: u32, v: u32| {
+        let d = depth[(v * width + u) as usize];
+        (d.is_finite() && d > 0.0).then_some(d)
+    };
+    let (voxel_size, truncation) = (tsdf.voxel_size, tsdf.truncation_distance);
+    let to_world = |p: (f32, f32, f32)| {
+        let (x, y, z) = pose.transform_point((p.0 as f64, p.1 as f64, p.2 as f64));
+        (x as f32, y as f32, z as f32)
+    };
+    let origin = to_world((0.0, 0.0, 0.0));
+
+    // Voxels within the truncation band of some pixel's ray
+    let mut candidates = HashSet::new();
+    for v in 0..height {
+        for u in 0..width {
+            let Some(d) = depth_at(u, v) else {
+                continue;
+            };
+            let ray = intrinsics.unproject(u, v, 1.0);
+            let norm = (ray.0 * ray.0 + ray.1 * ray.1 + ray.2 * ray.2).sqrt();
+            let target = to_world((ray.0 / norm, ray.1 / norm, ray.2 / norm));
+            let dir = (
+                target.0 - origin.0,
+                target.1 - origin.1,
+                target.2 - origin.2,
+            );
+            let range = d * norm;
+            let steps = (2.0 * truncation / (0.5 * voxel_size)).ceil() as u32;
+            for step in 0..=steps {
+                let t = range - truncation + step as f32 * 0.5 * voxel_size;
+                let pos = (
+                    origin.0 + dir.0 * t,
+                    origin.1 + dir.1 * t,
+                    origin.2 + dir.2 * t,
+                );
+                if let Some(idx) = voxel_at(pos, voxel_size) {
+                    candidates.insert(idx);
+                }
+            }
+        }
+    }
+
+    // Projective signed distance of each candidate along its pixel ray
+    let mut candidates: Vec<Index64> = candidates.into_iter().collect();
+    candidates.sort_unstable();
+    let updates: Vec<(Index64, Measurement)> = candidates
+        .into_iter()
+        .filter_map(|idx| {
+            let (x, y, z) = idx.decode_coords();
+            let world = (
+                x as f64 * voxel_size as f64,
+                y as f64 * voxel_size as f64,
+                z as f64 * voxel_size as f64,
+            );
+            let (cx, cy, cz) = pose.inverse_transform_point(world);
+            let camera = (cx as f32, cy as f32, cz as f32);
+            let (u, v) = intrinsics.project(camera)?;
+            let d = depth_at(u, v)?;
+            let ray = intrinsics.unproject(u, v, 1.0);
+            let norm = (ray.0 * ray.0 + ray.1 * ray.1 + ray.2 * ray.2).sqrt();
+            let distance = (camera.0 * camera.0 + camera.1 * camera.1 + camera.2 * camera.2).sqrt();
+            let sdf = d * norm - distance;
+            (sdf.abs() <= truncation).then(|| (idx, Measurement::depth(sdf, 1.0)))
+        })
+        .collect();
+    tsdf.update_batch(&updates)?;
+    Ok(updates.len())
+}
+
+/// Lattice voxel nearest a world position, if inside the index range
+fn voxel_at(pos: (f32, f32, f32), voxel_size: f32) -> Option<Index64> {
+    let (x, y, z) = physical_to_bcc_voxel(pos, voxel_size);
+    let coord = |c: i32| u16::try_from(c).ok();
+    Index64::new(0, 0, 5, coord(x)?, coord(y)?, coord(z)?).ok()
+}
+
 impl Layer for TSDFLayer {
     fn layer_type(&self) -> LayerType {
         LayerType::TSDF
@@ -317,6 +806,46 @@ impl Layer for TSDFLayer {
         }
     }
 
+    /// Integrate a batch of depth measurements
+    ///
+    /// Rejects the batch before changing anything if it holds a
+    /// non-depth measurement. Voxels are fused in parallel, each from its
+    /// measurements in batch order, so the result is bit-identical to
+    /// calling [`update`](Layer::update) for each in turn.
+    fn update_batch(&mut self, updates: &[(Index64, Measurement)]) -> Result<()> {
+        let mut samples = Vec::with_capacity(updates.len());
+        for (idx, measurement) in updates {
+            if measurement.measurement_type != MeasurementType::Depth {
+                return Err(Error::InvalidFormat(
+                    "TSDF layer requires depth measurements".to_string(),
+                ));
+            }
+            let sdf = measurement.as_depth()?;
+            if sdf.abs() > self.truncation_distance {
+                continue;
+            }
+            let truncated_sdf = sdf.clamp(-self.truncation_distance, self.truncation_distance);
+            samples.push((*idx, (truncated_sdf, measurement.confidence)));
+        }
+
+        let weights = self.weight_quantization();
+        let (distances, max_weight) = (self.quantization, self.max_weight);
+        let voxels = &self.voxels;
+        let fused = fold_by_voxel(samples, |idx, samples| {
+            samples.iter().fold(
+                voxels.get(idx).unwrap_or_default(),
+                |voxel, &(_, (sdf, confidence))| {
+                    fuse(voxel, sdf, confidence, max_weight, distances, weights)
+                },
+            )
+        });
+        for (idx, voxel) in fused {
+            self.dirty.mark(idx);
+            self.voxels.insert(idx, voxel);
+        }
+        Ok(())
+    }
+
     fn query(&self, idx: Index64) -> Option<f32> {
         self.get_distance(idx)
     }
@@ -325,14 +854,104 @@ impl Layer for TSDFLayer {
         self.voxels.len()
     }
 
+    fn indices(&self) -> Box<dyn Iterator<Item = Index64> + '_> {
+        Box::new(self.voxels.iter().map(|(idx, _)| idx))
+    }
+
     fn clear(&mut self) {
+        self.dirty.mark_all(self.voxels.iter().map(|(idx, _)| idx));
         self.voxels.clear();
     }
 
+    fn dirty_tracker(&self) -> Option<&DirtyTracker> {
+        Some(&self.dirty)
+    }
+
+    fn dirty_tracker_mut(&mut self) -> Option<&mut DirtyTracker> {
+        Some(&mut self.dirty)
+    }
+
+    fn encode_state(&self) -> Option<Vec<u8>> {
+        Some(self.encode_records(self.voxels.iter()))
+    }
+
+    fn encode_cells(&self, cells: &[Index64]) -> Option<Vec<u8>> {
+        let voxels = cells
+            .iter()
+            .filter_map(|&idx| Some((idx, self.voxels.get(idx)?)));
+        Some(self.encode_records(voxels))
+    }
+
+    fn decode_state(&mut self, bytes: &[u8]) -> Result<()> {
+        let voxels = self.decode_records(bytes)?;
+        self.clear();
+        for (idx, voxel) in voxels {
+            self.dirty.mark(idx);
+            self.voxels.insert(idx, voxel);
+        }
+        Ok(())
+    }
+
+    fn merge_state(&mut self, bytes: &[u8]) -> Result<()> {
+        for (idx, voxel) in self.decode_records(bytes)? {
+            self.dirty.mark(idx);
+            self.voxels.insert(idx, voxel);
+        }
+        Ok(())
+    }
+
+    fn fuse_state(&mut self, bytes: &[u8]) -> Result<()> {
+        let weights = self.weight_quantization();
+        for (idx, voxel) in self.decode_records(bytes)? {
+            self.dirty.mark(idx);
+            let voxel = match self.voxels.get(idx) {
+                Some(old) => fuse(
+                    old,
+                    voxel.distance,
+                    voxel.weight,
+                    self.max_weight,
+                    self.quantization,
+                    weights,
+                ),
+                None => voxel,
+            };
+            self.voxels.insert(idx, voxel);
+        }
+        Ok(())
+    }
+
+    fn config(&self) -> Option<LayerConfig> {
+        let (quantization, scale) = self.quantization.to_fields();
+        Some(
+            LayerConfig::new(LayerType::TSDF, CONFIG_VERSION)
+                .with("truncation_distance", self.truncation_distance as f64)
+                .with("max_weight", self.max_weight as f64)
+                .with("voxel_size", self.voxel_size as f64)
+                .with("quantization", quantization)
+                .with("quantization_scale", scale),
+        )
+    }
+
+    fn apply_config(&mut self, config: &LayerConfig) -> Result<()> {
+        let (quantization, scale) = self.quantization.to_fields();
+        let quantization = ValueQuantization::from_fields(
+            config.get_or("quantization", quantization),
+            config.get_or("quantization_scale", scale),
+        )?;
+        self.truncation_distance =
+            config.get_or("truncation_distance", self.truncation_distance as f64) as f32;
+        self.max_weight = config.get_or("max_weight", self.max_weight as f64) as f32;
+        self.voxel_size = config.get_or("voxel_size", self.voxel_size as f64) as f32;
+        self.set_quantization(quantization);
+        Ok(())
+    }
+
     fn memory_usage(&self) -> usize {
-        // HashMap overhead + voxel data
-        // Rough estimate: 24 bytes per entry (HashMap overhead) + 8 bytes (Index64) + 8 bytes (TSDFVoxel)
-        self.voxels.len() * 40
+        match &self.voxels {
+            // Rough estimate: 24 bytes per entry (HashMap overhead) + 8 bytes (Index64) + 8 bytes (TSDFVoxel)
+            Voxels::Map(map) => map.len() * 40,
+            Voxels::Soa(store) => store.memory_usage(),
+        }
     }
 }
 
@@ -444,4 +1063,157 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_quantized_state() -> Result<()> {
+        let truncation = 0.1;
+        let quantization = ValueQuantization::i8_for_range(truncation)?;
+        let mut exact = TSDFLayer::new(truncation);
+        let mut small = TSDFLayer::new(truncation).with_quantization(quantization);
+        for i in 0..200u16 {
+            let idx = Index64::new(0, 0, 8, i, i / 3, 7)?;
+            let sdf = (i as f32 * 0.37).sin() * 0.09;
+            exact.update_from_depth(idx, sdf, 1.0)?;
+            small.update_from_depth(idx, sdf, 1.0)?;
+        }
+        for idx in exact.indices() {
+            let error = (exact.get_distance(idx).unwrap() - small.get_distance(idx).unwrap()).abs();
+            assert!(error <= quantization.max_error(truncation) * 1.0001);
+        }
+
+        // 8-byte key plus 3 value bytes instead of 8
+        let state = small.encode_state().unwrap();
+        assert_eq!(state.len(), 10 + 200 * 11);
+        assert!(state.len() < exact.encode_state().unwrap().len() * 3 / 4);
+
+        let mut restored = TSDFLayer::new(truncation);
+        restored.apply_config(&small.config().unwrap())?;
+        restored.decode_state(&state)?;
+        assert_eq!(restored.quantization(), quantization);
+        assert_eq!(restored.encode_state().unwrap(), state);
+        // Quantized state needs a matching layer
+        assert!(TSDFLayer::new(truncation).decode_state(&state).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_soa_layout_matches_map() -> Result<()> {
+        let mut map = TSDFLayer::new(0.1);
+        let mut soa = TSDFLayer::new(0.1).with_layout(StorageLayout::Soa);
+        for i in 0..300u16 {
+            let idx = Index64::new(0, 0, 8, i % 20, i / 20, i % 7)?;
+            let sdf = 0.001 * ((i * 37) % 190) as f32 - 0.095;
+            map.update_from_depth(idx, sdf, 1.0)?;
+            soa.update_from_depth(idx, sdf, 1.0)?;
+        }
+        assert_eq!(soa.voxel_count(), map.voxel_count());
+        assert_eq!(soa.get_surface_voxels(0.02), map.get_surface_voxels(0.02));
+        assert_eq!(soa.encode_state(), map.encode_state());
+
+        // Switching layouts keeps the voxels
+        let back = soa.with_layout(StorageLayout::Map);
+        assert_eq!(back.layout(), StorageLayout::Map);
+        assert_eq!(back.encode_state(), map.encode_state());
+        Ok(())
+    }
+
+    #[test]
+    fn test_quantized_soa_storage_shrinks() -> Result<()> {
+        let fill = |mut tsdf: TSDFLayer| -> Result<TSDFLayer> {
+            for i in 0..4096u16 {
+                let idx = Index64::new(0, 0, 8, i % 16, (i / 16) % 16, i / 256)?;
+                tsdf.update_from_depth(idx, 0.001 * (i % 190) as f32 - 0.095, 1.0)?;
+            }
+            Ok(tsdf)
+        };
+        let soa = |q| {
+            TSDFLayer::new(0.1)
+                .with_layout(StorageLayout::Soa)
+                .with_quantization(q)
+        };
+        let full = fill(soa(ValueQuantization::F32))?;
+        let half = fill(soa(ValueQuantization::F16))?;
+        let small = fill(soa(ValueQuantization::i8_for_range(0.1)?))?;
+        assert!(half.memory_usage() * 100 / full.memory_usage() <= 52);
+        assert!(small.memory_usage() * 100 / full.memory_usage() <= 40);
+
+        // The codes decode to the values a map layout rounds to
+        let map = fill(TSDFLayer::new(0.1).with_quantization(ValueQuantization::F16))?;
+        assert_eq!(half.encode_state(), map.encode_state());
+        let restored = half.with_layout(StorageLayout::Map);
+        assert_eq!(restored.encode_state(), map.encode_state());
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_batch_matches_sequential() -> Result<()> {
+        let mut updates = Vec::new();
+        for k in 0..200u16 {
+            let idx = Index64::new(0, 0, 5, 100 + 2 * (k % 7), 100, 100)?;
+            let sdf = (k as f32 * 0.37).sin() * 0.12;
+            updates.push((idx, Measurement::depth(sdf, 0.3 + (k % 3) as f32 * 0.2)));
+        }
+        for layout in [StorageLayout::Map, StorageLayout::Soa] {
+            let make = || {
+                TSDFLayer::with_params(0.1, 5.0, 0.02)
+                    .with_quantization(ValueQuantization::F16)
+                    .with_layout(layout)
+            };
+            let mut sequential = make();
+            for (idx, measurement) in &updates {
+                sequential.update(*idx, measurement)?;
+            }
+            let mut batched = make();
+            batched.update_batch(&updates[..50])?;
+            batched.update_batch(&updates[50..])?;
+            assert_eq!(batched.encode_state(), sequential.encode_state());
+            assert_eq!(batched.take_dirty(), sequential.take_dirty());
+        }
+
+        // A bad measurement rejects the whole batch
+        let mut tsdf = TSDFLayer::new(0.1);
+        let idx = updates[0].0;
+        let mixed = [updates[0].clone(), (idx, Measurement::occupied(0.9))];
+        assert!(tsdf.update_batch(&mixed).is_err());
+        assert_eq!(tsdf.voxel_count(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_integrate_depth_image() -> Result<()> {
+        use crate::layers::ros2::{Point, Quaternion};
+
+        let mut tsdf = TSDFLayer::with_params(0.1, 100.0, 0.02);
+        let camera = CameraIntrinsics::new(32, 24, 20.0, 20.0, 16.0, 12.0);
+        // Looking along +x: camera z is world x, camera x is world -y
+        let pose = Pose {
+            position: Point {
+                x: 1.0,
+                y: 2.0,
+                z: 2.0,
+            },
+            orientation: Quaternion {
+                x: -0.5,
+                y: 0.5,
+                z: -0.5,
+                w: 0.5,
+            },
+        };
+        let mut depth = vec![1.0; 32 * 24];
+        depth[0] = 0.0;
+        depth[1] = f32::NAN;
+        let updated = integrate_depth_image(&mut tsdf, &depth, &camera, &pose)?;
+        assert_eq!(updated, tsdf.voxel_count());
+
+        // The wall at world x = 2 m is lattice x = 100, on the optical axis
+        let at = |x: u16| Index64::new(0, 0, 5, x, 100, 100);
+        assert!(tsdf.get_distance(at(100)?).unwrap().abs() < 1e-6);
+        assert!((tsdf.get_distance(at(96)?).unwrap() - 0.08).abs() < 1e-5);
+        assert!((tsdf.get_distance(at(104)?).unwrap() + 0.08).abs() < 1e-5);
+        assert_eq!(tsdf.get_distance(at(90)?), None);
+        assert_eq!(tsdf.get_weight(at(100)?), Some(1.0));
+
+        assert!(integrate_depth_image(&mut tsdf, &depth[1..], &camera, &pose).is_err());
+        Ok(())
+    }
 }
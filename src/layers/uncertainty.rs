@@ -0,0 +1,368 @@
+//! Per-cell Uncertainty Layer
+//!
+//! Tracks the spread of repeated observations of each cell with Welford's
+//! online algorithm, so a planner can tell space observed many times and
+//! consistently from space seen once or measured inconsistently. Values
+//! come from measurements, from snapshots of another layer (TSDF distance,
+//! occupancy probability, ...) after each integration, or in pairs for the
+//! covariance of two quantities such as TSDF distance and occupancy.
+//!
+//! As a [`Layer`], the uncertainty layer reports the standard error of each
+//! cell's mean, so it combines with other layers through
+//! [`combine`](super::combine) into planning costs.
+
+use super::{Layer, LayerType, Measurement, MeasurementType};
+use crate::error::{Error, Result};
+use crate::Index64;
+use std::collections::HashMap;
+
+/// Running mean and variance of one cell's observations
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CellStats {
+    /// Number of observations
+    pub count: u32,
+    /// Mean of the observations
+    pub mean: f32,
+    /// Sum of squared differences from the mean
+    m2: f32,
+}
+
+impl CellStats {
+    /// Add an observation
+    pub fn push(&mut self, value: f32) {
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f32;
+        self.m2 += delta * (value - self.mean);
+    }
+
+    /// Sample variance, `None` before two observations
+    pub fn variance(&self) -> Option<f32> {
+        (self.count >= 2).then(|| self.m2 / (self.count - 1) as f32)
+    }
+
+    /// Sample standard deviation, `None` before two observations
+    pub fn std_dev(&self) -> Option<f32> {
+        self.variance().map(f32::sqrt)
+    }
+}
+
+/// Running means, variances and covariance of paired observations
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PairStats {
+    /// Number of observed pairs
+    pub count: u32,
+    /// Means of the two quantities
+    pub mean: [f32; 2],
+    /// Sums of squared differences from the means
+    m2: [f32; 2],
+    /// Sum of products of the differences from the means
+    cross: f32,
+}
+
+impl PairStats {
+    /// Add an observed pair
+    pub fn push(&mut self, a: f32, b: f32) {
+        self.count += 1;
+        let n = self.count as f32;
+        let delta_a = a - self.mean[0];
+        let delta_b = b - self.mean[1];
+        self.mean[0] += delta_a / n;
+        self.mean[1] += delta_b / n;
+        self.m2[0] += delta_a * (a - self.mean[0]);
+        self.m2[1] += delta_b * (b - self.mean[1]);
+        self.cross += delta_a * (b - self.mean[1]);
+    }
+
+    /// Sample variances of the two quantities, `None` before two pairs
+    pub fn variance(&self) -> Option<[f32; 2]> {
+        (self.count >= 2).then(|| self.m2.map(|m2| m2 / (self.count - 1) as f32))
+    }
+
+    /// Sample covariance, `None` before two pairs
+    pub fn covariance(&self) -> Option<f32> {
+        (self.count >= 2).then(|| self.cross / (self.count - 1) as f32)
+    }
+
+    /// Pearson correlation, `None` before two pairs or when either
+    /// quantity has not varied
+    pub fn correlation(&self) -> Option<f32> {
+        let denominator = (self.m2[0] * self.m2[1]).sqrt();
+        (self.count >= 2 && denominator > 0.0).then(|| self.cross / denominator)
+    }
+}
+
+/// Layer tracking how consistently each cell has been observed
+///
+/// # Example
+///
+/// ```rust
+/// use octaindex3d::layers::UncertaintyLayer;
+/// use octaindex3d::Index64;
+///
+/// # fn example() -> octaindex3d::Result<()> {
+/// let mut uncertainty = UncertaintyLayer::new();
+/// let wall = Index64::new(0, 0, 5, 10, 10, 10)?;
+/// let glass = Index64::new(0, 0, 5, 20, 20, 20)?;
+///
+/// for i in 0..10 {
+///     uncertainty.observe(wall, 0.95);
+///     uncertainty.observe(glass, if i % 2 == 0 { 0.9 } else { 0.2 });
+/// }
+///
+/// assert_eq!(uncertainty.variance(wall), Some(0.0));
+/// assert!(uncertainty.variance(glass).unwrap() > 0.1);
+/// assert!(uncertainty.is_well_observed(wall, 0.05));
+/// assert!(!uncertainty.is_well_observed(glass, 0.05));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct UncertaintyLayer {
+    cells: HashMap<Index64, CellStats>,
+    pairs: HashMap<Index64, PairStats>,
+    /// Variance assumed for cells observed fewer than twice
+    prior_variance: f32,
+}
+
+impl Default for UncertaintyLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UncertaintyLayer {
+    /// Create an empty layer assuming a variance of 1 for cells observed
+    /// only once
+    pub fn new() -> Self {
+        Self::with_prior_variance(1.0)
+    }
+
+    /// Create an empty layer assuming `prior_variance` for cells observed
+    /// only once
+    pub fn with_prior_variance(prior_variance: f32) -> Self {
+        Self {
+            cells: HashMap::new(),
+            pairs: HashMap::new(),
+            prior_variance,
+        }
+    }
+
+    /// Variance assumed for cells observed only once
+    pub fn prior_variance(&self) -> f32 {
+        self.prior_variance
+    }
+
+    /// Record an observed value of `idx`; NaN values are ignored
+    pub fn observe(&mut self, idx: Index64, value: f32) {
+        if !value.is_nan() {
+            self.cells.entry(idx).or_default().push(value);
+        }
+    }
+
+    /// Record an observed pair of quantities of `idx`, for their covariance;
+    /// pairs with a NaN are ignored
+    pub fn observe_pair(&mut self, idx: Index64, a: f32, b: f32) {
+        if !a.is_nan() && !b.is_nan() {
+            self.pairs.entry(idx).or_default().push(a, b);
+        }
+    }
+
+    /// Record the current value of every cell of `layer`, e.g. after each
+    /// scan is integrated
+    pub fn observe_layer<L: Layer + ?Sized>(&mut self, layer: &L) {
+        for idx in layer.indices() {
+            if let Some(value) = layer.query(idx) {
+                self.observe(idx, value);
+            }
+        }
+    }
+
+    /// Record the values of every cell held by both layers as pairs
+    pub fn observe_layer_pairs<A, B>(&mut self, a: &A, b: &B)
+    where
+        A: Layer + ?Sized,
+        B: Layer + ?Sized,
+    {
+        for idx in a.indices() {
+            if let (Some(x), Some(y)) = (a.query(idx), b.query(idx)) {
+                self.observe_pair(idx, x, y);
+            }
+        }
+    }
+
+    /// Statistics of the values of `idx`
+    pub fn stats(&self, idx: Index64) -> Option<CellStats> {
+        self.cells.get(&idx).copied()
+    }
+
+    /// Statistics of the pairs of `idx`
+    pub fn pair_stats(&self, idx: Index64) -> Option<PairStats> {
+        self.pairs.get(&idx).copied()
+    }
+
+    /// Sample variance of `idx`, `None` before two observations
+    pub fn variance(&self, idx: Index64) -> Option<f32> {
+        self.cells.get(&idx).and_then(CellStats::variance)
+    }
+
+    /// Sample covariance of the pairs of `idx`, `None` before two pairs
+    pub fn covariance(&self, idx: Index64) -> Option<f32> {
+        self.pairs.get(&idx).and_then(PairStats::covariance)
+    }
+
+    /// Standard error of the mean of `idx`: small for cells observed often
+    /// and consistently, infinite for cells never observed
+    ///
+    /// Cells observed once use the prior variance.
+    pub fn standard_error(&self, idx: Index64) -> f32 {
+        match self.cells.get(&idx) {
+            None => f32::INFINITY,
+            Some(stats) => {
+                let variance = stats.variance().unwrap_or(self.prior_variance);
+                (variance / stats.count as f32).sqrt()
+            }
+        }
+    }
+
+    /// Whether the mean of `idx` is known to within `max_standard_error`
+    pub fn is_well_observed(&self, idx: Index64, max_standard_error: f32) -> bool {
+        self.standard_error(idx) <= max_standard_error
+    }
+
+    /// Forget the observations of `idx`
+    pub fn remove(&mut self, idx: Index64) {
+        self.cells.remove(&idx);
+        self.pairs.remove(&idx);
+    }
+
+    /// Number of cells with observed values
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Check if no cell has observed values
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+}
+
+impl Layer for UncertaintyLayer {
+    fn layer_type(&self) -> LayerType {
+        LayerType::Derived
+    }
+
+    /// Records the measured value: depth distance, intensity, or 1 for
+    /// occupied and 0 for free
+    fn update(&mut self, idx: Index64, measurement: &Measurement) -> Result<()> {
+        let value = match measurement.measurement_type {
+            MeasurementType::Depth => measurement.as_depth()?,
+            MeasurementType::Intensity => measurement.as_intensity()?,
+            MeasurementType::Occupancy => {
+                if measurement.is_occupied()? {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            MeasurementType::Color => {
+                return Err(Error::InvalidFormat(
+                    "uncertainty layer takes scalar measurements, not color".to_string(),
+                ))
+            }
+        };
+        self.observe(idx, value);
+        Ok(())
+    }
+
+    /// Standard error of the cell's mean
+    fn query(&self, idx: Index64) -> Option<f32> {
+        self.cells
+            .contains_key(&idx)
+            .then(|| self.standard_error(idx))
+    }
+
+    fn voxel_count(&self) -> usize {
+        self.cells.len()
+    }
+
+    fn indices(&self) -> Box<dyn Iterator<Item = Index64> + '_> {
+        Box::new(self.cells.keys().copied())
+    }
+
+    fn clear(&mut self) {
+        self.cells.clear();
+        self.pairs.clear();
+    }
+
+    fn memory_usage(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + self.cells.capacity() * std::mem::size_of::<(Index64, CellStats)>()
+            + self.pairs.capacity() * std::mem::size_of::<(Index64, PairStats)>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layers::{combine, CombineOp, OccupancyLayer};
+
+    #[test]
+    fn test_welford_matches_two_pass() {
+        let values = [2.0f32, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+        let mut stats = CellStats::default();
+        for v in values {
+            stats.push(v);
+        }
+        let mean = values.iter().sum::<f32>() / values.len() as f32;
+        let variance =
+            values.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / (values.len() - 1) as f32;
+        assert_eq!(stats.mean, mean);
+        assert!((stats.variance().unwrap() - variance).abs() < 1e-5);
+
+        let mut pairs = PairStats::default();
+        for v in values {
+            pairs.push(v, 10.0 - 2.0 * v);
+        }
+        assert!((pairs.covariance().unwrap() + 2.0 * variance).abs() < 1e-4);
+        assert!((pairs.correlation().unwrap() + 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_occupancy_snapshots_flag_inconsistent_cells() {
+        let mut occupancy = OccupancyLayer::new();
+        let mut uncertainty = UncertaintyLayer::new();
+        let wall = Index64::new(0, 0, 8, 10, 10, 10).unwrap();
+        let door = Index64::new(0, 0, 8, 20, 20, 20).unwrap();
+        let once = Index64::new(0, 0, 8, 30, 30, 30).unwrap();
+        let hall = Index64::new(0, 0, 8, 40, 40, 40).unwrap();
+        for scan in 0..8 {
+            occupancy.update(wall, &Measurement::occupied(0.9)).unwrap();
+            occupancy.update(hall, &Measurement::free(0.9)).unwrap();
+            let door_seen = if scan < 4 {
+                Measurement::occupied(0.9)
+            } else {
+                Measurement::free(0.9)
+            };
+            occupancy.update(door, &door_seen).unwrap();
+            if scan == 7 {
+                occupancy.update(once, &Measurement::free(0.9)).unwrap();
+            }
+            uncertainty.observe_layer(&occupancy);
+        }
+        assert_eq!(uncertainty.len(), 4);
+        assert_eq!(uncertainty.stats(wall).unwrap().count, 8);
+        assert_eq!(uncertainty.standard_error(once), 1.0);
+        assert!(uncertainty.standard_error(wall) < uncertainty.standard_error(door));
+        assert!(uncertainty.standard_error(door) < uncertainty.standard_error(once));
+
+        // Risk cost for planning: free space seen once costs more than
+        // free space seen in every scan
+        let cost = combine(&occupancy, &uncertainty, CombineOp::Add);
+        assert!(cost.get(hall).unwrap() < cost.get(once).unwrap());
+        assert_eq!(
+            uncertainty.query(Index64::new(0, 0, 8, 2, 2, 2).unwrap()),
+            None
+        );
+    }
+}
@@ -0,0 +1,599 @@
+//! Lazily evaluated layers defined by expressions
+//!
+//! A [`VirtualLayer`] computes its value at a cell from the values of other
+//! layers at the same cell, only when asked. One-off analyses such as
+//! `esdf < 0.5 && occupancy > 0.7` then need no intermediate layers.
+//!
+//! ## Expressions
+//!
+//! - Numbers (`0.5`, `1e-3`) and layer names bound with [`VirtualLayer::bind`]
+//! - Arithmetic `+ - * /`, comparisons `< <= > >= == !=`, logic `&& || !`
+//! - Functions `abs(x)`, `min(x, y)`, `max(x, y)`
+//! - Parentheses
+//!
+//! Comparisons and logic yield `1.0` for true and `0.0` for false; any
+//! non-zero value counts as true. A cell has a value only where every
+//! referenced layer has one.
+
+use super::{Layer, LayerType, Measurement};
+use crate::error::{Error, Result};
+use crate::Index64;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Function {
+    Abs,
+    Min,
+    Max,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Number(f32),
+    Variable(usize),
+    Neg(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Call(Function, Vec<Expr>),
+}
+
+fn truth(value: bool) -> f32 {
+    if value {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+impl Expr {
+    fn eval(&self, vars: &[f32]) -> f32 {
+        match self {
+            Expr::Number(v) => *v,
+            Expr::Variable(i) => vars[*i],
+            Expr::Neg(e) => -e.eval(vars),
+            Expr::Not(e) => truth(e.eval(vars) == 0.0),
+            Expr::Binary(op, l, r) => {
+                let l = l.eval(vars);
+                // Short-circuit logic, as written
+                match op {
+                    BinaryOp::And if l == 0.0 => return 0.0,
+                    BinaryOp::Or if l != 0.0 => return 1.0,
+                    _ => {}
+                }
+                let r = r.eval(vars);
+                match op {
+                    BinaryOp::Add => l + r,
+                    BinaryOp::Sub => l - r,
+                    BinaryOp::Mul => l * r,
+                    BinaryOp::Div => l / r,
+                    BinaryOp::Lt => truth(l < r),
+                    BinaryOp::Le => truth(l <= r),
+                    BinaryOp::Gt => truth(l > r),
+                    BinaryOp::Ge => truth(l >= r),
+                    BinaryOp::Eq => truth(l == r),
+                    BinaryOp::Ne => truth(l != r),
+                    BinaryOp::And | BinaryOp::Or => truth(r != 0.0),
+                }
+            }
+            Expr::Call(f, args) => match f {
+                Function::Abs => args[0].eval(vars).abs(),
+                Function::Min => args[0].eval(vars).min(args[1].eval(vars)),
+                Function::Max => args[0].eval(vars).max(args[1].eval(vars)),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f32),
+    Ident(String),
+    Op(&'static str),
+    LParen,
+    RParen,
+    Comma,
+}
+
+fn tokenize(src: &str) -> Result<Vec<Token>> {
+    const OPS: [&str; 15] = [
+        "&&", "||", "<=", ">=", "==", "!=", "<", ">", "!", "+", "-", "*", "/", "(", ")",
+    ];
+    let bytes = src.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        if c.is_ascii_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || c == b'.' {
+            let start = i;
+            while i < bytes.len()
+                && (bytes[i].is_ascii_alphanumeric()
+                    || bytes[i] == b'.'
+                    || (matches!(bytes[i], b'+' | b'-') && matches!(bytes[i - 1], b'e' | b'E')))
+            {
+                i += 1;
+            }
+            let text = &src[start..i];
+            let value = text.parse().map_err(|_| {
+                Error::InvalidFormat(format!("invalid number '{}' in expression", text))
+            })?;
+            tokens.push(Token::Number(value));
+        } else if c.is_ascii_alphabetic() || c == b'_' {
+            let start = i;
+            while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(src[start..i].to_string()));
+        } else if c == b',' {
+            tokens.push(Token::Comma);
+            i += 1;
+        } else {
+            let op = OPS
+                .iter()
+                .find(|op| src[i..].starts_with(*op))
+                .ok_or_else(|| {
+                    Error::InvalidFormat(format!(
+                        "unexpected character '{}' at offset {} in expression",
+                        &src[i..].chars().next().unwrap_or('?'),
+                        i
+                    ))
+                })?;
+            tokens.push(match *op {
+                "(" => Token::LParen,
+                ")" => Token::RParen,
+                op => Token::Op(op),
+            });
+            i += op.len();
+        }
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent parser, lowest precedence first
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    names: Vec<String>,
+}
+
+impl Parser {
+    fn peek_op(&self) -> Option<&'static str> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Op(op)) => Some(op),
+            _ => None,
+        }
+    }
+
+    fn expect(&mut self, token: Token) -> Result<()> {
+        if self.tokens.get(self.pos) == Some(&token) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(Error::InvalidFormat(format!(
+                "expected {:?} at token {} in expression",
+                token, self.pos
+            )))
+        }
+    }
+
+    fn binary<F>(&mut self, ops: &[(&str, BinaryOp)], mut next: F, chain: bool) -> Result<Expr>
+    where
+        F: FnMut(&mut Self) -> Result<Expr>,
+    {
+        let mut lhs = next(self)?;
+        while let Some(op) = self.peek_op() {
+            let Some(&(_, op)) = ops.iter().find(|(s, _)| *s == op) else {
+                break;
+            };
+            self.pos += 1;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(next(self)?));
+            if !chain {
+                break;
+            }
+        }
+        Ok(lhs)
+    }
+
+    fn or(&mut self) -> Result<Expr> {
+        self.binary(&[("||", BinaryOp::Or)], Self::and, true)
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        self.binary(&[("&&", BinaryOp::And)], Self::comparison, true)
+    }
+
+    fn comparison(&mut self) -> Result<Expr> {
+        const OPS: [(&str, BinaryOp); 6] = [
+            ("<", BinaryOp::Lt),
+            ("<=", BinaryOp::Le),
+            (">", BinaryOp::Gt),
+            (">=", BinaryOp::Ge),
+            ("==", BinaryOp::Eq),
+            ("!=", BinaryOp::Ne),
+        ];
+        self.binary(&OPS, Self::sum, false)
+    }
+
+    fn sum(&mut self) -> Result<Expr> {
+        self.binary(
+            &[("+", BinaryOp::Add), ("-", BinaryOp::Sub)],
+            Self::product,
+            true,
+        )
+    }
+
+    fn product(&mut self) -> Result<Expr> {
+        self.binary(
+            &[("*", BinaryOp::Mul), ("/", BinaryOp::Div)],
+            Self::unary,
+            true,
+        )
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        match self.peek_op() {
+            Some("-") => {
+                self.pos += 1;
+                Ok(Expr::Neg(Box::new(self.unary()?)))
+            }
+            Some("!") => {
+                self.pos += 1;
+                Ok(Expr::Not(Box::new(self.unary()?)))
+            }
+            _ => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        match token {
+            Some(Token::Number(v)) => Ok(Expr::Number(v)),
+            Some(Token::LParen) => {
+                let inner = self.or()?;
+                self.expect(Token::RParen)?;
+                Ok(inner)
+            }
+            Some(Token::Ident(name)) if self.tokens.get(self.pos) == Some(&Token::LParen) => {
+                let (function, arity) = match name.as_str() {
+                    "abs" => (Function::Abs, 1),
+                    "min" => (Function::Min, 2),
+                    "max" => (Function::Max, 2),
+                    _ => {
+                        return Err(Error::InvalidFormat(format!(
+                            "unknown function '{}' in expression",
+                            name
+                        )))
+                    }
+                };
+                self.pos += 1;
+                let mut args = vec![self.or()?];
+                while self.tokens.get(self.pos) == Some(&Token::Comma) {
+                    self.pos += 1;
+                    args.push(self.or()?);
+                }
+                self.expect(Token::RParen)?;
+                if args.len() != arity {
+                    return Err(Error::InvalidFormat(format!(
+                        "{}() takes {} argument(s), got {}",
+                        name,
+                        arity,
+                        args.len()
+                    )));
+                }
+                Ok(Expr::Call(function, args))
+            }
+            Some(Token::Ident(name)) => {
+                let slot = match self.names.iter().position(|n| *n == name) {
+                    Some(slot) => slot,
+                    None => {
+                        self.names.push(name);
+                        self.names.len() - 1
+                    }
+                };
+                Ok(Expr::Variable(slot))
+            }
+            other => Err(Error::InvalidFormat(format!(
+                "unexpected {:?} in expression",
+                other
+            ))),
+        }
+    }
+}
+
+/// Bindings by name, for building several virtual layers over the same inputs
+pub type LayerBindings<'a> = HashMap<&'a str, &'a dyn Layer>;
+
+/// Layer computed on demand from an expression over other layers
+///
+/// The virtual layer borrows its inputs and stores nothing itself, so it
+/// always reflects their current contents. It implements [`Layer`] and can
+/// be passed wherever a read-only layer is expected, including as input to
+/// another virtual layer or to [`super::combine`].
+///
+/// # Example
+///
+/// ```
+/// use octaindex3d::layers::{Layer, OccupancyLayer, ScalarLayer, VirtualLayer};
+/// use octaindex3d::Index64;
+///
+/// # fn main() -> octaindex3d::Result<()> {
+/// let idx = Index64::new(0, 0, 5, 10, 10, 10)?;
+/// let mut occupancy = OccupancyLayer::new();
+/// occupancy.update_occupancy(idx, true, 1.0);
+/// // Any layer works as input, e.g. an ESDFLayer
+/// let mut esdf = ScalarLayer::new();
+/// esdf.set(idx, 0.2);
+///
+/// let danger = VirtualLayer::parse("esdf < 0.5 && occupancy > 0.7")?
+///     .bind("esdf", &esdf)
+///     .bind("occupancy", &occupancy);
+/// danger.check()?;
+/// assert!(danger.matches(idx));
+/// assert_eq!(danger.query(idx), Some(1.0));
+/// # Ok(())
+/// # }
+/// ```
+pub struct VirtualLayer<'a> {
+    source: String,
+    expr: Expr,
+    names: Vec<String>,
+    inputs: Vec<Option<&'a dyn Layer>>,
+}
+
+impl<'a> VirtualLayer<'a> {
+    /// Parse an expression; layers are attached afterwards with [`bind`](Self::bind)
+    pub fn parse(expression: &str) -> Result<Self> {
+        let mut parser = Parser {
+            tokens: tokenize(expression)?,
+            pos: 0,
+            names: Vec::new(),
+        };
+        let expr = parser.or()?;
+        if parser.pos < parser.tokens.len() {
+            return Err(Error::InvalidFormat(format!(
+                "unexpected {:?} in expression",
+                parser.tokens[parser.pos]
+            )));
+        }
+        let inputs = vec![None; parser.names.len()];
+        Ok(Self {
+            source: expression.to_string(),
+            expr,
+            names: parser.names,
+            inputs,
+        })
+    }
+
+    /// Attach the layer read for `name`
+    ///
+    /// Names the expression does not use are ignored.
+    pub fn bind(mut self, name: &str, layer: &'a dyn Layer) -> Self {
+        if let Some(slot) = self.names.iter().position(|n| n == name) {
+            self.inputs[slot] = Some(layer);
+        }
+        self
+    }
+
+    /// Attach every referenced layer found in `bindings`
+    pub fn bind_all(mut self, bindings: &LayerBindings<'a>) -> Self {
+        for (slot, name) in self.names.iter().enumerate() {
+            if let Some(&layer) = bindings.get(name.as_str()) {
+                self.inputs[slot] = Some(layer);
+            }
+        }
+        self
+    }
+
+    /// Expression text
+    pub fn expression(&self) -> &str {
+        &self.source
+    }
+
+    /// Layer names referenced by the expression, in order of first use
+    pub fn variables(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Ensure every referenced layer is bound
+    pub fn check(&self) -> Result<()> {
+        match self.inputs.iter().position(Option::is_none) {
+            Some(slot) => Err(Error::InvalidFormat(format!(
+                "layer '{}' used in '{}' is not bound",
+                self.names[slot], self.source
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Evaluate at one cell
+    ///
+    /// `None` if a referenced layer is unbound or has not observed the cell.
+    pub fn evaluate(&self, idx: Index64) -> Option<f32> {
+        let mut vars = Vec::with_capacity(self.inputs.len());
+        for input in &self.inputs {
+            vars.push(input.as_ref()?.query(idx)?);
+        }
+        Some(self.expr.eval(&vars))
+    }
+
+    /// Check if the expression is true (non-zero) at a cell
+    pub fn matches(&self, idx: Index64) -> bool {
+        self.evaluate(idx).is_some_and(|v| v != 0.0)
+    }
+
+    /// Evaluate over a region, skipping cells without a value
+    pub fn evaluate_region<I>(&self, cells: I) -> impl Iterator<Item = (Index64, f32)> + '_
+    where
+        I: IntoIterator<Item = Index64>,
+        I::IntoIter: 'a,
+    {
+        cells
+            .into_iter()
+            .filter_map(move |idx| self.evaluate(idx).map(|v| (idx, v)))
+    }
+
+    /// Cells of a region where the expression is true
+    pub fn matching<I>(&self, cells: I) -> impl Iterator<Item = Index64> + '_
+    where
+        I: IntoIterator<Item = Index64>,
+        I::IntoIter: 'a,
+    {
+        cells.into_iter().filter(move |&idx| self.matches(idx))
+    }
+
+    /// Smallest bound input, whose cells bound the layer's domain
+    fn driver(&self) -> Option<&'a dyn Layer> {
+        self.inputs
+            .iter()
+            .flatten()
+            .copied()
+            .min_by_key(|layer| layer.voxel_count())
+    }
+}
+
+impl Layer for VirtualLayer<'_> {
+    fn layer_type(&self) -> LayerType {
+        LayerType::Derived
+    }
+
+    fn update(&mut self, _idx: Index64, _measurement: &Measurement) -> Result<()> {
+        Ok(()) // Values come from the input layers
+    }
+
+    fn query(&self, idx: Index64) -> Option<f32> {
+        self.evaluate(idx)
+    }
+
+    /// Number of cells with a value; evaluates the expression domain
+    fn voxel_count(&self) -> usize {
+        self.indices().count()
+    }
+
+    fn indices(&self) -> Box<dyn Iterator<Item = Index64> + '_> {
+        if self.check().is_err() {
+            return Box::new(std::iter::empty());
+        }
+        match self.driver() {
+            Some(driver) => Box::new(
+                driver
+                    .indices()
+                    .filter(move |&idx| self.evaluate(idx).is_some()),
+            ),
+            // Constant expression: no cells to anchor it to
+            None => Box::new(std::iter::empty()),
+        }
+    }
+
+    /// No-op: a virtual layer has no storage of its own
+    fn clear(&mut self) {}
+
+    fn memory_usage(&self) -> usize {
+        std::mem::size_of::<Self>() + self.source.len() + 8 * self.inputs.len()
+    }
+}
+
+impl std::fmt::Debug for VirtualLayer<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VirtualLayer")
+            .field("expression", &self.source)
+            .field("variables", &self.names)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{cell, layer};
+
+    #[test]
+    fn test_expression_evaluation() -> Result<()> {
+        let a = layer(&[(0, 1.0), (2, 2.0), (4, -3.0)]);
+        let b = layer(&[(2, 0.5), (4, 4.0), (6, 1.0)]);
+        let eval = |src: &str, x: u16| -> Result<Option<f32>> {
+            Ok(VirtualLayer::parse(src)?
+                .bind("a", &a)
+                .bind("b", &b)
+                .evaluate(cell(x)))
+        };
+
+        assert_eq!(eval("a + b * 2", 2)?, Some(3.0));
+        assert_eq!(eval("(a + b) * 2", 2)?, Some(5.0));
+        assert_eq!(eval("-a - -1", 4)?, Some(4.0));
+        assert_eq!(
+            eval("abs(a) + max(a, b) - min(1e1, 2.5e-1)", 4)?,
+            Some(6.75)
+        );
+        assert_eq!(eval("a > 1 && b < 1", 2)?, Some(1.0));
+        assert_eq!(eval("a > 1 && b < 1", 4)?, Some(0.0));
+        assert_eq!(eval("!(a > 0) || b == 0.5", 2)?, Some(1.0));
+        // Missing input values
+        assert_eq!(eval("a + b", 0)?, None);
+        assert_eq!(eval("a", 0)?, Some(1.0));
+        assert_eq!(eval("3", 99)?, Some(3.0));
+
+        for bad in [
+            "a +",
+            "(a",
+            "a b",
+            "foo(a)",
+            "min(a)",
+            "a $ b",
+            "1.2.3",
+            "a < b < 1",
+        ] {
+            assert!(VirtualLayer::parse(bad).is_err(), "{}", bad);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_virtual_layer_domain() -> Result<()> {
+        let esdf = layer(&[(0, 0.2), (2, 0.3), (4, 2.0), (6, 0.1)]);
+        let occupancy = layer(&[(0, 0.9), (2, 0.1), (4, 0.95)]);
+        let danger = VirtualLayer::parse("esdf < 0.5 && occupancy > 0.7")?;
+        assert_eq!(danger.variables(), ["esdf", "occupancy"]);
+        assert!(danger.check().is_err());
+
+        let mut bindings = LayerBindings::new();
+        bindings.insert("esdf", &esdf);
+        bindings.insert("occupancy", &occupancy);
+        let danger = danger.bind_all(&bindings);
+        danger.check()?;
+
+        let mut domain: Vec<Index64> = danger.indices().collect();
+        domain.sort();
+        assert_eq!(domain, vec![cell(0), cell(2), cell(4)]);
+        assert_eq!(danger.voxel_count(), 3);
+        let hits: Vec<Index64> = danger.matching((0..8).map(cell)).collect();
+        assert_eq!(hits, vec![cell(0)]);
+
+        // Virtual layers compose
+        let scaled = VirtualLayer::parse("danger * 10")?.bind("danger", &danger);
+        assert_eq!(
+            scaled
+                .evaluate_region([cell(0), cell(6)])
+                .collect::<Vec<_>>(),
+            vec![(cell(0), 10.0)]
+        );
+        Ok(())
+    }
+}
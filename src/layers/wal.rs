@@ -0,0 +1,670 @@
+//! Write-ahead log for crash-consistent live maps
+//!
+//! [`WalMap`] wraps a [`LayeredMap`] kept in a directory:
+//!
+//! - `wal.log`: every integration call, appended before it is applied and
+//!   cut again if the layer rejects it
+//! - `checkpoint.oct`: a container holding the state and configuration of
+//!   every layer as of a log sequence number
+//!
+//! On [`WalMap::open`] the checkpoint is restored and the log records
+//! after it are replayed, so a crashed mapping process resumes where it
+//! stopped. Checkpoints run every [`WalConfig::checkpoint_every`] records
+//! or [`WalConfig::checkpoint_interval`], and on demand; each one truncates
+//! the log.
+//!
+//! Records are written to the OS as they are logged, so a process crash
+//! loses nothing. Surviving power loss additionally needs them on disk,
+//! which [`WalConfig::sync_every`] controls. A record torn by a crash
+//! mid-write fails its checksum and is dropped on open, together with
+//! anything after it.
+
+use super::measurement::MeasurementData;
+use super::{ConfigUpgrades, LayerConfig, LayerType, LayeredMap, Measurement, MeasurementType};
+use crate::clock::{Clock, WallClock};
+use crate::container::{ContainerReader, ContainerWriter};
+use crate::error::{Error, Result};
+use crate::Index64;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+const LOG_FILE: &str = "wal.log";
+const CHECKPOINT_FILE: &str = "checkpoint.oct";
+const CHECKPOINT_TMP: &str = "checkpoint.oct.tmp";
+const CHECKPOINT_MAGIC: &[u8; 8] = b"OCTAWAL1";
+/// Leading byte of checkpoint frames holding a [`LayerConfig`]; state
+/// frames lead with the layer tag instead
+const CONFIG_FRAME: u8 = 0xFF;
+/// Upper bound on one record's payload; larger lengths mean corruption
+const MAX_RECORD_LEN: u32 = 64;
+
+/// Settings for [`WalMap`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalConfig {
+    /// Checkpoint after this many logged records (0: no record limit)
+    pub checkpoint_every: usize,
+    /// Checkpoint when the last one is older than this, checked on each
+    /// logged record
+    pub checkpoint_interval: Option<Duration>,
+    /// `fsync` the log after this many records (0: only at checkpoints)
+    pub sync_every: usize,
+}
+
+impl Default for WalConfig {
+    fn default() -> Self {
+        Self {
+            checkpoint_every: 1_000_000,
+            checkpoint_interval: Some(Duration::from_secs(300)),
+            sync_every: 1024,
+        }
+    }
+}
+
+/// [`LayeredMap`] whose integration calls are logged for crash recovery
+///
+/// # Example
+///
+/// ```
+/// use octaindex3d::layers::{LayeredMap, Measurement, OccupancyLayer, WalConfig, WalMap};
+/// use octaindex3d::Index64;
+///
+/// # fn main() -> octaindex3d::Result<()> {
+/// # let dir = std::env::temp_dir().join(format!("oi3d-wal-doc-{}", std::process::id()));
+/// # let _ = std::fs::remove_dir_all(&dir);
+/// let template = || {
+///     let mut map = LayeredMap::new();
+///     map.add_occupancy_layer(OccupancyLayer::new());
+///     map
+/// };
+/// let idx = Index64::new(0, 0, 8, 10, 10, 10)?;
+/// {
+///     let mut live = WalMap::open(&dir, template(), WalConfig::default())?;
+///     live.update_occupancy(idx, &Measurement::occupied(0.9))?;
+///     // Dropped without a checkpoint, as in a crash
+/// }
+/// let live = WalMap::open(&dir, template(), WalConfig::default())?;
+/// assert_eq!(live.replayed(), 1);
+/// assert!(live.map().query_occupancy(idx).unwrap() > 0.5);
+/// # std::fs::remove_dir_all(&dir).ok();
+/// # Ok(())
+/// # }
+/// ```
+pub struct WalMap {
+    map: LayeredMap,
+    dir: PathBuf,
+    config: WalConfig,
+    log: File,
+    /// Length of the log holding only applied records
+    log_len: u64,
+    /// Sequence number of the last logged record
+    seq: u64,
+    /// Records logged since the last checkpoint
+    pending: usize,
+    /// Records written since the last fsync
+    unsynced: usize,
+    /// Clock time of the last checkpoint (seconds)
+    last_checkpoint: f64,
+    /// Time source of the checkpoint interval
+    clock: Arc<dyn Clock>,
+    replayed: usize,
+}
+
+impl WalMap {
+    /// Open or create a live map in `dir`
+    ///
+    /// `template` supplies the layers. A checkpoint, if present, replaces
+    /// each layer's parameters with the stored configuration (upgraded from
+    /// older schema versions) and its voxels with the stored state; it fails
+    /// if it holds the state of a layer missing from the template. Log
+    /// records after the checkpoint's sequence number are then replayed,
+    /// and a torn tail is cut from the log.
+    ///
+    /// Opening does not checkpoint: replayed records count as
+    /// [`pending`](Self::pending), and the checkpoint interval starts now.
+    pub fn open<P: AsRef<Path>>(
+        dir: P,
+        mut template: LayeredMap,
+        config: WalConfig,
+    ) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+
+        let mut seq = 0;
+        let checkpoint = dir.join(CHECKPOINT_FILE);
+        if checkpoint.exists() {
+            seq = restore_checkpoint(&checkpoint, &mut template)?;
+        }
+
+        let log_path = dir.join(LOG_FILE);
+        let mut replayed = 0;
+        let mut log_len = 0;
+        if log_path.exists() {
+            let (records, valid_len) = read_log(&log_path)?;
+            for record in records {
+                if record.seq <= seq {
+                    continue; // Already in the checkpoint
+                }
+                template.update(record.layer, record.idx, &record.measurement)?;
+                seq = record.seq;
+                replayed += 1;
+            }
+            // Drop a torn tail so new records follow the last good one
+            OpenOptions::new()
+                .write(true)
+                .open(&log_path)?
+                .set_len(valid_len)?;
+            log_len = valid_len;
+        }
+        let log = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&log_path)?;
+
+        let clock: Arc<dyn Clock> = Arc::new(WallClock::new());
+        Ok(Self {
+            map: template,
+            dir,
+            config,
+            log,
+            log_len,
+            seq,
+            pending: replayed,
+            unsynced: 0,
+            last_checkpoint: clock.now(),
+            clock,
+            replayed,
+        })
+    }
+
+    /// Measure [`WalConfig::checkpoint_interval`] on `clock` instead of the
+    /// wall clock, e.g. in simulated time
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.last_checkpoint = clock.now();
+        self.clock = clock;
+        self
+    }
+
+    /// Log and apply a measurement to a layer
+    pub fn update(
+        &mut self,
+        layer_type: LayerType,
+        idx: Index64,
+        measurement: &Measurement,
+    ) -> Result<()> {
+        if !self.map.has_layer(layer_type) {
+            return Err(Error::InvalidFormat(format!(
+                "{} layer not initialized",
+                layer_type.name()
+            )));
+        }
+        let record = Record {
+            seq: self.seq + 1,
+            layer: layer_type,
+            idx,
+            measurement: measurement.clone(),
+        };
+        let bytes = record.encode();
+        let applied = self
+            .log
+            .write_all(&bytes)
+            .map_err(Error::from)
+            .and_then(|()| self.map.update(layer_type, idx, measurement));
+        if let Err(err) = applied {
+            // Keep rejected or partly written records out of the log, so
+            // replay never meets a record the layer refuses
+            self.log.set_len(self.log_len)?;
+            return Err(err);
+        }
+        self.log_len += bytes.len() as u64;
+        self.seq += 1;
+        self.pending += 1;
+        self.unsynced += 1;
+        if self.config.sync_every > 0 && self.unsynced >= self.config.sync_every {
+            self.sync()?;
+        }
+
+        let by_count =
+            self.config.checkpoint_every > 0 && self.pending >= self.config.checkpoint_every;
+        let by_time = self.config.checkpoint_interval.is_some_and(|interval| {
+            self.clock.now() - self.last_checkpoint >= interval.as_secs_f64()
+        });
+        if by_count || by_time {
+            self.checkpoint()?;
+        }
+        Ok(())
+    }
+
+    /// Log and apply a TSDF measurement
+    pub fn update_tsdf(&mut self, idx: Index64, measurement: &Measurement) -> Result<()> {
+        self.update(LayerType::TSDF, idx, measurement)
+    }
+
+    /// Log and apply an occupancy measurement
+    pub fn update_occupancy(&mut self, idx: Index64, measurement: &Measurement) -> Result<()> {
+        self.update(LayerType::Occupancy, idx, measurement)
+    }
+
+    /// Write the state of every layer to the checkpoint and truncate the log
+    ///
+    /// The new checkpoint replaces the old one atomically, so a crash at
+    /// any point leaves a consistent checkpoint and log.
+    pub fn checkpoint(&mut self) -> Result<()> {
+        self.sync()?;
+        let tmp = self.dir.join(CHECKPOINT_TMP);
+        write_checkpoint(&tmp, &self.map, self.seq)?;
+        fs::rename(&tmp, self.dir.join(CHECKPOINT_FILE))?;
+        sync_dir(&self.dir);
+        // Records up to `seq` are skipped on replay even if this is lost
+        self.log.set_len(0)?;
+        self.log.sync_all()?;
+        self.log_len = 0;
+        self.pending = 0;
+        self.last_checkpoint = self.clock.now();
+        Ok(())
+    }
+
+    /// Flush logged records to disk
+    pub fn sync(&mut self) -> Result<()> {
+        if self.unsynced > 0 {
+            self.log.sync_data()?;
+            self.unsynced = 0;
+        }
+        Ok(())
+    }
+
+    /// The map as of the last logged record
+    pub fn map(&self) -> &LayeredMap {
+        &self.map
+    }
+
+    /// Checkpoint and return the map
+    pub fn into_map(mut self) -> Result<LayeredMap> {
+        self.checkpoint()?;
+        Ok(self.map)
+    }
+
+    /// Number of log records replayed by [`open`](Self::open)
+    pub fn replayed(&self) -> usize {
+        self.replayed
+    }
+
+    /// Number of records not yet covered by a checkpoint
+    pub fn pending(&self) -> usize {
+        self.pending
+    }
+
+    /// Sequence number of the last logged record
+    pub fn sequence(&self) -> u64 {
+        self.seq
+    }
+
+    /// Directory holding the log and checkpoint
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+}
+
+/// One logged integration call
+struct Record {
+    seq: u64,
+    layer: LayerType,
+    idx: Index64,
+    measurement: Measurement,
+}
+
+impl Record {
+    /// `[len u32][crc32 u32][payload]`, little endian
+    fn encode(&self) -> Vec<u8> {
+        let mut payload = Vec::with_capacity(40);
+        payload.extend_from_slice(&self.seq.to_le_bytes());
+        payload.push(self.layer.tag());
+        payload.extend_from_slice(&self.idx.raw().to_le_bytes());
+        payload.extend_from_slice(&self.measurement.confidence.to_le_bytes());
+        match &self.measurement.data {
+            MeasurementData::Depth { distance, normal } => {
+                payload.push(0);
+                payload.extend_from_slice(&distance.to_le_bytes());
+                payload.push(normal.is_some() as u8);
+                for n in normal.unwrap_or_default() {
+                    payload.extend_from_slice(&n.to_le_bytes());
+                }
+            }
+            MeasurementData::Occupancy { occupied } => {
+                payload.push(1);
+                payload.push(*occupied as u8);
+            }
+            MeasurementData::Color { r, g, b } => {
+                payload.push(2);
+                payload.extend_from_slice(&[*r, *g, *b]);
+            }
+            MeasurementData::Intensity { value } => {
+                payload.push(3);
+                payload.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+
+        let mut out = Vec::with_capacity(8 + payload.len());
+        out.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        out.extend_from_slice(&crc32fast::hash(&payload).to_le_bytes());
+        out.extend_from_slice(&payload);
+        out
+    }
+
+    fn decode(payload: &[u8]) -> Option<Self> {
+        let mut rest = payload;
+        let mut take = |n: usize| -> Option<&[u8]> {
+            let head = rest.get(..n)?;
+            rest = &rest[n..];
+            Some(head)
+        };
+        let seq = u64::from_le_bytes(take(8)?.try_into().ok()?);
+        let layer = LayerType::from_tag(take(1)?[0])?;
+        let idx = Index64::from_value(u64::from_le_bytes(take(8)?.try_into().ok()?)).ok()?;
+        let confidence = f32::from_le_bytes(take(4)?.try_into().ok()?);
+        let (measurement_type, data) = match take(1)?[0] {
+            0 => {
+                let distance = f32::from_le_bytes(take(4)?.try_into().ok()?);
+                let normal = match take(1)?[0] {
+                    0 => None,
+                    _ => {
+                        let mut n = [0f32; 3];
+                        for v in &mut n {
+                            *v = f32::from_le_bytes(take(4)?.try_into().ok()?);
+                        }
+                        Some(n)
+                    }
+                };
+                (
+                    MeasurementType::Depth,
+                    MeasurementData::Depth { distance, normal },
+                )
+            }
+            1 => (
+                MeasurementType::Occupancy,
+                MeasurementData::Occupancy {
+                    occupied: take(1)?[0] != 0,
+                },
+            ),
+            2 => {
+                let rgb = take(3)?;
+                (
+                    MeasurementType::Color,
+                    MeasurementData::Color {
+                        r: rgb[0],
+                        g: rgb[1],
+                        b: rgb[2],
+                    },
+                )
+            }
+            3 => (
+                MeasurementType::Intensity,
+                MeasurementData::Intensity {
+                    value: f32::from_le_bytes(take(4)?.try_into().ok()?),
+                },
+            ),
+            _ => return None,
+        };
+        Some(Self {
+            seq,
+            layer,
+            idx,
+            measurement: Measurement {
+                measurement_type,
+                data,
+                confidence,
+            },
+        })
+    }
+}
+
+fn read_log(path: &Path) -> Result<(Vec<Record>, u64)> {
+    let mut bytes = Vec::new();
+    File::open(path)?.read_to_end(&mut bytes)?;
+    let mut records = Vec::new();
+    let mut pos = 0usize;
+    while let Some(header) = bytes.get(pos..pos + 8) {
+        let len = u32::from_le_bytes(header[..4].try_into().unwrap());
+        let crc = u32::from_le_bytes(header[4..].try_into().unwrap());
+        if len > MAX_RECORD_LEN {
+            break;
+        }
+        let Some(payload) = bytes.get(pos + 8..pos + 8 + len as usize) else {
+            break;
+        };
+        if crc32fast::hash(payload) != crc {
+            break;
+        }
+        let Some(record) = Record::decode(payload) else {
+            break;
+        };
+        records.push(record);
+        pos += 8 + len as usize;
+    }
+    Ok((records, pos as u64))
+}
+
+fn write_checkpoint(path: &Path, map: &LayeredMap, seq: u64) -> Result<()> {
+    let mut bytes = Vec::new();
+    let mut writer = ContainerWriter::new(&mut bytes)?;
+    let mut header = CHECKPOINT_MAGIC.to_vec();
+    header.extend_from_slice(&seq.to_le_bytes());
+    writer.write_frame(&header)?;
+
+    let mut types = map.layer_types();
+    types.sort_by_key(LayerType::tag);
+    for layer_type in types {
+        let layer = map.layer(layer_type).expect("listed layer exists");
+        if let Some(config) = layer.config() {
+            let mut frame = vec![CONFIG_FRAME];
+            frame.extend_from_slice(&config.to_bytes());
+            writer.write_frame(&frame)?;
+        }
+        let state = layer.encode_state().ok_or_else(|| {
+            Error::InvalidFormat(format!(
+                "{} layer cannot be checkpointed",
+                layer_type.name()
+            ))
+        })?;
+        let mut frame = Vec::with_capacity(1 + state.len());
+        frame.push(layer_type.tag());
+        frame.extend_from_slice(&state);
+        writer.write_frame(&frame)?;
+    }
+    writer.finish()?;
+
+    let mut file = File::create(path)?;
+    file.write_all(&bytes)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+/// Restore layer states into `map`, returning the checkpoint's sequence number
+fn restore_checkpoint(path: &Path, map: &mut LayeredMap) -> Result<u64> {
+    let mut reader = ContainerReader::open(BufReader::new(File::open(path)?))?;
+    let header = reader.next_frame()?.unwrap_or_default();
+    if header.len() != 16 || &header[..8] != CHECKPOINT_MAGIC {
+        return Err(Error::InvalidFormat("not a WAL checkpoint".to_string()));
+    }
+    let seq = u64::from_le_bytes(header[8..].try_into().unwrap());
+    let upgrades = ConfigUpgrades::new();
+    while let Some(frame) = reader.next_frame()? {
+        let (&tag, state) = frame
+            .split_first()
+            .ok_or_else(|| Error::InvalidFormat("empty checkpoint frame".to_string()))?;
+        if tag == CONFIG_FRAME {
+            let config = LayerConfig::from_bytes(state)?;
+            if let Some(layer) = map.layer_mut(config.layer_type()) {
+                upgrades.apply(layer, config)?;
+            }
+            continue;
+        }
+        let layer_type = LayerType::from_tag(tag)
+            .ok_or_else(|| Error::InvalidFormat(format!("unknown layer tag {}", tag)))?;
+        let layer = map.layer_mut(layer_type).ok_or_else(|| {
+            Error::InvalidFormat(format!(
+                "checkpoint holds a {} layer the map does not have",
+                layer_type.name()
+            ))
+        })?;
+        layer.decode_state(state)?;
+    }
+    Ok(seq)
+}
+
+/// Persist a rename in `dir`; best effort where directories cannot be opened
+fn sync_dir(dir: &Path) {
+    if let Ok(handle) = File::open(dir) {
+        let _ = handle.sync_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layers::{OccupancyLayer, TSDFLayer};
+
+    fn template() -> LayeredMap {
+        let mut map = LayeredMap::new();
+        map.add_occupancy_layer(OccupancyLayer::new());
+        map.add_tsdf_layer(TSDFLayer::new(0.5));
+        map
+    }
+
+    fn cell(x: u16) -> Index64 {
+        Index64::new(0, 0, 8, x, x, x).unwrap()
+    }
+
+    fn manual() -> WalConfig {
+        WalConfig {
+            checkpoint_every: 0,
+            checkpoint_interval: None,
+            sync_every: 1,
+        }
+    }
+
+    fn state(map: &LayeredMap, layer: LayerType) -> Vec<u8> {
+        map.layer(layer).unwrap().encode_state().unwrap()
+    }
+
+    #[test]
+    fn test_recovery_after_checkpoint() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("oi3d-wal-{}-a", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let mut expected = template();
+        {
+            let mut live = WalMap::open(&dir, template(), manual())?;
+            for x in 0..20 {
+                let m = Measurement::occupied(0.7);
+                live.update_occupancy(cell(x), &m)?;
+                expected.update_occupancy(cell(x), &m)?;
+                if x == 9 {
+                    live.checkpoint()?;
+                }
+            }
+            let depth = Measurement::depth_with_normal(0.2, [0.0, 0.0, 1.0], 0.8);
+            live.update_tsdf(cell(3), &depth)?;
+            expected.update_tsdf(cell(3), &depth)?;
+            assert!(live.update(LayerType::ESDF, cell(1), &depth).is_err());
+            assert_eq!(live.pending(), 11);
+        }
+
+        let live = WalMap::open(&dir, template(), manual())?;
+        assert_eq!(live.replayed(), 11);
+        assert_eq!(live.sequence(), 21);
+        for layer in [LayerType::Occupancy, LayerType::TSDF] {
+            assert_eq!(state(live.map(), layer), state(&expected, layer));
+        }
+
+        // A clean shutdown leaves nothing to replay
+        live.into_map()?;
+        let live = WalMap::open(&dir, template(), manual())?;
+        assert_eq!(live.replayed(), 0);
+        assert_eq!(
+            state(live.map(), LayerType::TSDF),
+            state(&expected, LayerType::TSDF)
+        );
+        drop(live);
+
+        // Layer parameters come from the checkpoint, not the template
+        let mut other = LayeredMap::new();
+        other.add_occupancy_layer(OccupancyLayer::new());
+        other.add_tsdf_layer(TSDFLayer::new(0.1));
+        let live = WalMap::open(&dir, other, manual())?;
+        let tsdf = live.map().layer(LayerType::TSDF).unwrap().config().unwrap();
+        assert_eq!(tsdf.get("truncation_distance"), Some(0.5));
+        fs::remove_dir_all(&dir).ok();
+        Ok(())
+    }
+
+    #[test]
+    fn test_rejected_update_is_not_logged() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("oi3d-wal-{}-c", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        {
+            let mut live = WalMap::open(&dir, template(), manual())?;
+            live.update_occupancy(cell(1), &Measurement::occupied(0.9))?;
+            // The TSDF layer refuses occupancy measurements
+            assert!(live
+                .update_tsdf(cell(2), &Measurement::occupied(0.9))
+                .is_err());
+            live.update_occupancy(cell(3), &Measurement::free(0.8))?;
+            assert_eq!(live.sequence(), 2);
+        }
+        let live = WalMap::open(&dir, template(), manual())?;
+        assert_eq!(live.replayed(), 2);
+        assert!(live.map().query_occupancy(cell(3)).unwrap() < 0.5);
+        fs::remove_dir_all(&dir).ok();
+        Ok(())
+    }
+
+    #[test]
+    fn test_torn_tail_and_auto_checkpoint() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("oi3d-wal-{}-b", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let config = WalConfig {
+            checkpoint_every: 4,
+            ..manual()
+        };
+        {
+            let mut live = WalMap::open(&dir, template(), config.clone())?;
+            for x in 0..6 {
+                live.update_occupancy(cell(x), &Measurement::free(0.8))?;
+            }
+            // Records 1-4 were checkpointed automatically
+            assert_eq!(live.pending(), 2);
+        }
+        // Simulate a crash in the middle of writing a record
+        let log = dir.join(LOG_FILE);
+        let mut torn = fs::read(&log)?;
+        let record_len = torn.len() / 2;
+        let partial = torn[..record_len - 3].to_vec();
+        torn.extend(partial);
+        fs::write(&log, &torn)?;
+
+        let mut live = WalMap::open(&dir, template(), config.clone())?;
+        assert_eq!(live.replayed(), 2);
+        assert_eq!(fs::metadata(&log)?.len() as usize, 2 * record_len);
+        assert_eq!(
+            live.map()
+                .layer(LayerType::Occupancy)
+                .unwrap()
+                .voxel_count(),
+            6
+        );
+        live.update_occupancy(cell(40), &Measurement::occupied(0.9))?;
+        drop(live);
+
+        let live = WalMap::open(&dir, template(), config)?;
+        assert_eq!(live.replayed(), 3);
+        assert!(live.map().query_occupancy(cell(40)).unwrap() > 0.5);
+
+        // Layers in the checkpoint must exist in the template
+        assert!(WalMap::open(&dir, LayeredMap::new(), manual()).is_err());
+        fs::remove_dir_all(&dir).ok();
+        Ok(())
+    }
+}
@@ -15,7 +15,8 @@
 //! - **Hierarchical Refinement**: Multi-resolution support
 //! - **Bech32m Encoding**: Human-readable text encoding with checksums
 //! - **Compression**: LZ4 (default) and optional Zstd support
-//! - **Frame Registry**: Coordinate reference system management
+//! - **Frame Registry**: Coordinate reference system management, with WGS84
+//!   geodetic conversions
 //! - **Container Format**: Compressed spatial data storage
 //!
 //! ## Example
@@ -41,17 +42,37 @@
 //! # }
 //! ```
 
+pub mod analysis;
+pub mod cellset;
+pub mod clock;
 pub mod compression;
 pub mod container;
+pub mod coverer;
 pub mod error;
+pub mod events;
 pub mod frame;
+pub mod frustum;
+pub mod geodetic;
+pub mod geofence;
 pub mod grid;
 pub mod ids;
+pub mod inspection;
+pub mod landmarks;
 pub mod lattice;
 pub mod layers;
+pub mod mission;
 pub mod morton;
 pub mod neighbors;
 pub mod performance;
+pub mod placement;
+pub mod propagation;
+pub mod render;
+pub mod routing;
+pub mod streaming;
+pub mod survey;
+pub mod synthetic;
+pub mod topology;
+pub mod visibility;
 
 // v0.3.1 modules (feature-gated)
 #[cfg(feature = "hilbert")]
@@ -60,9 +81,27 @@ pub mod hilbert;
 #[cfg(feature = "container_v2")]
 pub mod container_v2;
 
+#[cfg(feature = "container_v2")]
+pub mod remote;
+
 #[cfg(feature = "gis_geojson")]
 pub mod geojson;
 
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+#[cfg(feature = "datafusion")]
+pub mod datafusion;
+
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(test)]
+mod test_fixtures;
+
 // Legacy v0.2 modules (deprecated, kept for compatibility)
 pub mod id;
 #[cfg(feature = "serde")]
@@ -71,8 +110,9 @@ pub mod layer;
 pub mod path;
 
 // Re-export commonly used types
+pub use crate::cellset::CellSet;
 pub use crate::error::{Error, Result};
-pub use crate::frame::{get_frame, list_frames, register_frame, FrameDescriptor};
+pub use crate::frame::{get_frame, get_frame_at, list_frames, register_frame, FrameDescriptor};
 pub use crate::grid::{BccGrid, GridPath};
 pub use crate::ids::{FrameId, Galactic128, Index64, Route64};
 pub use crate::lattice::{Lattice, LatticeCoord, Parity, BCC_NEIGHBORS_14};
@@ -94,8 +134,13 @@ pub use crate::performance::{GpuBackend, GpuBatchProcessor};
 #[cfg(feature = "hilbert")]
 pub use crate::hilbert::Hilbert64;
 
+#[cfg(feature = "mmap")]
+pub use crate::container_v2::ContainerReaderMmap;
 #[cfg(feature = "container_v2")]
-pub use crate::container_v2::{ContainerWriterV2, HeaderV2, StreamConfig};
+pub use crate::container_v2::{
+    BlockIndex, BlockOrder, CellChunking, ContainerReaderV2, ContainerWriterV2, HeaderV2,
+    MetadataValue, StreamConfig,
+};
 
 #[cfg(feature = "gis_geojson")]
 pub use crate::geojson::{
@@ -0,0 +1,873 @@
+//! Missions: waypoint sequences for autopilots
+//!
+//! A [`Mission`] is an ordered list of [`Waypoint`]s in a local East-North-Up
+//! frame anchored at a geodetic home position, with per-waypoint actions
+//! such as speed changes or image capture. Missions bridge planner output to
+//! real vehicles:
+//!
+//! - Build from a planned path with [`Mission::from_grid_path`]
+//! - Check against the map with [`Mission::validate`] (obstacle clearance
+//!   and reachability between consecutive waypoints)
+//! - Exchange with QGroundControl and MAVLink autopilots as `.plan` JSON via
+//!   [`Mission::to_plan_json`] / [`Mission::from_plan_json`] (`serde` feature)
+//!
+//! Local positions are converted to latitude/longitude with a local tangent
+//! plane approximation around home, which is accurate to centimeters over
+//! the few-kilometer extent of a typical mission.
+//!
+//! # Example
+//!
+//! ```
+//! use octaindex3d::mission::{GeoPoint, Mission, MissionAction, Waypoint};
+//!
+//! let home = GeoPoint::new(47.397742, 8.545594, 488.0);
+//! let mut mission = Mission::new(home);
+//! mission.push(Waypoint::takeoff(10.0));
+//! mission.push(Waypoint::new((20.0, 0.0, 10.0)).with_action(MissionAction::CaptureImage));
+//! mission.push(Waypoint::land((20.0, 15.0, 0.0)));
+//!
+//! assert_eq!(mission.len(), 3);
+//! ```
+
+use crate::error::{Error, Result};
+use crate::grid::{BccGrid, GridPath};
+use crate::layers::{OccupancyLayer, OccupancyState};
+use crate::routing::{astar_with_config, PredicateCost, SearchConfig};
+use crate::{Index64, Route64};
+use rustc_hash::FxHashMap;
+use std::cell::RefCell;
+
+/// WGS84 equatorial radius (meters), used for the tangent plane approximation
+const EARTH_RADIUS: f64 = 6_378_137.0;
+
+/// Geodetic position
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GeoPoint {
+    /// Latitude in degrees
+    pub latitude: f64,
+    /// Longitude in degrees
+    pub longitude: f64,
+    /// Altitude above mean sea level in meters
+    pub altitude: f64,
+}
+
+impl GeoPoint {
+    /// Create a geodetic position
+    pub fn new(latitude: f64, longitude: f64, altitude: f64) -> Self {
+        Self {
+            latitude,
+            longitude,
+            altitude,
+        }
+    }
+
+    /// Geodetic position of a local East-North-Up offset from `self`
+    ///
+    /// The altitude is `self.altitude + up`.
+    pub fn offset_enu(&self, east: f64, north: f64, up: f64) -> Self {
+        let lat0 = self.latitude.to_radians();
+        Self {
+            latitude: self.latitude + (north / EARTH_RADIUS).to_degrees(),
+            longitude: self.longitude + (east / (EARTH_RADIUS * lat0.cos())).to_degrees(),
+            altitude: self.altitude + up,
+        }
+    }
+
+    /// Local East-North-Up offset of `other` from `self`
+    ///
+    /// Inverse of [`GeoPoint::offset_enu`].
+    pub fn enu_to(&self, other: &GeoPoint) -> (f64, f64, f64) {
+        let lat0 = self.latitude.to_radians();
+        (
+            (other.longitude - self.longitude).to_radians() * EARTH_RADIUS * lat0.cos(),
+            (other.latitude - self.latitude).to_radians() * EARTH_RADIUS,
+            other.altitude - self.altitude,
+        )
+    }
+}
+
+/// What the vehicle does at a waypoint
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WaypointKind {
+    /// Fly to the position, optionally holding there
+    Waypoint {
+        /// Hold time at the waypoint (seconds)
+        hold_seconds: f32,
+    },
+    /// Take off and climb to the waypoint altitude
+    Takeoff,
+    /// Land at the waypoint
+    Land,
+    /// Loiter at the position for a fixed time
+    Loiter {
+        /// Loiter time (seconds)
+        seconds: f32,
+    },
+    /// Return to the launch position (the waypoint position is ignored)
+    ReturnToLaunch,
+}
+
+/// Action performed after reaching a waypoint
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MissionAction {
+    /// Change the ground speed (m/s) for the following legs
+    ChangeSpeed {
+        /// New speed (m/s)
+        speed: f32,
+    },
+    /// Capture a single image
+    CaptureImage,
+}
+
+/// A mission waypoint
+#[derive(Debug, Clone, PartialEq)]
+pub struct Waypoint {
+    /// Position in the local ENU frame (meters; z is height above home)
+    pub position: (f64, f64, f64),
+    /// What the vehicle does at this waypoint
+    pub kind: WaypointKind,
+    /// Radius within which the waypoint counts as reached (meters)
+    pub acceptance_radius: f32,
+    /// Actions performed once the waypoint is reached
+    pub actions: Vec<MissionAction>,
+}
+
+impl Waypoint {
+    /// Fly-through waypoint at a local position
+    pub fn new(position: (f64, f64, f64)) -> Self {
+        Self {
+            position,
+            kind: WaypointKind::Waypoint { hold_seconds: 0.0 },
+            acceptance_radius: 1.0,
+            actions: Vec::new(),
+        }
+    }
+
+    /// Take off above home to `altitude` meters
+    pub fn takeoff(altitude: f64) -> Self {
+        Self {
+            kind: WaypointKind::Takeoff,
+            ..Self::new((0.0, 0.0, altitude))
+        }
+    }
+
+    /// Land at a local position
+    pub fn land(position: (f64, f64, f64)) -> Self {
+        Self {
+            kind: WaypointKind::Land,
+            ..Self::new(position)
+        }
+    }
+
+    /// Return to launch
+    pub fn return_to_launch() -> Self {
+        Self {
+            kind: WaypointKind::ReturnToLaunch,
+            ..Self::new((0.0, 0.0, 0.0))
+        }
+    }
+
+    /// Set the waypoint kind
+    pub fn with_kind(mut self, kind: WaypointKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Append an action
+    pub fn with_action(mut self, action: MissionAction) -> Self {
+        self.actions.push(action);
+        self
+    }
+
+    /// Whether the waypoint has a meaningful position
+    pub fn has_position(&self) -> bool {
+        self.kind != WaypointKind::ReturnToLaunch
+    }
+}
+
+/// An ordered waypoint sequence anchored at a home position
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mission {
+    /// Geodetic origin of the local frame (and launch position)
+    pub home: GeoPoint,
+    /// Waypoints in flight order
+    pub waypoints: Vec<Waypoint>,
+    /// Cruise speed for fixed-wing vehicles (m/s)
+    pub cruise_speed: f32,
+    /// Hover speed for multicopters (m/s)
+    pub hover_speed: f32,
+}
+
+impl Mission {
+    /// Create an empty mission
+    pub fn new(home: GeoPoint) -> Self {
+        Self {
+            home,
+            waypoints: Vec::new(),
+            cruise_speed: 15.0, // QGroundControl defaults
+            hover_speed: 5.0,
+        }
+    }
+
+    /// Build a mission flying through every cell of a planned path
+    ///
+    /// Each cell center becomes a fly-through waypoint. Collinear runs are
+    /// merged so only the turning points remain.
+    pub fn from_grid_path(home: GeoPoint, grid: &BccGrid, path: &GridPath) -> Self {
+        let mut mission = Self::new(home);
+        let cells = &path.cells;
+        for (i, &cell) in cells.iter().enumerate() {
+            let interior = i > 0 && i + 1 < cells.len();
+            if interior && is_collinear(cells[i - 1], cell, cells[i + 1]) {
+                continue;
+            }
+            mission.push(Waypoint::new(grid.center_of(cell)));
+        }
+        mission
+    }
+
+    /// Append a waypoint
+    pub fn push(&mut self, waypoint: Waypoint) {
+        self.waypoints.push(waypoint);
+    }
+
+    /// Number of waypoints
+    pub fn len(&self) -> usize {
+        self.waypoints.len()
+    }
+
+    /// Check if the mission has no waypoints
+    pub fn is_empty(&self) -> bool {
+        self.waypoints.is_empty()
+    }
+
+    /// Geodetic position of a waypoint
+    pub fn geodetic(&self, waypoint: &Waypoint) -> GeoPoint {
+        let (east, north, up) = waypoint.position;
+        self.home.offset_enu(east, north, up)
+    }
+
+    /// Check the mission against an occupancy map
+    ///
+    /// Reports every waypoint that lies inside an obstacle or closer than
+    /// the configured clearance to one, and every leg whose endpoints cannot
+    /// be connected by a path keeping that clearance. An empty result means
+    /// the mission passed. Waypoints without a position
+    /// ([`WaypointKind::ReturnToLaunch`]) are skipped.
+    pub fn validate(
+        &self,
+        occupancy: &OccupancyLayer,
+        config: &MissionValidationConfig,
+    ) -> Result<Vec<MissionIssue>> {
+        let grid = BccGrid::new(2.0 * config.voxel_size)?; // Lattice unit == voxel
+        let checker = ClearanceChecker::new(occupancy, config);
+        let mut issues = Vec::new();
+
+        let mut previous: Option<(usize, Route64)> = None;
+        for (index, waypoint) in self.waypoints.iter().enumerate() {
+            if !waypoint.has_position() {
+                previous = None;
+                continue;
+            }
+            let (x, y, z) = waypoint.position;
+            let Ok(cell) = grid.cell_at(x, y, z) else {
+                issues.push(MissionIssue::OutsideMap { index });
+                previous = None;
+                continue;
+            };
+
+            match checker.nearest_obstacle(cell) {
+                Some(0.0) => issues.push(MissionIssue::InsideObstacle { index }),
+                Some(distance) => issues.push(MissionIssue::InsufficientClearance {
+                    index,
+                    clearance: distance,
+                }),
+                None => {}
+            }
+
+            if config.check_reachability {
+                if let Some((from, from_cell)) = previous {
+                    let cost = PredicateCost::new(|c: Route64| {
+                        c == from_cell || c == cell || checker.is_clear(c)
+                    });
+                    let search = SearchConfig {
+                        max_expansions: config.max_expansions,
+                        ..Default::default()
+                    };
+                    match astar_with_config(from_cell, cell, &cost, &search) {
+                        Ok(_) => {}
+                        Err(Error::NoPathFound { .. }) | Err(Error::SearchLimitExceeded { .. }) => {
+                            issues.push(MissionIssue::Unreachable { from, to: index })
+                        }
+                        Err(e) => return Err(e),
+                    }
+                }
+            }
+            previous = Some((index, cell));
+        }
+
+        Ok(issues)
+    }
+}
+
+/// Settings for [`Mission::validate`]
+#[derive(Debug, Clone)]
+pub struct MissionValidationConfig {
+    /// Voxel size of the occupancy map (meters)
+    pub voxel_size: f64,
+    /// Minimum distance to any occupied voxel (meters)
+    pub min_clearance: f64,
+    /// Treat unknown space as traversable when checking reachability
+    pub allow_unknown: bool,
+    /// Check that consecutive waypoints can be connected
+    pub check_reachability: bool,
+    /// Node expansion limit for each reachability search
+    pub max_expansions: usize,
+}
+
+impl Default for MissionValidationConfig {
+    fn default() -> Self {
+        Self {
+            voxel_size: 0.1,
+            min_clearance: 0.5,
+            allow_unknown: true,
+            check_reachability: true,
+            max_expansions: 100_000,
+        }
+    }
+}
+
+/// A problem found by [`Mission::validate`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum MissionIssue {
+    /// The waypoint is inside an occupied voxel
+    InsideObstacle {
+        /// Waypoint index
+        index: usize,
+    },
+    /// An occupied voxel is closer than the required clearance
+    InsufficientClearance {
+        /// Waypoint index
+        index: usize,
+        /// Distance to the nearest occupied voxel (meters)
+        clearance: f64,
+    },
+    /// No path with the required clearance connects two waypoints
+    Unreachable {
+        /// Index of the leg's first waypoint
+        from: usize,
+        /// Index of the leg's last waypoint
+        to: usize,
+    },
+    /// The waypoint lies outside the representable map extent
+    OutsideMap {
+        /// Waypoint index
+        index: usize,
+    },
+}
+
+/// Clearance queries against an occupancy layer, cached per cell
+struct ClearanceChecker<'a> {
+    occupancy: &'a OccupancyLayer,
+    voxel_size: f64,
+    radius: f64,
+    allow_unknown: bool,
+    cache: RefCell<FxHashMap<Route64, bool>>,
+}
+
+impl<'a> ClearanceChecker<'a> {
+    fn new(occupancy: &'a OccupancyLayer, config: &MissionValidationConfig) -> Self {
+        Self {
+            occupancy,
+            voxel_size: config.voxel_size,
+            radius: config.min_clearance.max(0.0),
+            allow_unknown: config.allow_unknown,
+            cache: RefCell::new(FxHashMap::default()),
+        }
+    }
+
+    fn state(&self, x: i32, y: i32, z: i32) -> OccupancyState {
+        let in_range = |v: i32| (0..=u16::MAX as i32).contains(&v);
+        if !(in_range(x) && in_range(y) && in_range(z)) {
+            return OccupancyState::Unknown;
+        }
+        Index64::new(0, 0, 5, x as u16, y as u16, z as u16)
+            .map(|idx| self.occupancy.get_state(idx))
+            .unwrap_or(OccupancyState::Unknown)
+    }
+
+    /// Distance (meters) to the nearest occupied voxel within the clearance
+    /// radius, if any
+    fn nearest_obstacle(&self, cell: Route64) -> Option<f64> {
+        let reach = (self.radius / self.voxel_size).ceil() as i32;
+        let mut nearest: Option<f64> = None;
+        for dx in -reach..=reach {
+            for dy in -reach..=reach {
+                for dz in -reach..=reach {
+                    // Only BCC lattice points hold voxels: all offsets even
+                    // or all odd
+                    if (dx - dy) % 2 != 0 || (dy - dz) % 2 != 0 {
+                        continue;
+                    }
+                    let distance = ((dx * dx + dy * dy + dz * dz) as f64).sqrt() * self.voxel_size;
+                    if distance > self.radius || nearest.is_some_and(|n| n <= distance) {
+                        continue;
+                    }
+                    let state = self.state(cell.x() + dx, cell.y() + dy, cell.z() + dz);
+                    if state == OccupancyState::Occupied {
+                        nearest = Some(distance);
+                    }
+                }
+            }
+        }
+        nearest
+    }
+
+    fn is_clear(&self, cell: Route64) -> bool {
+        if let Some(&clear) = self.cache.borrow().get(&cell) {
+            return clear;
+        }
+        let clear = (self.allow_unknown
+            || self.state(cell.x(), cell.y(), cell.z()) == OccupancyState::Free)
+            && self.nearest_obstacle(cell).is_none();
+        self.cache.borrow_mut().insert(cell, clear);
+        clear
+    }
+}
+
+/// Whether `b` lies on the straight continuation from `a` to `c`
+fn is_collinear(a: Route64, b: Route64, c: Route64) -> bool {
+    (b.x() - a.x(), b.y() - a.y(), b.z() - a.z()) == (c.x() - b.x(), c.y() - b.y(), c.z() - b.z())
+}
+
+#[cfg(feature = "serde")]
+mod plan {
+    //! QGroundControl `.plan` (JSON) import and export
+
+    use super::*;
+    use serde_json::{json, Value};
+
+    // MAVLink MAV_CMD identifiers
+    const NAV_WAYPOINT: u64 = 16;
+    const NAV_LOITER_TIME: u64 = 19;
+    const NAV_RETURN_TO_LAUNCH: u64 = 20;
+    const NAV_LAND: u64 = 21;
+    const NAV_TAKEOFF: u64 = 22;
+    const DO_CHANGE_SPEED: u64 = 178;
+    const IMAGE_START_CAPTURE: u64 = 2000;
+
+    // MAVLink MAV_FRAME identifiers
+    const FRAME_GLOBAL: u64 = 0;
+    const FRAME_MISSION: u64 = 2;
+    const FRAME_GLOBAL_RELATIVE_ALT: u64 = 3;
+
+    impl Mission {
+        /// Export as a QGroundControl `.plan` JSON document
+        ///
+        /// Waypoint altitudes are written relative to home
+        /// (`MAV_FRAME_GLOBAL_RELATIVE_ALT`).
+        pub fn to_plan_json(&self) -> Result<String> {
+            let mut items = Vec::new();
+            let mut next_id = 1u64;
+            let mut push = |command: u64, frame: u64, params: [Value; 7], altitude: Option<f64>| {
+                let mut item = json!({
+                    "type": "SimpleItem",
+                    "autoContinue": true,
+                    "command": command,
+                    "doJumpId": next_id,
+                    "frame": frame,
+                    "params": params,
+                });
+                if let Some(altitude) = altitude {
+                    item["Altitude"] = json!(altitude);
+                    item["AltitudeMode"] = json!(1); // Relative to home
+                    item["AMSLAltAboveTerrain"] = Value::Null;
+                }
+                items.push(item);
+                next_id += 1;
+            };
+
+            for waypoint in &self.waypoints {
+                let geo = self.geodetic(waypoint);
+                let alt = waypoint.position.2;
+                let position = |p1: f64, p2: f64| -> [Value; 7] {
+                    [
+                        json!(p1),
+                        json!(p2),
+                        json!(0),
+                        Value::Null, // Yaw: keep current heading
+                        json!(geo.latitude),
+                        json!(geo.longitude),
+                        json!(alt),
+                    ]
+                };
+                let radius = waypoint.acceptance_radius as f64;
+
+                match waypoint.kind {
+                    WaypointKind::Waypoint { hold_seconds } => push(
+                        NAV_WAYPOINT,
+                        FRAME_GLOBAL_RELATIVE_ALT,
+                        position(hold_seconds as f64, radius),
+                        Some(alt),
+                    ),
+                    WaypointKind::Takeoff => push(
+                        NAV_TAKEOFF,
+                        FRAME_GLOBAL_RELATIVE_ALT,
+                        position(0.0, 0.0),
+                        Some(alt),
+                    ),
+                    WaypointKind::Land => push(
+                        NAV_LAND,
+                        FRAME_GLOBAL_RELATIVE_ALT,
+                        position(0.0, 0.0),
+                        Some(alt),
+                    ),
+                    WaypointKind::Loiter { seconds } => push(
+                        NAV_LOITER_TIME,
+                        FRAME_GLOBAL_RELATIVE_ALT,
+                        position(seconds as f64, 0.0),
+                        Some(alt),
+                    ),
+                    WaypointKind::ReturnToLaunch => push(
+                        NAV_RETURN_TO_LAUNCH,
+                        FRAME_MISSION,
+                        std::array::from_fn(|_| json!(0)),
+                        None,
+                    ),
+                }
+
+                for action in &waypoint.actions {
+                    match *action {
+                        MissionAction::ChangeSpeed { speed } => push(
+                            DO_CHANGE_SPEED,
+                            FRAME_MISSION,
+                            [
+                                json!(1), // Ground speed
+                                json!(speed),
+                                json!(-1), // Throttle unchanged
+                                json!(0),
+                                json!(0),
+                                json!(0),
+                                json!(0),
+                            ],
+                            None,
+                        ),
+                        MissionAction::CaptureImage => push(
+                            IMAGE_START_CAPTURE,
+                            FRAME_MISSION,
+                            [
+                                json!(0),
+                                json!(0), // No interval
+                                json!(1), // One image
+                                json!(0),
+                                json!(0),
+                                json!(0),
+                                json!(0),
+                            ],
+                            None,
+                        ),
+                    }
+                }
+            }
+
+            let plan = json!({
+                "fileType": "Plan",
+                "version": 1,
+                "groundStation": "OctaIndex3D",
+                "geoFence": { "circles": [], "polygons": [], "version": 2 },
+                "rallyPoints": { "points": [], "version": 2 },
+                "mission": {
+                    "version": 2,
+                    "firmwareType": 12, // PX4
+                    "vehicleType": 2,   // Quadrotor
+                    "globalPlanAltitudeMode": 1,
+                    "cruiseSpeed": self.cruise_speed,
+                    "hoverSpeed": self.hover_speed,
+                    "plannedHomePosition": [
+                        self.home.latitude,
+                        self.home.longitude,
+                        self.home.altitude
+                    ],
+                    "items": items,
+                },
+            });
+
+            serde_json::to_string_pretty(&plan).map_err(|e| Error::Codec(e.to_string()))
+        }
+
+        /// Import a QGroundControl `.plan` JSON document
+        ///
+        /// Supports the navigation and action commands produced by
+        /// [`Mission::to_plan_json`] in relative or absolute altitude frames.
+        /// Action items are attached to the preceding waypoint.
+        pub fn from_plan_json(json: &str) -> Result<Self> {
+            let plan: Value =
+                serde_json::from_str(json).map_err(|e| Error::InvalidFormat(e.to_string()))?;
+            if plan["fileType"] != "Plan" {
+                return Err(Error::InvalidFormat(
+                    "Not a QGroundControl plan".to_string(),
+                ));
+            }
+            let mission = &plan["mission"];
+
+            let home = mission["plannedHomePosition"]
+                .as_array()
+                .filter(|h| h.len() == 3)
+                .and_then(|h| {
+                    Some(GeoPoint::new(
+                        h[0].as_f64()?,
+                        h[1].as_f64()?,
+                        h[2].as_f64()?,
+                    ))
+                })
+                .ok_or_else(|| {
+                    Error::InvalidFormat("Plan is missing plannedHomePosition".to_string())
+                })?;
+
+            let mut result = Mission::new(home);
+            if let Some(speed) = mission["cruiseSpeed"].as_f64() {
+                result.cruise_speed = speed as f32;
+            }
+            if let Some(speed) = mission["hoverSpeed"].as_f64() {
+                result.hover_speed = speed as f32;
+            }
+
+            let items = mission["items"]
+                .as_array()
+                .ok_or_else(|| Error::InvalidFormat("Plan has no mission items".to_string()))?;
+
+            for (i, item) in items.iter().enumerate() {
+                if item["type"] != "SimpleItem" {
+                    return Err(Error::InvalidFormat(format!(
+                        "Mission item {}: unsupported item type {}",
+                        i, item["type"]
+                    )));
+                }
+                let command = item["command"].as_u64().unwrap_or(u64::MAX);
+                let frame = item["frame"].as_u64().unwrap_or(FRAME_MISSION);
+                let params: Vec<f64> = item["params"]
+                    .as_array()
+                    .map(|p| p.iter().map(|v| v.as_f64().unwrap_or(f64::NAN)).collect())
+                    .unwrap_or_default();
+                if params.len() != 7 {
+                    return Err(Error::InvalidFormat(format!(
+                        "Mission item {}: expected 7 params",
+                        i
+                    )));
+                }
+
+                let local = || -> Result<(f64, f64, f64)> {
+                    let altitude = match frame {
+                        FRAME_GLOBAL_RELATIVE_ALT => home.altitude + params[6],
+                        FRAME_GLOBAL => params[6],
+                        other => {
+                            return Err(Error::InvalidFormat(format!(
+                                "Mission item {}: unsupported frame {}",
+                                i, other
+                            )))
+                        }
+                    };
+                    Ok(home.enu_to(&GeoPoint::new(params[4], params[5], altitude)))
+                };
+
+                let action = match command {
+                    NAV_WAYPOINT => {
+                        let mut waypoint =
+                            Waypoint::new(local()?).with_kind(WaypointKind::Waypoint {
+                                hold_seconds: params[0] as f32,
+                            });
+                        if params[1] > 0.0 {
+                            waypoint.acceptance_radius = params[1] as f32;
+                        }
+                        result.push(waypoint);
+                        None
+                    }
+                    NAV_TAKEOFF => {
+                        result.push(Waypoint::new(local()?).with_kind(WaypointKind::Takeoff));
+                        None
+                    }
+                    NAV_LAND => {
+                        result.push(Waypoint::land(local()?));
+                        None
+                    }
+                    NAV_LOITER_TIME => {
+                        result.push(Waypoint::new(local()?).with_kind(WaypointKind::Loiter {
+                            seconds: params[0] as f32,
+                        }));
+                        None
+                    }
+                    NAV_RETURN_TO_LAUNCH => {
+                        result.push(Waypoint::return_to_launch());
+                        None
+                    }
+                    DO_CHANGE_SPEED => Some(MissionAction::ChangeSpeed {
+                        speed: params[1] as f32,
+                    }),
+                    IMAGE_START_CAPTURE => Some(MissionAction::CaptureImage),
+                    other => {
+                        return Err(Error::InvalidFormat(format!(
+                            "Mission item {}: unsupported command {}",
+                            i, other
+                        )))
+                    }
+                };
+
+                if let Some(action) = action {
+                    result
+                        .waypoints
+                        .last_mut()
+                        .ok_or_else(|| {
+                            Error::InvalidFormat(format!(
+                                "Mission item {}: action before the first waypoint",
+                                i
+                            ))
+                        })?
+                        .actions
+                        .push(action);
+                }
+            }
+
+            Ok(result)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn home() -> GeoPoint {
+        GeoPoint::new(47.397742, 8.545594, 488.0)
+    }
+
+    #[test]
+    fn test_enu_roundtrip() {
+        let home = home();
+        let point = home.offset_enu(120.0, -45.0, 30.0);
+        let (e, n, u) = home.enu_to(&point);
+        assert!((e - 120.0).abs() < 1e-6);
+        assert!((n + 45.0).abs() < 1e-6);
+        assert!((u - 30.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_from_grid_path_merges_collinear_cells() -> Result<()> {
+        let grid = BccGrid::new(1.0)?;
+        let start = grid.cell_at(0.0, 0.0, 0.0)?;
+        let goal = grid.cell_at(5.0, 0.0, 0.0)?;
+        let path = grid.astar(start, goal)?;
+
+        let mission = Mission::from_grid_path(home(), &grid, &path);
+        assert_eq!(mission.len(), 2);
+        assert_eq!(mission.waypoints[1].position, grid.center_of(goal));
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_clearance_and_reachability() -> Result<()> {
+        let voxel = 0.1;
+        let mut occupancy = OccupancyLayer::new();
+        // Solid wall at x = 2.0 m spanning the whole region
+        for y in 0..=60u16 {
+            for z in 0..=60u16 {
+                for x in [20u16, 21] {
+                    occupancy.update_occupancy(Index64::new(0, 0, 5, x, y, z)?, true, 0.95);
+                }
+            }
+        }
+
+        let config = MissionValidationConfig {
+            voxel_size: voxel,
+            min_clearance: 0.3,
+            allow_unknown: true,
+            check_reachability: true,
+            max_expansions: 20_000,
+        };
+
+        let mut mission = Mission::new(home());
+        mission.push(Waypoint::new((1.0, 3.0, 3.0)));
+        mission.push(Waypoint::new((1.2, 3.0, 4.0)));
+        mission.push(Waypoint::new((1.8, 3.0, 3.0))); // 0.2 m from the wall
+        mission.push(Waypoint::new((2.0, 3.0, 3.0))); // Inside the wall
+
+        let issues = mission.validate(&occupancy, &config)?;
+        assert!(issues.contains(&MissionIssue::InsufficientClearance {
+            index: 2,
+            clearance: 0.2
+        }));
+        assert!(issues.contains(&MissionIssue::InsideObstacle { index: 3 }));
+        assert!(!issues
+            .iter()
+            .any(|i| matches!(i, MissionIssue::Unreachable { from: 0, to: 1 })));
+        Ok(())
+    }
+
+    #[test]
+    fn test_clearance_sees_diagonal_neighbours() -> Result<()> {
+        let config = MissionValidationConfig {
+            voxel_size: 0.1,
+            min_clearance: 0.3,
+            check_reachability: false,
+            ..MissionValidationConfig::default()
+        };
+        let grid = BccGrid::new(2.0 * config.voxel_size)?;
+        let cell = grid.cell_at(1.0, 1.0, 1.0)?;
+        let (x, y, z) = (cell.x() as u16, cell.y() as u16, cell.z() as u16);
+
+        // Obstacles on lattice points only: a body-diagonal neighbour
+        // (0.173 m) and an axis neighbour two units away (0.2 m)
+        let mut occupancy = OccupancyLayer::new();
+        occupancy.update_occupancy(Index64::new(0, 0, 5, x + 1, y + 1, z - 1)?, true, 0.95);
+        occupancy.update_occupancy(Index64::new(0, 0, 5, x, y + 2, z)?, true, 0.95);
+
+        let mut mission = Mission::new(home());
+        mission.push(Waypoint::new(grid.center_of(cell)));
+        let issues = mission.validate(&occupancy, &config)?;
+        let [MissionIssue::InsufficientClearance {
+            index: 0,
+            clearance,
+        }] = issues[..]
+        else {
+            panic!("expected one clearance issue, got {issues:?}");
+        };
+        assert!((clearance - 3f64.sqrt() * 0.1).abs() < 1e-9);
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_plan_roundtrip() -> Result<()> {
+        let mut mission = Mission::new(home());
+        mission.push(Waypoint::takeoff(10.0));
+        mission.push(
+            Waypoint::new((25.0, -10.0, 15.0))
+                .with_action(MissionAction::ChangeSpeed { speed: 3.0 })
+                .with_action(MissionAction::CaptureImage),
+        );
+        mission.push(
+            Waypoint::new((25.0, 20.0, 15.0)).with_kind(WaypointKind::Loiter { seconds: 5.0 }),
+        );
+        mission.push(Waypoint::return_to_launch());
+
+        let json = mission.to_plan_json()?;
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed["mission"]["items"].as_array().unwrap().len(), 6);
+        assert_eq!(parsed["mission"]["items"][0]["command"], 22);
+
+        let restored = Mission::from_plan_json(&json)?;
+        assert_eq!(restored.home, mission.home);
+        assert_eq!(restored.len(), mission.len());
+        for (a, b) in restored.waypoints.iter().zip(&mission.waypoints) {
+            assert_eq!(a.kind, b.kind);
+            assert_eq!(a.actions, b.actions);
+            if b.has_position() {
+                assert!((a.position.0 - b.position.0).abs() < 1e-6);
+                assert!((a.position.1 - b.position.1).abs() < 1e-6);
+                assert!((a.position.2 - b.position.2).abs() < 1e-6);
+            }
+        }
+
+        assert!(Mission::from_plan_json("{\"fileType\": \"Other\"}").is_err());
+        Ok(())
+    }
+}
@@ -2,6 +2,11 @@
 //!
 //! Implements efficient interleaving of 3D coordinates into a single 64-bit value.
 //! Uses BMI2 instructions (pdep/pext) on x86_64 when available, with LUT fallback.
+//! [`morton_encode128`] extends the interleave to 32-bit coordinates for
+//! [`crate::Galactic128`].
+
+use std::collections::BinaryHeap;
+use std::ops::RangeInclusive;
 
 /// Morton encode three 16-bit coordinates into a 48-bit value
 #[must_use]
@@ -29,6 +34,34 @@ pub fn morton_decode(morton: u64) -> (u16, u16, u16) {
     morton_decode_lut(morton)
 }
 
+/// Morton encode three 32-bit coordinates into a 96-bit value
+///
+/// Bit `i` of x, y and z lands at bit `3i`, `3i + 1` and `3i + 2`, so the
+/// low 48 bits equal [`morton_encode`] of the coordinates' low halves.
+#[must_use]
+#[inline]
+pub fn morton_encode128(x: u32, y: u32, z: u32) -> u128 {
+    let lo = morton_encode(x as u16, y as u16, z as u16);
+    let hi = morton_encode((x >> 16) as u16, (y >> 16) as u16, (z >> 16) as u16);
+    ((hi as u128) << 48) | lo as u128
+}
+
+/// Morton decode a 96-bit value into three 32-bit coordinates
+///
+/// Bits above 96 are ignored.
+#[must_use]
+#[inline]
+pub fn morton_decode128(morton: u128) -> (u32, u32, u32) {
+    const LOW_48: u128 = (1 << 48) - 1;
+    let (lx, ly, lz) = morton_decode((morton & LOW_48) as u64);
+    let (hx, hy, hz) = morton_decode(((morton >> 48) & LOW_48) as u64);
+    (
+        (hx as u32) << 16 | lx as u32,
+        (hy as u32) << 16 | ly as u32,
+        (hz as u32) << 16 | lz as u32,
+    )
+}
+
 // BMI2 implementation (x86_64 only)
 #[cfg(all(target_arch = "x86_64", feature = "simd"))]
 #[target_feature(enable = "bmi2")]
@@ -116,6 +149,276 @@ fn morton_decode_lut(morton: u64) -> (u16, u16, u16) {
     (x, y, z)
 }
 
+/// Mask of the 48 Morton bits belonging to the x axis (bit 0, 3, 6, ...)
+const AXIS_MASK: u64 = 0x9249_2492_4924_9249 & 0xFFFF_FFFF_FFFF;
+
+/// Iterate over the Morton codes of every cell in an inclusive 3D box
+///
+/// Codes are yielded in ascending Morton order without materializing the
+/// box. Runs of codes that fall outside the box are skipped in one step using
+/// the BIGMIN computation of Tropf & Herzog, so the cost is proportional to
+/// the number of cells yielded rather than to the Morton span of the box.
+///
+/// Corners are normalized, so `min` and `max` may be given in any order.
+///
+/// # Example
+///
+/// ```
+/// use octaindex3d::morton::{box_range, morton_decode};
+///
+/// let cells: Vec<_> = box_range((1, 1, 1), (2, 2, 2)).map(morton_decode).collect();
+/// assert_eq!(cells.len(), 8);
+/// assert!(cells.iter().all(|&(x, y, z)| (1..=2).contains(&x)
+///     && (1..=2).contains(&y)
+///     && (1..=2).contains(&z)));
+/// ```
+pub fn box_range(min: (u16, u16, u16), max: (u16, u16, u16)) -> MortonBoxIter {
+    let lo = (min.0.min(max.0), min.1.min(max.1), min.2.min(max.2));
+    let hi = (min.0.max(max.0), min.1.max(max.1), min.2.max(max.2));
+    let remaining = (hi.0 - lo.0) as u64 + 1;
+    let remaining = remaining * ((hi.1 - lo.1) as u64 + 1) * ((hi.2 - lo.2) as u64 + 1);
+    MortonBoxIter {
+        min: lo,
+        max: hi,
+        zmin: morton_encode(lo.0, lo.1, lo.2),
+        zmax: morton_encode(hi.0, hi.1, hi.2),
+        next: Some(morton_encode(lo.0, lo.1, lo.2)),
+        remaining,
+    }
+}
+
+/// Iterator returned by [`box_range`]
+#[derive(Debug, Clone)]
+pub struct MortonBoxIter {
+    min: (u16, u16, u16),
+    max: (u16, u16, u16),
+    zmin: u64,
+    zmax: u64,
+    next: Option<u64>,
+    remaining: u64,
+}
+
+impl MortonBoxIter {
+    #[inline]
+    fn contains(&self, code: u64) -> bool {
+        let (x, y, z) = morton_decode(code);
+        (self.min.0..=self.max.0).contains(&x)
+            && (self.min.1..=self.max.1).contains(&y)
+            && (self.min.2..=self.max.2).contains(&z)
+    }
+}
+
+impl Iterator for MortonBoxIter {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        let current = self.next?;
+        self.remaining -= 1;
+
+        self.next = if current >= self.zmax {
+            None
+        } else if self.contains(current + 1) {
+            Some(current + 1)
+        } else {
+            bigmin(current + 1, self.zmin, self.zmax)
+        };
+
+        Some(current)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = usize::try_from(self.remaining).unwrap_or(usize::MAX);
+        (n, usize::try_from(self.remaining).ok())
+    }
+}
+
+impl std::iter::FusedIterator for MortonBoxIter {}
+
+/// Smallest Morton code greater than `code` that lies inside the box spanned
+/// by `zmin` and `zmax`, or `None` if there is none
+///
+/// `code` must lie outside the box.
+pub fn bigmin(code: u64, mut zmin: u64, mut zmax: u64) -> Option<u64> {
+    let mut result = None;
+
+    for bit in (0..48).rev() {
+        let mask = 1u64 << bit;
+        // Lower bits of the same axis as `bit`
+        let lower = (AXIS_MASK << (bit % 3)) & (mask - 1);
+
+        match (code & mask != 0, zmin & mask != 0, zmax & mask != 0) {
+            (false, false, true) => {
+                // Box straddles this bit: the upper half is a candidate, keep
+                // searching the lower half
+                result = Some((zmin | mask) & !lower);
+                zmax = (zmax & !mask) | lower;
+            }
+            (false, true, true) => return Some(zmin),
+            (true, false, false) => return result,
+            (true, false, true) => zmin = (zmin | mask) & !lower,
+            // Equal bits keep narrowing; min > max bits cannot occur
+            _ => {}
+        }
+    }
+
+    result
+}
+
+/// Cover an inclusive 3D box with contiguous Morton code ranges
+///
+/// Splits the box the way BIGMIN/LITMAX do (Tropf & Herzog): the Morton span
+/// `[zmin, zmax]` of a box is cut at the highest bit where its corners
+/// differ, giving a lower box ending at LITMAX and an upper box starting at
+/// BIGMIN; pieces whose span contains nothing but box cells become ranges.
+/// The result is sorted, with adjacent ranges merged, so a box query over
+/// Morton-sorted data becomes one range scan per entry.
+///
+/// At most `max_ranges` ranges are returned. When the exact cover needs
+/// more, the pieces spanning the most codes outside the box are left
+/// unsplit, so ranges may then include cells outside the box and results
+/// must still be filtered. Pass `usize::MAX` for an exact cover.
+///
+/// # Example
+///
+/// ```
+/// use octaindex3d::morton::{box_range, box_ranges};
+///
+/// let ranges = box_ranges((1, 1, 1), (2, 2, 2), usize::MAX);
+/// let covered: u64 = ranges.iter().map(|r| r.end() - r.start() + 1).sum();
+/// assert_eq!(covered, 8);
+/// assert_eq!(covered as usize, box_range((1, 1, 1), (2, 2, 2)).count());
+/// ```
+pub fn box_ranges(
+    min: (u16, u16, u16),
+    max: (u16, u16, u16),
+    max_ranges: usize,
+) -> Vec<RangeInclusive<u64>> {
+    let lo = (min.0.min(max.0), min.1.min(max.1), min.2.min(max.2));
+    let hi = (min.0.max(max.0), min.1.max(max.1), min.2.max(max.2));
+    let max_ranges = max_ranges.max(1);
+
+    // Pieces still to split, most wasted codes first
+    let mut pending = BinaryHeap::new();
+    let mut done: Vec<RangeInclusive<u64>> = Vec::new();
+    pending.push(BoxPiece::new(lo, hi));
+
+    while let Some(piece) = pending.pop() {
+        if piece.waste == 0 || done.len() + pending.len() + 1 >= max_ranges {
+            done.push(piece.zmin..=piece.zmax);
+            continue;
+        }
+        let (lower, upper) = piece.split();
+        pending.push(lower);
+        pending.push(upper);
+    }
+
+    done.sort_unstable_by_key(|r| *r.start());
+    let mut merged: Vec<RangeInclusive<u64>> = Vec::with_capacity(done.len());
+    for range in done {
+        match merged.last_mut() {
+            Some(last) if *last.end() + 1 == *range.start() => {
+                *last = *last.start()..=*range.end();
+            }
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+/// Largest Morton code smaller than `code` that lies inside the box spanned
+/// by `zmin` and `zmax`, or `None` if there is none
+///
+/// Counterpart of BIGMIN; `code` must lie outside the box.
+pub fn litmax(code: u64, mut zmin: u64, mut zmax: u64) -> Option<u64> {
+    let mut result = None;
+
+    for bit in (0..48).rev() {
+        let mask = 1u64 << bit;
+        let lower = (AXIS_MASK << (bit % 3)) & (mask - 1);
+
+        match (code & mask != 0, zmin & mask != 0, zmax & mask != 0) {
+            (true, false, true) => {
+                // Box straddles this bit: the lower half is a candidate, keep
+                // searching the upper half
+                result = Some((zmax & !mask) | lower);
+                zmin = (zmin | mask) & !lower;
+            }
+            (true, false, false) => return Some(zmax),
+            (false, true, true) => return result,
+            (false, false, true) => zmax = (zmax & !mask) | lower,
+            _ => {}
+        }
+    }
+
+    result
+}
+
+/// Sub-box awaiting decomposition in [`box_ranges`]
+#[derive(Debug, PartialEq, Eq)]
+struct BoxPiece {
+    min: (u16, u16, u16),
+    max: (u16, u16, u16),
+    zmin: u64,
+    zmax: u64,
+    /// Codes in `[zmin, zmax]` outside the box
+    waste: u64,
+}
+
+impl BoxPiece {
+    fn new(min: (u16, u16, u16), max: (u16, u16, u16)) -> Self {
+        let zmin = morton_encode(min.0, min.1, min.2);
+        let zmax = morton_encode(max.0, max.1, max.2);
+        let volume = (max.0 - min.0) as u64 + 1;
+        let volume = volume * ((max.1 - min.1) as u64 + 1) * ((max.2 - min.2) as u64 + 1);
+        Self {
+            min,
+            max,
+            zmin,
+            zmax,
+            waste: zmax - zmin + 1 - volume,
+        }
+    }
+
+    /// Split at the highest bit where the corner codes differ
+    ///
+    /// The lower piece ends at LITMAX and the upper starts at BIGMIN of the
+    /// first code past the cut.
+    fn split(&self) -> (BoxPiece, BoxPiece) {
+        let bit = 63 - (self.zmin ^ self.zmax).leading_zeros();
+        let axis_bit = bit / 3;
+        let cut = |lo: u16, hi: u16| -> (u16, u16) {
+            let start = (hi >> axis_bit) << axis_bit;
+            debug_assert!(lo < start && start <= hi);
+            (start - 1, start)
+        };
+
+        let (mut lower_max, mut upper_min) = (self.max, self.min);
+        match bit % 3 {
+            0 => (lower_max.0, upper_min.0) = cut(self.min.0, self.max.0),
+            1 => (lower_max.1, upper_min.1) = cut(self.min.1, self.max.1),
+            _ => (lower_max.2, upper_min.2) = cut(self.min.2, self.max.2),
+        }
+        (
+            BoxPiece::new(self.min, lower_max),
+            BoxPiece::new(upper_min, self.max),
+        )
+    }
+}
+
+impl Ord for BoxPiece {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.waste
+            .cmp(&other.waste)
+            .then_with(|| other.zmin.cmp(&self.zmin))
+    }
+}
+
+impl PartialOrd for BoxPiece {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 // Helper function for generating decode tables (kept for clarity)
 #[allow(dead_code)]
 #[inline]
@@ -232,6 +535,66 @@ mod tests {
         assert_eq!((x, y, z), (dx, dy, dz));
     }
 
+    #[test]
+    fn test_box_range_matches_brute_force() {
+        let boxes = [
+            ((0, 0, 0), (0, 0, 0)),
+            ((3, 5, 7), (9, 6, 12)),
+            ((14, 1, 30), (17, 2, 33)),
+            ((60, 60, 60), (67, 63, 65)),
+        ];
+
+        for (min, max) in boxes {
+            let mut expected = Vec::new();
+            for x in min.0..=max.0 {
+                for y in min.1..=max.1 {
+                    for z in min.2..=max.2 {
+                        expected.push(morton_encode(x, y, z));
+                    }
+                }
+            }
+            expected.sort_unstable();
+
+            let iter = box_range(min, max);
+            assert_eq!(iter.size_hint().0, expected.len());
+            let actual: Vec<u64> = iter.collect();
+            assert_eq!(actual, expected, "box {:?}..={:?}", min, max);
+        }
+    }
+
+    #[test]
+    fn test_box_range_extremes() {
+        let max = u16::MAX;
+        let corner: Vec<_> = box_range((max, max, max - 1), (max - 1, max, max)).collect();
+        assert_eq!(corner.len(), 4);
+        assert_eq!(corner.last(), Some(&morton_encode(max, max, max)));
+
+        // A thin slab far from the origin is visited without walking the span
+        let slab = box_range((0, 1000, 0), (65535, 1000, 0));
+        assert_eq!(slab.count(), 65536);
+    }
+
+    #[test]
+    fn test_morton128_roundtrip() {
+        let coords = [
+            (0, 0, 0),
+            (1, 2, 3),
+            (0x1_0000, 0xFFFF, 0x8000_0000),
+            (u32::MAX, 0x1234_5678, 0x9ABC_DEF0),
+        ];
+        for (x, y, z) in coords {
+            let code = morton_encode128(x, y, z);
+            assert!(code < 1 << 96);
+            assert_eq!(morton_decode128(code), (x, y, z));
+        }
+        // The low 48 bits agree with the 64-bit encoding
+        assert_eq!(
+            morton_encode128(0x1_1234, 0x2_5678, 0x3_0042) as u64 & ((1 << 48) - 1),
+            morton_encode(0x1234, 0x5678, 0x0042)
+        );
+        assert_eq!(morton_encode128(0, 0, 1 << 31), 1 << 95);
+    }
+
     #[cfg(all(target_arch = "x86_64", feature = "simd"))]
     #[test]
     fn test_morton_bmi2() {
@@ -242,4 +605,55 @@ mod tests {
             assert_eq!((x, y, z), (dx, dy, dz));
         }
     }
+
+    #[test]
+    fn test_box_ranges_exact_cover() {
+        let boxes = [
+            ((0, 0, 0), (7, 7, 7)),
+            ((3, 5, 1), (9, 6, 12)),
+            ((100, 37, 2), (103, 60, 9)),
+            ((5, 5, 5), (5, 5, 5)),
+        ];
+        for (min, max) in boxes {
+            let expected: Vec<u64> = box_range(min, max).collect();
+            let ranges = box_ranges(min, max, usize::MAX);
+            let covered: Vec<u64> = ranges.iter().flat_map(|r| r.clone()).collect();
+            assert_eq!(covered, expected);
+            // Adjacent ranges are merged
+            assert!(ranges.windows(2).all(|w| *w[0].end() + 1 < *w[1].start()));
+        }
+        assert_eq!(box_ranges((0, 0, 0), (7, 7, 7), usize::MAX).len(), 1);
+    }
+
+    #[test]
+    fn test_box_ranges_limited() {
+        let (min, max) = ((3, 5, 1), (90, 60, 120));
+        let exact = box_ranges(min, max, usize::MAX);
+        assert!(exact.len() > 8);
+
+        let coarse = box_ranges(min, max, 8);
+        assert!(coarse.len() <= 8);
+        // Every box cell is still covered
+        for code in box_range(min, max) {
+            assert!(coarse.iter().any(|r| r.contains(&code)));
+        }
+    }
+
+    #[test]
+    fn test_litmax_bigmin_bracket_gap() {
+        let (min, max) = ((3u16, 5u16, 1u16), (9u16, 6u16, 12u16));
+        let zmin = morton_encode(min.0, min.1, min.2);
+        let zmax = morton_encode(max.0, max.1, max.2);
+        let inside: Vec<u64> = box_range(min, max).collect();
+
+        for code in zmin..=zmax {
+            if inside.binary_search(&code).is_ok() {
+                continue;
+            }
+            let below = inside.iter().rev().find(|&&c| c < code).copied();
+            let above = inside.iter().find(|&&c| c > code).copied();
+            assert_eq!(litmax(code, zmin, zmax), below);
+            assert_eq!(bigmin(code, zmin, zmax), above);
+        }
+    }
 }
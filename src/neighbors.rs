@@ -2,6 +2,7 @@
 
 use crate::ids::{Galactic128, Index64, Route64};
 use crate::lattice::BCC_NEIGHBORS_14;
+use std::collections::{HashSet, VecDeque};
 
 /// Get 14 neighbors of a Route64 coordinate
 #[must_use]
@@ -23,35 +24,27 @@ pub fn neighbors_route64(route: Route64) -> Vec<Route64> {
 /// Get 14 neighbors of an Index64 coordinate
 #[must_use]
 pub fn neighbors_index64(index: Index64) -> Vec<Index64> {
-    let (x, y, z) = index.decode_coords();
-    let frame = index.frame_id();
-    let tier = index.scale_tier();
-    let lod = index.lod();
-
     BCC_NEIGHBORS_14
         .iter()
-        .filter_map(|(dx, dy, dz)| {
-            // Convert to signed and add offset
-            let nx = (x as i32).checked_add(*dx)?;
-            let ny = (y as i32).checked_add(*dy)?;
-            let nz = (z as i32).checked_add(*dz)?;
-
-            // Check if still in u16 range
-            if nx < 0
-                || ny < 0
-                || nz < 0
-                || nx > u16::MAX as i32
-                || ny > u16::MAX as i32
-                || nz > u16::MAX as i32
-            {
-                return None;
-            }
-
-            Index64::new(frame, tier, lod, nx as u16, ny as u16, nz as u16).ok()
-        })
+        .filter_map(|&delta| offset_index64(index, delta))
         .collect()
 }
 
+/// Cell of an Index64 shifted by a lattice offset, if still in range
+pub(crate) fn offset_index64(index: Index64, delta: (i32, i32, i32)) -> Option<Index64> {
+    let (x, y, z) = index.decode_coords();
+    let coord = |c: u16, d: i32| u16::try_from(c as i32 + d).ok();
+    Index64::new(
+        index.frame_id(),
+        index.scale_tier(),
+        index.lod(),
+        coord(x, delta.0)?,
+        coord(y, delta.1)?,
+        coord(z, delta.2)?,
+    )
+    .ok()
+}
+
 /// Get 14 neighbors of a Galactic128 coordinate
 #[must_use]
 pub fn neighbors_galactic128(galactic: Galactic128) -> Vec<Galactic128> {
@@ -73,6 +66,100 @@ pub fn neighbors_galactic128(galactic: Galactic128) -> Vec<Galactic128> {
         .collect()
 }
 
+/// Inclusive coordinate box `(min, max)` of 16-bit Index64 coordinates
+pub type CoordBox = ((u16, u16, u16), (u16, u16, u16));
+
+/// Limits for [`flood_fill_index64`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FloodFillLimits {
+    /// Maximum number of cells to collect
+    pub max_cells: usize,
+    /// Inclusive coordinate box the fill may not leave, as `(min, max)`
+    pub bounds: Option<CoordBox>,
+}
+
+impl Default for FloodFillLimits {
+    fn default() -> Self {
+        Self {
+            max_cells: 1_000_000,
+            bounds: None,
+        }
+    }
+}
+
+/// Result of a bounded flood fill
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FloodFill {
+    /// Filled cells in breadth-first order, starting with the seed
+    pub cells: Vec<Index64>,
+    /// The fill stopped at `max_cells` with cells left to visit
+    pub truncated: bool,
+    /// The fill tried to grow past the bounding box or the coordinate range
+    pub reached_bounds: bool,
+}
+
+impl FloodFill {
+    /// Check if the filled region is closed off by cells failing the predicate
+    ///
+    /// True when the fill completed without touching any limit, e.g. a void
+    /// fully enclosed by a reconstructed surface.
+    pub fn is_enclosed(&self) -> bool {
+        !self.cells.is_empty() && !self.truncated && !self.reached_bounds
+    }
+}
+
+/// Grow a 14-connected region from `seed` through cells accepted by `predicate`
+///
+/// The predicate is called at most once per cell. A seed rejected by the
+/// predicate yields an empty fill. See [`FloodFill::is_enclosed`] for
+/// detecting enclosed voids.
+pub fn flood_fill_index64<F>(seed: Index64, limits: &FloodFillLimits, mut predicate: F) -> FloodFill
+where
+    F: FnMut(Index64) -> bool,
+{
+    let mut result = FloodFill::default();
+    let in_bounds = |idx: Index64| {
+        limits.bounds.map_or(true, |(min, max)| {
+            let (x, y, z) = idx.decode_coords();
+            (min.0..=max.0).contains(&x)
+                && (min.1..=max.1).contains(&y)
+                && (min.2..=max.2).contains(&z)
+        })
+    };
+    if limits.max_cells == 0 || !in_bounds(seed) || !predicate(seed) {
+        return result;
+    }
+
+    let mut visited: HashSet<Index64> = HashSet::from([seed]);
+    let mut queue = VecDeque::from([seed]);
+    while let Some(cell) = queue.pop_front() {
+        if result.cells.len() == limits.max_cells {
+            result.truncated = true;
+            break;
+        }
+        result.cells.push(cell);
+
+        let neighbors = neighbors_index64(cell);
+        if neighbors.len() < BCC_NEIGHBORS_14.len() {
+            result.reached_bounds = true;
+        }
+        for neighbor in neighbors {
+            if !visited.insert(neighbor) {
+                continue;
+            }
+            if !predicate(neighbor) {
+                continue;
+            }
+            if !in_bounds(neighbor) {
+                result.reached_bounds = true;
+                continue;
+            }
+            queue.push_back(neighbor);
+        }
+    }
+    result
+}
+
 /// Compute Euclidean distance between two Route64 cells
 #[must_use]
 pub fn distance_route64(a: Route64, b: Route64) -> f64 {
@@ -140,4 +227,48 @@ mod tests {
         let manhattan = manhattan_distance_route64(a, b);
         assert_eq!(manhattan, 2);
     }
+
+    #[test]
+    fn test_flood_fill_enclosed_void() {
+        let at = |x: i32, y: i32, z: i32| {
+            Index64::new(0, 0, 5, (20 + x) as u16, (20 + y) as u16, (20 + z) as u16).unwrap()
+        };
+        // Two-unit thick hollow cube: steps change a coordinate by at most 2
+        let is_free = |idx: Index64| {
+            let (x, y, z) = idx.decode_coords();
+            let d = [x, y, z]
+                .iter()
+                .map(|&c| (c as i32 - 20).abs())
+                .max()
+                .unwrap();
+            d != 5 && d != 6
+        };
+        let limits = FloodFillLimits {
+            bounds: Some(((10, 10, 10), (30, 30, 30))),
+            ..Default::default()
+        };
+
+        let inside = flood_fill_index64(at(0, 0, 0), &limits, is_free);
+        assert!(inside.is_enclosed());
+        assert_eq!(inside.cells[0], at(0, 0, 0));
+        // 5³ even and 4³ odd lattice points in [-4, 4]³
+        assert_eq!(inside.cells.len(), 125 + 64);
+
+        let outside = flood_fill_index64(at(8, 0, 0), &limits, is_free);
+        assert!(outside.reached_bounds);
+        assert!(!outside.is_enclosed());
+        assert!(!outside.cells.contains(&at(0, 0, 0)));
+
+        let capped = FloodFillLimits {
+            max_cells: 10,
+            ..limits
+        };
+        let truncated = flood_fill_index64(at(0, 0, 0), &capped, is_free);
+        assert!(truncated.truncated);
+        assert_eq!(truncated.cells.len(), 10);
+
+        assert!(flood_fill_index64(at(5, 0, 0), &limits, is_free)
+            .cells
+            .is_empty());
+    }
 }
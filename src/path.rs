@@ -5,7 +5,9 @@
 //!
 //! **Deprecated:** new code should use [`crate::grid::BccGrid`], which provides
 //! `astar`, `astar_where`, `k_ring`, and `k_shell` on the modern
-//! [`crate::ids::Route64`] type.
+//! [`crate::ids::Route64`] type. Searches with pluggable costs, including
+//! symmetry-pruned (JPS-style) search for uniform costs, live in
+//! [`crate::routing`].
 
 #![allow(deprecated)]
 
@@ -0,0 +1,469 @@
+//! Spill-to-disk sorting for key sets larger than memory
+//!
+//! [`ExternalSorter`] buffers keys up to a fixed run size, sorts each full
+//! buffer and spills it to a temporary run file, then k-way merges the runs
+//! on [`finish`](ExternalSorter::finish). Memory use is bounded by the run
+//! size plus one read buffer per merged run, so unsorted LiDAR dumps of
+//! billions of keys can be turned into Morton- (or Hilbert-) ordered
+//! container frames directly.
+//!
+//! ```
+//! use octaindex3d::performance::ExternalSorter;
+//! use octaindex3d::container::ContainerWriter;
+//! use octaindex3d::Index64;
+//!
+//! # fn main() -> octaindex3d::Result<()> {
+//! let mut sorter = ExternalSorter::new(1024);
+//! for i in (0..5000u16).rev() {
+//!     sorter.push(Index64::new(0, 0, 10, i % 97, i % 89, i % 83)?)?;
+//! }
+//!
+//! let mut writer = ContainerWriter::new(Vec::new())?;
+//! let frames = sorter.finish()?.write_frames(4096, |frame| writer.write_frame(frame))?;
+//! assert_eq!(frames, 2);
+//! writer.finish()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{Error, Result};
+use crate::Index64;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Most runs merged at once; more runs are merged in several passes
+const MAX_FAN_IN: usize = 128;
+
+/// Read and write buffer per run file
+const RUN_BUFFER: usize = 64 * 1024;
+
+/// Distinguishes sorters of one process sharing a spill directory
+static SORTER_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Key that can be sorted externally, stored as its raw 64-bit value
+///
+/// Keys must order the same way as their raw values.
+pub trait ExternalKey: Copy + Ord + Send {
+    /// Raw value written to run files and frames
+    fn to_raw(self) -> u64;
+
+    /// Rebuild a key from its raw value
+    fn from_raw(raw: u64) -> Result<Self>;
+}
+
+impl ExternalKey for u64 {
+    fn to_raw(self) -> u64 {
+        self
+    }
+
+    fn from_raw(raw: u64) -> Result<Self> {
+        Ok(raw)
+    }
+}
+
+impl ExternalKey for Index64 {
+    fn to_raw(self) -> u64 {
+        self.raw()
+    }
+
+    fn from_raw(raw: u64) -> Result<Self> {
+        Index64::from_value(raw)
+    }
+}
+
+#[cfg(feature = "hilbert")]
+impl ExternalKey for crate::Hilbert64 {
+    fn to_raw(self) -> u64 {
+        self.as_u64()
+    }
+
+    fn from_raw(raw: u64) -> Result<Self> {
+        crate::Hilbert64::from_value(raw)
+    }
+}
+
+/// Sorted run spilled to disk, removed when dropped
+///
+/// Owning the file this way cleans up runs on every exit, including a
+/// spill or merge that fails part way.
+#[derive(Debug)]
+struct Run {
+    path: PathBuf,
+    len: u64,
+}
+
+impl Drop for Run {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Bounded-memory sorter that spills sorted runs to disk
+#[derive(Debug)]
+pub struct ExternalSorter<K: ExternalKey> {
+    buffer: Vec<K>,
+    run_capacity: usize,
+    dedup: bool,
+    spill_dir: PathBuf,
+    prefix: String,
+    runs: Vec<Run>,
+    /// Run files created so far, for unique names
+    created: u64,
+    pushed: u64,
+}
+
+impl<K: ExternalKey> ExternalSorter<K> {
+    /// Create a sorter holding at most `run_capacity` keys in memory,
+    /// spilling to the system temporary directory
+    pub fn new(run_capacity: usize) -> Self {
+        Self::with_spill_dir(run_capacity, std::env::temp_dir())
+    }
+
+    /// Create a sorter spilling its runs to `spill_dir`
+    pub fn with_spill_dir<P: AsRef<Path>>(run_capacity: usize, spill_dir: P) -> Self {
+        let id = SORTER_ID.fetch_add(1, Ordering::Relaxed);
+        Self {
+            buffer: Vec::new(),
+            run_capacity: run_capacity.max(1),
+            dedup: false,
+            spill_dir: spill_dir.as_ref().to_path_buf(),
+            prefix: format!("octaindex3d-sort-{}-{}", std::process::id(), id),
+            runs: Vec::new(),
+            created: 0,
+            pushed: 0,
+        }
+    }
+
+    /// Drop repeated keys from the output
+    pub fn dedup(mut self, dedup: bool) -> Self {
+        self.dedup = dedup;
+        self
+    }
+
+    /// Add a key, spilling a run when the buffer is full
+    pub fn push(&mut self, key: K) -> Result<()> {
+        if self.buffer.len() >= self.run_capacity {
+            self.spill()?;
+        }
+        self.buffer.push(key);
+        self.pushed += 1;
+        Ok(())
+    }
+
+    /// Add every key of an iterator
+    pub fn extend<I: IntoIterator<Item = K>>(&mut self, keys: I) -> Result<()> {
+        keys.into_iter().try_for_each(|key| self.push(key))
+    }
+
+    /// Keys pushed so far
+    pub fn len(&self) -> u64 {
+        self.pushed
+    }
+
+    /// Whether no key was pushed
+    pub fn is_empty(&self) -> bool {
+        self.pushed == 0
+    }
+
+    /// Runs spilled to disk so far
+    pub fn spilled_runs(&self) -> usize {
+        self.runs.len()
+    }
+
+    /// Merge everything into one sorted stream
+    ///
+    /// Inputs that fit in one run never touch the disk. With more than
+    /// 128 runs, groups of runs are first merged into longer runs.
+    pub fn finish(mut self) -> Result<SortedKeys<K>> {
+        if self.runs.is_empty() {
+            sort_keys(&mut self.buffer);
+            let buffer = std::mem::take(&mut self.buffer);
+            return Ok(SortedKeys {
+                source: Source::Memory(buffer.into_iter()),
+                dedup: self.dedup,
+                last: None,
+                _runs: Vec::new(),
+            });
+        }
+        if !self.buffer.is_empty() {
+            self.spill()?;
+        }
+
+        while self.runs.len() > MAX_FAN_IN {
+            let group: Vec<Run> = self.runs.drain(..MAX_FAN_IN).collect();
+            let mut run = Run {
+                path: self.run_path(),
+                len: 0,
+            };
+            let mut out = BufWriter::with_capacity(RUN_BUFFER, File::create(&run.path)?);
+            let mut merge = Merge::open(&group)?;
+            while let Some(raw) = merge.next_raw()? {
+                out.write_all(&raw.to_le_bytes())?;
+                run.len += 1;
+            }
+            out.flush()?;
+            // Close the merged files before their runs remove them
+            drop(merge);
+            drop(group);
+            self.runs.push(run);
+        }
+
+        let runs = std::mem::take(&mut self.runs);
+        Ok(SortedKeys {
+            source: Source::Disk(Merge::open(&runs)?),
+            dedup: self.dedup,
+            last: None,
+            _runs: runs,
+        })
+    }
+
+    fn run_path(&mut self) -> PathBuf {
+        self.created += 1;
+        self.spill_dir
+            .join(format!("{}-{}.run", self.prefix, self.created))
+    }
+
+    fn spill(&mut self) -> Result<()> {
+        sort_keys(&mut self.buffer);
+        let run = Run {
+            path: self.run_path(),
+            len: self.buffer.len() as u64,
+        };
+        let mut out = BufWriter::with_capacity(RUN_BUFFER, File::create(&run.path)?);
+        for key in &self.buffer {
+            out.write_all(&key.to_raw().to_le_bytes())?;
+        }
+        out.flush()?;
+        self.runs.push(run);
+        self.buffer.clear();
+        Ok(())
+    }
+}
+
+fn sort_keys<K: ExternalKey>(keys: &mut [K]) {
+    #[cfg(feature = "parallel")]
+    keys.par_sort_unstable();
+    #[cfg(not(feature = "parallel"))]
+    keys.sort_unstable();
+}
+
+/// K-way merge over run files
+#[derive(Debug)]
+struct Merge {
+    readers: Vec<(BufReader<File>, u64)>,
+    heap: BinaryHeap<Reverse<(u64, usize)>>,
+}
+
+impl Merge {
+    fn open(runs: &[Run]) -> Result<Self> {
+        let mut merge = Self {
+            readers: Vec::with_capacity(runs.len()),
+            heap: BinaryHeap::with_capacity(runs.len()),
+        };
+        for (i, run) in runs.iter().enumerate() {
+            let reader = BufReader::with_capacity(RUN_BUFFER, File::open(&run.path)?);
+            merge.readers.push((reader, run.len));
+            merge.refill(i)?;
+        }
+        Ok(merge)
+    }
+
+    /// Queue the next key of run `i`, if any
+    fn refill(&mut self, i: usize) -> Result<()> {
+        let (reader, remaining) = &mut self.readers[i];
+        if *remaining == 0 {
+            return Ok(());
+        }
+        let mut bytes = [0u8; 8];
+        reader.read_exact(&mut bytes).map_err(|e| match e.kind() {
+            ErrorKind::UnexpectedEof => Error::InvalidFormat("truncated sort run".to_string()),
+            _ => e.into(),
+        })?;
+        *remaining -= 1;
+        self.heap.push(Reverse((u64::from_le_bytes(bytes), i)));
+        Ok(())
+    }
+
+    fn next_raw(&mut self) -> Result<Option<u64>> {
+        let Some(Reverse((raw, i))) = self.heap.pop() else {
+            return Ok(None);
+        };
+        self.refill(i)?;
+        Ok(Some(raw))
+    }
+}
+
+#[derive(Debug)]
+enum Source<K> {
+    Memory(std::vec::IntoIter<K>),
+    Disk(Merge),
+}
+
+/// Sorted output of an [`ExternalSorter`]
+///
+/// Yields keys in ascending order; run files are removed when it is
+/// dropped.
+#[derive(Debug)]
+pub struct SortedKeys<K: ExternalKey> {
+    source: Source<K>,
+    dedup: bool,
+    last: Option<K>,
+    /// Held only to remove the run files on drop
+    _runs: Vec<Run>,
+}
+
+impl<K: ExternalKey> SortedKeys<K> {
+    /// Write the keys as container frames of up to `keys_per_frame` keys
+    ///
+    /// Each frame holds raw little-endian `u64` keys, and every frame's keys
+    /// sort after the previous frame's, so the result suits range-partitioned
+    /// containers. `write` receives each frame payload, typically
+    /// `|frame| writer.write_frame(frame)` for a [`ContainerWriter`] or
+    /// [`ContainerWriterV2`]. Returns the number of frames written; decode
+    /// them with [`decode_frame`].
+    ///
+    /// [`ContainerWriter`]: crate::container::ContainerWriter
+    /// [`ContainerWriterV2`]: crate::container_v2::ContainerWriterV2
+    pub fn write_frames<F>(self, keys_per_frame: usize, mut write: F) -> Result<u64>
+    where
+        F: FnMut(&[u8]) -> Result<()>,
+    {
+        let keys_per_frame = keys_per_frame.max(1);
+        let mut frame = Vec::with_capacity(keys_per_frame * 8);
+        let mut frames = 0;
+        for key in self {
+            frame.extend_from_slice(&key?.to_raw().to_le_bytes());
+            if frame.len() == keys_per_frame * 8 {
+                write(&frame)?;
+                frame.clear();
+                frames += 1;
+            }
+        }
+        if !frame.is_empty() {
+            write(&frame)?;
+            frames += 1;
+        }
+        Ok(frames)
+    }
+
+    fn next_key(&mut self) -> Result<Option<K>> {
+        match &mut self.source {
+            Source::Memory(keys) => Ok(keys.next()),
+            Source::Disk(merge) => merge.next_raw()?.map(K::from_raw).transpose(),
+        }
+    }
+}
+
+impl<K: ExternalKey> Iterator for SortedKeys<K> {
+    type Item = Result<K>;
+
+    fn next(&mut self) -> Option<Result<K>> {
+        loop {
+            let key = match self.next_key() {
+                Ok(Some(key)) => key,
+                Ok(None) => return None,
+                Err(e) => return Some(Err(e)),
+            };
+            if self.dedup && self.last == Some(key) {
+                continue;
+            }
+            self.last = Some(key);
+            return Some(Ok(key));
+        }
+    }
+}
+
+impl<K: ExternalKey> Drop for SortedKeys<K> {
+    fn drop(&mut self) {
+        // Close the files before the runs remove them, for Windows
+        self.source = Source::Memory(Vec::new().into_iter());
+    }
+}
+
+/// Decode a frame written by [`SortedKeys::write_frames`]
+pub fn decode_frame<K: ExternalKey>(frame: &[u8]) -> Result<Vec<K>> {
+    if frame.len() % 8 != 0 {
+        return Err(Error::InvalidFormat(format!(
+            "key frame of {} bytes is not a whole number of keys",
+            frame.len()
+        )));
+    }
+    frame
+        .chunks_exact(8)
+        .map(|raw| K::from_raw(u64::from_le_bytes(raw.try_into().unwrap())))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_external_sort_spills_and_merges() {
+        let dir = std::env::temp_dir().join(format!("oi3d-sort-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        // Pseudo-random keys with duplicates, over more runs than one
+        // merge pass takes
+        let keys: Vec<u64> = (0..20_000u64).map(|i| (i * 7919) % 5003).collect();
+        let mut sorter = ExternalSorter::with_spill_dir(100, &dir).dedup(true);
+        sorter.extend(keys.iter().copied()).unwrap();
+        assert_eq!(sorter.len(), 20_000);
+        assert_eq!(sorter.spilled_runs(), 199);
+
+        let sorted: Vec<u64> = sorter.finish().unwrap().map(|k| k.unwrap()).collect();
+        assert_eq!(sorted, (0..5003).collect::<Vec<u64>>());
+        // Run files are gone once the output is consumed and dropped
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+        fs::remove_dir(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_failed_merge_removes_runs() {
+        let dir = std::env::temp_dir().join(format!("oi3d-sort-fail-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut sorter = ExternalSorter::with_spill_dir(10, &dir);
+        sorter.extend((0..30u64).rev()).unwrap();
+        let run = fs::read_dir(&dir).unwrap().next().unwrap().unwrap().path();
+        File::create(run).unwrap();
+        assert!(sorter.finish().is_err());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+        fs::remove_dir(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_frames_round_trip() {
+        let mut sorter = ExternalSorter::new(16);
+        for i in (0..40u16).rev() {
+            sorter
+                .push(Index64::new(0, 0, 8, i, 0, 0).unwrap())
+                .unwrap();
+        }
+        let mut frames = Vec::new();
+        let count = sorter
+            .finish()
+            .unwrap()
+            .write_frames(16, |frame| {
+                frames.push(frame.to_vec());
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(count, 3);
+
+        let keys: Vec<Index64> = frames
+            .iter()
+            .flat_map(|f| decode_frame::<Index64>(f).unwrap())
+            .collect();
+        assert_eq!(keys.len(), 40);
+        assert!(keys.windows(2).all(|w| w[0] < w[1]));
+        assert!(decode_frame::<Index64>(&[0; 7]).is_err());
+    }
+}
@@ -14,7 +14,10 @@ pub mod metal;
 #[cfg(all(feature = "gpu-vulkan", not(target_os = "windows")))]
 pub mod wgpu_backend;
 
-#[cfg(all(feature = "gpu-cuda", not(target_os = "windows")))]
+#[cfg(all(
+    feature = "gpu-cuda",
+    not(any(target_os = "windows", target_arch = "wasm32"))
+))]
 pub mod cuda;
 
 #[cfg(feature = "gpu-rocm")]
@@ -80,7 +83,10 @@ impl GpuBatchProcessor {
     /// Get the best available GPU backend
     fn best_backend() -> Result<Box<dyn GpuBackend>> {
         // Try CUDA first (best for NVIDIA)
-        #[cfg(all(feature = "gpu-cuda", not(target_os = "windows")))]
+        #[cfg(all(
+            feature = "gpu-cuda",
+            not(any(target_os = "windows", target_arch = "wasm32"))
+        ))]
         {
             // Catch panic from cudarc when CUDA isn't available
             if let Ok(Ok(backend)) = std::panic::catch_unwind(cuda::CudaBackend::new) {
@@ -143,13 +149,19 @@ impl GpuBatchProcessor {
 }
 
 /// Check if CUDA is available
-#[cfg(all(feature = "gpu-cuda", not(target_os = "windows")))]
+#[cfg(all(
+    feature = "gpu-cuda",
+    not(any(target_os = "windows", target_arch = "wasm32"))
+))]
 pub fn is_cuda_available() -> bool {
     cuda::is_cuda_available()
 }
 
 /// Check if CUDA is available (always false without the `gpu-cuda` feature)
-#[cfg(not(all(feature = "gpu-cuda", not(target_os = "windows"))))]
+#[cfg(not(all(
+    feature = "gpu-cuda",
+    not(any(target_os = "windows", target_arch = "wasm32"))
+)))]
 pub fn is_cuda_available() -> bool {
     false
 }
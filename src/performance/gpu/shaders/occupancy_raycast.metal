@@ -182,3 +182,135 @@ kernel void batch_update_log_odds(
                              update,
                              memory_order_relaxed);
 }
+
+/// Viewpoint scoring parameters (matches `ScoreParams` in occupancy_gpu.rs)
+struct ScoreParams {
+    int min_x;
+    int min_y;
+    int min_z;
+    uint dim_x;
+    uint dim_y;
+    uint dim_z;
+    float voxel_size;
+    float sensor_range;
+    float unknown_weight;
+    uint viewpoint_count;
+    uint rays_per_viewpoint;
+    uint table_capacity;
+};
+
+/// Nearest BCC point to an integer voxel coordinate
+///
+/// Mirrors `snap_to_nearest_bcc` in bcc_utils.rs, including tie-breaking.
+inline int3 snap_to_nearest_bcc(int3 v) {
+    bool xe = (v.x & 1) == 0;
+    bool ye = (v.y & 1) == 0;
+    bool ze = (v.z & 1) == 0;
+    if (xe == ye && ye == ze) {
+        return v;
+    }
+
+    int3 best_even = int3(0);
+    int best_even_dist = INT_MAX;
+    int3 best_odd = int3(0);
+    int best_odd_dist = INT_MAX;
+    for (int i = 0; i < 8; i++) {
+        int3 even = int3(((i & 1) ? v.x + 1 : v.x) & ~1,
+                         ((i & 2) ? v.y + 1 : v.y) & ~1,
+                         ((i & 4) ? v.z + 1 : v.z) & ~1);
+        int3 odd = int3(((i & 1) ? v.x - 1 : v.x) | 1,
+                        ((i & 2) ? v.y - 1 : v.y) | 1,
+                        ((i & 4) ? v.z - 1 : v.z) | 1);
+        int3 de = even - v;
+        int3 dd = odd - v;
+        int even_dist = de.x * de.x + de.y * de.y + de.z * de.z;
+        int odd_dist = dd.x * dd.x + dd.y * dd.y + dd.z * dd.z;
+        if (even_dist < best_even_dist) {
+            best_even_dist = even_dist;
+            best_even = even;
+        }
+        if (odd_dist < best_odd_dist) {
+            best_odd_dist = odd_dist;
+            best_odd = odd;
+        }
+    }
+    return best_even_dist <= best_odd_dist ? best_even : best_odd;
+}
+
+/// Score exploration viewpoints by counting unknown voxels seen by their rays
+///
+/// One thread per viewpoint. Each ray is sampled every half voxel out to the
+/// sensor range and stops at the first occupied voxel. Unknown voxels are
+/// de-duplicated across a viewpoint's rays with an open-addressing table in
+/// `tables` (zero-initialized, `table_capacity` slots per viewpoint). The
+/// capacity is at least the number of samples a viewpoint takes, so a table
+/// never fills up.
+kernel void score_viewpoints(
+    constant uchar* cells [[buffer(0)]],           // 0 = unknown, 1 = free, 2 = occupied
+    constant packed_float3* origins [[buffer(1)]], // One per viewpoint
+    constant packed_float3* rays [[buffer(2)]],    // rays_per_viewpoint per viewpoint
+    constant ScoreParams& params [[buffer(3)]],
+    device uint* tables [[buffer(4)]],
+    device float* gains [[buffer(5)]],
+    uint gid [[thread_position_in_grid]])
+{
+    if (gid >= params.viewpoint_count) return;
+
+    float3 origin = float3(origins[gid]);
+    device uint* table = tables + gid * params.table_capacity;
+    uint mask = params.table_capacity - 1;
+    float step_size = params.voxel_size * 0.5;
+    uint num_steps = step_size > 0.0 ? uint(params.sensor_range / step_size) : 0;
+    float gain = 0.0;
+
+    for (uint r = 0; r < params.rays_per_viewpoint; r++) {
+        float3 dir = float3(rays[gid * params.rays_per_viewpoint + r]);
+
+        for (uint i = 0; i < num_steps; i++) {
+            float t = float(i) * step_size;
+            float3 pos = origin + dir * t;
+            int3 voxel = snap_to_nearest_bcc(int3(round(pos / params.voxel_size)));
+
+            int3 local = voxel - int3(params.min_x, params.min_y, params.min_z);
+            bool inside = local.x >= 0 && local.y >= 0 && local.z >= 0 &&
+                          uint(local.x) < params.dim_x &&
+                          uint(local.y) < params.dim_y &&
+                          uint(local.z) < params.dim_z;
+            uint cell_index = 0;
+            uchar state = 0;
+            if (inside) {
+                cell_index = (uint(local.z) * params.dim_y + uint(local.y)) * params.dim_x +
+                             uint(local.x);
+                state = cells[cell_index];
+            }
+
+            if (state == 2) break; // Sensor cannot see past obstacles
+            if (state != 0) continue;
+
+            // Voxels outside the snapshot hash by coordinate instead of index
+            uint key = inside ? cell_index
+                              : (uint(voxel.x) * 73856093u) ^ (uint(voxel.y) * 19349663u) ^
+                                    (uint(voxel.z) * 83492791u);
+            uint tag = key + 1; // 0 marks an empty slot
+            uint slot = (key * 2654435761u) & mask;
+            bool seen = false;
+            for (uint probe = 0; probe < params.table_capacity; probe++) {
+                uint existing = table[slot];
+                if (existing == tag) {
+                    seen = true;
+                    break;
+                }
+                if (existing == 0) {
+                    table[slot] = tag;
+                    break;
+                }
+                slot = (slot + 1) & mask;
+            }
+            if (!seen) {
+                gain += params.unknown_weight;
+            }
+        }
+    }
+
+    gains[gid] = gain;
+}
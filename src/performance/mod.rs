@@ -5,9 +5,11 @@
 //! - SIMD instructions (ARM NEON, x86 AVX2/AVX-512)
 //! - Multi-threading (Rayon)
 //! - GPU acceleration (Metal, Vulkan via wgpu)
+//! - Spill-to-disk sorting of key sets larger than memory
 
 pub mod arch_optimized;
 pub mod batch;
+pub mod external_sort;
 pub mod fast_neighbors;
 pub mod memory;
 pub mod morton_batch;
@@ -34,6 +36,7 @@ pub mod gpu;
 // Re-export commonly used items
 pub use arch_optimized::{has_bmi2, ArchInfo};
 pub use batch::{BatchIndexBuilder, BatchNeighborCalculator, BatchResult};
+pub use external_sort::{ExternalKey, ExternalSorter, SortedKeys};
 pub use fast_neighbors::{batch_neighbors_auto, neighbors_route64_fast, NeighborStream};
 pub use memory::{AlignedBatchProcessor, AlignedVec, NumaInfo, CACHE_LINE_SIZE};
 pub use morton_batch::{batch_morton_decode, batch_morton_encode};
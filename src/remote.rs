@@ -0,0 +1,502 @@
+//! Range reads of containers held remotely
+//!
+//! A [`RemoteContainer`] answers block queries on a v2 container in an
+//! object store or behind an HTTP server without downloading it: it reads
+//! the header and the [`BlockIndex`], then fetches only the byte ranges of
+//! the blocks a query needs, merging nearby ranges into one request.
+//!
+//! Storage is reached through [`RangeSource`]. With the `http` feature,
+//! [`HttpRangeSource`] reads over HTTP(S) range requests, which covers
+//! public buckets and presigned S3 URLs; requests signed per call are
+//! served by implementing [`RangeSource`] over the store's own client.
+//!
+//! Only containers with a block index can be read this way: those finished
+//! by [`ContainerWriterV2::finish`](crate::container_v2::ContainerWriterV2::finish)
+//! or rewritten by [`compact`](crate::container_v2::compact).
+
+use crate::compression::Compression;
+use crate::container_v2::{
+    decode_cell_records, decode_indexed_frame, BlockIndex, BlockIndexEntry, CellChunking,
+    CellRecord, HeaderV2, BLOCK_INDEX_ENTRY_LEN,
+};
+use crate::error::{Error, Result};
+use crate::{FrameId, Index64};
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Largest gap between two wanted ranges fetched as one, in bytes
+pub const DEFAULT_COALESCE_GAP: u64 = 64 * 1024;
+
+/// Random access to the bytes of a stored object
+pub trait RangeSource {
+    /// Length of the object in bytes
+    fn size(&self) -> Result<u64>;
+
+    /// Bytes `range` of the object, all of them or an error
+    fn read_range(&self, range: Range<u64>) -> Result<Vec<u8>>;
+}
+
+impl RangeSource for [u8] {
+    fn size(&self) -> Result<u64> {
+        Ok(self.len() as u64)
+    }
+
+    fn read_range(&self, range: Range<u64>) -> Result<Vec<u8>> {
+        usize::try_from(range.start)
+            .ok()
+            .zip(usize::try_from(range.end).ok())
+            .and_then(|(start, end)| self.get(start..end))
+            .map(<[u8]>::to_vec)
+            .ok_or_else(|| Error::Io(format!("range {:?} is past the end", range)))
+    }
+}
+
+impl RangeSource for Vec<u8> {
+    fn size(&self) -> Result<u64> {
+        self.as_slice().size()
+    }
+
+    fn read_range(&self, range: Range<u64>) -> Result<Vec<u8>> {
+        self.as_slice().read_range(range)
+    }
+}
+
+impl<S: RangeSource + ?Sized> RangeSource for &S {
+    fn size(&self) -> Result<u64> {
+        (**self).size()
+    }
+
+    fn read_range(&self, range: Range<u64>) -> Result<Vec<u8>> {
+        (**self).read_range(range)
+    }
+}
+
+/// Requests made and bytes fetched by a [`RemoteContainer`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FetchStats {
+    /// Range requests sent
+    pub requests: u64,
+    /// Bytes received
+    pub bytes: u64,
+}
+
+/// Container read through byte ranges of its blocks
+///
+/// # Example
+///
+/// ```
+/// use octaindex3d::container_v2::{CellChunking, ContainerWriterV2, StreamConfig};
+/// use octaindex3d::neighbors::neighbors_index64;
+/// use octaindex3d::remote::RemoteContainer;
+/// use octaindex3d::Index64;
+/// use std::io::Cursor;
+///
+/// # fn main() -> octaindex3d::Result<()> {
+/// let chunking = CellChunking::MortonBuckets { bits: 9 };
+/// let config = StreamConfig {
+///     cell_chunking: chunking,
+///     ..StreamConfig::default()
+/// };
+/// let mut bytes = Vec::new();
+/// let mut writer = ContainerWriterV2::new(Cursor::new(&mut bytes), config)?;
+/// for x in (0..512u16).step_by(2) {
+///     for y in (0..64u16).step_by(2) {
+///         writer.write_cell(Index64::new(0, 0, 8, x, y, 0)?, &[x as u8])?;
+///     }
+/// }
+/// writer.finish()?;
+///
+/// // `bytes` stands in for an object store; only a few blocks are fetched
+/// let remote = RemoteContainer::open(&bytes)?;
+/// let center = Index64::new(0, 0, 8, 100, 20, 0)?;
+/// let mut ring = neighbors_index64(center);
+/// ring.push(center);
+/// let cells = remote.read_cells(&ring, chunking)?;
+/// assert!(cells.iter().any(|(idx, record)| *idx == center && record == &[100]));
+/// assert!(remote.stats().bytes < bytes.len() as u64 / 4);
+/// # Ok(())
+/// # }
+/// ```
+pub struct RemoteContainer<S> {
+    source: S,
+    header: HeaderV2,
+    index: BlockIndex,
+    dictionary: Option<Box<dyn Compression>>,
+    coalesce_gap: u64,
+    requests: AtomicU64,
+    bytes: AtomicU64,
+}
+
+impl<S: RangeSource> RemoteContainer<S> {
+    /// Opens the container in `source`, fetching its header, dictionary
+    /// and block index
+    pub fn open(source: S) -> Result<Self> {
+        let mut remote = Self {
+            source,
+            header: HeaderV2::new(false),
+            index: BlockIndex::default(),
+            dictionary: None,
+            coalesce_gap: DEFAULT_COALESCE_GAP,
+            requests: AtomicU64::new(0),
+            bytes: AtomicU64::new(0),
+        };
+        let header: [u8; 32] = remote
+            .fetch(0..32)?
+            .try_into()
+            .map_err(|_| Error::InvalidFormat("header is truncated".to_string()))?;
+        remote.header = HeaderV2::from_bytes(&header)?;
+        if !remote.header.has_block_index() {
+            return Err(Error::InvalidFormat(
+                "container has no block index to read remotely; compact it first".to_string(),
+            ));
+        }
+        if remote.header.has_dictionary() {
+            remote.dictionary = remote.fetch_dictionary()?;
+        }
+
+        let offset = remote.header.block_index_offset;
+        let head = remote.fetch(offset..offset + 8)?;
+        let count = u32::from_be_bytes(head[4..8].try_into().unwrap()) as u64;
+        let rest =
+            remote.fetch(offset + 8..offset + 8 + count * BLOCK_INDEX_ENTRY_LEN as u64 + 4)?;
+        remote.index = BlockIndex::decode(&[head, rest].concat())?;
+        Ok(remote)
+    }
+
+    /// Builder form setting the largest gap between wanted ranges that
+    /// are still fetched in one request; wider gaps cost bytes, narrower
+    /// ones requests
+    pub fn with_coalesce_gap(mut self, gap: u64) -> Self {
+        self.coalesce_gap = gap;
+        self
+    }
+
+    /// Container header
+    pub fn header(&self) -> &HeaderV2 {
+        &self.header
+    }
+
+    /// Block index read at open
+    pub fn block_index(&self) -> &BlockIndex {
+        &self.index
+    }
+
+    /// Requests and bytes so far, including those made at open
+    pub fn stats(&self) -> FetchStats {
+        FetchStats {
+            requests: self.requests.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
+        }
+    }
+
+    /// The source the container is read from
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+
+    /// Block `key`, or `None` if the container does not hold it
+    pub fn read_block(&self, key: Index64) -> Result<Option<Vec<u8>>> {
+        Ok(self.read_blocks([key])?.remove(&key))
+    }
+
+    /// Every block of `keys` the container holds
+    pub fn read_blocks(
+        &self,
+        keys: impl IntoIterator<Item = Index64>,
+    ) -> Result<BTreeMap<Index64, Vec<u8>>> {
+        let keys: BTreeSet<Index64> = keys.into_iter().collect();
+        let pieces = keys.iter().flat_map(|&key| self.index.get(key)).copied();
+        self.fetch_pieces(pieces.collect())
+    }
+
+    /// Blocks in one frame, tier and LOD with a key Morton code in `lo..=hi`
+    pub fn read_morton_range(
+        &self,
+        frame: FrameId,
+        tier: u8,
+        lod: u8,
+        lo: u64,
+        hi: u64,
+    ) -> Result<BTreeMap<Index64, Vec<u8>>> {
+        let pieces = self.index.morton_range(frame, tier, lod, lo, hi);
+        self.fetch_pieces(pieces.to_vec())
+    }
+
+    /// Records of the `cells` the container holds, sorted by cell, from a
+    /// container written with [`write_cell`](crate::container_v2::ContainerWriterV2::write_cell)
+    /// and `chunking`
+    ///
+    /// Only the blocks the cells fall in are fetched.
+    pub fn read_cells(&self, cells: &[Index64], chunking: CellChunking) -> Result<Vec<CellRecord>> {
+        let wanted: BTreeSet<Index64> = cells.iter().copied().collect();
+        let mut keys = BTreeSet::new();
+        for &idx in &wanted {
+            let key = chunking.bucket_key(idx)?.ok_or_else(|| {
+                Error::InvalidFormat(
+                    "cells stored in arrival order have no block to look up".to_string(),
+                )
+            })?;
+            keys.insert(key);
+        }
+        let mut out = Vec::new();
+        for payload in self.read_blocks(keys)?.values() {
+            let records = decode_cell_records(payload)?
+                .ok_or_else(|| Error::InvalidFormat("block holds no cell records".to_string()))?;
+            out.extend(records.into_iter().filter(|(idx, _)| wanted.contains(idx)));
+        }
+        out.sort_by_key(|(idx, _)| *idx);
+        Ok(out)
+    }
+
+    /// Fetches stored pieces in runs of nearby ranges and joins the pieces
+    /// of each block in index order
+    fn fetch_pieces(&self, mut pieces: Vec<BlockIndexEntry>) -> Result<BTreeMap<Index64, Vec<u8>>> {
+        let order: Vec<BlockIndexEntry> = pieces.clone();
+        pieces.sort_by_key(|piece| piece.offset);
+        let mut stored: BTreeMap<u64, Vec<u8>> = BTreeMap::new();
+        let mut run: Vec<BlockIndexEntry> = Vec::new();
+        for piece in pieces {
+            let run_end = run.last().map(|last| last.offset + last.len as u64);
+            if run_end.is_some_and(|end| piece.offset > end + self.coalesce_gap) {
+                self.fetch_run(&run, &mut stored)?;
+                run.clear();
+            }
+            run.push(piece);
+        }
+        if !run.is_empty() {
+            self.fetch_run(&run, &mut stored)?;
+        }
+
+        let mut blocks: BTreeMap<Index64, Vec<u8>> = BTreeMap::new();
+        for piece in order {
+            let payload = decode_indexed_frame(
+                &stored[&piece.offset],
+                piece.key,
+                self.dictionary.as_deref(),
+            )?;
+            blocks.entry(piece.key).or_default().extend(payload);
+        }
+        Ok(blocks)
+    }
+
+    /// Fetches the span of `run`, sorted by offset, and splits it into
+    /// its pieces
+    fn fetch_run(
+        &self,
+        run: &[BlockIndexEntry],
+        stored: &mut BTreeMap<u64, Vec<u8>>,
+    ) -> Result<()> {
+        let start = run[0].offset;
+        let end = run
+            .iter()
+            .map(|piece| piece.offset + piece.len as u64)
+            .max()
+            .unwrap_or(start);
+        let span = self.fetch(start..end)?;
+        for piece in run {
+            let at = (piece.offset - start) as usize;
+            stored.insert(piece.offset, span[at..at + piece.len as usize].to_vec());
+        }
+        Ok(())
+    }
+
+    fn fetch(&self, range: Range<u64>) -> Result<Vec<u8>> {
+        let bytes = self.source.read_range(range.clone())?;
+        if bytes.len() as u64 != range.end - range.start {
+            return Err(Error::Io(format!(
+                "range {:?} returned {} bytes",
+                range,
+                bytes.len()
+            )));
+        }
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(bytes.len() as u64, Ordering::Relaxed);
+        Ok(bytes)
+    }
+
+    #[cfg(feature = "zstd")]
+    fn fetch_dictionary(&self) -> Result<Option<Box<dyn Compression>>> {
+        let section = self.fetch(32..self.header.first_frame_offset)?;
+        let dictionary = section
+            .get(4..)
+            .ok_or_else(|| Error::InvalidFormat("dictionary section is truncated".to_string()))?;
+        Ok(Some(Box::new(
+            crate::compression::ZstdDictCompression::new(dictionary),
+        )))
+    }
+
+    #[cfg(not(feature = "zstd"))]
+    fn fetch_dictionary(&self) -> Result<Option<Box<dyn Compression>>> {
+        // Blocks compressed with it fail to decode as an unsupported codec
+        Ok(None)
+    }
+}
+
+/// Object read with HTTP range requests
+///
+/// Works with any server honouring `Range`, including S3 and compatible
+/// stores for public objects and presigned URLs.
+#[cfg(feature = "http")]
+pub struct HttpRangeSource {
+    agent: ureq::Agent,
+    url: String,
+    headers: Vec<(String, String)>,
+}
+
+#[cfg(feature = "http")]
+impl HttpRangeSource {
+    /// Source reading the object at `url`
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            agent: ureq::AgentBuilder::new().build(),
+            url: url.into(),
+            headers: Vec::new(),
+        }
+    }
+
+    /// Builder form adding a header to every request, such as
+    /// `Authorization`
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// URL of the object
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    fn request(&self, method: &str) -> ureq::Request {
+        self.headers.iter().fold(
+            self.agent.request(method, &self.url),
+            |request, (name, value)| request.set(name, value),
+        )
+    }
+
+    fn error(&self, error: impl std::fmt::Display) -> Error {
+        Error::Io(format!("{}: {}", self.url, error))
+    }
+}
+
+#[cfg(feature = "http")]
+impl RangeSource for HttpRangeSource {
+    fn size(&self) -> Result<u64> {
+        let response = self.request("HEAD").call().map_err(|e| self.error(e))?;
+        response
+            .header("Content-Length")
+            .and_then(|len| len.parse().ok())
+            .ok_or_else(|| self.error("no Content-Length"))
+    }
+
+    fn read_range(&self, range: Range<u64>) -> Result<Vec<u8>> {
+        if range.is_empty() {
+            return Ok(Vec::new());
+        }
+        let response = self
+            .request("GET")
+            .set("Range", &format!("bytes={}-{}", range.start, range.end - 1))
+            .call()
+            .map_err(|e| self.error(e))?;
+        // A server ignoring the range answers 200 with the whole object
+        if response.status() != 206 {
+            return Err(self.error(format!(
+                "expected 206 Partial Content, got {}",
+                response.status()
+            )));
+        }
+        let mut bytes = Vec::with_capacity((range.end - range.start) as usize);
+        std::io::Read::read_to_end(&mut response.into_reader(), &mut bytes)
+            .map_err(|e| self.error(e))?;
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::container_v2::{ContainerWriterV2, StreamConfig};
+    use std::io::Cursor;
+
+    fn container() -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut writer =
+            ContainerWriterV2::new(Cursor::new(&mut bytes), StreamConfig::default()).unwrap();
+        for x in 0..200u16 {
+            let data: Vec<u8> = (0..256).map(|i| (x as usize * 7 + i) as u8).collect();
+            writer
+                .write_block(Index64::new(0, 0, 5, x, 0, 0).unwrap(), &data)
+                .unwrap();
+        }
+        writer.finish().unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_reads_only_wanted_blocks() {
+        let bytes = container();
+        let remote = RemoteContainer::open(&bytes).unwrap().with_coalesce_gap(0);
+        let opened = remote.stats();
+        assert_eq!(opened.requests, 3);
+
+        let key = |x| Index64::new(0, 0, 5, x, 0, 0).unwrap();
+        let expected =
+            |x: u16| -> Vec<u8> { (0..256).map(|i| (x as usize * 7 + i) as u8).collect() };
+        assert_eq!(remote.read_block(key(42)).unwrap(), Some(expected(42)));
+        assert_eq!(remote.read_block(key(500)).unwrap(), None);
+
+        // Adjacent blocks come in one request, far ones in another
+        let blocks = remote.read_blocks([key(10), key(11), key(150)]).unwrap();
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(blocks[&key(150)], expected(150));
+        let stats = remote.stats();
+        assert_eq!(stats.requests, opened.requests + 3);
+        assert!(stats.bytes < bytes.len() as u64 / 10);
+
+        let lo = key(20).morton();
+        let hi = key(23).morton();
+        assert_eq!(remote.read_morton_range(0, 0, 5, lo, hi).unwrap().len(), 4);
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn test_http_range_source() {
+        use std::io::{BufRead, BufReader, Write};
+        use std::net::TcpListener;
+
+        let bytes = container();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/map.oct", listener.local_addr().unwrap());
+        let served = bytes.clone();
+        let server = std::thread::spawn(move || {
+            for stream in listener.incoming().take(4) {
+                let mut stream = stream.unwrap();
+                let mut range = None;
+                for line in BufReader::new(&stream).lines() {
+                    let line = line.unwrap();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some(spec) = line.strip_prefix("Range: bytes=") {
+                        let (a, b) = spec.split_once('-').unwrap();
+                        range = Some(a.parse::<usize>().unwrap()..b.parse::<usize>().unwrap() + 1);
+                    }
+                }
+                let body = &served[range.unwrap()];
+                write!(
+                    stream,
+                    "HTTP/1.1 206 Partial Content\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                )
+                .unwrap();
+                stream.write_all(body).unwrap();
+            }
+        });
+
+        let remote = RemoteContainer::open(HttpRangeSource::new(url)).unwrap();
+        let key = Index64::new(0, 0, 5, 7, 0, 0).unwrap();
+        let block = remote.read_block(key).unwrap().unwrap();
+        assert_eq!(block[0], 49);
+        server.join().unwrap();
+    }
+}
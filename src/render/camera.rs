@@ -0,0 +1,140 @@
+//! Perspective camera
+
+use crate::frustum::Frustum;
+
+/// Perspective camera in lattice units
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera {
+    /// Eye position
+    pub eye: [f64; 3],
+    /// Point looked at
+    pub target: [f64; 3],
+    /// Up direction
+    pub up: [f64; 3],
+    /// Vertical field of view (radians)
+    pub vertical_fov: f64,
+    /// Viewport width over height
+    pub aspect: f64,
+    /// Near clip distance
+    pub near: f64,
+    /// Far clip distance
+    pub far: f64,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            eye: [0.0, -10.0, 0.0],
+            target: [0.0; 3],
+            up: [0.0, 0.0, 1.0],
+            vertical_fov: std::f64::consts::FRAC_PI_3,
+            aspect: 16.0 / 9.0,
+            near: 0.1,
+            far: 1000.0,
+        }
+    }
+}
+
+impl Camera {
+    /// Camera at `eye` looking at `target`, with default lens settings
+    pub fn look_at(eye: [f64; 3], target: [f64; 3]) -> Self {
+        Self {
+            eye,
+            target,
+            ..Default::default()
+        }
+    }
+
+    /// Unit viewing direction
+    pub fn forward(&self) -> [f64; 3] {
+        normalize(sub(self.target, self.eye))
+    }
+
+    /// View frustum for culling
+    pub fn frustum(&self) -> Frustum {
+        Frustum::perspective(
+            self.eye,
+            self.forward(),
+            self.up,
+            self.vertical_fov,
+            self.aspect,
+            self.near,
+            self.far,
+        )
+    }
+
+    /// Row-major view matrix (right-handed, camera looks down -z)
+    pub fn view_matrix(&self) -> [[f64; 4]; 4] {
+        let f = self.forward();
+        let r = normalize(cross(f, self.up));
+        let u = cross(r, f);
+        let e = self.eye;
+        [
+            [r[0], r[1], r[2], -dot(r, e)],
+            [u[0], u[1], u[2], -dot(u, e)],
+            [-f[0], -f[1], -f[2], dot(f, e)],
+            [0.0, 0.0, 0.0, 1.0],
+        ]
+    }
+
+    /// Row-major projection matrix with a `[0, 1]` depth range (wgpu, Vulkan)
+    pub fn projection_matrix(&self) -> [[f64; 4]; 4] {
+        let f = 1.0 / (0.5 * self.vertical_fov).tan();
+        let (n, far) = (self.near, self.far);
+        [
+            [f / self.aspect, 0.0, 0.0, 0.0],
+            [0.0, f, 0.0, 0.0],
+            [0.0, 0.0, far / (n - far), n * far / (n - far)],
+            [0.0, 0.0, -1.0, 0.0],
+        ]
+    }
+
+    /// Row-major view-projection matrix, `clip = m · [x, y, z, 1]`
+    pub fn view_projection(&self) -> [[f64; 4]; 4] {
+        let (p, v) = (self.projection_matrix(), self.view_matrix());
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| p[i][k] * v[k][j]).sum();
+            }
+        }
+        m
+    }
+
+    /// Column-major `f32` view-projection matrix, as shader uniforms expect
+    pub fn view_projection_f32(&self) -> [[f32; 4]; 4] {
+        let m = self.view_projection();
+        let mut out = [[0.0f32; 4]; 4];
+        for (j, column) in out.iter_mut().enumerate() {
+            for (i, value) in column.iter_mut().enumerate() {
+                *value = m[i][j] as f32;
+            }
+        }
+        out
+    }
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn sub(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn normalize(a: [f64; 3]) -> [f64; 3] {
+    let len = dot(a, a).sqrt();
+    if len > 0.0 {
+        [a[0] / len, a[1] / len, a[2] / len]
+    } else {
+        a
+    }
+}
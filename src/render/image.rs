@@ -0,0 +1,144 @@
+//! RGBA images and PNG encoding
+
+use crate::error::{Error, Result};
+use std::path::Path;
+
+/// 8-bit RGBA image, rows top to bottom
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+    /// `width * height` pixels of 4 bytes, sRGB encoded
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    /// Image filled with one color
+    pub fn new(width: u32, height: u32, fill: [u8; 4]) -> Self {
+        let count = width as usize * height as usize;
+        Self {
+            width,
+            height,
+            pixels: fill.repeat(count),
+        }
+    }
+
+    /// Color of the pixel at column `x`, row `y`
+    pub fn pixel(&self, x: u32, y: u32) -> Option<[u8; 4]> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let at = 4 * (y as usize * self.width as usize + x as usize);
+        self.pixels[at..at + 4].try_into().ok()
+    }
+
+    /// Encode as PNG
+    ///
+    /// Pixel data is stored without compression, which keeps the encoder
+    /// dependency-free; recompress with an external tool if size matters.
+    pub fn to_png(&self) -> Vec<u8> {
+        let row = 4 * self.width as usize;
+        let mut raw = Vec::with_capacity((row + 1) * self.height as usize);
+        for line in self
+            .pixels
+            .chunks_exact(row.max(1))
+            .take(self.height as usize)
+        {
+            raw.push(0); // Filter type: none
+            raw.extend_from_slice(line);
+        }
+
+        let mut out = b"\x89PNG\r\n\x1a\n".to_vec();
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&self.width.to_be_bytes());
+        header.extend_from_slice(&self.height.to_be_bytes());
+        header.extend_from_slice(&[8, 6, 0, 0, 0]); // 8-bit RGBA, no interlace
+        write_chunk(&mut out, b"IHDR", &header);
+        write_chunk(&mut out, b"IDAT", &zlib_stored(&raw));
+        write_chunk(&mut out, b"IEND", &[]);
+        out
+    }
+
+    /// Write as a PNG file
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        std::fs::write(path, self.to_png()).map_err(|e| Error::Io(e.to_string()))
+    }
+}
+
+fn write_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let mut crc = crc32fast::Hasher::new();
+    crc.update(kind);
+    crc.update(data);
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    out.extend_from_slice(&crc.finalize().to_be_bytes());
+}
+
+/// zlib stream made of stored (uncompressed) deflate blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    const BLOCK: usize = 65_535;
+    let mut out = Vec::with_capacity(data.len() + 6 + 5 * (data.len() / BLOCK + 1));
+    out.extend_from_slice(&[0x78, 0x01]);
+    let mut blocks = data.chunks(BLOCK).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let len = block.len() as u16;
+        out.push(blocks.peek().is_none() as u8);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= 65_521;
+        b %= 65_521;
+    }
+    out.extend_from_slice(&((b << 16) | a).to_be_bytes());
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_png_layout() {
+        let mut image = Image::new(3, 2, [10, 20, 30, 255]);
+        image.pixels[4..8].copy_from_slice(&[1, 2, 3, 4]);
+        assert_eq!(image.pixel(1, 0), Some([1, 2, 3, 4]));
+        assert_eq!(image.pixel(2, 1), Some([10, 20, 30, 255]));
+        assert_eq!(image.pixel(3, 0), None);
+
+        let png = image.to_png();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[16..24], &[0, 0, 0, 3, 0, 0, 0, 2]);
+        assert_eq!(&png[png.len() - 8..png.len() - 4], b"IEND");
+
+        // IDAT holds one stored block: 2 rows of filter byte + 12 bytes
+        let idat = &png[33..];
+        let len = u32::from_be_bytes(idat[..4].try_into().unwrap()) as usize;
+        assert_eq!(&idat[4..8], b"IDAT");
+        let zlib = &idat[8..8 + len];
+        assert_eq!(&zlib[..3], &[0x78, 0x01, 1]);
+        assert_eq!(u16::from_le_bytes([zlib[3], zlib[4]]), 26);
+        assert_eq!(&zlib[7..12], &[0, 10, 20, 30, 255]);
+        // Adler-32 of the raw scanlines
+        let raw = &zlib[7..7 + 26];
+        let a = 1 + raw.iter().map(|&v| v as u32).sum::<u32>();
+        let b: u32 = (0..raw.len())
+            .map(|i| 1 + raw[..=i].iter().map(|&v| v as u32).sum::<u32>())
+            .sum();
+        assert_eq!(&zlib[33..], &((b << 16) | a).to_be_bytes());
+    }
+}
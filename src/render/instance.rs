@@ -0,0 +1,252 @@
+//! Packed per-cell instance data for instanced drawing
+
+use super::Camera;
+use crate::ids::Index64;
+use bytemuck::{Pod, Zeroable};
+
+/// One instance of the unit cell mesh
+///
+/// 32 bytes: the vertex shader computes
+/// `world = position + scale * mesh_vertex` and passes `color` through.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct CellInstance {
+    /// Cell center (lattice units)
+    pub position: [f32; 3],
+    /// Uniform scale applied to the unit mesh
+    pub scale: f32,
+    /// Linear RGBA color
+    pub color: [f32; 4],
+}
+
+/// Vertex of [`truncated_octahedron_mesh`]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct MeshVertex {
+    /// Position relative to the cell center
+    pub position: [f32; 3],
+    /// Outward face normal
+    pub normal: [f32; 3],
+}
+
+/// Frustum-culled instances ready for upload
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InstanceBuffer {
+    /// Visible instances, nearest to the camera first
+    pub instances: Vec<CellInstance>,
+}
+
+impl InstanceBuffer {
+    /// Size of one instance in bytes
+    pub const STRIDE: usize = std::mem::size_of::<CellInstance>();
+
+    /// Raw bytes for a GPU vertex buffer with per-instance step mode
+    pub fn as_bytes(&self) -> &[u8] {
+        bytemuck::cast_slice(&self.instances)
+    }
+
+    /// Number of instances
+    pub fn len(&self) -> usize {
+        self.instances.len()
+    }
+
+    /// Check if no cell is visible
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty()
+    }
+}
+
+/// Instances for the visible cells, in a uniform light grey
+///
+/// See [`instance_buffer_with`].
+pub fn instance_buffer<I>(cells: I, camera: &Camera) -> InstanceBuffer
+where
+    I: IntoIterator<Item = Index64>,
+{
+    instance_buffer_with(cells, camera, |_| [0.8, 0.8, 0.8, 1.0])
+}
+
+/// Instances for the visible cells, colored per cell
+///
+/// Cells are culled against the camera frustum with their full
+/// truncated-octahedron bounds and sorted front to back, which lets early
+/// depth testing reject hidden fragments. Positions are the cell
+/// coordinates; the camera is expressed in the same lattice units.
+pub fn instance_buffer_with<I, F>(cells: I, camera: &Camera, mut color: F) -> InstanceBuffer
+where
+    I: IntoIterator<Item = Index64>,
+    F: FnMut(Index64) -> [f32; 4],
+{
+    let frustum = camera.frustum();
+    let eye = camera.eye;
+    let mut visible: Vec<(f64, CellInstance)> = frustum
+        .cull(cells)
+        .map(|idx| {
+            let (x, y, z) = idx.decode_coords();
+            let d = [x as f64 - eye[0], y as f64 - eye[1], z as f64 - eye[2]];
+            let instance = CellInstance {
+                position: [x as f32, y as f32, z as f32],
+                scale: 1.0,
+                color: color(idx),
+            };
+            (d[0] * d[0] + d[1] * d[1] + d[2] * d[2], instance)
+        })
+        .collect();
+    visible.sort_by(|a, b| a.0.total_cmp(&b.0));
+    InstanceBuffer {
+        instances: visible.into_iter().map(|(_, instance)| instance).collect(),
+    }
+}
+
+/// Triangle mesh of the unit BCC Voronoi cell, a truncated octahedron
+///
+/// The cell of the lattice point at the origin: square faces at `±1` on
+/// each axis and hexagonal faces at `|x| + |y| + |z| = 1.5`, so instances
+/// at neighboring lattice points tile space without gaps. Returns 72
+/// vertices (flat-shaded, four per square and six per hexagon) and 132
+/// counter-clockwise indices.
+pub fn truncated_octahedron_mesh() -> (Vec<MeshVertex>, Vec<u16>) {
+    // Vertices: all permutations of (0, ±1/2, ±1)
+    let mut corners = Vec::with_capacity(24);
+    for perm in [
+        [0, 1, 2],
+        [0, 2, 1],
+        [1, 0, 2],
+        [1, 2, 0],
+        [2, 0, 1],
+        [2, 1, 0],
+    ] {
+        for (a, b) in [(0.5, 1.0), (0.5, -1.0), (-0.5, 1.0), (-0.5, -1.0)] {
+            let mut v = [0.0f64; 3];
+            v[perm[1]] = a;
+            v[perm[2]] = b;
+            corners.push(v);
+        }
+    }
+
+    let mut normals: Vec<[f64; 3]> = Vec::with_capacity(14);
+    for axis in 0..3 {
+        for sign in [1.0, -1.0] {
+            let mut n = [0.0; 3];
+            n[axis] = sign;
+            normals.push(n);
+        }
+    }
+    for i in 0..8 {
+        let s = |bit: usize| if i & (1 << bit) == 0 { 1.0 } else { -1.0 };
+        let k = 1.0 / 3f64.sqrt();
+        normals.push([s(0) * k, s(1) * k, s(2) * k]);
+    }
+
+    let dot = |a: [f64; 3], b: [f64; 3]| a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+    let mut vertices = Vec::with_capacity(72);
+    let mut indices = Vec::with_capacity(132);
+    for n in normals {
+        let support = corners.iter().map(|&v| dot(n, v)).fold(f64::MIN, f64::max);
+        let mut face: Vec<[f64; 3]> = corners
+            .iter()
+            .copied()
+            .filter(|&v| (dot(n, v) - support).abs() < 1e-9)
+            .collect();
+
+        // Order the face vertices counter-clockwise around the normal
+        let count = face.len() as f64;
+        let c = face.iter().fold([0.0; 3], |acc, v| {
+            [
+                acc[0] + v[0] / count,
+                acc[1] + v[1] / count,
+                acc[2] + v[2] / count,
+            ]
+        });
+        let u = [face[0][0] - c[0], face[0][1] - c[1], face[0][2] - c[2]];
+        let w = [
+            n[1] * u[2] - n[2] * u[1],
+            n[2] * u[0] - n[0] * u[2],
+            n[0] * u[1] - n[1] * u[0],
+        ];
+        let angle = |v: &[f64; 3]| {
+            let d = [v[0] - c[0], v[1] - c[1], v[2] - c[2]];
+            dot(d, w).atan2(dot(d, u))
+        };
+        face.sort_by(|a, b| angle(a).total_cmp(&angle(b)));
+
+        let base = vertices.len() as u16;
+        for v in &face {
+            vertices.push(MeshVertex {
+                position: [v[0] as f32, v[1] as f32, v[2] as f32],
+                normal: [n[0] as f32, n[1] as f32, n[2] as f32],
+            });
+        }
+        for i in 1..face.len() as u16 - 1 {
+            indices.extend_from_slice(&[base, base + i, base + i + 1]);
+        }
+    }
+    (vertices, indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Result;
+
+    #[test]
+    fn test_mesh_is_closed_unit_cell() {
+        let (vertices, indices) = truncated_octahedron_mesh();
+        assert_eq!(vertices.len(), 72);
+        assert_eq!(indices.len(), 132);
+
+        // Divergence theorem: BCC cells with this spacing have volume 4
+        let mut volume = 0.0f64;
+        for tri in indices.chunks(3) {
+            let p = |i: u16| vertices[i as usize].position.map(|c| c as f64);
+            let (a, b, c) = (p(tri[0]), p(tri[1]), p(tri[2]));
+            let cross = [
+                b[1] * c[2] - b[2] * c[1],
+                b[2] * c[0] - b[0] * c[2],
+                b[0] * c[1] - b[1] * c[0],
+            ];
+            volume += (a[0] * cross[0] + a[1] * cross[1] + a[2] * cross[2]) / 6.0;
+
+            // Winding agrees with the stored normal
+            let n = vertices[tri[0] as usize].normal.map(|c| c as f64);
+            let e1 = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+            let e2 = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+            let face = [
+                e1[1] * e2[2] - e1[2] * e2[1],
+                e1[2] * e2[0] - e1[0] * e2[2],
+                e1[0] * e2[1] - e1[1] * e2[0],
+            ];
+            assert!(face[0] * n[0] + face[1] * n[1] + face[2] * n[2] > 0.0);
+        }
+        assert!((volume - 4.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_instances_are_culled_and_sorted() -> Result<()> {
+        let camera = Camera {
+            near: 1.0,
+            far: 100.0,
+            ..Camera::look_at([0.0, 50.0, 50.0], [100.0, 50.0, 50.0])
+        };
+        let cells: Vec<Index64> = (0..200u16)
+            .step_by(2)
+            .map(|x| Index64::new(0, 0, 5, x, 50, 50))
+            .collect::<Result<_>>()?;
+
+        let buffer = instance_buffer_with(cells, &camera, |idx| {
+            let (x, _, _) = idx.decode_coords();
+            [x as f32 / 200.0, 0.0, 0.0, 1.0]
+        });
+        // Cells at x = 0..=100 reach between the near and far planes
+        assert_eq!(buffer.len(), 51);
+        assert_eq!(buffer.instances[0].position, [0.0, 50.0, 50.0]);
+        assert!(buffer
+            .instances
+            .windows(2)
+            .all(|w| w[0].position[0] < w[1].position[0]));
+        assert_eq!(buffer.as_bytes().len(), 51 * InstanceBuffer::STRIDE);
+        assert_eq!(InstanceBuffer::STRIDE, 32);
+        assert_eq!(buffer.instances[1].color[0], 2.0 / 200.0);
+        Ok(())
+    }
+}
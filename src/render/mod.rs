@@ -0,0 +1,29 @@
+//! Rendering helpers for lattice cells
+//!
+//! Prepares GPU-ready data so applications can draw large numbers of cells
+//! without writing conversion code:
+//!
+//! - [`Camera`]: perspective camera producing view-projection matrices and
+//!   [`Frustum`](crate::frustum::Frustum)s
+//! - [`instance_buffer`]: frustum-culled, packed per-cell instances for
+//!   drawing one [`truncated_octahedron_mesh`] many times
+//! - `offscreen` (feature `gpu-vulkan`): headless wgpu rendering of a map
+//!   to an [`Image`], which encodes to PNG
+//!
+//! Instance and vertex types are `#[repr(C)]` and implement
+//! [`bytemuck::Pod`], so buffers upload directly to wgpu or OpenGL.
+
+mod camera;
+mod image;
+mod instance;
+#[cfg(all(feature = "gpu-vulkan", not(target_os = "windows")))]
+mod offscreen;
+
+pub use camera::Camera;
+pub use image::Image;
+pub use instance::{
+    instance_buffer, instance_buffer_with, truncated_octahedron_mesh, CellInstance, InstanceBuffer,
+    MeshVertex,
+};
+#[cfg(all(feature = "gpu-vulkan", not(target_os = "windows")))]
+pub use offscreen::{offscreen, OffscreenRenderer};
@@ -0,0 +1,408 @@
+//! Headless rendering to images with wgpu
+
+use super::{instance_buffer_with, truncated_octahedron_mesh, Camera, Image, InstanceBuffer};
+use crate::error::{Error, Result};
+use crate::layers::{LayerType, LayeredMap};
+use wgpu::util::DeviceExt;
+
+const COLOR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+/// Background color (linear RGBA)
+const BACKGROUND: wgpu::Color = wgpu::Color {
+    r: 0.02,
+    g: 0.02,
+    b: 0.025,
+    a: 1.0,
+};
+
+/// Offscreen renderer holding a GPU device and the cell pipeline
+///
+/// Creating a device is expensive; keep one renderer around when producing
+/// many images. No window or display server is needed.
+pub struct OffscreenRenderer {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::RenderPipeline,
+    mesh: wgpu::Buffer,
+    indices: wgpu::Buffer,
+    index_count: u32,
+}
+
+impl OffscreenRenderer {
+    /// Create a renderer on the first available adapter
+    pub fn new() -> Result<Self> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::all(),
+            ..wgpu::InstanceDescriptor::new_without_display_handle()
+        });
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            compatible_surface: None,
+            force_fallback_adapter: false,
+        }))
+        .map_err(|e| Error::InvalidFormat(format!("No suitable GPU adapter found: {}", e)))?;
+        let (device, queue) = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+            label: Some("OctaIndex3D Offscreen Device"),
+            required_features: wgpu::Features::empty(),
+            required_limits: wgpu::Limits::downlevel_defaults(),
+            experimental_features: wgpu::ExperimentalFeatures::default(),
+            memory_hints: Default::default(),
+            trace: wgpu::Trace::default(),
+        }))
+        .map_err(|e| Error::InvalidFormat(format!("Failed to create device: {}", e)))?;
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Cell Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/cells.wgsl").into()),
+        });
+        let vertex_buffers = [
+            wgpu::VertexBufferLayout {
+                array_stride: std::mem::size_of::<super::MeshVertex>() as u64,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3],
+            },
+            wgpu::VertexBufferLayout {
+                array_stride: InstanceBuffer::STRIDE as u64,
+                step_mode: wgpu::VertexStepMode::Instance,
+                attributes: &wgpu::vertex_attr_array![
+                    2 => Float32x3,
+                    3 => Float32,
+                    4 => Float32x4
+                ],
+            },
+        ];
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Cell Pipeline"),
+            layout: None,
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                compilation_options: Default::default(),
+                buffers: &vertex_buffers,
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DEPTH_FORMAT,
+                depth_write_enabled: Some(true),
+                depth_compare: Some(wgpu::CompareFunction::Less),
+                stencil: Default::default(),
+                bias: Default::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: COLOR_FORMAT,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview_mask: None,
+            cache: None,
+        });
+
+        let (vertices, indices) = truncated_octahedron_mesh();
+        let mesh = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Cell Mesh Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Cell Index Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        Ok(Self {
+            device,
+            queue,
+            pipeline,
+            mesh,
+            indices: index_buffer,
+            index_count: indices.len() as u32,
+        })
+    }
+
+    /// Render the occupied voxels of a map
+    ///
+    /// Draws the occupancy layer's voxels with probability ≥ 0.5, colored
+    /// by height. See [`OffscreenRenderer::render_instances`].
+    pub fn render_map(
+        &self,
+        map: &LayeredMap,
+        camera: &Camera,
+        resolution: (u32, u32),
+    ) -> Result<Image> {
+        let camera = fit_aspect(camera, resolution);
+        let cells: Vec<_> = map
+            .frustum_cull(LayerType::Occupancy, &camera.frustum())
+            .into_iter()
+            .filter(|&idx| map.query_occupancy(idx).is_some_and(|p| p >= 0.5))
+            .collect();
+        let (low, high) = cells
+            .iter()
+            .map(|idx| idx.decode_coords().2)
+            .fold((u16::MAX, 0), |(lo, hi), z| (lo.min(z), hi.max(z)));
+        let span = high.saturating_sub(low).max(1) as f32;
+        let instances = instance_buffer_with(cells, &camera, |idx| {
+            height_color((idx.decode_coords().2.saturating_sub(low)) as f32 / span)
+        });
+        self.render_instances(&instances, &camera, resolution)
+    }
+
+    /// Render prepared instances
+    ///
+    /// The camera's aspect ratio is replaced by that of `resolution`.
+    pub fn render_instances(
+        &self,
+        instances: &InstanceBuffer,
+        camera: &Camera,
+        resolution: (u32, u32),
+    ) -> Result<Image> {
+        let (width, height) = resolution;
+        let max = self.device.limits().max_texture_dimension_2d;
+        if width == 0 || height == 0 || width > max || height > max {
+            return Err(Error::OutOfRange(format!(
+                "resolution {}x{} outside 1..={}",
+                width, height, max
+            )));
+        }
+        let camera = fit_aspect(camera, resolution);
+
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let target = |label, format, usage| {
+            self.device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(label),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage,
+                view_formats: &[],
+            })
+        };
+        let color = target(
+            "Offscreen Color",
+            COLOR_FORMAT,
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        );
+        let depth = target(
+            "Offscreen Depth",
+            DEPTH_FORMAT,
+            wgpu::TextureUsages::RENDER_ATTACHMENT,
+        );
+        let color_view = color.create_view(&Default::default());
+        let depth_view = depth.create_view(&Default::default());
+
+        let forward = camera.forward();
+        let light = normalize([
+            -forward[0] + 0.5 * camera.up[0],
+            -forward[1] + 0.5 * camera.up[1],
+            -forward[2] + 0.5 * camera.up[2],
+        ]);
+        let mut uniforms = [0.0f32; 20];
+        for (j, column) in camera.view_projection_f32().iter().enumerate() {
+            uniforms[4 * j..4 * j + 4].copy_from_slice(column);
+        }
+        uniforms[16..19].copy_from_slice(&light.map(|c| c as f32));
+        let uniform_buffer = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Offscreen Uniforms"),
+                contents: bytemuck::cast_slice(&uniforms),
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Offscreen Bind Group"),
+            layout: &self.pipeline.get_bind_group_layout(0),
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+        let instance_data = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Cell Instance Buffer"),
+                contents: instances.as_bytes(),
+                usage: wgpu::BufferUsages::VERTEX,
+            });
+
+        // Rows of a texture-to-buffer copy must be 256-byte aligned
+        let row = 4 * width;
+        let padded_row =
+            row.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT) * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let readback = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Offscreen Readback Buffer"),
+            size: padded_row as u64 * height as u64,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Offscreen Encoder"),
+            });
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Offscreen Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &color_view,
+                    depth_slice: None,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(BACKGROUND),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Discard,
+                    }),
+                    stencil_ops: None,
+                }),
+                ..Default::default()
+            });
+            if !instances.is_empty() {
+                pass.set_pipeline(&self.pipeline);
+                pass.set_bind_group(0, &bind_group, &[]);
+                pass.set_vertex_buffer(0, self.mesh.slice(..));
+                pass.set_vertex_buffer(1, instance_data.slice(..));
+                pass.set_index_buffer(self.indices.slice(..), wgpu::IndexFormat::Uint16);
+                pass.draw_indexed(0..self.index_count, 0, 0..instances.len() as u32);
+            }
+        }
+        encoder.copy_texture_to_buffer(
+            color.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &readback,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            size,
+        );
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = readback.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.device
+            .poll(wgpu::PollType::Wait {
+                submission_index: None,
+                timeout: None,
+            })
+            .map_err(|e| Error::InvalidFormat(format!("Failed to poll device: {}", e)))?;
+        receiver
+            .recv()
+            .map_err(|e| Error::InvalidFormat(format!("Failed to receive buffer mapping: {}", e)))?
+            .map_err(|e| Error::InvalidFormat(format!("Failed to map buffer: {}", e)))?;
+
+        let data = slice.get_mapped_range();
+        let mut pixels = Vec::with_capacity(row as usize * height as usize);
+        for line in data.chunks_exact(padded_row as usize) {
+            pixels.extend_from_slice(&line[..row as usize]);
+        }
+        drop(data);
+        readback.unmap();
+
+        Ok(Image {
+            width,
+            height,
+            pixels,
+        })
+    }
+}
+
+/// Render the occupied voxels of a map to an image without a window
+///
+/// Convenience wrapper creating a one-off [`OffscreenRenderer`]. Fails if
+/// no GPU adapter (hardware or software) is available.
+pub fn offscreen(map: &LayeredMap, camera: &Camera, resolution: (u32, u32)) -> Result<Image> {
+    OffscreenRenderer::new()?.render_map(map, camera, resolution)
+}
+
+fn fit_aspect(camera: &Camera, (width, height): (u32, u32)) -> Camera {
+    Camera {
+        aspect: width.max(1) as f64 / height.max(1) as f64,
+        ..*camera
+    }
+}
+
+/// Blue → green → yellow ramp over `t` in `[0, 1]`
+fn height_color(t: f32) -> [f32; 4] {
+    let t = t.clamp(0.0, 1.0);
+    if t < 0.5 {
+        let s = 2.0 * t;
+        [0.1, 0.2 + 0.5 * s, 0.8 - 0.5 * s, 1.0]
+    } else {
+        let s = 2.0 * t - 1.0;
+        [0.1 + 0.8 * s, 0.7 + 0.2 * s, 0.3 - 0.2 * s, 1.0]
+    }
+}
+
+fn normalize(v: [f64; 3]) -> [f64; 3] {
+    let len = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    if len > 0.0 {
+        v.map(|c| c / len)
+    } else {
+        [0.0, 0.0, 1.0]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layers::OccupancyLayer;
+    use crate::Index64;
+
+    #[test]
+    fn test_offscreen_render() {
+        let renderer = match OffscreenRenderer::new() {
+            Ok(r) => r,
+            Err(_) => return, // Skip if no adapter is available
+        };
+
+        let mut layer = OccupancyLayer::new();
+        for x in (0..20u16).step_by(2) {
+            for y in (0..20u16).step_by(2) {
+                let idx = Index64::new(0, 0, 5, x + 10, y + 10, 10).unwrap();
+                layer.update_occupancy(idx, true, 0.9);
+            }
+        }
+        let mut map = LayeredMap::new();
+        map.add_occupancy_layer(layer);
+
+        let camera = Camera::look_at([19.0, 19.0, 60.0], [19.0, 19.0, 10.0]);
+        let camera = Camera {
+            up: [0.0, 1.0, 0.0],
+            ..camera
+        };
+        let image = renderer.render_map(&map, &camera, (64, 48)).unwrap();
+        assert_eq!((image.width, image.height), (64, 48));
+        assert_eq!(image.pixels.len(), 64 * 48 * 4);
+        // The slab fills the center and leaves the corners as background
+        let center = image.pixel(32, 24).unwrap();
+        let corner = image.pixel(0, 0).unwrap();
+        assert_ne!(center, corner);
+
+        assert!(renderer
+            .render_instances(&InstanceBuffer::default(), &camera, (0, 10))
+            .is_err());
+    }
+}
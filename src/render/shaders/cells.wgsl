@@ -0,0 +1,36 @@
+// Instanced truncated-octahedron cells with simple directional lighting
+
+struct Uniforms {
+    view_proj: mat4x4<f32>,
+    light_dir: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> uniforms: Uniforms;
+
+struct VertexOutput {
+    @builtin(position) clip: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    @location(1) normal: vec3<f32>,
+};
+
+@vertex
+fn vs_main(
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) center: vec3<f32>,
+    @location(3) scale: f32,
+    @location(4) color: vec4<f32>,
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip = uniforms.view_proj * vec4<f32>(center + scale * position, 1.0);
+    out.color = color;
+    out.normal = normal;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let diffuse = max(dot(normalize(in.normal), uniforms.light_dir.xyz), 0.0);
+    return vec4<f32>(in.color.rgb * (0.35 + 0.65 * diffuse), in.color.a);
+}
@@ -0,0 +1,1576 @@
+//! Cost-driven routing on modern [`Route64`] cells
+//!
+//! [`BccGrid`](crate::grid::BccGrid) covers the common case of shortest
+//! paths with a traversability predicate. This module adds pluggable edge
+//! costs through the [`Cost`] trait and planners built on top of it:
+//!
+//! - [`astar`]: optimal A* that, for costs declared uniform, prunes
+//!   symmetric paths the way Jump Point Search does on square grids.
+//! - [`astar_anytime`]: Anytime Repairing A* (ARA*) that returns a valid,
+//!   possibly suboptimal path quickly and keeps improving it until a time
+//!   budget expires, reporting the achieved suboptimality bound.
+//! - [`travel_costs`]: many-to-many travel cost matrix, e.g. for assigning
+//!   robots to exploration frontiers.
+//! - [`astar_risk_bounded`]: cheapest path whose cumulative collision risk
+//!   stays under a bound, with diagnostics when no such path exists.
+//!
+//! [`AltitudeBandCost`] keeps paths at a height band above terrain, and
+//! [`CompositeCost`] layers penalty terms and compiled
+//! [geofences](crate::geofence) on top of any base cost.
+//!
+//! Costs are expressed in lattice units: under [`EuclideanCost`] a diagonal
+//! step costs √3 and an axial step costs 2. Multiply by
+//! `BccGrid::cell_size() / 2` to convert to physical units.
+//!
+//! # Example
+//!
+//! ```
+//! use octaindex3d::routing::{astar_anytime, EuclideanCost};
+//! use octaindex3d::Route64;
+//! use std::time::Duration;
+//!
+//! # fn main() -> octaindex3d::Result<()> {
+//! let start = Route64::new(0, 0, 0, 0)?;
+//! let goal = Route64::new(0, 20, 10, 0)?;
+//!
+//! let result = astar_anytime(start, goal, &EuclideanCost, Duration::from_millis(10))?;
+//! assert_eq!(result.cells.first(), Some(&start));
+//! assert_eq!(result.cells.last(), Some(&goal));
+//! // The path cost is within `epsilon` of optimal
+//! assert!(result.epsilon >= 1.0);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{Error, Result};
+use crate::geofence::FenceMap;
+use crate::grid::{lattice_distance, BccGrid};
+use crate::ids::{Index64, Route64};
+use crate::lattice::BCC_NEIGHBORS_14;
+use crate::layers::{OccupancyLayer, TerrainLayer, UncertaintyLayer};
+use crate::neighbors::neighbors_route64;
+use ordered_float::OrderedFloat;
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::time::{Duration, Instant};
+
+/// Edge cost and heuristic used by the planners in this module
+///
+/// A cost of `f64::INFINITY` marks the move as impassable. For the
+/// suboptimality bounds reported by the planners to hold, `heuristic` must
+/// never overestimate the remaining cost (it must be admissible).
+pub trait Cost {
+    /// Cost of moving from `from` to its neighbor `to`
+    fn cost(&self, from: Route64, to: Route64) -> f64;
+
+    /// Lower-bound estimate of the cost from `from` to `goal`
+    ///
+    /// Defaults to the Euclidean lattice distance, which is admissible for
+    /// any cost that is at least the distance travelled.
+    fn heuristic(&self, from: Route64, goal: Route64) -> f64 {
+        lattice_distance(from, goal)
+    }
+
+    /// Whether every passable move costs exactly its Euclidean length
+    ///
+    /// Uniform costs may only differ from [`EuclideanCost`] by blocking
+    /// moves (returning `f64::INFINITY`). Declaring a cost uniform enables
+    /// symmetry pruning in [`astar`]; declaring a non-uniform cost uniform
+    /// can produce suboptimal paths.
+    fn is_uniform(&self) -> bool {
+        false
+    }
+}
+
+/// Pure distance cost: every move costs its Euclidean length in lattice units
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EuclideanCost;
+
+impl Cost for EuclideanCost {
+    fn cost(&self, from: Route64, to: Route64) -> f64 {
+        lattice_distance(from, to)
+    }
+
+    fn is_uniform(&self) -> bool {
+        true
+    }
+}
+
+/// Distance cost restricted to cells accepted by a predicate
+///
+/// Adapts the `traversable` closures used by
+/// [`BccGrid::astar_where`](crate::grid::BccGrid::astar_where) to [`Cost`].
+#[derive(Debug, Clone, Copy)]
+pub struct PredicateCost<F> {
+    traversable: F,
+}
+
+impl<F> PredicateCost<F>
+where
+    F: Fn(Route64) -> bool,
+{
+    /// Create a cost that blocks every cell for which `traversable` is false
+    pub fn new(traversable: F) -> Self {
+        Self { traversable }
+    }
+}
+
+impl<F> Cost for PredicateCost<F>
+where
+    F: Fn(Route64) -> bool,
+{
+    fn cost(&self, from: Route64, to: Route64) -> f64 {
+        if (self.traversable)(to) {
+            lattice_distance(from, to)
+        } else {
+            f64::INFINITY
+        }
+    }
+
+    fn is_uniform(&self) -> bool {
+        true
+    }
+}
+
+impl<C: Cost + ?Sized> Cost for &C {
+    fn cost(&self, from: Route64, to: Route64) -> f64 {
+        (**self).cost(from, to)
+    }
+
+    fn heuristic(&self, from: Route64, goal: Route64) -> f64 {
+        (**self).heuristic(from, goal)
+    }
+
+    fn is_uniform(&self) -> bool {
+        (**self).is_uniform()
+    }
+}
+
+/// Base cost plus weighted penalty terms and geofences
+///
+/// The cost of a move is the base cost plus each term's cost times its
+/// weight, plus the restricted-zone penalties of any [`FenceMap`] for the
+/// destination cell. Moves into no-fly cells, or that any part rejects,
+/// are impassable. The heuristic is the base heuristic, which stays
+/// admissible because terms and penalties are assumed non-negative.
+///
+/// # Example
+///
+/// ```
+/// use octaindex3d::routing::{astar, CompositeCost, EuclideanCost, PredicateCost};
+/// use octaindex3d::Route64;
+///
+/// # fn main() -> octaindex3d::Result<()> {
+/// // A zero-weight term only contributes the moves it blocks
+/// let cost = CompositeCost::new(EuclideanCost)
+///     .with_term(0.0, PredicateCost::new(|c: Route64| c.z() >= 0));
+/// let path = astar(Route64::new(0, 0, 0, 0)?, Route64::new(0, 8, 0, 0)?, &cost)?;
+/// assert!(path.cells.iter().all(|c| c.z() >= 0));
+/// # Ok(())
+/// # }
+/// ```
+pub struct CompositeCost<'a> {
+    base: Box<dyn Cost + 'a>,
+    terms: Vec<(f64, Box<dyn Cost + 'a>)>,
+    fences: Vec<&'a FenceMap>,
+}
+
+impl<'a> CompositeCost<'a> {
+    /// Create a composite around a base cost
+    pub fn new(base: impl Cost + 'a) -> Self {
+        Self {
+            base: Box::new(base),
+            terms: Vec::new(),
+            fences: Vec::new(),
+        }
+    }
+
+    /// Add a weighted cost term
+    pub fn with_term(mut self, weight: f64, term: impl Cost + 'a) -> Self {
+        self.terms.push((weight, Box::new(term)));
+        self
+    }
+
+    /// Enforce compiled geofences
+    pub fn with_fences(mut self, fences: &'a FenceMap) -> Self {
+        self.fences.push(fences);
+        self
+    }
+}
+
+impl Cost for CompositeCost<'_> {
+    fn cost(&self, from: Route64, to: Route64) -> f64 {
+        if self.fences.iter().any(|f| f.is_blocked(to)) {
+            return f64::INFINITY;
+        }
+        let mut total = self.base.cost(from, to);
+        for (weight, term) in &self.terms {
+            let c = term.cost(from, to);
+            total += if c.is_infinite() { c } else { weight * c };
+        }
+        let penalty: f64 = self.fences.iter().map(|f| f.penalty(to)).sum();
+        if penalty > 0.0 {
+            total += penalty * lattice_distance(from, to);
+        }
+        total
+    }
+
+    fn heuristic(&self, from: Route64, goal: Route64) -> f64 {
+        self.base.heuristic(from, goal)
+    }
+
+    /// Uniform when the base is uniform and nothing adds cost beyond blocking
+    fn is_uniform(&self) -> bool {
+        self.base.is_uniform()
+            && self.terms.is_empty()
+            && !self.fences.iter().any(|f| f.has_penalties())
+    }
+}
+
+/// Keeps paths within a height band above terrain
+///
+/// Blocks every cell whose center is less than `min_agl` or more than
+/// `max_agl` above the ground of a [`TerrainLayer`], as well as cells over
+/// parts of the terrain without elevation data. Without a target height the
+/// cost is [uniform](Cost::is_uniform); [`AltitudeBandCost::with_target`]
+/// additionally penalizes deviation from a preferred height, for surveys
+/// that must hold a constant AGL altitude.
+///
+/// # Example
+///
+/// ```
+/// use octaindex3d::grid::BccGrid;
+/// use octaindex3d::layers::TerrainLayer;
+/// use octaindex3d::routing::{astar, AltitudeBandCost};
+///
+/// # fn main() -> octaindex3d::Result<()> {
+/// // A ramp rising 1 m per 4 m eastwards
+/// let heights: Vec<f32> = (0..21 * 21).map(|i| (i % 21) as f32).collect();
+/// let terrain = TerrainLayer::new((0.0, 0.0), 4.0, 21, 21, heights)?;
+///
+/// let grid = BccGrid::new(1.0)?;
+/// let band = AltitudeBandCost::new(grid, &terrain, 2.0, 5.0);
+/// let start = grid.cell_at(2.0, 10.0, 4.0)?;
+/// let goal = grid.cell_at(40.0, 10.0, 14.0)?;
+/// let path = astar(start, goal, &band)?;
+///
+/// for &cell in &path.cells {
+///     let (x, y, z) = grid.center_of(cell);
+///     let agl = terrain.height_above_ground(x, y, z).unwrap();
+///     assert!((2.0..=5.0).contains(&agl));
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct AltitudeBandCost<'a> {
+    grid: BccGrid,
+    terrain: &'a TerrainLayer,
+    min_agl: f64,
+    max_agl: f64,
+    target: Option<(f64, f64)>,
+}
+
+impl<'a> AltitudeBandCost<'a> {
+    /// Restrict cells of `grid` to heights in `[min_agl, max_agl]` above `terrain`
+    pub fn new(grid: BccGrid, terrain: &'a TerrainLayer, min_agl: f64, max_agl: f64) -> Self {
+        Self {
+            grid,
+            terrain,
+            min_agl,
+            max_agl,
+            target: None,
+        }
+    }
+
+    /// Penalize deviation from a preferred height above ground
+    ///
+    /// Each move costs an extra `weight × |agl - target_agl|` per lattice
+    /// unit travelled, with `agl` measured at the destination cell in grid
+    /// cell sizes.
+    pub fn with_target(mut self, target_agl: f64, weight: f64) -> Self {
+        self.target = Some((target_agl, weight));
+        self
+    }
+
+    /// Height of a cell center above ground, if the terrain covers it
+    pub fn agl(&self, cell: Route64) -> Option<f64> {
+        let (x, y, z) = self.grid.center_of(cell);
+        self.terrain.height_above_ground(x, y, z)
+    }
+}
+
+impl Cost for AltitudeBandCost<'_> {
+    fn cost(&self, from: Route64, to: Route64) -> f64 {
+        match self.agl(to) {
+            Some(agl) if (self.min_agl..=self.max_agl).contains(&agl) => {
+                let distance = lattice_distance(from, to);
+                match self.target {
+                    Some((target, weight)) => {
+                        let deviation = (agl - target).abs() / self.grid.cell_size();
+                        distance * (1.0 + weight * deviation)
+                    }
+                    None => distance,
+                }
+            }
+            _ => f64::INFINITY,
+        }
+    }
+
+    fn is_uniform(&self) -> bool {
+        self.target.is_none()
+    }
+}
+
+/// Per-cell collision probability used by [`astar_risk_bounded`]
+///
+/// Implemented for closures `Fn(Route64) -> f64`.
+pub trait CollisionRisk {
+    /// Probability in `[0, 1]` of colliding when entering `cell`
+    fn collision_probability(&self, cell: Route64) -> f64;
+}
+
+impl<F> CollisionRisk for F
+where
+    F: Fn(Route64) -> f64,
+{
+    fn collision_probability(&self, cell: Route64) -> f64 {
+        self(cell)
+    }
+}
+
+/// Collision probability from occupancy, inflated by its uncertainty
+///
+/// A cell's probability is its occupancy probability plus `confidence`
+/// standard errors of the observations recorded for it in an
+/// [`UncertaintyLayer`], clamped to `[0, 1]`: cells observed rarely or
+/// inconsistently count as riskier than their mean suggests. Cells without
+/// occupancy data, or that `to_index` does not map, use the probability of
+/// unknown space (default 0.5).
+pub struct OccupancyRisk<'a, M> {
+    occupancy: &'a OccupancyLayer,
+    uncertainty: Option<(&'a UncertaintyLayer, f64)>,
+    to_index: M,
+    unknown_probability: f64,
+}
+
+impl<'a, M> OccupancyRisk<'a, M>
+where
+    M: Fn(Route64) -> Option<Index64>,
+{
+    /// Risk from `occupancy`, looking up routing cells with `to_index`
+    pub fn new(occupancy: &'a OccupancyLayer, to_index: M) -> Self {
+        Self {
+            occupancy,
+            uncertainty: None,
+            to_index,
+            unknown_probability: 0.5,
+        }
+    }
+
+    /// Add `confidence` standard errors from `uncertainty` to each probability
+    pub fn with_uncertainty(mut self, uncertainty: &'a UncertaintyLayer, confidence: f64) -> Self {
+        self.uncertainty = Some((uncertainty, confidence));
+        self
+    }
+
+    /// Collision probability of cells without occupancy data
+    pub fn with_unknown_probability(mut self, probability: f64) -> Self {
+        self.unknown_probability = probability.clamp(0.0, 1.0);
+        self
+    }
+}
+
+impl<M> CollisionRisk for OccupancyRisk<'_, M>
+where
+    M: Fn(Route64) -> Option<Index64>,
+{
+    fn collision_probability(&self, cell: Route64) -> f64 {
+        let Some(idx) = (self.to_index)(cell) else {
+            return self.unknown_probability;
+        };
+        let Some(p) = self.occupancy.get_probability(idx) else {
+            return self.unknown_probability;
+        };
+        let inflation = match self.uncertainty {
+            Some((uncertainty, confidence)) => {
+                let se = uncertainty.standard_error(idx) as f64;
+                if se.is_finite() {
+                    confidence * se
+                } else {
+                    0.0
+                }
+            }
+            None => 0.0,
+        };
+        (p as f64 + inflation).clamp(0.0, 1.0)
+    }
+}
+
+/// Tuning parameters for [`astar_with_config`]
+#[derive(Debug, Clone)]
+pub struct SearchConfig {
+    /// Limit on node expansions (default: 100,000)
+    pub max_expansions: usize,
+    /// Prune symmetric paths when the cost is uniform (default: true)
+    pub symmetry_pruning: bool,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            max_expansions: 100_000,
+            symmetry_pruning: true,
+        }
+    }
+}
+
+/// Result of an optimal search
+#[derive(Debug, Clone)]
+pub struct RoutePath {
+    /// Sequence of cells from start to goal (inclusive)
+    pub cells: Vec<Route64>,
+    /// Total path cost in lattice units
+    pub cost: f64,
+    /// Number of node expansions performed
+    pub expansions: usize,
+    /// Number of successors evaluated (cost function calls)
+    pub generated: usize,
+}
+
+/// Optimal A* over a [`Cost`]
+///
+/// When the cost [is uniform](Cost::is_uniform), symmetric paths are pruned
+/// (see [`astar_with_config`]).
+pub fn astar<C: Cost>(start: Route64, goal: Route64, cost: &C) -> Result<RoutePath> {
+    astar_with_config(start, goal, cost, &SearchConfig::default())
+}
+
+/// [`astar`] with explicit tuning parameters
+///
+/// # Symmetry pruning
+///
+/// On uniform-cost maps, the many optimal paths between two cells differ
+/// only in the order of their moves, and plain A* expands all of them.
+/// Adapting the pruning rule of Jump Point Search (Harabor & Grastien, 2011)
+/// to the 14-neighbor BCC lattice, each cell reached by move `k` only
+/// generates successors by moves at or after `k` in [`BCC_NEIGHBORS_14`]
+/// order, plus *forced* moves `j < k` whose reordered alternative
+/// (taking `j` before `k`) passes through a blocked cell. Every optimal path
+/// can be reordered into one that survives this rule, so results stay
+/// optimal. Cells reached with equal cost by several moves keep all of them.
+///
+/// Pruning roughly halves the successors evaluated per expansion (see
+/// [`RoutePath::generated`]); jumping over intermediate cells is not
+/// performed. Ties in f are broken towards deeper cells, which keeps
+/// expansions in symmetric open regions close to the path length.
+pub fn astar_with_config<C: Cost>(
+    start: Route64,
+    goal: Route64,
+    cost: &C,
+    config: &SearchConfig,
+) -> Result<RoutePath> {
+    if start == goal {
+        return Ok(RoutePath {
+            cells: vec![start],
+            cost: 0.0,
+            expansions: 0,
+            generated: 0,
+        });
+    }
+
+    let prune = config.symmetry_pruning && cost.is_uniform();
+    let all_moves: u16 = (1 << BCC_NEIGHBORS_14.len()) - 1;
+
+    // Per cell: best g, and the set of moves that reached it at that cost
+    let mut g_score: FxHashMap<Route64, f64> = FxHashMap::default();
+    let mut arrivals: FxHashMap<Route64, u16> = FxHashMap::default();
+    let mut expanded: FxHashMap<Route64, u16> = FxHashMap::default();
+    let mut came_from: FxHashMap<Route64, Route64> = FxHashMap::default();
+    let mut open = BinaryHeap::new();
+    let mut expansions = 0;
+    let mut generated = 0;
+
+    g_score.insert(start, 0.0);
+    arrivals.insert(start, all_moves); // Start expands every move
+    open.push(AraNode {
+        f_score: OrderedFloat(cost.heuristic(start, goal)),
+        g_score: OrderedFloat(0.0),
+        cell: start,
+    });
+
+    while let Some(AraNode {
+        g_score: g, cell, ..
+    }) = open.pop()
+    {
+        let best_g = g_score[&cell];
+        if g.0 > best_g {
+            continue; // Stale entry
+        }
+        let incoming = arrivals[&cell];
+        let done = expanded.get(&cell).copied().unwrap_or(0);
+        if incoming & !done == 0 {
+            continue; // Nothing new since the last expansion
+        }
+        expanded.insert(cell, done | incoming);
+
+        if cell == goal {
+            let mut cells = vec![goal];
+            let mut cursor = goal;
+            while let Some(&prev) = came_from.get(&cursor) {
+                cells.push(prev);
+                cursor = prev;
+            }
+            cells.reverse();
+            return Ok(RoutePath {
+                cells,
+                cost: best_g,
+                expansions,
+                generated,
+            });
+        }
+
+        expansions += 1;
+        if expansions > config.max_expansions {
+            return Err(Error::SearchLimitExceeded {
+                expansions,
+                limit: config.max_expansions,
+            });
+        }
+
+        let moves = if prune {
+            allowed_moves(cell, incoming & !done, cost)
+        } else {
+            all_moves
+        };
+
+        for (j, &delta) in BCC_NEIGHBORS_14.iter().enumerate() {
+            if moves & (1 << j) == 0 {
+                continue;
+            }
+            let Some(neighbor) = offset(cell, delta) else {
+                continue;
+            };
+            generated += 1;
+            let edge = cost.cost(cell, neighbor);
+            if !edge.is_finite() {
+                continue;
+            }
+
+            let tentative = best_g + edge;
+            let neighbor_g = *g_score.get(&neighbor).unwrap_or(&f64::INFINITY);
+            let bit = 1u16 << j;
+            if neighbor_g.is_infinite() || tentative < neighbor_g - tie_tolerance(neighbor_g) {
+                g_score.insert(neighbor, tentative);
+                arrivals.insert(neighbor, bit);
+                expanded.remove(&neighbor);
+                came_from.insert(neighbor, cell);
+            } else if tentative <= neighbor_g + tie_tolerance(neighbor_g) {
+                // Equal-cost arrival by another move: record it, and re-open
+                // the cell if that move has not been expanded yet
+                let entry = arrivals.entry(neighbor).or_insert(0);
+                if *entry & bit != 0 {
+                    continue;
+                }
+                *entry |= bit;
+            } else {
+                continue;
+            }
+
+            let neighbor_g = g_score[&neighbor];
+            open.push(AraNode {
+                f_score: OrderedFloat(neighbor_g + cost.heuristic(neighbor, goal)),
+                g_score: OrderedFloat(neighbor_g),
+                cell: neighbor,
+            });
+        }
+    }
+
+    Err(Error::NoPathFound {
+        start: format!("{}", start),
+        goal: format!("{}", goal),
+    })
+}
+
+/// Tolerance for treating two path costs as equal
+///
+/// Reordered moves sum to the same cost only up to floating-point rounding.
+#[inline]
+fn tie_tolerance(g: f64) -> f64 {
+    1e-9 * g.abs().max(1.0)
+}
+
+/// Apply a lattice move, if the result is a valid cell
+#[inline]
+fn offset(cell: Route64, delta: (i32, i32, i32)) -> Option<Route64> {
+    Route64::new(
+        cell.scale_tier(),
+        cell.x().checked_add(delta.0)?,
+        cell.y().checked_add(delta.1)?,
+        cell.z().checked_add(delta.2)?,
+    )
+    .ok()
+}
+
+/// Moves to generate from `cell` given the moves that reached it
+///
+/// Move `j` is natural after move `k` when `j >= k`; otherwise it is forced
+/// when the reordered path `parent -> parent + j -> cell + j` is blocked.
+fn allowed_moves<C: Cost>(cell: Route64, incoming: u16, cost: &C) -> u16 {
+    let mut allowed = 0u16;
+    for (k, &in_delta) in BCC_NEIGHBORS_14.iter().enumerate() {
+        if incoming & (1 << k) == 0 {
+            continue;
+        }
+        // Moves at or after k are always natural
+        allowed |= !((1u16 << k) - 1);
+
+        let parent = offset(cell, (-in_delta.0, -in_delta.1, -in_delta.2));
+        for (j, &delta) in BCC_NEIGHBORS_14.iter().enumerate().take(k) {
+            if allowed & (1 << j) != 0 {
+                continue;
+            }
+            let swapped_open = parent
+                .and_then(|p| {
+                    let via = offset(p, delta)?;
+                    let end = offset(cell, delta)?;
+                    Some(cost.cost(p, via).is_finite() && cost.cost(via, end).is_finite())
+                })
+                .unwrap_or(false);
+            if !swapped_open {
+                allowed |= 1 << j;
+            }
+        }
+    }
+    allowed & ((1 << BCC_NEIGHBORS_14.len()) - 1)
+}
+
+/// Tuning parameters for [`astar_anytime_with_config`]
+#[derive(Debug, Clone)]
+pub struct AnytimeConfig {
+    /// Heuristic inflation for the first search (default: 2.5)
+    pub initial_epsilon: f64,
+    /// Amount epsilon is decreased after each improvement (default: 0.5)
+    pub epsilon_step: f64,
+    /// Limit on total node expansions across all iterations (default: 1,000,000)
+    pub max_expansions: usize,
+}
+
+impl Default for AnytimeConfig {
+    fn default() -> Self {
+        Self {
+            initial_epsilon: 2.5,
+            epsilon_step: 0.5,
+            max_expansions: 1_000_000,
+        }
+    }
+}
+
+/// Result of an anytime search
+#[derive(Debug, Clone)]
+pub struct AnytimePath {
+    /// Sequence of cells from start to goal (inclusive)
+    pub cells: Vec<Route64>,
+    /// Total path cost in lattice units
+    pub cost: f64,
+    /// Proven suboptimality bound: `cost <= epsilon * optimal_cost`
+    pub epsilon: f64,
+    /// Number of completed search iterations (published solutions)
+    pub iterations: usize,
+    /// Total node expansions across all iterations
+    pub expansions: usize,
+}
+
+impl AnytimePath {
+    /// Whether the path is proven optimal
+    pub fn is_optimal(&self) -> bool {
+        self.epsilon <= 1.0
+    }
+}
+
+/// Anytime Repairing A* (ARA*) with a wall-clock budget
+///
+/// Returns the best path found before `budget` elapses, along with the
+/// suboptimality bound it achieved. Fails only if no path at all was found
+/// in time, or none exists.
+pub fn astar_anytime<C: Cost>(
+    start: Route64,
+    goal: Route64,
+    cost: &C,
+    budget: Duration,
+) -> Result<AnytimePath> {
+    astar_anytime_with_config(start, goal, cost, budget, &AnytimeConfig::default())
+}
+
+/// [`astar_anytime`] with explicit tuning parameters
+///
+/// See Likhachev, Gordon & Thrun, "ARA*: Anytime A* with Provable Bounds on
+/// Sub-Optimality" (NIPS 2003).
+pub fn astar_anytime_with_config<C: Cost>(
+    start: Route64,
+    goal: Route64,
+    cost: &C,
+    budget: Duration,
+    config: &AnytimeConfig,
+) -> Result<AnytimePath> {
+    let deadline = Instant::now() + budget;
+
+    if start == goal {
+        return Ok(AnytimePath {
+            cells: vec![start],
+            cost: 0.0,
+            epsilon: 1.0,
+            iterations: 1,
+            expansions: 0,
+        });
+    }
+
+    let mut search = AraSearch {
+        cost,
+        goal,
+        epsilon: config.initial_epsilon.max(1.0),
+        g_score: FxHashMap::default(),
+        came_from: FxHashMap::default(),
+        open: BinaryHeap::new(),
+        open_set: FxHashSet::default(),
+        closed: FxHashSet::default(),
+        incons: FxHashSet::default(),
+        expansions: 0,
+        max_expansions: config.max_expansions,
+        deadline,
+    };
+    search.g_score.insert(start, 0.0);
+    search.push_open(start);
+
+    let mut best: Option<AnytimePath> = None;
+    let mut iterations = 0;
+
+    loop {
+        match search.improve_path() {
+            Ok(()) => {}
+            Err(e) => {
+                // Out of time or expansions: hand back the last published path
+                return match best {
+                    Some(mut path) => {
+                        path.expansions = search.expansions;
+                        Ok(path)
+                    }
+                    None => Err(e),
+                };
+            }
+        }
+
+        let goal_g = search.g(goal);
+        if goal_g.is_infinite() {
+            return Err(Error::NoPathFound {
+                start: format!("{}", start),
+                goal: format!("{}", goal),
+            });
+        }
+
+        iterations += 1;
+        let bound = search.suboptimality_bound(goal_g);
+        best = Some(AnytimePath {
+            cells: search.reconstruct(goal),
+            cost: goal_g,
+            epsilon: bound,
+            iterations,
+            expansions: search.expansions,
+        });
+
+        if bound <= 1.0 || Instant::now() >= deadline {
+            return Ok(best.expect("just published"));
+        }
+
+        search.epsilon = (search.epsilon - config.epsilon_step.max(f64::EPSILON)).max(1.0);
+        search.restart_iteration();
+    }
+}
+
+/// Travel cost from every start to every goal
+///
+/// Runs one Dijkstra search per start that stops once all goals are settled
+/// or `max_expansions` nodes have been expanded. Entry `[i][j]` is the
+/// optimal cost from `starts[i]` to `goals[j]` in lattice units, or
+/// `f64::INFINITY` if the goal is unreachable within the limit.
+pub fn travel_costs<C: Cost>(
+    starts: &[Route64],
+    goals: &[Route64],
+    cost: &C,
+    max_expansions: usize,
+) -> Vec<Vec<f64>> {
+    starts
+        .iter()
+        .map(|&start| {
+            let mut remaining: FxHashSet<Route64> = goals.iter().copied().collect();
+            let mut dist: FxHashMap<Route64, f64> = FxHashMap::default();
+            let mut settled: FxHashSet<Route64> = FxHashSet::default();
+            let mut open = BinaryHeap::new();
+            let mut expansions = 0;
+
+            dist.insert(start, 0.0);
+            open.push(AraNode {
+                f_score: OrderedFloat(0.0),
+                g_score: OrderedFloat(0.0),
+                cell: start,
+            });
+
+            while let Some(AraNode { g_score, cell, .. }) = open.pop() {
+                if remaining.is_empty() || expansions >= max_expansions {
+                    break;
+                }
+                if !settled.insert(cell) {
+                    continue;
+                }
+                remaining.remove(&cell);
+                expansions += 1;
+
+                for neighbor in neighbors_route64(cell) {
+                    let edge = cost.cost(cell, neighbor);
+                    if !edge.is_finite() || settled.contains(&neighbor) {
+                        continue;
+                    }
+                    let tentative = g_score.0 + edge;
+                    if tentative < *dist.get(&neighbor).unwrap_or(&f64::INFINITY) {
+                        dist.insert(neighbor, tentative);
+                        open.push(AraNode {
+                            f_score: OrderedFloat(tentative),
+                            g_score: OrderedFloat(tentative),
+                            cell: neighbor,
+                        });
+                    }
+                }
+            }
+
+            goals
+                .iter()
+                .map(|goal| {
+                    if settled.contains(goal) {
+                        dist[goal]
+                    } else {
+                        f64::INFINITY
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Path found by [`astar_risk_bounded`]
+#[derive(Debug, Clone)]
+pub struct RiskBoundedPath {
+    /// Sequence of cells from start to goal (inclusive)
+    pub cells: Vec<Route64>,
+    /// Total path cost in lattice units
+    pub cost: f64,
+    /// Probability of colliding somewhere along the path
+    pub risk: f64,
+    /// Number of label expansions performed
+    pub expansions: usize,
+}
+
+/// Why [`astar_risk_bounded`] found no path under its risk bound
+#[derive(Debug, Clone)]
+pub struct RiskDiagnostics {
+    /// The bound that could not be met
+    pub max_risk: f64,
+    /// Lowest risk of any passable path, or `None` if the goal cannot be
+    /// reached at all within the expansion limit
+    pub min_risk: Option<f64>,
+    /// The least risky path; empty when the goal is unreachable
+    pub min_risk_path: Vec<Route64>,
+    /// Cells of the least risky path with a non-zero collision probability,
+    /// riskiest first: the cells to re-observe or clear to meet the bound
+    pub riskiest_cells: Vec<(Route64, f64)>,
+    /// Number of expansions performed by both searches
+    pub expansions: usize,
+}
+
+/// Outcome of [`astar_risk_bounded`]
+#[derive(Debug, Clone)]
+pub enum RiskBoundedPlan {
+    /// Cheapest path whose risk is within the bound
+    Path(RiskBoundedPath),
+    /// No path meets the bound
+    Infeasible(RiskDiagnostics),
+}
+
+/// Cheapest path whose cumulative collision risk stays under `max_risk`
+///
+/// The risk of a path is the probability of colliding in at least one of
+/// the cells it enters, `1 - Π(1 - p)`, assuming independent cells; the
+/// start cell is not counted. The search keeps, for each cell, every
+/// partial path not beaten on both cost and risk by another, and discards
+/// those over the bound, so the first path to reach the goal is the
+/// cheapest one within it. Cells with probability 1 are impassable.
+///
+/// When no path meets the bound, a minimum-risk search reports the lowest
+/// risk achievable and the cells responsible. Exceeding `max_expansions`
+/// in either search returns [`Error::SearchLimitExceeded`].
+///
+/// # Example
+///
+/// ```
+/// use octaindex3d::routing::{astar_risk_bounded, PredicateCost, RiskBoundedPlan};
+/// use octaindex3d::Route64;
+///
+/// # fn main() -> octaindex3d::Result<()> {
+/// let start = Route64::new(0, 0, 0, 0)?;
+/// let goal = Route64::new(0, 12, 0, 0)?;
+/// // A bounded workspace where every cell past the start carries a 1%
+/// // chance of collision
+/// let room = PredicateCost::new(|c: Route64| {
+///     (-4..=16).contains(&c.x()) && c.y().abs() <= 4 && c.z().abs() <= 4
+/// });
+/// let risk = |cell: Route64| if cell.x() > 0 { 0.01 } else { 0.0 };
+///
+/// match astar_risk_bounded(start, goal, &room, &risk, 0.1, 100_000)? {
+///     RiskBoundedPlan::Path(path) => assert!(path.risk <= 0.1),
+///     RiskBoundedPlan::Infeasible(_) => unreachable!(),
+/// }
+/// match astar_risk_bounded(start, goal, &room, &risk, 0.01, 100_000)? {
+///     RiskBoundedPlan::Infeasible(why) => assert!(why.min_risk.unwrap() > 0.01),
+///     RiskBoundedPlan::Path(_) => unreachable!(),
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn astar_risk_bounded<C: Cost, R: CollisionRisk>(
+    start: Route64,
+    goal: Route64,
+    cost: &C,
+    risk: &R,
+    max_risk: f64,
+    max_expansions: usize,
+) -> Result<RiskBoundedPlan> {
+    if !(0.0..=1.0).contains(&max_risk) {
+        return Err(Error::Pathfinding(format!(
+            "risk bound {} is not a probability",
+            max_risk
+        )));
+    }
+    // Risks add as -ln(1 - p), so the bound becomes a budget on the sum
+    let budget = -(-max_risk).ln_1p();
+    let probability = |cell: Route64| risk.collision_probability(cell).clamp(0.0, 1.0);
+
+    let mut labels = vec![RiskLabel {
+        cell: start,
+        g: 0.0,
+        risk: 0.0,
+        parent: None,
+        live: true,
+    }];
+    let mut at_cell: FxHashMap<Route64, Vec<usize>> = FxHashMap::default();
+    at_cell.insert(start, vec![0]);
+    let mut open = BinaryHeap::new();
+    open.push((
+        Reverse(OrderedFloat(cost.heuristic(start, goal))),
+        OrderedFloat(0.0),
+        0usize,
+    ));
+    let mut expansions = 0;
+
+    while let Some((_, _, id)) = open.pop() {
+        let RiskLabel {
+            cell,
+            g,
+            risk: spent,
+            live,
+            ..
+        } = labels[id];
+        if !live {
+            continue; // Dominated after it was queued
+        }
+        if cell == goal {
+            let mut cells = Vec::new();
+            let mut cursor = Some(id);
+            while let Some(at) = cursor {
+                cells.push(labels[at].cell);
+                cursor = labels[at].parent;
+            }
+            cells.reverse();
+            return Ok(RiskBoundedPlan::Path(RiskBoundedPath {
+                cells,
+                cost: g,
+                risk: -(-spent).exp_m1(),
+                expansions,
+            }));
+        }
+
+        expansions += 1;
+        if expansions > max_expansions {
+            return Err(Error::SearchLimitExceeded {
+                expansions,
+                limit: max_expansions,
+            });
+        }
+
+        for neighbor in neighbors_route64(cell) {
+            let edge = cost.cost(cell, neighbor);
+            let p = probability(neighbor);
+            if !edge.is_finite() || p >= 1.0 {
+                continue;
+            }
+            let risk_after = spent - (-p).ln_1p();
+            if risk_after > budget + tie_tolerance(budget) {
+                continue;
+            }
+            let g_after = g + edge;
+
+            let others = at_cell.entry(neighbor).or_default();
+            if others
+                .iter()
+                .any(|&o| labels[o].g <= g_after && labels[o].risk <= risk_after)
+            {
+                continue;
+            }
+            others.retain(|&o| {
+                let dominated = g_after <= labels[o].g && risk_after <= labels[o].risk;
+                if dominated {
+                    labels[o].live = false;
+                }
+                !dominated
+            });
+            others.push(labels.len());
+            open.push((
+                Reverse(OrderedFloat(g_after + cost.heuristic(neighbor, goal))),
+                OrderedFloat(g_after),
+                labels.len(),
+            ));
+            labels.push(RiskLabel {
+                cell: neighbor,
+                g: g_after,
+                risk: risk_after,
+                parent: Some(id),
+                live: true,
+            });
+        }
+    }
+
+    let (min_risk_path, min_expansions) =
+        least_risky_path(start, goal, cost, &probability, max_expansions)?;
+    let min_risk = (!min_risk_path.is_empty()).then(|| {
+        let survival: f64 = min_risk_path[1..]
+            .iter()
+            .map(|&c| 1.0 - probability(c))
+            .product();
+        1.0 - survival
+    });
+    let mut riskiest_cells: Vec<(Route64, f64)> = min_risk_path
+        .iter()
+        .skip(1)
+        .map(|&c| (c, probability(c)))
+        .filter(|&(_, p)| p > 0.0)
+        .collect();
+    riskiest_cells.sort_by(|a, b| b.1.total_cmp(&a.1));
+    Ok(RiskBoundedPlan::Infeasible(RiskDiagnostics {
+        max_risk,
+        min_risk,
+        min_risk_path,
+        riskiest_cells,
+        expansions: expansions + min_expansions,
+    }))
+}
+
+/// Partial path of the risk-bounded search; `risk` is the sum of -ln(1 - p)
+#[derive(Clone, Copy)]
+struct RiskLabel {
+    cell: Route64,
+    g: f64,
+    risk: f64,
+    parent: Option<usize>,
+    live: bool,
+}
+
+/// Dijkstra on additive risk, returning the path (empty if unreachable) and
+/// the expansions performed
+fn least_risky_path<C: Cost>(
+    start: Route64,
+    goal: Route64,
+    cost: &C,
+    probability: &dyn Fn(Route64) -> f64,
+    max_expansions: usize,
+) -> Result<(Vec<Route64>, usize)> {
+    let mut dist: FxHashMap<Route64, f64> = FxHashMap::default();
+    let mut came_from: FxHashMap<Route64, Route64> = FxHashMap::default();
+    let mut settled: FxHashSet<Route64> = FxHashSet::default();
+    let mut open = BinaryHeap::new();
+    let mut expansions = 0;
+
+    dist.insert(start, 0.0);
+    open.push(AraNode {
+        f_score: OrderedFloat(0.0),
+        g_score: OrderedFloat(0.0),
+        cell: start,
+    });
+
+    while let Some(AraNode { g_score, cell, .. }) = open.pop() {
+        if !settled.insert(cell) {
+            continue;
+        }
+        if cell == goal {
+            let mut cells = vec![goal];
+            let mut cursor = goal;
+            while let Some(&prev) = came_from.get(&cursor) {
+                cells.push(prev);
+                cursor = prev;
+            }
+            cells.reverse();
+            return Ok((cells, expansions));
+        }
+        expansions += 1;
+        if expansions > max_expansions {
+            return Err(Error::SearchLimitExceeded {
+                expansions,
+                limit: max_expansions,
+            });
+        }
+
+        for neighbor in neighbors_route64(cell) {
+            let p = probability(neighbor);
+            if p >= 1.0 || !cost.cost(cell, neighbor).is_finite() || settled.contains(&neighbor) {
+                continue;
+            }
+            let tentative = g_score.0 - (-p).ln_1p();
+            if tentative < *dist.get(&neighbor).unwrap_or(&f64::INFINITY) {
+                dist.insert(neighbor, tentative);
+                came_from.insert(neighbor, cell);
+                open.push(AraNode {
+                    f_score: OrderedFloat(tentative),
+                    g_score: OrderedFloat(tentative),
+                    cell: neighbor,
+                });
+            }
+        }
+    }
+    Ok((Vec::new(), expansions))
+}
+
+/// Open-list entry (min-heap on f, with g recorded for lazy deletion)
+#[derive(PartialEq, Eq)]
+struct AraNode {
+    f_score: OrderedFloat<f64>,
+    g_score: OrderedFloat<f64>,
+    cell: Route64,
+}
+
+impl PartialOrd for AraNode {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for AraNode {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        // Reversed for min-heap; among equal f prefer deeper nodes
+        other
+            .f_score
+            .cmp(&self.f_score)
+            .then_with(|| self.g_score.cmp(&other.g_score))
+    }
+}
+
+struct AraSearch<'a, C: Cost> {
+    cost: &'a C,
+    goal: Route64,
+    epsilon: f64,
+    g_score: FxHashMap<Route64, f64>,
+    came_from: FxHashMap<Route64, Route64>,
+    open: BinaryHeap<AraNode>,
+    open_set: FxHashSet<Route64>,
+    closed: FxHashSet<Route64>,
+    incons: FxHashSet<Route64>,
+    expansions: usize,
+    max_expansions: usize,
+    deadline: Instant,
+}
+
+impl<C: Cost> AraSearch<'_, C> {
+    fn g(&self, cell: Route64) -> f64 {
+        *self.g_score.get(&cell).unwrap_or(&f64::INFINITY)
+    }
+
+    fn f(&self, cell: Route64) -> f64 {
+        self.g(cell) + self.epsilon * self.cost.heuristic(cell, self.goal)
+    }
+
+    fn push_open(&mut self, cell: Route64) {
+        self.open_set.insert(cell);
+        self.open.push(AraNode {
+            f_score: OrderedFloat(self.f(cell)),
+            g_score: OrderedFloat(self.g(cell)),
+            cell,
+        });
+    }
+
+    /// Drop stale heap entries and return the current minimum f in OPEN
+    fn min_open_f(&mut self) -> f64 {
+        while let Some(top) = self.open.peek() {
+            let stale = !self.open_set.contains(&top.cell) || top.g_score.0 != self.g(top.cell);
+            if stale {
+                self.open.pop();
+            } else {
+                return top.f_score.0;
+            }
+        }
+        f64::INFINITY
+    }
+
+    fn improve_path(&mut self) -> Result<()> {
+        while self.g(self.goal) > self.min_open_f() {
+            let node = self.open.pop().expect("min_open_f found a live entry");
+            let current = node.cell;
+            self.open_set.remove(&current);
+            self.closed.insert(current);
+
+            self.expansions += 1;
+            if self.expansions > self.max_expansions {
+                return Err(Error::SearchLimitExceeded {
+                    expansions: self.expansions,
+                    limit: self.max_expansions,
+                });
+            }
+            if self.expansions % 256 == 0 && Instant::now() >= self.deadline {
+                return Err(Error::Pathfinding(
+                    "time budget exhausted before a path was found".to_string(),
+                ));
+            }
+
+            let current_g = self.g(current);
+            for neighbor in neighbors_route64(current) {
+                let edge = self.cost.cost(current, neighbor);
+                if !edge.is_finite() {
+                    continue;
+                }
+                let tentative = current_g + edge;
+                if tentative < self.g(neighbor) {
+                    self.g_score.insert(neighbor, tentative);
+                    self.came_from.insert(neighbor, current);
+                    if self.closed.contains(&neighbor) {
+                        self.incons.insert(neighbor);
+                    } else {
+                        self.push_open(neighbor);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Bound on the current solution: g(goal) / min over OPEN ∪ INCONS of g + h
+    fn suboptimality_bound(&mut self, goal_g: f64) -> f64 {
+        self.min_open_f(); // Prune stale entries so open_set reflects the heap
+        let lower = self
+            .open_set
+            .iter()
+            .chain(self.incons.iter())
+            .map(|&c| self.g(c) + self.cost.heuristic(c, self.goal))
+            .fold(f64::INFINITY, f64::min);
+        if lower.is_finite() && lower > 0.0 {
+            (goal_g / lower).clamp(1.0, self.epsilon)
+        } else {
+            1.0
+        }
+    }
+
+    /// Move INCONS into OPEN, re-key OPEN for the new epsilon and clear CLOSED
+    fn restart_iteration(&mut self) {
+        let mut cells: Vec<Route64> = self.open_set.drain().collect();
+        cells.extend(self.incons.drain());
+        self.open.clear();
+        for cell in cells {
+            self.push_open(cell);
+        }
+        self.closed.clear();
+    }
+
+    fn reconstruct(&self, goal: Route64) -> Vec<Route64> {
+        let mut cells = vec![goal];
+        let mut cursor = goal;
+        while let Some(&prev) = self.came_from.get(&cursor) {
+            cells.push(prev);
+            cursor = prev;
+        }
+        cells.reverse();
+        cells
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path_cost<C: Cost>(cells: &[Route64], cost: &C) -> f64 {
+        cells.windows(2).map(|w| cost.cost(w[0], w[1])).sum()
+    }
+
+    /// Slab at x in {10, 11} with collision probability 0.2, except for a
+    /// gap at y >= 8 when `gap` is set, inside a bounded box
+    fn slab_world(gap: bool) -> (impl Cost, impl Fn(Route64) -> f64) {
+        let room = PredicateCost::new(|c: Route64| {
+            (0..=20).contains(&c.x()) && (-2..=12).contains(&c.y()) && c.z().abs() <= 2
+        });
+        let risk = move |c: Route64| {
+            if (10..=11).contains(&c.x()) && !(gap && c.y() >= 8) {
+                0.2
+            } else {
+                0.0
+            }
+        };
+        (room, risk)
+    }
+
+    #[test]
+    fn test_risk_bound_trades_cost_for_safety() {
+        let start = Route64::new(0, 0, 0, 0).unwrap();
+        let goal = Route64::new(0, 20, 0, 0).unwrap();
+        let (room, risk) = slab_world(true);
+        let direct = astar(start, goal, &room).unwrap();
+
+        let RiskBoundedPlan::Path(loose) =
+            astar_risk_bounded(start, goal, &room, &risk, 0.5, 1_000_000).unwrap()
+        else {
+            panic!("a direct path is within the bound");
+        };
+        assert!((loose.cost - direct.cost).abs() < 1e-9);
+        assert!((loose.risk - 0.2).abs() < 1e-9);
+
+        let RiskBoundedPlan::Path(safe) =
+            astar_risk_bounded(start, goal, &room, &risk, 0.05, 1_000_000).unwrap()
+        else {
+            panic!("the gap is risk-free");
+        };
+        assert_eq!(safe.risk, 0.0);
+        assert!(safe.cost > direct.cost);
+        assert!(safe.cells.iter().all(|&c| risk(c) == 0.0));
+        assert_eq!(safe.cells.last(), Some(&goal));
+    }
+
+    #[test]
+    fn test_risk_bound_infeasible_diagnostics() {
+        let start = Route64::new(0, 0, 0, 0).unwrap();
+        let goal = Route64::new(0, 20, 0, 0).unwrap();
+        let (room, risk) = slab_world(false);
+
+        let RiskBoundedPlan::Infeasible(why) =
+            astar_risk_bounded(start, goal, &room, &risk, 0.1, 1_000_000).unwrap()
+        else {
+            panic!("every path crosses the slab");
+        };
+        assert!((why.min_risk.unwrap() - 0.2).abs() < 1e-9);
+        assert_eq!(why.min_risk_path.first(), Some(&start));
+        assert_eq!(why.min_risk_path.last(), Some(&goal));
+        assert_eq!(why.riskiest_cells.len(), 1);
+        assert_eq!(why.riskiest_cells[0].1, 0.2);
+    }
+
+    #[test]
+    fn test_occupancy_risk_inflates_uncertain_cells() {
+        let to_index = |c: Route64| {
+            Index64::new(
+                0,
+                0,
+                8,
+                (c.x() + 100) as u16,
+                (c.y() + 100) as u16,
+                (c.z() + 100) as u16,
+            )
+            .ok()
+        };
+        let steady = Route64::new(0, 0, 0, 0).unwrap();
+        let flaky = Route64::new(0, 2, 0, 0).unwrap();
+        let mut occupancy = OccupancyLayer::new();
+        let mut uncertainty = UncertaintyLayer::new();
+        for (cell, hits) in [
+            (steady, [false; 6]),
+            (flaky, [true, false, true, false, false, false]),
+        ] {
+            let idx = to_index(cell).unwrap();
+            for hit in hits {
+                occupancy.update_occupancy(idx, hit, 0.9);
+                uncertainty.observe(idx, if hit { 1.0 } else { 0.0 });
+            }
+        }
+
+        let plain = OccupancyRisk::new(&occupancy, to_index);
+        let joint = OccupancyRisk::new(&occupancy, to_index).with_uncertainty(&uncertainty, 2.0);
+        assert_eq!(
+            joint.collision_probability(steady),
+            plain.collision_probability(steady)
+        );
+        assert!(joint.collision_probability(flaky) > plain.collision_probability(flaky));
+        let unknown = Route64::new(0, 4, 0, 0).unwrap();
+        assert_eq!(joint.collision_probability(unknown), 0.5);
+    }
+
+    #[test]
+    fn test_anytime_converges_to_optimal() {
+        let start = Route64::new(0, 0, 0, 0).unwrap();
+        let goal = Route64::new(0, 12, 6, 0).unwrap();
+
+        let result = astar_anytime(start, goal, &EuclideanCost, Duration::from_secs(5)).unwrap();
+        assert!(result.is_optimal());
+        assert_eq!(result.cells.first(), Some(&start));
+        assert_eq!(result.cells.last(), Some(&goal));
+
+        // Matches plain A* on the grid facade
+        let grid = BccGrid::new(2.0).unwrap(); // unit length 1.0
+        let reference = grid.astar(start, goal).unwrap();
+        assert!((result.cost - reference.cost).abs() < 1e-9);
+        assert!((path_cost(&result.cells, &EuclideanCost) - result.cost).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_anytime_reports_bound_when_stopped_early() {
+        let start = Route64::new(0, 0, 0, 0).unwrap();
+        let goal = Route64::new(0, 40, 20, 10).unwrap();
+        let config = AnytimeConfig {
+            initial_epsilon: 3.0,
+            epsilon_step: 0.5,
+            max_expansions: 1_000_000,
+        };
+
+        // Zero budget: the first (inflated) search still completes unless it
+        // hits the periodic deadline check, so accept either outcome
+        match astar_anytime_with_config(start, goal, &EuclideanCost, Duration::ZERO, &config) {
+            Ok(result) => {
+                assert!(result.epsilon >= 1.0 && result.epsilon <= 3.0);
+                assert_eq!(result.iterations, 1);
+                let optimal = BccGrid::new(2.0).unwrap().astar(start, goal).unwrap().cost;
+                assert!(result.cost <= result.epsilon * optimal + 1e-6);
+            }
+            Err(Error::Pathfinding(_)) => {}
+            Err(e) => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn test_anytime_respects_obstacles() {
+        let start = Route64::new(0, 0, 0, 0).unwrap();
+        let goal = Route64::new(0, 8, 0, 0).unwrap();
+        // Wall at x == 4 except for a gap high above the straight line
+        let cost = PredicateCost::new(|c: Route64| c.x() != 4 || c.y() >= 6);
+
+        let result = astar_anytime(start, goal, &cost, Duration::from_secs(5)).unwrap();
+        assert!(result.cells.iter().all(|c| c.x() != 4 || c.y() >= 6));
+        assert!(result.is_optimal());
+    }
+
+    /// Deterministic pseudo-random obstacle field
+    fn scattered_obstacles(seed: u64, density: u64) -> impl Fn(Route64) -> bool {
+        move |c: Route64| {
+            let mut h = seed
+                ^ (c.x() as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+                ^ (c.y() as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F)
+                ^ (c.z() as u64).wrapping_mul(0x1656_67B1_9E37_79F9);
+            h ^= h >> 29;
+            h = h.wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            h ^= h >> 32;
+            let inside = c.x().abs() <= 12 && c.y().abs() <= 12 && c.z().abs() <= 12;
+            inside && h % 100 >= density
+        }
+    }
+
+    #[test]
+    fn test_symmetry_pruning_is_optimal() {
+        let start = Route64::new(0, -10, -10, -10).unwrap();
+        let goal = Route64::new(0, 10, 8, 6).unwrap();
+        let plain = SearchConfig {
+            symmetry_pruning: false,
+            ..Default::default()
+        };
+
+        for seed in 0..20 {
+            let passable = scattered_obstacles(seed, 30);
+            let cost = PredicateCost::new(|c: Route64| c == start || c == goal || passable(c));
+
+            let pruned = astar(start, goal, &cost);
+            let reference = astar_with_config(start, goal, &cost, &plain);
+            match (pruned, reference) {
+                (Ok(pruned), Ok(reference)) => {
+                    assert!(
+                        (pruned.cost - reference.cost).abs() < 1e-9,
+                        "seed {}: pruned {} vs plain {}",
+                        seed,
+                        pruned.cost,
+                        reference.cost
+                    );
+                    assert!((path_cost(&pruned.cells, &cost) - pruned.cost).abs() < 1e-9);
+                }
+                (Err(Error::NoPathFound { .. }), Err(Error::NoPathFound { .. })) => {}
+                (a, b) => panic!("seed {}: mismatch {:?} vs {:?}", seed, a, b),
+            }
+        }
+    }
+
+    #[test]
+    fn test_symmetry_pruning_reduces_generated() {
+        let start = Route64::new(0, 0, 0, 0).unwrap();
+        let goal = Route64::new(0, 30, 20, 10).unwrap();
+        let plain = SearchConfig {
+            symmetry_pruning: false,
+            ..Default::default()
+        };
+
+        let pruned = astar(start, goal, &EuclideanCost).unwrap();
+        let reference = astar_with_config(start, goal, &EuclideanCost, &plain).unwrap();
+        assert!((pruned.cost - reference.cost).abs() < 1e-9);
+        assert!(pruned.generated < reference.generated);
+    }
+
+    #[test]
+    fn test_travel_costs_matrix() {
+        let a = Route64::new(0, 0, 0, 0).unwrap();
+        let b = Route64::new(0, 10, 0, 0).unwrap();
+        let goals = [Route64::new(0, 2, 0, 0).unwrap(), b];
+
+        let costs = travel_costs(&[a, b], &goals, &EuclideanCost, 100_000);
+        assert_eq!(costs[0][0], 2.0);
+        assert_eq!(costs[0][1], 10.0);
+        assert_eq!(costs[1][1], 0.0);
+
+        // Blocked goal is unreachable
+        let blocked = PredicateCost::new(|c: Route64| c != goals[0]);
+        let costs = travel_costs(&[a], &goals, &blocked, 100_000);
+        assert!(costs[0][0].is_infinite());
+        // Detour around the blocked cell: two diagonals replace one axial step
+        assert!((costs[0][1] - (8.0 + 2.0 * 3f64.sqrt())).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_anytime_no_path() {
+        let start = Route64::new(0, 0, 0, 0).unwrap();
+        let goal = Route64::new(0, 4, 0, 0).unwrap();
+        let cost = PredicateCost::new(|c: Route64| c == start);
+        let result = astar_anytime(start, goal, &cost, Duration::from_secs(1));
+        assert!(matches!(result, Err(Error::NoPathFound { .. })));
+    }
+
+    #[test]
+    fn test_altitude_band_target_height() -> Result<()> {
+        // Flat ground at 0 m
+        let terrain = TerrainLayer::new((-10.0, -10.0), 5.0, 9, 9, vec![0.0; 81])?;
+        let grid = BccGrid::new(1.0)?;
+        let start = grid.cell_at(-6.0, 0.0, 3.0)?;
+        let goal = grid.cell_at(6.0, 0.0, 3.0)?;
+
+        let band = AltitudeBandCost::new(grid, &terrain, 1.0, 8.0);
+        assert!(band.is_uniform());
+        assert!(band
+            .cost(start, grid.cell_at(-6.0, 0.0, 0.0)?)
+            .is_infinite());
+        assert!(band
+            .cost(start, grid.cell_at(40.0, 0.0, 3.0)?)
+            .is_infinite());
+
+        // The target penalty keeps every cell at 3 m AGL
+        let held = band.clone().with_target(3.0, 1.0);
+        assert!(!held.is_uniform());
+        let path = astar(start, goal, &held)?;
+        for &cell in &path.cells {
+            assert!((held.agl(cell).unwrap() - 3.0).abs() < 1e-9);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,378 @@
+//! SQLite virtual table over container cell blocks
+//!
+//! Enabled with the `sqlite` feature. [`register`] adds the `octaindex`
+//! module to a connection; each virtual table reads one container written
+//! with [`StaticMap::write_container`](crate::layers::StaticMap::write_container)
+//! and exposes one row per cell:
+//!
+//! ```sql
+//! CREATE VIRTUAL TABLE cells USING octaindex('map.oct2');
+//! SELECT x, y, z, occupancy FROM cells WHERE lod = 12 AND occupancy > 0.5;
+//! ```
+//!
+//! The table has the columns `id, frame, tier, lod, x, y, z, morton` and
+//! one `REAL` column per layer type: `tsdf, esdf, occupancy, color,
+//! intensity, derived`, `NULL` where a cell has no value. Comparisons on
+//! `morton`, `x`, `y` and `z` and equality on `frame` and `lod` are pushed
+//! down into a [`ContainerScan`]: blocks that cannot hold a matching cell
+//! are never read or decompressed.
+//!
+//! The `sqlite_extension` feature also exports a loadable extension entry
+//! point. Build it with
+//!
+//! ```text
+//! cargo rustc --release --features sqlite_extension --crate-type cdylib
+//! ```
+//!
+//! and load it from the `sqlite3` shell with
+//! `.load ./liboctaindex3d sqlite3_octaindex_init`.
+
+use crate::container_v2::ContainerReaderV2;
+use crate::layers::scan::{BoundColumn, BoundsBuilder};
+use crate::layers::{CellBounds, CellRow, ContainerScan, LayerType};
+use rusqlite::vtab::{
+    dequote, parameter, read_only_module, Context, CreateVTab, Filters, IndexConstraintOp,
+    IndexInfo, VTab, VTabConnection, VTabCursor, VTabKind,
+};
+use rusqlite::{ffi, Connection};
+use std::ffi::c_int;
+
+const SCHEMA: &str = "CREATE TABLE x(id INTEGER, frame INTEGER, tier INTEGER, lod INTEGER, \
+     x INTEGER, y INTEGER, z INTEGER, morton INTEGER, tsdf REAL, esdf REAL, occupancy REAL, \
+     color REAL, intensity REAL, derived REAL)";
+
+const FRAME_COLUMN: c_int = 1;
+const LOD_COLUMN: c_int = 3;
+/// Columns of the x, y and z coordinates
+const COORD_COLUMNS: [c_int; 3] = [4, 5, 6];
+const MORTON_COLUMN: c_int = 7;
+
+/// First layer column; layer columns follow in tag order
+const LAYER_COLUMN: c_int = 8;
+
+/// Register the `octaindex` module on a connection
+pub fn register(conn: &Connection) -> rusqlite::Result<()> {
+    conn.create_module(c"octaindex", read_only_module::<CellTable>(), None)
+}
+
+/// Loadable extension entry point
+///
+/// # Safety
+///
+/// Called by SQLite with a valid connection and API routines.
+#[cfg(feature = "sqlite_extension")]
+#[no_mangle]
+pub unsafe extern "C" fn sqlite3_octaindex_init(
+    db: *mut ffi::sqlite3,
+    pz_err_msg: *mut *mut std::ffi::c_char,
+    p_api: *mut ffi::sqlite3_api_routines,
+) -> c_int {
+    Connection::extension_init2(db, pz_err_msg, p_api, |db| {
+        register(&db)?;
+        Ok(false)
+    })
+}
+
+fn module_error(e: crate::error::Error) -> rusqlite::Error {
+    rusqlite::Error::ModuleError(e.to_string())
+}
+
+/// Plan code of a comparison pushed down on `column`
+fn op_code(column: c_int, op: IndexConstraintOp) -> Option<char> {
+    let ordered = column == MORTON_COLUMN || COORD_COLUMNS.contains(&column);
+    if !ordered && column != FRAME_COLUMN && column != LOD_COLUMN {
+        return None;
+    }
+    match op {
+        IndexConstraintOp::SQLITE_INDEX_CONSTRAINT_EQ => Some('='),
+        IndexConstraintOp::SQLITE_INDEX_CONSTRAINT_GT if ordered => Some('>'),
+        IndexConstraintOp::SQLITE_INDEX_CONSTRAINT_GE if ordered => Some('g'),
+        IndexConstraintOp::SQLITE_INDEX_CONSTRAINT_LT if ordered => Some('<'),
+        IndexConstraintOp::SQLITE_INDEX_CONSTRAINT_LE if ordered => Some('l'),
+        _ => None,
+    }
+}
+
+#[repr(C)]
+struct CellTable {
+    /// Base class, must come first
+    base: ffi::sqlite3_vtab,
+    reader: ContainerReaderV2,
+    blocks: usize,
+}
+
+unsafe impl<'vtab> VTab<'vtab> for CellTable {
+    type Aux = ();
+    type Cursor = CellCursor<'vtab>;
+
+    fn connect(
+        _db: &mut VTabConnection,
+        _aux: Option<&()>,
+        args: &[&[u8]],
+    ) -> rusqlite::Result<(String, Self)> {
+        // Module name, database name and table name come first
+        let mut path = None;
+        for arg in args.iter().skip(3) {
+            let arg = std::str::from_utf8(arg)?.trim();
+            path = Some(match arg.split_once('=') {
+                Some(_) => match parameter(arg.as_bytes())? {
+                    ("path", value) => value.to_string(),
+                    (key, _) => {
+                        return Err(rusqlite::Error::ModuleError(format!(
+                            "unknown octaindex argument '{}'",
+                            key
+                        )))
+                    }
+                },
+                None => dequote(arg).to_string(),
+            });
+        }
+        let path = path.ok_or_else(|| {
+            rusqlite::Error::ModuleError("octaindex needs a container path".to_string())
+        })?;
+        let reader = ContainerReaderV2::open(&path).map_err(module_error)?;
+        let blocks = reader.frame_count();
+        let table = Self {
+            base: ffi::sqlite3_vtab::default(),
+            reader,
+            blocks,
+        };
+        Ok((SCHEMA.to_string(), table))
+    }
+
+    /// Pushed down comparisons reach `filter` as its arguments, described
+    /// by a plan string of one column number and operator code each
+    fn best_index(&self, info: &mut IndexInfo) -> rusqlite::Result<()> {
+        let pushed: Vec<(usize, c_int, char)> = info
+            .constraints()
+            .enumerate()
+            .filter(|(_, c)| c.is_usable())
+            .filter_map(|(i, c)| Some((i, c.column(), op_code(c.column(), c.operator())?)))
+            .collect();
+
+        let mut plan = String::new();
+        for (argv, &(i, column, op)) in pushed.iter().enumerate() {
+            plan.push_str(&format!("{}{}", column, op));
+            info.constraint_usage(i).set_argv_index(argv as c_int + 1);
+        }
+        info.set_idx_str(&plan);
+        let cells = self.blocks as f64 * 64.0;
+        let selectivity = 4f64.powi(pushed.len().min(8) as i32);
+        info.set_estimated_cost(cells / selectivity);
+        Ok(())
+    }
+
+    fn open(&'vtab mut self) -> rusqlite::Result<CellCursor<'vtab>> {
+        Ok(CellCursor {
+            base: ffi::sqlite3_vtab_cursor::default(),
+            table: self,
+            scan: None,
+            row: None,
+        })
+    }
+}
+
+impl CreateVTab<'_> for CellTable {
+    const KIND: VTabKind = VTabKind::Default;
+}
+
+#[repr(C)]
+struct CellCursor<'vtab> {
+    /// Base class, must come first
+    base: ffi::sqlite3_vtab_cursor,
+    table: &'vtab CellTable,
+    scan: Option<ContainerScan<'vtab>>,
+    /// Current cell, `None` past the end
+    row: Option<CellRow>,
+}
+
+impl CellCursor<'_> {
+    fn row(&self) -> rusqlite::Result<&CellRow> {
+        self.row
+            .as_ref()
+            .ok_or_else(|| rusqlite::Error::ModuleError("cursor past the end".to_string()))
+    }
+}
+
+/// Bounds of the comparisons described by `plan` against `args`
+fn plan_bounds(plan: &str, args: &Filters<'_>) -> rusqlite::Result<CellBounds> {
+    let mut bounds = BoundsBuilder::new();
+    let mut ops = plan.char_indices().filter(|(_, c)| !c.is_ascii_digit());
+    let mut start = 0;
+    for i in 0..args.len() {
+        let (at, op) = ops
+            .next()
+            .ok_or_else(|| rusqlite::Error::ModuleError(format!("bad plan '{}'", plan)))?;
+        let column: c_int = plan[start..at]
+            .parse()
+            .map_err(|_| rusqlite::Error::ModuleError(format!("bad plan '{}'", plan)))?;
+        start = at + 1;
+        // Comparisons with NULL match nothing
+        let v = args.get::<Option<f64>>(i)?.unwrap_or(f64::NAN);
+        let column = match column {
+            FRAME_COLUMN => BoundColumn::Frame,
+            LOD_COLUMN => BoundColumn::Lod,
+            MORTON_COLUMN => BoundColumn::Morton,
+            c => match COORD_COLUMNS.iter().position(|&x| x == c) {
+                Some(axis) => BoundColumn::Coord(axis),
+                None => continue,
+            },
+        };
+        bounds.narrow(column, op, v);
+    }
+    Ok(bounds.build())
+}
+
+unsafe impl VTabCursor for CellCursor<'_> {
+    fn filter(
+        &mut self,
+        _idx_num: c_int,
+        idx_str: Option<&str>,
+        args: &Filters<'_>,
+    ) -> rusqlite::Result<()> {
+        let bounds = plan_bounds(idx_str.unwrap_or_default(), args)?;
+        let mut scan = ContainerScan::new(&self.table.reader, bounds);
+        self.row = scan.next().transpose().map_err(module_error)?;
+        self.scan = Some(scan);
+        Ok(())
+    }
+
+    fn next(&mut self) -> rusqlite::Result<()> {
+        self.row = match &mut self.scan {
+            Some(scan) => scan.next().transpose().map_err(module_error)?,
+            None => None,
+        };
+        Ok(())
+    }
+
+    fn eof(&self) -> bool {
+        self.row.is_none()
+    }
+
+    fn column(&self, ctx: &mut Context, i: c_int) -> rusqlite::Result<()> {
+        let idx = self.row()?.index;
+        let (x, y, z) = idx.decode_coords();
+        match i {
+            0 => ctx.set_result(&(idx.raw() as i64)),
+            1 => ctx.set_result(&idx.frame_id()),
+            2 => ctx.set_result(&idx.scale_tier()),
+            3 => ctx.set_result(&idx.lod()),
+            4 => ctx.set_result(&x),
+            5 => ctx.set_result(&y),
+            6 => ctx.set_result(&z),
+            7 => ctx.set_result(&(idx.morton() as i64)),
+            _ => {
+                let value = u8::try_from(i - LAYER_COLUMN)
+                    .ok()
+                    .and_then(LayerType::from_tag)
+                    .and_then(|t| self.row.as_ref()?.value(t));
+                ctx.set_result(&value.map(f64::from))
+            }
+        }
+    }
+
+    fn rowid(&self) -> rusqlite::Result<i64> {
+        Ok(self.row()?.index.raw() as i64)
+    }
+}
+
+#[cfg(all(test, not(feature = "sqlite_extension")))]
+mod tests {
+    use super::*;
+    use crate::container_v2::{ContainerWriterV2, StreamConfig};
+    use crate::layers::{LayeredMap, OccupancyLayer, StaticMap};
+    use crate::Index64;
+
+    #[test]
+    fn test_sql_over_container() {
+        let cells: Vec<Index64> = (0..1500u16)
+            .map(|i| Index64::new(0, 0, 10, i % 40, i / 40 % 40, 0).unwrap())
+            .collect();
+        let mut occupancy = OccupancyLayer::new();
+        for (i, &idx) in cells.iter().enumerate() {
+            occupancy.update_occupancy(idx, i % 3 == 0, 0.9);
+        }
+        let mut map = LayeredMap::new();
+        map.add_occupancy_layer(occupancy);
+        let snapshot = StaticMap::from(&map);
+
+        let path = std::env::temp_dir().join(format!("oi3d_sqlite_{}.oct2", std::process::id()));
+        let mut writer = ContainerWriterV2::new(
+            std::fs::File::create(&path).unwrap(),
+            StreamConfig::default(),
+        )
+        .unwrap();
+        snapshot.write_container(&mut writer).unwrap();
+        writer.finish().unwrap();
+
+        let conn = Connection::open_in_memory().unwrap();
+        register(&conn).unwrap();
+        conn.execute_batch(&format!(
+            "CREATE VIRTUAL TABLE cells USING octaindex(path='{}')",
+            path.display()
+        ))
+        .unwrap();
+
+        let count: i64 = conn
+            .query_row("SELECT count(*) FROM cells", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(count, 1500);
+        let (frame, lod, tsdf): (i64, i64, Option<f64>) = conn
+            .query_row("SELECT frame, lod, tsdf FROM cells LIMIT 1", [], |r| {
+                Ok((r.get(0)?, r.get(1)?, r.get(2)?))
+            })
+            .unwrap();
+        assert_eq!((frame, lod, tsdf), (0, 10, None));
+
+        // Morton ranges match a full scan filtered in Rust
+        let mut mortons: Vec<u64> = cells.iter().map(Index64::morton).collect();
+        mortons.sort_unstable();
+        let (lo, hi) = (mortons[200], mortons[900]);
+        let in_range: i64 = conn
+            .query_row(
+                "SELECT count(*) FROM cells WHERE morton >= ?1 AND morton < ?2",
+                [lo as i64, hi as i64],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(in_range, 700);
+        let exact: (i64, i64) = conn
+            .query_row(
+                "SELECT x, y FROM cells WHERE morton = ?1",
+                [cells[41].morton() as i64],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(exact, (1, 1));
+
+        let occupied: i64 = conn
+            .query_row(
+                "SELECT count(*) FROM cells WHERE occupancy > 0.5",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(occupied, 500);
+
+        // Coordinate boxes and equalities combine with other filters
+        let boxed: i64 = conn
+            .query_row(
+                "SELECT count(*) FROM cells WHERE frame = 0 AND lod = 10 \
+                 AND x BETWEEN 5 AND 9 AND y > 30 AND z <= 0",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(boxed, 5 * 7);
+        let none: i64 = conn
+            .query_row(
+                "SELECT count(*) FROM cells WHERE lod = 9 OR x = NULL",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(none, 0);
+
+        std::fs::remove_file(&path).ok();
+    }
+}
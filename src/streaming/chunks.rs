@@ -0,0 +1,537 @@
+//! Chunk residency management around a viewpoint
+
+use super::lod::{lod_selection, LodAssignment, Viewpoint};
+use crate::error::{Error, Result};
+use crate::ids::{FrameId, Index64};
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Loads the payload of a chunk, typically by reading container frames
+///
+/// Called on worker threads. Implemented for closures
+/// `Fn(Index64) -> Result<T>`.
+pub trait ChunkLoader: Send + Sync + 'static {
+    /// Decoded chunk payload
+    type Chunk: Send + Sync + 'static;
+
+    /// Load the chunk with the given key
+    fn load(&self, key: Index64) -> Result<Self::Chunk>;
+}
+
+impl<T, F> ChunkLoader for F
+where
+    F: Fn(Index64) -> Result<T> + Send + Sync + 'static,
+    T: Send + Sync + 'static,
+{
+    type Chunk = T;
+
+    fn load(&self, key: Index64) -> Result<T> {
+        self(key)
+    }
+}
+
+/// Settings for [`ChunkManager`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamingConfig {
+    /// Frame of chunk keys
+    pub frame: FrameId,
+    /// Scale tier of chunk keys
+    pub tier: u8,
+    /// LOD of chunk keys
+    pub chunk_lod: u8,
+    /// LOD whose cell coordinates express the viewpoint
+    pub world_lod: u8,
+    /// Load chunks whose box is within this distance of the viewpoint
+    /// (world cells)
+    pub load_radius: f64,
+    /// Evict chunks whose box is farther than this (world cells); keep it
+    /// above `load_radius` so chunks near the edge do not thrash
+    pub evict_radius: f64,
+    /// Maximum number of loads running at once
+    pub max_in_flight: usize,
+    /// Number of worker threads
+    pub workers: usize,
+}
+
+impl Default for StreamingConfig {
+    fn default() -> Self {
+        Self {
+            frame: 0,
+            tier: 0,
+            chunk_lod: 4,
+            world_lod: 10,
+            load_radius: 128.0,
+            evict_radius: 192.0,
+            max_in_flight: 16,
+            workers: 2,
+        }
+    }
+}
+
+/// Changes made by [`ChunkManager::update`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChunkUpdate {
+    /// Chunks handed to the loader, nearest first
+    pub requested: Vec<Index64>,
+    /// Chunks dropped from memory
+    pub evicted: Vec<Index64>,
+    /// Chunks in range still waiting for a free load slot
+    pub queued: usize,
+}
+
+type Completion<T> = (Index64, Result<T>);
+type LoadedCallback<T> = Box<dyn FnMut(Index64, &Arc<T>)>;
+type FailedCallback = Box<dyn FnMut(Index64, &Error)>;
+
+/// Keeps the chunks around a moving viewpoint resident
+///
+/// Call [`update`](Self::update) when the viewpoint moves and
+/// [`poll`](Self::poll) once per frame. Loads run on a small pool of worker
+/// threads; their results are delivered by `poll` on the calling thread, so
+/// callbacks can touch engine state without locking.
+///
+/// # Example
+///
+/// ```
+/// use octaindex3d::streaming::{ChunkManager, StreamingConfig};
+/// use octaindex3d::Index64;
+/// use std::time::Duration;
+///
+/// # fn main() -> octaindex3d::Result<()> {
+/// let config = StreamingConfig {
+///     chunk_lod: 2,
+///     world_lod: 6,
+///     load_radius: 20.0,
+///     evict_radius: 40.0,
+///     ..Default::default()
+/// };
+/// // Each chunk is 16 world cells wide; the loader fabricates a payload
+/// let mut manager = ChunkManager::new(config, |key: Index64| Ok(key.morton()))?;
+/// manager.on_loaded(|key, payload| assert_eq!(key.morton(), **payload));
+///
+/// let update = manager.update((100.0, 100.0, 100.0))?;
+/// assert!(!update.requested.is_empty());
+/// manager.wait_idle(Duration::from_secs(5));
+/// assert!(manager.get(manager.chunk_at((100.0, 100.0, 100.0))?).is_some());
+/// # Ok(())
+/// # }
+/// ```
+pub struct ChunkManager<L: ChunkLoader> {
+    config: StreamingConfig,
+    loaded: HashMap<Index64, Arc<L::Chunk>>,
+    /// Loads handed to workers whose result is still wanted
+    in_flight: HashSet<Index64>,
+    /// Chunks in range waiting for a load slot, nearest first
+    queue: Vec<Index64>,
+    /// Chunks whose load failed; retried after they leave the evict radius
+    failed: HashSet<Index64>,
+    viewpoint: Option<(f64, f64, f64)>,
+    jobs: Option<Sender<Index64>>,
+    results: Receiver<Completion<L::Chunk>>,
+    workers: Vec<JoinHandle<()>>,
+    on_loaded: Vec<LoadedCallback<L::Chunk>>,
+    on_evicted: Vec<Box<dyn FnMut(Index64)>>,
+    on_failed: Vec<FailedCallback>,
+}
+
+impl<L: ChunkLoader> ChunkManager<L> {
+    /// Create a manager and start its worker threads
+    pub fn new(config: StreamingConfig, loader: L) -> Result<Self> {
+        if config.world_lod > 15 || config.chunk_lod > config.world_lod {
+            return Err(Error::InvalidLOD(format!(
+                "need chunk_lod <= world_lod <= 15, got {} and {}",
+                config.chunk_lod, config.world_lod
+            )));
+        }
+
+        let loader = Arc::new(loader);
+        let (job_tx, job_rx) = mpsc::channel::<Index64>();
+        let (result_tx, results) = mpsc::channel();
+        let job_rx = Arc::new(Mutex::new(job_rx));
+        let workers = (0..config.workers.max(1))
+            .map(|_| {
+                let loader = Arc::clone(&loader);
+                let jobs = Arc::clone(&job_rx);
+                let results = result_tx.clone();
+                std::thread::spawn(move || loop {
+                    let job = match jobs.lock() {
+                        Ok(jobs) => jobs.recv(),
+                        Err(_) => return,
+                    };
+                    let Ok(key) = job else {
+                        return; // Manager dropped
+                    };
+                    if results.send((key, loader.load(key))).is_err() {
+                        return;
+                    }
+                })
+            })
+            .collect();
+
+        Ok(Self {
+            config,
+            loaded: HashMap::new(),
+            in_flight: HashSet::new(),
+            queue: Vec::new(),
+            failed: HashSet::new(),
+            viewpoint: None,
+            jobs: Some(job_tx),
+            results,
+            workers,
+            on_loaded: Vec::new(),
+            on_evicted: Vec::new(),
+            on_failed: Vec::new(),
+        })
+    }
+
+    /// Register a callback for chunks that finished loading
+    pub fn on_loaded<F: FnMut(Index64, &Arc<L::Chunk>) + 'static>(&mut self, callback: F) {
+        self.on_loaded.push(Box::new(callback));
+    }
+
+    /// Register a callback for chunks dropped from memory
+    pub fn on_evicted<F: FnMut(Index64) + 'static>(&mut self, callback: F) {
+        self.on_evicted.push(Box::new(callback));
+    }
+
+    /// Register a callback for failed loads
+    pub fn on_failed<F: FnMut(Index64, &Error) + 'static>(&mut self, callback: F) {
+        self.on_failed.push(Box::new(callback));
+    }
+
+    /// Settings of this manager
+    pub fn config(&self) -> &StreamingConfig {
+        &self.config
+    }
+
+    /// Viewpoint of the last [`update`](Self::update)
+    pub fn viewpoint(&self) -> Option<(f64, f64, f64)> {
+        self.viewpoint
+    }
+
+    /// Width of a chunk in world cells
+    pub fn chunk_size(&self) -> f64 {
+        (1u64 << (self.config.world_lod - self.config.chunk_lod)) as f64
+    }
+
+    /// Key of the chunk containing a world position
+    pub fn chunk_at(&self, position: (f64, f64, f64)) -> Result<Index64> {
+        let size = self.chunk_size();
+        let coord = |v: f64| {
+            let c = (v / size).floor();
+            if (0.0..=u16::MAX as f64).contains(&c) {
+                Ok(c as u16)
+            } else {
+                Err(Error::OutOfRange(format!(
+                    "position {v} outside chunk grid"
+                )))
+            }
+        };
+        Index64::new(
+            self.config.frame,
+            self.config.tier,
+            self.config.chunk_lod,
+            coord(position.0)?,
+            coord(position.1)?,
+            coord(position.2)?,
+        )
+    }
+
+    /// Distance from a world position to the box of a chunk
+    pub fn distance_to(&self, key: Index64, position: (f64, f64, f64)) -> f64 {
+        let size = self.chunk_size();
+        let (x, y, z) = key.decode_coords();
+        let axis = |c: u16, v: f64| {
+            let lo = c as f64 * size;
+            (lo - v).max(v - (lo + size)).max(0.0)
+        };
+        let (dx, dy, dz) = (
+            axis(x, position.0),
+            axis(y, position.1),
+            axis(z, position.2),
+        );
+        (dx * dx + dy * dy + dz * dz).sqrt()
+    }
+
+    /// Move the viewpoint: evict distant chunks and request nearby ones
+    pub fn update(&mut self, viewpoint: (f64, f64, f64)) -> Result<ChunkUpdate> {
+        self.viewpoint = Some(viewpoint);
+        let mut update = ChunkUpdate::default();
+
+        // Evict, cancel and forget failures beyond the evict radius
+        let evict = self.config.evict_radius.max(self.config.load_radius);
+        let mut evicted: Vec<Index64> = self
+            .loaded
+            .keys()
+            .filter(|&&key| self.distance_to(key, viewpoint) > evict)
+            .copied()
+            .collect();
+        evicted.sort_unstable();
+        for key in &evicted {
+            self.loaded.remove(key);
+            for callback in &mut self.on_evicted {
+                callback(*key);
+            }
+        }
+        update.evicted = evicted;
+        let far = |key: &Index64| self.distance_to(*key, viewpoint) > evict;
+        let cancelled: Vec<Index64> = self.in_flight.iter().filter(|k| far(k)).copied().collect();
+        let forgotten: Vec<Index64> = self.failed.iter().filter(|k| far(k)).copied().collect();
+        for key in cancelled {
+            self.in_flight.remove(&key);
+        }
+        for key in forgotten {
+            self.failed.remove(&key);
+        }
+
+        // Chunks within the load radius, nearest first
+        let radius = self.config.load_radius.max(0.0);
+        let size = self.chunk_size();
+        let range = |v: f64| {
+            let lo = ((v - radius) / size).floor().max(0.0);
+            let hi = ((v + radius) / size).floor().min(u16::MAX as f64);
+            (lo as u32, hi as u32)
+        };
+        let (rx, ry, rz) = (range(viewpoint.0), range(viewpoint.1), range(viewpoint.2));
+        let mut wanted = Vec::new();
+        for z in rz.0..=rz.1 {
+            for y in ry.0..=ry.1 {
+                for x in rx.0..=rx.1 {
+                    let key = Index64::new(
+                        self.config.frame,
+                        self.config.tier,
+                        self.config.chunk_lod,
+                        x as u16,
+                        y as u16,
+                        z as u16,
+                    )?;
+                    let distance = self.distance_to(key, viewpoint);
+                    if distance <= radius
+                        && !self.loaded.contains_key(&key)
+                        && !self.in_flight.contains(&key)
+                        && !self.failed.contains(&key)
+                    {
+                        wanted.push((distance, key));
+                    }
+                }
+            }
+        }
+        wanted.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+        self.queue = wanted.into_iter().map(|(_, key)| key).collect();
+
+        update.requested = self.dispatch();
+        update.queued = self.queue.len();
+        Ok(update)
+    }
+
+    /// Hand queued chunks to the workers while load slots are free
+    fn dispatch(&mut self) -> Vec<Index64> {
+        let free = self
+            .config
+            .max_in_flight
+            .max(1)
+            .saturating_sub(self.in_flight.len());
+        let take = free.min(self.queue.len());
+        let requested: Vec<Index64> = self.queue.drain(..take).collect();
+        if let Some(jobs) = &self.jobs {
+            for &key in &requested {
+                self.in_flight.insert(key);
+                let _ = jobs.send(key);
+            }
+        }
+        requested
+    }
+
+    /// Deliver finished loads and start queued ones
+    ///
+    /// Runs the registered callbacks on the calling thread and returns the
+    /// number of chunks that became available.
+    pub fn poll(&mut self) -> usize {
+        let mut arrived = 0;
+        while let Ok(completion) = self.results.try_recv() {
+            arrived += usize::from(self.complete(completion));
+        }
+        self.dispatch();
+        arrived
+    }
+
+    /// Poll until nothing is loading or queued, or until `timeout` expires
+    ///
+    /// Returns `true` when idle. Intended for loading screens and tests.
+    pub fn wait_idle(&mut self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        loop {
+            self.poll();
+            if self.in_flight.is_empty() && self.queue.is_empty() {
+                return true;
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return false;
+            }
+            if let Ok(completion) = self.results.recv_timeout(remaining) {
+                self.complete(completion);
+            }
+        }
+    }
+
+    /// Store one load result; returns `true` if a chunk became available
+    fn complete(&mut self, (key, result): Completion<L::Chunk>) -> bool {
+        if !self.in_flight.remove(&key) {
+            return false; // Cancelled while loading
+        }
+        match result {
+            Ok(chunk) => {
+                let chunk = Arc::new(chunk);
+                for callback in &mut self.on_loaded {
+                    callback(key, &chunk);
+                }
+                self.loaded.insert(key, chunk);
+                true
+            }
+            Err(error) => {
+                self.failed.insert(key);
+                for callback in &mut self.on_failed {
+                    callback(key, &error);
+                }
+                false
+            }
+        }
+    }
+
+    /// Resident payload of a chunk
+    pub fn get(&self, key: Index64) -> Option<Arc<L::Chunk>> {
+        self.loaded.get(&key).cloned()
+    }
+
+    /// Check if a chunk is resident
+    pub fn is_loaded(&self, key: Index64) -> bool {
+        self.loaded.contains_key(&key)
+    }
+
+    /// Resident chunk keys in ascending order
+    pub fn loaded_keys(&self) -> Vec<Index64> {
+        let mut keys: Vec<Index64> = self.loaded.keys().copied().collect();
+        keys.sort_unstable();
+        keys
+    }
+
+    /// Rendering LODs for the resident chunks, in ascending key order
+    ///
+    /// See [`lod_selection`]; the viewpoint is in world cells.
+    pub fn lod_selection(
+        &self,
+        viewpoint: &Viewpoint,
+        screen_error_budget: f64,
+    ) -> Vec<LodAssignment> {
+        lod_selection(
+            viewpoint,
+            screen_error_budget,
+            &self.loaded_keys(),
+            self.config.world_lod,
+        )
+    }
+
+    /// Number of resident chunks
+    pub fn loaded_count(&self) -> usize {
+        self.loaded.len()
+    }
+
+    /// Number of chunks loading or waiting for a slot
+    pub fn pending_count(&self) -> usize {
+        self.in_flight.len() + self.queue.len()
+    }
+}
+
+impl<L: ChunkLoader> Drop for ChunkManager<L> {
+    fn drop(&mut self) {
+        // Closing the job channel stops the workers after their current load
+        self.jobs = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn config() -> StreamingConfig {
+        StreamingConfig {
+            chunk_lod: 2,
+            world_lod: 6,
+            load_radius: 16.0,
+            evict_radius: 32.0,
+            max_in_flight: 4,
+            workers: 2,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_streams_around_moving_viewpoint() -> Result<()> {
+        let mut manager = ChunkManager::new(config(), |key: Index64| Ok(key.decode_coords()))?;
+        let loaded = Rc::new(RefCell::new(Vec::new()));
+        let evicted = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&loaded);
+        manager.on_loaded(move |key, payload| {
+            assert_eq!(key.decode_coords(), **payload);
+            sink.borrow_mut().push(key);
+        });
+        let sink = Rc::clone(&evicted);
+        manager.on_evicted(move |key| sink.borrow_mut().push(key));
+
+        // 16-cell chunks; a 16-cell radius reaches the 27 chunks around (8, 8, 8) + 16
+        let start = (24.0, 24.0, 24.0);
+        let update = manager.update(start)?;
+        assert_eq!(update.requested.len(), 4);
+        assert_eq!(update.requested[0], manager.chunk_at(start)?);
+        assert_eq!(update.queued, 23);
+        assert!(manager.wait_idle(Duration::from_secs(10)));
+        assert_eq!(manager.loaded_count(), 27);
+        assert_eq!(loaded.borrow().len(), 27);
+
+        // Move far away: everything near the start is evicted
+        let update = manager.update((400.0, 24.0, 24.0))?;
+        assert_eq!(update.evicted.len(), 27);
+        assert_eq!(evicted.borrow().len(), 27);
+        assert!(manager.wait_idle(Duration::from_secs(10)));
+        assert!(!manager.is_loaded(manager.chunk_at(start)?));
+        assert!(manager.is_loaded(manager.chunk_at((400.0, 24.0, 24.0))?));
+
+        let view = Viewpoint::new((400.0, 24.0, 24.0), 1.0, 720.0);
+        let lods = manager.lod_selection(&view, 2.0);
+        assert_eq!(lods.len(), manager.loaded_count());
+        assert!(lods.iter().all(|a| (2..=6).contains(&a.lod)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_failed_loads_are_reported_once() -> Result<()> {
+        let mut manager = ChunkManager::new(config(), |key: Index64| {
+            if key.decode_coords() == (1, 1, 1) {
+                Err(Error::Io("missing chunk".into()))
+            } else {
+                Ok(())
+            }
+        })?;
+        let failures = Rc::new(RefCell::new(0));
+        let sink = Rc::clone(&failures);
+        manager.on_failed(move |_, _| *sink.borrow_mut() += 1);
+
+        manager.update((24.0, 24.0, 24.0))?;
+        assert!(manager.wait_idle(Duration::from_secs(10)));
+        manager.update((25.0, 24.0, 24.0))?;
+        assert!(manager.wait_idle(Duration::from_secs(10)));
+        assert_eq!(*failures.borrow(), 1);
+        assert_eq!(manager.loaded_count(), 26);
+        assert!(manager.chunk_at((-1.0, 0.0, 0.0)).is_err());
+        Ok(())
+    }
+}
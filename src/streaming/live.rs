@@ -0,0 +1,566 @@
+//! Live map streaming to viewers
+//!
+//! [`LiveStream`] follows a layer's [`DirtyTracker`](crate::layers::DirtyTracker)
+//! and turns the chunks changed since its previous frame into a
+//! [`LiveFrame`]: a snapshot of every chunk first, then deltas holding the
+//! full contents of each changed chunk. With the `websocket` feature,
+//! [`LiveServer`] sends the frames to browser viewers, each new viewer
+//! starting from a snapshot.
+//!
+//! # Wire format
+//!
+//! Frames are binary WebSocket messages, little-endian (the byte order of
+//! browser typed arrays):
+//!
+//! | Bytes | Field |
+//! |-------|-------|
+//! | 4 | tag `OLV1` |
+//! | 4 | sequence number, `u32` |
+//! | 1 | kind: 0 delta, 1 snapshot (drop every chunk first) |
+//! | 1 | [`LayerType::tag`] of the values |
+//! | 1 | chunk LOD |
+//! | 1 | reserved, 0 |
+//! | 4 | group count, `u32` |
+//!
+//! followed by the groups. A group holds the cells of one chunk at one
+//! LOD: the chunk's [`Index64`] as `u64`, the cell LOD as `u8`, the cell
+//! count as `u32`, then per cell its Morton offset within the chunk as
+//! `u32` and its value as `f32`. A cell's Morton code is the chunk's shifted
+//! left by three bits per LOD between them, plus the offset. The groups of
+//! a chunk are adjacent and replace everything the viewer held for it; a
+//! chunk emptied by the change is sent as one group without cells.
+//!
+//! `include/octaindex3d_live.js` is a reference decoder for viewers.
+
+use crate::error::{Error, Result};
+use crate::layers::{DirtyCursor, Layer, LayerType};
+use crate::Index64;
+use std::collections::BTreeMap;
+
+/// Tag opening every frame
+pub const LIVE_FRAME_TAG: &[u8; 4] = b"OLV1";
+
+/// Bytes before the first group
+const HEADER_LEN: usize = 16;
+
+/// Deepest cell LOD below the chunk LOD whose offsets fit 32 bits
+const MAX_CELL_DEPTH: u8 = 10;
+
+/// What a [`LiveFrame`] holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiveFrameKind {
+    /// Chunks changed since the previous frame
+    Delta,
+    /// Every chunk; viewers drop what they held
+    Snapshot,
+}
+
+/// Contents of one chunk
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkDelta {
+    /// Chunk key at the frame's chunk LOD
+    pub chunk: Index64,
+    /// Cells of the chunk with their values, empty if the chunk was cleared
+    pub cells: Vec<(Index64, f32)>,
+}
+
+/// One message of a live stream
+#[derive(Debug, Clone, PartialEq)]
+pub struct LiveFrame {
+    /// Position in the stream
+    pub seq: u32,
+    /// Delta or snapshot
+    pub kind: LiveFrameKind,
+    /// Type of the layer the values come from
+    pub layer_type: LayerType,
+    /// LOD of the chunk keys
+    pub chunk_lod: u8,
+    /// Chunks in key order
+    pub chunks: Vec<ChunkDelta>,
+}
+
+impl LiveFrame {
+    /// Serialize to the wire format
+    ///
+    /// Fails if a cell is more than ten LODs finer than its chunk.
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let mut groups: Vec<u8> = Vec::new();
+        let mut group_count = 0u32;
+        for delta in &self.chunks {
+            let mut by_lod: BTreeMap<u8, Vec<(u32, f32)>> = BTreeMap::new();
+            for &(cell, value) in &delta.cells {
+                let depth = cell.lod().saturating_sub(delta.chunk.lod());
+                if depth > MAX_CELL_DEPTH
+                    || cell.ancestor_at_lod(delta.chunk.lod()) != Some(delta.chunk)
+                {
+                    return Err(Error::OutOfRange(format!(
+                        "cell at LOD {} cannot be sent in chunk {:?}",
+                        cell.lod(),
+                        delta.chunk
+                    )));
+                }
+                let offset = cell.morton() & ((1u64 << (3 * depth as u32)) - 1);
+                by_lod
+                    .entry(cell.lod())
+                    .or_default()
+                    .push((offset as u32, value));
+            }
+            if by_lod.is_empty() {
+                by_lod.insert(delta.chunk.lod(), Vec::new());
+            }
+            for (lod, cells) in by_lod {
+                groups.extend_from_slice(&delta.chunk.raw().to_le_bytes());
+                groups.push(lod);
+                groups.extend_from_slice(&(cells.len() as u32).to_le_bytes());
+                for (offset, value) in cells {
+                    groups.extend_from_slice(&offset.to_le_bytes());
+                    groups.extend_from_slice(&value.to_le_bytes());
+                }
+                group_count += 1;
+            }
+        }
+
+        let mut out = Vec::with_capacity(HEADER_LEN + groups.len());
+        out.extend_from_slice(LIVE_FRAME_TAG);
+        out.extend_from_slice(&self.seq.to_le_bytes());
+        out.push(match self.kind {
+            LiveFrameKind::Delta => 0,
+            LiveFrameKind::Snapshot => 1,
+        });
+        out.push(self.layer_type.tag());
+        out.push(self.chunk_lod);
+        out.push(0);
+        out.extend_from_slice(&group_count.to_le_bytes());
+        out.extend_from_slice(&groups);
+        Ok(out)
+    }
+
+    /// Parse the wire format
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let invalid = |msg: &str| Error::InvalidFormat(format!("live frame {}", msg));
+        if bytes.len() < HEADER_LEN || &bytes[..4] != LIVE_FRAME_TAG {
+            return Err(invalid("tag missing"));
+        }
+        let u32_at = |at: usize| -> Result<u32> {
+            bytes
+                .get(at..at + 4)
+                .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+                .ok_or_else(|| invalid("is truncated"))
+        };
+        let kind = match bytes[8] {
+            0 => LiveFrameKind::Delta,
+            1 => LiveFrameKind::Snapshot,
+            other => return Err(invalid(&format!("kind {} is unknown", other))),
+        };
+        let layer_type =
+            LayerType::from_tag(bytes[9]).ok_or_else(|| invalid("layer is unknown"))?;
+        let mut frame = Self {
+            seq: u32_at(4)?,
+            kind,
+            layer_type,
+            chunk_lod: bytes[10],
+            chunks: Vec::new(),
+        };
+
+        let mut at = HEADER_LEN;
+        for _ in 0..u32_at(12)? {
+            let raw = bytes
+                .get(at..at + 8)
+                .ok_or_else(|| invalid("is truncated"))?;
+            let chunk = Index64::from_value(u64::from_le_bytes(raw.try_into().unwrap()))?;
+            let lod = *bytes.get(at + 8).ok_or_else(|| invalid("is truncated"))?;
+            let count = u32_at(at + 9)? as usize;
+            at += 13;
+            let depth = lod
+                .checked_sub(chunk.lod())
+                .filter(|&d| d <= MAX_CELL_DEPTH)
+                .ok_or_else(|| invalid("cell LOD is outside its chunk"))?;
+            if frame.chunks.last().map(|d| d.chunk) != Some(chunk) {
+                frame.chunks.push(ChunkDelta {
+                    chunk,
+                    cells: Vec::with_capacity(count.min((bytes.len() - at) / 8)),
+                });
+            }
+            let cells = &mut frame.chunks.last_mut().expect("pushed above").cells;
+            for _ in 0..count {
+                let offset = u32_at(at)? as u64;
+                let value = f32::from_bits(u32_at(at + 4)?);
+                at += 8;
+                let morton = chunk.morton() << (3 * depth as u32) | offset;
+                cells.push((with_lod_morton(chunk, lod, morton)?, value));
+            }
+        }
+        if at != bytes.len() {
+            return Err(invalid("has trailing bytes"));
+        }
+        Ok(frame)
+    }
+}
+
+/// `chunk` with its LOD and Morton code replaced
+fn with_lod_morton(chunk: Index64, lod: u8, morton: u64) -> Result<Index64> {
+    let (x, y, z) = crate::morton::morton_decode(morton);
+    Index64::new(chunk.frame_id(), chunk.scale_tier(), lod, x, y, z)
+}
+
+/// Producer of the frames of one layer
+///
+/// # Example
+///
+/// ```
+/// use octaindex3d::layers::OccupancyLayer;
+/// use octaindex3d::streaming::{LiveFrame, LiveFrameKind, LiveStream};
+/// use octaindex3d::Index64;
+///
+/// # fn main() -> octaindex3d::Result<()> {
+/// let mut occupancy = OccupancyLayer::new();
+/// occupancy.update_occupancy(Index64::new(0, 0, 8, 10, 10, 10)?, true, 0.9);
+///
+/// let mut stream = LiveStream::new();
+/// let first = stream.next_frame(&occupancy)?.expect("snapshot");
+/// assert_eq!(first.kind, LiveFrameKind::Snapshot);
+/// assert!(stream.next_frame(&occupancy)?.is_none());
+///
+/// occupancy.update_occupancy(Index64::new(0, 0, 8, 200, 10, 10)?, true, 0.9);
+/// let delta = stream.next_frame(&occupancy)?.expect("one chunk changed");
+/// assert_eq!(delta.chunks.len(), 1);
+///
+/// // Viewers receive the encoded bytes
+/// assert_eq!(LiveFrame::decode(&delta.encode()?)?, delta);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct LiveStream {
+    /// Position in the layer's changes, `None` before the first snapshot
+    cursor: Option<DirtyCursor>,
+    seq: u32,
+}
+
+impl LiveStream {
+    /// Create a stream whose first frame is a snapshot
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Snapshot on the first call, then the chunks changed since the
+    /// previous frame, or `None` if none changed
+    ///
+    /// Fails for layers without change tracking.
+    pub fn next_frame(&mut self, layer: &dyn Layer) -> Result<Option<LiveFrame>> {
+        let tracker = dirty_tracker(layer)?;
+        let Some(cursor) = &mut self.cursor else {
+            return self.snapshot(layer).map(Some);
+        };
+        let changed = tracker.take_dirty_since(cursor);
+        if changed.is_empty() {
+            return Ok(None);
+        }
+        let mut chunks: BTreeMap<Index64, Vec<(Index64, f32)>> = changed
+            .into_iter()
+            .map(|chunk| (chunk, Vec::new()))
+            .collect();
+        for idx in layer.indices() {
+            if let Some(cells) = chunks.get_mut(&tracker.chunk_of(idx)) {
+                cells.extend(layer.query(idx).map(|value| (idx, value)));
+            }
+        }
+        Ok(Some(self.frame(layer, LiveFrameKind::Delta, chunks)))
+    }
+
+    /// Every chunk of the layer; later frames report changes from here
+    pub fn snapshot(&mut self, layer: &dyn Layer) -> Result<LiveFrame> {
+        let tracker = dirty_tracker(layer)?;
+        self.cursor = Some(tracker.cursor());
+        let mut chunks: BTreeMap<Index64, Vec<(Index64, f32)>> = BTreeMap::new();
+        for idx in layer.indices() {
+            if let Some(value) = layer.query(idx) {
+                chunks
+                    .entry(tracker.chunk_of(idx))
+                    .or_default()
+                    .push((idx, value));
+            }
+        }
+        Ok(self.frame(layer, LiveFrameKind::Snapshot, chunks))
+    }
+
+    fn frame(
+        &mut self,
+        layer: &dyn Layer,
+        kind: LiveFrameKind,
+        chunks: BTreeMap<Index64, Vec<(Index64, f32)>>,
+    ) -> LiveFrame {
+        let frame = LiveFrame {
+            seq: self.seq,
+            kind,
+            layer_type: layer.layer_type(),
+            chunk_lod: layer.dirty_tracker().map_or(0, |t| t.chunk_lod()),
+            chunks: chunks
+                .into_iter()
+                .map(|(chunk, mut cells)| {
+                    cells.sort_unstable_by_key(|&(idx, _)| idx);
+                    ChunkDelta { chunk, cells }
+                })
+                .collect(),
+        };
+        self.seq = self.seq.wrapping_add(1);
+        frame
+    }
+}
+
+fn dirty_tracker(layer: &dyn Layer) -> Result<&crate::layers::DirtyTracker> {
+    layer.dirty_tracker().ok_or_else(|| {
+        Error::InvalidFormat(format!(
+            "{} layer does not track changes",
+            layer.layer_type().name()
+        ))
+    })
+}
+
+/// Frames queued for one viewer before it counts as too slow
+#[cfg(feature = "websocket")]
+const VIEWER_QUEUE: usize = 8;
+
+/// WebSocket server pushing a layer's [`LiveFrame`]s to viewers
+///
+/// [`Self::publish`] runs on the caller's thread: it accepts the viewers
+/// that connected since the previous call, queues a snapshot for them, and
+/// queues the chunks changed in between for everyone else. Each viewer has
+/// its own thread doing the handshake and the sends, so a slow viewer never
+/// holds up `publish`. A viewer whose queue is full is dropped, since
+/// skipping a delta would leave its map stale; it can reconnect for a fresh
+/// snapshot. Viewers that have gone away are dropped the same way.
+///
+/// # Example
+///
+/// ```no_run
+/// use octaindex3d::layers::OccupancyLayer;
+/// use octaindex3d::streaming::LiveServer;
+///
+/// # fn main() -> octaindex3d::Result<()> {
+/// let mut occupancy = OccupancyLayer::new();
+/// let mut server = LiveServer::bind("0.0.0.0:9001")?;
+/// loop {
+///     // ... integrate sensor data into `occupancy` ...
+///     server.publish(&occupancy)?;
+///     std::thread::sleep(std::time::Duration::from_millis(100));
+/// }
+/// # }
+/// ```
+#[cfg(feature = "websocket")]
+pub struct LiveServer {
+    listener: std::net::TcpListener,
+    stream: LiveStream,
+    viewers: Vec<Viewer>,
+}
+
+/// Frame queue of one viewer's sending thread
+#[cfg(feature = "websocket")]
+type Viewer = std::sync::mpsc::SyncSender<tungstenite::Message>;
+
+#[cfg(feature = "websocket")]
+impl LiveServer {
+    /// Listen for viewers on `addr`
+    pub fn bind<A: std::net::ToSocketAddrs>(addr: A) -> Result<Self> {
+        let listener = std::net::TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener,
+            stream: LiveStream::new(),
+            viewers: Vec::new(),
+        })
+    }
+
+    /// The address the server listens on
+    pub fn local_addr(&self) -> Result<std::net::SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Viewers connected at the last publish
+    pub fn viewer_count(&self) -> usize {
+        self.viewers.len()
+    }
+
+    /// Accept waiting viewers and queue every viewer its next frame,
+    /// returning the number of frames queued
+    pub fn publish(&mut self, layer: &dyn Layer) -> Result<usize> {
+        let joined = self.accept();
+        let mut sent = 0;
+        if let Some(frame) = self.stream.next_frame(layer)? {
+            // The first publish has no earlier viewers to send a delta to
+            if frame.kind == LiveFrameKind::Delta {
+                let bytes = frame.encode()?;
+                sent += send_all(&mut self.viewers, &bytes);
+            }
+        }
+        if !joined.is_empty() {
+            let mut joined = joined;
+            let bytes = self.stream.snapshot(layer)?.encode()?;
+            sent += send_all(&mut joined, &bytes);
+            self.viewers.append(&mut joined);
+        }
+        Ok(sent)
+    }
+
+    /// Start a sending thread for every viewer waiting to connect
+    fn accept(&mut self) -> Vec<Viewer> {
+        let mut joined = Vec::new();
+        while let Ok((tcp, _)) = self.listener.accept() {
+            let (queue, frames) = std::sync::mpsc::sync_channel(VIEWER_QUEUE);
+            let spawned = std::thread::Builder::new()
+                .name("live-viewer".to_string())
+                .spawn(move || serve_viewer(tcp, frames));
+            if spawned.is_ok() {
+                joined.push(queue);
+            }
+        }
+        joined
+    }
+}
+
+/// Complete a viewer's handshake and send it queued frames until the
+/// server drops its queue or a send fails
+#[cfg(feature = "websocket")]
+fn serve_viewer(tcp: std::net::TcpStream, frames: std::sync::mpsc::Receiver<tungstenite::Message>) {
+    let timeout = Some(std::time::Duration::from_secs(2));
+    let ready = tcp.set_nonblocking(false).is_ok()
+        && tcp.set_read_timeout(timeout).is_ok()
+        && tcp.set_write_timeout(timeout).is_ok();
+    if !ready {
+        return;
+    }
+    let Ok(mut socket) = tungstenite::accept(tcp) else {
+        return;
+    };
+    while let Ok(message) = frames.recv() {
+        if socket.send(message).is_err() {
+            return;
+        }
+    }
+    let _ = socket.close(None);
+}
+
+/// Queue `bytes` for every viewer, dropping those that are gone or whose
+/// queue is full; returns the number reached
+#[cfg(feature = "websocket")]
+fn send_all(viewers: &mut Vec<Viewer>, bytes: &[u8]) -> usize {
+    let message = tungstenite::Message::binary(bytes.to_vec());
+    viewers.retain(|queue| queue.try_send(message.clone()).is_ok());
+    viewers.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layers::OccupancyLayer;
+
+    #[test]
+    fn test_frames_follow_changes() {
+        let cell = |x, y| Index64::new(0, 0, 8, x, y, 3).unwrap();
+        let mut occupancy = OccupancyLayer::new();
+        occupancy.update_occupancy(cell(1, 1), true, 0.9);
+        occupancy.update_occupancy(cell(2, 1), false, 0.9);
+        occupancy.update_occupancy(cell(100, 1), true, 0.9);
+
+        let mut stream = LiveStream::new();
+        let snapshot = stream.next_frame(&occupancy).unwrap().unwrap();
+        assert_eq!(snapshot.kind, LiveFrameKind::Snapshot);
+        assert_eq!(snapshot.chunk_lod, 4);
+        assert_eq!(snapshot.chunks.len(), 2);
+        assert_eq!(snapshot.chunks[0].cells.len(), 2);
+
+        occupancy.update_occupancy(cell(3, 2), true, 0.9);
+        let delta = stream.next_frame(&occupancy).unwrap().unwrap();
+        assert_eq!((delta.seq, delta.kind), (1, LiveFrameKind::Delta));
+        // The whole chunk is resent
+        assert_eq!(delta.chunks.len(), 1);
+        assert_eq!(delta.chunks[0].cells.len(), 3);
+
+        let bytes = delta.encode().unwrap();
+        assert_eq!(bytes.len(), HEADER_LEN + 13 + 3 * 8);
+        assert_eq!(LiveFrame::decode(&bytes).unwrap(), delta);
+        assert!(LiveFrame::decode(&bytes[..bytes.len() - 1]).is_err());
+
+        // Mixed LODs split into groups; an emptied chunk keeps one group
+        let chunk = Index64::new(0, 0, 4, 0, 0, 0).unwrap();
+        let mixed = LiveFrame {
+            chunks: vec![
+                ChunkDelta {
+                    chunk,
+                    cells: vec![
+                        (cell(1, 1), 0.5),
+                        (Index64::new(0, 0, 6, 1, 1, 0).unwrap(), 0.25),
+                    ],
+                },
+                ChunkDelta {
+                    chunk: Index64::new(0, 0, 4, 1, 0, 0).unwrap(),
+                    cells: Vec::new(),
+                },
+            ],
+            ..delta
+        };
+        let bytes = mixed.encode().unwrap();
+        assert_eq!(u32::from_le_bytes(bytes[12..16].try_into().unwrap()), 3);
+        let decoded = LiveFrame::decode(&bytes).unwrap();
+        assert_eq!(decoded.chunks.len(), 2);
+        assert_eq!(decoded.chunks[0].cells.len(), 2);
+        assert!(decoded.chunks[1].cells.is_empty());
+
+        let outside = LiveFrame {
+            chunks: vec![ChunkDelta {
+                chunk,
+                cells: vec![(cell(100, 1), 1.0)],
+            }],
+            ..mixed
+        };
+        assert!(outside.encode().is_err());
+    }
+
+    #[cfg(feature = "websocket")]
+    #[test]
+    fn test_server_sends_snapshot_then_deltas() {
+        let mut occupancy = OccupancyLayer::new();
+        occupancy.update_occupancy(Index64::new(0, 0, 8, 5, 5, 5).unwrap(), true, 0.9);
+        let mut server = LiveServer::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}", server.local_addr().unwrap());
+
+        let viewer = std::thread::spawn(move || {
+            let (mut socket, _) = tungstenite::connect(url).unwrap();
+            (0..2)
+                .map(|_| LiveFrame::decode(&socket.read().unwrap().into_data()).unwrap())
+                .collect::<Vec<_>>()
+        });
+        while server.viewer_count() == 0 {
+            server.publish(&occupancy).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        occupancy.update_occupancy(Index64::new(0, 0, 8, 200, 5, 5).unwrap(), true, 0.9);
+        assert_eq!(server.publish(&occupancy).unwrap(), 1);
+
+        let frames = viewer.join().unwrap();
+        assert_eq!(frames[0].kind, LiveFrameKind::Snapshot);
+        assert_eq!(frames[0].chunks.len(), 1);
+        assert_eq!(frames[1].kind, LiveFrameKind::Delta);
+        assert_eq!(frames[1].chunks[0].cells[0].0.decode_coords(), (200, 5, 5));
+    }
+
+    #[cfg(feature = "websocket")]
+    #[test]
+    fn test_stalled_viewer_is_dropped_without_blocking() {
+        let mut occupancy = OccupancyLayer::new();
+        let mut server = LiveServer::bind("127.0.0.1:0").unwrap();
+        // Connects but never completes the handshake
+        let _stalled = std::net::TcpStream::connect(server.local_addr().unwrap()).unwrap();
+        while server.viewer_count() == 0 {
+            server.publish(&occupancy).unwrap();
+        }
+
+        let start = std::time::Instant::now();
+        for x in 0..2 * VIEWER_QUEUE as u16 {
+            occupancy.update_occupancy(Index64::new(0, 0, 8, 2 * x, 6, 6).unwrap(), true, 0.9);
+            server.publish(&occupancy).unwrap();
+        }
+        assert_eq!(server.viewer_count(), 0);
+        assert!(start.elapsed() < std::time::Duration::from_secs(1));
+    }
+}
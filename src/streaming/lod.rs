@@ -0,0 +1,201 @@
+//! Screen-space level-of-detail selection
+
+use crate::ids::Index64;
+use std::collections::HashMap;
+
+/// Perspective camera used for screen-space error estimates
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewpoint {
+    /// Eye position in world cells
+    pub position: (f64, f64, f64),
+    /// Vertical field of view (radians)
+    pub vertical_fov: f64,
+    /// Viewport height (pixels)
+    pub viewport_height: f64,
+}
+
+impl Viewpoint {
+    /// Create a viewpoint
+    pub fn new(position: (f64, f64, f64), vertical_fov: f64, viewport_height: f64) -> Self {
+        Self {
+            position,
+            vertical_fov,
+            viewport_height,
+        }
+    }
+
+    /// Pixels covered by one world cell at the given distance
+    pub fn pixels_per_cell(&self, distance: f64) -> f64 {
+        let half_height = distance.max(1e-9) * (0.5 * self.vertical_fov).tan();
+        0.5 * self.viewport_height / half_height
+    }
+}
+
+/// Rendering LOD chosen for one chunk
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LodAssignment {
+    /// Chunk key
+    pub chunk: Index64,
+    /// LOD to render, between the chunk's LOD and the world LOD
+    pub lod: u8,
+    /// Distance from the viewpoint to the chunk box (world cells)
+    pub distance: f64,
+    /// Projected size of one cell at `lod` (pixels)
+    pub projected_error: f64,
+    /// Geomorph factor in `[0, 1]`: 0 right after switching to `lod`,
+    /// approaching 1 where the next coarser LOD would suffice
+    pub blend: f64,
+}
+
+/// Choose a rendering LOD for each chunk from its projected cell size
+///
+/// A chunk renders at the coarsest LOD whose cells project to at most
+/// `screen_error_budget` pixels at the chunk's nearest point, clamped to
+/// `[chunk LOD, world_lod]`. Adjacent chunks (sharing a face, edge or
+/// corner) are then refined until their LODs differ by at most one, which
+/// keeps seams crack-free with simple stitching.
+///
+/// Chunks are given at their chunk LOD, and the viewpoint in cell
+/// coordinates at `world_lod`. Assignments come back in the order of
+/// `chunks`.
+pub fn lod_selection(
+    viewpoint: &Viewpoint,
+    screen_error_budget: f64,
+    chunks: &[Index64],
+    world_lod: u8,
+) -> Vec<LodAssignment> {
+    let budget = screen_error_budget.max(1e-9);
+    let mut assignments: Vec<LodAssignment> = chunks
+        .iter()
+        .map(|&chunk| {
+            let min_lod = chunk.lod().min(world_lod);
+            let distance = box_distance(chunk, world_lod, viewpoint.position);
+            // Finest cells project to `fine` pixels; each coarser LOD doubles it
+            let fine = viewpoint.pixels_per_cell(distance);
+            let levels = (budget / fine).log2().max(0.0);
+            let coarsening = levels.floor().min((world_lod - min_lod) as f64);
+            let lod = world_lod - coarsening as u8;
+            let blend = if lod == min_lod {
+                1.0
+            } else {
+                levels - coarsening
+            };
+            LodAssignment {
+                chunk,
+                lod,
+                distance,
+                projected_error: fine * (1u64 << (world_lod - lod)) as f64,
+                blend: blend.clamp(0.0, 1.0),
+            }
+        })
+        .collect();
+
+    restrict_transitions(&mut assignments, world_lod, viewpoint);
+    assignments
+}
+
+/// Refine chunks until adjacent LODs differ by at most one
+fn restrict_transitions(assignments: &mut [LodAssignment], world_lod: u8, viewpoint: &Viewpoint) {
+    let slot: HashMap<Index64, usize> = assignments
+        .iter()
+        .enumerate()
+        .map(|(i, a)| (a.chunk, i))
+        .collect();
+    let mut work: Vec<usize> = (0..assignments.len()).collect();
+    while let Some(i) = work.pop() {
+        let chunk = assignments[i].chunk;
+        let (x, y, z) = chunk.decode_coords();
+        for dz in -1i32..=1 {
+            for dy in -1i32..=1 {
+                for dx in -1i32..=1 {
+                    let coord = |c: u16, d: i32| u16::try_from(c as i32 + d).ok();
+                    let (Some(nx), Some(ny), Some(nz)) = (coord(x, dx), coord(y, dy), coord(z, dz))
+                    else {
+                        continue;
+                    };
+                    let Ok(neighbor) = Index64::new(
+                        chunk.frame_id(),
+                        chunk.scale_tier(),
+                        chunk.lod(),
+                        nx,
+                        ny,
+                        nz,
+                    ) else {
+                        continue;
+                    };
+                    let Some(&j) = slot.get(&neighbor) else {
+                        continue;
+                    };
+                    let needed = assignments[i].lod.saturating_sub(1);
+                    if assignments[j].lod < needed {
+                        let a = &mut assignments[j];
+                        a.lod = needed;
+                        a.projected_error = viewpoint.pixels_per_cell(a.distance)
+                            * (1u64 << (world_lod - needed)) as f64;
+                        a.blend = 0.0;
+                        work.push(j);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Distance from a point to the box of a chunk, in world cells
+fn box_distance(chunk: Index64, world_lod: u8, p: (f64, f64, f64)) -> f64 {
+    let size = (1u64 << world_lod.saturating_sub(chunk.lod())) as f64;
+    let (x, y, z) = chunk.decode_coords();
+    let axis = |c: u16, v: f64| {
+        let lo = c as f64 * size;
+        (lo - v).max(v - (lo + size)).max(0.0)
+    };
+    let (dx, dy, dz) = (axis(x, p.0), axis(y, p.1), axis(z, p.2));
+    (dx * dx + dy * dy + dz * dz).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(x: u16) -> Index64 {
+        Index64::new(0, 0, 2, x, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_lod_falls_off_with_distance() {
+        // 90° FOV over 1000 px: one world cell at distance d spans 500 / d px
+        let view = Viewpoint::new((8.0, 8.0, 8.0), std::f64::consts::FRAC_PI_2, 1000.0);
+        let chunks: Vec<Index64> = (0..64).map(chunk).collect();
+        let result = lod_selection(&view, 4.0, &chunks, 10);
+
+        assert_eq!(result[0].lod, 10); // Viewpoint inside the chunk
+        assert!(result.windows(2).all(|w| w[0].lod >= w[1].lod));
+        assert!(result.last().unwrap().lod < 10);
+        for a in &result {
+            assert!((2..=10).contains(&a.lod));
+            assert!(a.projected_error <= 4.0 || a.lod == 10);
+            assert!((0.0..=1.0).contains(&a.blend));
+        }
+
+        // Distance 8 * 256 - 8 = 2040: 500 / 2040 px per cell, so 2^4 cells fit 4 px
+        let far = result.iter().find(|a| a.chunk == chunk(8)).unwrap();
+        assert!((far.distance - 2040.0).abs() < 1e-9);
+        assert_eq!(far.lod, 6);
+    }
+
+    #[test]
+    fn test_adjacent_chunks_differ_by_at_most_one() {
+        let view = Viewpoint::new((8.0, 8.0, 8.0), std::f64::consts::FRAC_PI_2, 1000.0);
+        // A gap in the row: chunk 1 is missing, so 0 and 2 are not adjacent
+        let chunks = vec![chunk(0), chunk(2), chunk(3), chunk(40)];
+        let loose = lod_selection(&view, 64.0, &chunks, 12);
+        assert!(loose[1].lod + 1 < loose[0].lod);
+        assert!(loose[2].lod + 1 >= loose[1].lod);
+
+        let row: Vec<Index64> = (0..40).map(chunk).collect();
+        let result = lod_selection(&view, 64.0, &row, 12);
+        for w in result.windows(2) {
+            assert!(w[0].lod.abs_diff(w[1].lod) <= 1);
+        }
+    }
+}
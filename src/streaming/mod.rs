@@ -0,0 +1,27 @@
+//! Streaming of large worlds around a moving viewpoint
+//!
+//! Building blocks for game engines and large-world viewers that cannot
+//! hold a whole map in memory:
+//!
+//! - [`ChunkManager`]: keeps the chunks around a viewpoint resident, loading
+//!   them on background threads and reporting arrivals and evictions
+//!   through callbacks
+//! - [`lod_selection`]: picks the rendering LOD of each chunk from its
+//!   projected cell size and a screen-space error budget
+//! - [`LiveStream`]: turns a layer's changed chunks into compact binary
+//!   frames for live viewers, served over WebSocket by `LiveServer` with
+//!   the `websocket` feature
+//!
+//! Chunks are addressed by [`Index64`](crate::Index64) cells at a coarse
+//! chunk LOD; the viewpoint is given in cell coordinates of a finer world
+//! LOD, following the hierarchy of [`Index64::parent`](crate::Index64::parent).
+
+mod chunks;
+mod live;
+mod lod;
+
+pub use chunks::{ChunkLoader, ChunkManager, ChunkUpdate, StreamingConfig};
+#[cfg(feature = "websocket")]
+pub use live::LiveServer;
+pub use live::{ChunkDelta, LiveFrame, LiveFrameKind, LiveStream, LIVE_FRAME_TAG};
+pub use lod::{lod_selection, LodAssignment, Viewpoint};
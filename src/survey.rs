@@ -0,0 +1,379 @@
+//! Cut and fill volumes between surveys
+//!
+//! Mining and construction sites are surveyed repeatedly to track
+//! stockpiles and earthworks. [`volume_change`] compares two elevation
+//! models of the same site, given as [`TerrainLayer`]s, over a polygonal
+//! region: it samples both on a regular grid, sums the volume where the
+//! ground rose (fill) and where it fell (cut), and propagates the vertical
+//! accuracy of the surveys into a standard deviation of the net volume.
+//! The result can be written as CSV or, with the `serde` feature, as a
+//! GeoJSON report.
+//!
+//! Coordinates and elevations are in the planar frame of the terrain
+//! layers (typically meters in a projected CRS such as UTM).
+//!
+//! # Example
+//!
+//! ```
+//! use octaindex3d::layers::TerrainLayer;
+//! use octaindex3d::survey::volume_change;
+//!
+//! # fn main() -> octaindex3d::Result<()> {
+//! let before = TerrainLayer::new((0.0, 0.0), 1.0, 11, 11, vec![100.0; 121])?;
+//! let after = TerrainLayer::new((0.0, 0.0), 1.0, 11, 11, vec![102.0; 121])?;
+//! let pad = [(2.0, 2.0), (8.0, 2.0), (8.0, 8.0), (2.0, 8.0)];
+//!
+//! let change = volume_change(&before, &after, &pad)?;
+//! assert!((change.fill - 72.0).abs() < 1e-9); // 6 m x 6 m raised by 2 m
+//! assert_eq!(change.cut, 0.0);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{Error, Result};
+use crate::layers::TerrainLayer;
+use std::fmt::Write;
+
+/// Settings for [`volume_change_with`]
+#[derive(Debug, Clone)]
+pub struct SurveyConfig {
+    /// Sample spacing; defaults to the finer resolution of the two surveys
+    pub spacing: Option<f64>,
+    /// Random vertical error (1σ) of the earlier survey (default: 0.05)
+    pub sigma_before: f64,
+    /// Random vertical error (1σ) of the later survey (default: 0.05)
+    pub sigma_after: f64,
+    /// Systematic vertical offset (1σ) between the surveys, e.g. from
+    /// georeferencing (default: 0)
+    pub sigma_systematic: f64,
+    /// Level of detection: elevation changes smaller than this count as
+    /// neither cut nor fill (default: 0)
+    pub min_change: f64,
+}
+
+impl Default for SurveyConfig {
+    fn default() -> Self {
+        Self {
+            spacing: None,
+            sigma_before: 0.05,
+            sigma_after: 0.05,
+            sigma_systematic: 0.0,
+            min_change: 0.0,
+        }
+    }
+}
+
+/// Elevations of both surveys at one sample point
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChangeSample {
+    /// Easting of the sample
+    pub x: f64,
+    /// Northing of the sample
+    pub y: f64,
+    /// Elevation in the earlier survey
+    pub before: f64,
+    /// Elevation in the later survey
+    pub after: f64,
+}
+
+impl ChangeSample {
+    /// Elevation change, positive where material was added
+    pub fn change(&self) -> f64 {
+        self.after - self.before
+    }
+}
+
+/// Result of [`volume_change`]
+#[derive(Debug, Clone)]
+pub struct VolumeChange {
+    /// Volume of material removed
+    pub cut: f64,
+    /// Volume of material added
+    pub fill: f64,
+    /// Standard deviation of the net volume
+    pub sigma: f64,
+    /// Area compared, covered by both surveys
+    pub area: f64,
+    /// Part of `area` where material was removed
+    pub cut_area: f64,
+    /// Part of `area` where material was added
+    pub fill_area: f64,
+    /// Area of the region sampled by at most one survey
+    pub missing_area: f64,
+    /// Ground area each sample stands for
+    pub cell_area: f64,
+    /// Samples covered by both surveys
+    pub samples: Vec<ChangeSample>,
+}
+
+impl VolumeChange {
+    /// Net volume change, fill minus cut
+    pub fn net(&self) -> f64 {
+        self.fill - self.cut
+    }
+
+    /// Samples as CSV with columns `x,y,before,after,change`
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("x,y,before,after,change\n");
+        for s in &self.samples {
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{}",
+                s.x,
+                s.y,
+                s.before,
+                s.after,
+                s.change()
+            );
+        }
+        csv
+    }
+}
+
+/// Cut and fill volumes between two surveys inside a polygon
+///
+/// `region` lists the polygon's vertices in order, without repeating the
+/// first one. Uses default [`SurveyConfig`] settings.
+pub fn volume_change(
+    before: &TerrainLayer,
+    after: &TerrainLayer,
+    region: &[(f64, f64)],
+) -> Result<VolumeChange> {
+    volume_change_with(before, after, region, &SurveyConfig::default())
+}
+
+/// [`volume_change`] with explicit settings
+///
+/// Samples are taken at the centers of a grid with the configured spacing
+/// and each stands for one grid cell. The net volume's standard deviation
+/// combines the random errors of both surveys, which average out over `n`
+/// samples (`cell_area × √n × √(σ_before² + σ_after²)`), with the
+/// systematic offset, which does not (`area × σ_systematic`).
+pub fn volume_change_with(
+    before: &TerrainLayer,
+    after: &TerrainLayer,
+    region: &[(f64, f64)],
+    config: &SurveyConfig,
+) -> Result<VolumeChange> {
+    if region.len() < 3 {
+        return Err(Error::InvalidFormat(format!(
+            "survey region needs at least 3 vertices, got {}",
+            region.len()
+        )));
+    }
+    let spacing = config
+        .spacing
+        .unwrap_or_else(|| before.resolution().min(after.resolution()));
+    if !(spacing.is_finite() && spacing > 0.0) {
+        return Err(Error::InvalidFormat(format!(
+            "survey sample spacing must be positive, got {}",
+            spacing
+        )));
+    }
+
+    let (min_x, max_x, min_y, max_y) = region.iter().fold(
+        (
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+        ),
+        |(x0, x1, y0, y1), &(x, y)| (x0.min(x), x1.max(x), y0.min(y), y1.max(y)),
+    );
+    let cols = ((max_x - min_x) / spacing).ceil() as usize;
+    let rows = ((max_y - min_y) / spacing).ceil() as usize;
+    let cell_area = spacing * spacing;
+
+    let mut samples = Vec::new();
+    let mut missing = 0usize;
+    let (mut cut, mut fill) = (0.0, 0.0);
+    let (mut cut_cells, mut fill_cells) = (0usize, 0usize);
+    for row in 0..rows {
+        let y = min_y + (row as f64 + 0.5) * spacing;
+        for col in 0..cols {
+            let x = min_x + (col as f64 + 0.5) * spacing;
+            if !contains(region, x, y) {
+                continue;
+            }
+            let (Some(z0), Some(z1)) = (before.elevation_at(x, y), after.elevation_at(x, y)) else {
+                missing += 1;
+                continue;
+            };
+            let sample = ChangeSample {
+                x,
+                y,
+                before: z0,
+                after: z1,
+            };
+            let dz = sample.change();
+            if dz.abs() > config.min_change {
+                if dz > 0.0 {
+                    fill += dz * cell_area;
+                    fill_cells += 1;
+                } else {
+                    cut -= dz * cell_area;
+                    cut_cells += 1;
+                }
+            }
+            samples.push(sample);
+        }
+    }
+
+    let n = samples.len() as f64;
+    let area = n * cell_area;
+    let random =
+        cell_area * n.sqrt() * (config.sigma_before.powi(2) + config.sigma_after.powi(2)).sqrt();
+    let systematic = area * config.sigma_systematic;
+    Ok(VolumeChange {
+        cut,
+        fill,
+        sigma: (random * random + systematic * systematic).sqrt(),
+        area,
+        cut_area: cut_cells as f64 * cell_area,
+        fill_area: fill_cells as f64 * cell_area,
+        missing_area: missing as f64 * cell_area,
+        cell_area,
+        samples,
+    })
+}
+
+/// Even-odd point-in-polygon test
+fn contains(polygon: &[(f64, f64)], x: f64, y: f64) -> bool {
+    let mut inside = false;
+    let mut j = polygon.len() - 1;
+    for (i, &(xi, yi)) in polygon.iter().enumerate() {
+        let (xj, yj) = polygon[j];
+        if (yi > y) != (yj > y) && x < xi + (y - yi) / (yj - yi) * (xj - xi) {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+#[cfg(feature = "serde")]
+mod report {
+    //! GeoJSON survey report
+
+    use super::*;
+    use serde_json::{json, Value};
+
+    impl VolumeChange {
+        /// GeoJSON report: the region with the volume summary, then one
+        /// point per sample whose change passes the level of detection
+        ///
+        /// Coordinates are written in the terrain layers' frame; readers
+        /// expecting WGS84 need them reprojected.
+        pub fn to_geojson(&self, region: &[(f64, f64)], min_change: f64) -> Result<String> {
+            let mut ring: Vec<Value> = region.iter().map(|&(x, y)| json!([x, y])).collect();
+            if let Some(&(x, y)) = region.first() {
+                ring.push(json!([x, y]));
+            }
+            let mut features = vec![json!({
+                "type": "Feature",
+                "geometry": { "type": "Polygon", "coordinates": [ring] },
+                "properties": {
+                    "cut": self.cut,
+                    "fill": self.fill,
+                    "net": self.net(),
+                    "sigma": self.sigma,
+                    "area": self.area,
+                    "cut_area": self.cut_area,
+                    "fill_area": self.fill_area,
+                    "missing_area": self.missing_area,
+                },
+            })];
+            features.extend(
+                self.samples
+                    .iter()
+                    .filter(|s| s.change().abs() > min_change)
+                    .map(|s| {
+                        json!({
+                            "type": "Feature",
+                            "geometry": { "type": "Point", "coordinates": [s.x, s.y] },
+                            "properties": { "before": s.before, "after": s.after, "change": s.change() },
+                        })
+                    }),
+            );
+            let collection = json!({ "type": "FeatureCollection", "features": features });
+            serde_json::to_string_pretty(&collection).map_err(|e| Error::Codec(e.to_string()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 11 x 11 posts at 1 m spacing with elevations from `f(x, y)`
+    fn terrain(f: impl Fn(f64, f64) -> f32) -> TerrainLayer {
+        let heights = (0..121)
+            .map(|i| f((i % 11) as f64, (i / 11) as f64))
+            .collect();
+        TerrainLayer::new((0.0, 0.0), 1.0, 11, 11, heights).unwrap()
+    }
+
+    #[test]
+    fn test_cut_fill_and_uncertainty() {
+        let before = terrain(|_, _| 10.0);
+        // Excavated west of x = 5, filled east of it
+        let after = terrain(|x, _| 10.0 + (x - 5.0) as f32);
+        let region = [(1.0, 1.0), (9.0, 1.0), (9.0, 9.0), (1.0, 9.0)];
+
+        let change = volume_change(&before, &after, &region).unwrap();
+        assert!((change.fill - 64.0).abs() < 1e-9);
+        assert!((change.cut - 64.0).abs() < 1e-9);
+        assert!(change.net().abs() < 1e-9);
+        assert_eq!(change.area, 64.0);
+        assert_eq!(change.fill_area, 32.0);
+        let expected = 8.0 * (0.05f64.powi(2) * 2.0).sqrt();
+        assert!((change.sigma - expected).abs() < 1e-12);
+
+        // Changes under the level of detection are ignored
+        let config = SurveyConfig {
+            min_change: 2.0,
+            sigma_systematic: 0.1,
+            ..Default::default()
+        };
+        let filtered = volume_change_with(&before, &after, &region, &config).unwrap();
+        assert!((filtered.fill - 2.0 * 8.0 * 3.0).abs() < 1e-9);
+        assert!(filtered.sigma > 64.0 * 0.1);
+
+        let csv = change.to_csv();
+        assert_eq!(csv.lines().count(), 65);
+        assert!(csv.starts_with("x,y,before,after,change\n1.5,1.5,10,6.5,-3.5\n"));
+    }
+
+    #[test]
+    fn test_polygon_region_and_missing_data() {
+        let before = terrain(|_, _| 0.0);
+        let after = terrain(|x, _| if x > 8.0 { f32::NAN } else { 1.0 });
+        let triangle = [(0.0, 0.0), (10.0, 0.0), (0.0, 10.0)];
+
+        let change = volume_change(&before, &after, &triangle).unwrap();
+        // Half of the 10 x 10 square, part of it beyond the data
+        assert!((change.area + change.missing_area - 50.0).abs() < 6.0);
+        assert!(change.missing_area > 0.0);
+        assert!((change.fill - change.area).abs() < 1e-9);
+        assert!(volume_change(&before, &after, &triangle[..2]).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_geojson_report() {
+        let before = terrain(|_, _| 0.0);
+        let after = terrain(|x, _| if x < 3.0 { 1.0 } else { 0.0 });
+        let region = [(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)];
+        let change = volume_change(&before, &after, &region).unwrap();
+
+        let report: serde_json::Value =
+            serde_json::from_str(&change.to_geojson(&region, 0.5).unwrap()).unwrap();
+        let features = report["features"].as_array().unwrap();
+        assert_eq!(features[0]["properties"]["fill"], change.fill);
+        assert_eq!(features[0]["geometry"]["coordinates"][0][4][0], 0.0);
+        assert_eq!(
+            features.len() - 1,
+            change.samples.iter().filter(|s| s.change() > 0.5).count()
+        );
+    }
+}
@@ -0,0 +1,44 @@
+//! Seeded generators for synthetic test scenes
+//!
+//! Procedural content for demos, benchmarks and simulations. Every generator
+//! takes an explicit seed and produces the same output on every platform,
+//! independent of external random number crates.
+//!
+//! - [`space`]: star fields, nebula density and asteroid belts at
+//!   [`Galactic128`](crate::Galactic128) scales
+
+pub mod space;
+
+/// SplitMix64 generator: tiny, fast and stable across releases
+#[derive(Debug, Clone)]
+pub(crate) struct SeededRng {
+    state: u64,
+}
+
+impl SeededRng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        mix64(self.state)
+    }
+
+    /// Uniform sample in `[0, 1)`
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform sample in `[lo, hi)`
+    pub(crate) fn range(&mut self, lo: f64, hi: f64) -> f64 {
+        lo + (hi - lo) * self.next_f64()
+    }
+}
+
+/// SplitMix64 output mixer, also used as a stateless hash
+pub(crate) fn mix64(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
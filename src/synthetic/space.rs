@@ -0,0 +1,501 @@
+//! Procedural space scenes: star fields, nebulae and asteroid belts
+//!
+//! All generators work in [`Galactic128`] lattice coordinates and snap
+//! positions to valid BCC points, so their output can be indexed, stored in
+//! containers or converted to an [`OccupancyLayer`] for path planning demos.
+//!
+//! # Example
+//!
+//! ```
+//! use octaindex3d::synthetic::space::{AsteroidBelt, StarField};
+//!
+//! # fn main() -> octaindex3d::Result<()> {
+//! let stars = StarField {
+//!     count: 500,
+//!     seed: 42,
+//!     ..Default::default()
+//! }
+//! .generate()?;
+//! assert_eq!(stars.len(), 500);
+//!
+//! let belt = AsteroidBelt {
+//!     count: 50,
+//!     ..Default::default()
+//! }
+//! .generate()?;
+//! assert!(!belt.occupied_cells()?.is_empty());
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{mix64, SeededRng};
+use crate::error::Result;
+use crate::ids::{FrameId, Galactic128, Index64};
+use crate::layers::OccupancyLayer;
+
+/// Frame, scale and LOD stamped on generated identifiers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SpaceScale {
+    /// Coordinate frame
+    pub frame: FrameId,
+    /// Scale mantissa
+    pub scale_mant: u8,
+    /// Scale tier (0-3)
+    pub scale_tier: u8,
+    /// Level of detail
+    pub lod: u8,
+}
+
+impl SpaceScale {
+    /// Identifier of the BCC lattice point nearest to `(x, y, z)`
+    pub fn id_near(&self, x: f64, y: f64, z: f64) -> Result<Galactic128> {
+        let round = |v: f64| v.round().clamp(i32::MIN as f64, i32::MAX as f64) as i32;
+        let (x, y, z) = (round(x), round(y), round(z));
+        // Match the parity of x; flipping the low bit moves by one unit
+        let snap = |v: i32| if (v ^ x) & 1 == 0 { v } else { v ^ 1 };
+        Galactic128::new(
+            self.frame,
+            self.scale_mant,
+            self.scale_tier,
+            self.lod,
+            0,
+            x,
+            snap(y),
+            snap(z),
+        )
+    }
+}
+
+/// A generated star
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Star {
+    /// Position
+    pub id: Galactic128,
+    /// Apparent magnitude (smaller is brighter)
+    pub magnitude: f32,
+}
+
+/// Uniform star field in a sphere, with realistic magnitude counts
+#[derive(Debug, Clone, PartialEq)]
+pub struct StarField {
+    /// Number of stars
+    pub count: usize,
+    /// Center of the field (lattice units)
+    pub center: [f64; 3],
+    /// Radius of the field (lattice units)
+    pub radius: f64,
+    /// Brightest magnitude generated
+    pub min_magnitude: f32,
+    /// Faintest magnitude generated
+    pub max_magnitude: f32,
+    /// Identifier scale
+    pub scale: SpaceScale,
+    /// Random seed
+    pub seed: u64,
+}
+
+impl Default for StarField {
+    fn default() -> Self {
+        Self {
+            count: 1000,
+            center: [0.0; 3],
+            radius: 1_000_000.0,
+            min_magnitude: -1.0,
+            max_magnitude: 12.0,
+            scale: SpaceScale::default(),
+            seed: 0,
+        }
+    }
+}
+
+impl StarField {
+    /// Generate the stars
+    ///
+    /// Magnitudes follow the `N(< m) ∝ 10^(0.6 m)` law of a uniform star
+    /// density, so faint stars vastly outnumber bright ones.
+    pub fn generate(&self) -> Result<Vec<Star>> {
+        let mut rng = SeededRng::new(self.seed);
+        let span = (self.max_magnitude - self.min_magnitude).max(0.0) as f64;
+        (0..self.count)
+            .map(|_| {
+                let [x, y, z] = point_in_ball(&mut rng, self.center, self.radius);
+                let u = rng.next_f64().max(f64::MIN_POSITIVE);
+                let magnitude = self.max_magnitude as f64 + (u.log10() / 0.6).max(-span);
+                Ok(Star {
+                    id: self.scale.id_near(x, y, z)?,
+                    magnitude: magnitude as f32,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Spherical nebula with fractal density
+#[derive(Debug, Clone, PartialEq)]
+pub struct Nebula {
+    /// Center (lattice units)
+    pub center: [f64; 3],
+    /// Outer radius (lattice units)
+    pub radius: f64,
+    /// Distance between samples (lattice units, rounded to at least 1)
+    pub spacing: f64,
+    /// Size of the largest density features (lattice units)
+    pub feature_size: f64,
+    /// Number of noise octaves
+    pub octaves: u32,
+    /// Identifier scale
+    pub scale: SpaceScale,
+    /// Random seed
+    pub seed: u64,
+}
+
+impl Default for Nebula {
+    fn default() -> Self {
+        Self {
+            center: [0.0; 3],
+            radius: 64.0,
+            spacing: 4.0,
+            feature_size: 32.0,
+            octaves: 4,
+            scale: SpaceScale::default(),
+            seed: 0,
+        }
+    }
+}
+
+impl Nebula {
+    /// Density in `[0, 1]` at a point
+    ///
+    /// Fractal value noise shaped by a smooth radial falloff; zero outside
+    /// the radius.
+    pub fn density_at(&self, p: [f64; 3]) -> f32 {
+        let r2: f64 = (0..3).map(|i| (p[i] - self.center[i]).powi(2)).sum();
+        let t = r2.sqrt() / self.radius;
+        if t.is_nan() || t >= 1.0 {
+            return 0.0;
+        }
+        let falloff = (1.0 - t * t).powi(2);
+
+        let mut sum = 0.0;
+        let mut norm = 0.0;
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0 / self.feature_size.max(1e-9);
+        for octave in 0..self.octaves.max(1) {
+            let seed = mix64(self.seed ^ octave as u64);
+            sum +=
+                amplitude * value_noise(seed, p[0] * frequency, p[1] * frequency, p[2] * frequency);
+            norm += amplitude;
+            amplitude *= 0.5;
+            frequency *= 2.0;
+        }
+        (falloff * sum / norm) as f32
+    }
+
+    /// Sample the density on a BCC lattice of the given spacing
+    ///
+    /// Returns `(cell, density)` for every sample with non-zero density.
+    pub fn generate(&self) -> Result<Vec<(Galactic128, f32)>> {
+        let step = self.spacing.round().max(1.0);
+        let n = (self.radius / (2.0 * step)).ceil() as i64;
+        let mut samples = Vec::new();
+        for offset in [0.0, step] {
+            for i in -n..=n {
+                for j in -n..=n {
+                    for k in -n..=n {
+                        let p = [
+                            self.center[0] + 2.0 * step * i as f64 + offset,
+                            self.center[1] + 2.0 * step * j as f64 + offset,
+                            self.center[2] + 2.0 * step * k as f64 + offset,
+                        ];
+                        let density = self.density_at(p);
+                        if density > 0.0 {
+                            samples.push((self.scale.id_near(p[0], p[1], p[2])?, density));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(samples)
+    }
+}
+
+/// A generated asteroid
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Asteroid {
+    /// Center
+    pub id: Galactic128,
+    /// Radius (lattice units)
+    pub radius: f64,
+}
+
+/// Ring of asteroids around a center, in the XY plane
+#[derive(Debug, Clone, PartialEq)]
+pub struct AsteroidBelt {
+    /// Number of asteroids
+    pub count: usize,
+    /// Center of the belt (lattice units)
+    pub center: [f64; 3],
+    /// Inner radius of the ring
+    pub inner_radius: f64,
+    /// Outer radius of the ring
+    pub outer_radius: f64,
+    /// Full vertical thickness of the ring
+    pub thickness: f64,
+    /// Smallest asteroid radius
+    pub min_size: f64,
+    /// Largest asteroid radius
+    pub max_size: f64,
+    /// Identifier scale
+    pub scale: SpaceScale,
+    /// Random seed
+    pub seed: u64,
+}
+
+impl Default for AsteroidBelt {
+    fn default() -> Self {
+        Self {
+            count: 200,
+            center: [0.0; 3],
+            inner_radius: 400.0,
+            outer_radius: 600.0,
+            thickness: 40.0,
+            min_size: 1.0,
+            max_size: 8.0,
+            scale: SpaceScale::default(),
+            seed: 0,
+        }
+    }
+}
+
+impl AsteroidBelt {
+    /// Generate the asteroids
+    ///
+    /// Sizes follow a power law (`N(> r) ∝ r^-2`), so most asteroids are
+    /// small. Positions are uniform over the ring area.
+    pub fn generate(&self) -> Result<AsteroidField> {
+        let mut rng = SeededRng::new(self.seed);
+        let (r0, r1) = (self.inner_radius.max(0.0), self.outer_radius.max(0.0));
+        let asteroids = (0..self.count)
+            .map(|_| {
+                let angle = rng.range(0.0, std::f64::consts::TAU);
+                let radius = (rng.range(r0 * r0, r1 * r1)).sqrt();
+                let height = (rng.next_f64() - rng.next_f64()) * 0.5 * self.thickness;
+                // Inverse-CDF sampling of the truncated power law
+                let (a, b) = (self.min_size.max(1e-9).powi(-2), self.max_size.powi(-2));
+                let size = (a + (b - a) * rng.next_f64()).powf(-0.5);
+                Ok(Asteroid {
+                    id: self.scale.id_near(
+                        self.center[0] + radius * angle.cos(),
+                        self.center[1] + radius * angle.sin(),
+                        self.center[2] + height,
+                    )?,
+                    radius: size,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(AsteroidField { asteroids })
+    }
+}
+
+/// Asteroids produced by [`AsteroidBelt::generate`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AsteroidField {
+    /// Generated asteroids
+    pub asteroids: Vec<Asteroid>,
+}
+
+impl AsteroidField {
+    /// BCC lattice points inside any asteroid, sorted and deduplicated
+    ///
+    /// Each asteroid contributes about `2.1 r³` cells, so keep sizes small
+    /// relative to the lattice unit.
+    pub fn occupied_cells(&self) -> Result<Vec<Galactic128>> {
+        let mut cells = Vec::new();
+        for asteroid in &self.asteroids {
+            let c = asteroid.id;
+            let (cx, cy, cz) = (c.x() as i64, c.y() as i64, c.z() as i64);
+            let r = asteroid.radius;
+            let n = r.floor() as i64;
+            for dx in -n..=n {
+                for dy in -n..=n {
+                    for dz in -n..=n {
+                        // Offsets with matching parity keep the point on the lattice
+                        if (dx ^ dy) & 1 != 0 || (dx ^ dz) & 1 != 0 {
+                            continue;
+                        }
+                        if ((dx * dx + dy * dy + dz * dz) as f64) > r * r {
+                            continue;
+                        }
+                        let coord = |base: i64, d: i64| i32::try_from(base + d).ok();
+                        let (Some(x), Some(y), Some(z)) =
+                            (coord(cx, dx), coord(cy, dy), coord(cz, dz))
+                        else {
+                            continue;
+                        };
+                        cells.push(Galactic128::new(
+                            c.frame_id(),
+                            c.scale_mant(),
+                            c.scale_tier(),
+                            c.lod(),
+                            0,
+                            x,
+                            y,
+                            z,
+                        )?);
+                    }
+                }
+            }
+        }
+        cells.sort_unstable_by_key(|c| c.raw());
+        cells.dedup();
+        Ok(cells)
+    }
+
+    /// Rasterize into an occupancy layer for local planning
+    ///
+    /// Cells are translated by `-origin` into the unsigned voxel space of
+    /// [`Index64`] at LOD `lod`; cells falling outside it are dropped.
+    pub fn to_occupancy(&self, origin: (i32, i32, i32), lod: u8) -> Result<OccupancyLayer> {
+        let mut layer = OccupancyLayer::new();
+        for cell in self.occupied_cells()? {
+            let local = |v: i32, o: i32| u16::try_from(v as i64 - o as i64).ok();
+            let (Some(x), Some(y), Some(z)) = (
+                local(cell.x(), origin.0),
+                local(cell.y(), origin.1),
+                local(cell.z(), origin.2),
+            ) else {
+                continue;
+            };
+            let idx = Index64::new(cell.frame_id(), cell.scale_tier(), lod, x, y, z)?;
+            layer.update_occupancy(idx, true, 0.97);
+        }
+        Ok(layer)
+    }
+}
+
+/// Uniform point in a ball
+fn point_in_ball(rng: &mut SeededRng, center: [f64; 3], radius: f64) -> [f64; 3] {
+    loop {
+        let p = [
+            rng.range(-1.0, 1.0),
+            rng.range(-1.0, 1.0),
+            rng.range(-1.0, 1.0),
+        ];
+        if p[0] * p[0] + p[1] * p[1] + p[2] * p[2] <= 1.0 {
+            return [
+                center[0] + radius * p[0],
+                center[1] + radius * p[1],
+                center[2] + radius * p[2],
+            ];
+        }
+    }
+}
+
+/// Smooth value noise in `[0, 1]`
+fn value_noise(seed: u64, x: f64, y: f64, z: f64) -> f64 {
+    let (x0, y0, z0) = (x.floor(), y.floor(), z.floor());
+    let fade = |t: f64| t * t * (3.0 - 2.0 * t);
+    let (tx, ty, tz) = (fade(x - x0), fade(y - y0), fade(z - z0));
+    let lattice = |i: i64, j: i64, k: i64| {
+        let h = mix64(
+            seed ^ (i as u64).wrapping_mul(0x8DA6_B343)
+                ^ (j as u64).wrapping_mul(0xD816_3841)
+                ^ (k as u64).wrapping_mul(0xCB1A_B31F),
+        );
+        (h >> 11) as f64 / (1u64 << 53) as f64
+    };
+    let (i, j, k) = (x0 as i64, y0 as i64, z0 as i64);
+    let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+    let plane = |k: i64| {
+        lerp(
+            lerp(lattice(i, j, k), lattice(i + 1, j, k), tx),
+            lerp(lattice(i, j + 1, k), lattice(i + 1, j + 1, k), tx),
+            ty,
+        )
+    };
+    lerp(plane(k), plane(k + 1), tz)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_star_field_is_seeded() -> Result<()> {
+        let field = StarField {
+            count: 2000,
+            radius: 1000.0,
+            seed: 7,
+            ..Default::default()
+        };
+        let stars = field.generate()?;
+        assert_eq!(stars, field.generate()?);
+        assert_ne!(
+            stars,
+            StarField {
+                seed: 8,
+                ..field.clone()
+            }
+            .generate()?
+        );
+
+        for star in &stars {
+            let r2 = [star.id.x(), star.id.y(), star.id.z()]
+                .iter()
+                .map(|&v| (v as f64).powi(2))
+                .sum::<f64>();
+            assert!(r2.sqrt() <= 1002.0);
+            assert!((field.min_magnitude..=field.max_magnitude).contains(&star.magnitude));
+        }
+        // Faint stars dominate
+        let faint = stars.iter().filter(|s| s.magnitude > 10.0).count();
+        assert!(faint > stars.len() / 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_nebula_density() -> Result<()> {
+        let nebula = Nebula {
+            radius: 20.0,
+            spacing: 2.0,
+            seed: 3,
+            ..Default::default()
+        };
+        let samples = nebula.generate()?;
+        assert!(!samples.is_empty());
+        assert!(samples.iter().all(|&(_, d)| d > 0.0 && d <= 1.0));
+        assert_eq!(nebula.density_at([25.0, 0.0, 0.0]), 0.0);
+        assert_eq!(samples, nebula.generate()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_asteroid_belt_occupancy() -> Result<()> {
+        let belt = AsteroidBelt {
+            count: 30,
+            center: [1000.0, 1000.0, 1000.0],
+            inner_radius: 100.0,
+            outer_radius: 150.0,
+            thickness: 10.0,
+            min_size: 1.0,
+            max_size: 4.0,
+            seed: 11,
+            ..Default::default()
+        };
+        let field = belt.generate()?;
+        assert_eq!(field.asteroids.len(), 30);
+
+        let cells = field.occupied_cells()?;
+        for cell in &cells {
+            let dx = cell.x() as f64 - 1000.0;
+            let dy = cell.y() as f64 - 1000.0;
+            let ring = (dx * dx + dy * dy).sqrt();
+            assert!(ring > 100.0 - 6.0 && ring < 150.0 + 6.0);
+            assert!((cell.z() as f64 - 1000.0).abs() <= 5.0 + 6.0);
+        }
+
+        let layer = field.to_occupancy((800, 800, 800), 5)?;
+        assert_eq!(layer.get_occupied_voxels().len(), cells.len());
+        Ok(())
+    }
+}
@@ -0,0 +1,34 @@
+//! Fixtures shared by unit tests of several modules
+
+use crate::error::Result;
+use crate::layers::{OccupancyLayer, ScalarLayer};
+use crate::Index64;
+
+/// Occupied wall in the plane x = 110 at LOD 5 (both BCC parities),
+/// spanning y and z from 80 to 140
+pub(crate) fn wall() -> Result<OccupancyLayer> {
+    let mut occupancy = OccupancyLayer::new();
+    for y in 80..=140u16 {
+        for z in 80..=140u16 {
+            if y % 2 == z % 2 {
+                let x = if y % 2 == 0 { 110 } else { 111 };
+                occupancy.update_occupancy(Index64::new(0, 0, 5, x, y, z)?, true, 0.95);
+            }
+        }
+    }
+    Ok(occupancy)
+}
+
+/// Cell at `x` on the x axis at LOD 5
+pub(crate) fn cell(x: u16) -> Index64 {
+    Index64::new(0, 0, 5, x, 0, 0).unwrap()
+}
+
+/// Scalar layer holding `(x, value)` pairs at [`cell`]`(x)`
+pub(crate) fn layer(values: &[(u16, f32)]) -> ScalarLayer {
+    let mut out = ScalarLayer::new();
+    for &(x, v) in values {
+        out.set(cell(x), v);
+    }
+    out
+}
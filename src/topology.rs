@@ -0,0 +1,741 @@
+//! Topological maps: rooms and corridors joined by portals
+//!
+//! [`extract_graph`] segments the free space of an indoor occupancy map
+//! into regions and links them through the passages between them, giving
+//! a sparse graph for high-level planning: route over a handful of regions
+//! first, then plan cell paths only inside the regions on that route.
+//!
+//! Free space is sliced on the floorplan grid of [`crate::analysis`]. Each
+//! passage found by [`detect_passages`](crate::analysis::detect_passages)
+//! is cut across its narrowest section, from one side's obstacle to the
+//! other's; the cut columns are the portal and the free space left between
+//! cuts falls apart into regions. Elongated regions are corridors.
+//!
+//! [`describe_route`] turns a cell path into instructions over the named
+//! regions and portals of a graph, for operator interfaces and spoken
+//! guidance.
+//!
+//! # Example
+//!
+//! ```
+//! use octaindex3d::layers::OccupancyLayer;
+//! use octaindex3d::topology::{extract_graph, TopologyConfig};
+//! use octaindex3d::Index64;
+//!
+//! # fn main() -> octaindex3d::Result<()> {
+//! // Two rooms joined by a door in the wall between them
+//! let mut occupancy = OccupancyLayer::new();
+//! for x in 100..160u16 {
+//!     for y in 100..130u16 {
+//!         for z in 100..110u16 {
+//!             if x % 2 == y % 2 && y % 2 == z % 2 {
+//!                 let outer = !(104..156).contains(&x) || !(104..126).contains(&y);
+//!                 let wall = (128..132).contains(&x) && !(110..118).contains(&y);
+//!                 let idx = Index64::new(0, 0, 8, x, y, z)?;
+//!                 occupancy.update_occupancy(idx, outer || wall, 0.9);
+//!             }
+//!         }
+//!     }
+//! }
+//!
+//! let mut config = TopologyConfig::new(100..=109);
+//! config.passages.max_width = 10.0;
+//! let graph = extract_graph(&occupancy, &config);
+//! assert_eq!(graph.regions.len(), 2);
+//! assert_eq!(graph.portals.len(), 1);
+//! assert_eq!(graph.route(0, 1), Some(vec![0, 1]));
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::analysis::{column, column_center, passage_map, Passage, PassageParams, Point};
+use crate::layers::OccupancyLayer;
+use crate::Index64;
+use ordered_float::OrderedFloat;
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::ops::RangeInclusive;
+
+/// Settings for [`extract_graph`]
+#[derive(Debug, Clone)]
+pub struct TopologyConfig {
+    /// Height band and width limits of the passages that separate regions
+    pub passages: PassageParams,
+    /// Smallest region kept, in grid columns (default: 4)
+    pub min_region_cells: usize,
+    /// Length to width ratio from which a region is a corridor
+    /// (default: 3)
+    pub corridor_aspect: f64,
+}
+
+impl TopologyConfig {
+    /// Default settings for a height band
+    pub fn new(height_band: RangeInclusive<u16>) -> Self {
+        Self {
+            passages: PassageParams::new(height_band),
+            min_region_cells: 4,
+            corridor_aspect: 3.0,
+        }
+    }
+}
+
+/// Kind of a free-space region
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionKind {
+    /// Open area
+    Room,
+    /// Elongated area
+    Corridor,
+}
+
+/// Connected free space between portals
+#[derive(Debug, Clone, PartialEq)]
+pub struct Region {
+    /// Position in [`TopologicalGraph::regions`]
+    pub id: usize,
+    /// Display name: "room 1", "corridor A", or "junction 1" for
+    /// corridors with three or more portals
+    pub name: String,
+    /// Room or corridor
+    pub kind: RegionKind,
+    /// Centroid
+    pub center: Point,
+    /// Floor area
+    pub area: f64,
+    /// Length to width ratio of the region's principal axes
+    pub aspect: f64,
+    /// Free voxels of the height band in the region, sorted
+    pub cells: Vec<Index64>,
+}
+
+/// Passage joining two regions
+#[derive(Debug, Clone, PartialEq)]
+pub struct Portal {
+    /// Display name, "door 1" for the first portal
+    pub name: String,
+    /// Regions on either side, lower id first
+    pub regions: (usize, usize),
+    /// The passage the portal cuts across
+    pub passage: Passage,
+    /// Free voxels of the height band on the cut, sorted
+    pub cells: Vec<Index64>,
+    /// Distance from one region center through the portal to the other
+    pub length: f64,
+}
+
+/// Rooms-and-corridors graph of an occupancy map
+#[derive(Debug, Clone)]
+pub struct TopologicalGraph {
+    /// Regions, ordered by their lowest grid column
+    pub regions: Vec<Region>,
+    /// Portals between regions
+    pub portals: Vec<Portal>,
+    /// Region of every region column
+    columns: FxHashMap<(i32, i32), usize>,
+    /// Factor from lattice units to output coordinates
+    scale: f64,
+    /// Name of the output unit
+    unit: Option<&'static str>,
+}
+
+impl TopologicalGraph {
+    /// Region containing a voxel, if it lies in a region's columns
+    ///
+    /// Voxels outside the height band map to the region below or above
+    /// them.
+    pub fn region_of(&self, idx: Index64) -> Option<usize> {
+        let (x, y, _) = idx.decode_coords();
+        self.columns.get(&column(x, y)).copied()
+    }
+
+    /// Indices of the portals of a region
+    pub fn portals_of(&self, region: usize) -> impl Iterator<Item = usize> + '_ {
+        self.portals
+            .iter()
+            .enumerate()
+            .filter(move |(_, p)| p.regions.0 == region || p.regions.1 == region)
+            .map(|(i, _)| i)
+    }
+
+    /// Shortest sequence of regions from `from` to `to`, weighted by
+    /// portal length
+    pub fn route(&self, from: usize, to: usize) -> Option<Vec<usize>> {
+        if from >= self.regions.len() || to >= self.regions.len() {
+            return None;
+        }
+        let mut best = vec![f64::INFINITY; self.regions.len()];
+        let mut previous = vec![usize::MAX; self.regions.len()];
+        let mut heap = BinaryHeap::new();
+        best[from] = 0.0;
+        heap.push(Reverse((OrderedFloat(0.0), from)));
+        while let Some(Reverse((OrderedFloat(cost), region))) = heap.pop() {
+            if region == to {
+                let mut route = vec![to];
+                while *route.last().unwrap() != from {
+                    route.push(previous[*route.last().unwrap()]);
+                }
+                route.reverse();
+                return Some(route);
+            }
+            if cost > best[region] {
+                continue;
+            }
+            for portal in self.portals_of(region) {
+                let portal = &self.portals[portal];
+                let next = if portal.regions.0 == region {
+                    portal.regions.1
+                } else {
+                    portal.regions.0
+                };
+                let cost = cost + portal.length;
+                if cost < best[next] {
+                    best[next] = cost;
+                    previous[next] = region;
+                    heap.push(Reverse((OrderedFloat(cost), next)));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Direction change along a route
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Turn {
+    /// Turn left, counter-clockwise seen from above
+    Left,
+    /// Turn right
+    Right,
+    /// Turn back
+    Around,
+}
+
+/// One instruction of a route description
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RouteAction {
+    /// The route begins in a region
+    Start {
+        /// Region index
+        region: usize,
+    },
+    /// Move through a region
+    Traverse {
+        /// Region index
+        region: usize,
+        /// Distance covered, in output units
+        distance: f64,
+    },
+    /// Change direction inside a region
+    Turn {
+        /// Region index
+        region: usize,
+        /// Direction of the turn
+        turn: Turn,
+    },
+    /// Move into the next region, through a portal when the graph has one
+    /// between the two
+    Enter {
+        /// Portal index
+        portal: Option<usize>,
+        /// Region index
+        region: usize,
+    },
+    /// The route ends in a region
+    Arrive {
+        /// Region index
+        region: usize,
+    },
+}
+
+/// Route instruction with its human-readable text
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteStep {
+    /// What to do
+    pub action: RouteAction,
+    /// The instruction as a sentence, e.g. "Traverse corridor B for 12 m"
+    pub text: String,
+}
+
+/// Describe a cell path as instructions over the regions of a graph
+///
+/// The path is a sequence of voxels, e.g. from a planner. Steps name the
+/// regions and portals passed, the distance covered in each region and
+/// the turns taken, where a turn is a bend of more than 30 degrees in the
+/// simplified path. Distances are in the graph's output units (see
+/// [`PassageParams::scale`]), followed by [`PassageParams::unit`] if one
+/// is set. Cells outside every
+/// region, such as those on a portal, count towards the region before
+/// them. Returns nothing if no cell of the path lies in a region.
+pub fn describe_route(graph: &TopologicalGraph, path: &[Index64]) -> Vec<RouteStep> {
+    let mut regions: Vec<Option<usize>> = Vec::with_capacity(path.len());
+    for &idx in path {
+        let previous = regions.last().copied().flatten();
+        regions.push(graph.region_of(idx).or(previous));
+    }
+    let Some(first) = regions.iter().flatten().next().copied() else {
+        return Vec::new();
+    };
+    let regions: Vec<usize> = regions.into_iter().map(|r| r.unwrap_or(first)).collect();
+    let points: Vec<Point> = path
+        .iter()
+        .map(|idx| {
+            let (x, y, _) = idx.decode_coords();
+            (x as f64 * graph.scale, y as f64 * graph.scale)
+        })
+        .collect();
+    let mut turns = turns(&points, 4.0 * graph.scale).into_iter().peekable();
+
+    let mut actions = vec![RouteAction::Start { region: first }];
+    let mut travelled = 0.0;
+    for k in 1..path.len() {
+        let (previous, region) = (regions[k - 1], regions[k]);
+        if region != previous {
+            traverse(&mut actions, previous, &mut travelled);
+            let pair = (previous.min(region), previous.max(region));
+            let candidates: Vec<usize> = (0..graph.portals.len())
+                .filter(|&p| graph.portals[p].regions == pair)
+                .collect();
+            // Prefer the portal the path actually crosses
+            let crossed = candidates.iter().copied().find(|&p| {
+                path[..=k]
+                    .iter()
+                    .any(|idx| graph.portals[p].cells.binary_search(idx).is_ok())
+            });
+            actions.push(RouteAction::Enter {
+                portal: crossed.or(candidates.first().copied()),
+                region,
+            });
+        }
+        travelled += distance(points[k - 1], points[k]);
+        if let Some((_, turn)) = turns.next_if(|&(at, _)| at == k) {
+            traverse(&mut actions, region, &mut travelled);
+            actions.push(RouteAction::Turn { region, turn });
+        }
+    }
+    let last = *regions.last().expect("path has a region");
+    traverse(&mut actions, last, &mut travelled);
+    actions.push(RouteAction::Arrive { region: last });
+
+    let name = |region: usize| graph.regions[region].name.as_str();
+    actions
+        .into_iter()
+        .map(|action| {
+            let text = match action {
+                RouteAction::Start { region } => format!("Start in {}", name(region)),
+                RouteAction::Traverse { region, distance } => {
+                    let distance = format_distance(distance);
+                    match graph.unit {
+                        Some(unit) => format!("Traverse {} for {distance} {unit}", name(region)),
+                        None => format!("Traverse {} for {distance}", name(region)),
+                    }
+                }
+                RouteAction::Turn { region, turn } => {
+                    let turn = match turn {
+                        Turn::Left => "Turn left",
+                        Turn::Right => "Turn right",
+                        Turn::Around => "Turn around",
+                    };
+                    let at = if name(region).starts_with("junction") {
+                        "at"
+                    } else {
+                        "in"
+                    };
+                    format!("{turn} {at} {}", name(region))
+                }
+                RouteAction::Enter {
+                    portal: Some(portal),
+                    region,
+                } => format!(
+                    "Go through {} into {}",
+                    graph.portals[portal].name,
+                    name(region)
+                ),
+                RouteAction::Enter {
+                    portal: None,
+                    region,
+                } => format!("Enter {}", name(region)),
+                RouteAction::Arrive { region } => format!("Arrive in {}", name(region)),
+            };
+            RouteStep { action, text }
+        })
+        .collect()
+}
+
+/// Close the distance covered in a region as a traverse step
+fn traverse(actions: &mut Vec<RouteAction>, region: usize, travelled: &mut f64) {
+    if *travelled > 0.0 {
+        actions.push(RouteAction::Traverse {
+            region,
+            distance: *travelled,
+        });
+    }
+    *travelled = 0.0;
+}
+
+/// Bends of a polyline, as indices of the points where they occur
+///
+/// The polyline is simplified with Douglas–Peucker at `tolerance` so that
+/// lattice staircases do not count as turns.
+fn turns(points: &[Point], tolerance: f64) -> Vec<(usize, Turn)> {
+    if points.len() < 3 {
+        return Vec::new();
+    }
+    let mut kept = vec![0, points.len() - 1];
+    simplify(points, 0, points.len() - 1, tolerance, &mut kept);
+    kept.sort_unstable();
+    kept.windows(3)
+        .filter_map(|w| {
+            let (a, b, c) = (points[w[0]], points[w[1]], points[w[2]]);
+            let (u, v) = ((b.0 - a.0, b.1 - a.1), (c.0 - b.0, c.1 - b.1));
+            let angle = (u.0 * v.1 - u.1 * v.0).atan2(u.0 * v.0 + u.1 * v.1);
+            let turn = match angle.abs().to_degrees() {
+                a if a < 30.0 => return None,
+                a if a > 150.0 => Turn::Around,
+                _ if angle > 0.0 => Turn::Left,
+                _ => Turn::Right,
+            };
+            Some((w[1], turn))
+        })
+        .collect()
+}
+
+/// Douglas–Peucker on `points[first..=last]`, collecting kept indices
+fn simplify(points: &[Point], first: usize, last: usize, tolerance: f64, kept: &mut Vec<usize>) {
+    let (a, b) = (points[first], points[last]);
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length = (dx * dx + dy * dy).sqrt();
+    let offset = |p: Point| {
+        if length > 0.0 {
+            ((p.0 - a.0) * dy - (p.1 - a.1) * dx).abs() / length
+        } else {
+            distance(p, a)
+        }
+    };
+    let farthest = (first + 1..last)
+        .map(|k| (k, offset(points[k])))
+        .max_by(|x, y| x.1.total_cmp(&y.1));
+    if let Some((k, d)) = farthest {
+        if d > tolerance {
+            kept.push(k);
+            simplify(points, first, k, tolerance, kept);
+            simplify(points, k, last, tolerance, kept);
+        }
+    }
+}
+
+/// Distance as a short number: "12" or "4.5"
+fn format_distance(distance: f64) -> String {
+    if distance >= 10.0 {
+        format!("{distance:.0}")
+    } else {
+        format!("{distance:.1}")
+    }
+}
+
+/// Segment free space into regions joined by portals
+pub fn extract_graph(occupancy: &OccupancyLayer, config: &TopologyConfig) -> TopologicalGraph {
+    let scale = config.passages.scale;
+    let map = passage_map(occupancy, &config.passages);
+
+    // Cut every passage from one side's obstacle to the other's
+    let cuts: Vec<Vec<(i32, i32)>> = map
+        .passages
+        .iter()
+        .map(|(_, [a, b])| {
+            let mut cut: Vec<(i32, i32)> = line(*a, *b)
+                .filter(|cell| map.free.contains_key(cell))
+                .collect();
+            cut.sort_unstable();
+            cut.dedup();
+            cut
+        })
+        .collect();
+    let cut: FxHashSet<(i32, i32)> = cuts.iter().flatten().copied().collect();
+
+    // 4-connected components, so diagonal cuts are not leaked through
+    let mut starts: Vec<(i32, i32)> = map.free.keys().copied().collect();
+    starts.sort_unstable();
+    let mut seen: FxHashSet<(i32, i32)> = FxHashSet::default();
+    let mut graph = TopologicalGraph {
+        regions: Vec::new(),
+        portals: Vec::new(),
+        columns: FxHashMap::default(),
+        scale,
+        unit: config.passages.unit,
+    };
+    for start in starts {
+        if cut.contains(&start) || !seen.insert(start) {
+            continue;
+        }
+        let mut component = vec![start];
+        let mut next = 0;
+        while next < component.len() {
+            let (i, j) = component[next];
+            next += 1;
+            for n in [(i + 1, j), (i - 1, j), (i, j + 1), (i, j - 1)] {
+                if map.free.contains_key(&n) && !cut.contains(&n) && seen.insert(n) {
+                    component.push(n);
+                }
+            }
+        }
+        if component.len() < config.min_region_cells {
+            continue;
+        }
+        component.sort_unstable();
+        let id = graph.regions.len();
+        let region = region(id, &component, &map.free, scale, config.corridor_aspect);
+        graph
+            .columns
+            .extend(component.iter().map(|&cell| (cell, id)));
+        graph.regions.push(region);
+    }
+
+    // Join the regions on either side of each cut
+    for ((passage, _), cut) in map.passages.iter().zip(&cuts) {
+        let mut sides: Vec<usize> = cut
+            .iter()
+            .flat_map(|&(i, j)| [(i + 1, j), (i - 1, j), (i, j + 1), (i, j - 1)])
+            .filter_map(|n| graph.columns.get(&n).copied())
+            .collect();
+        sides.sort_unstable();
+        sides.dedup();
+        let mut cells: Vec<Index64> = cut
+            .iter()
+            .flat_map(|cell| map.free[cell].iter().copied())
+            .collect();
+        cells.sort_unstable();
+        for (k, &a) in sides.iter().enumerate() {
+            for &b in &sides[k + 1..] {
+                let (ca, cb, cp) = (
+                    graph.regions[a].center,
+                    graph.regions[b].center,
+                    passage.center,
+                );
+                graph.portals.push(Portal {
+                    name: format!("door {}", graph.portals.len() + 1),
+                    regions: (a, b),
+                    passage: passage.clone(),
+                    cells: cells.clone(),
+                    length: distance(ca, cp) + distance(cp, cb),
+                });
+            }
+        }
+    }
+
+    // Number rooms, letter corridors and junctions in region order
+    let (mut rooms, mut corridors, mut junctions) = (0, 0, 0);
+    for id in 0..graph.regions.len() {
+        let portals = graph.portals_of(id).count();
+        let region = &mut graph.regions[id];
+        region.name = match region.kind {
+            RegionKind::Room => {
+                rooms += 1;
+                format!("room {rooms}")
+            }
+            RegionKind::Corridor if portals >= 3 => {
+                junctions += 1;
+                format!("junction {junctions}")
+            }
+            RegionKind::Corridor => {
+                corridors += 1;
+                format!("corridor {}", letters(corridors))
+            }
+        };
+    }
+    graph
+}
+
+/// Spreadsheet-style letters: A, B, ..., Z, AA, AB, ...
+fn letters(mut n: usize) -> String {
+    let mut out = Vec::new();
+    while n > 0 {
+        n -= 1;
+        out.push(b'A' + (n % 26) as u8);
+        n /= 26;
+    }
+    out.reverse();
+    String::from_utf8(out).expect("ASCII letters")
+}
+
+/// Summarize the columns of one region
+fn region(
+    id: usize,
+    columns: &[(i32, i32)],
+    free: &FxHashMap<(i32, i32), Vec<Index64>>,
+    scale: f64,
+    corridor_aspect: f64,
+) -> Region {
+    let n = columns.len() as f64;
+    let centers: Vec<Point> = columns.iter().map(|&c| column_center(c, scale)).collect();
+    let mean = centers
+        .iter()
+        .fold((0.0, 0.0), |acc, p| (acc.0 + p.0 / n, acc.1 + p.1 / n));
+    // Principal axes of the column centers; a rectangle's length to width
+    // ratio is the square root of the eigenvalue ratio
+    let (mut sxx, mut syy, mut sxy) = (0.0, 0.0, 0.0);
+    for p in &centers {
+        let (dx, dy) = (p.0 - mean.0, p.1 - mean.1);
+        sxx += dx * dx / n;
+        syy += dy * dy / n;
+        sxy += dx * dy / n;
+    }
+    let half_trace = (sxx + syy) / 2.0;
+    let spread = (half_trace * half_trace - (sxx * syy - sxy * sxy))
+        .max(0.0)
+        .sqrt();
+    let (major, minor) = (half_trace + spread, half_trace - spread);
+    let aspect = if minor > 0.0 {
+        (major / minor).sqrt()
+    } else {
+        f64::INFINITY
+    };
+    let mut cells: Vec<Index64> = columns
+        .iter()
+        .flat_map(|cell| free[cell].iter().copied())
+        .collect();
+    cells.sort_unstable();
+    Region {
+        id,
+        name: String::new(),
+        kind: if aspect >= corridor_aspect {
+            RegionKind::Corridor
+        } else {
+            RegionKind::Room
+        },
+        center: mean,
+        area: n * (2.0 * scale).powi(2),
+        aspect,
+        cells,
+    }
+}
+
+/// Grid columns on the segment between two columns, 8-connected
+fn line(a: (i32, i32), b: (i32, i32)) -> impl Iterator<Item = (i32, i32)> {
+    let steps = (b.0 - a.0).abs().max((b.1 - a.1).abs()).max(1);
+    (0..=steps).map(move |k| {
+        let t = k as f64 / steps as f64;
+        (
+            (a.0 as f64 + t * (b.0 - a.0) as f64).round() as i32,
+            (a.1 as f64 + t * (b.1 - a.1) as f64).round() as i32,
+        )
+    })
+}
+
+fn distance(a: Point, b: Point) -> f64 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Free space of two rooms joined by a corridor through two doors
+    fn rooms_and_corridor() -> crate::Result<OccupancyLayer> {
+        let open = [
+            (104..140, 104..140), // Room A
+            (140..144, 118..126), // Door
+            (144..200, 116..128), // Corridor
+            (200..204, 118..126), // Door
+            (204..240, 104..140), // Room B
+        ];
+        let mut occupancy = OccupancyLayer::new();
+        for x in 100..244u16 {
+            for y in 100..144u16 {
+                for z in 100..110u16 {
+                    if x % 2 == y % 2 && y % 2 == z % 2 {
+                        let free = open
+                            .iter()
+                            .any(|(xs, ys)| xs.contains(&x) && ys.contains(&y));
+                        let idx = Index64::new(0, 0, 8, x, y, z)?;
+                        occupancy.update_occupancy(idx, !free, 0.9);
+                    }
+                }
+            }
+        }
+        Ok(occupancy)
+    }
+
+    #[test]
+    fn test_rooms_and_corridor_graph() -> crate::Result<()> {
+        let occupancy = rooms_and_corridor()?;
+        let mut config = TopologyConfig::new(100..=109);
+        config.passages.max_width = 10.0;
+        let graph = extract_graph(&occupancy, &config);
+
+        let kinds: Vec<RegionKind> = graph.regions.iter().map(|r| r.kind).collect();
+        assert_eq!(
+            kinds,
+            [RegionKind::Room, RegionKind::Corridor, RegionKind::Room]
+        );
+        assert_eq!(graph.portals.len(), 2);
+        assert_eq!(graph.portals[0].regions, (0, 1));
+        assert_eq!(graph.portals[1].regions, (1, 2));
+        assert!(graph.portals.iter().all(|p| p.passage.width == 8.0));
+        assert_eq!(graph.route(0, 2), Some(vec![0, 1, 2]));
+        assert_eq!(graph.route(2, 0), Some(vec![2, 1, 0]));
+
+        let names: Vec<&str> = graph.regions.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["room 1", "corridor A", "room 2"]);
+        assert_eq!(graph.portals[1].name, "door 2");
+
+        let in_b = Index64::new(0, 0, 8, 220, 120, 150)?;
+        assert_eq!(graph.region_of(in_b), Some(2));
+        let portal = graph.portals[0].cells[0];
+        assert_eq!(graph.region_of(portal), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_describe_route() -> crate::Result<()> {
+        let occupancy = rooms_and_corridor()?;
+        let mut config = TopologyConfig::new(100..=109);
+        // Widths are in output units: doors are 0.4 m, the corridor 0.6 m
+        config.passages.scale = 0.05;
+        config.passages.unit = Some("m");
+        config.passages.max_width = 0.5;
+        let graph = extract_graph(&occupancy, &config);
+
+        // South through room A, then east along the corridor into room B
+        let mut path = Vec::new();
+        for y in (122..=136).rev().step_by(2) {
+            path.push(Index64::new(0, 0, 8, 120, y, 100)?);
+        }
+        for x in (122..=230).step_by(2) {
+            path.push(Index64::new(0, 0, 8, x, 122, 100)?);
+        }
+        let steps = describe_route(&graph, &path);
+        let texts: Vec<&str> = steps.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            [
+                "Start in room 1",
+                "Traverse room 1 for 0.7 m",
+                "Turn left in room 1",
+                "Traverse room 1 for 1.0 m",
+                "Go through door 1 into corridor A",
+                "Traverse corridor A for 3.0 m",
+                "Go through door 2 into room 2",
+                "Traverse room 2 for 1.5 m",
+                "Arrive in room 2",
+            ]
+        );
+        assert_eq!(
+            steps[4].action,
+            RouteAction::Enter {
+                portal: Some(0),
+                region: 1
+            }
+        );
+        assert!(describe_route(&graph, &[]).is_empty());
+
+        // Without a unit the distances are written bare
+        config.passages.unit = None;
+        let graph = extract_graph(&occupancy, &config);
+        assert_eq!(
+            describe_route(&graph, &path)[1].text,
+            "Traverse room 1 for 0.7"
+        );
+        Ok(())
+    }
+}
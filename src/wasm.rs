@@ -0,0 +1,336 @@
+//! JavaScript bindings for ID math and pathfinding
+//!
+//! Enabled with the `wasm` feature. Build for the browser with
+//!
+//! ```text
+//! cargo build --release --target wasm32-unknown-unknown --no-default-features --features wasm
+//! wasm-bindgen --target web target/wasm32-unknown-unknown/release/octaindex3d.wasm --out-dir pkg
+//! ```
+//!
+//! The exported classes wrap [`Index64`], [`Route64`] and [`BccGrid`]
+//! under those names. Raw ID values cross the boundary as `BigInt`s, and
+//! errors are thrown as JavaScript `Error`s carrying the crate's message.
+//!
+//! ```text
+//! import init, { BccGrid } from "./pkg/octaindex3d.js";
+//!
+//! await init();
+//! const grid = new BccGrid(0.5);
+//! const path = grid.astar(grid.cellAt(0, 0, 0), grid.cellAt(3, 3, 3));
+//! const xyz = path.positions(); // Float64Array of cell centers
+//! ```
+
+use crate::grid::{BccGrid, GridPath};
+use crate::ids::{Index64, Route64};
+use crate::neighbors::{neighbors_index64, neighbors_route64};
+use std::collections::HashSet;
+use wasm_bindgen::prelude::*;
+
+/// Morton-ordered hierarchical cell ID
+#[wasm_bindgen(js_name = Index64)]
+#[derive(Debug, Clone, Copy)]
+pub struct JsIndex64(Index64);
+
+#[wasm_bindgen(js_class = Index64)]
+impl JsIndex64 {
+    /// Create an ID from frame, scale tier, LOD and 16-bit coordinates
+    #[wasm_bindgen(constructor)]
+    pub fn new(frame: u8, tier: u8, lod: u8, x: u16, y: u16, z: u16) -> Result<JsIndex64, JsError> {
+        Ok(Self(Index64::new(frame, tier, lod, x, y, z)?))
+    }
+
+    /// Parse an ID from its raw 64-bit value
+    #[wasm_bindgen(js_name = fromValue)]
+    pub fn from_value(value: u64) -> Result<JsIndex64, JsError> {
+        Ok(Self(Index64::from_value(value)?))
+    }
+
+    /// Parse an ID from its bech32m string
+    #[wasm_bindgen(js_name = fromBech32m)]
+    pub fn from_bech32m(s: &str) -> Result<JsIndex64, JsError> {
+        Ok(Self(Index64::from_bech32m(s)?))
+    }
+
+    /// Raw 64-bit value
+    #[wasm_bindgen(getter)]
+    pub fn value(&self) -> u64 {
+        self.0.raw()
+    }
+
+    /// Frame ID
+    #[wasm_bindgen(getter)]
+    pub fn frame(&self) -> u8 {
+        self.0.frame_id()
+    }
+
+    /// Scale tier
+    #[wasm_bindgen(getter)]
+    pub fn tier(&self) -> u8 {
+        self.0.scale_tier()
+    }
+
+    /// Level of detail
+    #[wasm_bindgen(getter)]
+    pub fn lod(&self) -> u8 {
+        self.0.lod()
+    }
+
+    /// Coordinates as `[x, y, z]`
+    pub fn coords(&self) -> Vec<u16> {
+        let (x, y, z) = self.0.decode_coords();
+        vec![x, y, z]
+    }
+
+    /// Parent cell, or `undefined` at LOD 0
+    pub fn parent(&self) -> Option<JsIndex64> {
+        self.0.parent().map(Self)
+    }
+
+    /// The 8 child cells, empty at the finest LOD
+    pub fn children(&self) -> Vec<JsIndex64> {
+        self.0.children().into_iter().map(Self).collect()
+    }
+
+    /// The 14 neighbors at the same LOD that fit in the coordinate range
+    pub fn neighbors(&self) -> Vec<JsIndex64> {
+        neighbors_index64(self.0).into_iter().map(Self).collect()
+    }
+
+    /// Encode as a bech32m string
+    #[wasm_bindgen(js_name = toBech32m)]
+    pub fn to_bech32m(&self) -> Result<String, JsError> {
+        Ok(self.0.to_bech32m()?)
+    }
+
+    /// Same as [`Self::to_bech32m`], used by string conversion in JavaScript
+    #[wasm_bindgen(js_name = toString)]
+    pub fn to_js_string(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+/// Local routing cell ID on the BCC lattice
+#[wasm_bindgen(js_name = Route64)]
+#[derive(Debug, Clone, Copy)]
+pub struct JsRoute64(Route64);
+
+#[wasm_bindgen(js_class = Route64)]
+impl JsRoute64 {
+    /// Create a route from scale tier and lattice coordinates of equal parity
+    #[wasm_bindgen(constructor)]
+    pub fn new(tier: u8, x: i32, y: i32, z: i32) -> Result<JsRoute64, JsError> {
+        Ok(Self(Route64::new(tier, x, y, z)?))
+    }
+
+    /// Parse a route from its raw 64-bit value
+    #[wasm_bindgen(js_name = fromValue)]
+    pub fn from_value(value: u64) -> Result<JsRoute64, JsError> {
+        Ok(Self(Route64::from_value(value)?))
+    }
+
+    /// Parse a route from its bech32m string
+    #[wasm_bindgen(js_name = fromBech32m)]
+    pub fn from_bech32m(s: &str) -> Result<JsRoute64, JsError> {
+        Ok(Self(Route64::from_bech32m(s)?))
+    }
+
+    /// Raw 64-bit value
+    #[wasm_bindgen(getter)]
+    pub fn value(&self) -> u64 {
+        self.0.raw()
+    }
+
+    /// Scale tier
+    #[wasm_bindgen(getter)]
+    pub fn tier(&self) -> u8 {
+        self.0.scale_tier()
+    }
+
+    /// X coordinate
+    #[wasm_bindgen(getter)]
+    pub fn x(&self) -> i32 {
+        self.0.x()
+    }
+
+    /// Y coordinate
+    #[wasm_bindgen(getter)]
+    pub fn y(&self) -> i32 {
+        self.0.y()
+    }
+
+    /// Z coordinate
+    #[wasm_bindgen(getter)]
+    pub fn z(&self) -> i32 {
+        self.0.z()
+    }
+
+    /// The 14 BCC neighbors
+    pub fn neighbors(&self) -> Vec<JsRoute64> {
+        neighbors_route64(self.0).into_iter().map(Self).collect()
+    }
+
+    /// Encode as a bech32m string
+    #[wasm_bindgen(js_name = toBech32m)]
+    pub fn to_bech32m(&self) -> Result<String, JsError> {
+        Ok(self.0.to_bech32m()?)
+    }
+
+    /// Same as [`Self::to_bech32m`], used by string conversion in JavaScript
+    #[wasm_bindgen(js_name = toString)]
+    pub fn to_js_string(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+/// Result of an A* search
+#[wasm_bindgen(js_name = GridPath)]
+#[derive(Debug, Clone)]
+pub struct JsGridPath {
+    grid: BccGrid,
+    path: GridPath,
+}
+
+#[wasm_bindgen(js_class = GridPath)]
+impl JsGridPath {
+    /// Total path length in physical units
+    #[wasm_bindgen(getter)]
+    pub fn cost(&self) -> f64 {
+        self.path.cost
+    }
+
+    /// Number of cells, start and goal included
+    #[wasm_bindgen(getter)]
+    pub fn length(&self) -> usize {
+        self.path.len()
+    }
+
+    /// Cells from start to goal
+    pub fn cells(&self) -> Vec<JsRoute64> {
+        self.path.cells.iter().copied().map(JsRoute64).collect()
+    }
+
+    /// Cell centers as a flat `[x0, y0, z0, x1, ...]` array, ready for a
+    /// vertex buffer
+    pub fn positions(&self) -> Vec<f64> {
+        self.path
+            .cells
+            .iter()
+            .flat_map(|&cell| {
+                let (x, y, z) = self.grid.center_of(cell);
+                [x, y, z]
+            })
+            .collect()
+    }
+}
+
+/// BCC grid in physical units
+#[wasm_bindgen(js_name = BccGrid)]
+#[derive(Debug, Clone, Copy)]
+pub struct JsBccGrid(BccGrid);
+
+#[wasm_bindgen(js_class = BccGrid)]
+impl JsBccGrid {
+    /// Create a grid with the given cell size
+    #[wasm_bindgen(constructor)]
+    pub fn new(cell_size: f64) -> Result<JsBccGrid, JsError> {
+        Ok(Self(BccGrid::new(cell_size)?))
+    }
+
+    /// Cell size in physical units
+    #[wasm_bindgen(getter, js_name = cellSize)]
+    pub fn cell_size(&self) -> f64 {
+        self.0.cell_size()
+    }
+
+    /// Cell containing a point
+    #[wasm_bindgen(js_name = cellAt)]
+    pub fn cell_at(&self, x: f64, y: f64, z: f64) -> Result<JsRoute64, JsError> {
+        Ok(JsRoute64(self.0.cell_at(x, y, z)?))
+    }
+
+    /// Center of a cell as `[x, y, z]`
+    #[wasm_bindgen(js_name = centerOf)]
+    pub fn center_of(&self, cell: &JsRoute64) -> Vec<f64> {
+        let (x, y, z) = self.0.center_of(cell.0);
+        vec![x, y, z]
+    }
+
+    /// Distance between cell centers in physical units
+    pub fn distance(&self, a: &JsRoute64, b: &JsRoute64) -> f64 {
+        self.0.distance(a.0, b.0)
+    }
+
+    /// Cells within `k` steps of `center`
+    #[wasm_bindgen(js_name = kRing)]
+    pub fn k_ring(&self, center: &JsRoute64, k: usize) -> Vec<JsRoute64> {
+        self.0
+            .k_ring(center.0, k)
+            .into_iter()
+            .map(JsRoute64)
+            .collect()
+    }
+
+    /// Shortest path through free space
+    pub fn astar(&self, start: &JsRoute64, goal: &JsRoute64) -> Result<JsGridPath, JsError> {
+        self.path(self.0.astar(start.0, goal.0)?)
+    }
+
+    /// Shortest path avoiding cells whose raw values are in `blocked`
+    #[wasm_bindgen(js_name = astarAvoiding)]
+    pub fn astar_avoiding(
+        &self,
+        start: &JsRoute64,
+        goal: &JsRoute64,
+        blocked: Vec<u64>,
+    ) -> Result<JsGridPath, JsError> {
+        let blocked: HashSet<u64> = blocked.into_iter().collect();
+        let path = self
+            .0
+            .astar_where(start.0, goal.0, |cell| !blocked.contains(&cell.raw()))?;
+        self.path(path)
+    }
+}
+
+impl JsBccGrid {
+    fn path(&self, path: GridPath) -> Result<JsGridPath, JsError> {
+        Ok(JsGridPath { grid: self.0, path })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Error paths throw into JavaScript and only run on wasm targets
+
+    #[test]
+    fn test_ids_round_trip() {
+        let index = JsIndex64::new(0, 1, 5, 10, 20, 30).unwrap();
+        assert_eq!(index.coords(), vec![10, 20, 30]);
+        let parsed = JsIndex64::from_bech32m(&index.to_bech32m().unwrap()).unwrap();
+        assert_eq!(parsed.value(), index.value());
+        assert_eq!(index.children().len(), 8);
+        assert_eq!(index.parent().unwrap().lod(), 4);
+
+        let route = JsRoute64::new(0, 2, 4, 6).unwrap();
+        assert_eq!(route.neighbors().len(), 14);
+        assert_eq!(JsRoute64::from_value(route.value()).unwrap().y(), 4);
+    }
+
+    #[test]
+    fn test_astar_avoiding() {
+        let grid = JsBccGrid::new(1.0).unwrap();
+        let start = grid.cell_at(0.0, 0.0, 0.0).unwrap();
+        let goal = grid.cell_at(4.0, 0.0, 0.0).unwrap();
+        let direct = grid.astar(&start, &goal).unwrap();
+
+        let blocked: Vec<u64> = direct.cells()[1..direct.length() - 1]
+            .iter()
+            .map(JsRoute64::value)
+            .collect();
+        let detour = grid.astar_avoiding(&start, &goal, blocked.clone()).unwrap();
+        assert!(detour.cost() > direct.cost());
+        assert!(detour.cells().iter().all(|c| !blocked.contains(&c.value())));
+        assert_eq!(detour.positions().len(), 3 * detour.length());
+    }
+}